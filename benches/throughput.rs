@@ -0,0 +1,16 @@
+//! Runs `mixal bench` on the binary built with the bench profile, passing on the
+//! arguments given after `--`, as in `cargo bench -- --record` or
+//! `cargo bench -- compare benches/baseline.json`, and exiting with its code.
+
+use std::process::{exit, Command};
+
+fn main() {
+    // Cargo adds `--bench` for the harness, which `mixal bench` doesn't take.
+    let args: Vec<String> = std::env::args().skip(1).filter(|arg| arg != "--bench").collect();
+    let status = Command::new(env!("CARGO_BIN_EXE_mixal"))
+        .arg("bench")
+        .args(&args)
+        .status()
+        .expect("[Error throughput] Couldn't run mixal");
+    exit(status.code().unwrap_or(1));
+}
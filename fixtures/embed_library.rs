@@ -0,0 +1,87 @@
+// Generated by `mixal embed` from a MIXAL program. Don't edit it; embed the program again.
+
+pub mod library {
+    use crate::codegen::{self, Address};
+    use crate::computer::Computer;
+    use crate::word::Word;
+
+    /// The hash of the source the program was assembled from.
+    pub const SOURCE_HASH: u64 = 0x10ba5feb9d7d21d0;
+
+    /// The address the program starts at.
+    pub const ENTRY_POINT: Address = Address(3000);
+
+    pub const DEXIT: Address = Address(3003);
+    pub const DOUBLE: Address = Address(3000);
+    pub const LOOP: Address = Address(3007);
+    pub const SIZE: i64 = 4;
+    pub const TABLE: Address = Address(1000);
+    pub const TEMP: Address = Address(3011);
+    pub const TEXIT: Address = Address(3010);
+    pub const TOTAL: Address = Address(3004);
+
+    /// The runs of consecutive words the program emits, each as its first address and
+    /// the address after its last.
+    pub static PROGRAM_REGIONS: [(Address, Address); 2] = [
+        (Address(1000), Address(1004)),
+        (Address(3000), Address(3012)),
+    ];
+
+    /// Every word of the program, as its address, sign, and bytes.
+    pub static PROGRAM_WORDS: [(Address, bool, [u8; 5]); 16] = [
+        (Address(1000), true, [0, 0, 0, 0, 3]),
+        (Address(1001), true, [0, 0, 0, 0, 5]),
+        (Address(1002), true, [0, 0, 0, 0, 8]),
+        (Address(1003), true, [0, 0, 0, 0, 13]),
+        (Address(3000), true, [11, 187, 0, 2, 32]),
+        (Address(3001), true, [11, 195, 0, 5, 24]),
+        (Address(3002), true, [11, 195, 0, 5, 1]),
+        (Address(3003), true, [11, 187, 0, 0, 39]),
+        (Address(3004), true, [11, 194, 0, 2, 32]),
+        (Address(3005), true, [0, 0, 0, 2, 48]),
+        (Address(3006), true, [0, 4, 0, 2, 49]),
+        (Address(3007), true, [3, 231, 1, 5, 1]),
+        (Address(3008), true, [0, 1, 0, 1, 49]),
+        (Address(3009), true, [11, 191, 0, 2, 41]),
+        (Address(3010), true, [11, 194, 0, 0, 39]),
+        (Address(3011), true, [0, 0, 0, 0, 0]),
+    ];
+
+    /// The symbols of the program, as `load_into` hands them back.
+    #[allow(non_snake_case)]
+    #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    pub struct Handle {
+        pub DEXIT: Address,
+        pub DOUBLE: Address,
+        pub LOOP: Address,
+        pub SIZE: i64,
+        pub TABLE: Address,
+        pub TEMP: Address,
+        pub TEXIT: Address,
+        pub TOTAL: Address,
+    }
+
+    /// Writes the words of the program into the memory of `computer` and points its `pc`
+    /// at the entry point.
+    pub fn load_into(computer: &mut Computer) -> Handle {
+        for (address, positive, bytes) in PROGRAM_WORDS.iter() {
+            computer.write_mem(address.0, Word::new(*positive, *bytes));
+        }
+        computer.pc = ENTRY_POINT.0;
+        Handle {
+            DEXIT,
+            DOUBLE,
+            LOOP,
+            SIZE,
+            TABLE,
+            TEMP,
+            TEXIT,
+            TOTAL,
+        }
+    }
+
+    /// Whether `source` isn't the source the program was embedded from.
+    pub fn is_stale(source: &str) -> bool {
+        codegen::source_hash(source) != SOURCE_HASH
+    }
+}
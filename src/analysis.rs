@@ -0,0 +1,758 @@
+//! A map of an assembled program for reviewing it: every symbol with its address and kind,
+//! every word with what it references and what jumps to it, and the code no path from the
+//! entry point reaches along with the data nothing refers to.
+//!
+//! Reachability follows static jump targets from the start of the program. Since the
+//! assembled program is all it looks at, it is conservative wherever the targets are only
+//! known at run time:
+//!
+//! - `JMP` may be a subroutine call, so the word after it is reached as well as its target.
+//!   `JSJ` is the only jump that never falls through.
+//! - An indexed jump such as `JMP TABLE,1` is computed. Its possible targets are the
+//!   entries of the table at its address: the words from there up to the next labelled
+//!   word or the end of the run of words emitted together. Each is a dynamic target,
+//!   reached but not by a known jump.
+//! - A jump some store in the program overwrites, as `STJ` does to the `JMP *` ending a
+//!   subroutine, has a dynamic target and adds no static edge.
+//!
+//! An indexed reference to data likewise refers to every entry of its table. Words are
+//! listed in order of address, with the gaps between the runs of words emitted together
+//! marked, so that `ORIG` gaps stay visible.
+//!
+//! A routine assembled high in memory, such as a resident utility at `ORIG 3900`, must
+//! stop falling through before the last word of memory. The map flags the first reached
+//! instruction from which execution can run on, word after word, past the end: one with
+//! no `HLT`, `JMP`, or `JSJ` anywhere from it to the last word. The words nothing emitted
+//! hold +0, which does nothing and runs on, so only the words of the program can stop the
+//! run, and the answer is the same for any size of memory holding the program.
+//!
+//! `linked_program_map` maps a program linked from modules, naming each local symbol by
+//! its module.
+//!
+//! `check_equivalence` checks that two routines, such as a routine and a hand-optimized
+//! version of it, compute the same function, by running both on the same inputs and
+//! comparing their outputs. `examples/maximum.mixal` and `examples/maximum_rotated.mixal`
+//! are the maximum routine of section 1.3.2 and a version with its loop rotated, which
+//! `maximum_routines_are_equivalent` compares.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+use crate::assembler::{AssembleError, AssemblyState};
+use crate::assembler::link::{LinkedModule, LinkedProgram};
+use crate::computer::{ComparisonFlag, Computer};
+use crate::consts::{JumpCondition, Opcode, SpecialOp};
+use crate::determinism::Prng;
+use crate::error::MixError;
+use crate::loader::AssembledProgram;
+use crate::migration::BYTE_SIZES;
+use crate::opcodes;
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::word::Word;
+use crate::field::FieldSpec;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SymbolKind {
+    /// The label of an instruction.
+    Code,
+    /// The label of a `CON`.
+    Data,
+    /// A symbol given its value by `EQU`, or the label of an `ORIG` or `END`, neither of
+    /// which emits a word.
+    Constant,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MappedSymbol {
+    pub name: String,
+    pub value: i64,
+    pub kind: SymbolKind,
+}
+
+/// One word of the program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MapEntry {
+    pub address: usize,
+    /// The source line emitting the word, counting from 1.
+    pub line: usize,
+    pub label: Option<String>,
+    /// The MIXAL statement for an instruction, or `CON` and the value for data.
+    pub statement: String,
+    pub is_data: bool,
+    /// The symbols the operand uses, in order and without repeats.
+    pub references: Vec<String>,
+    /// The addresses of the instructions jumping here statically, in order.
+    pub incoming: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProgramMap {
+    pub start: usize,
+    /// Every symbol, in order of value and then of name.
+    pub symbols: Vec<MappedSymbol>,
+    /// Every word, in order of address.
+    pub entries: Vec<MapEntry>,
+    /// The instructions no path from the start reaches.
+    pub unreachable: Vec<usize>,
+    /// The data words nothing refers to.
+    pub unreferenced: Vec<usize>,
+    /// The words a computed jump may reach, each with the jumps that may reach it.
+    pub dynamic_targets: BTreeMap<usize, Vec<usize>>,
+    /// The jumps a store in the program overwrites.
+    pub dynamic_jumps: Vec<usize>,
+    /// The lowest reached instruction from which execution can run past the last word of
+    /// memory, if any.
+    pub runs_off_end: Option<usize>,
+}
+
+/// How control leaves an instruction.
+enum Flow {
+    /// On to the next word only.
+    Next,
+    /// To the target only, as `JSJ` does.
+    Goto(usize),
+    /// To the target, or on to the next word.
+    Branch(usize),
+    Stop,
+}
+
+/// The flow of `word`, ignoring indexing and stores.
+fn flow_of(word: &Word) -> Flow {
+    let target = word.address();
+    if word.opcode() == Opcode::SPECIAL.code() && word.field() == SpecialOp::Halt.field() {
+        Flow::Stop
+    } else if never_falls_through(word) {
+        Flow::Goto(target)
+    } else if is_jump(word) {
+        Flow::Branch(target)
+    } else {
+        Flow::Next
+    }
+}
+
+/// Whether `word` is `JSJ`, the one jump that never falls through.
+fn never_falls_through(word: &Word) -> bool {
+    word.opcode() == Opcode::JMP.code() && word.field() == JumpCondition::SaveJ.field()
+}
+
+/// Whether execution never runs on from `word` to the word after it: `HLT`, or a `JMP` or
+/// `JSJ`, which transfer wherever they go.
+fn ends_straight_line(word: &Word) -> bool {
+    matches!(flow_of(word), Flow::Stop | Flow::Goto(_))
+        || (word.opcode() == Opcode::JMP.code() && word.field() == JumpCondition::Always.field())
+}
+
+fn is_jump(word: &Word) -> bool {
+    match Opcode::try_from(word.opcode()) {
+        Ok(opcode) => opcode == Opcode::JBUS || (Opcode::JRED..=Opcode::JX).contains(&opcode),
+        Err(_) => false,
+    }
+}
+
+fn is_store(word: &Word) -> bool {
+    (Opcode::STA.code()..=Opcode::STZ.code()).contains(&word.opcode())
+}
+
+/// The map of the program `state` assembled.
+///
+/// ## Errors
+/// - The first problem with the source, when it didn't assemble.
+pub fn program_map(state: &AssemblyState) -> Result<ProgramMap, AssembleError> {
+    let program = match (&state.program, state.diagnostics.first()) {
+        (Some(program), None) => program,
+        (_, Some(error)) => return Err(error.clone()),
+        (None, None) => unreachable!("[Error program_map] An assembly without problems has a program"),
+    };
+    Ok(map_of(program, &[(state, None)]))
+}
+
+/// The map of a program linked from modules, naming each local symbol by its module, as
+/// `MAIN.LOOP`. The line of each word is the line of its module.
+pub fn linked_program_map(linked: &LinkedProgram) -> ProgramMap {
+    let sources: Vec<(&AssemblyState, Option<&LinkedModule>)> = linked.modules.iter().map(|module| (module.state(), Some(module))).collect();
+    map_of(&linked.program, &sources)
+}
+
+/// The name by which `module`, if the symbol is from one, knows the symbol `name`.
+fn qualified(module: Option<&LinkedModule>, name: &str) -> String {
+    module.map_or_else(|| name.to_string(), |module| module.qualified(name))
+}
+
+/// The map of `program`, assembled from the statements of `sources`, each with the module
+/// it is when the program was linked.
+fn map_of(program: &AssembledProgram, sources: &[(&AssemblyState, Option<&LinkedModule>)]) -> ProgramMap {
+    let words = &program.words;
+    let mut entries: Vec<MapEntry> = sources.iter()
+        .flat_map(|(state, module)| state.statements().iter().map(move |statement| (statement, *module)))
+        .filter(|(statement, _)| statement.location >= 0 && words.contains_key(&(statement.location as usize)))
+        .map(|(statement, module)| {
+            let address = statement.location as usize;
+            let word = words[&address];
+            let mut references: Vec<String> = Vec::new();
+            for (name, _) in statement.parsed.operand.symbols() {
+                let name = qualified(module, name);
+                if !references.contains(&name) {
+                    references.push(name);
+                }
+            }
+            MapEntry {
+                address,
+                line: statement.line + 1,
+                label: statement.parsed.label.as_ref().map(|(label, _)| qualified(module, label)),
+                statement: if statement.is_data {
+                    format!("CON {}", if word.positive { word.field_value(FieldSpec::MAGNITUDE) } else { -word.field_value(FieldSpec::MAGNITUDE) })
+                } else {
+                    opcodes::disassemble(&word).unwrap_or_default()
+                },
+                is_data: statement.is_data,
+                references,
+                incoming: Vec::new(),
+            }
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.address);
+    // Later words at the same address replace earlier ones, as when loading.
+    entries.dedup_by(|later, earlier| {
+        if later.address == earlier.address {
+            std::mem::swap(later, earlier);
+            return true;
+        }
+        false
+    });
+
+    let labelled: BTreeSet<usize> = entries.iter().filter(|entry| entry.label.is_some()).map(|entry| entry.address).collect();
+    let is_code = |address: usize| !program.source_map.data.contains(&address) && words.contains_key(&address);
+    let overwritten: BTreeSet<usize> = words.iter()
+        .filter(|(address, word)| is_code(**address) && is_store(word) && word.index() == 0)
+        .map(|(_, word)| word.address())
+        .collect();
+
+    let mut incoming: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut dynamic_targets: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut dynamic_jumps = Vec::new();
+    let mut reached = BTreeSet::new();
+    let mut pending = vec![program.start];
+    while let Some(address) = pending.pop() {
+        if !is_code(address) || !reached.insert(address) {
+            continue;
+        }
+        let word = words[&address];
+        if is_jump(&word) && overwritten.contains(&address) {
+            dynamic_jumps.push(address);
+            if !never_falls_through(&word) {
+                pending.push(address + 1);
+            }
+            continue;
+        }
+        if is_jump(&word) && word.index() != 0 {
+            for target in table_at(program, &labelled, word.address()) {
+                dynamic_targets.entry(target).or_default().push(address);
+                pending.push(target);
+            }
+            if !never_falls_through(&word) {
+                pending.push(address + 1);
+            }
+            continue;
+        }
+        match flow_of(&word) {
+            Flow::Next => pending.push(address + 1),
+            Flow::Goto(target) => {
+                incoming.entry(target).or_default().push(address);
+                pending.push(target);
+            },
+            Flow::Branch(target) => {
+                incoming.entry(target).or_default().push(address);
+                pending.push(target);
+                pending.push(address + 1);
+            },
+            Flow::Stop => {},
+        }
+    }
+    // Static edges from unreached jumps are listed too, so that dead code shows who would
+    // have reached it.
+    for (address, word) in words.iter() {
+        if is_code(*address) && !reached.contains(address) && is_jump(word) && word.index() == 0 && !overwritten.contains(address) {
+            incoming.entry(word.address()).or_default().push(*address);
+        }
+    }
+    for sources in incoming.values_mut().chain(dynamic_targets.values_mut()) {
+        sources.sort_unstable();
+        sources.dedup();
+    }
+    dynamic_jumps.sort_unstable();
+
+    let mut symbols: Vec<MappedSymbol> = sources.iter()
+        .flat_map(|(state, module)| state.symbols.iter().map(move |symbol| {
+            let kind = match state.parsed_line(symbol.line - 1).operation.as_ref().map(|(operation, _)| operation.as_str()) {
+                Some("EQU") | Some("ORIG") | Some("END") | Some("PUBL") => SymbolKind::Constant,
+                Some("CON") | Some("ALF") => SymbolKind::Data,
+                _ => SymbolKind::Code,
+            };
+            MappedSymbol { name: qualified(*module, &symbol.name), value: symbol.value, kind }
+        }))
+        .collect();
+    symbols.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+
+    // An indexed operand such as `TABLE-1,1` may address a word before the table, so the
+    // tables at the symbols it uses count as referenced too.
+    let values: BTreeMap<&str, i64> = symbols.iter().map(|symbol| (symbol.name.as_str(), symbol.value)).collect();
+    let mut referenced = BTreeSet::new();
+    for entry in entries.iter().filter(|entry| !entry.is_data) {
+        let word = words[&entry.address];
+        let mut bases = vec![word.address()];
+        bases.extend(entry.references.iter()
+            .filter_map(|name| values.get(name.as_str()))
+            .filter(|value| **value >= 0)
+            .map(|value| *value as usize));
+        for base in bases {
+            if word.index() == 0 {
+                referenced.insert(base);
+            } else {
+                referenced.extend(table_at(program, &labelled, base));
+            }
+        }
+    }
+    for entry in entries.iter_mut() {
+        entry.incoming = incoming.remove(&entry.address).unwrap_or_default();
+    }
+    let unreachable = entries.iter().filter(|entry| !entry.is_data && !reached.contains(&entry.address)).map(|entry| entry.address).collect();
+    let unreferenced = entries.iter().filter(|entry| entry.is_data && !referenced.contains(&entry.address)).map(|entry| entry.address).collect();
+    // Only the words emitted can stop the run to the end, so this looks at no more words
+    // than the program has, however far from the end it lies.
+    let straight_to_end = words.iter().rev()
+        .find(|(_, word)| ends_straight_line(word))
+        .map_or(0, |(address, _)| address + 1);
+    let runs_off_end = reached.range(straight_to_end..).next().copied();
+
+    ProgramMap { start: program.start, symbols, entries, unreachable, unreferenced, dynamic_targets, dynamic_jumps, runs_off_end }
+}
+
+/// The entries of the table at `address`: the words from there up to the next labelled
+/// word, within the run of words emitted together.
+fn table_at(program: &AssembledProgram, labelled: &BTreeSet<usize>, address: usize) -> Vec<usize> {
+    let end = program.regions.iter()
+        .find(|region| region.range.contains(&address))
+        .map_or(address, |region| region.range.end);
+    let next_label = labelled.range(address + 1..).next().copied().unwrap_or(end);
+    (address..end.min(next_label)).collect()
+}
+
+impl ProgramMap {
+    /// The map as a document of four sections: the symbols, the listing, the dynamic
+    /// targets and jumps, and the summary of unreachable code, unreferenced data, and code
+    /// running off the end of memory.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        self.write_text(&mut text).expect("[Error to_text] Writing to a String can't fail");
+        text
+    }
+
+    /// Writes the document `to_text` gives to `out` a line at a time, holding no more
+    /// than a line of it in memory, for maps too large to render into one string.
+    pub fn write_text(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "PROGRAM MAP, START {}\n\nSYMBOLS\n", self.start)?;
+        for symbol in self.symbols.iter() {
+            let kind = match symbol.kind {
+                SymbolKind::Code => "code",
+                SymbolKind::Data => "data",
+                SymbolKind::Constant => "constant",
+            };
+            writeln!(out, "  {:<10} {:>6}  {}", symbol.name, symbol.value, kind)?;
+        }
+
+        out.write_str("\nLISTING\n")?;
+        let mut previous: Option<usize> = None;
+        let mut line = String::new();
+        for entry in self.entries.iter() {
+            if let Some(previous) = previous.filter(|previous| previous + 1 != entry.address) {
+                writeln!(out, "  ---- gap {}..{}", previous + 1, entry.address)?;
+            }
+            previous = Some(entry.address);
+            line.clear();
+            write!(line, "  {:04} {:<10} {:<20} ", entry.address, entry.label.as_deref().unwrap_or(""), entry.statement)?;
+            if !entry.references.is_empty() {
+                line.push_str("refs ");
+                write_joined(&mut line, entry.references.iter(), " ", |out, name| out.write_str(name))?;
+            }
+            if !entry.incoming.is_empty() {
+                if !entry.references.is_empty() {
+                    line.push_str("; ");
+                }
+                line.push_str("from ");
+                write_joined(&mut line, entry.incoming.iter(), ", ", |out, address| self.write_label(out, *address))?;
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+
+        out.write_str("\nDYNAMIC\n")?;
+        for (target, sources) in self.dynamic_targets.iter() {
+            out.write_str("  ")?;
+            self.write_label(out, *target)?;
+            out.write_str(" may be reached from ")?;
+            write_joined(out, sources.iter(), ", ", |out, address| self.write_label(out, *address))?;
+            out.write_str("\n")?;
+        }
+        for address in self.dynamic_jumps.iter() {
+            out.write_str("  ")?;
+            self.write_label(out, *address)?;
+            out.write_str(" jumps where a store sets it\n")?;
+        }
+
+        let summary = [
+            ("\nUNREACHABLE CODE\n", &self.unreachable[..]),
+            ("\nUNREFERENCED DATA\n", &self.unreferenced[..]),
+            ("\nRUNS OFF THE END OF MEMORY\n", self.runs_off_end.as_slice()),
+        ];
+        for (title, addresses) in summary {
+            out.write_str(title)?;
+            for address in addresses.iter() {
+                out.write_str("  ")?;
+                self.write_label(out, *address)?;
+                out.write_str("\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `address`, followed by the label of its word when it has one.
+    fn write_label(&self, out: &mut dyn fmt::Write, address: usize) -> fmt::Result {
+        let label = self.entries.binary_search_by_key(&address, |entry| entry.address).ok()
+            .and_then(|i| self.entries[i].label.as_deref());
+        match label {
+            Some(label) => write!(out, "{} {}", address, label),
+            None => write!(out, "{}", address),
+        }
+    }
+}
+
+/// Writes each item with `write_item`, with `separator` between them.
+fn write_joined<T>(out: &mut dyn fmt::Write, items: impl Iterator<Item = T>, separator: &str,
+    mut write_item: impl FnMut(&mut dyn fmt::Write, T) -> fmt::Result) -> fmt::Result
+{
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.write_str(separator)?;
+        }
+        write_item(out, item)?;
+    }
+    Ok(())
+}
+
+/// A place a routine reads an input from or leaves an output in. The overflow toggle is
+/// +1 when on and +0 when off, and the comparison indicator is -1, +0 or +1 for less,
+/// equal and greater, so that every location holds a word.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Location {
+    /// One of `rA`, `rX`, `rI1` to `rI6`, and `rJ`.
+    Register(&'static str),
+    Memory(usize),
+    Overflow,
+    Comparison,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Register(name) => write!(f, "{}", name),
+            Location::Memory(address) => write!(f, "mem {}", address),
+            Location::Overflow => write!(f, "overflow"),
+            Location::Comparison => write!(f, "comparison"),
+        }
+    }
+}
+
+/// A routine to compare: the program holding it, where it starts, and where its inputs
+/// and outputs are. The routine finishes at `HLT`, so a subroutine is compared through a
+/// program calling it.
+#[derive(Clone, Debug)]
+pub struct RoutineSpec {
+    pub program: AssembledProgram,
+    pub entry: usize,
+    pub inputs: Vec<Location>,
+    pub outputs: Vec<Location>,
+    /// The most instructions the routine may execute before it is taken to hang.
+    pub step_limit: usize,
+}
+
+/// The instructions a routine may execute unless its spec says otherwise.
+pub const DEFAULT_STEP_LIMIT: usize = 100_000;
+
+impl RoutineSpec {
+    /// The routine of `program` starting at `entry`, finishing at `HLT` within
+    /// `DEFAULT_STEP_LIMIT` instructions, with no inputs or outputs yet.
+    pub fn new(program: AssembledProgram, entry: usize) -> RoutineSpec {
+        RoutineSpec { program, entry, inputs: Vec::new(), outputs: Vec::new(), step_limit: DEFAULT_STEP_LIMIT }
+    }
+}
+
+/// How the input vectors are chosen. Either way, the state the routines don't declare
+/// as input, every other register, the flags, and the memory outside their programs, is
+/// random, and the same for both routines, so that a routine depending on it by
+/// accident is likely to be caught.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    /// Every combination of the values of `domains`, one list of values per input, in
+    /// the order of the inputs.
+    Exhaustive { domains: Vec<Vec<Word>> },
+    /// `cases` vectors, each input drawn from its range in `ranges`, in the order of the
+    /// inputs. The same seed gives the same vectors and state.
+    Randomized { cases: usize, seed: u64, ranges: Vec<RangeInclusive<i64>> },
+}
+
+/// The seed of the state outside the inputs in an exhaustive check.
+pub const EXHAUSTIVE_SEED: u64 = 0x6d69_7861_6c00;
+
+/// How a routine ended on one input vector.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RoutineOutcome {
+    /// It finished, leaving `outputs` in its output locations, in order.
+    Finished { outputs: Vec<Word>, steps: usize },
+    /// It hadn't finished after its step limit.
+    Hung { steps: usize },
+    Failed(MixError),
+}
+
+/// The first input vector on which the routines differ, with how each ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Counterexample {
+    /// The number of the case, from 0.
+    pub case: usize,
+    /// The value of each input, in order.
+    pub inputs: Vec<Word>,
+    pub a: RoutineOutcome,
+    pub b: RoutineOutcome,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EquivalenceResult {
+    /// Both routines finished with the same outputs in all `cases`.
+    Equivalent { cases: usize },
+    /// They didn't, or one of them hung or failed.
+    Differs(Counterexample),
+}
+
+/// Errors raised when two routines can't be compared.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EquivalenceError {
+    /// The routines have `a` and `b` inputs, or outputs when `outputs`.
+    Mismatch { outputs: bool, a: usize, b: usize },
+    /// The strategy gives `domains` domains or ranges for `inputs` inputs.
+    DomainCount { inputs: usize, domains: usize },
+    /// An input has no values to take.
+    EmptyDomain { input: usize },
+    /// The byte size isn't one of `migration::BYTE_SIZES`.
+    ByteSize(u16),
+}
+
+impl fmt::Display for EquivalenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EquivalenceError::Mismatch { outputs, a, b } =>
+                write!(f, "The routines have {} and {} {}", a, b, if *outputs { "outputs" } else { "inputs" }),
+            EquivalenceError::DomainCount { inputs, domains } =>
+                write!(f, "The strategy gives {} domains for {} inputs", domains, inputs),
+            EquivalenceError::EmptyDomain { input } => write!(f, "Input {} has no values", input),
+            EquivalenceError::ByteSize(byte_size) => write!(f, "{} is not a byte size the computer takes", byte_size),
+        }
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Case {} with inputs", self.case)?;
+        write_joined(f, self.inputs.iter(), ",", |out, word| write!(out, " {}", word.field_value(FieldSpec::WHOLE)))?;
+        for (name, outcome) in [("a", &self.a), ("b", &self.b)] {
+            write!(f, "\n  {}: ", name)?;
+            match outcome {
+                RoutineOutcome::Finished { outputs, steps } => {
+                    write!(f, "finished after {} steps with", steps)?;
+                    write_joined(f, outputs.iter(), ",", |out, word| write!(out, " {}", word.field_value(FieldSpec::WHOLE)))?;
+                },
+                RoutineOutcome::Hung { steps } => write!(f, "hadn't finished after {} steps", steps)?,
+                RoutineOutcome::Failed(error) => write!(f, "failed: {}", error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the routines `a` and `b` compute the same function of their inputs: run
+/// from the same state with the same inputs, in the order each declares them, they
+/// leave the same words in their outputs, compared in order. `-0` and `+0` differ.
+///
+/// ## Returns
+/// - `Differs` at the first input vector on which the outputs differ or either routine
+///   hangs or fails, and `Equivalent` with the number of vectors tried otherwise.
+///
+/// ## Errors
+/// - `Mismatch` when the routines don't have as many inputs or outputs as each other.
+/// - `DomainCount` when the strategy doesn't give a domain for each input.
+/// - `EmptyDomain` when one of them is empty.
+pub fn check_equivalence(a: RoutineSpec, b: RoutineSpec, strategy: Strategy) -> Result<EquivalenceResult, EquivalenceError> {
+    check_equivalence_in_base(a, b, strategy, CONFIGURED_BYTE_SIZE as u16)
+}
+
+/// As `check_equivalence`, running both routines on a computer whose bytes hold
+/// `byte_size` values.
+///
+/// ## Errors
+/// - Any error of `check_equivalence`.
+/// - `ByteSize` when `byte_size` isn't in `migration::BYTE_SIZES`.
+pub fn check_equivalence_in_base(a: RoutineSpec, b: RoutineSpec, strategy: Strategy, byte_size: u16) -> Result<EquivalenceResult, EquivalenceError> {
+    if !BYTE_SIZES.contains(&byte_size) {
+        return Err(EquivalenceError::ByteSize(byte_size));
+    }
+    if a.inputs.len() != b.inputs.len() {
+        return Err(EquivalenceError::Mismatch { outputs: false, a: a.inputs.len(), b: b.inputs.len() });
+    }
+    if a.outputs.len() != b.outputs.len() {
+        return Err(EquivalenceError::Mismatch { outputs: true, a: a.outputs.len(), b: b.outputs.len() });
+    }
+    let (domains, seed) = match &strategy {
+        Strategy::Exhaustive { domains } => (domains.iter().map(|domain| domain.is_empty()).collect::<Vec<_>>(), EXHAUSTIVE_SEED),
+        Strategy::Randomized { ranges, seed, .. } => (ranges.iter().map(|range| range.is_empty()).collect(), *seed),
+    };
+    if domains.len() != a.inputs.len() {
+        return Err(EquivalenceError::DomainCount { inputs: a.inputs.len(), domains: domains.len() });
+    }
+    if let Some(input) = domains.iter().position(|empty| *empty) {
+        return Err(EquivalenceError::EmptyDomain { input });
+    }
+
+    let mut random = Prng::new(seed);
+    let cases = match &strategy {
+        Strategy::Exhaustive { domains } => domains.iter().map(Vec::len).product(),
+        Strategy::Randomized { cases, .. } => *cases,
+    };
+    for case in 0..cases {
+        let inputs: Vec<Word> = match &strategy {
+            Strategy::Exhaustive { domains } => {
+                let mut rest = case;
+                domains.iter().rev()
+                    .map(|domain| {
+                        let value = domain[rest % domain.len()];
+                        rest /= domain.len();
+                        value
+                    })
+                    .collect::<Vec<_>>().into_iter().rev().collect()
+            },
+            Strategy::Randomized { ranges, .. } =>
+                ranges.iter().map(|range| Word::from_value_in_base(random.in_range(range.clone()), byte_size)).collect(),
+        };
+        let state = random_state(&mut random, byte_size);
+        let (outcome_a, outcome_b) = (run_routine(&a, &inputs, &state), run_routine(&b, &inputs, &state));
+        let same = match (&outcome_a, &outcome_b) {
+            (RoutineOutcome::Finished { outputs: first, .. }, RoutineOutcome::Finished { outputs: second, .. }) => first == second,
+            _ => false,
+        };
+        if !same {
+            return Ok(EquivalenceResult::Differs(Counterexample { case, inputs, a: outcome_a, b: outcome_b }));
+        }
+    }
+    Ok(EquivalenceResult::Equivalent { cases })
+}
+
+/// Runs the routine of `spec` from `state` with `inputs` in its input locations.
+fn run_routine(spec: &RoutineSpec, inputs: &[Word], state: &Computer) -> RoutineOutcome {
+    let mut computer = state.clone();
+    spec.program.load_into(&mut computer);
+    for (location, value) in spec.inputs.iter().zip(inputs.iter()) {
+        set_location(&mut computer, *location, *value);
+    }
+    computer.pc = spec.entry;
+    let mut steps = 0;
+    while !computer.is_halted() {
+        if steps == spec.step_limit {
+            return RoutineOutcome::Hung { steps };
+        }
+        if let Err(error) = computer.step() {
+            return RoutineOutcome::Failed(error);
+        }
+        if let Some(unit) = computer.blocked_on() {
+            return RoutineOutcome::Failed(MixError::UnitBlocked { pc: computer.pc, unit });
+        }
+        steps += 1;
+    }
+    let outputs = spec.outputs.iter().map(|location| location_value(&computer, *location)).collect();
+    RoutineOutcome::Finished { outputs, steps }
+}
+
+/// A computer with every register, both flags, and every word of memory random. The
+/// index registers and rJ hold values that fit in two bytes of `byte_size` values, rJ a
+/// positive one.
+fn random_state(random: &mut Prng, byte_size: u16) -> Computer {
+    let mut computer = Computer::default();
+    computer.set_byte_size(byte_size).expect("[Error random_state] check_equivalence_in_base checked the byte size");
+    for word in computer.memory.iter_mut() {
+        *word = random.word_in_base(byte_size);
+    }
+    let largest_index = byte_size as i64 * byte_size as i64 - 1;
+    computer.ra = random.word_in_base(byte_size);
+    computer.rx = random.word_in_base(byte_size);
+    for name in ["rI1", "rI2", "rI3", "rI4", "rI5", "rI6"] {
+        let value = random.in_range(-largest_index..=largest_index);
+        let magnitude = Word::from_value_in_base(value.abs(), byte_size);
+        *register_mut(&mut computer, name) = if value < 0 { magnitude.negate() } else { magnitude };
+    }
+    computer.rj = Word::from_value_in_base(random.in_range(0..=largest_index), byte_size);
+    computer.overflow_flag = random.bool();
+    computer.comparison_flag = *random.choose(&[ComparisonFlag::less, ComparisonFlag::equal, ComparisonFlag::greater]);
+    computer
+}
+
+fn register(computer: &Computer, name: &str) -> Word {
+    match name {
+        "rA" => computer.ra,
+        "rX" => computer.rx,
+        "rI1" => computer.ri1,
+        "rI2" => computer.ri2,
+        "rI3" => computer.ri3,
+        "rI4" => computer.ri4,
+        "rI5" => computer.ri5,
+        "rI6" => computer.ri6,
+        "rJ" => computer.rj,
+        _ => panic!("[Error register] {} is not a register", name),
+    }
+}
+
+fn register_mut<'a>(computer: &'a mut Computer, name: &str) -> &'a mut Word {
+    match name {
+        "rA" => &mut computer.ra,
+        "rX" => &mut computer.rx,
+        "rI1" => &mut computer.ri1,
+        "rI2" => &mut computer.ri2,
+        "rI3" => &mut computer.ri3,
+        "rI4" => &mut computer.ri4,
+        "rI5" => &mut computer.ri5,
+        "rI6" => &mut computer.ri6,
+        "rJ" => &mut computer.rj,
+        _ => panic!("[Error register_mut] {} is not a register", name),
+    }
+}
+
+pub(crate) fn set_location(computer: &mut Computer, location: Location, value: Word) {
+    match location {
+        Location::Register(name) => *register_mut(computer, name) = value,
+        Location::Memory(address) => computer.write_mem(address, value),
+        Location::Overflow => computer.overflow_flag = value.field_value(FieldSpec::WHOLE) != 0,
+        Location::Comparison => computer.comparison_flag = match value.field_value(FieldSpec::WHOLE) {
+            value if value < 0 => ComparisonFlag::less,
+            0 => ComparisonFlag::equal,
+            _ => ComparisonFlag::greater,
+        },
+    }
+}
+
+pub(crate) fn location_value(computer: &Computer, location: Location) -> Word {
+    match location {
+        Location::Register(name) => register(computer, name),
+        Location::Memory(address) => computer.memory.get(address).copied().unwrap_or(Word::default()),
+        Location::Overflow => Word::from_value(computer.overflow_flag as i64),
+        Location::Comparison => Word::from_value(match computer.comparison_flag {
+            ComparisonFlag::less => -1,
+            ComparisonFlag::equal => 0,
+            ComparisonFlag::greater => 1,
+        }),
+    }
+}
@@ -0,0 +1,239 @@
+//! The public surface of the crate, listed so that a change to it is made on purpose. A
+//! listing names every `pub` item written out in the sources, module by module: the
+//! items of each module, the public fields and variants of its structs and enums, the
+//! methods of its traits, and the public functions and constants of its inherent impls.
+//! Items visible to the crate alone (`pub(crate)`, `pub(super)`) aren't part of it, nor
+//! are the impls of traits, nor the items a macro generates, such as the instructions of
+//! `create_instruction!`, which the instruction reference covers instead.
+//!
+//! Items are marked with what the sources say of their stability:
+//!
+//! - `hidden`, for `#[doc(hidden)]`, public only while the crate moves off them;
+//! - `deprecated`, for `#[deprecated]`;
+//! - `sealed`, for a trait with a `Sealed` supertrait, which only the crate implements;
+//! - `non_exhaustive`, for `#[non_exhaustive]`;
+//! - `unstable`, for a module whose docs have a paragraph beginning `Unstable:`, whose
+//!   items may change in any release.
+//!
+//! `docs/public_api.txt` is the listing of `src` checked in, and a test fails when
+//! listing it again gives anything else, so that adding or removing an item shows up in
+//! review. `mixal public-api` writes it again after such a change.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// The line a listing begins with.
+pub const LISTING_TITLE: &str = "# mixal public API";
+
+/// The sources never listed: the tests, the library's root, which only declares the
+/// modules, and the binary's entry point.
+const UNLISTED: [&str; 3] = ["lib.rs", "main.rs", "tests.rs"];
+
+/// The module paths and texts of the sources under `root`, sorted by path, leaving out
+/// `UNLISTED`.
+///
+/// ## Errors
+/// - The first directory or file that couldn't be read.
+pub fn read_sources(root: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut sources = vec![];
+    read_directory(root, "", &mut sources)?;
+    sources.sort();
+    Ok(sources)
+}
+
+fn read_directory(directory: &Path, prefix: &str, sources: &mut Vec<(String, String)>) -> io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+        if path.is_dir() {
+            read_directory(&path, &format!("{}{}::", prefix, name), sources)?;
+        } else if name.ends_with(".rs") && !(prefix.is_empty() && UNLISTED.contains(&name.as_str())) {
+            let module = match name.trim_end_matches(".rs") {
+                "mod" => prefix.trim_end_matches("::").to_string(),
+                stem => format!("{}{}", prefix, stem),
+            };
+            sources.push((module, std::fs::read_to_string(&path)?));
+        }
+    }
+    Ok(())
+}
+
+/// What the lines being read belong to.
+enum Scope {
+    Module,
+    /// The fields of a struct, or the variants of an enum when `is_enum`.
+    Members { owner: String, is_enum: bool },
+    /// The methods of an inherent impl, or of a trait when `is_trait`, whose methods are
+    /// public without saying so.
+    Associated { owner: String, is_trait: bool },
+    /// An impl of a trait, or anything else whose lines aren't listed.
+    Skipped,
+}
+
+/// The listing of `sources`, as `read_sources` gives them.
+pub fn listing(sources: &[(String, String)]) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}", LISTING_TITLE).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "Generated from the sources by `mixal public-api`. Don't edit this file; change the code and").unwrap();
+    writeln!(out, "run `cargo run -- public-api > docs/public_api.txt`.").unwrap();
+    for (module, text) in sources {
+        let items = module_items(text);
+        if items.is_empty() {
+            continue;
+        }
+        writeln!(out).unwrap();
+        if is_unstable(text) {
+            writeln!(out, "mod {} (unstable)", module).unwrap();
+        } else {
+            writeln!(out, "mod {}", module).unwrap();
+        }
+        for item in items {
+            writeln!(out, "    {}", item).unwrap();
+        }
+    }
+    out
+}
+
+/// Whether the module docs of `text` have a paragraph beginning `Unstable:`.
+fn is_unstable(text: &str) -> bool {
+    text.lines().take_while(|line| line.starts_with("//!") || line.trim().is_empty())
+        .any(|line| line.trim_start_matches("//!").trim_start().starts_with("Unstable:"))
+}
+
+/// The public items of one source, in the order written.
+fn module_items(text: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut scope = Scope::Module;
+    let mut markers: Vec<&str> = vec![];
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        if line.starts_with("//") || line.is_empty() {
+            continue;
+        }
+        if line.starts_with("#[") {
+            if line.starts_with("#[doc(hidden)]") {
+                markers.push("hidden");
+            } else if line.starts_with("#[deprecated") {
+                markers.push("deprecated");
+            } else if line.starts_with("#[non_exhaustive]") {
+                markers.push("non_exhaustive");
+            }
+            continue;
+        }
+        let found = std::mem::take(&mut markers);
+        if indent == 0 {
+            if line.starts_with('}') {
+                scope = Scope::Module;
+                continue;
+            }
+            if line.starts_with("impl") {
+                scope = inherent_impl(line).map_or(Scope::Skipped, |owner| Scope::Associated { owner, is_trait: false });
+                continue;
+            }
+            let (kind, name) = match declared(line) {
+                Some(declared) => declared,
+                None => continue,
+            };
+            let mut found = found;
+            if kind == "trait" && line.contains("Sealed") {
+                found.push("sealed");
+            }
+            items.push(marked(format!("{} {}", kind, name), &found));
+            let opens = line.ends_with('{');
+            scope = match kind {
+                "struct" | "enum" if opens => Scope::Members { owner: name, is_enum: kind == "enum" },
+                "trait" if opens => Scope::Associated { owner: name, is_trait: true },
+                _ => Scope::Module,
+            };
+        } else if indent == 4 {
+            match &scope {
+                Scope::Members { owner, is_enum: false } => {
+                    if let Some(field) = line.strip_prefix("pub ").and_then(|rest| rest.split(':').next()) {
+                        items.push(marked(format!("field {}.{}", owner, field.trim()), &found));
+                    }
+                },
+                Scope::Members { owner, is_enum: true } => {
+                    if line.starts_with(|c: char| c.is_alphabetic()) {
+                        let variant: String = line.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+                        items.push(marked(format!("variant {}::{}", owner, variant), &found));
+                    }
+                },
+                Scope::Associated { owner, is_trait } => {
+                    let line = if *is_trait { format!("pub {}", line) } else { line.to_string() };
+                    if let Some((kind @ ("fn" | "const" | "type"), name)) = declared(&line) {
+                        items.push(marked(format!("{} {}::{}", kind, owner, name), &found));
+                    }
+                },
+                Scope::Module | Scope::Skipped => {},
+            }
+        }
+    }
+    items
+}
+
+/// The kind and name of the public item `line` declares, or `None` when it declares none.
+fn declared(line: &str) -> Option<(&'static str, String)> {
+    let mut rest = line.strip_prefix("pub ")?;
+    for qualifier in ["const fn ", "unsafe fn ", "async fn "] {
+        if let Some(function) = rest.strip_prefix(qualifier) {
+            rest = &line[line.len() - function.len() - 3..];
+        }
+    }
+    let kind = ["fn", "struct", "enum", "trait", "type", "const", "static", "mod", "use"].iter()
+        .find(|kind| rest.starts_with(&format!("{} ", kind)))?;
+    let rest = &rest[kind.len() + 1..];
+    if *kind == "use" {
+        return Some(("use", rest.trim_end_matches(';').to_string()));
+    }
+    let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    if name.is_empty() { None } else { Some((kind, name)) }
+}
+
+/// The type an impl line is an inherent impl of, or `None` for an impl of a trait.
+fn inherent_impl(line: &str) -> Option<String> {
+    let mut rest = line.strip_prefix("impl")?;
+    if rest.starts_with('<') {
+        let mut depth = 0;
+        let end = rest.char_indices().find(|(_, c)| {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {},
+            }
+            depth == 0
+        })?.0;
+        rest = &rest[end + 1..];
+    }
+    let rest = rest.trim_end_matches('{').trim();
+    if rest.contains(" for ") {
+        return None;
+    }
+    let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+fn marked(item: String, markers: &[&str]) -> String {
+    if markers.is_empty() { item } else { format!("{} ({})", item, markers.join(", ")) }
+}
+
+/// Writes the listing of the sources under `args[0]`, or under `src` when no directory
+/// is given.
+///
+/// ## Returns
+/// - The exit code: 0 once written, and 1 when the sources couldn't be read.
+pub fn main(args: &[String]) -> i32 {
+    let root = args.first().map_or("src", String::as_str);
+    match read_sources(Path::new(root)) {
+        Ok(sources) => {
+            print!("{}", listing(&sources));
+            0
+        },
+        Err(error) => {
+            eprintln!("[Error main] Couldn't read the sources under {}: {}", root, error);
+            1
+        },
+    }
+}
@@ -0,0 +1,228 @@
+//! Caching of assembled programs, for callers that assemble the same sources many times.
+//! Entries are keyed by a hash of the source and of every option that affects the result,
+//! and a hit is only taken once the source and options compare equal to the entry's, so a
+//! hash collision costs a reassembly rather than a wrong program.
+//!
+//! `Assembler` keeps its cache in memory. `assemble_cached` keeps one in a directory, each
+//! entry named by the hash of its source and written in the raw format of the loader, with
+//! the regions, overlaps, and symbols of the program in comment lines.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::charset::CharsetMapping;
+use crate::fingerprint::Fnv1a;
+use crate::layout::MemoryLayout;
+use crate::loader::{self, AssembledProgram, EmittedRegion, Overlap};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use super::{AssembleError, AssemblyState};
+
+/// The first word of the header line of a cache file.
+const CACHE_HEADER: &str = "# mixal-cache";
+
+/// Everything besides the source that affects what assembly produces.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AssemblyOptions {
+    /// The layout each `ORIG` is held to, as by `assemble_with_layout`.
+    pub layout: Option<MemoryLayout>,
+    /// The number of values a byte of the program holds, one of `BYTE_SIZES`, for a
+    /// computer set to it with `Computer::set_byte_size`.
+    pub byte_size: u16,
+    /// The characters the operands of `ALF` are read in, and that what the program
+    /// prints is rendered in.
+    pub charset: CharsetMapping,
+}
+
+impl Default for AssemblyOptions {
+    /// No layout, bytes of `CONFIGURED_BYTE_SIZE` values, and the default mapping.
+    fn default() -> AssemblyOptions {
+        AssemblyOptions { layout: None, byte_size: CONFIGURED_BYTE_SIZE as u16, charset: CharsetMapping::default() }
+    }
+}
+
+struct CacheEntry {
+    key: u64,
+    source: String,
+    options: AssemblyOptions,
+    result: Result<AssembledProgram, AssembleError>,
+}
+
+/// An assembler remembering the results of its last `capacity` distinct assemblies.
+pub struct Assembler {
+    pub options: AssemblyOptions,
+    capacity: usize,
+    /// The entries, least recently used first.
+    entries: Vec<CacheEntry>,
+    hits: usize,
+    misses: usize,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Assembler::new()
+    }
+}
+
+impl Assembler {
+    /// An assembler with the default options that caches nothing.
+    pub fn new() -> Assembler {
+        Assembler::with_cache(0)
+    }
+
+    /// An assembler with the default options caching up to `capacity` results, dropping
+    /// the least recently used when full.
+    pub fn with_cache(capacity: usize) -> Assembler {
+        Assembler { options: AssemblyOptions::default(), capacity, entries: Vec::new(), hits: 0, misses: 0 }
+    }
+
+    /// The number of assemblies answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of assemblies that had to be done.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Assembles `source` under the current options, as `assemble` or
+    /// `assemble_with_layout` would.
+    ///
+    /// ## Errors
+    /// - The first problem found, in order of the source.
+    pub fn assemble(&mut self, source: &str) -> Result<AssembledProgram, AssembleError> {
+        let key = cache_key(source, &self.options);
+        let found = self.entries.iter()
+            .position(|entry| entry.key == key && entry.source == source && entry.options == self.options);
+        if let Some(i) = found {
+            self.hits += 1;
+            let entry = self.entries.remove(i);
+            let result = entry.result.clone();
+            self.entries.push(entry);
+            return result;
+        }
+        self.misses += 1;
+        let result = assemble_with_options(source, &self.options);
+        if self.capacity > 0 {
+            if self.entries.len() == self.capacity {
+                self.entries.remove(0);
+            }
+            self.entries.push(CacheEntry { key, source: source.to_string(), options: self.options.clone(), result: result.clone() });
+        }
+        result
+    }
+}
+
+fn assemble_with_options(source: &str, options: &AssemblyOptions) -> Result<AssembledProgram, AssembleError> {
+    let state = AssemblyState::with_options(source, options);
+    match state.diagnostics.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(state.program.unwrap_or_default()),
+    }
+}
+
+fn cache_key(source: &str, options: &AssemblyOptions) -> u64 {
+    let mut hasher = Fnv1a::new();
+    source.hash(&mut hasher);
+    options.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug)]
+pub enum CacheError {
+    /// The source couldn't be read or the cache couldn't be written.
+    Io(io::Error),
+    Assemble(AssembleError),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheError::Io(error) => write!(f, "I/O error on the assembly cache: {}", error),
+            CacheError::Assemble(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(error: io::Error) -> CacheError {
+        CacheError::Io(error)
+    }
+}
+
+/// Assembles the source at `path` with the default options, taking the program from
+/// `cache_dir` when an earlier call assembled the same source, whatever the times on the
+/// files. An entry that is unreadable or doesn't match the source is replaced. Sources
+/// with errors aren't cached.
+///
+/// ## Errors
+/// - `Io` when the source can't be read, or the entry can't be written to `cache_dir`.
+/// - `Assemble` for the first problem in the source.
+pub fn assemble_cached(path: &Path, cache_dir: &Path) -> Result<AssembledProgram, CacheError> {
+    let source = fs::read_to_string(path)?;
+    let key = cache_key(&source, &AssemblyOptions::default());
+    let header = format!("{} {:016x} {}", CACHE_HEADER, key, source.len());
+    let entry_path = cache_dir.join(format!("{:016x}.raw", key));
+    if let Ok(text) = fs::read_to_string(&entry_path) {
+        if let Some(program) = read_entry(&text, &header) {
+            return Ok(program);
+        }
+    }
+    let program = assemble_with_options(&source, &AssemblyOptions::default()).map_err(CacheError::Assemble)?;
+    fs::create_dir_all(cache_dir)?;
+    let partial_path = cache_dir.join(format!("{:016x}.raw.partial", key));
+    fs::write(&partial_path, write_entry(&program, &header))?;
+    fs::rename(&partial_path, &entry_path)?;
+    Ok(program)
+}
+
+fn write_entry(program: &AssembledProgram, header: &str) -> String {
+    let mut text = format!("{}\n", header);
+    for region in program.regions.iter() {
+        text.push_str(&format!("# region {} {} {} {}\n", region.range.start, region.range.end, region.first_line, region.last_line));
+    }
+    for overlap in program.overlaps.iter() {
+        text.push_str(&format!("# overlap {} {} {}\n", overlap.address, overlap.first_line, overlap.second_line));
+    }
+    for (name, value) in program.source_map.symbols.iter() {
+        text.push_str(&format!("# symbol {} {}\n", name, value));
+    }
+    text + &loader::render_raw(program)
+}
+
+/// The program in the cache entry `text`, when its first line is `header` and the rest
+/// reads back.
+fn read_entry(text: &str, header: &str) -> Option<AssembledProgram> {
+    let mut lines = text.lines();
+    if lines.next()? != header {
+        return None;
+    }
+    let mut program = loader::parse_raw(text).ok()?;
+    program.regions.clear();
+    let mut symbols = BTreeMap::new();
+    for line in lines {
+        let numbers = |prefix: &str| -> Option<Vec<usize>> {
+            line.strip_prefix(prefix)?.split_whitespace().map(|part| part.parse().ok()).collect()
+        };
+        if let Some(numbers) = numbers("# region ") {
+            match numbers[..] {
+                [start, end, first_line, last_line] => program.regions.push(EmittedRegion { range: start..end, first_line, last_line }),
+                _ => return None,
+            }
+        } else if let Some(numbers) = numbers("# overlap ") {
+            match numbers[..] {
+                [address, first_line, second_line] => program.overlaps.push(Overlap { address, first_line, second_line }),
+                _ => return None,
+            }
+        } else if let Some(symbol) = line.strip_prefix("# symbol ") {
+            let (name, value) = symbol.split_once(' ')?;
+            symbols.insert(name.to_string(), value.parse().ok()?);
+        }
+    }
+    Arc::make_mut(&mut program.source_map).symbols = symbols;
+    Some(program)
+}
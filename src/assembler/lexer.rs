@@ -0,0 +1,387 @@
+//! Splits a MIXAL line into its fields and parses its operand. Nothing here depends on
+//! other lines, so a parsed line can be reused for as long as its text doesn't change,
+//! wherever in the source it moves; every span is relative to the start of the line.
+
+use super::{AssembleErrorKind, Span};
+use crate::charset::{CharsetError, CharsetMapping};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Atom {
+    Number(i64),
+    Symbol(String),
+    /// `*`, the location of the line.
+    Here,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// `L:R`, which is `8L + R`.
+    Field,
+}
+
+/// `[+|-] ATOM (OP ATOM)*`, evaluated strictly from left to right as MIXAL requires.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Expr {
+    pub negated: bool,
+    pub first: (Atom, Span),
+    pub rest: Vec<(BinaryOp, Atom, Span)>,
+}
+
+impl Expr {
+    /// Every atom of the expression along with its span, in order.
+    pub fn atoms(&self) -> impl Iterator<Item = (&Atom, Span)> {
+        std::iter::once((&self.first.0, self.first.1)).chain(self.rest.iter().map(|(_, atom, span)| (atom, *span)))
+    }
+
+    /// The span from the first atom to the last.
+    pub fn span(&self) -> Span {
+        Span::new(self.first.1.start, self.rest.last().map_or(self.first.1.end, |(_, _, span)| span.end))
+    }
+}
+
+/// The parts of a W-value `EXPR(FIELD),EXPR(FIELD),...`, in order.
+pub type WValue = Vec<(Expr, Option<Expr>)>;
+
+/// The operand field: `ADDRESS,INDEX(FIELD)` for an instruction, each part of which may be
+/// left out and whose address may be a literal `=W-VALUE=`, a W-value
+/// `EXPR(FIELD),EXPR(FIELD),...` for a pseudo-operation, each field of which may be left
+/// out, or the five characters of `ALF`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Operand {
+    pub address: Option<Expr>,
+    /// The W-value of a literal address, with the span from one `=` to the other.
+    pub literal: Option<(WValue, Span)>,
+    pub index: Option<Expr>,
+    pub field: Option<Expr>,
+    pub parts: WValue,
+    /// The character codes of an `ALF` operand, padded with blanks.
+    pub characters: Option<([u8; 5], Span)>,
+}
+
+impl Operand {
+    pub fn is_empty(&self) -> bool {
+        self.address.is_none() && self.literal.is_none() && self.index.is_none() && self.field.is_none()
+            && self.parts.is_empty() && self.characters.is_none()
+    }
+
+    /// Every symbol the operand uses along with its span, in order.
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, Span)> {
+        let literal = self.literal.iter().flat_map(|(parts, _)| parts.iter());
+        self.address.iter()
+            .chain(literal.chain(self.parts.iter()).flat_map(|(expr, field)| std::iter::once(expr).chain(field.iter())))
+            .chain(self.index.iter()).chain(self.field.iter())
+            .flat_map(|expr| expr.atoms())
+            .filter_map(|(atom, span)| match atom {
+                Atom::Symbol(name) => Some((name.as_str(), span)),
+                _ => None,
+            })
+    }
+}
+
+/// A line split into its label, operation, and operand fields. A line that is blank or a
+/// comment has none of them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParsedLine {
+    pub label: Option<(String, Span)>,
+    pub operation: Option<(String, Span)>,
+    pub operand: Operand,
+    /// The first problem found in the line, which leaves the rest of it unparsed.
+    pub error: Option<(Span, AssembleErrorKind)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Colon,
+    Comma,
+    Equals,
+    OpenParen,
+    CloseParen,
+}
+
+/// Whether `text` is a MIXAL symbol: one to ten letters and digits, at least one a letter.
+pub fn is_symbol(text: &str) -> bool {
+    (1..=10).contains(&text.len())
+        && text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && text.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// Whether `name` refers to a local symbol, as `dB` to the last `dH` before it and `dF` to
+/// the first `dH` after it, giving the digit and whether it refers forward.
+pub fn local_reference(name: &str) -> Option<(u8, bool)> {
+    match name.as_bytes() {
+        [digit, b'B'] if digit.is_ascii_digit() => Some((digit - b'0', false)),
+        [digit, b'F'] if digit.is_ascii_digit() => Some((digit - b'0', true)),
+        _ => None,
+    }
+}
+
+/// The whitespace-separated fields of `line` as spans, up to `limit` of them.
+fn fields(line: &str, limit: usize) -> Vec<Span> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                fields.push(Span::new(s, i));
+                start = None;
+                if fields.len() == limit {
+                    break;
+                }
+            },
+            (false, None) => start = Some(i),
+            _ => {},
+        }
+    }
+    fields
+}
+
+/// Whether `operation` is a pseudo-operation, whose operand is a W-value.
+pub fn is_pseudo_operation(operation: &str) -> bool {
+    matches!(operation, "EQU" | "ORIG" | "CON" | "END" | "PUBL")
+}
+
+/// Parses a single line. A line starting with `*` is a comment, and a line starting with
+/// anything but whitespace has a label. Whatever follows the operand is a remark. The
+/// operand of `ALF` is read in `charset`.
+pub fn parse_line(line: &str, charset: &CharsetMapping) -> ParsedLine {
+    let mut parsed = ParsedLine::default();
+    if line.starts_with('*') {
+        return parsed;
+    }
+    let has_label = !line.starts_with(char::is_whitespace);
+    let mut fields = fields(line, if has_label { 3 } else { 2 }).into_iter();
+    let text = |span: Span| line[span.start..span.end].to_string();
+    if has_label {
+        if let Some(span) = fields.next() {
+            let label = text(span);
+            if !is_symbol(&label) || local_reference(&label).is_some() {
+                parsed.error = Some((span, AssembleErrorKind::InvalidSymbol(label)));
+                return parsed;
+            }
+            parsed.label = Some((label, span));
+        }
+    }
+    match fields.next() {
+        Some(span) => parsed.operation = Some((text(span), span)),
+        None => {
+            if let Some((_, span)) = &parsed.label {
+                parsed.error = Some((*span, AssembleErrorKind::MissingOperation));
+            }
+            return parsed;
+        },
+    }
+    if let Some((operation, span)) = &parsed.operation {
+        if operation == "ALF" {
+            match characters(line, span.end, charset) {
+                Ok(characters) => parsed.operand.characters = Some(characters),
+                Err(error) => parsed.error = Some(error),
+            }
+            return parsed;
+        }
+    }
+    let is_w_value = parsed.operation.as_ref().is_some_and(|(operation, _)| is_pseudo_operation(operation));
+    if let Some(span) = fields.next() {
+        let parse = |tokens| {
+            let parser = Parser { tokens, next: 0 };
+            if is_w_value { parser.w_value(span) } else { parser.operand(span) }
+        };
+        match tokenize(&line[span.start..span.end], span.start).and_then(parse) {
+            Ok(operand) => parsed.operand = operand,
+            Err(error) => parsed.error = Some(error),
+        }
+    }
+    parsed
+}
+
+/// The operand of `ALF`, the first field of `line` from `start`: up to five characters
+/// without blanks, or up to five characters between double quotes, which may be blanks.
+/// Either is padded with blanks on the right, and no operand at all is five blanks.
+fn characters(line: &str, start: usize, charset: &CharsetMapping) -> Result<([u8; 5], Span), (Span, AssembleErrorKind)> {
+    let rest = &line[start..];
+    let begin = start + rest.len() - rest.trim_start().len();
+    let rest = &line[begin..];
+    let (text, span) = match rest.strip_prefix('"') {
+        Some(quoted) => match quoted.find('"') {
+            Some(end) => (&quoted[..end], Span::new(begin, begin + end + 2)),
+            None => return Err((Span::new(begin, line.len()), AssembleErrorKind::Syntax("expected a closing \"".to_string()))),
+        },
+        None => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], Span::new(begin, begin + end))
+        },
+    };
+    let codes = charset.encode(text).map_err(|error| (span, AssembleErrorKind::Charset(error)))?;
+    if codes.len() > 5 {
+        return Err((span, AssembleErrorKind::Charset(CharsetError::TooLong(codes.len()))));
+    }
+    let mut bytes = [0; 5];
+    bytes[..codes.len()].copy_from_slice(&codes);
+    Ok((bytes, span))
+}
+
+fn tokenize(text: &str, offset: usize) -> Result<Vec<(Token, Span)>, (Span, AssembleErrorKind)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            '*' => Token::Star,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '/' => Token::Slash,
+            ':' => Token::Colon,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            c if c.is_ascii_alphanumeric() => {
+                let mut end = start + 1;
+                while let Some((i, c)) = chars.peek().copied() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let word = &text[start..end];
+                let span = Span::new(offset + start, offset + end);
+                if word.chars().all(|c| c.is_ascii_digit()) {
+                    let value = word.parse().map_err(|_| (span, AssembleErrorKind::Syntax(format!("{} is too large", word))))?;
+                    tokens.push((Token::Number(value), span));
+                } else if is_symbol(word) {
+                    tokens.push((Token::Symbol(word.to_string()), span));
+                } else {
+                    return Err((span, AssembleErrorKind::InvalidSymbol(word.to_string())));
+                }
+                continue;
+            },
+            _ => {
+                let span = Span::new(offset + start, offset + start + c.len_utf8());
+                return Err((span, AssembleErrorKind::Syntax(format!("unexpected {:?}", c))));
+            },
+        };
+        tokens.push((token, Span::new(offset + start, offset + start + c.len_utf8())));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn error(&self, field: Span, message: &str) -> (Span, AssembleErrorKind) {
+        let span = self.tokens.get(self.next).map(|(_, span)| *span).unwrap_or(Span::new(field.end, field.end));
+        (span, AssembleErrorKind::Syntax(message.to_string()))
+    }
+
+    fn operand(mut self, field: Span) -> Result<Operand, (Span, AssembleErrorKind)> {
+        let mut operand = Operand::default();
+        if self.peek() == Some(&Token::Equals) {
+            let start = self.tokens[self.next].1.start;
+            self.next += 1;
+            let parts = self.parts(field, Some(&Token::Equals))?;
+            operand.literal = Some((parts, Span::new(start, self.tokens[self.next - 1].1.end)));
+        } else if !matches!(self.peek(), None | Some(Token::Comma) | Some(Token::OpenParen)) {
+            operand.address = Some(self.expr(field)?);
+        }
+        if self.peek() == Some(&Token::Comma) {
+            self.next += 1;
+            operand.index = Some(self.expr(field)?);
+        }
+        if self.peek() == Some(&Token::OpenParen) {
+            self.next += 1;
+            operand.field = Some(self.expr(field)?);
+            if self.peek() != Some(&Token::CloseParen) {
+                return Err(self.error(field, "expected )"));
+            }
+            self.next += 1;
+        }
+        if self.peek().is_some() {
+            return Err(self.error(field, "expected the end of the operand"));
+        }
+        Ok(operand)
+    }
+
+    fn w_value(mut self, field: Span) -> Result<Operand, (Span, AssembleErrorKind)> {
+        let parts = self.parts(field, None)?;
+        Ok(Operand { parts, ..Operand::default() })
+    }
+
+    /// The parts of a W-value, up to and including `closing`, or up to the end of the
+    /// operand when there is no `closing`.
+    fn parts(&mut self, field: Span, closing: Option<&Token>) -> Result<WValue, (Span, AssembleErrorKind)> {
+        let mut parts = Vec::new();
+        loop {
+            let expr = self.expr(field)?;
+            let mut part_field = None;
+            if self.peek() == Some(&Token::OpenParen) {
+                self.next += 1;
+                part_field = Some(self.expr(field)?);
+                if self.peek() != Some(&Token::CloseParen) {
+                    return Err(self.error(field, "expected )"));
+                }
+                self.next += 1;
+            }
+            parts.push((expr, part_field));
+            match self.peek() {
+                Some(Token::Comma) => self.next += 1,
+                token if token == closing => {
+                    self.next += closing.map_or(0, |_| 1);
+                    return Ok(parts);
+                },
+                _ if closing.is_some() => return Err(self.error(field, "expected , or =")),
+                _ => return Err(self.error(field, "expected , or the end of the operand")),
+            }
+        }
+    }
+
+    fn expr(&mut self, field: Span) -> Result<Expr, (Span, AssembleErrorKind)> {
+        let negated = match self.peek() {
+            Some(Token::Plus) => { self.next += 1; false },
+            Some(Token::Minus) => { self.next += 1; true },
+            _ => false,
+        };
+        let first = self.atom(field)?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Sub,
+                Some(Token::Star) => BinaryOp::Mul,
+                Some(Token::Slash) => BinaryOp::Div,
+                Some(Token::Colon) => BinaryOp::Field,
+                _ => break,
+            };
+            self.next += 1;
+            let (atom, span) = self.atom(field)?;
+            rest.push((op, atom, span));
+        }
+        Ok(Expr { negated, first, rest })
+    }
+
+    fn atom(&mut self, field: Span) -> Result<(Atom, Span), (Span, AssembleErrorKind)> {
+        let atom = match self.tokens.get(self.next) {
+            Some((Token::Number(value), span)) => (Atom::Number(*value), *span),
+            Some((Token::Symbol(name), span)) => (Atom::Symbol(name.clone()), *span),
+            Some((Token::Star, span)) => (Atom::Here, *span),
+            _ => return Err(self.error(field, "expected a number, a symbol, or *")),
+        };
+        self.next += 1;
+        Ok(atom)
+    }
+}
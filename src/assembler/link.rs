@@ -0,0 +1,243 @@
+//! Separate assembly and linking. `assemble_relocatable` assembles one module of a
+//! program on its own, and `link` places modules one after another in memory and resolves
+//! the symbols each uses from the others.
+//!
+//! Every symbol a module defines is local to it unless exported, by `PUBL` or by the
+//! export list given to `assemble_relocatable`. Only exports enter the namespace shared by
+//! the modules, so two modules may each define `LOOP` or `TEMP` without meeting, while two
+//! modules exporting the same symbol is an error naming both. A symbol a module uses but
+//! doesn't define is an import, which must name a symbol another module exports.
+//!
+//! A module is relocated by assembling it again from its base, rather than by patching
+//! its words, so that `*` and every label move with the module while numbers and `EQU`s
+//! of numbers don't. Its imports are only known once every module is placed, so `EQU` and
+//! `ORIG`, whose operands the first pass evaluates, can't use them. An `ORIG` to a number
+//! places words at that address whatever the module's base. Each module starts where the
+//! location counter of the one before it ended, and the program starts at the `END` of
+//! the first module with one.
+//!
+//! Debugging a linked program names each local symbol by its module, as `MAIN.LOOP`, in
+//! the labels of its source map and in its program map, while exports keep their names.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+use crate::computer::DEFAULT_MEMORY_SIZE;
+use crate::loader::{AssembledProgram, OverlapPolicy, ProgramBuilder};
+use super::{lexer, AssembleError, AssemblyState, ModuleContext};
+
+/// A module assembled on its own, for `link`.
+#[derive(Clone, Debug)]
+pub struct ObjectModule {
+    pub name: String,
+    /// The symbols the module exports.
+    pub exports: BTreeSet<String>,
+    /// The symbols the module uses without defining, each with the line first using it,
+    /// counting from 1.
+    pub imports: BTreeMap<String, usize>,
+    /// The module assembled from 0, with its imports valued 0.
+    state: AssemblyState,
+}
+
+impl ObjectModule {
+    /// The module assembled from 0, with its imports valued 0.
+    pub fn state(&self) -> &AssemblyState {
+        &self.state
+    }
+}
+
+/// A module as `link` placed it.
+#[derive(Clone, Debug)]
+pub struct LinkedModule {
+    pub name: String,
+    /// Where its location counter started.
+    pub base: usize,
+    pub exports: BTreeSet<String>,
+    state: AssemblyState,
+}
+
+impl LinkedModule {
+    /// The module assembled from its base, with its imports resolved.
+    pub fn state(&self) -> &AssemblyState {
+        &self.state
+    }
+
+    /// The name by which the rest of the program knows the symbol `name` of this module:
+    /// `MODULE.NAME` when the module defines it without exporting it, and `name` itself
+    /// when it is an export or an import.
+    pub fn qualified(&self, name: &str) -> String {
+        if self.state.symbols.get(name).is_some() && !self.exports.contains(name) {
+            format!("{}.{}", self.name, name)
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// A program linked from modules, along with the modules as placed.
+#[derive(Clone, Debug)]
+pub struct LinkedProgram {
+    pub program: AssembledProgram,
+    /// The modules, in the order given to `link`.
+    pub modules: Vec<LinkedModule>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LinkError {
+    /// A module's name isn't a valid symbol, and couldn't qualify its symbols.
+    InvalidModuleName(String),
+    /// Two modules have the same name.
+    DuplicateModule(String),
+    /// A problem with the source of `module`.
+    Assemble { module: String, error: AssembleError },
+    /// The export list of `module` names a symbol it doesn't define.
+    UndefinedExport { module: String, name: String },
+    /// The modules `first` and `second` both export `name`.
+    DuplicateExport { name: String, first: String, second: String },
+    /// `module` uses `name` on `line`, and no module defines it.
+    Unresolved { module: String, name: String, line: usize },
+    /// `module` uses `name` on `line`, which `defined_in` defines without exporting.
+    NotExported { module: String, name: String, line: usize, defined_in: String },
+    /// Two modules place a word at `address`, each given as the module and line.
+    Overlap { address: usize, first: (String, usize), second: (String, usize) },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkError::InvalidModuleName(name) => write!(f, "{} is not a valid module name", name),
+            LinkError::DuplicateModule(name) => write!(f, "There are two modules named {}", name),
+            LinkError::Assemble { module, error } => write!(f, "Module {}: {}", module, error),
+            LinkError::UndefinedExport { module, name } =>
+                write!(f, "Module {}: {} is exported but not defined", module, name),
+            LinkError::DuplicateExport { name, first, second } =>
+                write!(f, "{} is exported by both {} and {}", name, first, second),
+            LinkError::Unresolved { module, name, line } =>
+                write!(f, "Module {}, line {}: {} is not defined in any module", module, line, name),
+            LinkError::NotExported { module, name, line, defined_in } =>
+                write!(f, "Module {}, line {}: {} exists in {} but is not exported", module, line, name, defined_in),
+            LinkError::Overlap { address, first, second } =>
+                write!(f, "Module {}, line {}: address {} was already emitted by module {}, line {}",
+                       second.0, second.1, address, first.0, first.1),
+        }
+    }
+}
+
+/// Assembles the module `name` from `source`, exporting the symbols of `exports` along
+/// with those its `PUBL`s name.
+///
+/// ## Errors
+/// - `InvalidModuleName` when `name` isn't a valid symbol.
+/// - `Assemble` with the first problem with the source, in order of the source. A symbol
+///   the module doesn't define isn't a problem until linking.
+/// - `UndefinedExport` when `exports` names a symbol the module doesn't define.
+pub fn assemble_relocatable(name: &str, source: &str, exports: &[&str]) -> Result<ObjectModule, LinkError> {
+    if !lexer::is_symbol(name) {
+        return Err(LinkError::InvalidModuleName(name.to_string()));
+    }
+    let state = AssemblyState::with_module(source, ModuleContext { allow_imports: true, ..ModuleContext::default() });
+    if let Some(error) = state.diagnostics.first() {
+        return Err(LinkError::Assemble { module: name.to_string(), error: error.clone() });
+    }
+    if let Some(undefined) = exports.iter().find(|export| state.symbols.get(export).is_none()) {
+        return Err(LinkError::UndefinedExport { module: name.to_string(), name: undefined.to_string() });
+    }
+    let exports = state.pass_one().exports.iter().map(|(export, _, _)| export.clone())
+        .chain(exports.iter().map(|export| export.to_string()))
+        .collect();
+    let imports = state.imports().iter().map(|(import, i)| (import.clone(), i + 1)).collect();
+    Ok(ObjectModule { name: name.to_string(), exports, imports, state })
+}
+
+/// Links `modules` into one program, placing the first at `origin` and each of the
+/// others where the location counter of the one before it ended.
+///
+/// ## Errors
+/// - `DuplicateModule` when two modules have the same name.
+/// - `DuplicateExport` for the first symbol exported twice, in order of the modules.
+/// - `NotExported` or `Unresolved` for the first import no module exports, in order of
+///   the modules and then of name.
+/// - `Assemble` when a module can't be assembled from its base, as when its words would
+///   land outside of memory.
+/// - `Overlap` when two modules place a word at the same address.
+pub fn link(modules: &[ObjectModule], origin: usize) -> Result<LinkedProgram, LinkError> {
+    let mut names = BTreeSet::new();
+    let mut exporters: BTreeMap<&str, &str> = BTreeMap::new();
+    for module in modules.iter() {
+        if !names.insert(module.name.as_str()) {
+            return Err(LinkError::DuplicateModule(module.name.clone()));
+        }
+        for export in module.exports.iter() {
+            if let Some(first) = exporters.insert(export, &module.name) {
+                return Err(LinkError::DuplicateExport { name: export.clone(), first: first.to_string(), second: module.name.clone() });
+            }
+        }
+    }
+    for module in modules.iter() {
+        if let Some((name, line)) = module.imports.iter().find(|(name, _)| !exporters.contains_key(name.as_str())) {
+            let (module, name, line) = (module.name.clone(), name.clone(), *line);
+            return Err(match modules.iter().find(|other| other.state.symbols.get(&name).is_some()) {
+                Some(other) => LinkError::NotExported { module, name, line, defined_in: other.name.clone() },
+                None => LinkError::Unresolved { module, name, line },
+            });
+        }
+    }
+
+    // Placing every module gives the values of the exports, and then assembling each
+    // again from its base resolves its imports.
+    let assemble = |module: &ObjectModule, context: ModuleContext| {
+        let state = AssemblyState::with_module(module.state.source(), context);
+        match state.diagnostics.first() {
+            Some(error) => Err(LinkError::Assemble { module: module.name.clone(), error: error.clone() }),
+            None => Ok(state),
+        }
+    };
+    let mut bases = Vec::new();
+    let mut externals = BTreeMap::new();
+    let mut base = origin as i64;
+    for module in modules.iter() {
+        let state = assemble(module, ModuleContext { base, allow_imports: true, ..ModuleContext::default() })?;
+        for export in module.exports.iter() {
+            externals.insert(export.clone(), state.symbols.get(export).map_or(0, |symbol| symbol.value));
+        }
+        bases.push(base);
+        base = state.pass_one().end_location.max(base);
+    }
+    let mut linked = Vec::new();
+    for (module, base) in modules.iter().zip(bases) {
+        let state = assemble(module, ModuleContext { base, externals: externals.clone(), allow_imports: false })?;
+        linked.push(LinkedModule { name: module.name.clone(), base: base as usize, exports: module.exports.clone(), state });
+    }
+
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    let mut owners: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    let mut labels = BTreeMap::new();
+    let mut start = None;
+    for (k, module) in linked.iter().enumerate() {
+        let program = module.state.program.as_ref().expect("[Error link] A module assembled without problems has a program");
+        for statement in module.state.statements() {
+            let (address, line) = (statement.location as usize, statement.line + 1);
+            if let Some((first, first_line)) = owners.insert(address, (k, line)) {
+                return Err(LinkError::Overlap {
+                    address,
+                    first: (linked[first].name.clone(), first_line),
+                    second: (module.name.clone(), line),
+                });
+            }
+            builder.emit(statement.location, program.words[&address], line, statement.is_data)
+                .expect("[Error link] A word a module emitted is in memory");
+            if let Some((label, _)) = &statement.parsed.label {
+                labels.insert(address, module.qualified(label));
+            }
+        }
+        if start.is_none() && module.state.pass_one().end.is_some() {
+            start = Some(program.start);
+        }
+    }
+    builder.set_start(start.unwrap_or(origin));
+    let mut program = builder.finish().expect("[Error link] Modules placing no word at the same address don't overlap");
+    let source_map = Arc::make_mut(&mut program.source_map);
+    source_map.symbols = labels.iter().map(|(address, name)| (name.clone(), *address as i64)).collect();
+    source_map.labels = labels;
+    Ok(LinkedProgram { program, modules: linked })
+}
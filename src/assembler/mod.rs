@@ -0,0 +1,730 @@
+//! A MIXAL assembler. Lines are free-format: `LABEL OP OPERAND REMARKS`, with the fields
+//! separated by whitespace, no label when the line starts with whitespace, and `*` in the
+//! first column for a comment. Supported are instructions with an operand
+//! `ADDRESS,INDEX(FIELD)`, the `ORIG`, `EQU`, `CON`, and `END` pseudo-operations, each
+//! taking a W-value, and `ALF`. Expressions are numbers, symbols, and `*` combined by `+`,
+//! `-`, `*`, `/`, and `:` strictly from left to right.
+//!
+//! The address of an instruction may be a literal `=W-VALUE=`, for which the assembler
+//! makes a `CON` of the W-value after the last word before `END`, one for each literal in
+//! order of the source. A label `dH`, for a digit d, may be defined any number of times;
+//! `dB` in an operand refers to the last `dH` before its line and `dF` to the first one
+//! after it. The operand of `ALF` is five characters, as `ALF HELLO`; fewer are padded
+//! with blanks, and characters including blanks are written in double quotes, as
+//! `ALF "A B"`. The characters are read in `AssemblyOptions::charset`, or in the default
+//! `CharsetMapping`.
+//!
+//! A W-value `E1(F1),E2(F2),...` is the word made by starting from +0 and storing the value
+//! of each expression into its field in turn, as `STA` would, so that later parts win
+//! where fields overlap. A field left out is (0:5). Each value must fit in the bytes of
+//! its field, and a value stored into a field without the sign only gives its magnitude.
+//! Bytes hold `CONFIGURED_BYTE_SIZE` values unless `AssemblyOptions::byte_size` says
+//! otherwise, for a computer set to that byte size.
+//!
+//! Assembly takes two passes over the lines, each with one home in this module:
+//!
+//! - Lexing (`lexer::parse_line`) makes a `ParsedLine` of each line on its own, with its
+//!   operand parsed into expressions. It depends on nothing but the text of the line and
+//!   the charset of `ALF`, so incremental assembly, which keeps the charset, keeps the
+//!   parse of every line an edit doesn't touch.
+//! - The first pass (`AssemblyState::first_pass`) walks the parsed lines in order,
+//!   giving every label its value and every line that emits a word its location. It
+//!   makes a `PassOne`: the `Statement`s to emit, in order, and the line of `END`.
+//! - The second pass (`AssemblyState::second_pass`) evaluates the operand of each
+//!   statement, now that every label has its value, and emits the words.
+//!
+//! Since `EQU` and `ORIG` operands are evaluated in the first pass, they may only use
+//! symbols defined on earlier lines, as in MIXAL: a later one would need a third pass.
+//! Using one is the error `FutureReference`. Operands evaluated in the second pass,
+//! those of instructions, `CON`, and `END`, may use any symbol.
+//!
+//! Every diagnostic and symbol carries a span of byte offsets into the source, for
+//! editors to point at.
+//!
+//! `PUBL SYMBOL,SYMBOL,...` exports symbols from a module assembled on its own, as the
+//! `link` module describes. It emits nothing, and in a whole program it only checks that
+//! the symbols are defined.
+
+pub mod cache;
+pub mod lexer;
+pub mod link;
+pub mod symbols;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::word::Word;
+use crate::field::FieldSpec;
+use crate::computer::DEFAULT_MEMORY_SIZE;
+use crate::opcodes::{self, EncodeError};
+use crate::loader::{AssembledProgram, EmitError, OverlapPolicy, ProgramBuilder};
+use crate::layout::{MemoryLayout, Region};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::migration::BYTE_SIZES;
+use crate::instruction_functions::digits_to_value;
+use crate::charset::{CharsetError, CharsetMapping};
+use cache::AssemblyOptions;
+use lexer::{Atom, BinaryOp, Expr, Operand, ParsedLine};
+use symbols::SymbolTable;
+
+/// A range of byte offsets into the source, `start` included and `end` excluded.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    fn offset_by(self, offset: usize) -> Span {
+        Span::new(self.start + offset, self.end + offset)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AssembleErrorKind {
+    /// A label or symbol isn't one to ten capital letters and digits with at least one letter.
+    InvalidSymbol(String),
+    /// A label is given without an operation.
+    MissingOperation,
+    UnknownOperation(String),
+    /// The operand isn't well formed.
+    Syntax(String),
+    UndefinedSymbol(String),
+    /// An `EQU` or `ORIG` operand uses a symbol defined by the later label at `definition`.
+    FutureReference { name: String, definition: Span },
+    /// The symbol was already defined by the label at `first`.
+    DuplicateSymbol { name: String, first: Span },
+    /// A value doesn't fit in the part of the word it is meant for.
+    OutOfRange { part: &'static str, value: i64 },
+    /// Part `part` of a W-value, counting from 1, has a value too large for its field.
+    PartOutOfRange { part: usize, value: i64, field: FieldSpec },
+    DivisionByZero,
+    /// The words emitted after an `ORIG` into `region` run `overflow` words past its end.
+    RegionOverflow { region: String, overflow: usize },
+    Encode(EncodeError),
+    Emit(EmitError),
+    /// The operand of `ALF` isn't five characters or fewer of the MIX character code.
+    Charset(CharsetError),
+    /// The byte size of the options isn't one of `BYTE_SIZES`, reported against the first
+    /// line.
+    ByteSize(u16),
+}
+
+impl fmt::Display for AssembleErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleErrorKind::InvalidSymbol(text) => write!(f, "{} is not a valid symbol", text),
+            AssembleErrorKind::MissingOperation => write!(f, "the label has no operation"),
+            AssembleErrorKind::UnknownOperation(operation) => write!(f, "unknown operation {}", operation),
+            AssembleErrorKind::Syntax(message) => write!(f, "{}", message),
+            AssembleErrorKind::UndefinedSymbol(name) => write!(f, "{} is not defined", name),
+            AssembleErrorKind::FutureReference { name, definition } =>
+                write!(f, "{} is defined later, at offset {}, but EQU and ORIG may only use symbols defined before them", name, definition.start),
+            AssembleErrorKind::DuplicateSymbol { name, first } =>
+                write!(f, "{} was already defined at offset {}", name, first.start),
+            AssembleErrorKind::OutOfRange { part, value } => write!(f, "{} {} doesn't fit", part, value),
+            AssembleErrorKind::PartOutOfRange { part, value, field } =>
+                write!(f, "part {} of the W-value, {}, doesn't fit in ({})", part, value, field),
+            AssembleErrorKind::DivisionByZero => write!(f, "division by zero"),
+            AssembleErrorKind::RegionOverflow { region, overflow } =>
+                write!(f, "region {} overflows by {} words", region, overflow),
+            AssembleErrorKind::Encode(error) => write!(f, "{}", error),
+            AssembleErrorKind::Emit(error) => write!(f, "{}", error),
+            AssembleErrorKind::Charset(error) => write!(f, "{}", error),
+            AssembleErrorKind::ByteSize(byte_size) =>
+                write!(f, "{} is not a byte size from {} to {}", byte_size, BYTE_SIZES.start(), BYTE_SIZES.end()),
+        }
+    }
+}
+
+/// A problem with the source, on the line numbered `line` counting from 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub span: Span,
+    pub kind: AssembleErrorKind,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.kind)
+    }
+}
+
+/// Replaces the bytes `range` of a source with `text`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// A line the first pass found to emit a word.
+#[derive(Clone, Debug)]
+pub(crate) struct Statement {
+    /// The index of the line, counting from 0.
+    pub line: usize,
+    pub location: i64,
+    /// Whether the word is data, from `CON`, `ALF`, or a literal, rather than an instruction.
+    pub is_data: bool,
+    pub parsed: Rc<ParsedLine>,
+    /// The location of the `CON` made for the literal address of the operand, if any.
+    pub literal: Option<i64>,
+}
+
+/// What the first pass makes of a source, which is all the second pass needs besides the
+/// symbol table.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PassOne {
+    /// The lines emitting words, in order of the source.
+    pub statements: Vec<Statement>,
+    /// The index of the `END` line, if any.
+    pub end: Option<usize>,
+    /// The location counter after the last line and the literals.
+    pub end_location: i64,
+    /// The symbols `PUBL` exports, each with its span and the index of its line.
+    pub exports: Vec<(String, Span, usize)>,
+}
+
+/// How a module assembled on its own for linking differs from a whole program.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ModuleContext {
+    /// Where the location counter starts.
+    pub base: i64,
+    /// The values of the symbols other modules export.
+    pub externals: BTreeMap<String, i64>,
+    /// Whether a symbol the module neither defines nor finds among `externals` is an
+    /// import, valued 0 until linked, rather than an error.
+    pub allow_imports: bool,
+}
+
+/// Everything known about one assembly of a source, kept so that the next assembly after
+/// an edit can reuse the lines the edit didn't touch.
+#[derive(Clone, Debug)]
+pub struct AssemblyState {
+    source: String,
+    /// The byte offset at which each line starts.
+    line_starts: Vec<usize>,
+    lines: Vec<Rc<ParsedLine>>,
+    lines_parsed: usize,
+    layout: Option<MemoryLayout>,
+    module: Option<ModuleContext>,
+    /// The number of values a byte of the emitted words holds.
+    byte_size: u16,
+    /// The characters the operands of `ALF` are read in.
+    charset: CharsetMapping,
+    pass_one: PassOne,
+    /// Whether operands may use the symbols of other modules, which they only may in the
+    /// second pass.
+    resolving_externals: bool,
+    /// The symbols used but left to other modules, each with the index of the line first
+    /// using it, when imports are allowed.
+    imports: BTreeMap<String, usize>,
+    pub symbols: SymbolTable,
+    /// Every problem found, in order of the source.
+    pub diagnostics: Vec<AssembleError>,
+    /// The assembled program, when there were no problems.
+    pub program: Option<AssembledProgram>,
+}
+
+/// Assembles `source` from scratch.
+///
+/// ## Errors
+/// - The first problem found, in order of the source.
+pub fn assemble(source: &str) -> Result<AssembledProgram, AssembleError> {
+    let state = AssemblyState::new(source);
+    match state.diagnostics.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(state.program.unwrap_or_default()),
+    }
+}
+
+/// Assembles `source` from scratch, holding each `ORIG` to the region of `layout` its
+/// address falls in.
+///
+/// ## Errors
+/// - The first problem found, in order of the source, including `RegionOverflow` when the
+///   words after an `ORIG` run past the end of its region.
+pub fn assemble_with_layout(source: &str, layout: &MemoryLayout) -> Result<AssembledProgram, AssembleError> {
+    let state = AssemblyState::with_layout(source, layout.clone());
+    match state.diagnostics.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(state.program.unwrap_or_default()),
+    }
+}
+
+/// Assembles the source of `previous` with `edit` applied, parsing again only the lines
+/// the edit touches. The result is the same as assembling the edited source from scratch.
+///
+/// ## Panics
+/// - When the range of `edit` isn't within the source or doesn't fall on character
+///   boundaries.
+pub fn assemble_incremental(previous: &AssemblyState, edit: TextEdit) -> AssemblyState {
+    let mut source = previous.source.clone();
+    source.replace_range(edit.range.clone(), &edit.text);
+    let line_of = |offset: usize| previous.line_starts.partition_point(|start| *start <= offset) - 1;
+    let (first, last) = (line_of(edit.range.start), line_of(edit.range.end));
+    // Lines before the edit and lines after it are unchanged, whatever their new offsets.
+    let kept_after = previous.lines.len() - last - 1;
+    let line_count = source.split('\n').count();
+    AssemblyState::build(source, previous.layout.clone(), previous.module.clone(), previous.byte_size, previous.charset, |i| {
+        if i < first {
+            Some(previous.lines[i].clone())
+        } else if i >= line_count - kept_after {
+            Some(previous.lines[i + previous.lines.len() - line_count].clone())
+        } else {
+            None
+        }
+    })
+}
+
+impl AssemblyState {
+    pub fn new(source: &str) -> AssemblyState {
+        AssemblyState::build(source.to_string(), None, None, CONFIGURED_BYTE_SIZE as u16, CharsetMapping::default(), |_| None)
+    }
+
+    /// As `new`, holding the words after each `ORIG` to the region of `layout` holding the
+    /// `ORIG`'s address. Words emitted before any `ORIG` are held to the region holding 0,
+    /// and an `ORIG` outside every region isn't held to anything.
+    pub fn with_layout(source: &str, layout: MemoryLayout) -> AssemblyState {
+        AssemblyState::build(source.to_string(), Some(layout), None, CONFIGURED_BYTE_SIZE as u16, CharsetMapping::default(), |_| None)
+    }
+
+    /// As `new`, under `options`: held to their layout as by `with_layout`, emitting
+    /// words in their byte size, and reading `ALF` in their charset.
+    pub fn with_options(source: &str, options: &AssemblyOptions) -> AssemblyState {
+        AssemblyState::build(source.to_string(), options.layout.clone(), None, options.byte_size, options.charset, |_| None)
+    }
+
+    /// As `new`, for a module assembled on its own as `module` says.
+    pub(crate) fn with_module(source: &str, module: ModuleContext) -> AssemblyState {
+        AssemblyState::build(source.to_string(), None, Some(module), CONFIGURED_BYTE_SIZE as u16, CharsetMapping::default(), |_| None)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The characters the operands of `ALF` were read in.
+    pub fn charset(&self) -> CharsetMapping {
+        self.charset
+    }
+
+    /// Every line emitting a word, in order of the source.
+    pub(crate) fn statements(&self) -> &[Statement] {
+        &self.pass_one.statements
+    }
+
+    /// What the first pass found.
+    pub(crate) fn pass_one(&self) -> &PassOne {
+        &self.pass_one
+    }
+
+    /// The symbols left to other modules, each with the index of the line first using it.
+    pub(crate) fn imports(&self) -> &BTreeMap<String, usize> {
+        &self.imports
+    }
+
+    /// The parse of line `i`, counting from 0.
+    pub(crate) fn parsed_line(&self, i: usize) -> &ParsedLine {
+        &self.lines[i]
+    }
+
+    /// The number of lines parsed rather than reused to make this state.
+    pub fn lines_parsed(&self) -> usize {
+        self.lines_parsed
+    }
+
+    /// Builds the state of `source`, taking the parse of line `i` from `reuse(i)` when it
+    /// has one.
+    fn build<F: Fn(usize) -> Option<Rc<ParsedLine>>>(source: String, layout: Option<MemoryLayout>, module: Option<ModuleContext>, byte_size: u16, charset: CharsetMapping, reuse: F) -> AssemblyState {
+        let mut line_starts = Vec::new();
+        let mut lines = Vec::new();
+        let mut lines_parsed = 0;
+        let mut start = 0;
+        for (i, text) in source.split('\n').enumerate() {
+            line_starts.push(start);
+            lines.push(reuse(i).unwrap_or_else(|| {
+                lines_parsed += 1;
+                Rc::new(lexer::parse_line(text.strip_suffix('\r').unwrap_or(text), &charset))
+            }));
+            start += text.len() + 1;
+        }
+        let mut state = AssemblyState {
+            source,
+            line_starts,
+            lines,
+            lines_parsed,
+            layout,
+            module,
+            byte_size,
+            charset,
+            pass_one: PassOne::default(),
+            resolving_externals: false,
+            imports: BTreeMap::new(),
+            symbols: SymbolTable::default(),
+            diagnostics: Vec::new(),
+            program: None,
+        };
+        state.assemble();
+        state
+    }
+
+    fn error(&mut self, i: usize, span: Span, kind: AssembleErrorKind) {
+        self.diagnostics.push(AssembleError { line: i + 1, span: span.offset_by(self.line_starts[i]), kind });
+    }
+
+    fn assemble(&mut self) {
+        if !BYTE_SIZES.contains(&self.byte_size) {
+            self.error(0, Span::default(), AssembleErrorKind::ByteSize(self.byte_size));
+            return;
+        }
+        self.pass_one = self.first_pass();
+        self.second_pass();
+        self.diagnostics.sort_by_key(|error| error.span);
+    }
+
+    /// Gives every label its value, and finds the location of every line that emits a word.
+    fn first_pass(&mut self) -> PassOne {
+        let mut pass = PassOne::default();
+        let mut location: i64 = self.module.as_ref().map_or(0, |module| module.base);
+        // The region of the last ORIG, and the first line emitting past its end along with
+        // the number of words that do.
+        let mut section = self.region_at(location);
+        let mut overflow: Option<(usize, usize)> = None;
+        // The diagnostics from EQU and ORIG operands, which may turn out to be future
+        // references once every label has its value.
+        let mut early_diagnostics = Vec::new();
+        // The statements with a literal address, whose constants follow the last word.
+        let mut literals = Vec::new();
+        for i in 0..self.lines.len() {
+            let line = self.lines[i].clone();
+            let offset = self.line_starts[i];
+            for (name, span) in line.operand.symbols() {
+                self.symbols.record_use(name, span.offset_by(offset));
+            }
+            if let Some((span, kind)) = &line.error {
+                self.error(i, *span, kind.clone());
+                continue;
+            }
+            let (operation, operation_span) = match &line.operation {
+                Some((operation, span)) => (operation.as_str(), *span),
+                None => continue,
+            };
+            let mut value = location;
+            match operation {
+                "EQU" | "ORIG" => {
+                    let first_diagnostic = self.diagnostics.len();
+                    match self.w_value(i, location) {
+                        Some(operand) => {
+                            let operand = signed_value(&operand, self.byte_size);
+                            if operation == "EQU" {
+                                value = operand;
+                            } else {
+                                location = operand;
+                                self.report_overflow(&section, overflow.take());
+                                section = self.region_at(location);
+                            }
+                        },
+                        None => {
+                            early_diagnostics.extend(first_diagnostic..self.diagnostics.len());
+                            continue;
+                        },
+                    }
+                },
+                "PUBL" => {
+                    if line.operand.parts.is_empty() {
+                        self.error(i, operation_span, AssembleErrorKind::Syntax("expected an operand".to_string()));
+                    }
+                    for (expr, field) in line.operand.parts.iter() {
+                        match (&expr.first.0, expr.negated || !expr.rest.is_empty() || field.is_some()) {
+                            (Atom::Symbol(name), false) => pass.exports.push((name.clone(), expr.first.1.offset_by(offset), i)),
+                            _ => self.error(i, expr.span(), AssembleErrorKind::Syntax("PUBL takes symbols separated by commas".to_string())),
+                        }
+                    }
+                },
+                "CON" | "ALF" | "END" => {},
+                _ if opcodes::lookup(operation).is_none() => {
+                    self.error(i, operation_span, AssembleErrorKind::UnknownOperation(operation.to_string()));
+                    continue;
+                },
+                _ => {},
+            }
+            if let Some((label, span)) = &line.label {
+                let name = symbols::local_definition(label, i + 1).unwrap_or_else(|| label.clone());
+                if let Err(first) = self.symbols.define(&name, value, span.offset_by(offset), i + 1) {
+                    self.error(i, *span, AssembleErrorKind::DuplicateSymbol { name: label.clone(), first });
+                }
+            }
+            match operation {
+                "END" => {
+                    pass.end = Some(i);
+                    break;
+                },
+                "EQU" | "ORIG" | "PUBL" => {},
+                _ => {
+                    if section.as_ref().is_some_and(|region| location < 0 || location as usize >= region.range.end) {
+                        let (first, words) = overflow.unwrap_or((i, 0));
+                        overflow = Some((first, words + 1));
+                    }
+                    if line.operand.literal.is_some() {
+                        literals.push(pass.statements.len());
+                    }
+                    let is_data = operation == "CON" || operation == "ALF";
+                    pass.statements.push(Statement { line: i, location, is_data, parsed: line, literal: None });
+                    location += 1;
+                },
+            }
+        }
+        for k in literals {
+            let statement = &pass.statements[k];
+            let (i, (parts, span)) = (statement.line, statement.parsed.operand.literal.clone().unwrap_or_default());
+            if section.as_ref().is_some_and(|region| location < 0 || location as usize >= region.range.end) {
+                let (first, words) = overflow.unwrap_or((i, 0));
+                overflow = Some((first, words + 1));
+            }
+            let constant = ParsedLine {
+                operation: Some(("CON".to_string(), span)),
+                operand: Operand { parts, ..Operand::default() },
+                ..ParsedLine::default()
+            };
+            pass.statements[k].literal = Some(location);
+            pass.statements.push(Statement { line: i, location, is_data: true, parsed: Rc::new(constant), literal: None });
+            location += 1;
+        }
+        self.report_overflow(&section, overflow);
+        pass.end_location = location;
+        for (name, span, i) in pass.exports.iter() {
+            if self.symbols.get(name).is_none() {
+                self.diagnostics.push(AssembleError { line: i + 1, span: *span, kind: AssembleErrorKind::UndefinedSymbol(name.clone()) });
+            }
+        }
+        for j in early_diagnostics {
+            if let AssembleErrorKind::UndefinedSymbol(name) = &self.diagnostics[j].kind {
+                if let Some(symbol) = self.symbols.get(name) {
+                    self.diagnostics[j].kind = AssembleErrorKind::FutureReference { name: name.clone(), definition: symbol.definition };
+                }
+            }
+        }
+        pass
+    }
+
+    /// Evaluates the operand of every statement of the first pass and emits its word,
+    /// making the program when nothing went wrong in either pass.
+    fn second_pass(&mut self) {
+        self.resolving_externals = true;
+        let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+        for statement in self.pass_one.statements.clone() {
+            let i = statement.line;
+            if let Some(word) = self.emitted_word(&statement) {
+                if let Err(error) = builder.emit(statement.location, word, i + 1, statement.is_data) {
+                    let span = statement.parsed.operation.as_ref().map(|(_, span)| *span).unwrap_or_default();
+                    self.error(i, span, AssembleErrorKind::Emit(error));
+                }
+            }
+        }
+        if let Some(i) = self.pass_one.end {
+            if let Some(start) = self.w_value(i, 0) {
+                if let Some(start) = self.checked(i, "start", signed_value(&start, self.byte_size), 0..DEFAULT_MEMORY_SIZE as i64) {
+                    builder.set_start(start as usize);
+                }
+            }
+        }
+        match builder.finish() {
+            Ok(mut program) if self.diagnostics.is_empty() => {
+                Arc::make_mut(&mut program.source_map).symbols = self.symbols.iter()
+                    .map(|symbol| (symbol.name.clone(), symbol.value))
+                    .collect();
+                self.program = Some(program);
+            },
+            Ok(_) => {},
+            Err(error) => {
+                let line = match &error {
+                    EmitError::OutOfMemory { line, .. } => *line,
+                    EmitError::Overlap(overlap) => overlap.second_line,
+                };
+                let span = self.lines[line - 1].operation.as_ref().map(|(_, span)| *span).unwrap_or_default();
+                self.error(line - 1, span, AssembleErrorKind::Emit(error));
+            },
+        }
+    }
+
+    /// The region of the layout holding `location`, if there is a layout and such a region.
+    fn region_at(&self, location: i64) -> Option<Region> {
+        let layout = self.layout.as_ref()?;
+        if location < 0 {
+            return None;
+        }
+        layout.region_at(location as usize).cloned()
+    }
+
+    /// Records a `RegionOverflow` against the first line to emit past the end of `section`,
+    /// given `overflow` as that line and the number of words past the end.
+    fn report_overflow(&mut self, section: &Option<Region>, overflow: Option<(usize, usize)>) {
+        if let (Some(region), Some((i, words))) = (section, overflow) {
+            let span = self.lines[i].operation.as_ref().map(|(_, span)| *span).unwrap_or_default();
+            self.error(i, span, AssembleErrorKind::RegionOverflow { region: region.name.clone(), overflow: words });
+        }
+    }
+
+    /// The W-value operand of the pseudo-operation on line `i`, with `*` at `location`.
+    fn w_value(&mut self, i: usize, location: i64) -> Option<Word> {
+        let line = self.lines[i].clone();
+        self.w_value_of(i, &line, location)
+    }
+
+    /// The W-value operand of `line`, a pseudo-operation made from line `i`, with `*` at
+    /// `location`.
+    fn w_value_of(&mut self, i: usize, line: &ParsedLine, location: i64) -> Option<Word> {
+        if line.operand.parts.is_empty() {
+            let operation_span = line.operation.as_ref().map(|(_, span)| *span).unwrap_or_default();
+            self.error(i, operation_span, AssembleErrorKind::Syntax("expected an operand".to_string()));
+            return None;
+        }
+        let byte_size = self.byte_size as i64;
+        let mut word = Word::default();
+        for (j, (expr, field)) in line.operand.parts.iter().enumerate() {
+            let value = self.evaluate(i, expr, location)?;
+            let field = match field {
+                Some(field) => {
+                    let value = self.evaluate(i, field, location)?;
+                    match u8::try_from(value).ok().and_then(|byte| FieldSpec::from_byte(byte).ok()) {
+                        Some(field) => field,
+                        None => {
+                            self.error(i, field.span(), AssembleErrorKind::OutOfRange { part: "field", value });
+                            return None;
+                        },
+                    }
+                },
+                None => FieldSpec::WHOLE,
+            };
+            if value.abs() >= byte_size.pow(field.width() as u32) {
+                self.error(i, expr.span(), AssembleErrorKind::PartOutOfRange { part: j + 1, value, field });
+                return None;
+            }
+            let mut part = Word::new(value >= 0 && !(expr.negated && value == 0), [0; 5]);
+            for (k, byte) in part.bytes.iter_mut().enumerate() {
+                *byte = (value.abs() / byte_size.pow(4 - k as u32) % byte_size) as u8;
+            }
+            word.set_field(field, &part);
+        }
+        Some(word)
+    }
+
+    /// The word `statement` emits.
+    fn emitted_word(&mut self, statement: &Statement) -> Option<Word> {
+        let (i, location, line) = (statement.line, statement.location, statement.parsed.clone());
+        let (operation, operation_span) = line.operation.as_ref().map(|(operation, span)| (operation.as_str(), *span))?;
+        let byte_size = self.byte_size as i64;
+        if let Some((codes, _)) = line.operand.characters {
+            return Some(Word::new(true, codes));
+        }
+        if statement.is_data {
+            return self.w_value_of(i, &line, location);
+        }
+        let operand = &line.operand;
+        let mut part = |expr: &Option<Expr>, name: &'static str, range: Range<i64>| -> Option<Option<i64>> {
+            match expr {
+                Some(expr) => {
+                    let value = self.evaluate(i, expr, location)?;
+                    self.checked(i, name, value, range).map(Some)
+                },
+                None => Some(None),
+            }
+        };
+        let address = match statement.literal {
+            Some(literal) => literal,
+            None => part(&operand.address, "address", (1 - byte_size * byte_size)..(byte_size * byte_size))?.unwrap_or(0),
+        };
+        let index = part(&operand.index, "index", 0..byte_size)?.unwrap_or(0);
+        let field = part(&operand.field, "field", 0..byte_size)?;
+        match opcodes::encode_in_base(operation, address, index as u8, field.map(|field| field as u8), self.byte_size) {
+            Ok(mut word) => {
+                word.positive = address > 0 || (address == 0 && !operand.address.as_ref().is_some_and(|expr| expr.negated));
+                Some(word)
+            },
+            Err(error) => {
+                self.error(i, operation_span, AssembleErrorKind::Encode(error));
+                None
+            },
+        }
+    }
+
+    /// `value` when it is in `range`, recording an error against the operand of line `i`
+    /// otherwise.
+    fn checked(&mut self, i: usize, part: &'static str, value: i64, range: Range<i64>) -> Option<i64> {
+        if range.contains(&value) {
+            return Some(value);
+        }
+        let span = self.lines[i].operand.address.as_ref().map(|expr| expr.first.1)
+            .or_else(|| self.lines[i].operation.as_ref().map(|(_, span)| *span))
+            .unwrap_or_default();
+        self.error(i, span, AssembleErrorKind::OutOfRange { part, value });
+        None
+    }
+
+    /// Evaluates `expr` from line `i`, with `*` at `location`.
+    fn evaluate(&mut self, i: usize, expr: &Expr, location: i64) -> Option<i64> {
+        let mut atom_value = |atom: &Atom, span: Span| -> Option<i64> {
+            match atom {
+                Atom::Number(value) => Some(*value),
+                Atom::Here => Some(location),
+                Atom::Symbol(name) => {
+                    let symbol = match lexer::local_reference(name) {
+                        Some((digit, forward)) => self.symbols.resolve_local(digit, forward, i + 1),
+                        None => self.symbols.get(name),
+                    };
+                    if let Some(symbol) = symbol {
+                        return Some(symbol.value);
+                    }
+                    let module = self.module.as_ref().filter(|_| self.resolving_externals);
+                    if let Some(value) = module.and_then(|module| module.externals.get(name)) {
+                        return Some(*value);
+                    }
+                    if module.is_some_and(|module| module.allow_imports) {
+                        self.imports.entry(name.clone()).or_insert(i);
+                        return Some(0);
+                    }
+                    self.error(i, span, AssembleErrorKind::UndefinedSymbol(name.clone()));
+                    None
+                },
+            }
+        };
+        let mut value = atom_value(&expr.first.0, expr.first.1)?;
+        if expr.negated {
+            value = -value;
+        }
+        for (op, atom, span) in expr.rest.iter() {
+            let operand = atom_value(atom, *span)?;
+            value = match op {
+                BinaryOp::Add => value + operand,
+                BinaryOp::Sub => value - operand,
+                BinaryOp::Mul => value * operand,
+                BinaryOp::Div if operand == 0 => {
+                    self.error(i, *span, AssembleErrorKind::DivisionByZero);
+                    return None;
+                },
+                BinaryOp::Div => value / operand,
+                BinaryOp::Field => 8 * value + operand,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// The signed value of `word`, with bytes of `byte_size` values, which may be as large as a
+/// whole word.
+fn signed_value(word: &Word, byte_size: u16) -> i64 {
+    let magnitude = digits_to_value(&word.bytes, byte_size) as i64;
+    if word.positive { magnitude } else { -magnitude }
+}
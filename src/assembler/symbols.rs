@@ -0,0 +1,84 @@
+//! The symbols an assembly defines, and every place the source uses them. Each definition
+//! of a local symbol `dH` is a symbol of its own, named `dH@LINE` after its line.
+
+use std::collections::BTreeMap;
+use super::Span;
+
+/// A defined symbol.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub value: i64,
+    /// The label defining the symbol.
+    pub definition: Span,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SymbolTable {
+    symbols: BTreeMap<String, SymbolInfo>,
+    /// Every use of a symbol in an operand, in order of the source.
+    uses: Vec<(String, Span)>,
+}
+
+impl SymbolTable {
+    pub fn get(&self, name: &str) -> Option<&SymbolInfo> {
+        self.symbols.get(name)
+    }
+
+    /// Every defined symbol, in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = &SymbolInfo> {
+        self.symbols.values()
+    }
+
+    /// The symbol defined or used at the byte `offset` of the source, if it is defined.
+    pub fn lookup_at(&self, offset: usize) -> Option<&SymbolInfo> {
+        self.symbols.values().find(|symbol| symbol.definition.contains(offset))
+            .or_else(|| {
+                let (name, _) = self.uses.iter().find(|(_, span)| span.contains(offset))?;
+                self.symbols.get(name)
+            })
+    }
+
+    /// The spans of every use of `name` in an operand, in order of the source. The label
+    /// defining it isn't a use.
+    pub fn references(&self, name: &str) -> Vec<Span> {
+        self.uses.iter().filter(|(used, _)| used == name).map(|(_, span)| *span).collect()
+    }
+
+    /// Defines `name`.
+    ///
+    /// ## Errors
+    /// - The span of the earlier definition, when `name` is already defined.
+    pub(crate) fn define(&mut self, name: &str, value: i64, definition: Span, line: usize) -> Result<(), Span> {
+        if let Some(symbol) = self.symbols.get(name) {
+            return Err(symbol.definition);
+        }
+        self.symbols.insert(name.to_string(), SymbolInfo { name: name.to_string(), value, definition, line });
+        Ok(())
+    }
+
+    /// The definition of the local symbol `dH`, for `digit` d, that `dB` on `line` refers to,
+    /// or `dF` when `forward` is set.
+    pub(crate) fn resolve_local(&self, digit: u8, forward: bool, line: usize) -> Option<&SymbolInfo> {
+        let prefix = format!("{}H@", digit);
+        let definitions = self.symbols.range(prefix.clone()..).take_while(|(name, _)| name.starts_with(&prefix)).map(|(_, symbol)| symbol);
+        if forward {
+            definitions.filter(|symbol| symbol.line > line).min_by_key(|symbol| symbol.line)
+        } else {
+            definitions.filter(|symbol| symbol.line < line).max_by_key(|symbol| symbol.line)
+        }
+    }
+
+    pub(crate) fn record_use(&mut self, name: &str, span: Span) {
+        self.uses.push((name.to_string(), span));
+    }
+}
+
+/// The name defining `label` on `line` when it is a local symbol `dH`.
+pub(crate) fn local_definition(label: &str, line: usize) -> Option<String> {
+    match label.as_bytes() {
+        [digit, b'H'] if digit.is_ascii_digit() => Some(format!("{}@{}", label, line)),
+        _ => None,
+    }
+}
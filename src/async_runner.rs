@@ -0,0 +1,120 @@
+//! A cooperative driver for running a program inside an async host without blocking its
+//! executor. The computer itself stays synchronous: `AsyncRunner::run` executes it in
+//! slices of at most `steps_per_yield` instructions, awaits the host's yield hook between
+//! slices, and stops early once its `CancellationToken` is cancelled. The hook is any
+//! function returning a future, such as `tokio::task::yield_now`, so the crate needs no
+//! async runtime of its own.
+//!
+//! Slicing doesn't change what the program does: a run that isn't cancelled ends in the
+//! same state, at the same time, as `Computer::run` would.
+//!
+//! Unstable: the runner's shape waits on how hosts end up driving it, and may change in
+//! any release.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::computer::Computer;
+use crate::error::MixError;
+
+/// The number of instructions executed between yields unless the options say otherwise.
+pub const DEFAULT_STEPS_PER_YIELD: usize = 4096;
+
+/// A flag shared between a runner and whoever may want to stop it. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Asks every runner holding this token to stop before its next slice.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RunnerOptions<Y> {
+    /// The most instructions executed before yielding to the host.
+    pub steps_per_yield: usize,
+    /// Called between slices, with the runner awaiting the future it returns.
+    pub yield_now: Y,
+    pub cancellation: CancellationToken,
+}
+
+impl<Y> RunnerOptions<Y> {
+    /// Options yielding through `yield_now` every `DEFAULT_STEPS_PER_YIELD` instructions,
+    /// with a fresh cancellation token.
+    pub fn new(yield_now: Y) -> RunnerOptions<Y> {
+        RunnerOptions { steps_per_yield: DEFAULT_STEPS_PER_YIELD, yield_now, cancellation: CancellationToken::new() }
+    }
+}
+
+/// How an asynchronous run ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RunOutcome {
+    Halted,
+    /// The token was cancelled, leaving the computer where the last slice stopped.
+    Cancelled,
+    /// An error `Computer::run` would have returned.
+    Failed(MixError),
+}
+
+pub struct AsyncRunner<Y> {
+    /// The computer being run, which may be inspected between and after runs.
+    pub computer: Computer,
+    options: RunnerOptions<Y>,
+    steps: usize,
+}
+
+impl<Y, F> AsyncRunner<Y> where Y: Fn() -> F, F: Future<Output = ()> {
+    /// ## Panics
+    /// - When `options.steps_per_yield` is 0, since no slice would make progress.
+    pub fn new(computer: Computer, options: RunnerOptions<Y>) -> AsyncRunner<Y> {
+        assert!(options.steps_per_yield > 0, "[Error AsyncRunner::new] A slice must execute at least one instruction");
+        AsyncRunner { computer, options, steps: 0 }
+    }
+
+    /// The number of instructions executed by this runner so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Runs the computer until `HLT`, an error, or cancellation, yielding after each slice.
+    /// Each slice steps the computer as `Computer::run` does, so the errors are the same.
+    /// The token is checked before every slice, so cancellation takes effect within one
+    /// slice of being requested.
+    pub async fn run(&mut self) -> RunOutcome {
+        loop {
+            if self.options.cancellation.is_cancelled() {
+                return RunOutcome::Cancelled;
+            }
+            for _ in 0..self.options.steps_per_yield {
+                if self.computer.is_halted() {
+                    return RunOutcome::Halted;
+                }
+                if let Err(error) = self.computer.step() {
+                    return RunOutcome::Failed(error);
+                }
+                self.steps += 1;
+                if let Some(unit) = self.computer.blocked_on() {
+                    return RunOutcome::Failed(MixError::UnitBlocked { pc: self.computer.pc, unit });
+                }
+            }
+            if self.computer.is_halted() {
+                return RunOutcome::Halted;
+            }
+            (self.options.yield_now)().await;
+        }
+    }
+
+    pub fn into_computer(self) -> Computer {
+        self.computer
+    }
+}
@@ -0,0 +1,535 @@
+//! A guard on the throughput of the interpreter, so that a change made for correctness
+//! doesn't slow every run without anyone noticing. `mixal bench`, which `cargo bench` runs
+//! on the binary built with the bench profile, times `WORKLOADS`, each for a fixed number
+//! of steps on data drawn from a fixed seed, and reports the steps per second of each:
+//!
+//! - `mixal bench [--record] [--history PATH] [--save-baseline PATH]` runs the workloads.
+//!   `--record` adds the results to the history, `DEFAULT_HISTORY` unless `--history`
+//!   says otherwise, keyed by the commit checked out, and `--save-baseline` writes them
+//!   as the only entry of a history file, such as the checked-in `benches/baseline.json`.
+//! - `mixal bench compare BASE [NEW] [--history PATH] [--threshold PERCENT]` compares
+//!   two entries, each a commit, or the start of one, in the history, or a history file,
+//!   standing for its latest entry. Without `NEW` the workloads are run for it. It exits
+//!   with 0 when no workload is slower than `BASE` by more than the threshold,
+//!   `DEFAULT_THRESHOLD` unless given, `EXIT_REGRESSED` when one is, and
+//!   `EXIT_NOT_COMPARABLE` when the entries weren't measured alike.
+//!
+//! Everything the harness controls is pinned: the steps and seed of each workload, the
+//! warm-up runs before the samples, and, in `Cargo.toml`, the settings of the bench
+//! profile. What it can't control it records in the `Environment` of each entry, and two
+//! entries whose environments differ, or whose workloads were run differently, are not
+//! compared, since the difference would say more about the machines than the code.
+//! Each workload is reported by its fastest sample, which a busy machine disturbs least.
+
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::computer::Computer;
+use crate::determinism::Prng;
+use crate::opcodes;
+use crate::word::Word;
+
+/// The version of the history file written by this code.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Where `--record` keeps the history unless `--history` says otherwise. It is under
+/// `target`, as the numbers of one machine mean little on another.
+pub const DEFAULT_HISTORY: &str = "target/bench-history.json";
+
+/// The slowdown, in percent, a comparison allows unless `--threshold` says otherwise.
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+/// The exit code of a comparison finding a workload slower than the threshold allows.
+pub const EXIT_REGRESSED: i32 = 1;
+
+/// The exit code of a comparison between entries that weren't measured alike.
+pub const EXIT_NOT_COMPARABLE: i32 = 2;
+
+/// The steps each sample of a workload runs.
+pub const STEPS: u64 = 2_000_000;
+
+/// The untimed runs of a workload before its samples.
+pub const WARM_UP_RUNS: usize = 2;
+
+/// The timed runs of a workload.
+pub const SAMPLES: usize = 5;
+
+/// The seed of the data of the `seeded` workload.
+pub const SEED: u64 = 0x4d49_5821;
+
+/// A program timed by the harness, with what it needs in memory.
+pub struct Workload {
+    pub name: &'static str,
+    pub seed: u64,
+    /// Loads the program and its data into a reset computer.
+    pub setup: fn(&mut Computer, u64),
+}
+
+/// The workloads, in the order they are run: a loop of arithmetic on a register, a loop
+/// copying words and fields of words, and a loop over a table of random words taking a
+/// branch on the sign of each.
+pub const WORKLOADS: [Workload; 3] = [
+    Workload { name: "arithmetic", seed: 0, setup: arithmetic },
+    Workload { name: "memory", seed: 0, setup: memory },
+    Workload { name: "seeded", seed: SEED, setup: seeded },
+];
+
+/// `mnemonic` encoded, for programs known to be valid.
+fn encode(mnemonic: &str, address: i64, index: u8, field: Option<u8>) -> Word {
+    opcodes::encode(mnemonic, address, index, field).expect("[Error bench::encode] The workloads are valid")
+}
+
+fn arithmetic(computer: &mut Computer, _seed: u64) {
+    computer.load(0, &[
+        encode("ENT1", 1000, 0, None),
+        encode("ADD", 100, 0, None),                // 1
+        encode("SUB", 101, 0, None),
+        encode("INCA", 3, 0, None),
+        encode("CMPA", 100, 0, None),
+        encode("DEC1", 1, 0, None),
+        encode("J1P", 1, 0, None),
+        encode("HLT", 0, 0, None),
+    ]);
+    computer.write_mem(100, Word::from_value(500));
+    computer.write_mem(101, Word::from_value(2));
+}
+
+fn memory(computer: &mut Computer, _seed: u64) {
+    computer.load(0, &[
+        encode("ENT1", 900, 0, None),
+        encode("LDA", 1000, 1, None),               // 1
+        encode("STA", 2000, 1, None),
+        encode("LDX", 1000, 1, Some(13)),
+        encode("STX", 3000, 1, Some(45)),
+        encode("DEC1", 1, 0, None),
+        encode("J1P", 1, 0, None),
+        encode("HLT", 0, 0, None),
+    ]);
+}
+
+fn seeded(computer: &mut Computer, seed: u64) {
+    computer.load(0, &[
+        encode("ENT1", 999, 0, None),
+        encode("LDA", 1000, 1, None),               // 1
+        encode("JAN", 5, 0, None),
+        encode("ADD", 2000, 0, None),
+        encode("STA", 2000, 0, None),
+        encode("DEC1", 1, 0, None),                 // 5
+        encode("J1NN", 1, 0, None),
+        encode("HLT", 0, 0, None),
+    ]);
+    let mut prng = Prng::new(seed);
+    for address in 1000..2000 {
+        computer.write_mem(address, prng.word_in_base(64));
+    }
+}
+
+/// What of the machine running the harness may change its numbers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// The model of the processor, as the operating system names it, or `unknown`.
+    pub cpu: String,
+    pub os: String,
+    pub arch: String,
+    /// `release` when built without debug assertions, as the bench profile is, and
+    /// otherwise `debug`.
+    pub profile: String,
+}
+
+impl Environment {
+    /// The environment of this process.
+    pub fn current() -> Environment {
+        Environment {
+            cpu: cpu_model().unwrap_or_else(|| "unknown".to_string()),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        }
+    }
+}
+
+fn cpu_model() -> Option<String> {
+    if let Ok(info) = std::fs::read_to_string("/proc/cpuinfo") {
+        return info.lines()
+            .find(|line| line.starts_with("model name"))
+            .and_then(|line| line.split(':').nth(1))
+            .map(|model| model.trim().to_string());
+    }
+    let output = Command::new("sysctl").args(["-n", "machdep.cpu.brand_string"]).output().ok()?;
+    let model = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if output.status.success() && !model.is_empty() { Some(model) } else { None }
+}
+
+/// The timing of one workload.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkloadResult {
+    pub name: String,
+    pub seed: u64,
+    pub steps: u64,
+    pub warm_up_runs: usize,
+    pub samples: usize,
+    /// Of the fastest sample.
+    pub steps_per_second: f64,
+    pub median_steps_per_second: f64,
+}
+
+impl WorkloadResult {
+    /// Whether `other` was run with the same steps, seed, warm-up runs, and samples.
+    fn run_alike(&self, other: &WorkloadResult) -> bool {
+        (self.seed, self.steps, self.warm_up_runs, self.samples) == (other.seed, other.steps, other.warm_up_runs, other.samples)
+    }
+}
+
+/// Runs `workload` for `warm_up_runs` and then `samples` runs of `steps` steps each,
+/// starting it again whenever it halts.
+///
+/// ## Panics
+/// - When the workload fails, which it is written never to do.
+pub fn run_workload(workload: &Workload, steps: u64, warm_up_runs: usize, samples: usize) -> WorkloadResult {
+    let mut computer = Computer::default();
+    let mut rates = Vec::with_capacity(samples);
+    for run in 0..warm_up_runs + samples {
+        computer.reset();
+        (workload.setup)(&mut computer, workload.seed);
+        let start = Instant::now();
+        for _ in 0..steps {
+            if computer.is_halted() {
+                computer.reset();
+                (workload.setup)(&mut computer, workload.seed);
+            }
+            computer.step().unwrap_or_else(|error| panic!("[Error bench::run_workload] {} failed: {}", workload.name, error));
+        }
+        if run >= warm_up_runs {
+            rates.push(steps as f64 / start.elapsed().as_secs_f64());
+        }
+    }
+    rates.sort_by(|a, b| b.partial_cmp(a).expect("[Error bench::run_workload] A rate is a number"));
+    WorkloadResult {
+        name: workload.name.to_string(),
+        seed: workload.seed,
+        steps,
+        warm_up_runs,
+        samples,
+        steps_per_second: rates.first().copied().unwrap_or(0.0),
+        median_steps_per_second: rates.get(rates.len() / 2).copied().unwrap_or(0.0),
+    }
+}
+
+/// The results of every workload at one commit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchEntry {
+    /// The hash of the commit checked out, or `unknown` outside of a git checkout.
+    pub commit: String,
+    /// Whether the checkout had changes to tracked files.
+    pub dirty: bool,
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    pub environment: Environment,
+    pub results: Vec<WorkloadResult>,
+}
+
+impl BenchEntry {
+    /// Runs every workload as pinned, at the commit checked out.
+    pub fn measure() -> BenchEntry {
+        let (commit, dirty) = git_commit().unwrap_or_else(|| ("unknown".to_string(), false));
+        BenchEntry {
+            commit,
+            dirty,
+            recorded_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+            environment: Environment::current(),
+            results: WORKLOADS.iter().map(|workload| run_workload(workload, STEPS, WARM_UP_RUNS, SAMPLES)).collect(),
+        }
+    }
+
+    pub fn write_text(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(out, "{}{} on {} ({}, {}, {})", self.commit, if self.dirty { " (dirty)" } else { "" },
+            self.environment.cpu, self.environment.os, self.environment.arch, self.environment.profile)?;
+        for result in self.results.iter() {
+            writeln!(out, "  {:<12} {:>14.0} steps/s (median {:.0})", result.name, result.steps_per_second, result.median_steps_per_second)?;
+        }
+        Ok(())
+    }
+}
+
+/// The hash of the commit checked out, and whether tracked files have changed since.
+fn git_commit() -> Option<(String, bool)> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    let status = Command::new("git").args(["status", "--porcelain", "--untracked-files=no"]).output().ok()?;
+    Some((commit, !status.stdout.is_empty()))
+}
+
+/// Errors raised reading or finding entries of a history.
+#[derive(Debug)]
+pub enum BenchError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The history is of a later schema than this code reads.
+    UnknownVersion(u32),
+    /// No entry matches the commit or file given.
+    NoEntry(String),
+    /// More than one commit of the history starts with what was given.
+    Ambiguous(String),
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchError::Io(error) => write!(f, "{}", error),
+            BenchError::Json(error) => write!(f, "The history isn't valid: {}", error),
+            BenchError::UnknownVersion(version) => write!(f, "The history is of schema version {}, but only {} is known", version, SCHEMA_VERSION),
+            BenchError::NoEntry(key) => write!(f, "No entry of the history is for {}", key),
+            BenchError::Ambiguous(key) => write!(f, "More than one commit of the history starts with {}", key),
+        }
+    }
+}
+
+/// The entries recorded so far, oldest first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    pub schema_version: u32,
+    pub entries: Vec<BenchEntry>,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History { schema_version: SCHEMA_VERSION, entries: Vec::new() }
+    }
+}
+
+impl History {
+    /// ## Errors
+    /// - `Json` when `json` isn't a history, and `UnknownVersion` when it is of a later
+    ///   schema.
+    pub fn from_json(json: &str) -> Result<History, BenchError> {
+        let history: History = serde_json::from_str(json).map_err(BenchError::Json)?;
+        if history.schema_version > SCHEMA_VERSION {
+            return Err(BenchError::UnknownVersion(history.schema_version));
+        }
+        Ok(history)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("[Error to_json] History always serializes")
+    }
+
+    /// The history at `path`, or an empty one when there is no file there.
+    ///
+    /// ## Errors
+    /// As `from_json`, and `Io` when the file can't be read.
+    pub fn load(path: &Path) -> Result<History, BenchError> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => History::from_json(&json),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(History::default()),
+            Err(error) => Err(BenchError::Io(error)),
+        }
+    }
+
+    /// Writes the history to `path`, creating the directories leading to it.
+    pub fn save(&self, path: &Path) -> Result<(), BenchError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(BenchError::Io)?;
+        }
+        std::fs::write(path, self.to_json() + "\n").map_err(BenchError::Io)
+    }
+
+    /// Adds `entry`, replacing any entry of the same commit, which a run of a later
+    /// build of it supersedes.
+    pub fn record(&mut self, entry: BenchEntry) {
+        self.entries.retain(|recorded| recorded.commit != entry.commit);
+        self.entries.push(entry);
+    }
+
+    /// The entry of the commit starting with `prefix`.
+    ///
+    /// ## Errors
+    /// - `NoEntry` when no commit starts with it, and `Ambiguous` when several do.
+    pub fn find(&self, prefix: &str) -> Result<&BenchEntry, BenchError> {
+        let mut matches = self.entries.iter().filter(|entry| !prefix.is_empty() && entry.commit.starts_with(prefix));
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(BenchError::Ambiguous(prefix.to_string())),
+            (None, _) => Err(BenchError::NoEntry(prefix.to_string())),
+        }
+    }
+}
+
+/// The change in throughput of one workload between two entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delta {
+    pub name: String,
+    pub baseline: f64,
+    pub current: f64,
+    /// Positive when faster than the baseline.
+    pub percent: f64,
+}
+
+/// Two entries compared workload by workload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub baseline: String,
+    pub current: String,
+    pub threshold: f64,
+    /// Of the workloads both entries ran alike, in the order of the baseline.
+    pub deltas: Vec<Delta>,
+    /// Why the entries can't be compared, empty when they can.
+    pub mismatches: Vec<String>,
+}
+
+/// Compares `current` with `baseline`, allowing each workload to be slower by
+/// `threshold` percent.
+pub fn compare(baseline: &BenchEntry, current: &BenchEntry, threshold: f64) -> Comparison {
+    let mut mismatches = Vec::new();
+    let (before, after) = (&baseline.environment, &current.environment);
+    for (what, before, after) in [("CPU", &before.cpu, &after.cpu), ("OS", &before.os, &after.os),
+                                  ("architecture", &before.arch, &after.arch), ("profile", &before.profile, &after.profile)] {
+        if before != after {
+            mismatches.push(format!("The {} differs: {} against {}", what, before, after));
+        }
+    }
+    if before.cpu == "unknown" && after.cpu == "unknown" {
+        mismatches.push("The CPU of neither entry is known".to_string());
+    }
+
+    let mut deltas = Vec::new();
+    for result in baseline.results.iter() {
+        match current.results.iter().find(|other| other.name == result.name) {
+            None => mismatches.push(format!("{} wasn't run for {}", result.name, current.commit)),
+            Some(other) if !result.run_alike(other) => mismatches.push(format!("{} was run differently", result.name)),
+            Some(other) => deltas.push(Delta {
+                name: result.name.clone(),
+                baseline: result.steps_per_second,
+                current: other.steps_per_second,
+                percent: (other.steps_per_second - result.steps_per_second) / result.steps_per_second * 100.0,
+            }),
+        }
+    }
+    for other in current.results.iter().filter(|other| !baseline.results.iter().any(|result| result.name == other.name)) {
+        mismatches.push(format!("{} wasn't run for {}", other.name, baseline.commit));
+    }
+    Comparison { baseline: baseline.commit.clone(), current: current.commit.clone(), threshold, deltas, mismatches }
+}
+
+impl Comparison {
+    /// The workloads slower than the threshold allows.
+    pub fn regressions(&self) -> Vec<&Delta> {
+        self.deltas.iter().filter(|delta| delta.percent < -self.threshold).collect()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if !self.mismatches.is_empty() {
+            EXIT_NOT_COMPARABLE
+        } else if !self.regressions().is_empty() {
+            EXIT_REGRESSED
+        } else {
+            0
+        }
+    }
+
+    /// The deltas, or only why there are none to trust when the entries can't be compared.
+    pub fn write_text(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(out, "{} against {}", self.current, self.baseline)?;
+        if !self.mismatches.is_empty() {
+            writeln!(out, "Not comparable:")?;
+            for mismatch in self.mismatches.iter() {
+                writeln!(out, "  {}", mismatch)?;
+            }
+            return Ok(());
+        }
+        for delta in self.deltas.iter() {
+            let flag = if delta.percent < -self.threshold { "  REGRESSED" } else { "" };
+            writeln!(out, "  {:<12} {:>14.0} -> {:>14.0} steps/s {:>+7.2}%{}", delta.name, delta.baseline, delta.current, delta.percent, flag)?;
+        }
+        match self.regressions().len() {
+            0 => writeln!(out, "Within {}% of the baseline", self.threshold),
+            count => writeln!(out, "{} workload(s) slower than the baseline by more than {}%", count, self.threshold),
+        }
+    }
+}
+
+/// Runs `mixal bench` with `args`, the arguments after `bench`.
+///
+/// ## Returns
+/// - The exit code, 1 on a usage or file error.
+pub fn main(args: &[String]) -> i32 {
+    match run(args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("[Error bench] {}", message);
+            1
+        },
+    }
+}
+
+fn run(args: &[String]) -> Result<i32, String> {
+    let mut positional = Vec::new();
+    let mut history_path = DEFAULT_HISTORY.to_string();
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut record = false;
+    let mut baseline_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--record" => record = true,
+            "--history" => history_path = value()?,
+            "--save-baseline" => baseline_path = Some(value()?),
+            "--threshold" => threshold = value()?.parse().map_err(|_| "The threshold must be a number of percent".to_string())?,
+            option if option.starts_with("--") => return Err(format!("Unknown option {}", option)),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let history = History::load(Path::new(&history_path)).map_err(|error| error.to_string())?;
+
+    match positional.as_slice() {
+        [] => {
+            let entry = BenchEntry::measure();
+            print_text(|out| entry.write_text(out));
+            if let Some(path) = baseline_path {
+                History { schema_version: SCHEMA_VERSION, entries: vec![entry.clone()] }.save(Path::new(&path)).map_err(|error| error.to_string())?;
+            }
+            if record {
+                if entry.commit == "unknown" {
+                    return Err("Recording needs the commit, and `git rev-parse HEAD` failed".to_string());
+                }
+                let mut history = history;
+                history.record(entry);
+                history.save(Path::new(&history_path)).map_err(|error| error.to_string())?;
+            }
+            Ok(0)
+        },
+        ["compare", base, rest @ ..] if rest.len() <= 1 => {
+            let baseline = resolve(&history, base)?;
+            let current = match rest.first() {
+                Some(key) => resolve(&history, key)?,
+                None => BenchEntry::measure(),
+            };
+            let comparison = compare(&baseline, &current, threshold);
+            print_text(|out| comparison.write_text(out));
+            Ok(comparison.exit_code())
+        },
+        _ => Err("Usage: bench [--record] [--history PATH] [--save-baseline PATH] | bench compare BASE [NEW] [--history PATH] [--threshold PERCENT]".to_string()),
+    }
+}
+
+/// The latest entry of the history file at `key`, or else the entry of `history` whose
+/// commit starts with `key`.
+fn resolve(history: &History, key: &str) -> Result<BenchEntry, String> {
+    let path = Path::new(key);
+    if path.is_file() {
+        let file = History::load(path).map_err(|error| format!("{}: {}", key, error))?;
+        return file.entries.last().cloned().ok_or_else(|| BenchError::NoEntry(key.to_string()).to_string());
+    }
+    history.find(key).cloned().map_err(|error| error.to_string())
+}
+
+fn print_text(write: impl FnOnce(&mut String) -> fmt::Result) {
+    let mut text = String::new();
+    write(&mut text).expect("[Error bench] Writing to a string doesn't fail");
+    print!("{}", text);
+}
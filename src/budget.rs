@@ -0,0 +1,118 @@
+//! Limits on what a computer's instrumentation keeps, so that a run of hours with every
+//! kind of recording on holds as much memory at its end as it did once its lists filled.
+//! Each structure that grows with the length of a run, rather than the size of its
+//! program, has a maximum in `InstrumentationBudget`:
+//!
+//! - The warning lists of the computer, `portability_warnings`, `undefined_warnings`,
+//!   `code_write_warnings`, and `unattached_warnings`, each hold at most `max_warnings`.
+//! - `uninitialized_reads` holds at most `max_uninitialized_reads`.
+//! - A `Trace` holds at most `max_trace_records`, counting steps, transfers, and stubbed
+//!   calls together.
+//! - An `AccessHeatmap` holds at most `max_heatmap_cells`. Cells it holds go on counting,
+//!   but accesses to a cell it doesn't hold yet are dropped.
+//!
+//! Each saturates: once full it keeps what it has, the oldest entries, and drops the rest,
+//! marking itself truncated so that whatever reports it knows the data is partial. The
+//! computer's lists are marked in `Computer::truncated`, a trace in `Trace::truncated`,
+//! a heatmap by `AccessHeatmap::is_truncated`, and a `RunReport` made of any of them in
+//! its `truncated`. The rest is bounded already: a `CheckpointRing` by its ring size, the
+//! standard output units by their depth, and a mirror by its window.
+//!
+//! The budget is the computer's `budget`, kept on reset, which `CheckpointRing::run`
+//! sets from its `RunOptions`. `soak_instrumentation_stays_bounded` runs random programs
+//! for minutes with everything on and checks that memory stops growing, and
+//! `check_budget`, an invariant of every computer, that no list outgrows its maximum.
+
+use std::ops::Range;
+use crate::computer::Computer;
+use crate::invariants::InvariantViolation;
+
+/// The most entries each warning list holds unless the budget says otherwise.
+pub const DEFAULT_MAX_WARNINGS: usize = 10_000;
+
+/// The most uninitialized reads recorded unless the budget says otherwise.
+pub const DEFAULT_MAX_UNINITIALIZED_READS: usize = 10_000;
+
+/// The most records a trace holds unless the budget says otherwise.
+pub const DEFAULT_MAX_TRACE_RECORDS: usize = 1_000_000;
+
+/// The most cells a heatmap holds unless the budget says otherwise.
+pub const DEFAULT_MAX_HEATMAP_CELLS: usize = 100_000;
+
+/// The most each accumulating structure of a computer's instrumentation holds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InstrumentationBudget {
+    /// For each warning list of the computer.
+    pub max_warnings: usize,
+    pub max_uninitialized_reads: usize,
+    pub max_trace_records: usize,
+    pub max_heatmap_cells: usize,
+}
+
+impl Default for InstrumentationBudget {
+    fn default() -> InstrumentationBudget {
+        InstrumentationBudget {
+            max_warnings: DEFAULT_MAX_WARNINGS,
+            max_uninitialized_reads: DEFAULT_MAX_UNINITIALIZED_READS,
+            max_trace_records: DEFAULT_MAX_TRACE_RECORDS,
+            max_heatmap_cells: DEFAULT_MAX_HEATMAP_CELLS,
+        }
+    }
+}
+
+impl InstrumentationBudget {
+    /// No limits, for a run known to be short.
+    pub fn unlimited() -> InstrumentationBudget {
+        InstrumentationBudget {
+            max_warnings: usize::MAX,
+            max_uninitialized_reads: usize::MAX,
+            max_trace_records: usize::MAX,
+            max_heatmap_cells: usize::MAX,
+        }
+    }
+}
+
+/// Which of the computer's lists filled and dropped entries since it was created or reset.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Truncation {
+    pub portability_warnings: bool,
+    pub undefined_warnings: bool,
+    pub code_write_warnings: bool,
+    pub unattached_warnings: bool,
+    pub uninitialized_reads: bool,
+}
+
+impl Truncation {
+    /// Whether any list dropped entries.
+    pub fn any(&self) -> bool {
+        self.portability_warnings || self.undefined_warnings || self.code_write_warnings
+            || self.unattached_warnings || self.uninitialized_reads
+    }
+}
+
+/// Checks that no list of `computer`, nor its trace or heatmap, holds more than the budget
+/// allows, for `Computer::check_invariants`.
+pub(crate) fn check_budget(computer: &Computer, _written: Range<usize>) -> Result<(), InvariantViolation> {
+    let budget = computer.budget;
+    let lists = [
+        ("portability_warnings", computer.portability_warnings.len(), budget.max_warnings),
+        ("undefined_warnings", computer.undefined_warnings.len(), budget.max_warnings),
+        ("code_write_warnings", computer.code_write_warnings.len(), budget.max_warnings),
+        ("unattached_warnings", computer.unattached_warnings.len(), budget.max_warnings),
+        ("uninitialized_reads", computer.uninitialized_reads.len(), budget.max_uninitialized_reads),
+        ("trace", computer.trace.as_ref().map_or(0, |trace| trace.records.len()), budget.max_trace_records),
+    ];
+    match lists.iter().find(|(_, length, max)| length > max) {
+        Some(&(list, length, max)) => Err(InvariantViolation::OverBudget { list, length, max }),
+        None => Ok(()),
+    }
+}
+
+/// Adds `entry` to `list` unless it holds `max` already, in which case `truncated` is set.
+pub(crate) fn record<T>(list: &mut Vec<T>, entry: T, max: usize, truncated: &mut bool) {
+    if list.len() < max {
+        list.push(entry);
+    } else {
+        *truncated = true;
+    }
+}
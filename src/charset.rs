@@ -0,0 +1,132 @@
+//! The MIX character code of TAOCP section 1.3.1, and its mapping to host characters.
+//!
+//! Codes 0 to 55 are characters; three of them, Δ (10), Σ (20), and Π (21), have no
+//! ASCII equivalent, and tools disagree on what stands in for them. A `CharsetMapping`
+//! chooses the host character for each of those three, and everything that turns bytes
+//! into text or text into bytes goes through one. The bytes themselves never depend on
+//! the mapping.
+
+use std::fmt;
+
+/// The number of characters in the MIX character code.
+pub const CHARACTER_CODES: usize = 56;
+
+/// The code of the digit 0, which the digits 1 to 9 follow.
+pub const DIGIT_ZERO: u8 = 30;
+
+/// The code of Δ.
+pub const DELTA: u8 = 10;
+
+/// The code of Σ.
+pub const SIGMA: u8 = 20;
+
+/// The code of Π.
+pub const PI: u8 = 21;
+
+/// The host character of every code, with `None` for the codes a mapping chooses.
+const CANONICAL: [Option<char>; CHARACTER_CODES] = [
+    Some(' '), Some('A'), Some('B'), Some('C'), Some('D'), Some('E'), Some('F'), Some('G'),
+    Some('H'), Some('I'), None, Some('J'), Some('K'), Some('L'), Some('M'), Some('N'),
+    Some('O'), Some('P'), Some('Q'), Some('R'), None, None, Some('S'), Some('T'),
+    Some('U'), Some('V'), Some('W'), Some('X'), Some('Y'), Some('Z'), Some('0'), Some('1'),
+    Some('2'), Some('3'), Some('4'), Some('5'), Some('6'), Some('7'), Some('8'), Some('9'),
+    Some('.'), Some(','), Some('('), Some(')'), Some('+'), Some('-'), Some('*'), Some('/'),
+    Some('='), Some('$'), Some('<'), Some('>'), Some('@'), Some(';'), Some(':'), Some('\''),
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CharsetError {
+    /// The byte at `position` isn't a character code at all.
+    InvalidCode { code: u8, position: usize },
+    /// The mapping has no host character for the code at `position`.
+    Unrepresentable { code: u8, position: usize },
+    /// The host character at `position` has no code under the mapping.
+    UnknownCharacter { character: char, position: usize },
+    /// Text of this many characters was given for a single word, which holds five.
+    TooLong(usize),
+}
+
+impl fmt::Display for CharsetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CharsetError::InvalidCode { code, position } =>
+                write!(f, "{} at position {} is not a character code", code, position),
+            CharsetError::Unrepresentable { code, position } =>
+                write!(f, "Character code {} at position {} has no host character", code, position),
+            CharsetError::UnknownCharacter { character, position } =>
+                write!(f, "{:?} at position {} has no character code", character, position),
+            CharsetError::TooLong(length) => write!(f, "{} characters don't fit in a word", length),
+        }
+    }
+}
+
+/// The host characters standing in for Δ, Σ, and Π. `None` leaves the code without a host
+/// character, so that rendering it is an error.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CharsetMapping {
+    pub delta: Option<char>,
+    pub sigma: Option<char>,
+    pub pi: Option<char>,
+}
+
+impl Default for CharsetMapping {
+    fn default() -> Self {
+        CharsetMapping::unicode()
+    }
+}
+
+impl CharsetMapping {
+    /// Δ, Σ, and Π as `{`, `}`, and `#`.
+    pub fn mdk() -> Self {
+        CharsetMapping { delta: Some('{'), sigma: Some('}'), pi: Some('#') }
+    }
+
+    /// Δ, Σ, and Π as themselves.
+    pub fn unicode() -> Self {
+        CharsetMapping { delta: Some('Δ'), sigma: Some('Σ'), pi: Some('Π') }
+    }
+
+    /// ASCII only, so that Δ, Σ, and Π can't be rendered or read.
+    pub fn strict_ascii() -> Self {
+        CharsetMapping { delta: None, sigma: None, pi: None }
+    }
+
+    /// The host character of `code`, if it has one.
+    pub fn char_for(&self, code: u8) -> Option<char> {
+        match code {
+            DELTA => self.delta,
+            SIGMA => self.sigma,
+            PI => self.pi,
+            _ => CANONICAL.get(code as usize).copied().flatten(),
+        }
+    }
+
+    /// The code of the host character `character`, if it has one.
+    pub fn code_for(&self, character: char) -> Option<u8> {
+        (0..CHARACTER_CODES as u8).find(|code| self.char_for(*code) == Some(character))
+    }
+
+    /// Renders `codes` as host text.
+    ///
+    /// ## Errors
+    /// - `InvalidCode` for a byte above 55.
+    /// - `Unrepresentable` for a code the mapping leaves without a host character.
+    pub fn decode(&self, codes: &[u8]) -> Result<String, CharsetError> {
+        codes.iter().enumerate().map(|(position, code)| {
+            if *code as usize >= CHARACTER_CODES {
+                return Err(CharsetError::InvalidCode { code: *code, position });
+            }
+            self.char_for(*code).ok_or(CharsetError::Unrepresentable { code: *code, position })
+        }).collect()
+    }
+
+    /// Reads host text as character codes.
+    ///
+    /// ## Errors
+    /// - `UnknownCharacter` for a character with no code under the mapping.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, CharsetError> {
+        text.chars().enumerate().map(|(position, character)| {
+            self.code_for(character).ok_or(CharsetError::UnknownCharacter { character, position })
+        }).collect()
+    }
+}
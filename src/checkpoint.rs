@@ -0,0 +1,290 @@
+//! Checkpoints taken every so many cycles of a long run, so that a run which faults near
+//! its end can be rolled back to shortly before the fault and run forward again, rather
+//! than from the start.
+//!
+//! A `CheckpointRing` keeps the newest `checkpoint_ring_size` checkpoints. The oldest is
+//! held in full, as a `MachineImage`, and each later one as the words of memory it
+//! changed along with the registers and flags, so that a ring costs two images, the
+//! oldest and the newest, and the words the program wrote between checkpoints. When the
+//! ring is full, the oldest checkpoint is folded into the one after it.
+//!
+//! A checkpoint holds what an image does, along with the time, so a program rolled back
+//! and run again does exactly what it did the first time unless it depends on state an
+//! image leaves out: the transfers in flight and the peripherals. `verify_replay` checks
+//! that it does, comparing the state it reaches again with `Computer::state_hash`, and
+//! with `Computer::state_eq` when the hashes agree.
+
+use std::collections::VecDeque;
+use std::fmt;
+use crate::budget::InstrumentationBudget;
+use crate::computer::{ComparisonFlag, Computer};
+use crate::error::MixError;
+use crate::image::{ImageError, MachineImage};
+use crate::word::Word;
+
+/// The checkpoints a ring keeps unless the options say otherwise.
+pub const DEFAULT_RING_SIZE: usize = 8;
+
+/// How a run takes checkpoints, and what its instrumentation may keep.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RunOptions {
+    /// Take a checkpoint after the first instruction ending at or past each multiple of
+    /// this many cycles, or never when 0.
+    pub checkpoint_every_cycles: u64,
+    /// The most checkpoints kept, the newest ones.
+    pub checkpoint_ring_size: usize,
+    /// Given to the computer by `CheckpointRing::run`.
+    pub budget: InstrumentationBudget,
+}
+
+impl Default for RunOptions {
+    /// No checkpoints, and the default budget.
+    fn default() -> RunOptions {
+        RunOptions { checkpoint_every_cycles: 0, checkpoint_ring_size: DEFAULT_RING_SIZE, budget: InstrumentationBudget::default() }
+    }
+}
+
+/// When a checkpoint was taken.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CheckpointTime {
+    pub instruction_time: u64,
+    pub stolen_time: u64,
+}
+
+impl CheckpointTime {
+    /// The time of the computer then, as `Computer::time` gives it.
+    pub fn time(&self) -> u64 {
+        self.instruction_time + self.stolen_time
+    }
+}
+
+/// What changed between a checkpoint and the one before it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Delta {
+    pc: usize,
+    overflow: bool,
+    comparison: ComparisonFlag,
+    halted: bool,
+    registers: [Word; 9],
+    /// The words of memory changed, with their new values, in order of address.
+    memory: Vec<(usize, Word)>,
+}
+
+impl Delta {
+    fn apply_to(&self, image: &mut MachineImage) {
+        image.pc = self.pc;
+        image.overflow = self.overflow;
+        image.comparison = self.comparison;
+        image.halted = self.halted;
+        image.registers = self.registers;
+        for (address, word) in self.memory.iter() {
+            image.memory[*address] = *word;
+        }
+    }
+}
+
+/// Errors raised rolling back to a checkpoint.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RollbackError {
+    /// There is no checkpoint `back` from the newest, as the ring holds `held`.
+    NoCheckpoint { back: usize, held: usize },
+    Image(ImageError),
+}
+
+impl fmt::Display for RollbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RollbackError::NoCheckpoint { back, held } =>
+                write!(f, "There is no checkpoint {} back, as there are {}", back, held),
+            RollbackError::Image(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Why a run from a checkpoint didn't do again what it did.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReplayError {
+    Rollback(RollbackError),
+    /// The run from the checkpoint faulted, at `time`.
+    Failed { time: u64, error: MixError },
+    /// The run from the checkpoint reached `time` in a different state.
+    Diverged { time: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Rollback(error) => write!(f, "{}", error),
+            ReplayError::Failed { time, error } => write!(f, "The replay failed at time {}: {}", time, error),
+            ReplayError::Diverged { time } => write!(f, "The replay reached time {} in a different state", time),
+        }
+    }
+}
+
+/// The newest checkpoints of a run, oldest first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CheckpointRing {
+    options: RunOptions,
+    /// The oldest checkpoint, in full.
+    oldest: Option<(CheckpointTime, MachineImage)>,
+    /// The checkpoints after the oldest, each as its changes from the one before.
+    later: VecDeque<(CheckpointTime, Delta)>,
+    /// The newest checkpoint in full, to find what changed since it.
+    newest: Option<MachineImage>,
+    /// The time from which the next checkpoint is taken.
+    next_at: u64,
+}
+
+impl CheckpointRing {
+    /// An empty ring, taking checkpoints as `options` say. The first is taken once the
+    /// computer's time reaches `checkpoint_every_cycles`.
+    ///
+    /// ## Panics
+    /// - When checkpoints are taken but `checkpoint_ring_size` is 0.
+    pub fn new(options: RunOptions) -> CheckpointRing {
+        assert!(options.checkpoint_every_cycles == 0 || options.checkpoint_ring_size > 0,
+                "[Error CheckpointRing::new] A ring must hold at least one checkpoint");
+        CheckpointRing { options, next_at: options.checkpoint_every_cycles, ..CheckpointRing::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.oldest.iter().count() + self.later.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.oldest.is_none()
+    }
+
+    /// The times of the checkpoints, oldest first.
+    pub fn times(&self) -> impl Iterator<Item = CheckpointTime> + '_ {
+        self.oldest.iter().map(|(time, _)| *time).chain(self.later.iter().map(|(time, _)| *time))
+    }
+
+    /// Runs `computer` until `HLT` as `Computer::run` does, taking checkpoints on the way,
+    /// under the budget of the options. With checkpoints off, this is `Computer::run`
+    /// under the budget.
+    ///
+    /// ## Errors
+    /// - The errors of `Computer::run`, with the checkpoints taken up to the fault kept.
+    pub fn run(&mut self, computer: &mut Computer) -> Result<(), MixError> {
+        computer.budget = self.options.budget;
+        if self.options.checkpoint_every_cycles == 0 {
+            return computer.run();
+        }
+        while !computer.is_halted() {
+            computer.step()?;
+            self.after_step(computer);
+            if let Some(unit) = computer.blocked_on() {
+                return Err(MixError::UnitBlocked { pc: computer.pc, unit });
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes a checkpoint of `computer` if its time has reached the next one. Called
+    /// after each step by whatever is stepping the computer.
+    pub fn after_step(&mut self, computer: &Computer) {
+        if self.options.checkpoint_every_cycles == 0 || computer.time() < self.next_at {
+            return;
+        }
+        let every = self.options.checkpoint_every_cycles;
+        self.next_at = (computer.time() / every + 1) * every;
+        self.push(computer);
+    }
+
+    fn push(&mut self, computer: &Computer) {
+        let time = CheckpointTime { instruction_time: computer.instruction_time(), stolen_time: computer.stolen_time() };
+        let newest = match self.newest.as_mut() {
+            Some(newest) => newest,
+            None => {
+                let image = MachineImage::of(computer);
+                self.oldest = Some((time, image.clone()));
+                self.newest = Some(image);
+                return;
+            },
+        };
+        let memory = newest.memory.iter_mut().zip(computer.memory.iter()).enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(address, (before, after))| {
+                *before = *after;
+                (address, *after)
+            })
+            .collect();
+        let delta = Delta {
+            pc: computer.pc,
+            overflow: computer.overflow_flag,
+            comparison: computer.comparison_flag,
+            halted: computer.is_halted(),
+            registers: [computer.ra, computer.rx, computer.ri1, computer.ri2, computer.ri3,
+                        computer.ri4, computer.ri5, computer.ri6, computer.rj],
+            memory,
+        };
+        delta.apply_to(newest);
+        self.later.push_back((time, delta));
+        if self.len() > self.options.checkpoint_ring_size {
+            let (time, delta) = self.later.pop_front().expect("[Error push] A full ring has a checkpoint after its oldest");
+            let (oldest_time, oldest) = self.oldest.as_mut().expect("[Error push] A full ring has an oldest checkpoint");
+            delta.apply_to(oldest);
+            *oldest_time = time;
+        }
+        debug_assert_eq!(self.image(1).as_ref(), Some(&MachineImage::of(computer)),
+                         "[Error push] The checkpoint doesn't rebuild the state it was taken of");
+    }
+
+    /// The checkpoint `back` from the newest, 1 being the newest, in full.
+    pub fn image(&self, back: usize) -> Option<MachineImage> {
+        let (_, oldest) = self.oldest.as_ref()?;
+        let later = self.later.len().checked_sub(back.checked_sub(1)?)?;
+        let mut image = oldest.clone();
+        for (_, delta) in self.later.iter().take(later) {
+            delta.apply_to(&mut image);
+        }
+        Some(image)
+    }
+
+    /// Checks that `computer`, rolled back to the checkpoint `back` from the newest and run
+    /// again to its time, is in the state it is in now. Neither `computer` nor the ring is
+    /// changed, but the replay shares its peripherals, so it should be made only of a run
+    /// whose peripherals are deterministic and whose transfers are done.
+    ///
+    /// ## Errors
+    /// - `Rollback` for any error of `rollback`.
+    /// - `Failed` when the replay faults or blocks on a unit.
+    /// - `Diverged` when the replay reaches the time, or halts before it, in another state.
+    pub fn verify_replay(&self, back: usize, computer: &Computer) -> Result<(), ReplayError> {
+        let mut replay = computer.clone();
+        self.clone().rollback(back, &mut replay).map_err(ReplayError::Rollback)?;
+        while replay.time() < computer.time() && !replay.is_halted() {
+            let pc = replay.pc;
+            replay.step().map_err(|error| ReplayError::Failed { time: replay.time(), error })?;
+            if let Some(unit) = replay.blocked_on() {
+                return Err(ReplayError::Failed { time: replay.time(), error: MixError::UnitBlocked { pc, unit } });
+            }
+        }
+        if replay.time() != computer.time() || replay.state_hash() != computer.state_hash() || !replay.state_eq(computer) {
+            return Err(ReplayError::Diverged { time: replay.time() });
+        }
+        Ok(())
+    }
+
+    /// Gives `computer` the state and time of the checkpoint `back` from the newest, 1
+    /// being the newest, and forgets the checkpoints after it, so that running on takes
+    /// them again.
+    ///
+    /// ## Errors
+    /// - `NoCheckpoint` when the ring holds fewer than `back` checkpoints, or `back` is 0.
+    /// - `Image` when the computer's memory isn't the size of the checkpoint's.
+    ///
+    /// In either case the computer and the ring are left alone.
+    pub fn rollback(&mut self, back: usize, computer: &mut Computer) -> Result<CheckpointTime, RollbackError> {
+        let held = self.len();
+        let image = self.image(back).ok_or(RollbackError::NoCheckpoint { back, held })?;
+        image.restore_into(computer).map_err(RollbackError::Image)?;
+        self.later.truncate(held - back);
+        let time = self.times().last().expect("[Error rollback] The ring holds the checkpoint rolled back to");
+        computer.set_times(time.instruction_time, time.stolen_time);
+        self.newest = Some(image);
+        self.next_at = (time.time() / self.options.checkpoint_every_cycles + 1) * self.options.checkpoint_every_cycles;
+        Ok(time)
+    }
+}
@@ -0,0 +1,238 @@
+//! The subcommands of the binary. Each is there when the features it needs are, and
+//! otherwise its arguments fall through to the usage `main` prints:
+//!
+//! - `--tui PROGRAM`, with `tui`: steps through the raw deck `PROGRAM` in the terminal.
+//! - `script SCRIPT`, with `tui`, `assembler`, and `peripherals`: runs a monitor script.
+//! - `map SOURCE`, with `analysis`: writes the program map of the MIXAL `SOURCE`.
+//! - `isa-docs [--json]`: writes the instruction reference, as JSON with `json`.
+//! - `public-api [SRC]`: writes the listing of the public items of the sources (see `api`).
+//! - `embed SOURCE MODULE [CRATE]`, with `assembler`: writes Rust source embedding the
+//!   MIXAL `SOURCE` as the module `MODULE` (see `codegen`).
+//! - `bench ...`, with `json`: times the interpreter and compares the timings (see `bench`).
+//! - `migrate-image ...`: rewrites an image for another byte size (see `migration`).
+//! - `run SOURCE [--json]`, with `assembler`, `peripherals`, and `debugger`: assembles and
+//!   runs the MIXAL `SOURCE` as `run_report::run_source` does, writing what it printed,
+//!   or with `--json` and `json` its `RunReport`, or the `FaultReport` of its failing.
+//! - `asm SOURCE [--json]`, with `assembler` and `debugger`: assembles the MIXAL `SOURCE`,
+//!   writing it as a raw deck, or with `--json` and `json` the `FaultReport` of the
+//!   assembly, whose `errors` are empty when it assembled.
+//!
+//! `run` and `asm` write only the program's output or the report to stdout, and their
+//! warnings and errors to stderr, exiting with 1 when the source doesn't assemble or the
+//! run faults.
+
+/// Runs the subcommand `args` name, the first argument being the binary.
+///
+/// ## Returns
+/// - The exit code of the subcommand, or `None` when `args` names none.
+pub fn run(args: &[String]) -> Option<i32> {
+    match (args.get(1).map(String::as_str), args.len()) {
+        #[cfg(feature = "tui")]
+        (Some("--tui"), 3) => Some(tui(&args[2])),
+        #[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+        (Some("script"), 3) => Some(crate::script::run_file(std::path::Path::new(&args[2]), &mut std::io::stdout(), &mut std::io::stderr())),
+        #[cfg(feature = "analysis")]
+        (Some("map"), 3) => Some(map(&args[2])),
+        (Some("isa-docs"), _) => Some(isa_docs(args.get(2).map(String::as_str))),
+        (Some("public-api"), _) => Some(crate::api::main(&args[2..])),
+        #[cfg(feature = "assembler")]
+        (Some("embed"), _) => Some(crate::codegen::main(&args[2..])),
+        #[cfg(feature = "json")]
+        (Some("bench"), _) => Some(crate::bench::main(&args[2..])),
+        (Some("migrate-image"), _) => Some(crate::migration::main(&args[2..])),
+        #[cfg(all(feature = "assembler", feature = "peripherals", feature = "debugger"))]
+        (Some("run"), 3 | 4) => Some(output(args.get(3)).map_or(1, |output| run_program(&args[2], output, &mut std::io::stdout(), &mut std::io::stderr()))),
+        #[cfg(all(feature = "assembler", feature = "debugger"))]
+        (Some("asm"), 3 | 4) => Some(output(args.get(3)).map_or(1, |output| assemble_program(&args[2], output, &mut std::io::stdout(), &mut std::io::stderr()))),
+        _ => None,
+    }
+}
+
+/// The usage of the binary named `binary`, one line for each subcommand built in.
+pub fn usage(binary: &str) -> String {
+    let mut lines = vec![format!("Usage: {} SUBCOMMAND ...", binary)];
+    #[cfg(feature = "tui")]
+    lines.push("  --tui PROGRAM".to_string());
+    #[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+    lines.push("  script SCRIPT".to_string());
+    #[cfg(feature = "analysis")]
+    lines.push("  map SOURCE".to_string());
+    lines.push("  isa-docs [--json]".to_string());
+    lines.push("  public-api [SRC]".to_string());
+    #[cfg(feature = "assembler")]
+    lines.push("  embed SOURCE MODULE [CRATE]".to_string());
+    #[cfg(feature = "json")]
+    lines.push("  bench ...".to_string());
+    lines.push("  migrate-image ...".to_string());
+    #[cfg(all(feature = "assembler", feature = "peripherals", feature = "debugger"))]
+    lines.push("  run SOURCE [--json]".to_string());
+    #[cfg(all(feature = "assembler", feature = "debugger"))]
+    lines.push("  asm SOURCE [--json]".to_string());
+    lines.join("\n")
+}
+
+/// What `run` and `asm` write to stdout.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Output {
+    /// What the program printed, or the program assembled.
+    Text,
+    /// The report, as JSON.
+    #[cfg(feature = "json")]
+    Json,
+}
+
+/// The output `option`, the argument after the source, asks for.
+///
+/// ## Returns
+/// - `None`, having said so on stderr, when the option isn't known.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn output(option: Option<&String>) -> Option<Output> {
+    match option.map(String::as_str) {
+        None => Some(Output::Text),
+        #[cfg(feature = "json")]
+        Some("--json") => Some(Output::Json),
+        Some(option) => {
+            eprintln!("[Error main] Unknown option {}", option);
+            None
+        },
+    }
+}
+
+/// Writes each of `diagnostics` to `err` as a line, after `path` and the line of the
+/// source it concerns, if any.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn write_diagnostics(path: &str, severity: &str, diagnostics: &[crate::run_report::Diagnostic], err: &mut dyn std::io::Write) {
+    for diagnostic in diagnostics {
+        let _ = match diagnostic.line {
+            Some(line) => writeln!(err, "{}:{}: {} [{}]: {}", path, line, severity, diagnostic.code, diagnostic.message),
+            None => writeln!(err, "{}: {} [{}]: {}", path, severity, diagnostic.code, diagnostic.message),
+        };
+    }
+}
+
+/// Writes the `FaultReport` of a source at `path` that failed to `out` as `output` asks,
+/// and its errors to `err`.
+///
+/// ## Returns
+/// - The exit code, 1.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[cfg_attr(not(feature = "json"), allow(unused_variables))]
+fn fail(path: &str, report: &crate::run_report::FaultReport, output: Output, out: &mut dyn std::io::Write, err: &mut dyn std::io::Write) -> i32 {
+    write_diagnostics(path, "error", &report.errors, err);
+    match output {
+        Output::Text => {},
+        #[cfg(feature = "json")]
+        Output::Json => if writeln!(out, "{}", report.to_json()).is_err() {
+            let _ = writeln!(err, "[Error main] Couldn't write the report");
+        },
+    }
+    1
+}
+
+/// The `run` subcommand, for the MIXAL source at `path`, writing to `out` and `err`.
+///
+/// ## Returns
+/// - The exit code: 0 when the program ran to `HLT`, or 1.
+#[cfg(all(feature = "assembler", feature = "peripherals", feature = "debugger"))]
+pub(crate) fn run_program(path: &str, output: Output, out: &mut dyn std::io::Write, err: &mut dyn std::io::Write) -> i32 {
+    use crate::run_report;
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            let _ = writeln!(err, "{}: {}", path, error);
+            return 1;
+        },
+    };
+    let report = match run_report::run_source(&source, crate::computer::UnattachedPolicy::Fault) {
+        Ok(report) => report,
+        Err(report) => return fail(path, &report, output, out, err),
+    };
+    write_diagnostics(path, "warning", &report.warnings, err);
+    let written = match output {
+        Output::Text => report.printer.iter().flatten().try_for_each(|line| writeln!(out, "{}", line)),
+        #[cfg(feature = "json")]
+        Output::Json => writeln!(out, "{}", report.to_json()),
+    };
+    if written.is_err() {
+        let _ = writeln!(err, "[Error main] Couldn't write the output");
+        return 1;
+    }
+    0
+}
+
+/// The `asm` subcommand, for the MIXAL source at `path`, writing to `out` and `err`.
+///
+/// ## Returns
+/// - The exit code: 0 when the source assembled, or 1.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+pub(crate) fn assemble_program(path: &str, output: Output, out: &mut dyn std::io::Write, err: &mut dyn std::io::Write) -> i32 {
+    use crate::run_report::FaultReport;
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            let _ = writeln!(err, "{}: {}", path, error);
+            return 1;
+        },
+    };
+    let program = match crate::assembler::assemble(&source) {
+        Ok(program) => program,
+        Err(error) => return fail(path, &FaultReport::from_assemble_error(&error), output, out, err),
+    };
+    let written = match output {
+        Output::Text => write!(out, "{}", crate::loader::render_raw(&program)),
+        #[cfg(feature = "json")]
+        Output::Json => {
+            let report = FaultReport { schema_version: crate::run_report::SCHEMA_VERSION, stage: "assemble".to_string(), pc: None, region: None, errors: Vec::new(), checkpoints: None };
+            writeln!(out, "{}", report.to_json())
+        },
+    };
+    if written.is_err() {
+        let _ = writeln!(err, "[Error main] Couldn't write the program");
+        return 1;
+    }
+    0
+}
+
+#[cfg(feature = "tui")]
+fn tui(path: &str) -> i32 {
+    let text = std::fs::read_to_string(path).expect("[Error main] Couldn't read the program");
+    let program = crate::loader::parse_raw(&text).unwrap_or_else(|error| panic!("[Error main] {}", error));
+    let mut computer = crate::computer::Computer::default();
+    program.load_into(&mut computer);
+    crate::tui::run(&mut computer).expect("[Error main] The terminal failed");
+    0
+}
+
+#[cfg(feature = "analysis")]
+fn map(path: &str) -> i32 {
+    let source = std::fs::read_to_string(path).expect("[Error main] Couldn't read the source");
+    match crate::analysis::program_map(&crate::assembler::AssemblyState::new(&source)) {
+        Ok(map) => {
+            let mut out = crate::report::IoWriter::new(std::io::BufWriter::new(std::io::stdout().lock()));
+            if map.write_text(&mut out).is_err() || std::io::Write::flush(&mut out.inner).is_err() {
+                eprintln!("[Error main] Couldn't write the map");
+                return 1;
+            }
+            0
+        },
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        },
+    }
+}
+
+fn isa_docs(option: Option<&str>) -> i32 {
+    let reference = crate::docgen::Reference::generate();
+    match option {
+        #[cfg(feature = "json")]
+        Some("--json") => println!("{}", reference.to_json()),
+        None => print!("{}", reference.to_markdown()),
+        Some(option) => {
+            eprintln!("[Error main] Unknown option {}", option);
+            return 1;
+        },
+    }
+    0
+}
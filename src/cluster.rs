@@ -0,0 +1,197 @@
+//! Orchestration of several machines connected by channels, where the blocks one machine
+//! writes to a unit are read by another machine from one of its units.
+//!
+//! Unstable: orchestration covers only machines in one thread, stepped in turn, and may
+//! change in any release as it grows.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::peripherals::{Channel, ChannelEnd};
+
+/// The number of blocks a channel can hold before its producer has to wait.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ChannelDepth(pub usize);
+
+/// Bounds on a round-robin run.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RoundRobinLimits {
+    /// The most instructions a machine executes before the next machine takes its turn.
+    /// A machine's turn also ends as soon as it waits on a channel.
+    pub instructions_per_turn: usize,
+    /// The most rounds, each giving every running machine one turn, before giving up.
+    pub max_rounds: usize,
+}
+
+/// A machine left waiting on a channel when a cluster deadlocks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockedMachine {
+    pub machine: String,
+    pub unit: u8,
+    /// The channel the machine is waiting on, written `producer:unit -> consumer:unit,`
+    /// followed by whether the channel is full or empty.
+    pub channel: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClusterError {
+    /// A machine with this name was already added.
+    DuplicateMachine(String),
+    /// No machine with this name was added.
+    UnknownMachine(String),
+    /// The unit of the machine is already connected to a channel.
+    UnitInUse { machine: String, unit: u8 },
+    /// A machine failed with `error`.
+    Fault { machine: String, error: MixError },
+    /// Every running machine is waiting on a channel that no other machine will ever act on.
+    Deadlock(Vec<BlockedMachine>),
+    /// The machines were still running after the given number of rounds.
+    RoundLimit { rounds: usize },
+}
+
+impl fmt::Display for ClusterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClusterError::DuplicateMachine(name) => write!(f, "Machine {} was already added", name),
+            ClusterError::UnknownMachine(name) => write!(f, "No machine named {}", name),
+            ClusterError::UnitInUse { machine, unit } =>
+                write!(f, "Unit {} of machine {} is already connected", unit, machine),
+            ClusterError::Fault { machine, error } => write!(f, "Machine {} failed: {}", machine, error),
+            ClusterError::Deadlock(blocked) => {
+                write!(f, "Deadlock:")?;
+                for machine in blocked {
+                    write!(f, " {} waits on unit {} ({});", machine.machine, machine.unit, machine.channel)?;
+                }
+                Ok(())
+            },
+            ClusterError::RoundLimit { rounds } => write!(f, "Still running after {} rounds", rounds),
+        }
+    }
+}
+
+/// A channel between the unit of one machine and the unit of another, by machine index.
+struct Link {
+    producer: (usize, u8),
+    consumer: (usize, u8),
+    channel: Rc<RefCell<Channel>>,
+}
+
+/// A group of named machines run together on a deterministic round-robin schedule.
+pub struct Cluster {
+    machines: Vec<(String, Computer)>,
+    links: Vec<Link>,
+}
+
+impl Default for Cluster {
+    fn default() -> Cluster {
+        Cluster::new()
+    }
+}
+
+impl Cluster {
+    pub fn new() -> Cluster {
+        Cluster { machines: Vec::new(), links: Vec::new() }
+    }
+
+    /// Adds `computer` under `name`. Machines take their turns in the order they were added.
+    pub fn add(&mut self, name: &str, computer: Computer) -> Result<(), ClusterError> {
+        if self.index_of(name).is_ok() {
+            return Err(ClusterError::DuplicateMachine(name.to_string()));
+        }
+        self.machines.push((name.to_string(), computer));
+        Ok(())
+    }
+
+    /// The machine added under `name`.
+    pub fn machine(&self, name: &str) -> Option<&Computer> {
+        self.machines.iter().find(|(n, _)| n == name).map(|(_, computer)| computer)
+    }
+
+    /// Connects `producer`, a machine name and unit, to `consumer` with a channel holding
+    /// up to `depth` blocks. Blocks written by the producer's `OUT` are read in order by
+    /// the consumer's `IN`.
+    ///
+    /// ## Errors
+    /// - `UnknownMachine` when either machine hasn't been added.
+    /// - `UnitInUse` when either unit is already connected to a channel.
+    pub fn connect(&mut self, producer: (&str, u8), consumer: (&str, u8), depth: ChannelDepth) -> Result<(), ClusterError> {
+        let producer = (self.index_of(producer.0)?, producer.1);
+        let consumer = (self.index_of(consumer.0)?, consumer.1);
+        for end in [producer, consumer].iter() {
+            if self.links.iter().any(|link| link.producer == *end || link.consumer == *end) {
+                return Err(ClusterError::UnitInUse { machine: self.machines[end.0].0.clone(), unit: end.1 });
+            }
+        }
+        let channel = Channel::new(depth.0);
+        self.machines[producer.0].1.attach(producer.1, ChannelEnd::producer(&channel));
+        self.machines[consumer.0].1.attach(consumer.1, ChannelEnd::consumer(&channel));
+        self.links.push(Link { producer, consumer, channel });
+        Ok(())
+    }
+
+    /// Runs every machine until it halts, giving each running machine a turn of up to
+    /// `instructions_per_turn` instructions in the order they were added.
+    ///
+    /// ## Returns
+    /// - The number of rounds taken.
+    ///
+    /// ## Errors
+    /// - `Fault` when any machine fails.
+    /// - `Deadlock` when a whole round passes in which every running machine only waited
+    ///   on channels, naming each machine and the channel it waits on.
+    /// - `RoundLimit` when machines are still running after `max_rounds` rounds.
+    pub fn run_round_robin(&mut self, limits: RoundRobinLimits) -> Result<usize, ClusterError> {
+        for round in 0..limits.max_rounds {
+            if self.machines.iter().all(|(_, computer)| computer.is_halted()) {
+                return Ok(round);
+            }
+            let mut progressed = false;
+            for (name, computer) in self.machines.iter_mut() {
+                for _ in 0..limits.instructions_per_turn {
+                    if computer.is_halted() {
+                        break;
+                    }
+                    computer.step().map_err(|error| ClusterError::Fault { machine: name.clone(), error })?;
+                    if computer.blocked_on().is_some() {
+                        break;
+                    }
+                    progressed = true;
+                }
+            }
+            if !progressed {
+                return Err(ClusterError::Deadlock(self.blocked_machines()));
+            }
+        }
+        if self.machines.iter().all(|(_, computer)| computer.is_halted()) {
+            return Ok(limits.max_rounds);
+        }
+        Err(ClusterError::RoundLimit { rounds: limits.max_rounds })
+    }
+
+    fn index_of(&self, name: &str) -> Result<usize, ClusterError> {
+        self.machines.iter().position(|(n, _)| n == name)
+            .ok_or_else(|| ClusterError::UnknownMachine(name.to_string()))
+    }
+
+    fn blocked_machines(&self) -> Vec<BlockedMachine> {
+        let mut blocked = Vec::new();
+        for (i, (name, computer)) in self.machines.iter().enumerate() {
+            let unit = match computer.blocked_on() {
+                Some(unit) => unit,
+                None => continue,
+            };
+            let channel = self.links.iter()
+                .find(|link| link.producer == (i, unit) || link.consumer == (i, unit))
+                .map(|link| {
+                    let state = if link.channel.borrow().is_empty() { "empty" } else { "full" };
+                    format!("{}:{} -> {}:{}, {}", self.machines[link.producer.0].0, link.producer.1,
+                            self.machines[link.consumer.0].0, link.consumer.1, state)
+                })
+                .unwrap_or_default();
+            blocked.push(BlockedMachine { machine: name.clone(), unit, channel });
+        }
+        blocked
+    }
+}
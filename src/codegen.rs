@@ -0,0 +1,201 @@
+//! Rust source embedding an assembled program, so that an application can carry a fixed
+//! MIX routine and call it by name. `embed` writes a module holding the words of the
+//! program, its entry point and symbols as constants, a `load_into` that places the words
+//! and returns a `Handle` of the symbols, and an `is_stale` that compares a source with
+//! the hash of the one embedded, for a build to notice a `.mixal` file edited since.
+//!
+//! The output is meant to be written by a build script, or by `mixal embed`, and checked
+//! in or included with `include!`. It is laid out as rustfmt lays it out, so that it
+//! passes `cargo fmt --check` as written.
+//!
+//! A symbol naming a word the program emits is an `Address`; any other, such as one
+//! defined by `EQU`, is an `i64`. Symbols that aren't Rust identifiers, such as the local
+//! `2H`, are left out, and the items `embed` adds have underscores in their names, which
+//! MIXAL symbols can't, so the two never collide. The assembler has no literal pool, so
+//! there are no literals to embed.
+
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::hash::Hasher;
+use crate::fingerprint::Fnv1a;
+use crate::loader::AssembledProgram;
+
+/// The widths rustfmt lays out code in by default: the longest line, the longest array
+/// of several items kept on one line, and the longest fields of a struct literal.
+const MAX_WIDTH: usize = 100;
+const ARRAY_WIDTH: usize = 60;
+const STRUCT_LITERAL_WIDTH: usize = 18;
+
+/// The address of a word of an embedded program.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Address(pub usize);
+
+/// The hash of `source` an embedded program records, FNV-1a of its bytes.
+pub fn source_hash(source: &str) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(source.as_bytes());
+    hasher.finish()
+}
+
+/// A constant of the embedded program: its name and its type and value, as written.
+struct Constant {
+    name: String,
+    ty: &'static str,
+    value: String,
+}
+
+/// The Rust source of a module `module_name` embedding `program`, assembled from
+/// `source`.
+///
+/// ## Arguments
+/// - `crate_path`: The path the generated code names this crate by, as `mixal` from
+///   another crate or `crate` from within it.
+pub fn embed(program: &AssembledProgram, source: &str, module_name: &str, crate_path: &str) -> String {
+    let constants: Vec<Constant> = program.source_map.symbols.iter()
+        .filter(|(name, _)| is_identifier(name))
+        .map(|(name, value)| {
+            let emitted = usize::try_from(*value).ok().filter(|address| program.words.contains_key(address));
+            match emitted {
+                Some(address) => Constant { name: name.clone(), ty: "Address", value: format!("Address({})", address) },
+                None => Constant { name: name.clone(), ty: "i64", value: value.to_string() },
+            }
+        })
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by `mixal embed` from a MIXAL program. Don't edit it; embed the program again.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub mod {} {{", module_name).unwrap();
+    writeln!(out, "    use {}::codegen::{{self, Address}};", crate_path).unwrap();
+    writeln!(out, "    use {}::computer::Computer;", crate_path).unwrap();
+    writeln!(out, "    use {}::word::Word;", crate_path).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The hash of the source the program was assembled from.").unwrap();
+    writeln!(out, "    pub const SOURCE_HASH: u64 = {:#018x};", source_hash(source)).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The address the program starts at.").unwrap();
+    writeln!(out, "    pub const ENTRY_POINT: Address = Address({});", program.start).unwrap();
+    if !constants.is_empty() {
+        writeln!(out).unwrap();
+    }
+    for constant in constants.iter() {
+        writeln!(out, "    pub const {}: {} = {};", constant.name, constant.ty, constant.value).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The runs of consecutive words the program emits, each as its first address and").unwrap();
+    writeln!(out, "    /// the address after its last.").unwrap();
+    let regions: Vec<String> = program.regions.iter()
+        .map(|region| format!("(Address({}), Address({}))", region.range.start, region.range.end))
+        .collect();
+    write_array(&mut out, &format!("    pub static PROGRAM_REGIONS: [(Address, Address); {}] =", regions.len()), &regions);
+    writeln!(out).unwrap();
+    writeln!(out, "    /// Every word of the program, as its address, sign, and bytes.").unwrap();
+    let words: Vec<String> = program.words.iter().map(|(address, word)| {
+        let bytes = word.bytes();
+        format!("(Address({}), {}, [{}, {}, {}, {}, {}])", address, word.is_positive(), bytes[0], bytes[1], bytes[2], bytes[3], bytes[4])
+    }).collect();
+    write_array(&mut out, &format!("    pub static PROGRAM_WORDS: [(Address, bool, [u8; 5]); {}] =", words.len()), &words);
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The symbols of the program, as `load_into` hands them back.").unwrap();
+    writeln!(out, "    #[allow(non_snake_case)]").unwrap();
+    writeln!(out, "    #[derive(Copy, Clone, Debug, Eq, PartialEq)]").unwrap();
+    if constants.is_empty() {
+        writeln!(out, "    pub struct Handle {{}}").unwrap();
+    } else {
+        writeln!(out, "    pub struct Handle {{").unwrap();
+        for constant in constants.iter() {
+            writeln!(out, "        pub {}: {},", constant.name, constant.ty).unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "    /// Writes the words of the program into the memory of `computer` and points its `pc`").unwrap();
+    writeln!(out, "    /// at the entry point.").unwrap();
+    writeln!(out, "    pub fn load_into(computer: &mut Computer) -> Handle {{").unwrap();
+    writeln!(out, "        for (address, positive, bytes) in PROGRAM_WORDS.iter() {{").unwrap();
+    writeln!(out, "            computer.write_mem(address.0, Word::new(*positive, *bytes));").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        computer.pc = ENTRY_POINT.0;").unwrap();
+    let fields = constants.iter().map(|constant| constant.name.as_str()).collect::<Vec<&str>>().join(", ");
+    if constants.is_empty() {
+        writeln!(out, "        Handle {{}}").unwrap();
+    } else if fields.len() <= STRUCT_LITERAL_WIDTH {
+        writeln!(out, "        Handle {{ {} }}", fields).unwrap();
+    } else {
+        writeln!(out, "        Handle {{").unwrap();
+        for constant in constants.iter() {
+            writeln!(out, "            {},", constant.name).unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// Whether `source` isn't the source the program was embedded from.").unwrap();
+    writeln!(out, "    pub fn is_stale(source: &str) -> bool {{").unwrap();
+    writeln!(out, "        codegen::source_hash(source) != SOURCE_HASH").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Writes `declaration`, ending in `=`, followed by the array of `items`, laid out as
+/// rustfmt lays it out: on one line when its items fit in `ARRAY_WIDTH`, or there is only
+/// one, after the declaration or else on the next line, and otherwise an item to a line.
+fn write_array(out: &mut String, declaration: &str, items: &[String]) {
+    let inline = items.join(", ");
+    if items.len() < 2 || inline.len() <= ARRAY_WIDTH {
+        let array = format!("[{}];", inline);
+        if declaration.len() + 1 + array.len() <= MAX_WIDTH {
+            writeln!(out, "{} {}", declaration, array).unwrap();
+            return;
+        }
+        if 8 + array.len() <= MAX_WIDTH {
+            writeln!(out, "{}\n        {}", declaration, array).unwrap();
+            return;
+        }
+    }
+    writeln!(out, "{} [", declaration).unwrap();
+    for item in items {
+        writeln!(out, "        {},", item).unwrap();
+    }
+    writeln!(out, "    ];").unwrap();
+}
+
+/// Whether `name` can name a Rust constant: it doesn't start with a digit.
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Writes the module embedding the MIXAL source at `args[0]` as `args[1]`, naming this
+/// crate `args[2]`, or `mixal` when it isn't given.
+///
+/// ## Returns
+/// - The exit code: 0 once written, and 1 when the arguments are wrong or the source
+///   can't be read or assembled.
+pub fn main(args: &[String]) -> i32 {
+    let (path, module_name) = match args {
+        [path, module_name] | [path, module_name, _] => (path, module_name),
+        _ => {
+            eprintln!("[Error main] Usage: mixal embed SOURCE MODULE [CRATE]");
+            return 1;
+        },
+    };
+    let crate_path = args.get(2).map_or("mixal", String::as_str);
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("[Error main] Couldn't read {}: {}", path, error);
+            return 1;
+        },
+    };
+    match crate::assembler::assemble(&source) {
+        Ok(program) => {
+            print!("{}", embed(&program, &source, module_name, crate_path));
+            0
+        },
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            1
+        },
+    }
+}
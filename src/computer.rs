@@ -1,190 +1,1880 @@
-use std::fmt;
-use crate::word::{Word};
-use crate::instruction::*;
-use crate::instruction_functions::register_for_index;
-
-macro_rules! boxed {
-    ($name:ident) => {
-        Box::new($name::new())
-    };
-    ($name:ident, $($item:ident),*) => {
-        Box::new($name::new($($item),*))
-    };
-    ($name:ident, $expr:expr, $($item:ident),*) => {
-        Box::new($name::new($expr, $($item),*))
-    };
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum ComparisonFlag {
-    less,
-    equal,
-    greater
-}
-
-impl fmt::Display for ComparisonFlag {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let st = match self {
-            ComparisonFlag::less => "Less",
-            ComparisonFlag::equal => "Equal",
-            ComparisonFlag::greater => "Greater",
-        };
-        write!(f, "{}", st)
-    }
-}
-
-#[derive(Copy, Clone)]
-pub struct Computer {
-    pub ra: Word,
-    pub rx: Word, 
-    pub ri1: Word, 
-    pub ri2: Word,
-    pub ri3: Word,
-    pub ri4: Word,
-    pub ri5: Word,
-    pub ri6: Word,
-    pub rj: Word,
-    pub overflow_flag: bool,
-    pub comparison_flag: ComparisonFlag,
-    pub memory: [Word; 4000],
-    pub peripherals: [u8; 20],
-    pub pc: usize,
-}
-
-impl Computer {
-
-    pub fn new(mem: [Word; 4000], start: usize) -> Computer {
-        Computer {
-            ra: Word::default(),
-            rx: Word::default(), 
-            ri1: Word::default(), 
-            ri2: Word::default(),
-            ri3: Word::default(),
-            ri4: Word::default(),
-            ri5: Word::default(),
-            ri6: Word::default(),
-            rj: Word::default(),
-            overflow_flag: false,
-            comparison_flag: ComparisonFlag::equal,
-            memory: mem,
-            peripherals: [0; 20],
-            pc: start,
-        }
-    }
-
-    pub fn default() -> Computer {
-        Computer::new([Word::default(); 4000], 0)
-    }
-
-    fn fetch(self) -> Word {
-        self.memory[self.pc]
-    }
-
-    fn decode_index(&mut self, index: &u8) -> usize {
-        if *index == 0 {
-            return 0;
-        }
-        let ri = register_for_index(self, *index);
-        ri.field_value((3, 4)) as usize
-    }
-
-    fn decode_field(&self, field: &u8) -> (usize, usize) {
-        let right = field % 8;
-        let left = field / 8;
-        (left as usize, right as usize)
-    }
-
-    fn decode(&mut self, instruction: &Word) ->  Box<dyn Instruction> {
-        let (address, index, field, opcode) = (instruction.address(), 
-                                               instruction.index(), 
-                                               instruction.field(), 
-                                               instruction.opcode());
-
-        // Handle the index register
-        let offset_address = address + self.decode_index(&index);
-        let field_specification = self.decode_field(&field);
-        let positive = instruction.positive;
-        let field = instruction.field();
-
-
-        let inst : Box<dyn Instruction> = match opcode {
-            1 => boxed!(Add, offset_address, field_specification),
-            2 => boxed!(Sub, offset_address, field_specification),
-            3 => boxed!(Mult, offset_address, field_specification),
-            4 => boxed!(Div, offset_address, field_specification),
-            5 => match field {
-                2 => boxed!(Halt),
-                _ => boxed!(NoOperation)
-            },
-            6 => match field {
-                0 => boxed!(SLA, address, false),
-                1 => boxed!(SRA, address, false),
-                2 => boxed!(SLAX, address),
-                3 => boxed!(SRAX, address),
-                4 => boxed!(SLA, address, true),
-                5 => boxed!(SRA, address, true),
-                _ => boxed!(NoOperation),
-            }, 
-            8 => boxed!(LoadA, offset_address, field_specification, false),
-            9 | 10 | 11 | 12 | 13 | 14 => boxed!(LoadI, opcode - 8, offset_address, field_specification, false),
-            15 => boxed!(LoadX, offset_address, field_specification, false),
-            16 => boxed!(LoadA, offset_address, field_specification, true),
-            17 | 18 | 19 | 20 | 21 | 22 => boxed!(LoadI, opcode - 16, offset_address, field_specification, true),
-            23 =>boxed!(LoadX, offset_address, field_specification, true),
-            24 => boxed!(StoreA, offset_address, field_specification),
-            25 | 26 | 27 | 28 | 29 | 30 => boxed!(StoreI, opcode - 24, offset_address, field_specification),
-            31 => boxed!(StoreX, offset_address, field_specification),
-            32 => boxed!(StoreJ, offset_address, field_specification),
-            33 => boxed!(StoreZ, offset_address, field_specification),
-            39 => match field {
-                0 => boxed!(Jmp, address, true),
-                1 => boxed!(Jmp, address, false),
-                2 => boxed!(JmpO, address, false),
-                3 => boxed!(JmpO, address, true),
-                4 | 5 | 6 | 7 | 8 | 9 => boxed!(JmpC, address, field),
-                _ => boxed!(NoOperation),
-            },
-            40 => boxed!(JmpA, address, field),
-            41 | 42 | 43 | 44 | 45 | 46 => boxed!(JmpI, opcode - 40, address, field),
-            47 => boxed!(JmpX, address, field),
-            48 => match field {
-                0 => boxed!(IncA, offset_address, positive, false),
-                1 => boxed!(IncA, offset_address, positive, true),
-                2 => boxed!(EntA, offset_address, positive, false),
-                3 => boxed!(EntA, offset_address, positive, true),
-                _ => boxed!(NoOperation),
-            },
-            49 | 50 | 51 | 52 | 53 | 54 => match field {
-                0 => boxed!(IncI, opcode - 48, offset_address, positive, false),
-                1 => boxed!(IncI, opcode - 48, offset_address, positive, true),
-                2 => boxed!(EntI, opcode - 48, offset_address, positive, false),
-                3 => boxed!(EntI, opcode - 48, offset_address, positive, true),
-                _ => boxed!(NoOperation),
-            },
-            55 => match field {
-                0 => boxed!(IncX, offset_address, positive, false),
-                1 => boxed!(IncX, offset_address, positive, true),
-                2 => boxed!(EntX, offset_address, positive, false),
-                3 => boxed!(EntX, offset_address, positive, true),
-                _ => boxed!(NoOperation),
-            },
-            56 => boxed!(CmpA, offset_address, field_specification),
-            57 | 58 | 59 | 60 | 61 | 62 => boxed!(CmpI, opcode - 56, offset_address, field_specification),
-            63 => boxed!(CmpX, offset_address, field_specification),
-            _ => boxed!(NoOperation),
-        };
-
-        inst
-    }
-
-    pub fn run(&mut self) {
-        loop {
-            let instruction = self.fetch();
-            let decoded_instruction = self.decode(&instruction);
-            decoded_instruction.execute_on(self);
-            if self.pc == 4000 { break }
-            self.pc = self.pc + 1;
-        }
-    }
-
-}
+//! The MIX computer: its registers, memory, and units, and the fetch, decode, and execute
+//! of each step, along with the policies deciding what a step does where TAOCP leaves the
+//! machine's behaviour open.
+
+use std::fmt;
+use std::ops::Range;
+use crate::word::{Word};
+use crate::field::FieldSpec;
+use crate::instruction::*;
+use crate::instruction_functions::device_error;
+use crate::portability::{self, PortabilityWarning, CONFIGURED_BYTE_SIZE};
+use crate::error::MixError;
+use crate::extensions::ExtensionDecoder;
+use crate::peripherals::{OutputLimits, Peripheral, BLOCK_WORDS, CARD_WORDS};
+use crate::timing::{CostModel, IoTimingModel, Transfer};
+use crate::trace::{Direction, IoTransfer, StubInvocation, Trace, TraceRecord};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hasher;
+use crate::fingerprint::Fnv1a;
+use crate::loader::SourceMap;
+use crate::layout::MemoryLayout;
+use crate::heatmap::{Access, AccessHeatmap, HeatmapOptions};
+use crate::budget::{self, InstrumentationBudget, Truncation};
+use crate::migration::BYTE_SIZES;
+use crate::invariants::{self, InvariantCheck, InvariantCheckFn, InvariantViolation};
+use crate::mirror::{MirrorOptions, MirrorReader, MirrorSlot, Publisher};
+use crate::decode::{self, DecodeError, DecodedInstruction, DecodedItem};
+use crate::phases::{ControlFlow, Phase, Snapshot};
+use crate::opcodes;
+use crate::consts::{EnterOp, JumpCondition, Opcode, ShiftOp, SpecialOp, UnitNumber, MAX_MOVE_COUNT};
+use std::convert::TryFrom;
+use crate::dispatch::{Decoded, Dispatch, Op, Prepared};
+use std::rc::Rc;
+
+macro_rules! boxed {
+    ($name:ident) => {
+        Box::new($name::new())
+    };
+    ($name:ident, $($item:ident),*) => {
+        Box::new($name::new_unchecked($($item),*))
+    };
+    ($name:ident, $expr:expr, $($item:ident),*) => {
+        Box::new($name::new_unchecked($expr, $($item),*))
+    };
+}
+
+/// Builds an instruction of the core instruction set as whatever `D` the decoder is
+/// making (see `dispatch::Decoded`).
+macro_rules! decoded {
+    ($name:ident) => {
+        D::wrap($name::new())
+    };
+    ($name:ident, $($item:ident),*) => {
+        D::wrap($name::new_unchecked($($item),*))
+    };
+    ($name:ident, $expr:expr, $($item:ident),*) => {
+        D::wrap($name::new_unchecked($expr, $($item),*))
+    };
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ComparisonFlag {
+    less,
+    equal,
+    greater
+}
+
+/// What happens when the computer meets an instruction that neither the core
+/// instruction set nor a registered extension implements.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UnimplementedPolicy {
+    /// The instruction is treated as `NOP`.
+    NoOperation,
+    /// The run stops with `MixError::UnimplementedInstruction`.
+    Fault,
+}
+
+/// What happens when an instruction meets a case Knuth leaves undefined, such as `LDi`,
+/// `INCi` or `DECi` giving a value that doesn't fit in an index register.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UndefinedPolicy {
+    /// The run stops with an error naming the case.
+    Fault,
+    /// The result is cut down to fit, and an `UndefinedWarning` is recorded.
+    Truncate,
+}
+
+/// Records an instruction that met an undefined case and went on under
+/// `UndefinedPolicy::Truncate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UndefinedWarning {
+    pub pc: usize,
+    pub operation: String,
+    pub detail: String,
+}
+
+/// What happens when `IN`, `OUT`, `IOC`, `JBUS`, or `JRED` names a unit with no device
+/// attached.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UnattachedPolicy {
+    /// The run stops with `MixError::UnitNotAttached`.
+    Fault,
+    /// The unit acts as one that is always ready and holds nothing: `OUT` and `IOC` do
+    /// nothing, `IN` reads a block of +0, `JBUS` never jumps, and `JRED` always does. The
+    /// first use of each unit by each operation is recorded as an `UnattachedWarning`.
+    Lenient,
+}
+
+/// Records the first use of a unit with no device attached by an operation under
+/// `UnattachedPolicy::Lenient`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnattachedWarning {
+    pub pc: usize,
+    pub unit: u8,
+    pub operation: String,
+}
+
+/// Records a read of positions of a word that nothing has written, found when
+/// `Computer::track_written_bytes` is on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UninitializedRead {
+    pub pc: usize,
+    pub operation: String,
+    pub address: usize,
+    /// The positions read but never written, 0 for the sign and 1 to 5 for the bytes.
+    pub positions: Vec<usize>,
+}
+
+/// What happens when an instruction reads rA or rX while its contents are undefined, as
+/// they are after a `DIV` overflow, and before anything has written the whole register.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PoisonPolicy {
+    /// Nothing is tracked, and the registers keep the values the emulator leaves there.
+    Off,
+    /// The registers keep those values, and the first read of each is recorded as an
+    /// `UndefinedWarning`.
+    Warn,
+    /// The registers are filled with `POISON`, and a read stops the run with an error.
+    Fault,
+}
+
+/// What happens when a store writes byte 5, the opcode byte, of a word the loaded program
+/// gave as an instruction. Outside of deliberate self-modifying code, which can say so
+/// with `Computer::allow_code_write`, this is almost always `STJ` given (0:5) by mistake.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CodeWritePolicy {
+    /// Stores are not checked.
+    Allow,
+    /// The store goes ahead, and a `CodeWriteWarning` is recorded.
+    Warn,
+    /// The run stops with an error before anything is written.
+    Fault,
+}
+
+/// Records a store into the opcode byte of an instruction under `CodeWritePolicy::Warn`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CodeWriteWarning {
+    pub pc: usize,
+    pub operation: String,
+    pub address: usize,
+    pub field: FieldSpec,
+}
+
+/// The registers whose contents an instruction can leave undefined.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Accumulator {
+    A,
+    X,
+}
+
+impl fmt::Display for Accumulator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Accumulator::A => write!(f, "rA"),
+            Accumulator::X => write!(f, "rX"),
+        }
+    }
+}
+
+/// What an undefined register holds under `PoisonPolicy::Fault`, chosen to stand out in a
+/// dump whatever the byte size.
+pub const POISON: Word = Word { positive: false, bytes: [63; 5] };
+
+impl fmt::Display for ComparisonFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let st = match self {
+            ComparisonFlag::less => "Less",
+            ComparisonFlag::equal => "Equal",
+            ComparisonFlag::greater => "Greater",
+        };
+        write!(f, "{}", st)
+    }
+}
+
+/// The mask of every position of a word, as `FieldSpec::WHOLE.mask()` gives it.
+const FULL_MASK: u8 = 0b11_1111;
+
+/// The bit of a byte of `Computer::peripherals_legacy` set when a device is attached.
+pub const UNIT_ATTACHED: u8 = 1 << 0;
+
+/// The bit of a byte of `Computer::peripherals_legacy` set when the unit is busy.
+pub const UNIT_BUSY: u8 = 1 << 1;
+
+/// `Computer::unchecked_writes` when nothing has been written since the last check.
+const NOTHING_WRITTEN: (usize, usize) = (usize::MAX, 0);
+
+/// The number of words of memory a computer has unless given another size.
+pub const DEFAULT_MEMORY_SIZE: usize = 4000;
+
+/// The number of words the address of an instruction constructed directly may name: those
+/// two 64-value bytes can, so that the instruction is portable.
+pub const MAX_MEMORY_DECLARED: usize = 64 * 64;
+
+/// The most blocks the card punch and line printer of `Computer::attach_standard_devices`
+/// hold before the program waits on them.
+pub const STANDARD_OUTPUT_DEPTH: usize = 10_000;
+
+/// Whether the computer is still running a program.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MachineState {
+    Running,
+    /// `HLT` was executed. `pc` has advanced past it as after any other instruction.
+    Halted,
+}
+
+/// The state of a MIX machine.
+///
+/// Alongside the memory, the computer keeps an occupancy bitset recording which words
+/// have ever been written through `write_mem` (or loaded as part of an image), so that
+/// tooling can visit only the words in use. A word stays occupied even when it is later
+/// overwritten with +0, and only becomes unoccupied again through `clear_range` or
+/// `reset`. Writes made directly to the `memory` field bypass this bookkeeping.
+///
+/// Read the registers, memory, and program counter with `registers`, `memory`, and `pc`.
+/// Their fields are still public while the crate moves off them, but left out of the
+/// documentation, as memory may not always be a `Vec`.
+#[derive(Clone)]
+pub struct Computer {
+    #[doc(hidden)]
+    pub ra: Word,
+    #[doc(hidden)]
+    pub rx: Word,
+    #[doc(hidden)]
+    pub ri1: Word,
+    #[doc(hidden)]
+    pub ri2: Word,
+    #[doc(hidden)]
+    pub ri3: Word,
+    #[doc(hidden)]
+    pub ri4: Word,
+    #[doc(hidden)]
+    pub ri5: Word,
+    #[doc(hidden)]
+    pub ri6: Word,
+    #[doc(hidden)]
+    pub rj: Word,
+    pub overflow_flag: bool,
+    pub comparison_flag: ComparisonFlag,
+    #[doc(hidden)]
+    pub memory: Vec<Word>,
+    #[doc(hidden)]
+    pub pc: usize,
+    pub check_portability: bool,
+    pub portability_warnings: Vec<PortabilityWarning>,
+    pub unimplemented_policy: UnimplementedPolicy,
+    pub undefined_policy: UndefinedPolicy,
+    pub undefined_warnings: Vec<UndefinedWarning>,
+    /// The reads of unwritten positions, kept while `track_written_bytes` is on.
+    pub uninitialized_reads: Vec<UninitializedRead>,
+    pub poison_policy: PoisonPolicy,
+    pub code_write_policy: CodeWritePolicy,
+    pub code_write_warnings: Vec<CodeWriteWarning>,
+    pub unattached_policy: UnattachedPolicy,
+    /// The uses of units with no device attached, one for each unit and operation.
+    pub unattached_warnings: Vec<UnattachedWarning>,
+    pub io_timing: IoTimingModel,
+    pub word_transfer_time: u64,
+    pub steal_cost: u64,
+    pub source_map: Option<SourceMap>,
+    /// The records of the run, kept only when set.
+    pub trace: Option<Trace>,
+    /// The named regions of memory, for reports to place addresses in.
+    pub layout: Option<MemoryLayout>,
+    pub output_limits: OutputLimits,
+    /// How each word is executed once decoded, kept on reset.
+    pub dispatch: Dispatch,
+    /// The most the warning lists, trace, and heatmap hold, kept on reset.
+    pub budget: InstrumentationBudget,
+    /// The lists that dropped entries for want of budget.
+    pub truncated: Truncation,
+    /// Whether every step checks the invariants once it has executed (see `invariants`),
+    /// on by default in debug builds, and kept on reset.
+    pub verify_invariants: bool,
+    /// The number of values a byte holds, `CONFIGURED_BYTE_SIZE` unless set with
+    /// `set_byte_size`, and kept on reset.
+    byte_size: u16,
+    state: MachineState,
+    poisoned: [bool; 2],
+    code_writes_allowed: BTreeSet<usize>,
+    occupancy: Vec<u64>,
+    /// For each word, the positions written, as in `FieldSpec::mask`, when tracked.
+    written: Option<Vec<u8>>,
+    extensions: BTreeMap<(u8, u8), ExtensionDecoder>,
+    cost_model: CostModel,
+    instruction_cycles: u64,
+    /// The words the instruction being executed has moved, which only `MOVE` sets, for
+    /// the time it is charged.
+    pub(crate) words_moved: u64,
+    /// Whether the instruction being executed jumped, so that the step goes on at `pc`
+    /// rather than after it.
+    pub(crate) jumped: bool,
+    stolen_cycles: u64,
+    transfers: Vec<Transfer>,
+    /// The device attached as each unit. This replaced `peripherals`, an array of bytes
+    /// standing for the units that the computer never read: attach a device with
+    /// `attach`, and read what the array held with `peripherals_legacy`.
+    devices: BTreeMap<u8, Rc<RefCell<dyn Peripheral>>>,
+    blocked_on: Option<u8>,
+    blocks_written: BTreeMap<u8, usize>,
+    phase_observer: Option<PhaseObserver>,
+    stubs: BTreeMap<usize, Stub>,
+    heatmap: Option<AccessHeatmap>,
+    mirror: MirrorSlot,
+    invariant_checks: Vec<InvariantCheck>,
+    /// The addresses written since the invariants were last checked, as the least and one
+    /// past the greatest, or `NOTHING_WRITTEN`.
+    unchecked_writes: Cell<(usize, usize)>,
+}
+
+/// Called with each phase of every step (see `Computer::set_phase_observer`).
+pub type PhaseObserver = Rc<RefCell<Box<dyn FnMut(Phase)>>>;
+
+/// Host code run in place of a subroutine (see `Computer::stub_subroutine`).
+pub type HostStub = Box<dyn FnMut(&mut Computer) -> Result<(), MixError>>;
+
+/// How a stubbed subroutine is linked to its callers, which decides what a stub does on
+/// entry and where it returns to.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum StubLinkage {
+    /// The subroutine starts with `STJ EXIT`, saving rJ into the `JMP *` at `EXIT` that
+    /// returns. That `STJ` is executed before the stub runs, as the real subroutine would,
+    /// and the stub returns to the address it saved.
+    #[default]
+    Stj,
+    /// The stub returns to the address in rJ as it was on entry, storing nothing.
+    Raw,
+}
+
+#[derive(Clone)]
+struct Stub {
+    linkage: StubLinkage,
+    call: Rc<RefCell<HostStub>>,
+}
+
+/// A write of many words of memory by one instruction, as `IN` and `MOVE` make. Every
+/// such write goes through here, so that the features watching single words treat a
+/// block the same way whichever instruction writes it:
+///
+/// - The whole destination is checked before any word of it is written: it must lie in
+///   memory, and `code_write_policy` must allow a whole-word store into each of its words.
+///   A block failing either check leaves memory as it was, rather than stopping partway.
+/// - Under `CodeWritePolicy::Warn`, each code word the block overwrites is warned about
+///   once the block has been written.
+/// - Every word of the destination is marked occupied, whatever was written into it.
+/// - A block transferred from a unit makes a single trace record covering its whole
+///   range.
+pub(crate) struct BlockWrite {
+    pub range: Range<usize>,
+    /// The code words `code_write_policy` warns about.
+    code_writes: Vec<usize>,
+}
+
+impl BlockWrite {
+    /// Checks a write of `words` words at `address` by the instruction at the `pc` of
+    /// `computer`.
+    ///
+    /// ## Errors
+    /// - `BlockOutOfRange` when the block runs past the end of memory.
+    /// - `CodeWrite` for the lowest code word of the block under `CodeWritePolicy::Fault`.
+    pub(crate) fn check(computer: &Computer, address: usize, words: usize) -> Result<BlockWrite, MixError> {
+        let range = computer.block_range(address, words)?;
+        let code_writes: Vec<usize> = range.clone().filter(|address| computer.is_code_write(*address, FieldSpec::WHOLE)).collect();
+        if let (Some(address), true) = (code_writes.first(), computer.code_write_policy == CodeWritePolicy::Fault) {
+            return Err(MixError::CodeWrite { pc: computer.pc, address: *address, field: FieldSpec::WHOLE });
+        }
+        Ok(BlockWrite { range, code_writes })
+    }
+
+    /// Accounts for the block once it has been written, giving back its range.
+    pub(crate) fn finish(self, computer: &mut Computer) -> Range<usize> {
+        for address in self.code_writes {
+            let warning = CodeWriteWarning { pc: computer.pc, operation: computer.current_operation(), address, field: FieldSpec::WHOLE };
+            computer.warn_code_write(warning);
+        }
+        computer.mark_occupied(self.range.clone());
+        computer.record_access(self.range.clone(), Access::Write);
+        self.range
+    }
+
+    /// Accounts for the block once it has been written from `unit`.
+    pub(crate) fn finish_transfer(self, computer: &mut Computer, unit: u8, direction: Direction) {
+        let range = self.finish(computer);
+        computer.record_io(unit, direction, range);
+    }
+}
+
+impl Computer {
+
+    /// Creates a computer from a memory image, with execution starting at `start`. The
+    /// computer has as many words of memory as the image, and every word of the image
+    /// other than +0 is considered occupied.
+    pub fn new(mem: Vec<Word>, start: usize) -> Computer {
+        let mut occupancy = vec![0; mem.len().div_ceil(64)];
+        for (address, word) in mem.iter().enumerate() {
+            if *word != Word::default() {
+                occupancy[address / 64] |= 1 << (address % 64);
+            }
+        }
+        Computer {
+            ra: Word::default(),
+            rx: Word::default(), 
+            ri1: Word::default(), 
+            ri2: Word::default(),
+            ri3: Word::default(),
+            ri4: Word::default(),
+            ri5: Word::default(),
+            ri6: Word::default(),
+            rj: Word::default(),
+            overflow_flag: false,
+            comparison_flag: ComparisonFlag::equal,
+            memory: mem,
+            pc: start,
+            state: MachineState::Running,
+            poisoned: [false; 2],
+            code_writes_allowed: BTreeSet::new(),
+            check_portability: false,
+            portability_warnings: Vec::new(),
+            unimplemented_policy: UnimplementedPolicy::NoOperation,
+            undefined_policy: UndefinedPolicy::Fault,
+            undefined_warnings: Vec::new(),
+            uninitialized_reads: Vec::new(),
+            poison_policy: if cfg!(debug_assertions) { PoisonPolicy::Warn } else { PoisonPolicy::Off },
+            code_write_policy: CodeWritePolicy::Allow,
+            code_write_warnings: Vec::new(),
+            unattached_policy: UnattachedPolicy::Fault,
+            unattached_warnings: Vec::new(),
+            io_timing: IoTimingModel::Overlapped,
+            word_transfer_time: 2,
+            steal_cost: 1,
+            source_map: None,
+            trace: None,
+            layout: None,
+            output_limits: OutputLimits::unlimited(),
+            dispatch: Dispatch::default(),
+            budget: InstrumentationBudget::default(),
+            truncated: Truncation::default(),
+            occupancy,
+            written: None,
+            extensions: BTreeMap::new(),
+            cost_model: CostModel::knuth(),
+            instruction_cycles: 0,
+            words_moved: 0,
+            jumped: false,
+            stolen_cycles: 0,
+            transfers: Vec::new(),
+            devices: BTreeMap::new(),
+            blocked_on: None,
+            blocks_written: BTreeMap::new(),
+            phase_observer: None,
+            stubs: BTreeMap::new(),
+            heatmap: None,
+            mirror: MirrorSlot::default(),
+            verify_invariants: cfg!(debug_assertions),
+            byte_size: CONFIGURED_BYTE_SIZE as u16,
+            invariant_checks: vec![
+                InvariantCheck { name: "occupancy", check: check_occupancy },
+                InvariantCheck { name: "budget", check: budget::check_budget },
+            ],
+            unchecked_writes: Cell::new(NOTHING_WRITTEN),
+        }
+    }
+
+    pub fn default() -> Computer {
+        Computer::with_memory_size(DEFAULT_MEMORY_SIZE)
+    }
+
+    /// Creates a computer whose memory holds `size` words, all +0.
+    pub fn with_memory_size(size: usize) -> Computer {
+        Computer::new(vec![Word::default(); size], 0)
+    }
+
+    /// The number of words of memory.
+    pub fn memory_size(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn memory(&self) -> &[Word] {
+        &self.memory
+    }
+
+    /// The address of the next instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// The number of values a byte holds.
+    pub fn byte_size(&self) -> u16 {
+        self.byte_size
+    }
+
+    /// Makes every byte hold `byte_size` values, as on the machines Knuth allows with bytes
+    /// of 64 to 256 values, for decoding and every instruction. Words already in memory or
+    /// the registers are left as they are; `migration` converts an image between sizes.
+    ///
+    /// ## Errors
+    /// - `UnsupportedByteSize` when `byte_size` isn't in `migration::BYTE_SIZES`.
+    pub fn set_byte_size(&mut self, byte_size: u16) -> Result<(), MixError> {
+        if !BYTE_SIZES.contains(&byte_size) {
+            return Err(MixError::UnsupportedByteSize { byte_size });
+        }
+        self.byte_size = byte_size;
+        Ok(())
+    }
+
+    /// rA, rX, rI1 to rI6, and rJ, in that order.
+    pub fn registers(&self) -> [Word; 9] {
+        [self.ra, self.rx, self.ri1, self.ri2, self.ri3, self.ri4, self.ri5, self.ri6, self.rj]
+    }
+
+    /// Restores the registers, flags, memory, and clock to their initial state, leaving
+    /// every word of memory unoccupied and abandoning any transfers in progress. Settings
+    /// such as `check_portability`, the unimplemented, undefined, poison, code write, and
+    /// unattached unit policies, the I/O timing, the cost model, the trace options, the layout, registered
+    /// extensions, attached units, the size of memory, whether written bytes are tracked,
+    /// the options of the access heatmap, the instrumentation budget, the mirror, the
+    /// invariant checks, and the byte size are kept.
+    pub fn reset(&mut self) {
+        let mut computer = Computer::with_memory_size(self.memory_size());
+        computer.check_portability = self.check_portability;
+        computer.unimplemented_policy = self.unimplemented_policy;
+        computer.undefined_policy = self.undefined_policy;
+        computer.poison_policy = self.poison_policy;
+        computer.code_write_policy = self.code_write_policy;
+        computer.unattached_policy = self.unattached_policy;
+        computer.io_timing = self.io_timing;
+        computer.word_transfer_time = self.word_transfer_time;
+        computer.steal_cost = self.steal_cost;
+        computer.output_limits = self.output_limits;
+        computer.dispatch = self.dispatch;
+        computer.budget = self.budget;
+        computer.trace = self.trace.as_ref().map(Trace::restarted);
+        computer.layout = self.layout.take();
+        computer.cost_model = std::mem::take(&mut self.cost_model);
+        computer.extensions = std::mem::take(&mut self.extensions);
+        computer.devices = std::mem::take(&mut self.devices);
+        computer.phase_observer = self.phase_observer.take();
+        computer.stubs = std::mem::take(&mut self.stubs);
+        computer.heatmap = self.heatmap.as_ref().map(AccessHeatmap::restarted);
+        computer.mirror = std::mem::take(&mut self.mirror);
+        computer.verify_invariants = self.verify_invariants;
+        computer.byte_size = self.byte_size;
+        computer.invariant_checks = std::mem::take(&mut self.invariant_checks);
+        if self.written.is_some() {
+            computer.track_written_bytes();
+        }
+        *self = computer;
+    }
+
+    /// The number of time units elapsed since the computer was created or reset. This is
+    /// always `instruction_time() + stolen_time()`.
+    pub fn time(&self) -> u64 {
+        self.instruction_cycles + self.stolen_cycles
+    }
+
+    /// The time spent executing instructions, including time spent waiting on busy units.
+    pub fn instruction_time(&self) -> u64 {
+        self.instruction_cycles
+    }
+
+    /// The time taken from the program by cycle-stealing transfers.
+    pub fn stolen_time(&self) -> u64 {
+        self.stolen_cycles
+    }
+
+    /// Sets the time spent executing instructions and stolen by transfers, as when
+    /// going back to a checkpoint.
+    pub(crate) fn set_times(&mut self, instruction_time: u64, stolen_time: u64) {
+        self.instruction_cycles = instruction_time;
+        self.stolen_cycles = stolen_time;
+    }
+
+    /// Charges instructions executed from now on by `model`. Time already elapsed is kept.
+    pub fn set_cost_model(&mut self, model: CostModel) {
+        self.cost_model = model;
+    }
+
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    /// Attaches `device` as `unit`, replacing any device already attached there. The
+    /// returned handle shares the device with the computer, so that it can be inspected
+    /// while or after the program runs.
+    pub fn attach<P: Peripheral + 'static>(&mut self, unit: u8, device: P) -> Rc<RefCell<P>> {
+        let device = Rc::new(RefCell::new(device));
+        self.devices.insert(unit, device.clone());
+        self.register_invariant_check("transfers", check_transfers);
+        device
+    }
+
+    /// The device attached as `unit`, if any.
+    pub fn device(&self, unit: u8) -> Option<Rc<RefCell<dyn Peripheral>>> {
+        self.devices.get(&unit).cloned()
+    }
+
+    /// The units with a device attached, in order.
+    pub fn attached_units(&self) -> Vec<u8> {
+        self.devices.keys().copied().collect()
+    }
+
+    /// A byte for each unit, 0 to 20, standing for its state now, as the `peripherals`
+    /// array the device table replaced was meant to: `UNIT_ATTACHED` is set when a device
+    /// is attached as the unit, and `UNIT_BUSY` when `is_busy` is. The bytes are a copy,
+    /// so writing them changes nothing.
+    #[deprecated(note = "the `peripherals` array is now the device table; use `attach`, `device`, `attached_units`, and `is_busy`")]
+    pub fn peripherals_legacy(&self) -> [u8; UnitNumber::LAST as usize + 1] {
+        let mut status = [0; UnitNumber::LAST as usize + 1];
+        for (unit, byte) in status.iter_mut().enumerate() {
+            if self.devices.contains_key(&(unit as u8)) {
+                *byte |= UNIT_ATTACHED;
+            }
+            if self.is_busy(unit as u8) {
+                *byte |= UNIT_BUSY;
+            }
+        }
+        status
+    }
+
+    /// Presses the GO button of the console, which starts a program from its loading
+    /// routine: one card is read from the card reader, unit 16, into the words 0 to 15,
+    /// and once the reader is no longer busy the computer goes to 0 with rJ +0, running
+    /// again if it had halted. Of the block the reader gives, only the card is stored, so
+    /// the words from 16 on are left as they were.
+    ///
+    /// ## Errors
+    /// - `UnitNotAttached` when no device is attached as unit 16.
+    /// - `Device` when the reader fails.
+    /// - `CodeWrite` when one of the words 0 to 15 is code under `CodeWritePolicy::Fault`.
+    pub fn press_go(&mut self) -> Result<(), MixError> {
+        let unit = UnitNumber::CardReader.number();
+        let device = self.device(unit).ok_or_else(|| MixError::UnitNotAttached {
+            pc: self.pc, unit, operation: "GO".to_string(), attached: self.attached_units(),
+        })?;
+        self.wait_for(unit)?;
+        let card = BlockWrite::check(self, 0, CARD_WORDS)?;
+        let mut block = [Word::default(); BLOCK_WORDS];
+        device.borrow_mut().read_block_into(&mut block, self.byte_size)
+            .map_err(|error| device_error(self, unit, error))?;
+        self.memory[card.range.clone()].copy_from_slice(&block[..CARD_WORDS]);
+        card.finish_transfer(self, unit, Direction::In);
+        self.start_transfer(unit, CARD_WORDS);
+        self.wait_for(unit)?;
+        self.rj = Word::default();
+        self.pc = 0;
+        self.state = MachineState::Running;
+        Ok(())
+    }
+
+    /// Accounts for the instruction being executed using `unit`, which has no device
+    /// attached, under `unattached_policy`.
+    ///
+    /// ## Errors
+    /// - `UnitNotAttached` under `UnattachedPolicy::Fault`.
+    pub(crate) fn use_unattached(&mut self, unit: u8) -> Result<(), MixError> {
+        let operation = self.current_operation();
+        if self.unattached_policy == UnattachedPolicy::Fault {
+            return Err(MixError::UnitNotAttached { pc: self.pc, unit, operation, attached: self.attached_units() });
+        }
+        if !self.unattached_warnings.iter().any(|warning| warning.unit == unit && warning.operation == operation) {
+            let warning = UnattachedWarning { pc: self.pc, unit, operation };
+            budget::record(&mut self.unattached_warnings, warning, self.budget.max_warnings, &mut self.truncated.unattached_warnings);
+        }
+        Ok(())
+    }
+
+    /// Whether `unit` still has a transfer in progress, or the device itself is busy.
+    pub fn is_busy(&self, unit: u8) -> bool {
+        self.transfers.iter().any(|transfer| transfer.unit == unit) || self.is_device_busy(unit)
+    }
+
+    /// Whether any unit is busy, as `is_busy` says of each.
+    pub fn any_busy(&self) -> bool {
+        !self.transfers.is_empty() || self.devices.values().any(|device| device.borrow().is_busy())
+    }
+
+    /// Whether the device attached as `unit` is busy for reasons outside of this computer,
+    /// as with a full or empty channel.
+    pub fn is_device_busy(&self, unit: u8) -> bool {
+        self.devices.get(&unit).is_some_and(|device| device.borrow().is_busy())
+    }
+
+    /// The unit the last step waited on, if it could make no progress until something
+    /// outside of this computer acts on that unit.
+    pub fn blocked_on(&self) -> Option<u8> {
+        self.blocked_on
+    }
+
+    /// Records that the current step is waiting on `unit`.
+    pub(crate) fn block_on(&mut self, unit: u8) {
+        self.blocked_on = Some(unit);
+    }
+
+    /// The number of blocks written to `unit` since the computer was created or reset.
+    pub fn blocks_written(&self, unit: u8) -> usize {
+        self.blocks_written.get(&unit).copied().unwrap_or(0)
+    }
+
+    /// Counts a block about to be written to `unit` against `output_limits`.
+    ///
+    /// ## Errors
+    /// - `OutputLimitExceeded` when `unit` already has as many blocks as it may, in which
+    ///   case the block isn't counted and mustn't be written.
+    pub(crate) fn count_block_written(&mut self, unit: u8) -> Result<(), MixError> {
+        let written = self.blocks_written(unit);
+        if let Some(limit) = self.output_limits.limit_for(unit) {
+            if written >= limit {
+                return Err(MixError::OutputLimitExceeded { pc: self.pc, unit, limit });
+            }
+        }
+        self.blocks_written.insert(unit, written + 1);
+        Ok(())
+    }
+
+    /// Starts a transfer of `words` words on `unit`, the first of which moves one word
+    /// time from now.
+    pub(crate) fn start_transfer(&mut self, unit: u8, words: usize) {
+        if self.io_timing == IoTimingModel::Instant || words == 0 {
+            return;
+        }
+        let next_word_at = self.time() + self.word_transfer_time;
+        self.transfers.push(Transfer { unit, words_remaining: words, next_word_at, output: None, pc: self.pc });
+    }
+
+    /// Writes the words of `range` to `unit` for `OUT`: at once under `Instant` timing,
+    /// or otherwise as the last word moves, as `IoTimingModel` describes.
+    ///
+    /// ## Errors
+    /// - `Device` when the unit can't take the block written at once.
+    pub(crate) fn start_output(&mut self, unit: u8, range: Range<usize>) -> Result<(), MixError> {
+        if self.io_timing == IoTimingModel::Instant || range.is_empty() {
+            return self.complete_output(unit, range, self.pc, self.time());
+        }
+        let next_word_at = self.time() + self.word_transfer_time;
+        self.transfers.push(Transfer { unit, words_remaining: range.len(), next_word_at, output: Some(range), pc: self.pc });
+        Ok(())
+    }
+
+    /// Copies the words of `range` to `unit` as they stand now, for the `OUT` at `pc`.
+    fn complete_output(&mut self, unit: u8, range: Range<usize>, pc: usize, completes_at: u64) -> Result<(), MixError> {
+        if let Some(device) = self.devices.get(&unit).cloned() {
+            device.borrow_mut().write_block(&self.memory[range.clone()])
+                .map_err(|error| MixError::Device { pc, unit, message: error.to_string() })?;
+        }
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record(completes_at, range.clone(), Access::Read, self.budget.max_heatmap_cells);
+        }
+        self.record_io_at(unit, Direction::Out, range, pc, completes_at);
+        Ok(())
+    }
+
+    /// Copies every block still moving to its unit, as memory stands now, for a computer
+    /// that has halted and so can no longer change them.
+    fn flush_outputs(&mut self) -> Result<(), MixError> {
+        for i in 0..self.transfers.len() {
+            let transfer = &mut self.transfers[i];
+            if let Some(range) = transfer.output.take() {
+                let (unit, pc) = (transfer.unit, transfer.pc);
+                let completes_at = transfer.next_word_at + (transfer.words_remaining as u64 - 1) * self.word_transfer_time;
+                self.complete_output(unit, range, pc, completes_at)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a record of the transfer just issued on `unit` to the trace, if there is one,
+    /// covering the words of `range`.
+    ///
+    /// ## Panics
+    /// - With debug assertions, when `direction` is `Out` and the unit reports a last block
+    ///   other than the words of `range`, since the trace would no longer match the unit.
+    pub(crate) fn record_io(&mut self, unit: u8, direction: Direction, range: Range<usize>) {
+        let completes_at = if self.io_timing == IoTimingModel::Instant {
+            self.time()
+        } else {
+            self.time() + range.len() as u64 * self.word_transfer_time
+        };
+        self.record_io_at(unit, direction, range, self.pc, completes_at);
+    }
+
+    /// As `record_io`, for the transfer issued at `pc` whose last word moves at
+    /// `completes_at`.
+    fn record_io_at(&mut self, unit: u8, direction: Direction, range: Range<usize>, pc: usize, completes_at: u64) {
+        let trace = match &self.trace {
+            Some(trace) => trace,
+            None => return,
+        };
+        let block = &self.memory[range.clone()];
+        if direction == Direction::Out {
+            if let Some(written) = self.devices.get(&unit).and_then(|device| device.borrow().last_block_written()) {
+                debug_assert!(written == block, "[Error record_io] Unit {} holds a different block than OUT wrote from {}", unit, range.start);
+            }
+        }
+        let transfer = IoTransfer {
+            unit,
+            direction,
+            block: if trace.options.include_blocks { Some(block.to_vec()) } else { None },
+            range,
+            pc,
+            completes_at,
+        };
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceRecord::Io(transfer), self.budget.max_trace_records);
+        }
+    }
+
+    /// Waits until `unit` is no longer busy. The time spent waiting is charged as
+    /// instruction time, while words moved in the meantime still steal their cost.
+    ///
+    /// ## Errors
+    /// - `Device` when an `OUT` completing meanwhile can't write its block.
+    pub(crate) fn wait_for(&mut self, unit: u8) -> Result<(), MixError> {
+        while self.transfers.iter().any(|transfer| transfer.unit == unit) {
+            let due = self.transfers.iter().map(|transfer| transfer.next_word_at).min().unwrap_or(0);
+            self.instruction_cycles += due.saturating_sub(self.time());
+            self.advance_transfers()?;
+        }
+        Ok(())
+    }
+
+    /// Moves every word that is due by now. Words are moved earliest first, with ties
+    /// going to the transfer started first, and under `CycleStealing` each one pushes the
+    /// clock forward by `steal_cost`, which may make further words due. An `OUT` whose
+    /// last word moves writes its block then.
+    ///
+    /// ## Errors
+    /// - `Device`, for the `OUT`, when its unit can't take the block.
+    fn advance_transfers(&mut self) -> Result<(), MixError> {
+        loop {
+            let now = self.time();
+            let due = self.transfers.iter().enumerate()
+                .filter(|(_, transfer)| transfer.next_word_at <= now)
+                .min_by_key(|(_, transfer)| transfer.next_word_at)
+                .map(|(i, _)| i);
+            let i = match due {
+                Some(i) => i,
+                None => break,
+            };
+            if self.io_timing == IoTimingModel::CycleStealing {
+                self.stolen_cycles += self.steal_cost;
+            }
+            let transfer = &mut self.transfers[i];
+            let moved_at = transfer.next_word_at;
+            transfer.words_remaining -= 1;
+            transfer.next_word_at += self.word_transfer_time;
+            if transfer.words_remaining == 0 {
+                let transfer = self.transfers.remove(i);
+                if let Some(range) = transfer.output {
+                    self.complete_output(transfer.unit, range, transfer.pc, moved_at)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers an extension instruction under the given opcode and field, to be used
+    /// whenever the core instruction set doesn't implement that combination.
+    ///
+    /// ## Errors
+    /// - `ExtensionConflict` when the core instruction set or another extension already
+    ///   implements the combination.
+    pub fn register_extension<F>(&mut self, opcode: u8, field: u8, decoder: F) -> Result<(), MixError>
+        where F: Fn(&Word, usize) -> Box<dyn Instruction> + 'static
+    {
+        let instruction = Word::from_instruction(0, 0, field, opcode);
+        if self.extensions.contains_key(&(opcode, field)) || self.decode_core(&instruction).is_some() {
+            return Err(MixError::ExtensionConflict { opcode, field });
+        }
+        self.extensions.insert((opcode, field), Rc::new(decoder) as ExtensionDecoder);
+        Ok(())
+    }
+
+    /// Encodes the overflow toggle and comparison indicator as a status word
+    /// `+ 0 0 0 V C`, where `V` is 1 when the overflow toggle is on and `C` is 0, 1, or 2
+    /// for less, equal, and greater.
+    pub fn flags_word(&self) -> Word {
+        let comparison = match self.comparison_flag {
+            ComparisonFlag::less => 0,
+            ComparisonFlag::equal => 1,
+            ComparisonFlag::greater => 2,
+        };
+        Word::new(true, [0, 0, 0, self.overflow_flag as u8, comparison])
+    }
+
+    /// Restores the overflow toggle and comparison indicator from a status word, as
+    /// produced by `flags_word`.
+    ///
+    /// ## Returns
+    /// - `false`, leaving the flags untouched, when `word` isn't a valid status word.
+    pub fn set_flags_word(&mut self, word: &Word) -> bool {
+        let comparison = match word.bytes[4] {
+            0 => ComparisonFlag::less,
+            1 => ComparisonFlag::equal,
+            2 => ComparisonFlag::greater,
+            _ => return false,
+        };
+        if !word.positive || word.bytes[..3] != [0, 0, 0] || word.bytes[3] > 1 {
+            return false;
+        }
+        self.overflow_flag = word.bytes[3] == 1;
+        self.comparison_flag = comparison;
+        true
+    }
+
+    /// Writes `word` to `address`, marking the address as occupied.
+    ///
+    /// ## Panics
+    /// Panics when `address` is outside of memory.
+    pub fn write_mem(&mut self, address: usize, word: Word) {
+        if let Err(error) = self.store_field(address, word, FieldSpec::WHOLE) {
+            panic!("[Error write_mem] {}", error);
+        }
+    }
+
+    /// The word at `address`, without recording a read, for an instruction storing into
+    /// part of it.
+    ///
+    /// ## Errors
+    /// - `AddressOutOfRange` when `address` is outside of memory.
+    // A body marked with `no_panic!` builds its errors lazily (see `no_panic`).
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    pub(crate) fn word_at(&self, address: usize) -> Result<Word, MixError> { no_panic!({
+        self.memory.get(address).copied().ok_or_else(|| MixError::AddressOutOfRange { pc: self.pc, address: address as i64 })
+    })}
+
+    /// Writes `word` to `address` as a store of `field_specification` does, marking the
+    /// address as occupied and the positions of the field as written.
+    ///
+    /// ## Errors
+    /// - `AddressOutOfRange` when `address` is outside of memory.
+    pub(crate) fn store_field(&mut self, address: usize, word: Word, field_specification: FieldSpec) -> Result<(), MixError> {
+        let pc = self.pc;
+        *self.memory.get_mut(address).ok_or(MixError::AddressOutOfRange { pc, address: address as i64 })? = word;
+        self.note_writes(address..address + 1);
+        self.record_access(address..address + 1, Access::Write);
+        self.occupancy[address / 64] |= 1 << (address % 64);
+        if let Some(written) = self.written.as_mut() {
+            written[address] |= field_specification.mask();
+        }
+        Ok(())
+    }
+
+    /// The word at `address`, of which an instruction reads `field_specification`. While
+    /// written bytes are tracked, a read of positions nothing wrote is recorded in
+    /// `uninitialized_reads`.
+    ///
+    /// ## Errors
+    /// - `AddressOutOfRange` when `address` is outside of memory.
+    pub(crate) fn read_field(&mut self, address: usize, field_specification: FieldSpec) -> Result<Word, MixError> {
+        let word = self.word_at(address)?;
+        self.record_access(address..address + 1, Access::Read);
+        let unwritten = match self.written.as_ref() {
+            Some(written) => field_specification.mask() & !written[address],
+            None => 0,
+        };
+        if unwritten != 0 {
+            let read = UninitializedRead {
+                pc: self.pc,
+                operation: self.current_operation(),
+                address,
+                positions: (0..=5).filter(|position| unwritten & (1 << position) != 0).collect(),
+            };
+            budget::record(&mut self.uninitialized_reads, read, self.budget.max_uninitialized_reads, &mut self.truncated.uninitialized_reads);
+        }
+        Ok(word)
+    }
+
+    /// Counts every access to memory from now on in a heatmap divided as `options` says,
+    /// replacing any heatmap already being recorded.
+    ///
+    /// ## Panics
+    /// - When either size of `options` is 0.
+    pub fn record_access_heatmap(&mut self, options: HeatmapOptions) {
+        self.heatmap = Some(AccessHeatmap::new(options));
+        self.register_invariant_check("heatmap", crate::heatmap::check_heatmap);
+    }
+
+    /// The accesses counted since `record_access_heatmap`, or `None` when none are.
+    pub fn access_heatmap(&self) -> Option<&AccessHeatmap> {
+        self.heatmap.as_ref()
+    }
+
+    /// Stops counting accesses, giving back the heatmap counted so far.
+    pub fn take_access_heatmap(&mut self) -> Option<AccessHeatmap> {
+        self.heatmap.take()
+    }
+
+    /// Publishes the state of the computer for other threads to read from now on, as
+    /// described in `mirror`, starting with its state now. Replaces any mirror already
+    /// enabled, whose readers stop seeing new states.
+    pub fn enable_mirror(&mut self, options: MirrorOptions) -> MirrorReader {
+        let (publisher, reader) = Publisher::new(self, options);
+        self.mirror.0 = Some(Box::new(publisher));
+        reader
+    }
+
+    /// Stops publishing, leaving readers with the last state published, and returns
+    /// whether a mirror was enabled.
+    pub fn disable_mirror(&mut self) -> bool {
+        self.mirror.0.take().is_some()
+    }
+
+    /// Counts an access to each word of `range` now, when a heatmap is being recorded.
+    pub(crate) fn record_access(&mut self, range: Range<usize>, access: Access) {
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record(self.instruction_cycles + self.stolen_cycles, range, access, self.budget.max_heatmap_cells);
+        }
+    }
+
+    /// Tracks which positions of each word have been written, so that loads, arithmetic,
+    /// and comparisons reading a field nothing wrote are recorded in
+    /// `uninitialized_reads`, down to the sign and each byte. Words occupied already count
+    /// as wholly written. This costs a byte for each word of memory.
+    pub fn track_written_bytes(&mut self) {
+        if self.written.is_none() {
+            let written = (0..self.memory_size()).map(|address| if self.is_occupied(address) { FULL_MASK } else { 0 }).collect();
+            self.written = Some(written);
+            self.note_writes(0..self.memory_size());
+            self.register_invariant_check("written", check_written);
+        }
+    }
+
+    pub fn tracks_written_bytes(&self) -> bool {
+        self.written.is_some()
+    }
+
+    /// Writes consecutive `words` to memory starting at `address`, marking each as occupied.
+    ///
+    /// ## Panics
+    /// Panics when the words don't fit between `address` and the end of memory.
+    pub fn load(&mut self, address: usize, words: &[Word]) {
+        for (offset, word) in words.iter().enumerate() {
+            self.write_mem(address + offset, *word);
+        }
+    }
+
+    /// Marks every word in `range` as occupied, for writes made to `memory` in bulk.
+    pub(crate) fn mark_occupied(&mut self, range: Range<usize>) {
+        self.note_writes(range.clone());
+        for address in range.start..range.end.min(self.memory_size()) {
+            self.occupancy[address / 64] |= 1 << (address % 64);
+            if let Some(written) = self.written.as_mut() {
+                written[address] = FULL_MASK;
+            }
+        }
+    }
+
+    /// Sets every word in `range` to +0 and marks them as unoccupied.
+    pub fn clear_range(&mut self, range: Range<usize>) {
+        self.note_writes(range.clone());
+        for address in range.start..range.end.min(self.memory_size()) {
+            self.memory[address] = Word::default();
+            self.occupancy[address / 64] &= !(1 << (address % 64));
+            if let Some(written) = self.written.as_mut() {
+                written[address] = 0;
+            }
+        }
+    }
+
+    /// Whether the word at `address` has been written since the last clear.
+    pub fn is_occupied(&self, address: usize) -> bool {
+        address < self.memory_size() && self.occupancy[address / 64] & (1 << (address % 64)) != 0
+    }
+
+    /// Iterates over the occupied words of memory in increasing address order. Only the
+    /// occupancy bitset is scanned, so the cost is proportional to the words in use rather
+    /// than to the size of memory.
+    pub fn iter_nonzero(&self) -> impl Iterator<Item = (usize, &Word)> {
+        self.iter_range(0..self.memory_size())
+    }
+
+    /// Iterates over the occupied words of memory whose addresses lie in `range`, in
+    /// increasing address order.
+    pub fn iter_range(&self, range: Range<usize>) -> impl Iterator<Item = (usize, &Word)> {
+        let end = range.end.min(self.memory_size());
+        let start = range.start.min(end);
+        let blocks = (start / 64)..end.div_ceil(64);
+        blocks.flat_map(move |block| {
+            let mut bits = self.occupancy[block];
+            std::iter::from_fn(move || {
+                if bits == 0 { return None; }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(block * 64 + bit)
+            })
+        })
+        .filter(move |address| (start..end).contains(address))
+        .map(move |address| (address, &self.memory[address]))
+    }
+
+    /// Finds the lowest occupied address whose word satisfies `predicate`.
+    pub fn find<P: FnMut(&Word) -> bool>(&self, mut predicate: P) -> Option<usize> {
+        self.iter_nonzero().find(|(_, word)| predicate(word)).map(|(address, _)| address)
+    }
+
+    /// The occupied addresses in `range` whose whole word has the signed value `value`, in
+    /// the computer's byte size.
+    pub fn search_value(&self, value: i64, range: Range<usize>) -> Vec<usize> {
+        self.iter_range(range)
+            .filter(|(_, word)| word.field_value_in_base(FieldSpec::WHOLE, self.byte_size) == value)
+            .map(|(address, _)| address)
+            .collect()
+    }
+
+    /// The occupied addresses in `range` whose word matches `pattern`, where `None`
+    /// matches any byte, and whose sign matches `sign` when given (`true` for +).
+    pub fn search_bytes(&self, pattern: &[Option<u8>; 5], sign: Option<bool>, range: Range<usize>) -> Vec<usize> {
+        self.iter_range(range)
+            .filter(|(_, word)| sign.is_none_or(|positive| word.positive == positive))
+            .filter(|(_, word)| pattern.iter().zip(word.bytes.iter()).all(|(want, byte)| want.is_none_or(|want| want == *byte)))
+            .map(|(address, _)| address)
+            .collect()
+    }
+
+    /// The occupied addresses in `range` holding an instruction whose address part, before
+    /// indexing, is `address`. Only words `decode_range` classifies as instructions count.
+    pub fn search_references(&self, address: usize, range: Range<usize>) -> Vec<usize> {
+        self.decode_range(range)
+            .filter(|(_, item)| matches!(item, DecodedItem::Instruction(instruction) if instruction.address == address as i64))
+            .map(|(location, _)| location)
+            .collect()
+    }
+
+    /// The occupied words in `range`, in order of address, each classified by the rules
+    /// in the `decode` module docs. Nothing is executed or allocated along the way, and
+    /// indexing isn't applied, so the registers don't matter.
+    pub fn decode_range(&self, range: Range<usize>) -> impl Iterator<Item = (usize, DecodedItem)> + '_ {
+        self.iter_range(range).map(move |(address, word)| {
+            let is_data = self.source_map.as_ref().is_some_and(|map| map.data.contains(&address));
+            let (opcode, field) = (word.opcode(), word.field());
+            let is_implemented = self.implements(opcode, field);
+            (address, decode::classify(word, is_data, is_implemented, self.byte_size))
+        })
+    }
+
+    /// Whether the core MIX instruction set implements the operation with `opcode` and
+    /// `field`, which is exactly when `decode_core` gives an instruction for it.
+    pub fn core_implements(opcode: u8, field: u8) -> bool {
+        let opcode = match Opcode::try_from(opcode) {
+            Ok(opcode) => opcode,
+            Err(_) => return false,
+        };
+        match opcode {
+            Opcode::SPECIAL => field <= SpecialOp::Halt.field(),
+            Opcode::SHIFT => field <= ShiftOp::RightCircular.field(),
+            Opcode::JBUS | Opcode::IOC | Opcode::IN | Opcode::OUT | Opcode::JRED => field <= UnitNumber::LAST,
+            Opcode::JMP => field <= JumpCondition::LessOrEqual.field(),
+            Opcode::MOVE => field <= MAX_MOVE_COUNT,
+            _ if (Opcode::ENTA..=Opcode::ENTX).contains(&opcode) => field <= EnterOp::EnterNegative.field(),
+            _ => true,
+        }
+    }
+
+    /// Whether the core instruction set or a registered extension implements the opcode
+    /// and field combination.
+    pub fn implements(&self, opcode: u8, field: u8) -> bool {
+        Computer::core_implements(opcode, field) || self.extensions.contains_key(&(opcode, field))
+    }
+
+    /// A fingerprint of the machine state: the registers, the overflow toggle, the
+    /// comparison indicator, `pc`, and every word of memory other than +0. Equal states,
+    /// as decided by `state_eq`, hash equal, and the hash is stable across runs and
+    /// platforms. The state is fed to 64-bit FNV-1a in that order, each word as its sign
+    /// byte (0 for +, 1 for -) followed by its five bytes, and memory words prefixed by
+    /// their address as a little-endian `u64`. Memory is visited through the occupancy
+    /// bitset, so the cost is proportional to the words in use, and words written straight
+    /// to the `memory` field aren't seen.
+    pub fn state_hash(&self) -> u64 {
+        self.state_hash_counted().0
+    }
+
+    /// Computes `state_hash`, along with the number of memory words visited.
+    pub(crate) fn state_hash_counted(&self) -> (u64, usize) {
+        let mut hasher = Fnv1a::new();
+        let write_word = |hasher: &mut Fnv1a, word: &Word| {
+            hasher.write(&[if word.positive { 0 } else { 1 }]);
+            hasher.write(&word.bytes);
+        };
+        for register in [&self.ra, &self.rx, &self.ri1, &self.ri2, &self.ri3,
+                         &self.ri4, &self.ri5, &self.ri6, &self.rj].iter() {
+            write_word(&mut hasher, register);
+        }
+        let flags = self.flags_word();
+        hasher.write(&flags.bytes[3..]);
+        hasher.write_u64(self.pc as u64);
+        let mut visited = 0;
+        for (address, word) in self.iter_nonzero() {
+            visited += 1;
+            if *word != Word::default() {
+                hasher.write_u64(address as u64);
+                write_word(&mut hasher, word);
+            }
+        }
+        (hasher.finish(), visited)
+    }
+
+    /// Whether `other` is in exactly the same state as this computer, comparing everything
+    /// `state_hash` covers, with +0 and -0 told apart.
+    pub fn state_eq(&self, other: &Computer) -> bool {
+        self.ra == other.ra && self.rx == other.rx
+            && self.ri1 == other.ri1 && self.ri2 == other.ri2 && self.ri3 == other.ri3
+            && self.ri4 == other.ri4 && self.ri5 == other.ri5 && self.ri6 == other.ri6
+            && self.rj == other.rj
+            && self.overflow_flag == other.overflow_flag
+            && self.comparison_flag == other.comparison_flag
+            && self.pc == other.pc
+            && self.memory[..] == other.memory[..]
+    }
+
+    /// The instruction at `pc`.
+    ///
+    /// ## Errors
+    /// - `ProgramCounterOutOfRange` when `pc` is outside of memory.
+    fn fetch(&self) -> Result<Word, MixError> { no_panic!({
+        self.memory.get(self.pc).copied()
+            .ok_or_else(|| MixError::ProgramCounterOutOfRange { pc: self.pc, memory_size: self.memory_size() })
+    })}
+
+    /// The signed value of the index register `index`, held in its sign and bytes 4 and 5,
+    /// or 0 when no index register is given.
+    ///
+    /// ## Errors
+    /// - `InvalidInstruction` when `index` is above 6.
+    fn decode_index(&self, index: &u8) -> Result<i64, MixError> {
+        let ri = match index {
+            0 => return Ok(0),
+            1 => &self.ri1,
+            2 => &self.ri2,
+            3 => &self.ri3,
+            4 => &self.ri4,
+            5 => &self.ri5,
+            6 => &self.ri6,
+            _ => return Err(self.invalid_index(*index)),
+        };
+        let value = ri.field_value_in_base(FieldSpec::of(4, 5), self.byte_size);
+        Ok(if ri.positive { value } else { -value })
+    }
+
+    /// The error of the instruction at `pc` naming `index`, which isn't an index register.
+    pub(crate) fn invalid_index(&self, index: u8) -> MixError {
+        let word = self.memory.get(self.pc).copied().unwrap_or_else(Word::default);
+        MixError::InvalidInstruction { pc: self.pc, word, error: DecodeError::InvalidIndex(index) }
+    }
+
+    /// Decodes `instruction` as `dispatch` says.
+    ///
+    /// ## Errors
+    /// - `UnimplementedInstruction` under `UnimplementedPolicy::Fault` when neither the
+    ///   core instruction set nor an extension implements the word.
+    /// - `InvalidInstruction` when `decode::check_word` rejects the word.
+    /// - `AddressOutOfRange` when the word refers to memory outside of memory.
+    fn prepare(&self, instruction: &Word) -> Result<Prepared, MixError> {
+        self.check_operands(instruction)?;
+        match self.dispatch {
+            Dispatch::Enum => match self.decode_core_as::<Op>(instruction.opcode(), instruction) {
+                Some(op) => Ok(Prepared::Op(op)),
+                None => self.decode(instruction).map(|instruction| Prepared::Op(Op::Boxed(instruction))),
+            },
+            #[cfg(any(test, feature = "dispatch-bench"))]
+            Dispatch::Boxed => self.decode(instruction).map(Prepared::Boxed),
+            #[cfg(any(test, feature = "dispatch-bench"))]
+            Dispatch::Table => {
+                let (opcode, field) = (instruction.opcode(), instruction.field());
+                let implemented = self.implements(opcode, field);
+                if !implemented && self.unimplemented_policy == UnimplementedPolicy::Fault {
+                    return Err(MixError::UnimplementedInstruction { pc: self.pc, opcode, field });
+                }
+                match crate::dispatch::OPCODE_TABLE.get(opcode as usize) {
+                    Some(handler) => Ok(Prepared::Table(*handler)),
+                    None => self.decode(instruction).map(Prepared::Boxed),
+                }
+            },
+        }
+    }
+
+    /// Checks what executing `instruction` needs of it besides an implementation: that its
+    /// index and field are valid, and that the memory it refers to, if any, is in memory.
+    fn check_operands(&self, instruction: &Word) -> Result<(), MixError> {
+        let operation = decode::check_word(instruction)
+            .map_err(|error| MixError::InvalidInstruction { pc: self.pc, word: *instruction, error })?;
+        if operation.is_some_and(|operation| operation.references_memory()) {
+            let address = DecodedInstruction::of_in_base(instruction, self.byte_size).address + self.decode_index(&instruction.index())?;
+            if address < 0 || address >= self.memory_size() as i64 {
+                return Err(MixError::AddressOutOfRange { pc: self.pc, address });
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes `instruction` as the word at `pc`, and nothing else: it isn't fetched, and
+    /// `pc` isn't moved on past it, nor are time, transfers, the trace, phases or stubs
+    /// involved. A jump leaves `pc` at its address.
+    ///
+    /// ## Errors
+    /// - Any error decoding or executing the instruction raises, among those
+    ///   `opcodes::Operation::errors` gives for its operation.
+    pub fn execute(&mut self, instruction: &Word) -> Result<(), MixError> {
+        self.prepare(instruction)?.execute_on(self, instruction)
+    }
+
+    pub(crate) fn decode(&self, instruction: &Word) -> Result<Box<dyn Instruction>, MixError> {
+        if let Some(inst) = self.decode_core(instruction) {
+            return Ok(inst);
+        }
+        let (opcode, field) = (instruction.opcode(), instruction.field());
+        if let Some(decoder) = self.extensions.get(&(opcode, field)).cloned() {
+            let address = (instruction.address_in_base(self.byte_size) as i64 + self.decode_index(&instruction.index())?) as usize;
+            return Ok(decoder(instruction, address));
+        }
+        match self.unimplemented_policy {
+            UnimplementedPolicy::NoOperation => Ok(boxed!(NoOperation)),
+            UnimplementedPolicy::Fault => Err(MixError::UnimplementedInstruction { pc: self.pc, opcode, field }),
+        }
+    }
+
+    /// Decodes the instructions of the core MIX instruction set.
+    ///
+    /// ## Returns
+    /// - `None` when the core instruction set doesn't implement the instruction.
+    fn decode_core(&self, instruction: &Word) -> Option<Box<dyn Instruction>> {
+        self.decode_core_as(instruction.opcode(), instruction)
+    }
+
+    /// Decodes the instructions of the core MIX instruction set as `D`, for the word
+    /// `instruction` whose opcode is `opcode`. The opcode is given apart so that a caller
+    /// knowing it in advance, as each entry of `dispatch::OPCODE_TABLE` does, is compiled
+    /// with only its own arm of the match.
+    ///
+    /// ## Returns
+    /// - `None` when the core instruction set doesn't implement the instruction.
+    #[inline(always)]
+    pub(crate) fn decode_core_as<D: Decoded>(&self, opcode: u8, instruction: &Word) -> Option<D> {
+        let (address, index, field) = (instruction.address_in_base(self.byte_size),
+                                       instruction.index(),
+                                       instruction.field());
+        if !Computer::core_implements(opcode, field) {
+            return None;
+        }
+
+        // Handle the index register
+        let offset_address = (address as i64 + self.decode_index(&index).ok()?) as usize;
+        // `decode::check_word` has checked the field of the operations taking a field
+        // specification, and the others don't use it.
+        let field_specification = FieldSpec::from_byte(field).unwrap_or(FieldSpec::WHOLE);
+        let positive = instruction.positive;
+        let field = instruction.field();
+        let signed_address = offset_address as i64 - if positive { 0 } else { 2 * address as i64 };
+        // `prepare` has checked that this is in memory for the operations referring to it.
+        let memory_address = signed_address as usize;
+
+
+        let opcode = Opcode::try_from(opcode).ok()?;
+        let inst: D = match opcode {
+            Opcode::NOP => decoded!(NoOperation),
+            Opcode::ADD => decoded!(Add, memory_address, field_specification),
+            Opcode::SUB => decoded!(Sub, memory_address, field_specification),
+            Opcode::MUL => decoded!(Mult, memory_address, field_specification),
+            Opcode::DIV => decoded!(Div, memory_address, field_specification),
+            Opcode::SPECIAL => match SpecialOp::try_from(field) {
+                Ok(SpecialOp::Num) => decoded!(Num),
+                Ok(SpecialOp::Char) => decoded!(Char),
+                Ok(SpecialOp::Halt) => decoded!(Halt),
+                _ => return None
+            },
+            Opcode::MOVE => decoded!(Move, memory_address, field),
+            Opcode::SHIFT => match ShiftOp::try_from(field) {
+                Ok(ShiftOp::LeftA) => decoded!(SLA, memory_address),
+                Ok(ShiftOp::RightA) => decoded!(SRA, memory_address),
+                Ok(ShiftOp::LeftAX) => decoded!(SLAX, memory_address),
+                Ok(ShiftOp::RightAX) => decoded!(SRAX, memory_address),
+                Ok(ShiftOp::LeftCircular) => decoded!(SLC, memory_address),
+                Ok(ShiftOp::RightCircular) => decoded!(SRC, memory_address),
+                _ => return None,
+            },
+            Opcode::LDA => decoded!(LoadA, memory_address, field_specification, false),
+            Opcode::LDX => decoded!(LoadX, memory_address, field_specification, false),
+            Opcode::LDAN => decoded!(LoadA, memory_address, field_specification, true),
+            Opcode::LDXN => decoded!(LoadX, memory_address, field_specification, true),
+            _ if (Opcode::LD1..=Opcode::LD6).contains(&opcode) =>
+                decoded!(LoadI, opcode.register_from(Opcode::LDA)?, memory_address, field_specification, false),
+            _ if (Opcode::LD1N..=Opcode::LD6N).contains(&opcode) =>
+                decoded!(LoadI, opcode.register_from(Opcode::LDAN)?, memory_address, field_specification, true),
+            Opcode::STA => decoded!(StoreA, memory_address, field_specification),
+            Opcode::STX => decoded!(StoreX, memory_address, field_specification),
+            _ if (Opcode::ST1..=Opcode::ST6).contains(&opcode) =>
+                decoded!(StoreI, opcode.register_from(Opcode::STA)?, memory_address, field_specification),
+            Opcode::STJ => decoded!(StoreJ, memory_address, field_specification),
+            Opcode::STZ => decoded!(StoreZ, memory_address, field_specification),
+            Opcode::JBUS if field <= UnitNumber::LAST => decoded!(JumpBusy, field, offset_address),
+            Opcode::IOC if field <= UnitNumber::LAST => decoded!(IoControl, field, signed_address),
+            Opcode::IN if field <= UnitNumber::LAST => decoded!(Input, field, memory_address),
+            Opcode::OUT if field <= UnitNumber::LAST => decoded!(Output, field, memory_address),
+            Opcode::JRED if field <= UnitNumber::LAST => decoded!(JumpReady, field, offset_address),
+            // A jump goes to M, indexed as any other address. One outside memory faults
+            // when it is fetched.
+            Opcode::JMP => match JumpCondition::try_from(field) {
+                Ok(JumpCondition::Always) => decoded!(Jmp, memory_address, true),
+                Ok(JumpCondition::SaveJ) => decoded!(Jmp, memory_address, false),
+                Ok(JumpCondition::Overflow) => decoded!(JmpO, memory_address, false),
+                Ok(JumpCondition::NoOverflow) => decoded!(JmpO, memory_address, true),
+                Ok(_) => decoded!(JmpC, memory_address, field),
+                Err(_) => return None,
+            },
+            Opcode::JA => decoded!(JmpA, memory_address, field),
+            Opcode::JX => decoded!(JmpX, memory_address, field),
+            _ if (Opcode::J1..=Opcode::J6).contains(&opcode) =>
+                decoded!(JmpI, opcode.register_from(Opcode::JA)?, memory_address, field),
+            Opcode::ENTA => match EnterOp::try_from(field) {
+                Ok(EnterOp::Increase) => decoded!(IncA, offset_address, positive, false),
+                Ok(EnterOp::Decrease) => decoded!(IncA, offset_address, positive, true),
+                Ok(EnterOp::Enter) => decoded!(EntA, offset_address, positive, false),
+                Ok(EnterOp::EnterNegative) => decoded!(EntA, offset_address, positive, true),
+                _ => return None,
+            },
+            Opcode::ENTX => match EnterOp::try_from(field) {
+                Ok(EnterOp::Increase) => decoded!(IncX, offset_address, positive, false),
+                Ok(EnterOp::Decrease) => decoded!(IncX, offset_address, positive, true),
+                Ok(EnterOp::Enter) => decoded!(EntX, offset_address, positive, false),
+                Ok(EnterOp::EnterNegative) => decoded!(EntX, offset_address, positive, true),
+                _ => return None,
+            },
+            _ if (Opcode::ENT1..=Opcode::ENT6).contains(&opcode) => {
+                let register = opcode.register_from(Opcode::ENTA)?;
+                match EnterOp::try_from(field) {
+                    Ok(EnterOp::Increase) => decoded!(IncI, register, offset_address, positive, false),
+                    Ok(EnterOp::Decrease) => decoded!(IncI, register, offset_address, positive, true),
+                    Ok(EnterOp::Enter) => decoded!(EntI, register, offset_address, positive, false),
+                    Ok(EnterOp::EnterNegative) => decoded!(EntI, register, offset_address, positive, true),
+                    _ => return None,
+                }
+            },
+            Opcode::CMPA => decoded!(CmpA, memory_address, field_specification),
+            Opcode::CMPX => decoded!(CmpX, memory_address, field_specification),
+            _ if (Opcode::CMP1..=Opcode::CMP6).contains(&opcode) =>
+                decoded!(CmpI, opcode.register_from(Opcode::CMPA)?, memory_address, field_specification),
+            _ => return None,
+        };
+
+        Some(inst)
+    }
+
+    /// Records a portability warning for `instruction` if it would behave differently
+    /// with 64-value bytes. Each pc is only ever reported once, and is not checked again
+    /// afterwards, so the cost of checking stays bounded over long runs. Once the warnings
+    /// fill the budget, nothing is checked.
+    fn check_portability_of(&mut self, instruction: &Word) {
+        if self.truncated.portability_warnings
+            || self.portability_warnings.iter().any(|warning| warning.pc == self.pc)
+            || decode::check_word(instruction).is_err() {
+            return;
+        }
+        let address = match self.decode_index(&instruction.index()) {
+            Ok(offset) => (instruction.address() as i64 + offset) as usize,
+            Err(_) => return,
+        };
+        if let Some(warning) = portability::check_instruction(self, instruction, address) {
+            budget::record(&mut self.portability_warnings, warning, self.budget.max_warnings, &mut self.truncated.portability_warnings);
+        }
+    }
+
+    /// Marks rA and rX as undefined, as `DIV` does when it overflows. Under
+    /// `PoisonPolicy::Fault` both are filled with `POISON`; otherwise their contents are
+    /// left alone.
+    pub fn poison_accumulators(&mut self) {
+        if self.poison_policy == PoisonPolicy::Off {
+            return;
+        }
+        if self.poison_policy == PoisonPolicy::Fault {
+            self.ra = POISON;
+            self.rx = POISON;
+        }
+        self.poisoned = [true; 2];
+    }
+
+    /// Whether `register` is undefined and hasn't been written since.
+    pub fn is_poisoned(&self, register: Accumulator) -> bool {
+        self.poisoned[register as usize]
+    }
+
+    /// Notes that the instruction at `pc` is about to read `register`. Under
+    /// `PoisonPolicy::Warn` a read of an undefined register is recorded once, after which
+    /// the register counts as defined.
+    ///
+    /// ## Errors
+    /// - `PoisonedRead` when `register` is undefined under `PoisonPolicy::Fault`.
+    pub fn read_accumulator(&mut self, register: Accumulator) -> Result<(), MixError> {
+        if !self.is_poisoned(register) {
+            return Ok(());
+        }
+        if self.poison_policy == PoisonPolicy::Fault {
+            return Err(MixError::PoisonedRead { pc: self.pc, register });
+        }
+        self.warn_undefined(UndefinedWarning {
+            pc: self.pc,
+            operation: self.current_operation(),
+            detail: format!("read {}, which is undefined after an overflowing DIV", register),
+        });
+        self.poisoned[register as usize] = false;
+        Ok(())
+    }
+
+    /// Notes that `register` was written as a whole, so that it is defined again.
+    pub fn define_accumulator(&mut self, register: Accumulator) {
+        self.poisoned[register as usize] = false;
+    }
+
+    /// Lets stores write the opcode byte of the instruction at `address`, for programs that
+    /// modify themselves on purpose.
+    pub fn allow_code_write(&mut self, address: usize) {
+        self.code_writes_allowed.insert(address);
+    }
+
+    /// Checks a store of `field` into `address` by the instruction at `pc` against
+    /// `code_write_policy`. Only stores including byte 5 into words the source map gives
+    /// as instructions are caught.
+    ///
+    /// ## Errors
+    /// - `CodeWrite` when the store is caught under `CodeWritePolicy::Fault`.
+    pub fn check_code_write(&mut self, address: usize, field: FieldSpec) -> Result<(), MixError> {
+        if !self.is_code_write(address, field) {
+            return Ok(());
+        }
+        if self.code_write_policy == CodeWritePolicy::Fault {
+            return Err(MixError::CodeWrite { pc: self.pc, address, field });
+        }
+        self.warn_code_write(CodeWriteWarning { pc: self.pc, operation: self.current_operation(), address, field });
+        Ok(())
+    }
+
+    /// Adds `warning` to `undefined_warnings`, within the budget.
+    pub(crate) fn warn_undefined(&mut self, warning: UndefinedWarning) {
+        budget::record(&mut self.undefined_warnings, warning, self.budget.max_warnings, &mut self.truncated.undefined_warnings);
+    }
+
+    /// Adds `warning` to `code_write_warnings`, within the budget.
+    fn warn_code_write(&mut self, warning: CodeWriteWarning) {
+        budget::record(&mut self.code_write_warnings, warning, self.budget.max_warnings, &mut self.truncated.code_write_warnings);
+    }
+
+    /// Whether `code_write_policy` catches a store of `field` into `address`.
+    fn is_code_write(&self, address: usize, field: FieldSpec) -> bool {
+        self.code_write_policy != CodeWritePolicy::Allow
+            && field.right() == 5
+            && !self.code_writes_allowed.contains(&address)
+            && self.source_map.as_ref().is_some_and(|map| map.code.contains(&address))
+    }
+
+    /// The `words` words of memory from `address`, for an instruction moving a block.
+    ///
+    /// ## Errors
+    /// - `BlockOutOfRange` when the block runs past the end of memory.
+    pub(crate) fn block_range(&self, address: usize, words: usize) -> Result<Range<usize>, MixError> {
+        match address.checked_add(words) {
+            Some(end) if end <= self.memory_size() => Ok(address..end),
+            _ => Err(MixError::BlockOutOfRange { pc: self.pc, address, words }),
+        }
+    }
+
+    /// The mnemonic of the instruction at `pc`, or `?` for one outside the MIX operation
+    /// table.
+    pub(crate) fn current_operation(&self) -> String {
+        self.memory.get(self.pc)
+            .and_then(opcodes::operation_of)
+            .map_or_else(|| "?".to_string(), |operation| operation.mnemonic.to_string())
+    }
+
+    pub fn state(&self) -> MachineState {
+        self.state
+    }
+
+    /// Whether the computer has executed `HLT`.
+    pub fn is_halted(&self) -> bool {
+        self.state == MachineState::Halted
+    }
+
+    /// Stops the computer, as `HLT` does, leaving `pc` alone.
+    pub fn halt(&mut self) {
+        self.state = MachineState::Halted;
+    }
+
+    /// Checks every invariant of `invariants`: those of the registers, and those registered
+    /// by the subsystems in use, in the order they were registered.
+    ///
+    /// ## Errors
+    /// - The first invariant found not to hold.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        invariants::check_registers(self)?;
+        let (start, end) = self.unchecked_writes.replace(NOTHING_WRITTEN);
+        for check in self.invariant_checks.iter() {
+            if let Err(violation) = (check.check)(self, start.min(end)..end) {
+                self.note_writes(start.min(end)..end);
+                return Err(violation);
+            }
+        }
+        Ok(())
+    }
+
+    /// Has `check_invariants` run `check` from now on, in place of any check registered
+    /// as `name` already. The checks are kept on reset and by clones of the computer.
+    pub fn register_invariant_check(&mut self, name: &'static str, check: InvariantCheckFn) {
+        match self.invariant_checks.iter_mut().find(|registered| registered.name == name) {
+            Some(registered) => registered.check = check,
+            None => self.invariant_checks.push(InvariantCheck { name, check }),
+        }
+    }
+
+    /// The names of the invariant checks registered, in the order they run.
+    pub fn invariant_checks(&self) -> Vec<&'static str> {
+        self.invariant_checks.iter().map(|check| check.name).collect()
+    }
+
+    /// Adds `range` to the addresses the next invariant check looks at.
+    fn note_writes(&self, range: Range<usize>) {
+        if !range.is_empty() {
+            let (start, end) = self.unchecked_writes.get();
+            self.unchecked_writes.set((start.min(range.start), end.max(range.end)));
+        }
+    }
+
+    /// Checks the invariants after the step of the instruction `word` at `pc`, which found
+    /// the flags as `flags`. A step running a stub isn't held to the flags of its jump.
+    fn verify_step(&self, pc: usize, word: &Word, flags: (bool, ComparisonFlag), stubbed: bool) -> Result<(), MixError> {
+        let violated = |violation| MixError::InvariantViolated { pc, violation };
+        if !stubbed && (flags.0 != self.overflow_flag || flags.1 != self.comparison_flag) {
+            let affects = self.decode(word).map_or(&[][..], |instruction| instruction.affects());
+            let operation = opcodes::operation_of(word).map_or_else(|| format!("[{}]", word), |operation| operation.mnemonic.to_string());
+            invariants::check_flags(self, flags, &operation, affects).map_err(violated)?;
+        }
+        self.check_invariants().map_err(violated)
+    }
+
+    /// Executes the instruction at `pc`, leaving `pc` at the word after it, or at M when it
+    /// jumps. `HLT` leaves it after the `HLT`, where a restart resumes. When the
+    /// instruction can't proceed because its unit is busy for reasons outside of this
+    /// computer, `blocked_on` names the unit and `pc` is left unchanged so that the
+    /// instruction is retried by the next step.
+    ///
+    /// ## Errors
+    /// - `ProgramCounterOutOfRange` when `pc` is outside of memory.
+    /// - Any error of the instruction itself.
+    /// - `InvariantViolated` when `verify_invariants` is on and the step broke one.
+    pub fn step(&mut self) -> Result<(), MixError> {
+        self.blocked_on = None;
+        let instruction = self.fetch()?;
+        let pc = self.pc;
+        self.record_access(pc..pc + 1, Access::Fetch);
+        self.observe(|_| Phase::Fetched { pc, word: instruction });
+        if self.check_portability {
+            self.check_portability_of(&instruction);
+        }
+        let decoded_instruction = match self.prepare(&instruction) {
+            Ok(decoded_instruction) => decoded_instruction,
+            Err(error) => {
+                self.observe(|_| Phase::DecodeFailed { pc, word: instruction, error: error.clone() });
+                return Err(error);
+            },
+        };
+        self.observe(|computer| Phase::Decoded {
+            pc, word: instruction, breakdown: DecodedInstruction::of_in_base(&instruction, self.byte_size), mnemonic: computer.current_operation(),
+        });
+        let snapshot = self.phase_observer.as_ref().map(|_| Snapshot::of(self));
+        let watched_before = self.trace.as_ref().and_then(|trace| trace.watched(self));
+        let flags_before = (self.overflow_flag, self.comparison_flag);
+        self.words_moved = 0;
+        self.jumped = false;
+        let mut result = decoded_instruction.execute_on(self, &instruction);
+        let stubbed = result.is_ok() && self.jumped && self.stubs.contains_key(&self.pc);
+        if stubbed {
+            result = self.call_stub(pc);
+        }
+        self.instruction_cycles += self.cost_model.executed_cost(&instruction, self.words_moved);
+        let transferred = self.advance_transfers().and_then(|()| if self.is_halted() { self.flush_outputs() } else { Ok(()) });
+        if let (Ok(()), Err(error)) = (&result, transferred) {
+            result = Err(error);
+        }
+        if self.verify_invariants && matches!(result, Ok(()) | Err(MixError::UnitBlocked { .. })) {
+            if let Err(error) = self.verify_step(pc, &instruction, flags_before, stubbed) {
+                result = Err(error);
+            }
+        }
+        let control = match &result {
+            Err(MixError::UnitBlocked { unit, .. }) => ControlFlow::Blocked { unit: *unit },
+            Err(error) => ControlFlow::Failed(error.clone()),
+            Ok(()) if self.is_halted() => ControlFlow::Halted,
+            Ok(()) if stubbed || self.jumped => ControlFlow::Jump { to: self.pc },
+            Ok(()) => ControlFlow::Next,
+        };
+        match &result {
+            Err(MixError::UnitBlocked { unit, .. }) => self.block_on(*unit),
+            Err(_) => {},
+            Ok(()) if stubbed || self.jumped => {},
+            Ok(()) => self.pc += 1,
+        }
+        if self.trace.as_ref().is_some_and(|trace| trace.steps.is_some()) {
+            let mut trace = self.trace.take().unwrap();
+            trace.record_step(self, pc, instruction, watched_before);
+            self.trace = Some(trace);
+        }
+        if let Some(mut mirror) = self.mirror.0.take() {
+            let ended = self.is_halted() || matches!(control, ControlFlow::Failed(_));
+            mirror.after_step(self, control != ControlFlow::Next, ended);
+            self.mirror.0 = Some(mirror);
+        }
+        if let Some(snapshot) = snapshot {
+            self.observe(|computer| Phase::Executed { pc, changes: snapshot.delta(computer), control });
+        }
+        match result {
+            Err(MixError::UnitBlocked { .. }) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Runs `stub` in place of the subroutine at `entry` whenever a jump reaches it, then
+    /// returns to the caller as the subroutine would under the `STJ` linkage (see
+    /// `StubLinkage`), with whatever the stub left in the registers and memory. No word of
+    /// the subroutine but its `STJ` is executed. Stubs are kept on reset and shared with
+    /// clones of the computer, and replace any stub at `entry` already.
+    ///
+    /// A stubbed call is part of the step of the jump reaching it: the step's phases show
+    /// its changes together with the jump's, and the trace, if any, gets a
+    /// `StubInvoked` record of it.
+    ///
+    /// ## Errors
+    /// - `StubLinkage`, from the jump, when the word at `entry` isn't an `STJ`.
+    /// - Any error the stub returns.
+    pub fn stub_subroutine(&mut self, entry: usize, stub: HostStub) {
+        self.stub_subroutine_with(entry, StubLinkage::Stj, stub);
+    }
+
+    /// As `stub_subroutine`, for a subroutine linked by `linkage`.
+    pub fn stub_subroutine_with(&mut self, entry: usize, linkage: StubLinkage, stub: HostStub) {
+        self.stubs.insert(entry, Stub { linkage, call: Rc::new(RefCell::new(stub)) });
+    }
+
+    /// Removes the stub at `entry`, so that jumps there run the subroutine again,
+    /// returning whether there was one.
+    pub fn unstub_subroutine(&mut self, entry: usize) -> bool {
+        self.stubs.remove(&entry).is_some()
+    }
+
+    /// Calls the stub at `pc`, which the jump at `caller` reached, leaving `pc` at the
+    /// address it returns to.
+    fn call_stub(&mut self, caller: usize) -> Result<(), MixError> {
+        let entry = self.pc;
+        let stub = self.stubs[&entry].clone();
+        let return_to = self.rj.field_value_in_base(FieldSpec::WHOLE, self.byte_size) as usize;
+        if stub.linkage == StubLinkage::Stj {
+            let prologue = self.memory[entry];
+            if prologue.opcode() != Opcode::STJ.code() {
+                return Err(MixError::StubLinkage { pc: caller, entry, word: prologue });
+            }
+            self.execute(&prologue)?;
+        }
+        (stub.call.borrow_mut())(self)?;
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceRecord::StubInvoked(StubInvocation { entry, caller, returned_to: return_to }), self.budget.max_trace_records);
+        }
+        self.pc = return_to;
+        Ok(())
+    }
+
+    /// Sets the observer called with each phase of every step from now on, as described
+    /// in `phases`, in place of any observer set before. The observer is kept on reset,
+    /// and shared with clones of the computer.
+    pub fn set_phase_observer(&mut self, observer: Box<dyn FnMut(Phase)>) {
+        self.phase_observer = Some(Rc::new(RefCell::new(observer)));
+    }
+
+    /// Removes the phase observer, returning whether there was one.
+    pub fn clear_phase_observer(&mut self) -> bool {
+        self.phase_observer.take().is_some()
+    }
+
+    /// Calls the phase observer, if any, with the phase `phase` makes of the computer.
+    fn observe<F: FnOnce(&Computer) -> Phase>(&self, phase: F) {
+        if let Some(observer) = &self.phase_observer {
+            let phase = phase(self);
+            (observer.borrow_mut())(phase);
+        }
+    }
+
+    /// Runs until `HLT`.
+    ///
+    /// ## Errors
+    /// - `ProgramCounterOutOfRange` when control leaves memory, as by a jump past its end.
+    /// - `UnitBlocked` when the program waits on a unit that only another machine could
+    ///   make ready, since it would otherwise wait forever.
+    pub fn run(&mut self) -> Result<(), MixError> {
+        while !self.is_halted() {
+            self.step()?;
+            if let Some(unit) = self.blocked_on {
+                return Err(MixError::UnitBlocked { pc: self.pc, unit });
+            }
+        }
+        Ok(())
+    }
+
+}
+
+/// The state only the computer's own code changes, open to tests of `check_invariants` so
+/// that they can break each invariant.
+#[cfg(test)]
+impl Computer {
+    pub(crate) fn occupancy_mut(&mut self) -> &mut Vec<u64> {
+        &mut self.occupancy
+    }
+
+    /// The written positions when tracked, all of memory counting as written since.
+    pub(crate) fn written_mut(&mut self) -> Option<&mut Vec<u8>> {
+        self.note_writes(0..self.memory_size());
+        self.written.as_mut()
+    }
+
+    pub(crate) fn transfers_mut(&mut self) -> &mut Vec<Transfer> {
+        &mut self.transfers
+    }
+}
+
+/// Checks that the occupancy bitset covers memory and marks nothing past its end.
+fn check_occupancy(computer: &Computer, _written: Range<usize>) -> Result<(), InvariantViolation> {
+    let memory_size = computer.memory_size();
+    let expected = memory_size.div_ceil(64);
+    if computer.occupancy.len() != expected {
+        return Err(InvariantViolation::OccupancyLength { found: computer.occupancy.len(), expected });
+    }
+    let past_end = match (computer.occupancy.last(), memory_size % 64) {
+        (Some(last), used) if used != 0 => last & !((1u64 << used) - 1),
+        _ => 0,
+    };
+    if past_end != 0 {
+        let address = (expected - 1) * 64 + past_end.trailing_zeros() as usize;
+        return Err(InvariantViolation::OccupiedPastEnd { address, memory_size });
+    }
+    Ok(())
+}
+
+/// Checks that the written positions cover memory, and that those of each word written
+/// since the last check are positions of a word.
+fn check_written(computer: &Computer, written: Range<usize>) -> Result<(), InvariantViolation> {
+    let Some(masks) = computer.written.as_ref() else { return Ok(()) };
+    if masks.len() != computer.memory_size() {
+        return Err(InvariantViolation::WrittenLength { found: masks.len(), expected: computer.memory_size() });
+    }
+    let end = written.end.min(masks.len());
+    match (written.start.min(end)..end).find(|address| masks[*address] & !FULL_MASK != 0) {
+        Some(address) => Err(InvariantViolation::WrittenMask { address, found: masks[address] }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the words each transfer in progress will copy lie in memory.
+fn check_transfers(computer: &Computer, _written: Range<usize>) -> Result<(), InvariantViolation> {
+    let memory_size = computer.memory_size();
+    match computer.transfers.iter().find(|transfer| transfer.output.as_ref().is_some_and(|range| range.end > memory_size)) {
+        Some(transfer) => Err(InvariantViolation::TransferOutOfMemory {
+            unit: transfer.unit, range: transfer.output.clone().unwrap_or_default(), memory_size,
+        }),
+        None => Ok(()),
+    }
+}
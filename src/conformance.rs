@@ -0,0 +1,373 @@
+//! Runs a MIXAL source through both this crate and GNU MDK and compares what they end
+//! with, built with the `mdk` feature. MDK is run as `mixasm` and then `mixvm --run
+//! --dump`, found on `PATH`; when they aren't there, `mdk_available` says so and callers
+//! skip the comparison.
+//!
+//! Both sides are normalized to an `Outcome`: whether the program halted, the signed value
+//! of each register, the overflow toggle, and the comparison indicator. Values rather
+//! than bytes are compared, since MDK has bytes of 64 values. Words beyond the range of
+//! such bytes are a deliberate difference from MDK, and show up as divergences. Neither
+//! side's printer output is compared, since this crate has no line printer yet.
+//!
+//! A program that diverges can be cut down with `minimize`, which keeps removing and
+//! simplifying lines for as long as the divergence persists.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use crate::assembler;
+use crate::computer::{Computer, ComparisonFlag};
+use crate::fingerprint::Fnv1a;
+use crate::instruction_functions::digits_to_value;
+use crate::portability::PORTABLE_BYTE_SIZE;
+use crate::word::Word;
+
+/// The registers of an `Outcome`, in the order they are compared.
+pub const REGISTERS: [&str; 9] = ["rA", "rX", "rI1", "rI2", "rI3", "rI4", "rI5", "rI6", "rJ"];
+
+/// The most instructions this crate executes before giving up on a program.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// How long either MDK program may run.
+pub const MDK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a program ended with, on either side.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub halted: bool,
+    /// The signed value of each register in `REGISTERS`.
+    pub registers: BTreeMap<String, i64>,
+    pub overflow: bool,
+    pub comparison: ComparisonFlag,
+}
+
+impl Outcome {
+    /// The first way in which `theirs` differs from this outcome, in the order halt
+    /// status, `REGISTERS`, overflow, comparison.
+    pub fn first_divergence(&self, theirs: &Outcome) -> Option<String> {
+        if self.halted != theirs.halted {
+            return Some(format!("halted: {} here, {} in MDK", self.halted, theirs.halted));
+        }
+        for name in REGISTERS.iter() {
+            let (ours, mdk) = (self.registers.get(*name), theirs.registers.get(*name));
+            if ours != mdk {
+                return Some(format!("{}: {:?} here, {:?} in MDK", name, ours, mdk));
+            }
+        }
+        if self.overflow != theirs.overflow {
+            return Some(format!("overflow: {} here, {} in MDK", self.overflow, theirs.overflow));
+        }
+        if self.comparison != theirs.comparison {
+            return Some(format!("comparison: {} here, {} in MDK", self.comparison, theirs.comparison));
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum MdkError {
+    /// `mixasm` or `mixvm` isn't on `PATH`.
+    NotFound(&'static str),
+    /// The program ran for longer than `MDK_TIMEOUT` and was killed.
+    Timeout(&'static str),
+    /// The program exited with `code`, or was killed by a signal when there is none.
+    Failed { program: &'static str, code: Option<i32>, stderr: String },
+    /// The dump printed by `mixvm` couldn't be read.
+    Dump(String),
+    /// This crate couldn't assemble the source.
+    Assemble(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for MdkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MdkError::NotFound(program) => write!(f, "{} is not on PATH", program),
+            MdkError::Timeout(program) => write!(f, "{} took longer than {:?}", program, MDK_TIMEOUT),
+            MdkError::Failed { program, code: Some(code), stderr } => write!(f, "{} exited with {}: {}", program, code, stderr.trim()),
+            MdkError::Failed { program, code: None, stderr } => write!(f, "{} was killed: {}", program, stderr.trim()),
+            MdkError::Dump(message) => write!(f, "the dump of mixvm couldn't be read: {}", message),
+            MdkError::Assemble(message) => write!(f, "{}", message),
+            MdkError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for MdkError {
+    fn from(error: io::Error) -> Self {
+        MdkError::Io(error)
+    }
+}
+
+/// A hash of `source` that is the same in every run, for naming files after it.
+fn content_hash(source: &str) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(source.as_bytes());
+    hasher.finish()
+}
+
+/// The signed value of `word`, with bytes of `byte_size` values, read from `bytes` of it.
+fn signed_value(word: &Word, bytes: std::ops::Range<usize>, byte_size: u16) -> i64 {
+    let magnitude = digits_to_value(&word.bytes[bytes], byte_size) as i64;
+    if word.positive { magnitude } else { -magnitude }
+}
+
+/// The outcome of `computer` once it has stopped.
+pub fn outcome_of(computer: &Computer) -> Outcome {
+    let byte_size = computer.byte_size();
+    let words = [&computer.ra, &computer.rx, &computer.ri1, &computer.ri2, &computer.ri3,
+                 &computer.ri4, &computer.ri5, &computer.ri6, &computer.rj];
+    let registers = REGISTERS.iter().zip(words.iter()).enumerate()
+        .map(|(i, (name, word))| {
+            let bytes = if i < 2 { 0..5 } else { 3..5 };
+            (name.to_string(), signed_value(word, bytes, byte_size))
+        })
+        .collect();
+    Outcome {
+        halted: computer.is_halted(),
+        registers,
+        overflow: computer.overflow_flag,
+        comparison: computer.comparison_flag,
+    }
+}
+
+/// Assembles and runs `source` in this crate for at most `STEP_LIMIT` instructions. A
+/// program that faults or runs out of steps hasn't halted.
+///
+/// ## Errors
+/// - `Assemble` when the source doesn't assemble.
+pub fn run_here(source: &str) -> Result<Outcome, MdkError> {
+    let program = assembler::assemble(source).map_err(|error| MdkError::Assemble(error.to_string()))?;
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    for _ in 0..STEP_LIMIT {
+        if computer.is_halted() || computer.step().is_err() || computer.blocked_on().is_some() {
+            break;
+        }
+    }
+    Ok(outcome_of(&computer))
+}
+
+/// Reads the register dump `mixvm --dump` prints after a run, which gives each register
+/// as its name and a colon, a sign, and its bytes of 64 values, followed by the value in
+/// parentheses, along with `Overflow: T|F` and `Cmp: L|E|G`:
+///
+/// ```text
+/// rA: + 00 00 00 00 05 (0000000005)
+/// rJ: + 00 00 (0000)    rI1: + 00 01 (0001)
+/// Overflow: F
+/// Cmp: E
+/// ```
+///
+/// Registers may share a line, and anything else in the output is skipped.
+///
+/// ## Errors
+/// - `Dump` naming the first register or indicator missing or unreadable.
+pub fn parse_mdk_dump(text: &str, halted: bool) -> Result<Outcome, MdkError> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut registers = BTreeMap::new();
+    let (mut overflow, mut comparison) = (None, None);
+    for (i, token) in tokens.iter().enumerate() {
+        let name = match token.strip_suffix(':') {
+            Some(name) => name,
+            None => continue,
+        };
+        let next = tokens.get(i + 1).copied().unwrap_or("");
+        if REGISTERS.contains(&name) {
+            let positive = match next {
+                "+" => true,
+                "-" => false,
+                _ => return Err(MdkError::Dump(format!("{} has no sign", name))),
+            };
+            let digits: Vec<u8> = tokens[i + 2..].iter()
+                .take_while(|token| token.chars().all(|c| c.is_ascii_digit()))
+                .map(|token| token.parse::<u8>().map_err(|_| MdkError::Dump(format!("{} has a bad byte {}", name, token))))
+                .collect::<Result<_, _>>()?;
+            let magnitude = digits_to_value(&digits, PORTABLE_BYTE_SIZE as u16) as i64;
+            registers.insert(name.to_string(), if positive { magnitude } else { -magnitude });
+        } else if name == "Overflow" {
+            overflow = Some(next == "T");
+        } else if name == "Cmp" {
+            comparison = Some(match next {
+                "L" => ComparisonFlag::less,
+                "E" => ComparisonFlag::equal,
+                "G" => ComparisonFlag::greater,
+                _ => return Err(MdkError::Dump(format!("{} is not a comparison", next))),
+            });
+        }
+    }
+    if let Some(name) = REGISTERS.iter().find(|name| !registers.contains_key(**name)) {
+        return Err(MdkError::Dump(format!("{} is missing", name)));
+    }
+    Ok(Outcome {
+        halted,
+        registers,
+        overflow: overflow.ok_or(MdkError::Dump("Overflow is missing".to_string()))?,
+        comparison: comparison.ok_or(MdkError::Dump("Cmp is missing".to_string()))?,
+    })
+}
+
+/// Whether `mixasm` and `mixvm` can both be run.
+pub fn mdk_available() -> bool {
+    ["mixasm", "mixvm"].iter().all(|program| {
+        Command::new(program).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok()
+    })
+}
+
+/// Runs `program` with `args` in `directory`, killing it after `MDK_TIMEOUT`.
+///
+/// ## Returns
+/// - Whether it exited successfully, and its standard output.
+///
+/// ## Errors
+/// - `NotFound` when it can't be started, and `Timeout` when it is killed.
+/// - `Failed` when it exits with a code other than 0 and `allow_failure` isn't set.
+fn run_program(program: &'static str, args: &[&str], directory: &Path, allow_failure: bool) -> Result<(bool, String), MdkError> {
+    let mut child = Command::new(program).args(args).current_dir(directory)
+        .stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| MdkError::NotFound(program))?;
+    let deadline = Instant::now() + MDK_TIMEOUT;
+    while child.try_wait()?.is_none() {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(MdkError::Timeout(program));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() && !allow_failure {
+        return Err(MdkError::Failed {
+            program,
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok((output.status.success(), stdout))
+}
+
+/// Assembles and runs `source` with MDK, in a fresh directory under the system's
+/// temporary directory. A run that `mixvm` reports as failed hasn't halted.
+///
+/// ## Errors
+/// - Any failure to assemble the source or run either program.
+pub fn run_mdk(source: &str) -> Result<Outcome, MdkError> {
+    let directory = std::env::temp_dir().join(format!("mixal-mdk-{}-{:016x}", std::process::id(), content_hash(source)));
+    fs::create_dir_all(&directory)?;
+    let result = (|| {
+        fs::write(directory.join("program.mixal"), source)?;
+        run_program("mixasm", &["program.mixal"], &directory, false)?;
+        let (halted, dump) = run_program("mixvm", &["--noinit", "--run", "program", "--dump"], &directory, true)?;
+        parse_mdk_dump(&dump, halted)
+    })();
+    let _ = fs::remove_dir_all(&directory);
+    result
+}
+
+/// Runs `source` on both sides.
+///
+/// ## Returns
+/// - The first divergence, if any.
+///
+/// ## Errors
+/// - Any error running either side.
+pub fn compare(source: &str) -> Result<Option<String>, MdkError> {
+    let ours = run_here(source)?;
+    let theirs = run_mdk(source)?;
+    Ok(ours.first_divergence(&theirs))
+}
+
+/// The simpler forms of a line, simplest first: without its remarks, field, or index.
+fn simplifications(line: &str) -> Vec<String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let has_label = !line.starts_with(char::is_whitespace);
+    let operand_at = if has_label { 2 } else { 1 };
+    let mut simpler = Vec::new();
+    if fields.len() <= operand_at {
+        return simpler;
+    }
+    let prefix = if has_label { format!("{}\t{}", fields[0], fields[1]) } else { format!("\t{}", fields[0]) };
+    let operand = fields[operand_at];
+    if let Some(open) = operand.find('(') {
+        simpler.push(format!("{}\t{}", prefix, &operand[..open]));
+    }
+    if let Some(comma) = operand.find(',') {
+        simpler.push(format!("{}\t{}", prefix, &operand[..comma]));
+    }
+    if fields.len() > operand_at + 1 {
+        simpler.push(format!("{}\t{}", prefix, operand));
+    }
+    simpler
+}
+
+/// Cuts `source` down to a smaller program for which `diverges` still holds, first by
+/// removing ever smaller runs of lines, then by simplifying the lines left, until
+/// nothing more can go. The `END` line and comments are never the reason a line stays.
+///
+/// ## Panics
+/// - When `diverges` doesn't hold for `source` itself.
+pub fn minimize<F: FnMut(&str) -> bool>(source: &str, mut diverges: F) -> String {
+    assert!(diverges(source), "[Error minimize] The source doesn't diverge to begin with");
+    let mut lines: Vec<String> = source.lines()
+        .filter(|line| !line.starts_with('*') && !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    let join = |lines: &[String]| lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+    if !diverges(&join(&lines)) {
+        lines = source.lines().map(|line| line.to_string()).collect();
+    }
+    loop {
+        let before = lines.clone();
+        let mut run = lines.len() / 2;
+        while run >= 1 {
+            let mut start = 0;
+            while start < lines.len() {
+                let end = (start + run).min(lines.len());
+                let is_end = |line: &String| line.split_whitespace().any(|field| field == "END");
+                if lines[start..end].iter().any(is_end) {
+                    start = end;
+                    continue;
+                }
+                let candidate: Vec<String> = lines[..start].iter().chain(lines[end..].iter()).cloned().collect();
+                if diverges(&join(&candidate)) {
+                    lines = candidate;
+                } else {
+                    start = end;
+                }
+            }
+            run /= 2;
+        }
+        for i in 0..lines.len() {
+            for simpler in simplifications(&lines[i]) {
+                let mut candidate = lines.clone();
+                candidate[i] = simpler;
+                if diverges(&join(&candidate)) {
+                    lines = candidate;
+                    break;
+                }
+            }
+        }
+        if lines == before {
+            return join(&lines);
+        }
+    }
+}
+
+/// Writes `source` to `directory` as `reproducer-HASH.mixal`, named for its contents so
+/// that the same reproducer is only ever saved once.
+///
+/// ## Returns
+/// - The path written.
+pub fn save_reproducer(directory: &Path, source: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(format!("reproducer-{:016x}.mixal", content_hash(source)));
+    fs::write(&path, source)?;
+    Ok(path)
+}
@@ -0,0 +1,360 @@
+//! Names for the numbers of the MIX instruction set: the opcodes, the field values that
+//! select an operation within an opcode, and the unit numbers of the standard devices,
+//! following TAOCP section 1.3.1. Everything that decodes, encodes, or describes an
+//! instruction takes its numbers from here rather than writing them out.
+//!
+//! `Opcode` names all 64 opcodes a byte holds, so it is complete. The operations selected
+//! by field are named by `SpecialOp`, `ShiftOp`, `JumpCondition`, `RegisterCondition`
+//! and `EnterOp`, and the extensions claim field values the core instruction set leaves
+//! free, so those enums, like `UnitNumber`, are `non_exhaustive`. Each converts to its
+//! number with `From` and back with `TryFrom`, which fails with `UnknownValue`.
+
+use std::convert::TryFrom;
+use std::fmt;
+use crate::opcodes;
+
+/// A number that no value of an enum of this module has.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct UnknownValue {
+    /// What the number was meant to be, such as `opcode`.
+    pub kind: &'static str,
+    pub value: u8,
+}
+
+impl fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} is not defined", self.kind, self.value)
+    }
+}
+
+/// Defines an enum of numbered values along with `ALL`, `name`, the accessor `$number`,
+/// and the conversions to and from `u8`.
+macro_rules! numbered {
+    ($(#[$meta:meta])* pub enum $name:ident: $kind:literal, $number:ident {
+        $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)*
+    }) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        #[repr(u8)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $value,)*
+        }
+
+        impl $name {
+            /// Every value, in order.
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            pub const fn $number(self) -> u8 {
+                self as u8
+            }
+
+            /// The name of the value, as written here.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)*
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> u8 {
+                value as u8
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = UnknownValue;
+
+            fn try_from(value: u8) -> Result<$name, UnknownValue> {
+                match value {
+                    $($value => Ok($name::$variant),)*
+                    _ => Err(UnknownValue { kind: $kind, value }),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+    };
+}
+
+numbered! {
+    /// The opcode of an instruction, its byte C. An opcode shared by several operations is
+    /// named for the family, and its field selects the operation: `SPECIAL` by `SpecialOp`,
+    /// `SHIFT` by `ShiftOp`, `JMP` by `JumpCondition`, `JA` to `JX` by `RegisterCondition`,
+    /// and `ENTA` to `ENTX` by `EnterOp`. Every other name is the mnemonic of the one
+    /// operation with the opcode.
+    #[allow(clippy::upper_case_acronyms)]
+    pub enum Opcode: "opcode", code {
+        NOP = 0,
+        ADD = 1,
+        SUB = 2,
+        MUL = 3,
+        DIV = 4,
+        /// `NUM`, `CHAR` and `HLT`.
+        SPECIAL = 5,
+        /// `SLA`, `SRA`, `SLAX`, `SRAX`, `SLC` and `SRC`.
+        SHIFT = 6,
+        MOVE = 7,
+        LDA = 8,
+        LD1 = 9,
+        LD2 = 10,
+        LD3 = 11,
+        LD4 = 12,
+        LD5 = 13,
+        LD6 = 14,
+        LDX = 15,
+        LDAN = 16,
+        LD1N = 17,
+        LD2N = 18,
+        LD3N = 19,
+        LD4N = 20,
+        LD5N = 21,
+        LD6N = 22,
+        LDXN = 23,
+        STA = 24,
+        ST1 = 25,
+        ST2 = 26,
+        ST3 = 27,
+        ST4 = 28,
+        ST5 = 29,
+        ST6 = 30,
+        STX = 31,
+        STJ = 32,
+        STZ = 33,
+        JBUS = 34,
+        IOC = 35,
+        IN = 36,
+        OUT = 37,
+        JRED = 38,
+        /// `JMP`, `JSJ`, `JOV`, `JNOV`, and the jumps on the comparison indicator.
+        JMP = 39,
+        /// The jumps on rA, `JAN` to `JANP`.
+        JA = 40,
+        J1 = 41,
+        J2 = 42,
+        J3 = 43,
+        J4 = 44,
+        J5 = 45,
+        J6 = 46,
+        JX = 47,
+        /// `INCA`, `DECA`, `ENTA` and `ENNA`.
+        ENTA = 48,
+        ENT1 = 49,
+        ENT2 = 50,
+        ENT3 = 51,
+        ENT4 = 52,
+        ENT5 = 53,
+        ENT6 = 54,
+        ENTX = 55,
+        CMPA = 56,
+        CMP1 = 57,
+        CMP2 = 58,
+        CMP3 = 59,
+        CMP4 = 60,
+        CMP5 = 61,
+        CMP6 = 62,
+        CMPX = 63,
+    }
+}
+
+impl Opcode {
+    /// The opcode of the operation `mnemonic`, as `HLT` has `SPECIAL`.
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        opcodes::lookup(mnemonic).and_then(|operation| Opcode::try_from(operation.code).ok())
+    }
+
+    /// Which of the registers A, I1 to I6, and X, counting from 0, an opcode of a family
+    /// of eight such as `LDA` to `LDX` works on, given the first of the family.
+    ///
+    /// ## Returns
+    /// - `None` when the opcode isn't one of the eight from `first`.
+    pub fn register_from(self, first: Opcode) -> Option<u8> {
+        let register = self.code().checked_sub(first.code())?;
+        if register < 8 { Some(register) } else { None }
+    }
+}
+
+numbered! {
+    /// The operations of `Opcode::SPECIAL`, by field. Fields 6 and up belong to the
+    /// extensions.
+    #[non_exhaustive]
+    pub enum SpecialOp: "special operation", field {
+        Num = 0,
+        Char = 1,
+        Halt = 2,
+        /// `FLOT`, of the floating point extension.
+        Flot = 6,
+        /// `FIX`, of the floating point extension.
+        Fix = 7,
+        /// `STF`, of the status flags extension.
+        StoreFlags = 8,
+        /// `LDF`, of the status flags extension.
+        LoadFlags = 9,
+        /// `AND`, of the binary extension.
+        And = 10,
+        /// `OR`, of the binary extension.
+        Or = 11,
+        /// `XOR`, of the binary extension.
+        Xor = 12,
+        /// `PUSH`, of the stack extension.
+        Push = 13,
+        /// `POP`, of the stack extension.
+        Pop = 14,
+    }
+}
+
+numbered! {
+    /// The shifts of `Opcode::SHIFT`, by field. Fields 6 and 7 belong to the binary
+    /// extension.
+    #[non_exhaustive]
+    pub enum ShiftOp: "shift", field {
+        /// `SLA`.
+        LeftA = 0,
+        /// `SRA`.
+        RightA = 1,
+        /// `SLAX`.
+        LeftAX = 2,
+        /// `SRAX`.
+        RightAX = 3,
+        /// `SLC`.
+        LeftCircular = 4,
+        /// `SRC`.
+        RightCircular = 5,
+        /// `SLB`, of the binary extension.
+        LeftBinary = 6,
+        /// `SRB`, of the binary extension.
+        RightBinary = 7,
+    }
+}
+
+numbered! {
+    /// The jumps of `Opcode::JMP`, by field.
+    #[non_exhaustive]
+    pub enum JumpCondition: "jump condition", field {
+        /// `JMP`.
+        Always = 0,
+        /// `JSJ`, which leaves rJ alone.
+        SaveJ = 1,
+        /// `JOV`.
+        Overflow = 2,
+        /// `JNOV`.
+        NoOverflow = 3,
+        /// `JL`.
+        Less = 4,
+        /// `JE`.
+        Equal = 5,
+        /// `JG`.
+        Greater = 6,
+        /// `JGE`.
+        GreaterOrEqual = 7,
+        /// `JNE`.
+        NotEqual = 8,
+        /// `JLE`.
+        LessOrEqual = 9,
+    }
+}
+
+numbered! {
+    /// The jumps of `Opcode::JA` to `Opcode::JX` on the sign of a register, by field.
+    #[non_exhaustive]
+    pub enum RegisterCondition: "register condition", field {
+        Negative = 0,
+        Zero = 1,
+        Positive = 2,
+        NonNegative = 3,
+        NonZero = 4,
+        NonPositive = 5,
+    }
+}
+
+numbered! {
+    /// The address transfers of `Opcode::ENTA` to `Opcode::ENTX`, by field.
+    #[non_exhaustive]
+    pub enum EnterOp: "address transfer", field {
+        /// `INC`.
+        Increase = 0,
+        /// `DEC`.
+        Decrease = 1,
+        /// `ENT`.
+        Enter = 2,
+        /// `ENN`.
+        EnterNegative = 3,
+    }
+}
+
+/// The unit number of a device, the field of an input-output instruction, by the
+/// standard assignments of TAOCP section 1.3.1.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum UnitNumber {
+    /// Magnetic tape `i`, for `i` from 0 to 7.
+    Tape(u8),
+    /// Disk or drum `i`, for `i` from 0 to 7.
+    Disk(u8),
+    CardReader,
+    CardPunch,
+    Printer,
+    Typewriter,
+    PaperTape,
+}
+
+impl UnitNumber {
+    /// The number of tape units, and of disk units.
+    pub const UNITS_OF_A_KIND: u8 = 8;
+    /// The highest unit number.
+    pub const LAST: u8 = 20;
+
+    /// The unit number.
+    ///
+    /// ## Panics
+    /// - In debug builds, for a tape or disk above 7.
+    pub const fn number(self) -> u8 {
+        match self {
+            UnitNumber::Tape(i) => {
+                debug_assert!(i < UnitNumber::UNITS_OF_A_KIND);
+                i
+            },
+            UnitNumber::Disk(i) => {
+                debug_assert!(i < UnitNumber::UNITS_OF_A_KIND);
+                UnitNumber::UNITS_OF_A_KIND + i
+            },
+            UnitNumber::CardReader => 16,
+            UnitNumber::CardPunch => 17,
+            UnitNumber::Printer => 18,
+            UnitNumber::Typewriter => 19,
+            UnitNumber::PaperTape => 20,
+        }
+    }
+}
+
+impl From<UnitNumber> for u8 {
+    fn from(unit: UnitNumber) -> u8 {
+        unit.number()
+    }
+}
+
+impl TryFrom<u8> for UnitNumber {
+    type Error = UnknownValue;
+
+    fn try_from(value: u8) -> Result<UnitNumber, UnknownValue> {
+        let kind = UnitNumber::UNITS_OF_A_KIND;
+        match value {
+            _ if value < kind => Ok(UnitNumber::Tape(value)),
+            _ if value < 2 * kind => Ok(UnitNumber::Disk(value - kind)),
+            16 => Ok(UnitNumber::CardReader),
+            17 => Ok(UnitNumber::CardPunch),
+            18 => Ok(UnitNumber::Printer),
+            19 => Ok(UnitNumber::Typewriter),
+            20 => Ok(UnitNumber::PaperTape),
+            _ => Err(UnknownValue { kind: "unit number", value }),
+        }
+    }
+}
+
+/// The most words `MOVE` moves. Its count is the field, which a byte of 64 values holds up
+/// to 63; a word with a larger field isn't a core `MOVE`, and is left to the extensions.
+pub const MAX_MOVE_COUNT: u8 = 63;
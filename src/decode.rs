@@ -0,0 +1,111 @@
+//! What a word of memory holds, as far as tools reading a program without running it are
+//! concerned. Every such tool classifies words through `Computer::decode_range` (or, for
+//! a word on its own, `check_word`), so that they all agree on the rules, which are
+//! applied in this order:
+//!
+//! 1. A word the source map marks as data is `Data`, whatever its bytes.
+//! 2. A word whose index byte isn't 0 to 6, or whose field isn't a valid `L:R` for an
+//!    operation taking a field specification, is `Invalid`.
+//! 3. A word whose opcode and field the core instruction set or a registered extension
+//!    implements is an `Instruction`.
+//! 4. Any other word is `Unimplemented`.
+
+use std::fmt;
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::word::Word;
+use crate::field::FieldSpec;
+use crate::opcodes::{self, FieldRule, Operation};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The index byte doesn't name rI1 to rI6, or no index register with 0.
+    InvalidIndex(u8),
+    /// The field of an operation taking a field specification isn't `L:R` with
+    /// `L <= R <= 5`.
+    InvalidField { opcode: u8, field: u8 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::InvalidIndex(index) => write!(f, "{} is not an index register", index),
+            DecodeError::InvalidField { opcode, field } =>
+                write!(f, "{}:{} is not a field for opcode {}", field / 8, field % 8, opcode),
+        }
+    }
+}
+
+/// The parts of an instruction word, before any indexing.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DecodedInstruction {
+    /// The operation in the MIX operation table, or `None` for an extension instruction.
+    pub operation: Option<&'static Operation>,
+    /// The signed address part.
+    pub address: i64,
+    pub index: u8,
+    pub field: u8,
+    pub opcode: u8,
+}
+
+impl DecodedInstruction {
+    /// The parts of `word`, with the operation the MIX operation table gives it.
+    pub fn of(word: &Word) -> DecodedInstruction {
+        DecodedInstruction::of_in_base(word, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `of`, for a word whose bytes hold `byte_size` values.
+    pub fn of_in_base(word: &Word, byte_size: u16) -> DecodedInstruction {
+        let address = word.address_in_base(byte_size) as i64;
+        DecodedInstruction {
+            operation: opcodes::operation_of(word),
+            address: if word.positive { address } else { -address },
+            index: word.index(),
+            field: word.field(),
+            opcode: word.opcode(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodedItem {
+    Instruction(DecodedInstruction),
+    Data(Word),
+    Unimplemented { opcode: u8, field: u8 },
+    Invalid(DecodeError),
+}
+
+/// Checks the index and field of `word` (rule 2), which need nothing but the word.
+///
+/// ## Returns
+/// - The operation `word` encodes in the MIX operation table, if any.
+///
+/// ## Errors
+/// - `InvalidIndex` when the index byte is above 6.
+/// - `InvalidField` when the operation takes a field specification and the field isn't one.
+pub fn check_word(word: &Word) -> Result<Option<&'static Operation>, DecodeError> {
+    if word.index() > 6 {
+        return Err(DecodeError::InvalidIndex(word.index()));
+    }
+    let operation = opcodes::operation_of(word);
+    let field = word.field();
+    if operation.is_some_and(|operation| matches!(operation.field, FieldRule::Default(_))) && FieldSpec::from_byte(field).is_err() {
+        return Err(DecodeError::InvalidField { opcode: word.opcode(), field });
+    }
+    Ok(operation)
+}
+
+/// Classifies `word`, whose bytes hold `byte_size` values, by the rules in the module docs,
+/// given whether it is marked as data and whether its opcode and field are implemented.
+pub(crate) fn classify(word: &Word, is_data: bool, is_implemented: bool, byte_size: u16) -> DecodedItem {
+    if is_data {
+        return DecodedItem::Data(*word);
+    }
+    let operation = match check_word(word) {
+        Ok(operation) => operation,
+        Err(error) => return DecodedItem::Invalid(error),
+    };
+    if !is_implemented {
+        return DecodedItem::Unimplemented { opcode: word.opcode(), field: word.field() };
+    }
+    DecodedItem::Instruction(DecodedInstruction { operation, ..DecodedInstruction::of_in_base(word, byte_size) })
+}
@@ -0,0 +1,83 @@
+//! The one source of random numbers in the crate. Anything that makes random choices,
+//! such as the randomized strategy of `analysis::check_equivalence`, takes a seed and
+//! draws from a `Prng` made from it, so that the same seed always makes the same choices,
+//! on every machine and with every version of the crate's dependencies. Nothing in the
+//! crate draws from the operating system or a thread-local generator, and the crate
+//! depends on no random number crate; `no_rand_dependency` checks the manifest for one.
+//!
+//! The generator is SplitMix64 (Steele, Lea and Flood, "Fast splittable pseudorandom
+//! number generators", 2014): a 64-bit state advanced by a fixed odd constant, with each
+//! output a mix of the state. It needs nothing but wrapping arithmetic on `u64`, so it
+//! would work as well without `std`.
+
+use core::ops::RangeInclusive;
+use crate::word::Word;
+
+/// The increment of the state, the golden ratio as a 64-bit fraction.
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A SplitMix64 generator. Clones go on to draw the same numbers as the original.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Prng {
+    state: u64,
+}
+
+impl Prng {
+    pub fn new(seed: u64) -> Prng {
+        Prng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A value below `bound`, every one equally likely.
+    ///
+    /// ## Panics
+    /// - When `bound` is 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "[Error Prng::below] There is no value below 0");
+        // Drawing from a whole number of multiples of `bound`, 2^64 less the remainder,
+        // keeps the small values from being more likely.
+        let skipped = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u64();
+            if value >= skipped {
+                return value % bound;
+            }
+        }
+    }
+
+    /// A value in `range`, every one equally likely.
+    ///
+    /// ## Panics
+    /// - When `range` is empty.
+    pub fn in_range(&mut self, range: RangeInclusive<i64>) -> i64 {
+        assert!(!range.is_empty(), "[Error Prng::in_range] The range is empty");
+        let span = (*range.end() as i128 - *range.start() as i128) as u64;
+        let offset = if span == u64::MAX { self.next_u64() } else { self.below(span + 1) };
+        (*range.start() as i128 + offset as i128) as i64
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+
+    /// One of `choices`, which isn't empty.
+    pub fn choose<'a, T>(&mut self, choices: &'a [T]) -> &'a T {
+        &choices[self.below(choices.len() as u64) as usize]
+    }
+
+    /// A word of either sign with bytes holding any of `byte_size` values.
+    pub fn word_in_base(&mut self, byte_size: u16) -> Word {
+        let mut bytes = [0; 5];
+        for byte in bytes.iter_mut() {
+            *byte = self.below(byte_size as u64) as u8;
+        }
+        Word::new(self.bool(), bytes)
+    }
+}
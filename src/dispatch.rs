@@ -0,0 +1,151 @@
+//! How `Computer::step` gets from a decoded word to executing it. Three strategies are
+//! kept behind one interface, `Prepared`, so that they can be compared on the same runs:
+//!
+//! - `Dispatch::Enum`: the word decodes to an `Op`, a flat enum holding the instruction,
+//!   and executing it is a `match` calling each instruction's `execute_on` directly.
+//! - `Dispatch::Boxed`: the word decodes to a `Box<dyn Instruction>`, executed through its
+//!   vtable. This is how extensions are always decoded.
+//! - `Dispatch::Table`: `OPCODE_TABLE` holds a function for each of the 64 opcodes, each
+//!   compiled with only its own arm of the decoder, and the word is passed to the function
+//!   for its opcode, which decodes and executes it in one go.
+//!
+//! All three decode through `Computer::decode_core_as`, so they can't disagree on what a
+//! word means, and `dispatch_parity` in the tests runs every opcode and field, and the
+//! fixture programs, through each and compares the machines they leave.
+//!
+//! `Enum` is the default. Measured with `dispatch_benchmark` (an ignored test, run with
+//! `cargo test --release -- --ignored dispatch_benchmark --nocapture`), in nanoseconds per
+//! step on the three workloads:
+//!
+//! | Workload          | Enum | Boxed | Table |
+//! |-------------------|------|-------|-------|
+//! | Arithmetic loop   |  125 |   141 |   127 |
+//! | Memory copy loop  |  118 |   134 |   117 |
+//! | Jump-heavy code   |  109 |   124 |   109 |
+//!
+//! Boxing costs an allocation on every step, and it shows: `Boxed` is the slowest by
+//! 12–15%. `Enum` and `Table` are within noise of each other, since either way the
+//! decoder's match is all that's left besides the instruction itself. `Enum` is kept as
+//! the default because it's the simpler of the two: extensions and the no-op policy fit
+//! into it as `Op::Boxed`, where the table needs its own check for unimplemented words.
+//!
+//! `Boxed` and `Table` are compiled only for tests and under the `dispatch-bench`
+//! feature, so the comparison can be repeated as the instruction set grows.
+
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::instruction::*;
+use crate::word::Word;
+
+/// What the decoder makes of a word, so that `Computer::decode_core_as` can build either
+/// an `Op` or a `Box<dyn Instruction>` from the same match.
+pub(crate) trait Decoded: Sized {
+    fn wrap<I: Instruction + 'static>(instruction: I) -> Self where Op: From<I>;
+}
+
+impl Decoded for Box<dyn Instruction> {
+    fn wrap<I: Instruction + 'static>(instruction: I) -> Self where Op: From<I> {
+        Box::new(instruction)
+    }
+}
+
+impl Decoded for Op {
+    fn wrap<I: Instruction + 'static>(instruction: I) -> Self where Op: From<I> {
+        Op::from(instruction)
+    }
+}
+
+macro_rules! ops {
+    ($($name:ident),*) => {
+        /// An instruction of the core instruction set, or any other boxed.
+        #[allow(clippy::upper_case_acronyms)]
+        pub(crate) enum Op {
+            $($name($name),)*
+            /// An instruction the core set doesn't decode: an extension, or the no-op
+            /// `UnimplementedPolicy::NoOperation` gives.
+            Boxed(Box<dyn Instruction>),
+        }
+
+        $(impl From<$name> for Op {
+            fn from(instruction: $name) -> Op {
+                Op::$name(instruction)
+            }
+        })*
+
+        impl Op {
+            pub(crate) fn execute_on(&self, computer: &mut Computer) -> Result<(), MixError> {
+                match self {
+                    $(Op::$name(instruction) => instruction.execute_on(computer),)*
+                    Op::Boxed(instruction) => instruction.execute_on(computer),
+                }
+            }
+        }
+    };
+}
+
+ops!(
+    NoOperation, Halt, LoadA, LoadX, LoadI, StoreA, StoreX, StoreI, StoreJ, StoreZ,
+    Add, Sub, Mult, Div, EntA, EntX, EntI, IncA, IncX, IncI,
+    CmpA, CmpX, CmpI, Jmp, JmpO, JmpC, JmpA, JmpX, JmpI, SLA,
+    SRA, SLAX, SRAX, SLC, SRC, JumpBusy, JumpReady, IoControl, Input, Output, Num, Char,
+    Move
+);
+
+/// How `Computer::step` executes each word (see the module documentation).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Dispatch {
+    #[default]
+    Enum,
+    #[cfg(any(test, feature = "dispatch-bench"))]
+    Boxed,
+    #[cfg(any(test, feature = "dispatch-bench"))]
+    Table,
+}
+
+/// Decodes and executes a word with the opcode its place in `OPCODE_TABLE` gives it.
+#[cfg(any(test, feature = "dispatch-bench"))]
+pub(crate) type OpcodeHandler = fn(&mut Computer, &Word) -> Result<(), MixError>;
+
+#[cfg(any(test, feature = "dispatch-bench"))]
+fn handle<const OPCODE: u8>(computer: &mut Computer, word: &Word) -> Result<(), MixError> {
+    match computer.decode_core_as::<Op>(OPCODE, word) {
+        Some(op) => op.execute_on(computer),
+        None => computer.decode(word)?.execute_on(computer),
+    }
+}
+
+/// The handler of every opcode a byte of 64 values holds.
+#[cfg(any(test, feature = "dispatch-bench"))]
+pub(crate) static OPCODE_TABLE: [OpcodeHandler; 64] = [
+    handle::<0>, handle::<1>, handle::<2>, handle::<3>, handle::<4>, handle::<5>, handle::<6>, handle::<7>,
+    handle::<8>, handle::<9>, handle::<10>, handle::<11>, handle::<12>, handle::<13>, handle::<14>, handle::<15>,
+    handle::<16>, handle::<17>, handle::<18>, handle::<19>, handle::<20>, handle::<21>, handle::<22>, handle::<23>,
+    handle::<24>, handle::<25>, handle::<26>, handle::<27>, handle::<28>, handle::<29>, handle::<30>, handle::<31>,
+    handle::<32>, handle::<33>, handle::<34>, handle::<35>, handle::<36>, handle::<37>, handle::<38>, handle::<39>,
+    handle::<40>, handle::<41>, handle::<42>, handle::<43>, handle::<44>, handle::<45>, handle::<46>, handle::<47>,
+    handle::<48>, handle::<49>, handle::<50>, handle::<51>, handle::<52>, handle::<53>, handle::<54>, handle::<55>,
+    handle::<56>, handle::<57>, handle::<58>, handle::<59>, handle::<60>, handle::<61>, handle::<62>, handle::<63>,
+];
+
+/// A word decoded for executing, as the computer's `Dispatch` decodes it.
+pub(crate) enum Prepared {
+    Op(Op),
+    #[cfg(any(test, feature = "dispatch-bench"))]
+    Boxed(Box<dyn Instruction>),
+    #[cfg(any(test, feature = "dispatch-bench"))]
+    Table(OpcodeHandler),
+}
+
+impl Prepared {
+    /// Executes the word `word` this was prepared from on `computer`.
+    #[cfg_attr(not(any(test, feature = "dispatch-bench")), allow(unused_variables))]
+    pub(crate) fn execute_on(&self, computer: &mut Computer, word: &Word) -> Result<(), MixError> {
+        match self {
+            Prepared::Op(op) => op.execute_on(computer),
+            #[cfg(any(test, feature = "dispatch-bench"))]
+            Prepared::Boxed(instruction) => instruction.execute_on(computer),
+            #[cfg(any(test, feature = "dispatch-bench"))]
+            Prepared::Table(handler) => handler(computer, word),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! The instruction reference, generated rather than written so that it can't drift from
+//! the implementation. Every operation of `OPCODES` gets an entry, in the order of the
+//! table, built from:
+//!
+//! - the table, for the mnemonic, opcode, field, and the errors the operation may raise;
+//! - `CostModel::knuth`, for the time it takes;
+//! - the instruction the core set decodes it to, for what it does and the flags it may
+//!   change, as given in its `create_instruction!`. An operation the core set doesn't
+//!   decode is listed as not implemented.
+//!
+//! `docs/instructions.md` is `Reference::to_markdown` checked in, and a test fails when
+//! regenerating it gives anything else. `mixal isa-docs` writes it again after a change,
+//! and `mixal isa-docs --json` writes `Reference::to_json` for tools.
+
+use std::fmt;
+#[cfg(feature = "json")]
+use serde::Serialize;
+use crate::computer::Computer;
+use crate::consts::Opcode;
+use crate::instruction::Instruction;
+use crate::opcodes::{self, FieldRule, Operation, OPCODES};
+use crate::timing::CostModel;
+
+/// The line `to_markdown` begins with.
+pub const MARKDOWN_TITLE: &str = "# MIX instruction reference";
+
+/// The entry of one operation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct InstructionDoc {
+    pub mnemonic: &'static str,
+    pub opcode: u8,
+    /// One of `default`, `fixed`, `unit`, or `count`, as the operation's `FieldRule`.
+    pub field_rule: &'static str,
+    /// The field used when a statement gives none, or `None` for a unit that must be given.
+    pub field: Option<u8>,
+    /// The time units taken, not counting time spent waiting on a busy unit.
+    pub time: u64,
+    /// The time units added for each word moved, for `MOVE`.
+    pub time_per_word: Option<u64>,
+    /// By `Flag::name`.
+    pub affects: Vec<&'static str>,
+    /// The codes (see `MixError::code`) of the errors the operation may raise.
+    pub errors: &'static [&'static str],
+    pub implemented: bool,
+    /// What the operation does, on one line, or `None` when it isn't implemented.
+    pub semantics: Option<String>,
+}
+
+impl InstructionDoc {
+    /// The entry of `operation`, timed by `costs`.
+    pub fn of(operation: &Operation, costs: &CostModel) -> InstructionDoc {
+        let field = match operation.field {
+            FieldRule::Unit => Some(0),
+            _ => None,
+        };
+        let word = opcodes::encode(operation.mnemonic, 0, 0, field)
+            .expect("[Error InstructionDoc::of] Every operation of the table encodes at address 0");
+        let decoded = Computer::default().decode_core_as::<Box<dyn Instruction>>(operation.code, &word);
+        let field_rule = match operation.field {
+            FieldRule::Default(_) => "default",
+            FieldRule::Fixed(_) => "fixed",
+            FieldRule::Unit => "unit",
+            FieldRule::Count(_) => "count",
+        };
+        InstructionDoc {
+            mnemonic: operation.mnemonic,
+            opcode: operation.code,
+            field_rule,
+            field: operation.default_field(),
+            time: costs.cost_of(operation.mnemonic).unwrap_or(1),
+            time_per_word: if operation.opcode() == Opcode::MOVE { Some(costs.move_per_word) } else { None },
+            affects: decoded.as_ref().map_or(vec![], |instruction| instruction.affects().iter().map(|flag| flag.name()).collect()),
+            errors: operation.errors(),
+            implemented: decoded.is_some(),
+            semantics: decoded.map(|instruction| instruction.semantics().split_whitespace().collect::<Vec<_>>().join(" ")),
+        }
+    }
+
+    /// The field as a reader of MIXAL would write it.
+    fn field_text(&self) -> String {
+        match (self.field_rule, self.field) {
+            ("default", Some(field)) => format!("({}:{}) by default", field / 8, field % 8),
+            ("fixed", Some(field)) => format!("{}, selecting the operation", field),
+            ("count", Some(field)) => format!("the number of words, {} by default", field),
+            _ => "the unit number, which must be given".to_string(),
+        }
+    }
+
+    fn time_text(&self) -> String {
+        match self.time_per_word {
+            Some(per_word) => format!("{} + {} per word", self.time, per_word),
+            None => self.time.to_string(),
+        }
+    }
+
+    fn list_text(items: &[&str]) -> String {
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.iter().map(|item| format!("`{}`", item)).collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+
+/// The entries of every operation of `OPCODES`, in the order of the table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Reference {
+    pub instructions: Vec<InstructionDoc>,
+}
+
+impl Reference {
+    /// The reference of the core instruction set, timed as Knuth gives.
+    pub fn generate() -> Reference {
+        let costs = CostModel::knuth();
+        Reference { instructions: OPCODES.iter().map(|operation| InstructionDoc::of(operation, &costs)).collect() }
+    }
+
+    /// Writes a summary table of every operation, then a section for each.
+    pub fn write_markdown(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(out, "{}", MARKDOWN_TITLE)?;
+        writeln!(out)?;
+        writeln!(out, "Generated from the operation table and the instruction definitions by `mixal isa-docs`.")?;
+        writeln!(out, "Don't edit this file; change the code and run `cargo run -- isa-docs > docs/instructions.md`.")?;
+        writeln!(out)?;
+        writeln!(out, "| Mnemonic | C | F | Time | Flags | Implemented |")?;
+        writeln!(out, "|---|---|---|---|---|---|")?;
+        for doc in self.instructions.iter() {
+            let field = doc.field.map_or("unit".to_string(), |field| field.to_string());
+            let flags = if doc.affects.is_empty() { "".to_string() } else { doc.affects.join(", ") };
+            let implemented = if doc.implemented { "yes" } else { "no" };
+            writeln!(out, "| [`{}`](#{}) | {} | {} | {} | {} | {} |",
+                     doc.mnemonic, doc.mnemonic.to_lowercase(), doc.opcode, field, doc.time_text(), flags, implemented)?;
+        }
+        for doc in self.instructions.iter() {
+            writeln!(out)?;
+            writeln!(out, "## {}", doc.mnemonic)?;
+            writeln!(out)?;
+            writeln!(out, "- Opcode: C = {}", doc.opcode)?;
+            writeln!(out, "- Field: {}", doc.field_text())?;
+            writeln!(out, "- Time: {}", doc.time_text())?;
+            writeln!(out, "- Flags affected: {}", InstructionDoc::list_text(&doc.affects))?;
+            writeln!(out, "- Errors: {}", InstructionDoc::list_text(doc.errors))?;
+            match doc.semantics.as_ref() {
+                Some(semantics) => {
+                    writeln!(out, "- Implemented: yes")?;
+                    writeln!(out)?;
+                    writeln!(out, "{}", semantics)?;
+                },
+                None => writeln!(out, "- Implemented: no")?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = String::new();
+        self.write_markdown(&mut text).expect("[Error to_markdown] Writing to a String can't fail");
+        text
+    }
+}
+
+#[cfg(feature = "json")]
+impl Reference {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("[Error to_json] Reference always serializes")
+    }
+}
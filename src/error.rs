@@ -0,0 +1,194 @@
+//! The errors a computer raises while decoding or executing a word, each naming the pc of
+//! the word.
+
+use std::fmt;
+use crate::word::{Word};
+use crate::field::FieldSpec;
+use crate::computer::Accumulator;
+use crate::decode::DecodeError;
+use crate::invariants::InvariantViolation;
+
+/// Errors raised while decoding or executing MIX instructions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MixError {
+    /// The instruction at `pc` has an opcode and field combination that neither the
+    /// core instruction set nor a registered extension implements.
+    UnimplementedInstruction { pc: usize, opcode: u8, field: u8 },
+    /// An extension tried to claim an opcode and field combination which is already
+    /// taken, either by the core instruction set or by another extension.
+    ExtensionConflict { opcode: u8, field: u8 },
+    /// The word at `address` was expected to hold a status word (see
+    /// `Computer::flags_word`) but doesn't.
+    InvalidStatusWord { pc: usize, address: usize, word: Word },
+    /// The `operation` at `pc` performs I/O on a unit that isn't attached (see
+    /// `UnattachedPolicy`). `attached` lists the units that are, for the message to
+    /// suggest.
+    UnitNotAttached { pc: usize, unit: u8, operation: String, attached: Vec<u8> },
+    /// The device attached as `unit` failed while the instruction at `pc` used it.
+    Device { pc: usize, unit: u8, message: String },
+    /// The instruction at `pc` can't proceed until something outside of the computer
+    /// acts on `unit`, such as another machine draining or filling a channel.
+    UnitBlocked { pc: usize, unit: u8 },
+    /// Bytes holding `byte_size` values can't be treated as groups of bits, because
+    /// `byte_size` isn't a power of two.
+    UnsupportedByteSize { byte_size: u16 },
+    /// The instruction at `pc` would write more than `limit` blocks to `unit` in one run
+    /// (see `OutputLimits`). Every block before it was written.
+    OutputLimitExceeded { pc: usize, unit: u8, limit: usize },
+    /// Control reached `pc`, which is outside of a memory of `memory_size` words.
+    ProgramCounterOutOfRange { pc: usize, memory_size: usize },
+    /// The instruction at `pc` loaded or computed `value` for index register `index`,
+    /// which can only hold a sign and two bytes (see `UndefinedPolicy`).
+    IndexRegisterOverflow { pc: usize, index: u8, value: Word },
+    /// The instruction at `pc` would push below `bottom`, the lowest address of the stack
+    /// region (see `extensions::stack`).
+    StackOverflow { pc: usize, bottom: usize },
+    /// The instruction at `pc` would pop from `top` or above, past the end of the stack
+    /// region.
+    StackUnderflow { pc: usize, top: usize },
+    /// The instruction at `pc` read `register` while its contents were undefined (see
+    /// `PoisonPolicy`).
+    PoisonedRead { pc: usize, register: Accumulator },
+    /// The instruction at `pc` would store `field`, which includes the opcode byte, into
+    /// the instruction at `address` (see `CodeWritePolicy`).
+    CodeWrite { pc: usize, address: usize, field: FieldSpec },
+    /// The instruction at `pc` would move a block of `words` words at `address`, which
+    /// runs past the end of memory.
+    BlockOutOfRange { pc: usize, address: usize, words: usize },
+    /// The jump at `pc` reached the stub at `entry`, which is linked by `STJ`, but the word
+    /// there is `word` rather than an `STJ` (see `Computer::stub_subroutine`).
+    StubLinkage { pc: usize, entry: usize, word: Word },
+    /// The word at `pc` names an index register other than rI1 to rI6, or has a field
+    /// that isn't a field specification where its operation takes one (see
+    /// `decode::check_word`).
+    InvalidInstruction { pc: usize, word: Word, error: DecodeError },
+    /// The instruction at `pc` refers to memory at `address`, its address after
+    /// indexing, which is outside of memory.
+    AddressOutOfRange { pc: usize, address: i64 },
+    /// The step of the instruction at `pc` left the machine breaking `violation` (see
+    /// `Computer::verify_invariants`).
+    InvariantViolated { pc: usize, violation: InvariantViolation },
+}
+
+impl MixError {
+    /// A short, stable name for the kind of error, for tools that sort errors without
+    /// parsing their messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MixError::UnimplementedInstruction { .. } => "unimplemented-instruction",
+            MixError::ExtensionConflict { .. } => "extension-conflict",
+            MixError::InvalidStatusWord { .. } => "invalid-status-word",
+            MixError::UnitNotAttached { .. } => "unit-not-attached",
+            MixError::Device { .. } => "device",
+            MixError::UnitBlocked { .. } => "unit-blocked",
+            MixError::UnsupportedByteSize { .. } => "unsupported-byte-size",
+            MixError::OutputLimitExceeded { .. } => "output-limit-exceeded",
+            MixError::ProgramCounterOutOfRange { .. } => "program-counter-out-of-range",
+            MixError::IndexRegisterOverflow { .. } => "index-register-overflow",
+            MixError::StackOverflow { .. } => "stack-overflow",
+            MixError::StackUnderflow { .. } => "stack-underflow",
+            MixError::PoisonedRead { .. } => "poisoned-read",
+            MixError::CodeWrite { .. } => "code-write",
+            MixError::BlockOutOfRange { .. } => "block-out-of-range",
+            MixError::StubLinkage { .. } => "stub-linkage",
+            MixError::InvalidInstruction { .. } => "invalid-instruction",
+            MixError::AddressOutOfRange { .. } => "address-out-of-range",
+            MixError::InvariantViolated { .. } => "invariant-violated",
+        }
+    }
+
+    /// The location of the instruction at fault, if the error has one.
+    pub fn pc(&self) -> Option<usize> {
+        match self {
+            MixError::UnimplementedInstruction { pc, .. }
+            | MixError::InvalidStatusWord { pc, .. }
+            | MixError::UnitNotAttached { pc, .. }
+            | MixError::Device { pc, .. }
+            | MixError::UnitBlocked { pc, .. }
+            | MixError::OutputLimitExceeded { pc, .. }
+            | MixError::ProgramCounterOutOfRange { pc, .. }
+            | MixError::IndexRegisterOverflow { pc, .. }
+            | MixError::StackOverflow { pc, .. }
+            | MixError::StackUnderflow { pc, .. }
+            | MixError::PoisonedRead { pc, .. }
+            | MixError::CodeWrite { pc, .. }
+            | MixError::BlockOutOfRange { pc, .. }
+            | MixError::StubLinkage { pc, .. }
+            | MixError::InvalidInstruction { pc, .. }
+            | MixError::AddressOutOfRange { pc, .. }
+            | MixError::InvariantViolated { pc, .. } => Some(*pc),
+            MixError::ExtensionConflict { .. } | MixError::UnsupportedByteSize { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for MixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MixError::UnimplementedInstruction { pc, opcode, field } =>
+                write!(f, "Unimplemented instruction at {} (opcode {}, field {})", pc, opcode, field),
+            MixError::ExtensionConflict { opcode, field } =>
+                write!(f, "Opcode {} with field {} is already implemented", opcode, field),
+            MixError::InvalidStatusWord { pc, address, word } =>
+                write!(f, "Invalid status word [{}] at {} loaded by the instruction at {}", word, address, pc),
+            MixError::UnitNotAttached { pc, unit, operation, attached } => {
+                write!(f, "Unit {} used by {} at {} is not attached", unit, operation, pc)?;
+                match attached.split_first() {
+                    None => write!(f, "; no units are attached"),
+                    Some((first, rest)) => {
+                        write!(f, "; the attached units are {}", first)?;
+                        rest.iter().try_for_each(|unit| write!(f, ", {}", unit))
+                    },
+                }
+            },
+            MixError::Device { pc, unit, message } =>
+                write!(f, "Unit {} failed at {}: {}", unit, pc, message),
+            MixError::UnitBlocked { pc, unit } =>
+                write!(f, "The instruction at {} is waiting on unit {}", pc, unit),
+            MixError::UnsupportedByteSize { byte_size } =>
+                write!(f, "Bytes of size {} are not a whole number of bits", byte_size),
+            MixError::OutputLimitExceeded { pc, unit, limit } =>
+                write!(f, "The instruction at {} would write more than {} blocks to unit {}", pc, limit, unit),
+            MixError::ProgramCounterOutOfRange { pc, memory_size } =>
+                write!(f, "Control reached {}, outside of a memory of {} words", pc, memory_size),
+            MixError::IndexRegisterOverflow { pc, index, value } =>
+                write!(f, "The instruction at {} gave rI{} [{}], which doesn't fit in it", pc, index, value),
+            MixError::StackOverflow { pc, bottom } =>
+                write!(f, "The instruction at {} would push below the stack bottom {}", pc, bottom),
+            MixError::StackUnderflow { pc, top } =>
+                write!(f, "The instruction at {} would pop past the stack top {}", pc, top),
+            MixError::PoisonedRead { pc, register } =>
+                write!(f, "The instruction at {} read {}, which is undefined after an overflowing DIV", pc, register),
+            MixError::CodeWrite { pc, address, field } =>
+                write!(f, "The instruction at {} would store ({}) over the opcode of the instruction at {}", pc, field, address),
+            MixError::BlockOutOfRange { pc, address, words } =>
+                write!(f, "The instruction at {} would move {} words at {}, past the end of memory", pc, words, address),
+            MixError::StubLinkage { pc, entry, word } =>
+                write!(f, "The jump at {} reached the stub at {}, which should start with STJ but holds [{}]", pc, entry, word),
+            MixError::InvalidInstruction { pc, word, error } =>
+                write!(f, "The word [{}] at {} is not an instruction: {}", word, pc, error),
+            MixError::AddressOutOfRange { pc, address } =>
+                write!(f, "The instruction at {} refers to {}, outside of memory", pc, address),
+            MixError::InvariantViolated { pc, violation } =>
+                write!(f, "The instruction at {} broke an invariant: {}", pc, violation),
+        }
+    }
+}
+
+/// A parameter of an instruction constructed directly, with `try_new`, is outside the
+/// values the instruction can execute with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstructionParamError {
+    pub instruction: &'static str,
+    pub parameter: &'static str,
+    /// The value given, as written in Rust.
+    pub value: String,
+    /// The values allowed.
+    pub allowed: String,
+}
+
+impl fmt::Display for InstructionParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} can't be constructed with {} {}, which must be {}", self.instruction, self.parameter, self.value, self.allowed)
+    }
+}
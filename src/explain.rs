@@ -0,0 +1,351 @@
+//! Plain sentences saying what an instruction did, for students stepping through a
+//! program. `explain` takes the instruction, the changes it made (see `phases`), and the
+//! computer after it, and writes the statement followed by what happened, such as
+//!
+//! ```text
+//! LDA 2000,3(1:5): loaded bytes 1–5 of memory cell 2531 (effective address 2000 + rI3=531) into rA, which is now +0 0 0 9 1; the sign was not copied because the field excludes position 0
+//! ```
+//!
+//! Each operation of the MIX operation table has a `Template`, chosen by `template_for`
+//! from its opcode and field. An operation without one, or one the computer doesn't
+//! implement, is said to be so rather than given a sentence that may not be true.
+
+use std::convert::TryFrom;
+use crate::computer::{ComparisonFlag, Computer};
+use crate::consts::{EnterOp, JumpCondition, Opcode, RegisterCondition, ShiftOp, SpecialOp};
+use crate::decode::DecodedInstruction;
+use crate::instruction::condition_match;
+use crate::instruction_functions::compare_words;
+use crate::opcodes::{self, FieldRule, Operation};
+use crate::peripherals::BLOCK_WORDS;
+use crate::phases::StateDelta;
+use crate::word::Word;
+use crate::field::FieldSpec;
+
+/// The registers in the order the operation table numbers them within a family: rA,
+/// rI1 to rI6, then rX.
+const FAMILY_REGISTERS: [&str; 8] = ["rA", "rI1", "rI2", "rI3", "rI4", "rI5", "rI6", "rX"];
+
+/// The conditions of the register jumps, by field.
+const REGISTER_CONDITIONS: [&str; 6] = ["negative", "zero", "positive", "nonnegative", "nonzero", "nonpositive"];
+
+/// The conditions of `JL` to `JLE`, by field less that of `JL`.
+const COMPARISON_CONDITIONS: [&str; 6] = ["less", "equal", "greater", "greater or equal", "not equal", "less or equal"];
+
+/// How an operation is explained.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Template {
+    NoOperation,
+    /// `ADD`, `SUB`, `MUL` or `DIV`.
+    Arithmetic(Opcode),
+    /// `NUM`, or `CHAR` when `to_characters`.
+    Convert { to_characters: bool },
+    Halt,
+    /// `SLA` to `SRC`.
+    Shift(ShiftOp),
+    Move,
+    Load { register: &'static str, negate: bool },
+    Store { register: &'static str },
+    StoreZero,
+    JumpBusy,
+    IoControl,
+    Input,
+    Output,
+    JumpReady,
+    /// `JMP` to `JLE`.
+    Jump(JumpCondition),
+    /// The jumps on a register.
+    RegisterJump { register: &'static str, condition: RegisterCondition },
+    /// `INC`, `DEC`, `ENT` or `ENN`.
+    AddressTransfer { register: &'static str, operation: EnterOp },
+    Compare { register: &'static str },
+}
+
+/// The template explaining `operation`.
+///
+/// ## Returns
+/// - `None` when no template covers the operation.
+pub fn template_for(operation: &Operation) -> Option<Template> {
+    use Opcode::*;
+    let opcode = operation.opcode();
+    let family = |first: Opcode| opcode.register_from(first).map(|register| FAMILY_REGISTERS[register as usize]);
+    let fixed = match operation.field {
+        FieldRule::Fixed(field) => Some(field),
+        _ => None,
+    };
+    let template = match opcode {
+        NOP => Template::NoOperation,
+        ADD | SUB | MUL | DIV => Template::Arithmetic(opcode),
+        SPECIAL => match SpecialOp::try_from(fixed?).ok()? {
+            SpecialOp::Num => Template::Convert { to_characters: false },
+            SpecialOp::Char => Template::Convert { to_characters: true },
+            SpecialOp::Halt => Template::Halt,
+            _ => return None,
+        },
+        SHIFT if fixed? <= ShiftOp::RightCircular.field() => Template::Shift(ShiftOp::try_from(fixed?).ok()?),
+        MOVE => Template::Move,
+        STJ => Template::Store { register: "rJ" },
+        STZ => Template::StoreZero,
+        JBUS => Template::JumpBusy,
+        IOC => Template::IoControl,
+        IN => Template::Input,
+        OUT => Template::Output,
+        JRED => Template::JumpReady,
+        JMP => Template::Jump(JumpCondition::try_from(fixed?).ok()?),
+        _ if family(LDA).is_some() => Template::Load { register: family(LDA)?, negate: false },
+        _ if family(LDAN).is_some() => Template::Load { register: family(LDAN)?, negate: true },
+        _ if family(STA).is_some() => Template::Store { register: family(STA)? },
+        _ if family(JA).is_some() =>
+            Template::RegisterJump { register: family(JA)?, condition: RegisterCondition::try_from(fixed?).ok()? },
+        _ if family(ENTA).is_some() =>
+            Template::AddressTransfer { register: family(ENTA)?, operation: EnterOp::try_from(fixed?).ok()? },
+        _ if family(CMPA).is_some() => Template::Compare { register: family(CMPA)? },
+        _ => return None,
+    };
+    Some(template)
+}
+
+/// Says what `instruction` did, given the changes it made to the state, `delta`, and the
+/// computer after it.
+pub fn explain(instruction: &DecodedInstruction, delta: &StateDelta, computer: &Computer) -> String {
+    let byte_size = computer.byte_size();
+    let word = Word::from_instruction_in_base(instruction.address, instruction.index, instruction.field, instruction.opcode, byte_size);
+    let statement = opcodes::disassemble_in_base(&word, byte_size)
+        .unwrap_or_else(|| format!("opcode {} with field {}", instruction.opcode, instruction.field));
+    let operation = match instruction.operation {
+        Some(operation) if computer.implements(instruction.opcode, instruction.field) => operation,
+        Some(operation) => return format!("{}: was not executed, as this computer doesn't implement {}", statement, operation.mnemonic),
+        None => return format!("{}: an extension instruction, which has no explanation", statement),
+    };
+    match template_for(operation) {
+        Some(template) => format!("{}: {}", statement, State { instruction, delta, computer }.sentence(template)),
+        None => format!("{}: {} has no explanation", statement, operation.mnemonic),
+    }
+}
+
+/// The instruction explained and the state around it.
+struct State<'a> {
+    instruction: &'a DecodedInstruction,
+    delta: &'a StateDelta,
+    computer: &'a Computer,
+}
+
+impl State<'_> {
+    fn signed_value(&self, word: &Word) -> i64 {
+        word.field_value_in_base(FieldSpec::WHOLE, self.computer.byte_size())
+    }
+
+    fn sentence(&self, template: Template) -> String {
+        // Only the templates of operations taking a field specification describe it, and
+        // those fields were checked when the instruction was decoded.
+        let field = FieldSpec::from_byte(self.instruction.field).unwrap_or(FieldSpec::WHOLE);
+        let address = self.effective_address();
+        let cell = format!("memory cell {}{}", address, self.indexing());
+        match template {
+            Template::NoOperation => "did nothing".to_string(),
+            Template::Halt => "halted the machine".to_string(),
+            Template::Arithmetic(Opcode::ADD) => format!("added {} of {} to rA, which is now {}{}", describe_field(field), cell, self.after("rA"), self.overflow("sum")),
+            Template::Arithmetic(Opcode::SUB) => format!("subtracted {} of {} from rA, which is now {}{}", describe_field(field), cell, self.after("rA"), self.overflow("difference")),
+            Template::Arithmetic(Opcode::MUL) => format!("multiplied rA by {} of {}, leaving the product in rA and rX, which are now {} and {}",
+                                               describe_field(field), cell, self.after("rA"), self.after("rX")),
+            Template::Arithmetic(_) if self.overflow_set() => format!("couldn't divide rA and rX by {} of {}: the divisor is zero or the quotient doesn't fit in rA, so the overflow toggle was turned on",
+                                                                      describe_field(field), cell),
+            Template::Arithmetic(_) => format!("divided rA and rX by {} of {}, leaving the quotient {} in rA and the remainder {} in rX",
+                                               describe_field(field), cell, self.after("rA"), self.after("rX")),
+            Template::Convert { to_characters: false } => format!("converted the character codes in rA and rX to the number {} in rA", self.after("rA")),
+            Template::Convert { to_characters: true } => format!("converted the number in rA to the character codes {} and {} in rA and rX", self.after("rA"), self.after("rX")),
+            Template::Shift(shift) => {
+                let bytes = plural(self.instruction.address.max(0), "byte");
+                let direction = if shift.field() % 2 == 0 { "left" } else { "right" };
+                match shift {
+                    ShiftOp::LeftA | ShiftOp::RightA => format!("shifted rA {} by {}, which is now {}", direction, bytes, self.after("rA")),
+                    ShiftOp::LeftAX | ShiftOp::RightAX => format!("shifted rA and rX {} together by {}; they are now {} and {}", direction, bytes, self.after("rA"), self.after("rX")),
+                    _ => format!("rotated rA and rX {} together by {}; they are now {} and {}", direction, bytes, self.after("rA"), self.after("rX")),
+                }
+            },
+            Template::Move => format!("copied {} from {} to the cells from rI1={}",
+                                      plural(self.instruction.field as i64, "word"), cell, self.signed_value(&self.before("rI1"))),
+            Template::Load { register, negate } => {
+                let mut sentence = format!("loaded {} of {} into {}", describe_field(field), cell, register);
+                if field.right() < 5 || field.left() > 1 {
+                    sentence.push_str(", in the same positions, leaving the rest of it alone");
+                }
+                if negate {
+                    sentence.push_str(" and negated it");
+                }
+                sentence.push_str(&format!(", which is now {}", self.after(register)));
+                if !field.includes_sign() && !negate {
+                    sentence.push_str("; the sign was not copied because the field excludes position 0");
+                }
+                sentence
+            },
+            Template::Store { register } => format!("stored {} into {} of {}, which is now {}",
+                                                    describe_stored(field, register), describe_field(field), cell, self.cell_after(address)),
+            Template::StoreZero => format!("cleared {} of {}, which is now {}", describe_field(field), cell, self.cell_after(address)),
+            Template::JumpBusy | Template::JumpReady => {
+                let unit = self.instruction.field;
+                let busy = template == Template::JumpBusy;
+                if self.changed("rJ") {
+                    format!("jumped to {} because unit {} was {}", address, unit, if busy { "busy" } else { "ready" })
+                } else {
+                    format!("didn't jump because unit {} was {}", unit, if busy { "ready" } else { "busy" })
+                }
+            },
+            Template::IoControl => format!("sent the control {} to unit {}", address, self.instruction.field),
+            Template::Input => format!("started reading a block from unit {} into memory cells {}–{}{}",
+                                       self.instruction.field, address, address + BLOCK_WORDS as i64 - 1, self.indexing()),
+            Template::Output => format!("started writing memory cells {}–{}{} to unit {}",
+                                        address, address + BLOCK_WORDS as i64 - 1, self.indexing(), self.instruction.field),
+            Template::Jump(JumpCondition::Always) => format!("jumped to {}, saving the address to return to, {}, in rJ", address, self.signed_value(&self.after_word("rJ"))),
+            Template::Jump(JumpCondition::SaveJ) => format!("jumped to {}, leaving rJ alone", address),
+            Template::Jump(kind @ JumpCondition::Overflow) | Template::Jump(kind @ JumpCondition::NoOverflow) => {
+                let was_on = self.delta.overflow.map_or(self.computer.overflow_flag, |(before, _)| before);
+                let on = if was_on { "on" } else { "off" };
+                let turned_off = if was_on { ", and turned it off" } else { "" };
+                if was_on == (kind == JumpCondition::Overflow) {
+                    format!("jumped to {} because the overflow toggle was {}{}", address, on, turned_off)
+                } else {
+                    format!("didn't jump because the overflow toggle was {}{}", on, turned_off)
+                }
+            },
+            Template::Jump(condition) => {
+                let flag = self.delta.comparison.map_or(self.computer.comparison_flag, |(before, _)| before);
+                let relative = condition.field() - JumpCondition::Less.field();
+                let wanted = COMPARISON_CONDITIONS[relative as usize];
+                if condition_match(relative, flag) {
+                    format!("jumped to {} because the comparison indicator is {}", address, indicator(flag))
+                } else {
+                    format!("didn't jump because the comparison indicator is {}, not {}", indicator(flag), wanted)
+                }
+            },
+            Template::RegisterJump { register, condition } => {
+                let value = self.before(register);
+                let wanted = REGISTER_CONDITIONS[condition.field() as usize];
+                if condition_match(condition.field(), compare_words(&value, &Word::default(), FieldSpec::WHOLE)) {
+                    format!("jumped to {} because {} is {} (it is {})", address, register, wanted, notation(&value))
+                } else {
+                    format!("didn't jump because {} is not {} (it is {})", register, wanted, notation(&value))
+                }
+            },
+            Template::AddressTransfer { register, operation } => {
+                let value = address;
+                match operation {
+                    EnterOp::Increase => format!("added {} to {}, which is now {}{}", value, register, self.after(register), self.overflow("sum")),
+                    EnterOp::Decrease => format!("subtracted {} from {}, which is now {}{}", value, register, self.after(register), self.overflow("difference")),
+                    EnterOp::Enter => format!("entered {} into {}, which is now {}", value, register, self.after(register)),
+                    _ => format!("entered the negation of {} into {}, which is now {}", value, register, self.after(register)),
+                }
+            },
+            Template::Compare { register } => {
+                format!("compared {} of {} with {} of {}, so the comparison indicator is {}",
+                        describe_field(field), register, describe_field(field), cell, indicator(self.computer.comparison_flag))
+            },
+        }
+    }
+
+    /// The address after indexing, with the index register as it was before.
+    fn effective_address(&self) -> i64 {
+        match self.instruction.index {
+            0 => self.instruction.address,
+            index => self.instruction.address + self.signed_value(&self.before(FAMILY_REGISTERS[index as usize])),
+        }
+    }
+
+    /// How the address was indexed, if it was, to follow the effective address.
+    fn indexing(&self) -> String {
+        match self.instruction.index {
+            0 => String::new(),
+            index => format!(" (effective address {} + rI{}={})", self.instruction.address, index,
+                             self.signed_value(&self.before(FAMILY_REGISTERS[index as usize]))),
+        }
+    }
+
+    /// The note for an overflowing `result`, when the instruction turned overflow on.
+    fn overflow(&self, result: &str) -> String {
+        if self.overflow_set() {
+            format!("; the {} didn't fit, so the overflow toggle was turned on", result)
+        } else {
+            String::new()
+        }
+    }
+
+    fn overflow_set(&self) -> bool {
+        self.delta.overflow == Some((false, true))
+    }
+
+    fn changed(&self, register: &str) -> bool {
+        self.delta.registers.iter().any(|(name, _, _)| *name == register)
+    }
+
+    /// The register as it was before the instruction.
+    fn before(&self, register: &str) -> Word {
+        self.delta.registers.iter().find(|(name, _, _)| *name == register)
+            .map_or_else(|| self.after_word(register), |(_, before, _)| *before)
+    }
+
+    fn after_word(&self, register: &str) -> Word {
+        let computer = self.computer;
+        match register {
+            "rA" => computer.ra,
+            "rX" => computer.rx,
+            "rI1" => computer.ri1,
+            "rI2" => computer.ri2,
+            "rI3" => computer.ri3,
+            "rI4" => computer.ri4,
+            "rI5" => computer.ri5,
+            "rI6" => computer.ri6,
+            _ => computer.rj,
+        }
+    }
+
+    fn after(&self, register: &str) -> String {
+        notation(&self.after_word(register))
+    }
+
+    fn cell_after(&self, address: i64) -> String {
+        usize::try_from(address).ok()
+            .and_then(|address| self.computer.memory.get(address))
+            .map_or_else(|| "outside of memory".to_string(), notation)
+    }
+}
+
+/// A word as the explanations write it, such as `+0 0 0 9 1`.
+pub fn notation(word: &Word) -> String {
+    let bytes: Vec<String> = word.bytes.iter().map(|byte| byte.to_string()).collect();
+    format!("{}{}", if word.positive { '+' } else { '-' }, bytes.join(" "))
+}
+
+/// The comparison indicator as the explanations write it, such as `less`.
+fn indicator(flag: ComparisonFlag) -> String {
+    flag.to_string().to_lowercase()
+}
+
+/// `count` of `noun`, made plural unless it is one.
+fn plural(count: i64, noun: &str) -> String {
+    if count == 1 { format!("1 {}", noun) } else { format!("{} {}s", count, noun) }
+}
+
+/// The positions of a word `field` covers, such as `bytes 1–5`.
+fn describe_field(field: FieldSpec) -> String {
+    let right = field.right();
+    let bytes = |from: usize| if from == right { format!("byte {}", from) } else { format!("bytes {}–{}", from, right) };
+    match (field.left(), right) {
+        (0, 0) => "the sign".to_string(),
+        (0, 5) => "all".to_string(),
+        (0, _) => format!("the sign and {}", bytes(1)),
+        (left, _) => bytes(left),
+    }
+}
+
+/// What a store of `field` takes from `register`: its sign if the field includes
+/// position 0, and as many of its rightmost bytes as the field has.
+fn describe_stored(field: FieldSpec, register: &str) -> String {
+    let count = field.width() as i64;
+    match (field.left(), field.right()) {
+        (0, 0) => format!("the sign of {}", register),
+        (0, 5) => register.to_string(),
+        (0, _) => format!("the sign and the rightmost {} of {}", plural(count, "byte"), register),
+        _ if count == 1 => format!("the rightmost byte of {}", register),
+        _ => format!("the rightmost {} of {}", plural(count, "byte"), register),
+    }
+}
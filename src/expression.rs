@@ -0,0 +1,144 @@
+//! The expressions the monitor takes wherever it takes an address: an integer, a symbol
+//! of the program loaded, or a sum of them with `+` and `-`, as `BUF+3` or `LOOP-1`.
+//! Symbols are looked up in the `SourceMap` the program was loaded with, and a symbol it
+//! doesn't define is reported with the names it does define that are spelled nearly the
+//! same, so that a typo shows its fix.
+//!
+//! Literals such as `=500=` aren't expressions: the assembler doesn't pool literal
+//! constants, so there is no address to give one. A constant to watch is written with
+//! `CON` under a label instead.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use crate::loader::SourceMap;
+use crate::parse::{self, ParseDiag};
+
+/// The most names an unknown symbol is reported with.
+const MOST_NEAR_MISSES: usize = 3;
+
+/// The most edits a name may be from an unknown symbol to be reported with it.
+const NEAR_MISS_DISTANCE: usize = 2;
+
+/// Why an expression has no value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExpressionError {
+    /// Not an integer, a symbol, or a sum of them.
+    Syntax(String),
+    /// A literal constant, which has no address.
+    Literal(String),
+    /// A symbol the program doesn't define, with the names it defines spelled nearly the
+    /// same, nearest first.
+    UnknownSymbol { name: String, near_misses: Vec<String> },
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::Syntax(text) => write!(f, "'{}' is not an address, a symbol, or a sum of them", text),
+            ExpressionError::Literal(text) => {
+                write!(f, "{} is a literal, which the assembler doesn't pool; give the constant a label with CON", text)
+            },
+            ExpressionError::UnknownSymbol { name, near_misses } if near_misses.is_empty() => {
+                write!(f, "'{}' is not a symbol of the program", name)
+            },
+            ExpressionError::UnknownSymbol { name, near_misses } => {
+                write!(f, "'{}' is not a symbol of the program; did you mean {}?", name, near_misses.join(", "))
+            },
+        }
+    }
+}
+
+/// The value of `text` with the symbols of `symbols`.
+///
+/// ## Errors
+/// - `Literal` for a literal, `UnknownSymbol` for a symbol `symbols` doesn't have, and
+///   `Syntax` for anything else that isn't an expression.
+pub fn evaluate(text: &str, symbols: &BTreeMap<String, i64>) -> Result<i64, ExpressionError> {
+    let text = text.trim();
+    if text.len() > 1 && text.starts_with('=') && text.ends_with('=') {
+        return Err(ExpressionError::Literal(text.to_string()));
+    }
+    let syntax = || ExpressionError::Syntax(text.to_string());
+    let mut value: i64 = 0;
+    let mut sign = 1;
+    let mut rest = text;
+    // A sign may come first, and is required before every later term.
+    if let Some(after) = rest.strip_prefix('-') {
+        sign = -1;
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix('+') {
+        rest = after;
+    }
+    loop {
+        let rest_trimmed = rest.trim_start();
+        let end = rest_trimmed.find(['+', '-']).unwrap_or(rest_trimmed.len());
+        let term = rest_trimmed[..end].trim();
+        value += sign * term_value(term, symbols).ok_or_else(syntax)??;
+        rest = &rest_trimmed[end..];
+        sign = match rest.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Ok(value),
+        };
+        rest = &rest[1..];
+    }
+}
+
+/// The value of one term, or `None` when it is neither an integer nor a symbol.
+fn term_value(term: &str, symbols: &BTreeMap<String, i64>) -> Option<Result<i64, ExpressionError>> {
+    if term.is_empty() || !term.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    if term.chars().all(|c| c.is_ascii_digit()) {
+        return parse::parse_integer(term).ok().and_then(|(_, magnitude)| i64::try_from(magnitude).ok()).map(Ok);
+    }
+    Some(symbols.get(term).copied().ok_or_else(|| ExpressionError::UnknownSymbol {
+        name: term.to_string(),
+        near_misses: near_misses(term, symbols.keys()),
+    }))
+}
+
+/// The names of `candidates` at most `NEAR_MISS_DISTANCE` edits from `name`, ignoring
+/// case, nearest first and then in order of name.
+fn near_misses<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let name = name.to_ascii_uppercase();
+    let mut near: Vec<(usize, &String)> = candidates
+        .map(|candidate| (edit_distance(&name, &candidate.to_ascii_uppercase()), candidate))
+        .filter(|(distance, _)| *distance <= NEAR_MISS_DISTANCE)
+        .collect();
+    near.sort();
+    near.into_iter().take(MOST_NEAR_MISSES).map(|(_, candidate)| candidate.clone()).collect()
+}
+
+/// The fewest insertions, deletions, and substitutions of a character turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The address `text` names, with the symbols of `source_map` when there is one. A
+/// number alone is read as `parse::parse_address` reads it.
+///
+/// ## Errors
+/// - A message for the monitor when `text` has no value, or its value is outside a memory
+///   of `memory_size` words.
+pub fn address(text: &str, source_map: Option<&SourceMap>, memory_size: usize) -> Result<usize, String> {
+    if parse::parse_integer(text).is_ok() {
+        return parse::parse_address(text, memory_size).map_err(|diag| diag.to_string());
+    }
+    let no_symbols = BTreeMap::new();
+    let symbols = source_map.map_or(&no_symbols, |map| &map.symbols);
+    let value = evaluate(text, symbols).map_err(|error| error.to_string())?;
+    usize::try_from(value).ok().filter(|address| *address < memory_size)
+        .ok_or_else(|| format!("{}; it is {}", ParseDiag::new(text, parse::address_range(memory_size)), value))
+}
@@ -0,0 +1,403 @@
+//! `FLOT` and `FIX` of the floating point attachment, and `MixFloat`, the floating point
+//! format they convert to and from. A float word `±(E, F1 F2 F3 F4)` stands for
+//! `±0.F1F2F3F4 × 256^(E-128)`: its first byte is the exponent in excess 128, and the
+//! other four are the fraction in base 256. A float is normalized when `F1` isn't zero or
+//! the whole fraction is. On a computer whose bytes hold `b` values (see
+//! `Computer::byte_size`), the fraction is in base `b` and the exponent in excess `b/2`,
+//! as in section 4.2.1; the decimal conversions of `MixFloat` are for bytes of 256.
+//!
+//! Both take the special operations code C = 5, with F = 6 and F = 7, the field values the
+//! attachment gives them. Neither has an operand:
+//!
+//! - `FLOT` replaces the integer in rA by the normalized float nearest to it. Integers of
+//!   up to four bytes convert exactly.
+//! - `FIX` replaces the float in rA by the integer nearest to it, keeping its sign. When
+//!   that integer doesn't fit in five bytes, the overflow toggle is set and rA is left
+//!   alone.
+//!
+//! Every rounding here, including the decimal conversions of `MixFloat`, is to nearest
+//! with ties to even, as in the normalization of section 4.2.1. The conversions work on
+//! exact integers throughout and never go through `f64`, so they give the same digits on
+//! every host.
+
+use crate::computer::Computer;
+use crate::instruction::Instruction;
+use crate::error::MixError;
+use crate::word::Word;
+use crate::field::FieldSpec;
+use crate::consts::{Opcode, SpecialOp};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+
+/// The opcode shared by `FLOT` and `FIX`.
+pub const OPCODE: u8 = Opcode::SPECIAL.code();
+
+/// The field selecting `FLOT`.
+pub const FLOT_FIELD: u8 = SpecialOp::Flot.field();
+
+/// The field selecting `FIX`.
+pub const FIX_FIELD: u8 = SpecialOp::Fix.field();
+
+/// The excess of the exponent byte.
+pub const EXCESS: i64 = 128;
+
+/// The excess of the exponent byte for bytes holding `byte_size` values.
+fn excess_in_base(byte_size: u16) -> i64 {
+    byte_size as i64 / 2
+}
+
+/// A floating point word, unpacked.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MixFloat {
+    pub positive: bool,
+    /// The exponent in excess 128, or half the byte size.
+    pub exponent: u8,
+    /// The fraction bytes F1 to F4, as one integer with F1 the most significant.
+    pub fraction: u32,
+}
+
+impl MixFloat {
+    pub fn from_word(word: &Word) -> MixFloat {
+        MixFloat::from_word_in_base(word, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `from_word`, for bytes holding `byte_size` values.
+    pub fn from_word_in_base(word: &Word, byte_size: u16) -> MixFloat {
+        let fraction = word.bytes[1..].iter().fold(0, |fraction, byte| fraction * byte_size as u32 + *byte as u32);
+        MixFloat { positive: word.positive, exponent: word.bytes[0], fraction }
+    }
+
+    pub fn to_word(self) -> Word {
+        self.to_word_in_base(CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `to_word`, for bytes holding `byte_size` values.
+    pub fn to_word_in_base(self, byte_size: u16) -> Word {
+        let mut word = Word::from_value_in_base(self.fraction as i64, byte_size);
+        word.positive = self.positive;
+        word.bytes[0] = self.exponent;
+        word
+    }
+
+    /// The normalized float nearest to `value`, which needs at most five bytes.
+    pub fn from_integer(value: i64) -> MixFloat {
+        MixFloat::from_integer_in_base(value, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `from_integer`, for bytes holding `byte_size` values.
+    pub fn from_integer_in_base(value: i64, byte_size: u16) -> MixFloat {
+        normalize(value >= 0, Natural::from(value.unsigned_abs(), byte_size), 0, false)
+            .expect("[Error MixFloat::from_integer] A five-byte integer has an exponent in range")
+    }
+
+    /// The integer nearest to this float.
+    ///
+    /// ## Returns
+    /// - `None` when the integer doesn't fit in five bytes.
+    pub fn to_integer(self) -> Option<i64> {
+        self.to_integer_in_base(CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `to_integer`, for bytes holding `byte_size` values.
+    pub fn to_integer_in_base(self, byte_size: u16) -> Option<i64> {
+        let base = byte_size as u128;
+        let shift = self.exponent as i64 - excess_in_base(byte_size) - 4;
+        let fraction = self.fraction as u128;
+        let magnitude = if fraction == 0 || shift < -5 {
+            // Below b^4 / b^6, so well under a half.
+            0
+        } else if shift >= 0 {
+            fraction * base.pow(shift.min(6) as u32)
+        } else {
+            let divisor = base.pow(-shift as u32);
+            let (quotient, remainder) = (fraction / divisor, fraction % divisor);
+            let (twice, odd) = (2 * remainder, quotient % 2 == 1);
+            if twice > divisor || (twice == divisor && odd) { quotient + 1 } else { quotient }
+        };
+        if Word::largest_magnitude(byte_size).is_none_or(|largest| magnitude > largest as u128) {
+            return None;
+        }
+        Some(if self.positive { magnitude as i64 } else { -(magnitude as i64) })
+    }
+
+    /// The value in decimal scientific notation with `digits` significant digits, such as
+    /// `-1.2800E+2`, generated from the exact value of the fraction.
+    ///
+    /// ## Panics
+    /// - When `digits` is 0.
+    pub fn to_decimal_string(self, digits: usize) -> String {
+        assert!(digits > 0, "[Error MixFloat::to_decimal_string] A rendering needs at least one digit");
+        let sign = if self.positive { "" } else { "-" };
+        if self.fraction == 0 {
+            return format!("{}{}E+0", sign, with_point(&"0".repeat(digits)));
+        }
+        // The value is exactly `integer × 10^power`, since 1/256 is 5^8 / 10^8.
+        let shift = self.exponent as i64 - EXCESS - 4;
+        let mut integer = Natural::from(self.fraction as u64, 256);
+        for _ in 0..(8 * shift.abs()) {
+            integer.mul_small(if shift >= 0 { 2 } else { 5 });
+        }
+        let power = if shift < 0 { 8 * shift } else { 0 };
+        let all_digits = integer.to_decimal();
+        let mut exponent = all_digits.len() as i64 - 1 + power;
+        let mut kept: Vec<u8> = all_digits.bytes().take(digits).collect();
+        kept.resize(digits, b'0');
+        if round_up(&kept, all_digits.as_bytes().get(digits..).unwrap_or(&[])) {
+            let mut i = digits;
+            while i > 0 && kept[i - 1] == b'9' {
+                kept[i - 1] = b'0';
+                i -= 1;
+            }
+            if i == 0 {
+                kept.insert(0, b'1');
+                kept.truncate(digits);
+                exponent += 1;
+            } else {
+                kept[i - 1] += 1;
+            }
+        }
+        let kept = String::from_utf8(kept).unwrap_or_default();
+        format!("{}{}E{}{}", sign, with_point(&kept), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    }
+
+    /// The normalized float nearest to the decimal `text`, an optional `-`, digits with an
+    /// optional point, and an optional exponent such as `E-3`.
+    ///
+    /// ## Returns
+    /// - `None` when `text` isn't such a number or its float exponent is out of range.
+    pub fn from_decimal_str(text: &str) -> Option<MixFloat> {
+        let (positive, text) = match text.strip_prefix('-') {
+            Some(rest) => (false, rest),
+            None => (true, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match text.find(['E', 'e']) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if whole.is_empty() && fraction.is_empty() || !(whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mut integer = Natural::from(0, 256);
+        for digit in whole.bytes().chain(fraction.bytes()) {
+            integer.mul_small(10);
+            integer.add_small((digit - b'0') as u64);
+        }
+        let power = exponent - fraction.len() as i64;
+        if power >= 0 {
+            for _ in 0..power {
+                integer.mul_small(10);
+            }
+            return normalize(positive, integer, 0, false);
+        }
+        // Scale up by enough bytes that the quotient keeps well over four, so that the
+        // remainder only decides ties.
+        let mut divisor = Natural::from(1, 256);
+        for _ in 0..-power {
+            divisor.mul_small(10);
+        }
+        let scale = divisor.len() as i64 + 6;
+        for _ in 0..scale {
+            integer.mul_small(256);
+        }
+        let mut inexact = false;
+        for _ in 0..-power {
+            inexact |= integer.div_small(10) != 0;
+        }
+        normalize(positive, integer, scale, inexact)
+    }
+}
+
+/// `digits` with a point after the first.
+fn with_point(digits: &str) -> String {
+    if digits.len() == 1 {
+        digits.to_string()
+    } else {
+        format!("{}.{}", &digits[..1], &digits[1..])
+    }
+}
+
+/// Whether `kept` rounds up, given the digits dropped after it.
+fn round_up(kept: &[u8], dropped: &[u8]) -> bool {
+    match dropped.first() {
+        Some(b'6'..=b'9') => true,
+        Some(b'5') => dropped[1..].iter().any(|digit| *digit != b'0') || kept.last().is_some_and(|digit| digit % 2 == 1),
+        _ => false,
+    }
+}
+
+/// The normalized float nearest to `integer × b^-scale`, where `b` is the base of
+/// `integer` and of the float, plus less than one unit of `integer` when `inexact` is set.
+///
+/// ## Returns
+/// - `None` when the exponent is out of range.
+fn normalize(positive: bool, mut integer: Natural, scale: i64, inexact: bool) -> Option<MixFloat> {
+    if integer.is_zero() {
+        return Some(MixFloat { positive, exponent: 0, fraction: 0 });
+    }
+    let base = integer.base;
+    let length = integer.len();
+    let mut exponent = excess_in_base(base as u16) + length as i64 - scale;
+    let mut fraction = if length <= 4 {
+        integer.low_u64() * (base as u64).pow(4 - length as u32)
+    } else {
+        let count = length - 4;
+        let mut twice_dropped = Natural { digits: integer.shift_digits_right(count), base };
+        twice_dropped.trim();
+        twice_dropped.mul_small(2);
+        let kept = integer.low_u64();
+        // Twice the dropped digits reach b^count exactly when they are at least a half.
+        let at_least_half = twice_dropped.len() > count;
+        let exactly_half = at_least_half && twice_dropped.digits[..count].iter().all(|digit| *digit == 0) && twice_dropped.digits[count] == 1;
+        let above_half = at_least_half && (!exactly_half || inexact);
+        if above_half || (exactly_half && !inexact && kept % 2 == 1) { kept + 1 } else { kept }
+    };
+    if fraction == (base as u64).pow(4) {
+        fraction /= base as u64;
+        exponent += 1;
+    }
+    if !(0..base as i64).contains(&exponent) {
+        return None;
+    }
+    Some(MixFloat { positive, exponent: exponent as u8, fraction: fraction as u32 })
+}
+
+/// An unsigned integer of any size, as its digits in `base` from least significant,
+/// without trailing zeros.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Natural {
+    digits: Vec<u32>,
+    base: u32,
+}
+
+impl Natural {
+    fn from(value: u64, base: u16) -> Natural {
+        let mut natural = Natural { digits: Vec::new(), base: base as u32 };
+        natural.add_small(value);
+        natural
+    }
+
+    fn trim(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The number of digits.
+    fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    /// The value of the low eight digits.
+    fn low_u64(&self) -> u64 {
+        self.digits.iter().take(8).rev().fold(0, |value, digit| value * self.base as u64 + *digit as u64)
+    }
+
+    fn mul_small(&mut self, factor: u32) {
+        let base = self.base as u64;
+        let mut carry = 0u64;
+        for digit in self.digits.iter_mut() {
+            let product = *digit as u64 * factor as u64 + carry;
+            *digit = (product % base) as u32;
+            carry = product / base;
+        }
+        while carry > 0 {
+            self.digits.push((carry % base) as u32);
+            carry /= base;
+        }
+        self.trim();
+    }
+
+    fn add_small(&mut self, addend: u64) {
+        let base = self.base as u64;
+        let mut carry = addend;
+        for digit in self.digits.iter_mut() {
+            if carry == 0 {
+                return;
+            }
+            let sum = *digit as u64 + carry;
+            *digit = (sum % base) as u32;
+            carry = sum / base;
+        }
+        while carry > 0 {
+            self.digits.push((carry % base) as u32);
+            carry /= base;
+        }
+    }
+
+    /// Divides by `divisor`, returning the remainder.
+    fn div_small(&mut self, divisor: u32) -> u32 {
+        let base = self.base as u64;
+        let mut remainder = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let dividend = remainder * base + *digit as u64;
+            *digit = (dividend / divisor as u64) as u32;
+            remainder = dividend % divisor as u64;
+        }
+        self.trim();
+        remainder as u32
+    }
+
+    /// Drops the low `count` digits, returning them from least significant.
+    fn shift_digits_right(&mut self, count: usize) -> Vec<u32> {
+        self.digits.drain(..count).collect()
+    }
+
+    fn to_decimal(&self) -> String {
+        if self.is_zero() {
+            return "0".to_string();
+        }
+        let mut rest = self.clone();
+        let mut digits = Vec::new();
+        while !rest.is_zero() {
+            digits.push(b'0' + rest.div_small(10) as u8);
+        }
+        digits.reverse();
+        String::from_utf8(digits).unwrap_or_default()
+    }
+}
+
+create_instruction!(
+    /// Converts the integer in rA to a floating point number in rA.
+    Float, (self, computer) {
+    let byte_size = computer.byte_size();
+    let mut float = MixFloat::from_integer_in_base(computer.ra.field_value_in_base(FieldSpec::MAGNITUDE, byte_size), byte_size);
+    float.positive = computer.ra.positive;
+    computer.ra = float.to_word_in_base(byte_size);
+});
+
+impl Default for Float {
+    fn default() -> Float {
+        Float::new()
+    }
+}
+
+create_instruction!(
+    /// Converts the floating point number in rA to the integer in rA it rounds to. A number
+    /// too large for rA leaves rA unchanged and turns the overflow toggle on.
+    Fix affects [Overflow], (self, computer) {
+    let byte_size = computer.byte_size();
+    match MixFloat::from_word_in_base(&computer.ra, byte_size).to_integer_in_base(byte_size) {
+        Some(value) => {
+            let positive = computer.ra.positive;
+            computer.ra = Word::from_value_in_base(value.abs(), byte_size);
+            computer.ra.positive = positive;
+        },
+        None => computer.overflow_flag = true,
+    }
+});
+
+impl Default for Fix {
+    fn default() -> Fix {
+        Fix::new()
+    }
+}
+
+/// Registers `FLOT` and `FIX` with `computer`.
+pub fn register(computer: &mut Computer) -> Result<(), MixError> {
+    computer.register_extension(OPCODE, FLOT_FIELD, |_, _| Box::new(Float::new()))?;
+    computer.register_extension(OPCODE, FIX_FIELD, |_, _| Box::new(Fix::new()))
+}
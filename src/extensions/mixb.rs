@@ -0,0 +1,127 @@
+//! The binary operations of Knuth's binary MIX, in which every byte is a group of bits:
+//!
+//! - `SLB` and `SRB` shift the magnitude of rA and rX, taken together as one number, left
+//!   or right by M bits, bringing in zeros. The signs of both registers are unchanged.
+//! - `AND`, `OR`, and `XOR` combine the magnitude of rA with that of the word at M bit by
+//!   bit. The sign of rA is unchanged.
+//!
+//! `SLB` and `SRB` take C = 6 with F = 6 and F = 7, the assignment Knuth gives them.
+//! Knuth assigns no codes to `AND`, `OR`, and `XOR`, so they take the special operations
+//! code C = 5 with F = 10, 11, and 12, the first field values left free by `NUM`, `CHAR`,
+//! `HLT`, and the status flag extension.
+
+use crate::computer::{Accumulator, Computer};
+use crate::error::MixError;
+use crate::instruction::{params, Instruction};
+use crate::word::Word;
+use crate::consts::{Opcode, ShiftOp, SpecialOp};
+
+/// The opcode shared by `SLB` and `SRB`, that of the other shifts.
+pub const SHIFT_OPCODE: u8 = Opcode::SHIFT.code();
+
+/// The field selecting `SLB`.
+pub const SLB_FIELD: u8 = ShiftOp::LeftBinary.field();
+
+/// The field selecting `SRB`.
+pub const SRB_FIELD: u8 = ShiftOp::RightBinary.field();
+
+/// The opcode shared by `AND`, `OR`, and `XOR`.
+pub const LOGIC_OPCODE: u8 = Opcode::SPECIAL.code();
+
+/// The field selecting `AND`.
+pub const AND_FIELD: u8 = SpecialOp::And.field();
+
+/// The field selecting `OR`.
+pub const OR_FIELD: u8 = SpecialOp::Or.field();
+
+/// The field selecting `XOR`.
+pub const XOR_FIELD: u8 = SpecialOp::Xor.field();
+
+/// Replaces the magnitudes of rA and rX with `f` of their bits taken together, rA highest.
+fn map_ax_bits<F: Fn(u128) -> u128>(computer: &mut Computer, byte_bits: u32, f: F) {
+    let word_bits = 5 * byte_bits;
+    let ax = ((computer.ra.to_bits(byte_bits) as u128) << word_bits) | computer.rx.to_bits(byte_bits) as u128;
+    let shifted = f(ax) & ((1u128 << (2 * word_bits)) - 1);
+    computer.ra = Word::from_bits(computer.ra.positive, (shifted >> word_bits) as u64, byte_bits);
+    computer.rx = Word::from_bits(computer.rx.positive, shifted as u64, byte_bits);
+}
+
+/// Replaces the magnitude of rA with `f` of its bits and those of the word at `address`.
+///
+/// ## Errors
+/// - `AddressOutOfRange` when `address` is outside of memory.
+fn map_a_bits<F: Fn(u64, u64) -> u64>(computer: &mut Computer, address: usize, byte_bits: u32, f: F) -> Result<(), MixError> {
+    let bits = f(computer.ra.to_bits(byte_bits), computer.word_at(address)?.to_bits(byte_bits));
+    computer.ra = Word::from_bits(computer.ra.positive, bits, byte_bits);
+    Ok(())
+}
+
+create_instruction!(
+    /// Shifts the bits of rA and rX, taken together with rA on the left, left by M, bringing
+    /// in zeros at the right. The signs are unchanged.
+    ShiftLeftBinary, amount: usize, byte_bits: u32, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let amount = self.amount.min(128) as u32;
+    map_ax_bits(computer, self.byte_bits, |ax| ax.checked_shl(amount).unwrap_or(0));
+});
+
+create_instruction!(
+    /// Shifts the bits of rA and rX, taken together with rA on the left, right by M,
+    /// bringing in zeros at the left. The signs are unchanged.
+    ShiftRightBinary, amount: usize, byte_bits: u32, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let amount = self.amount.min(128) as u32;
+    map_ax_bits(computer, self.byte_bits, |ax| ax.checked_shr(amount).unwrap_or(0));
+});
+
+create_instruction!(
+    /// Sets each bit of rA to the AND of it and the bit of the word at M. The sign of rA is
+    /// unchanged.
+    And, address: usize where params::address, byte_bits: u32, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    map_a_bits(computer, self.address, self.byte_bits, |a, m| a & m)?;
+});
+
+create_instruction!(
+    /// Sets each bit of rA to the OR of it and the bit of the word at M. The sign of rA is
+    /// unchanged.
+    Or, address: usize where params::address, byte_bits: u32, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    map_a_bits(computer, self.address, self.byte_bits, |a, m| a | m)?;
+});
+
+create_instruction!(
+    /// Sets each bit of rA to the XOR of it and the bit of the word at M. The sign of rA is
+    /// unchanged.
+    Xor, address: usize where params::address, byte_bits: u32, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    map_a_bits(computer, self.address, self.byte_bits, |a, m| a ^ m)?;
+});
+
+/// Registers `SLB`, `SRB`, `AND`, `OR`, and `XOR` with `computer`, treating its bytes,
+/// of `Computer::byte_size` values, as groups of bits.
+///
+/// ## Errors
+/// - `UnsupportedByteSize` when the byte size of `computer` isn't a power of two.
+/// - `ExtensionConflict` when another extension already claims one of the instructions.
+pub fn register(computer: &mut Computer) -> Result<(), MixError> {
+    let byte_size = computer.byte_size();
+    register_in_base(computer, byte_size)
+}
+
+/// As `register`, for bytes holding `byte_size` values.
+///
+/// ## Errors
+/// - `UnsupportedByteSize` when `byte_size` isn't a power of two, such as on a decimal
+///   machine with bytes of size 100.
+/// - `ExtensionConflict` when another extension already claims one of the instructions.
+pub fn register_in_base(computer: &mut Computer, byte_size: u16) -> Result<(), MixError> {
+    let byte_bits = Word::bits_per_byte(byte_size)?;
+    computer.register_extension(SHIFT_OPCODE, SLB_FIELD, move |_, address| Box::new(ShiftLeftBinary::new_unchecked(address, byte_bits)))?;
+    computer.register_extension(SHIFT_OPCODE, SRB_FIELD, move |_, address| Box::new(ShiftRightBinary::new_unchecked(address, byte_bits)))?;
+    computer.register_extension(LOGIC_OPCODE, AND_FIELD, move |_, address| Box::new(And::new_unchecked(address, byte_bits)))?;
+    computer.register_extension(LOGIC_OPCODE, OR_FIELD, move |_, address| Box::new(Or::new_unchecked(address, byte_bits)))?;
+    computer.register_extension(LOGIC_OPCODE, XOR_FIELD, move |_, address| Box::new(Xor::new_unchecked(address, byte_bits)))
+}
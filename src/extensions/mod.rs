@@ -0,0 +1,20 @@
+//! Optional instructions outside of the core MIX instruction set.
+//!
+//! Extensions claim opcode and field combinations that the core instruction set leaves
+//! unimplemented, and are only decoded on computers they have been registered with;
+//! everywhere else those words fall through to the computer's unimplemented policy, so
+//! strict MIX behavior is preserved unless an extension is asked for.
+
+use std::rc::Rc;
+use crate::word::{Word};
+use crate::instruction::Instruction;
+
+pub mod status_flags;
+pub mod stack;
+pub mod float;
+#[cfg(feature = "mixb")]
+pub mod mixb;
+
+/// Builds an extension instruction from its instruction word and its effective
+/// (indexed) address.
+pub type ExtensionDecoder = Rc<dyn Fn(&Word, usize) -> Box<dyn Instruction>>;
@@ -0,0 +1,75 @@
+//! `PUSH` and `POP`, a conventional stack for teaching, with rI6 as the stack pointer:
+//!
+//! - `PUSH` stores rA at the address in rI6, then decrements rI6.
+//! - `POP` increments rI6, then loads rA from the address in rI6.
+//!
+//! The stack grows downward through a region of memory given when the extension is
+//! registered, and leaving the region in either direction faults before anything
+//! changes. Both take the special operations code C = 5, with F = 13 and F = 14, the
+//! field values following those of the binary MIX extension, and so take the 10 units of
+//! time of the other special operations. A push is an ordinary memory write.
+
+use std::ops::Range;
+use crate::field::FieldSpec;
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::instruction::{Instruction, IncI, LoadA, StoreA};
+use crate::consts::{Opcode, SpecialOp};
+
+/// The opcode shared by `PUSH` and `POP`.
+pub const OPCODE: u8 = Opcode::SPECIAL.code();
+
+/// The field selecting `PUSH`.
+pub const PUSH_FIELD: u8 = SpecialOp::Push.field();
+
+/// The field selecting `POP`.
+pub const POP_FIELD: u8 = SpecialOp::Pop.field();
+
+/// The signed value of rI6.
+fn stack_pointer(computer: &Computer) -> i64 {
+    let value = computer.ri6.field_value_in_base(FieldSpec::of(4, 5), computer.byte_size());
+    if computer.ri6.positive { value } else { -value }
+}
+
+/// The address `pointer` as long as it lies in `region`.
+///
+/// ## Errors
+/// - `StackOverflow` naming the bottom of the region when `pointer` is below it.
+/// - `StackUnderflow` naming the top of the region when `pointer` is at or above it.
+fn check_in_region(computer: &Computer, pointer: i64, region: &Range<usize>) -> Result<usize, MixError> {
+    if pointer < region.start as i64 {
+        return Err(MixError::StackOverflow { pc: computer.pc, bottom: region.start });
+    }
+    if pointer >= region.end as i64 {
+        return Err(MixError::StackUnderflow { pc: computer.pc, top: region.end });
+    }
+    Ok(pointer as usize)
+}
+
+create_instruction!(
+    /// Stores rA at the address in rI6, then decrements rI6. Faults, changing nothing, when
+    /// the address is outside the stack's region.
+    Push, region: Range<usize>, (self, computer) {
+    let address = check_in_region(computer, stack_pointer(computer), &self.region)?;
+    StoreA::new_unchecked(address, FieldSpec::WHOLE).execute_on(computer)?;
+    IncI::new_unchecked(6, 1, true, true).execute_on(computer)?;
+});
+
+create_instruction!(
+    /// Increments rI6, then loads rA from the address in rI6. Faults, changing nothing, when
+    /// the address is outside the stack's region.
+    Pop, region: Range<usize>, (self, computer) {
+    let address = check_in_region(computer, stack_pointer(computer) + 1, &self.region)?;
+    IncI::new_unchecked(6, 1, true, false).execute_on(computer)?;
+    LoadA::new_unchecked(address, FieldSpec::WHOLE, false).execute_on(computer)?;
+});
+
+/// Registers `PUSH` and `POP` with `computer`, keeping the stack within `region`.
+///
+/// ## Errors
+/// - `ExtensionConflict` when another extension already claims one of the instructions.
+pub fn register(computer: &mut Computer, region: Range<usize>) -> Result<(), MixError> {
+    let push_region = region.clone();
+    computer.register_extension(OPCODE, PUSH_FIELD, move |_, _| Box::new(Push::new_unchecked(push_region.clone())))?;
+    computer.register_extension(OPCODE, POP_FIELD, move |_, _| Box::new(Pop::new_unchecked(region.clone())))
+}
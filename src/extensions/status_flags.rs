@@ -0,0 +1,42 @@
+//! `STF` and `LDF`, which save and restore the overflow toggle and comparison indicator
+//! through a status word in memory, using the encoding of `Computer::flags_word`.
+
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::field::FieldSpec;
+use crate::instruction::{params, Instruction};
+use crate::consts::{Opcode, SpecialOp};
+
+/// The opcode shared by `STF` and `LDF`, the special-operations opcode whose unused
+/// field values they occupy.
+pub const OPCODE: u8 = Opcode::SPECIAL.code();
+
+/// The field selecting `STF`.
+pub const STF_FIELD: u8 = SpecialOp::StoreFlags.field();
+
+/// The field selecting `LDF`.
+pub const LDF_FIELD: u8 = SpecialOp::LoadFlags.field();
+
+create_instruction!(
+    /// Stores the overflow toggle and comparison indicator at M as a status word.
+    StoreFlags, address: usize where params::address, (self, computer) {
+    computer.check_code_write(self.address, FieldSpec::WHOLE)?;
+    let word = computer.flags_word();
+    computer.store_field(self.address, word, FieldSpec::WHOLE)?;
+});
+
+create_instruction!(
+    /// Sets the overflow toggle and comparison indicator from the status word at M. Faults,
+    /// changing neither, when the word isn't a status word.
+    LoadFlags affects [Overflow, Comparison], address: usize where params::address, (self, computer) {
+    let word = computer.word_at(self.address)?;
+    if !computer.set_flags_word(&word) {
+        return Err(MixError::InvalidStatusWord { pc: computer.pc, address: self.address, word });
+    }
+});
+
+/// Registers `STF` and `LDF` with `computer`.
+pub fn register(computer: &mut Computer) -> Result<(), MixError> {
+    computer.register_extension(OPCODE, STF_FIELD, |_, address| Box::new(StoreFlags::new_unchecked(address)))?;
+    computer.register_extension(OPCODE, LDF_FIELD, |_, address| Box::new(LoadFlags::new_unchecked(address)))
+}
@@ -0,0 +1,128 @@
+//! Field specifications, the `L:R` of an instruction naming the positions of a word it
+//! works on, position 0 being the sign and 1 to 5 the bytes. A `FieldSpec` is checked
+//! once, when it is made, so that everything given one can count on `L <= R <= 5`.
+//!
+//! A word keeps its sign apart from its bytes, so position `i` of a field is the byte at
+//! `bytes[i - 1]`; `byte_indices` gives those indices, and `includes_sign` whether the
+//! field covers the sign as well. Nothing else converts between the two numberings.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// A field specification `L:R`, with `L <= R <= 5`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FieldSpec {
+    left: u8,
+    right: u8,
+}
+
+/// Why `L:R` isn't a field specification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FieldError {
+    /// `R` is past position 5.
+    PastEnd { left: usize, right: usize },
+    /// `L` is past `R`.
+    Reversed { left: usize, right: usize },
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::PastEnd { left, right } => write!(f, "{}:{} runs past position 5", left, right),
+            FieldError::Reversed { left, right } => write!(f, "{}:{} starts after it ends", left, right),
+        }
+    }
+}
+
+impl FieldSpec {
+    /// The whole word, `0:5`.
+    pub const WHOLE: FieldSpec = FieldSpec { left: 0, right: 5 };
+    /// The bytes of a word without its sign, `1:5`.
+    pub const MAGNITUDE: FieldSpec = FieldSpec { left: 1, right: 5 };
+    /// The sign alone, `0:0`.
+    pub const SIGN: FieldSpec = FieldSpec { left: 0, right: 0 };
+
+    /// The field `left:right`.
+    ///
+    /// ## Errors
+    /// - `PastEnd` when `right` is above 5.
+    /// - `Reversed` when `left` is above `right`.
+    pub const fn new(left: usize, right: usize) -> Result<FieldSpec, FieldError> {
+        if right > 5 {
+            Err(FieldError::PastEnd { left, right })
+        } else if left > right {
+            Err(FieldError::Reversed { left, right })
+        } else {
+            Ok(FieldSpec { left: left as u8, right: right as u8 })
+        }
+    }
+
+    /// The field `left:right`, for fields known to be valid, such as those written out in
+    /// the code.
+    ///
+    /// ## Panics
+    /// - When `new` fails, which in a constant fails the build.
+    pub const fn of(left: usize, right: usize) -> FieldSpec {
+        match FieldSpec::new(left, right) {
+            Ok(field) => field,
+            Err(_) => panic!("[Error FieldSpec::of] Not a field specification"),
+        }
+    }
+
+    /// The field of the byte `8L + R` of an instruction.
+    ///
+    /// ## Errors
+    /// - As `new`.
+    pub const fn from_byte(byte: u8) -> Result<FieldSpec, FieldError> {
+        FieldSpec::new((byte / 8) as usize, (byte % 8) as usize)
+    }
+
+    /// The byte `8L + R` of an instruction with this field.
+    pub const fn to_byte(self) -> u8 {
+        8 * self.left + self.right
+    }
+
+    pub const fn left(self) -> usize {
+        self.left as usize
+    }
+
+    pub const fn right(self) -> usize {
+        self.right as usize
+    }
+
+    /// Whether the field covers position 0, the sign.
+    pub const fn includes_sign(self) -> bool {
+        self.left == 0
+    }
+
+    /// Whether the field covers the sign and no byte.
+    pub const fn is_sign_only(self) -> bool {
+        self.right == 0
+    }
+
+    /// The indices into `Word::bytes` of the bytes the field covers, which are empty for
+    /// the sign alone.
+    pub fn byte_indices(self) -> RangeInclusive<usize> {
+        if self.is_sign_only() {
+            return RangeInclusive::new(1, 0);
+        }
+        RangeInclusive::new(self.left.max(1) as usize - 1, self.right as usize - 1)
+    }
+
+    /// The number of bytes the field covers, leaving out the sign.
+    pub const fn width(self) -> usize {
+        if self.is_sign_only() { 0 } else { (self.right - if self.left == 0 { 1 } else { self.left } + 1) as usize }
+    }
+
+    /// The positions the field covers, as a mask with bit 0 for the sign and bits 1 to 5
+    /// for the bytes.
+    pub const fn mask(self) -> u8 {
+        (1 << (self.right + 1)) - (1 << self.left)
+    }
+}
+
+impl fmt::Display for FieldSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.left, self.right)
+    }
+}
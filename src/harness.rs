@@ -0,0 +1,245 @@
+//! Running one routine of a larger program on its own, as a test of it would. A
+//! `Harness` declares what the routine expects on entry, the words it reads from
+//! registers and memory and how it returns, and what the caller expects back: the
+//! locations holding its outputs and the registers it must preserve.
+//!
+//! The routine is entered with rJ holding a trap address of the harness, as if a `JMP`
+//! there had called it. Its return jump reaches the trap, where a raw stub (see
+//! `Computer::stub_subroutine_with`) stops the machine, so the run ends cleanly however
+//! the routine was called in its program. The registers before and after are compared
+//! as a step's phases compare them, by `Snapshot::delta`.
+//!
+//! ```text
+//! let result = Harness::for_routine(3000)
+//!     .input(Location::Register("rI1"), Word::from_value(3))
+//!     .input_mem(1001, Word::from_value(7))
+//!     .linkage(Linkage::Stj { exit_word: 3012 })
+//!     .output(Location::Register("rA"))
+//!     .preserve(&["rX", "rI4"])
+//!     .expect_exit_within(100)
+//!     .run(&program)?;
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use crate::analysis::{self, Location, DEFAULT_STEP_LIMIT};
+use crate::computer::{Computer, StubLinkage};
+use crate::consts::{JumpCondition, Opcode};
+use crate::error::MixError;
+use crate::loader::AssembledProgram;
+use crate::phases::Snapshot;
+use crate::word::Word;
+
+/// The address the routine returns to unless `trap_at` says otherwise: the last word
+/// of a computer of the default size, which few programs use.
+pub const DEFAULT_TRAP: usize = crate::computer::DEFAULT_MEMORY_SIZE - 1;
+
+/// How a routine returns to its caller.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Linkage {
+    /// As the book's subroutines do: `STJ` saves rJ in the address of the `JMP` at
+    /// `exit_word`, which returns. Returning through any other jump is an error.
+    Stj { exit_word: usize },
+    /// To the address rJ held on entry, by any jump.
+    Rj,
+}
+
+/// What a routine left behind on returning.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoutineResult {
+    /// The words of the output locations, in the order they were declared.
+    pub outputs: Vec<Word>,
+    /// The instructions executed, counting the return jump.
+    pub steps: usize,
+    /// The time units taken, counting the return jump.
+    pub cycles: u64,
+    /// The preserved registers the routine changed, in the order rA, rX, rI1 to rI6, rJ,
+    /// each with its value on entry and on return.
+    pub clobbered: Vec<(&'static str, Word, Word)>,
+}
+
+impl RoutineResult {
+    /// Whether the routine returned with every preserved register as it found it.
+    pub fn preserved(&self) -> bool {
+        self.clobbered.is_empty()
+    }
+}
+
+/// Errors raised when a routine doesn't return as its harness expects.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HarnessError {
+    /// The word at the exit word of `Stj` linkage isn't a `JMP`.
+    NotAnExit { exit_word: usize, word: Word },
+    /// The routine hadn't returned after its step limit.
+    Hung { steps: usize },
+    /// It stopped at the `HLT` at `pc` instead of returning.
+    Halted { pc: usize, steps: usize },
+    /// It returned by the jump at `pc`, not by the exit word of its linkage.
+    WrongExit { pc: usize, exit_word: usize },
+    Failed { error: MixError, steps: usize },
+}
+
+impl fmt::Display for HarnessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HarnessError::NotAnExit { exit_word, word } =>
+                write!(f, "The exit word {} holds {}, not a JMP", exit_word, word),
+            HarnessError::Hung { steps } => write!(f, "The routine hadn't returned after {} steps", steps),
+            HarnessError::Halted { pc, steps } => write!(f, "The routine halted at {} after {} steps", pc, steps),
+            HarnessError::WrongExit { pc, exit_word } =>
+                write!(f, "The routine returned by the jump at {} instead of its exit word {}", pc, exit_word),
+            HarnessError::Failed { error, steps } => write!(f, "The routine failed after {} steps: {}", steps, error),
+        }
+    }
+}
+
+/// The entry conditions of a routine and what its caller expects of it, built up from
+/// `for_routine`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Harness {
+    pub entry: usize,
+    pub linkage: Linkage,
+    /// The address rJ holds on entry, where the routine's return is trapped.
+    pub trap: usize,
+    pub inputs: Vec<(Location, Word)>,
+    pub outputs: Vec<Location>,
+    /// The names of the registers, of `rA`, `rX`, `rI1` to `rI6`, and `rJ`, the routine
+    /// must return unchanged.
+    pub preserved: Vec<&'static str>,
+    /// The most instructions the routine may execute before it is taken to hang.
+    pub step_limit: usize,
+}
+
+impl Harness {
+    /// The routine starting at `entry`, returning to rJ at `DEFAULT_TRAP` within
+    /// `DEFAULT_STEP_LIMIT` instructions, with no inputs, outputs, or preserved registers
+    /// yet.
+    pub fn for_routine(entry: usize) -> Harness {
+        Harness {
+            entry,
+            linkage: Linkage::Rj,
+            trap: DEFAULT_TRAP,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            preserved: Vec::new(),
+            step_limit: DEFAULT_STEP_LIMIT,
+        }
+    }
+
+    /// Puts `value` in `location` on entry, after any input declared before.
+    pub fn input(mut self, location: Location, value: Word) -> Harness {
+        self.inputs.push((location, value));
+        self
+    }
+
+    pub fn input_ra(self, value: Word) -> Harness {
+        self.input(Location::Register("rA"), value)
+    }
+
+    pub fn input_rx(self, value: Word) -> Harness {
+        self.input(Location::Register("rX"), value)
+    }
+
+    pub fn input_mem(self, address: usize, value: Word) -> Harness {
+        self.input(Location::Memory(address), value)
+    }
+
+    /// Samples `location` on return, after any output declared before.
+    pub fn output(mut self, location: Location) -> Harness {
+        self.outputs.push(location);
+        self
+    }
+
+    /// Adds `registers` to those the routine must preserve.
+    pub fn preserve(mut self, registers: &[&'static str]) -> Harness {
+        self.preserved.extend_from_slice(registers);
+        self
+    }
+
+    pub fn linkage(mut self, linkage: Linkage) -> Harness {
+        self.linkage = linkage;
+        self
+    }
+
+    /// Traps the return at `address` in place of `DEFAULT_TRAP`, for a program using
+    /// that word or a smaller computer.
+    pub fn trap_at(mut self, address: usize) -> Harness {
+        self.trap = address;
+        self
+    }
+
+    pub fn expect_exit_within(mut self, steps: usize) -> Harness {
+        self.step_limit = steps;
+        self
+    }
+
+    /// Loads `program` into a computer of the default size and runs the routine on it,
+    /// as `run_on`.
+    pub fn run(&self, program: &AssembledProgram) -> Result<RoutineResult, HarnessError> {
+        let mut computer = Computer::default();
+        program.load_into(&mut computer);
+        self.run_on(&computer)
+    }
+
+    /// Runs the routine on a copy of `computer`, with the inputs in place, rJ at the
+    /// trap, and `pc` at the entry.
+    ///
+    /// ## Errors
+    /// - `NotAnExit` when the exit word of `Stj` linkage isn't a `JMP`.
+    /// - `Hung`, `Halted`, or `Failed` when the routine doesn't return.
+    /// - `WrongExit` when it returns by a jump other than its exit word.
+    pub fn run_on(&self, computer: &Computer) -> Result<RoutineResult, HarnessError> {
+        let mut computer = computer.clone();
+        if let Linkage::Stj { exit_word } = self.linkage {
+            let word = computer.memory.get(exit_word).copied().unwrap_or(Word::default());
+            if word.opcode() != Opcode::JMP.code() || word.field() != JumpCondition::Always.field() {
+                return Err(HarnessError::NotAnExit { exit_word, word });
+            }
+        }
+        let trapped = Rc::new(Cell::new(false));
+        let trap = trapped.clone();
+        computer.stub_subroutine_with(self.trap, StubLinkage::Raw, Box::new(move |computer| {
+            trap.set(true);
+            computer.halt();
+            Ok(())
+        }));
+        for (location, value) in self.inputs.iter() {
+            analysis::set_location(&mut computer, *location, *value);
+        }
+        computer.rj = Word::from_value(self.trap as i64);
+        computer.pc = self.entry;
+
+        let before = Snapshot::of(&computer);
+        let start = computer.time();
+        let mut steps = 0;
+        while !computer.is_halted() {
+            if steps == self.step_limit {
+                return Err(HarnessError::Hung { steps });
+            }
+            let pc = computer.pc;
+            if let Err(error) = computer.step() {
+                return Err(HarnessError::Failed { error, steps });
+            }
+            if let Some(unit) = computer.blocked_on() {
+                return Err(HarnessError::Failed { error: MixError::UnitBlocked { pc, unit }, steps });
+            }
+            steps += 1;
+            match (trapped.get(), self.linkage) {
+                (true, Linkage::Stj { exit_word }) if pc != exit_word => return Err(HarnessError::WrongExit { pc, exit_word }),
+                (false, _) if computer.is_halted() => return Err(HarnessError::Halted { pc, steps }),
+                _ => {},
+            }
+        }
+
+        let clobbered = before.delta(&computer).registers.into_iter()
+            .filter(|(name, _, _)| self.preserved.contains(name))
+            .collect();
+        Ok(RoutineResult {
+            outputs: self.outputs.iter().map(|location| analysis::location_value(&computer, *location)).collect(),
+            steps,
+            cycles: computer.time() - start,
+            clobbered,
+        })
+    }
+}
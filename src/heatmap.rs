@@ -0,0 +1,208 @@
+//! Counts of a run's memory accesses by time and address, for seeing how an algorithm
+//! moves through memory: the merge passes of a tape sort, the probes of a hash search.
+//! While `Computer::record_access_heatmap` is on, every read, write, and instruction
+//! fetch is counted in the cell of its time bucket and address line, both as sized by
+//! `HeatmapOptions`. Only cells something touched are kept, so a heatmap costs memory in
+//! proportion to the buckets and lines a run actually used, up to the computer's budget
+//! (see `budget`), and a computer recording none pays only the check that it isn't.
+//!
+//! `AccessHeatmap::to_csv` writes the cells for plotting elsewhere, and `to_ascii` draws
+//! them on a grid small enough for a terminal.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt;
+use std::ops::Range;
+use crate::computer::Computer;
+use crate::invariants::InvariantViolation;
+
+/// The characters `to_ascii` draws a cell with, from no accesses to the most of any cell.
+pub const DENSITY: &[u8] = b" .:-=+*#%@";
+
+/// The header line of `to_csv`.
+pub const CSV_HEADER: &str = "bucket_start_cycle,address_line,reads,writes,fetches";
+
+/// How finely a heatmap divides a run.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct HeatmapOptions {
+    /// The cycles of each time bucket, counted from time 0.
+    pub bucket_cycles: u64,
+    /// The words of each address line, counted from address 0.
+    pub line_words: usize,
+}
+
+impl Default for HeatmapOptions {
+    /// Buckets of 1000 cycles and lines of 16 words.
+    fn default() -> HeatmapOptions {
+        HeatmapOptions { bucket_cycles: 1000, line_words: 16 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Access {
+    /// An operand read by an instruction, or a word copied out by `OUT`.
+    Read,
+    /// A store, or a word filled by `IN`.
+    Write,
+    /// The fetch of an instruction.
+    Fetch,
+}
+
+/// The accesses counted in a cell.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct AccessCounts {
+    pub reads: u64,
+    pub writes: u64,
+    pub fetches: u64,
+}
+
+impl AccessCounts {
+    pub fn total(&self) -> u64 {
+        self.reads + self.writes + self.fetches
+    }
+
+    fn add(&mut self, other: &AccessCounts) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.fetches += other.fetches;
+    }
+}
+
+/// The accesses of a run, by time bucket and address line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccessHeatmap {
+    pub options: HeatmapOptions,
+    /// The counts of each cell touched, by bucket and line number.
+    cells: BTreeMap<(u64, usize), AccessCounts>,
+    /// Whether accesses to cells not held were dropped for want of budget.
+    truncated: bool,
+}
+
+impl AccessHeatmap {
+    /// ## Panics
+    /// - When either size of `options` is 0.
+    pub fn new(options: HeatmapOptions) -> AccessHeatmap {
+        assert!(options.bucket_cycles > 0 && options.line_words > 0,
+                "[Error AccessHeatmap::new] Buckets and lines must have a size above 0.");
+        AccessHeatmap { options, cells: BTreeMap::new(), truncated: false }
+    }
+
+    /// An empty heatmap with the same options, for a computer that was reset.
+    pub fn restarted(&self) -> AccessHeatmap {
+        AccessHeatmap::new(self.options)
+    }
+
+    /// Counts an access of kind `access` to each word of `range` at `time`. Once the
+    /// heatmap holds `max_cells`, accesses to any other cell are dropped.
+    pub(crate) fn record(&mut self, time: u64, range: Range<usize>, access: Access, max_cells: usize) {
+        let bucket = time / self.options.bucket_cycles;
+        let mut address = range.start;
+        while address < range.end {
+            let line = address / self.options.line_words;
+            let words = ((line + 1) * self.options.line_words).min(range.end) - address;
+            address += words;
+            let full = self.cells.len() >= max_cells;
+            let counts = match self.cells.entry((bucket, line)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(_) if full => {
+                    self.truncated = true;
+                    continue;
+                },
+                Entry::Vacant(entry) => entry.insert(AccessCounts::default()),
+            };
+            let count = match access {
+                Access::Read => &mut counts.reads,
+                Access::Write => &mut counts.writes,
+                Access::Fetch => &mut counts.fetches,
+            };
+            *count += words as u64;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// The number of cells held.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether accesses were dropped because the heatmap held the computer's
+    /// `InstrumentationBudget::max_heatmap_cells`, so that the counts are partial.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The cells touched, in order of bucket and then line, each as the first cycle of its
+    /// bucket, the first address of its line, and its counts.
+    pub fn cells(&self) -> impl Iterator<Item = (u64, usize, AccessCounts)> + '_ {
+        let HeatmapOptions { bucket_cycles, line_words } = self.options;
+        self.cells.iter().map(move |(&(bucket, line), counts)| (bucket * bucket_cycles, line * line_words, *counts))
+    }
+
+    /// The counts of every cell added up.
+    pub fn totals(&self) -> AccessCounts {
+        let mut totals = AccessCounts::default();
+        for counts in self.cells.values() {
+            totals.add(counts);
+        }
+        totals
+    }
+
+    /// Writes `CSV_HEADER` and then a line for each cell touched, in the order of `cells`.
+    pub fn to_csv(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(out, "{}", CSV_HEADER)?;
+        for (cycle, address, counts) in self.cells() {
+            writeln!(out, "{},{},{},{},{}", cycle, address, counts.reads, counts.writes, counts.fetches)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the heatmap with time running across and addresses running down, from the
+    /// first bucket and line touched to the last. Each character stands for the cells it
+    /// covers, drawn from `DENSITY` by their accesses against those of the busiest
+    /// character, so that only a character covering none is blank. A heatmap with fewer
+    /// buckets than `width`, or fewer lines than `height`, gets a character for each
+    /// rather than being stretched.
+    ///
+    /// ## Returns
+    /// - The rows, each ending in a newline, or nothing when no cell was touched or
+    ///   either size is 0.
+    pub fn to_ascii(&self, width: usize, height: usize) -> String {
+        let (first, last) = match (self.cells.keys().next(), self.cells.keys().next_back()) {
+            (Some(first), Some(last)) if width > 0 && height > 0 => (first.0, last.0),
+            _ => return String::new(),
+        };
+        let lowest = self.cells.keys().map(|(_, line)| *line).min().unwrap_or(0);
+        let highest = self.cells.keys().map(|(_, line)| *line).max().unwrap_or(0);
+        let (buckets, lines) = ((last - first + 1) as usize, highest - lowest + 1);
+        let (columns, rows) = (width.min(buckets), height.min(lines));
+        let mut grid = vec![vec![0u64; columns]; rows];
+        for (&(bucket, line), counts) in self.cells.iter() {
+            let column = (bucket - first) as usize * columns / buckets;
+            let row = (line - lowest) * rows / lines;
+            grid[row][column] += counts.total();
+        }
+        let busiest = grid.iter().flatten().copied().max().unwrap_or(0).max(1);
+        let levels = DENSITY.len() as u64 - 1;
+        let mut text = String::new();
+        for row in grid {
+            for total in row {
+                text.push(DENSITY[((total * levels).div_ceil(busiest)) as usize] as char);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Checks that the heatmap of `computer` holds no more cells than the budget allows, for
+/// `Computer::check_invariants`.
+pub(crate) fn check_heatmap(computer: &Computer, _written: Range<usize>) -> Result<(), InvariantViolation> {
+    let max = computer.budget.max_heatmap_cells;
+    match computer.access_heatmap().map_or(0, AccessHeatmap::cell_count) {
+        length if length > max => Err(InvariantViolation::OverBudget { list: "heatmap", length, max }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,337 @@
+//! Binary images of a computer's state: its registers, flags, `pc`, whether it halted,
+//! and every word of memory. An image is the magic bytes `MIXI`, a format byte, then the
+//! sections:
+//!
+//! - Registers: `pc` as a big-endian `u32`, the overflow flag, the comparison flag (0 for
+//!   less, 1 for equal, 2 for greater), and whether the computer halted, a byte each, then
+//!   rA, rX, rI1 to rI6, and rJ.
+//! - Memory: the number of words as a big-endian `u32`, then the words.
+//!
+//! Words are a sign byte (0 for +, 1 for -) followed by the five bytes, as on tape. The
+//! format byte says how the memory is stored:
+//!
+//! - `FORMAT_PLAIN`: every word in order, with nothing after the memory.
+//! - `FORMAT_COMPRESSED`: runs of words, each a kind byte and a big-endian `u32` count,
+//!   followed for `RUN_REPEAT` by one word standing for `count` copies of it, and for
+//!   `RUN_LITERAL` by `count` words. Any word repeated `MIN_REPEAT` or more times in a row
+//!   is a repeat run, including the runs of +0 that make up most of memory, and the words
+//!   between are literal runs. The image ends with the CRC-32 of every byte before it.
+//!   A 4000-word machine with a handful of scattered words in use takes under 128 bytes,
+//!   where the plain format takes over 24,000.
+//!
+//! Both formats are read, and the runs are chosen the same way every time, so the same
+//! state always gives the same bytes and images can be compared or hashed as they are.
+//! The words read pass through the gate of `sanitize`.
+
+use std::fmt;
+use crate::computer::{ComparisonFlag, Computer};
+use crate::fingerprint::crc32;
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::sanitize::{self, IngestError, IngestPolicy};
+use crate::word::Word;
+
+pub const IMAGE_MAGIC: [u8; 4] = *b"MIXI";
+/// Every word stored as it is, without a checksum, as the first images were written.
+pub const FORMAT_PLAIN: u8 = 0;
+/// Memory stored as runs, with a CRC-32 at the end.
+pub const FORMAT_COMPRESSED: u8 = 1;
+
+pub const RUN_LITERAL: u8 = 0;
+pub const RUN_REPEAT: u8 = 1;
+/// The fewest copies of a word in a row stored as a repeat run, the count at which a
+/// repeat run is no longer than the words themselves.
+pub const MIN_REPEAT: usize = 3;
+
+const WORD_BYTES: usize = 6;
+
+/// Errors raised while reading an image.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImageError {
+    /// The image doesn't start with `IMAGE_MAGIC`.
+    BadMagic,
+    /// The format byte is neither `FORMAT_PLAIN` nor `FORMAT_COMPRESSED`.
+    UnknownFormat(u8),
+    /// The image ends at `offset`, in the middle of what it was reading.
+    Truncated { offset: usize },
+    /// The byte at `offset` can't be read as what should be there.
+    Corrupt { offset: usize, reason: String },
+    /// The CRC-32 stored in a compressed image isn't the one its bytes give.
+    Integrity { expected: u32, found: u32 },
+    /// The image holds `image` words, but the computer restored into holds `computer`.
+    MemorySize { image: usize, computer: usize },
+    /// A word of `section`, `registers` or `memory`, has a byte too large, the index of
+    /// the error being the register's, from rA, or the word's address.
+    InvalidWord { section: &'static str, error: IngestError },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::BadMagic => write!(f, "Not a machine image"),
+            ImageError::UnknownFormat(format) => write!(f, "Unknown image format {}", format),
+            ImageError::Truncated { offset } => write!(f, "The image ends early, at byte {}", offset),
+            ImageError::Corrupt { offset, reason } => write!(f, "Corrupt image at byte {}: {}", offset, reason),
+            ImageError::Integrity { expected, found } =>
+                write!(f, "The image is damaged: its checksum is {:08x}, but its bytes give {:08x}", expected, found),
+            ImageError::MemorySize { image, computer } =>
+                write!(f, "The image holds {} words, but the computer has {}", image, computer),
+            ImageError::InvalidWord { section, error } => write!(f, "Invalid word in the image's {}: {}", section, error),
+        }
+    }
+}
+
+/// The state of a computer an image holds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MachineImage {
+    pub pc: usize,
+    pub overflow: bool,
+    pub comparison: ComparisonFlag,
+    pub halted: bool,
+    /// rA, rX, rI1 to rI6, and rJ.
+    pub registers: [Word; 9],
+    pub memory: Vec<Word>,
+}
+
+impl MachineImage {
+    pub fn of(computer: &Computer) -> MachineImage {
+        MachineImage {
+            pc: computer.pc,
+            overflow: computer.overflow_flag,
+            comparison: computer.comparison_flag,
+            halted: computer.is_halted(),
+            registers: [computer.ra, computer.rx, computer.ri1, computer.ri2, computer.ri3,
+                        computer.ri4, computer.ri5, computer.ri6, computer.rj],
+            memory: computer.memory.clone(),
+        }
+    }
+
+    /// Resets `computer` and gives it the state of the image. The words other than +0 are
+    /// written as a program is loaded, so they count as occupied.
+    ///
+    /// ## Errors
+    /// - `MemorySize` when the computer's memory isn't the size of the image's, in which
+    ///   case the computer is left alone.
+    pub fn restore_into(&self, computer: &mut Computer) -> Result<(), ImageError> {
+        if self.memory.len() != computer.memory_size() {
+            return Err(ImageError::MemorySize { image: self.memory.len(), computer: computer.memory_size() });
+        }
+        computer.reset();
+        for (address, word) in self.memory.iter().enumerate() {
+            if *word != Word::default() {
+                computer.write_mem(address, *word);
+            }
+        }
+        let [ra, rx, ri1, ri2, ri3, ri4, ri5, ri6, rj] = self.registers;
+        (computer.ra, computer.rx, computer.rj) = (ra, rx, rj);
+        (computer.ri1, computer.ri2, computer.ri3, computer.ri4, computer.ri5, computer.ri6) = (ri1, ri2, ri3, ri4, ri5, ri6);
+        computer.pc = self.pc;
+        computer.overflow_flag = self.overflow;
+        computer.comparison_flag = self.comparison;
+        if self.halted {
+            computer.halt();
+        }
+        Ok(())
+    }
+
+    /// The image as bytes in `format`, `FORMAT_PLAIN` or `FORMAT_COMPRESSED`.
+    ///
+    /// ## Panics
+    /// - When `format` is neither.
+    pub fn to_bytes(&self, format: u8) -> Vec<u8> {
+        let mut bytes = IMAGE_MAGIC.to_vec();
+        bytes.push(format);
+        bytes.extend_from_slice(&(self.pc as u32).to_be_bytes());
+        let comparison = match self.comparison {
+            ComparisonFlag::less => 0,
+            ComparisonFlag::equal => 1,
+            ComparisonFlag::greater => 2,
+        };
+        bytes.extend_from_slice(&[self.overflow as u8, comparison, self.halted as u8]);
+        for register in self.registers.iter() {
+            push_word(&mut bytes, register);
+        }
+        bytes.extend_from_slice(&(self.memory.len() as u32).to_be_bytes());
+        match format {
+            FORMAT_PLAIN => {
+                for word in self.memory.iter() {
+                    push_word(&mut bytes, word);
+                }
+            },
+            FORMAT_COMPRESSED => {
+                push_runs(&mut bytes, &self.memory);
+                let checksum = crc32(&bytes);
+                bytes.extend_from_slice(&checksum.to_be_bytes());
+            },
+            format => panic!("[Error to_bytes] Unknown image format {}", format),
+        }
+        bytes
+    }
+
+    /// Reads an image in either format, rejecting words with a byte too large for this
+    /// machine.
+    ///
+    /// ## Errors
+    /// - `Integrity` when a compressed image's checksum doesn't match, before anything
+    ///   else in it is read.
+    /// - Any other `ImageError` for the first thing in the image that can't be read.
+    pub fn from_bytes(bytes: &[u8]) -> Result<MachineImage, ImageError> {
+        MachineImage::from_bytes_with(bytes, IngestPolicy::Reject, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// Reads an image in either format for a machine with bytes of `byte_size` values,
+    /// with the words holding a byte too large handled as `policy` says.
+    ///
+    /// ## Errors
+    /// As `from_bytes`, with `InvalidWord` only under `Reject`.
+    pub fn from_bytes_with(bytes: &[u8], policy: IngestPolicy, byte_size: u16) -> Result<MachineImage, ImageError> {
+        if bytes.len() < 5 || bytes[..4] != IMAGE_MAGIC {
+            return Err(ImageError::BadMagic);
+        }
+        let format = bytes[4];
+        let body = match format {
+            FORMAT_PLAIN => bytes,
+            FORMAT_COMPRESSED => {
+                let split = bytes.len().checked_sub(4).filter(|split| *split >= 5).ok_or(ImageError::Truncated { offset: bytes.len() })?;
+                let (body, stored) = bytes.split_at(split);
+                let expected = u32::from_be_bytes([stored[0], stored[1], stored[2], stored[3]]);
+                let found = crc32(body);
+                if expected != found {
+                    return Err(ImageError::Integrity { expected, found });
+                }
+                body
+            },
+            format => return Err(ImageError::UnknownFormat(format)),
+        };
+        let mut reader = Reader { bytes: body, offset: 5 };
+        let pc = reader.u32()? as usize;
+        let overflow = reader.flag()?;
+        let comparison = match reader.byte()? {
+            0 => ComparisonFlag::less,
+            1 => ComparisonFlag::equal,
+            2 => ComparisonFlag::greater,
+            flag => return Err(reader.corrupt(format!("comparison flag {}", flag))),
+        };
+        let halted = reader.flag()?;
+        let mut registers = [Word::default(); 9];
+        for register in registers.iter_mut() {
+            *register = reader.word()?;
+        }
+        let length = reader.u32()? as usize;
+        let mut memory = Vec::with_capacity(length.min(body.len()));
+        match format {
+            FORMAT_PLAIN => {
+                for _ in 0..length {
+                    memory.push(reader.word()?);
+                }
+            },
+            _ => {
+                while memory.len() < length {
+                    let (kind, count) = (reader.byte()?, reader.u32()? as usize);
+                    if count == 0 || memory.len() + count > length {
+                        return Err(reader.corrupt(format!("a run of {} words after {} of {}", count, memory.len(), length)));
+                    }
+                    match kind {
+                        RUN_LITERAL => for _ in 0..count {
+                            memory.push(reader.word()?);
+                        },
+                        RUN_REPEAT => {
+                            let word = reader.word()?;
+                            memory.resize(memory.len() + count, word);
+                        },
+                        kind => return Err(reader.corrupt(format!("run kind {}", kind))),
+                    }
+                }
+            },
+        }
+        if reader.offset != body.len() {
+            return Err(reader.corrupt(format!("{} bytes after the memory", body.len() - reader.offset)));
+        }
+        sanitize::ingest_in_place(&mut registers, policy, byte_size)
+            .map_err(|error| ImageError::InvalidWord { section: "registers", error })?;
+        let memory = sanitize::ingest_words(memory, policy, byte_size)
+            .map_err(|error| ImageError::InvalidWord { section: "memory", error })?;
+        Ok(MachineImage { pc, overflow, comparison, halted, registers, memory })
+    }
+}
+
+fn push_word(bytes: &mut Vec<u8>, word: &Word) {
+    bytes.push(if word.positive { 0 } else { 1 });
+    bytes.extend_from_slice(&word.bytes);
+}
+
+/// Writes `words` as runs, as `FORMAT_COMPRESSED` describes.
+fn push_runs(bytes: &mut Vec<u8>, words: &[Word]) {
+    let mut literal_start = 0;
+    let mut i = 0;
+    let flush_literal = |bytes: &mut Vec<u8>, literal: &[Word]| {
+        if !literal.is_empty() {
+            bytes.push(RUN_LITERAL);
+            bytes.extend_from_slice(&(literal.len() as u32).to_be_bytes());
+            for word in literal.iter() {
+                push_word(bytes, word);
+            }
+        }
+    };
+    while i < words.len() {
+        let repeats = words[i..].iter().take_while(|word| **word == words[i]).count();
+        if repeats >= MIN_REPEAT {
+            flush_literal(bytes, &words[literal_start..i]);
+            bytes.push(RUN_REPEAT);
+            bytes.extend_from_slice(&(repeats as u32).to_be_bytes());
+            push_word(bytes, &words[i]);
+            i += repeats;
+            literal_start = i;
+        } else {
+            i += repeats;
+        }
+    }
+    flush_literal(bytes, &words[literal_start..]);
+}
+
+/// Reads the parts of an image in order, failing at the first that isn't there.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], ImageError> {
+        let taken = self.bytes.get(self.offset..self.offset + length).ok_or(ImageError::Truncated { offset: self.bytes.len() })?;
+        self.offset += length;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, ImageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, ImageError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            flag => Err(self.corrupt(format!("flag byte {}", flag))),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, ImageError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn word(&mut self) -> Result<Word, ImageError> {
+        let bytes = self.take(WORD_BYTES)?;
+        let positive = match bytes[0] {
+            0 => true,
+            1 => false,
+            sign => return Err(ImageError::Corrupt { offset: self.offset - WORD_BYTES, reason: format!("sign byte {}", sign) }),
+        };
+        let mut word = Word { positive, bytes: [0; 5] };
+        word.bytes.copy_from_slice(&bytes[1..]);
+        Ok(word)
+    }
+
+    /// The error for what was just read, which ends at `offset`.
+    fn corrupt(&self, reason: String) -> ImageError {
+        ImageError::Corrupt { offset: self.offset - 1, reason }
+    }
+}
@@ -1,287 +1,773 @@
-use crate::computer::{Computer, ComparisonFlag};
-use crate::word::{Word};
-use crate::instruction_functions::*;
-
-
-/// Provides a useful macro for creating instructions, so that the amount 
-/// of copy and paste code is minimized. 
-/// 
-/// ## Arguments
-/// - Instruction Name: the name of the instruction being created. This is usually the 
-/// verbatim word used in MIX.
-/// - *Optional* `parameter: type`: There is an optional list of paramters to be 
-/// used in each instruction definition. For this, just input the standard rust 
-/// definition of `parameter: type` pairings and they will be generated in the instruction 
-/// struct. 
-/// - `(self, computer) { ... }`: This is a mandatory block of code necessary 
-/// to make the instruction run. This block of code is macro for the `execute_on`
-/// implementation of the instruction for this specific instruction. The `(self, computer)` 
-/// is necessary before the block since these variables need to be included in 
-/// the function definition and macro expansions don't allow them to just be entered 
-/// in the macro by default.
-macro_rules! create_instruction {
-    ($i:ident, ($s:ident, $c:ident) $body:block) => {
-        pub struct $i {}
-        impl $i {
-            pub fn new() -> $i { $i {} }
-        }
-        impl Instruction for $i {
-            fn execute_on(&$s, $c: &mut Computer) {
-                $body
-            }
-        }
-    };
-    ($i:ident, $($v:ident: $t:ty),*, ($s:ident, $c:ident) $body:block) => {
-        pub struct $i {
-            $(pub $v: $t),*
-        }
-        impl $i {
-            pub fn new($($v: $t),*) -> $i {
-                $i {
-                    $($v: $v),*
-                }
-            }
-        }
-        impl Instruction for $i {
-            fn execute_on(&$s, $c: &mut Computer) {
-                $body
-            }
-        }
-    };
-}
-
-/// MARK: Instructions
-
-pub trait Instruction {
-    fn execute_on(&self, computer: &mut Computer);
-}
-
-create_instruction!(NoOperation, (self, _c) {});
-
-create_instruction!(Halt, (self, computer) { computer.pc = 4000; });
-
-create_instruction!(LoadA, address: usize, field_specification: (usize, usize), negative: bool, (self, computer) {
-    let ra =  &mut computer.ra;
-    let mem = &computer.memory[self.address];
-    copy_word_fields(mem, ra, self.field_specification);
-    if self.negative { ra.positive = !ra.positive; }
-});
-
-create_instruction!(LoadX, address: usize, field_specification: (usize, usize), negative: bool, (self, computer) {
-    let rx =  &mut computer.rx;
-    let mem = &computer.memory[self.address];
-    copy_word_fields(mem, rx, self.field_specification);
-    if self.negative { rx.positive = !rx.positive; }
-});
-
-create_instruction!(LoadI, index: u8, address: usize, field_specification: (usize, usize), negative: bool, (self, computer) {
-    let mem = &computer.memory[self.address].clone();
-    let ri =  register_for_index(computer, self.index);
-    copy_word_fields_i(mem, ri, self.field_specification);
-    if self.negative { ri.positive = !ri.positive; }
-});
-
-create_instruction!(StoreA, address: usize, field_specification: (usize, usize), (self, computer) {
-    store_operation(&computer.ra, &mut computer.memory[self.address], self.field_specification);
-});
-
-create_instruction!(StoreX, address: usize, field_specification: (usize, usize), (self, computer) {
-    store_operation(&computer.rx, &mut computer.memory[self.address], self.field_specification);
-});
-
-create_instruction!(StoreI, index: u8, address: usize, field_specification: (usize, usize), (self, computer) {
-    let ri =  register_for_index(computer, self.index);
-    let reg_clone = ri.clone();
-    store_operation(
-        &reg_clone, 
-        &mut computer.memory[self.address], 
-        self.field_specification
-        );    
-});
-
-create_instruction!(StoreJ, address: usize, field_specification: (usize, usize), (self, computer) {
-    store_operation(&computer.rj, &mut computer.memory[self.address], self.field_specification);
-});
-
-create_instruction!(StoreZ, address: usize, field_specification: (usize, usize), (self, computer) {
-    let zero = Word::default();
-    store_operation(&zero, &mut computer.memory[self.address], self.field_specification);
-});
-
-create_instruction!(Add, address: usize, field_specification: (usize, usize), (self, computer) {
-    let (value, overflow) = add_words(&computer.ra, &computer.memory[self.address], self.field_specification);
-    copy_word_fields(&value, &mut computer.ra, self.field_specification);
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(Sub, address: usize, field_specification: (usize, usize), (self, computer) {
-    let (value, overflow) = add_words(&computer.ra, &computer.memory[self.address].negate(), self.field_specification);
-    copy_word_fields(&value, &mut computer.ra, self.field_specification);
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(Mult, address: usize, field_specification: (usize, usize) , (self, computer) {
-    let (lower_value, upper_value) = multiply_words(&computer.ra, &computer.memory[self.address].negate(), self.field_specification);
-    copy_word_fields(&lower_value, &mut computer.rx, (0,5));
-    copy_word_fields(&upper_value, &mut computer.ra, (0,5));
-});
-
-create_instruction!(Div, address: usize, field_specification: (usize, usize) , (self, computer) {
-    let (dividend, remainder, overflow) = divide_words(&computer.ra, &computer.rx, &computer.memory[self.address].negate(), self.field_specification);
-    copy_word_fields(&remainder, &mut computer.rx, (0,5));
-    copy_word_fields(&dividend, &mut computer.ra, (0,5));
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(EntA, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    copy_word_fields(&word, &mut computer.ra, (0, 5));
-});
-
-create_instruction!(EntX, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    copy_word_fields(&word, &mut computer.rx, (0, 5));
-});
-
-create_instruction!(EntI, index: u8, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    let mut ri =  register_for_index(computer, self.index);
-    copy_word_fields_i(&word, &mut ri, (0,5));
-});
-
-create_instruction!(IncA, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    let (value, overflow) = add_words(&computer.ra, &word, (0,5));
-    copy_word_fields(&value, &mut computer.ra, (0, 5));
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(IncX, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    let (value, overflow) = add_words(&computer.rx, &word, (0,5));
-    copy_word_fields(&value, &mut computer.rx, (0, 5));
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(IncI, index: u8, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
-    let mut word = Word::from_value(self.value as i64);
-    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
-    let mut ri =  register_for_index(computer, self.index);
-    let (value, overflow) = add_words(&ri, &word, (0,5));
-    copy_word_fields(&value, &mut ri, (0, 5));
-    computer.overflow_flag = overflow;
-});
-
-create_instruction!(CmpA, address: usize, field_specification: (usize, usize), (self, computer) {
-    let result = compare_words(&computer.ra, &computer.memory[self.address], self.field_specification);
-    computer.comparison_flag = result;
-});
-
-create_instruction!(CmpX, address: usize, field_specification: (usize, usize), (self, computer) {
-    let result = compare_words(&computer.rx, &computer.memory[self.address], self.field_specification);
-    computer.comparison_flag = result;
-});
-
-create_instruction!(CmpI, index: u8, address: usize, field_specification: (usize, usize), (self, computer) {
-    let mem = computer.memory[self.address].clone();
-    let ri =  register_for_index(computer, self.index);
-    let result = compare_words(&ri, &mem, self.field_specification);
-    computer.comparison_flag = result;
-});
-
-create_instruction!(Jmp, address: usize, save_address: bool, (self, computer) {
-    if self.save_address {
-        save_jump(computer);
-    }
-    computer.pc = self.address;
-});
-
-create_instruction!(JmpO, address: usize, should_negate: bool, (self, computer) {
-    if computer.overflow_flag.clone() != self.should_negate {
-        save_jump(computer);
-        computer.pc = self.address;
-    }
-    computer.overflow_flag = false;
-});
-
-pub fn condition_match(op: u8, condition: ComparisonFlag) -> bool {
-    match op {
-        0 => condition == ComparisonFlag::less,
-        1 => condition == ComparisonFlag::equal,
-        2 => condition == ComparisonFlag::greater,
-        3 => condition != ComparisonFlag::less,
-        4 => condition != ComparisonFlag::equal,
-        5=> condition != ComparisonFlag::greater,
-        _ => false,
-    }
-}
-
-create_instruction!(JmpC, address: usize, operation: u8, (self, computer) {
-    let condition = condition_match(self.operation - 4, computer.comparison_flag);
-    if condition {
-        save_jump(computer);
-        computer.pc = self.address;
-    }
-});
-
-create_instruction!(JmpA, address: usize, operation: u8, (self, computer) {
-    let zero = Word::default();
-    let result = compare_words(&computer.ra, &zero, (0, 5));
-    let condition = condition_match(self.operation, result);
-    if condition {
-        save_jump(computer);
-        computer.pc = self.address;
-    }
-});
-
-create_instruction!(JmpX, address: usize, operation: u8, (self, computer) {
-    let zero = Word::default();
-    let result = compare_words(&computer.rx, &zero, (0, 5));
-    let condition = condition_match(self.operation, result);
-    if condition {
-        save_jump(computer);
-        computer.pc = self.address;
-    }
-});
-
-create_instruction!(JmpI, index: u8, address: usize, operation: u8, (self, computer) {
-    let zero = Word::default();
-    let ri =  register_for_index(computer, self.index);
-    let result = compare_words(&ri, &zero, (0, 5));
-    let condition = condition_match(self.operation, result);
-    if condition {
-        save_jump(computer);
-        computer.pc = self.address;
-    }
-});
-
-create_instruction!(SLA, amount: usize, cycle: bool, (self, computer) {
-    let r = computer.ra.clone();
-    computer.ra = single_word_left_shift(&r, self.amount, self.cycle);
-});
-
-create_instruction!(SRA, amount: usize, cycle: bool, (self, computer) {
-    let r = computer.ra.clone();
-    computer.ra = single_word_right_shift(&r, self.amount, self.cycle);
-});
-
-create_instruction!(SLAX, amount: usize, (self, computer) {
-    let a = computer.ra.clone();
-    let x = computer.rx.clone();
-    let (ra, rx) = double_word_left_shift(&a, &x, self.amount);
-    computer.ra = ra;
-    computer.rx = rx;
-});
-
-create_instruction!(SRAX, amount: usize, (self, computer) {
-    let a = computer.ra.clone();
-    let x = computer.rx.clone();
-    let (ra, rx) = double_word_right_shift(&a, &x, self.amount);
-    computer.ra = ra;
-    computer.rx = rx;
-});
+//! The instructions of the core instruction set, one type for each, made by
+//! `create_instruction!`, and the `Instruction` trait a computer executes them through.
+
+use crate::computer::{Accumulator, BlockWrite, Computer, ComparisonFlag, UndefinedPolicy, UndefinedWarning};
+use crate::word::{Word};
+use crate::field::FieldSpec;
+use crate::error::MixError;
+use crate::instruction_functions::*;
+use crate::peripherals::BLOCK_WORDS;
+use crate::charset::DIGIT_ZERO;
+use crate::trace::Direction;
+use crate::consts::{JumpCondition, RegisterCondition};
+use std::convert::TryFrom;
+
+
+/// Provides a useful macro for creating instructions, so that the amount 
+/// of copy and paste code is minimized. 
+/// 
+/// ## Arguments
+/// - `/// ...`: Doc comments describing what the instruction does. They document the
+/// generated struct and are returned by `semantics`, from which `docgen` writes the
+/// instruction reference.
+/// - Instruction Name: the name of the instruction being created. This is usually the 
+/// verbatim word used in MIX.
+/// - *Optional* `affects [Flag, ...]`: The `Flag`s the instruction may change, returned
+/// by `affects`.
+/// - *Optional* `parameter: type`: There is an optional list of paramters to be 
+/// used in each instruction definition. For this, just input the standard rust 
+/// definition of `parameter: type` pairings and they will be generated in the instruction 
+/// struct. 
+/// - `(self, computer) { ... }`: This is a mandatory block of code necessary 
+/// to make the instruction run. This block of code is macro for the `execute_on`
+/// implementation of the instruction for this specific instruction. The `(self, computer)` 
+/// is necessary before the block since these variables need to be included in 
+/// the function definition and macro expansions don't allow them to just be entered 
+/// in the macro by default. The block may `return Err(...)` to fail with a `MixError`;
+/// otherwise the instruction succeeds once the block completes.
+///
+/// A parameter may be followed by `where rule`, naming one of the `params` functions
+/// below, which `try_new` checks the parameter with. Decoding builds instructions with
+/// `new_unchecked` instead, as the word it decodes was already checked.
+macro_rules! create_instruction {
+    ($(#[doc = $doc:literal])* $i:ident $(affects [$($flag:ident),*])?, ($s:ident, $c:ident) $body:block) => {
+        $(#[doc = $doc])*
+        pub struct $i {}
+        impl $i {
+            pub fn new() -> $i { $i {} }
+        }
+        impl Instruction for $i {
+            fn execute_on(&$s, $c: &mut Computer) -> Result<(), MixError> {
+                $body
+                Ok(())
+            }
+
+            fn semantics(&self) -> &'static str {
+                concat!($($doc, "\n"),*)
+            }
+
+            fn affects(&self) -> &'static [$crate::instruction::Flag] {
+                &[$($($crate::instruction::Flag::$flag),*)?]
+            }
+        }
+    };
+    ($(#[doc = $doc:literal])* $i:ident $(affects [$($flag:ident),*])?, $($v:ident: $t:ty $(where $rule:path)?),*, ($s:ident, $c:ident) $body:block) => {
+        $(#[doc = $doc])*
+        pub struct $i {
+            $(pub $v: $t),*
+        }
+        impl $i {
+            /// Constructs the instruction after checking its parameters.
+            ///
+            /// ## Errors
+            /// - The first parameter outside the values the instruction can execute with.
+            pub fn try_new($($v: $t),*) -> Result<$i, $crate::error::InstructionParamError> {
+                $($(
+                    $rule(&$v).map_err(|allowed| $crate::error::InstructionParamError {
+                        instruction: stringify!($i),
+                        parameter: stringify!($v),
+                        value: format!("{:?}", $v),
+                        allowed,
+                    })?;
+                )?)*
+                Ok($i::new_unchecked($($v),*))
+            }
+
+            pub(crate) fn new_unchecked($($v: $t),*) -> $i {
+                $i {
+                    $($v: $v),*
+                }
+            }
+        }
+        impl Instruction for $i {
+            fn execute_on(&$s, $c: &mut Computer) -> Result<(), MixError> {
+                $body
+                Ok(())
+            }
+
+            fn semantics(&self) -> &'static str {
+                concat!($($doc, "\n"),*)
+            }
+
+            fn affects(&self) -> &'static [$crate::instruction::Flag] {
+                &[$($($crate::instruction::Flag::$flag),*)?]
+            }
+        }
+    };
+}
+
+/// The rules `try_new` checks parameters with. Each gives the values it allows when the
+/// value it is given isn't one of them.
+pub mod params {
+    use crate::computer::MAX_MEMORY_DECLARED;
+    use crate::consts::{JumpCondition, RegisterCondition, UnitNumber, MAX_MOVE_COUNT};
+
+    pub fn address(value: &usize) -> Result<(), String> {
+        if *value < MAX_MEMORY_DECLARED { Ok(()) } else { Err(format!("below {}", MAX_MEMORY_DECLARED)) }
+    }
+
+    /// An index register, rI1 to rI6.
+    pub fn index(value: &u8) -> Result<(), String> {
+        within(*value, 1, 6)
+    }
+
+    pub fn unit(value: &u8) -> Result<(), String> {
+        within(*value, 0, UnitNumber::LAST)
+    }
+
+    /// The number of words `MOVE` moves.
+    pub fn move_count(value: &u8) -> Result<(), String> {
+        within(*value, 0, MAX_MOVE_COUNT)
+    }
+
+    /// The condition of `JL` to `JLE`, as the field of its word.
+    pub fn comparison_jump(value: &u8) -> Result<(), String> {
+        within(*value, JumpCondition::Less.field(), JumpCondition::LessOrEqual.field())
+    }
+
+    /// The condition of a jump on a register, as the field of its word.
+    pub fn register_jump(value: &u8) -> Result<(), String> {
+        within(*value, RegisterCondition::Negative.field(), RegisterCondition::NonPositive.field())
+    }
+
+    fn within(value: u8, low: u8, high: u8) -> Result<(), String> {
+        if (low..=high).contains(&value) { Ok(()) } else { Err(format!("{} to {}", low, high)) }
+    }
+}
+
+/// MARK: Instructions
+
+/// A flag of the machine an instruction may change.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Flag {
+    Overflow,
+    Comparison,
+}
+
+impl Flag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::Overflow => "overflow",
+            Flag::Comparison => "comparison",
+        }
+    }
+}
+
+/// An operation a decoded word executes. The trait is open: the instructions of an
+/// extension implement it, and reach the computer through the decoders given to
+/// `Computer::register_extension`, rather than by decoding of their own.
+pub trait Instruction {
+    fn execute_on(&self, computer: &mut Computer) -> Result<(), MixError>;
+
+    /// What the instruction does, as given by the doc comments of its
+    /// `create_instruction!`, one line to each.
+    fn semantics(&self) -> &'static str;
+
+    /// The flags the instruction may change.
+    fn affects(&self) -> &'static [Flag];
+}
+
+create_instruction!(
+    /// Does nothing.
+    NoOperation, (self, _c) {});
+
+create_instruction!(
+    /// Stops the machine.
+    Halt, (self, computer) { computer.halt(); });
+
+create_instruction!(
+    /// Copies field F of the word at M into rA, the sign only when F includes byte 0. `LDAN`
+    /// does the same and then inverts the sign of rA.
+    LoadA, address: usize where params::address, field_specification: FieldSpec, negative: bool, (self, computer) {
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let ra =  &mut computer.ra;
+    copy_word_fields(&mem, ra, self.field_specification);
+    if self.negative { ra.positive = !ra.positive; }
+    if self.field_specification == FieldSpec::WHOLE { computer.define_accumulator(Accumulator::A); }
+});
+
+create_instruction!(
+    /// Copies field F of the word at M into rX, the sign only when F includes byte 0. `LDXN`
+    /// does the same and then inverts the sign of rX.
+    LoadX, address: usize where params::address, field_specification: FieldSpec, negative: bool, (self, computer) {
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let rx =  &mut computer.rx;
+    copy_word_fields(&mem, rx, self.field_specification);
+    if self.negative { rx.positive = !rx.positive; }
+    if self.field_specification == FieldSpec::WHOLE { computer.define_accumulator(Accumulator::X); }
+});
+
+create_instruction!(
+    /// Copies field F of the word at M into rIi, the sign only when F includes byte 0. `LDiN`
+    /// does the same and then inverts the sign of rIi. A value that doesn't fit the two bytes
+    /// of an index register is undefined: it faults, or is truncated with a warning, as the
+    /// computer's `UndefinedPolicy` says.
+    LoadI, index: u8 where params::index, address: usize where params::address, field_specification: FieldSpec, negative: bool, (self, computer) {
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let (pc, policy) = (computer.pc, computer.undefined_policy);
+    if let Err(value) = copy_word_fields_i(&mem, register_for_index(computer, self.index)?, self.field_specification) {
+        match policy {
+            UndefinedPolicy::Fault => return Err(MixError::IndexRegisterOverflow { pc, index: self.index, value }),
+            UndefinedPolicy::Truncate => {
+                set_index_register(register_for_index(computer, self.index)?, &value);
+                computer.warn_undefined(UndefinedWarning {
+                    pc,
+                    operation: format!("LD{}", self.index),
+                    detail: format!("[{}] doesn't fit in an index register and was truncated", value),
+                });
+            },
+        }
+    }
+    let ri = register_for_index(computer, self.index)?;
+    if self.negative { ri.positive = !ri.positive; }
+});
+
+create_instruction!(
+    /// Replaces field F of the word at M with the same number of bytes from the right of rA,
+    /// and its sign with that of rA when F includes byte 0.
+    StoreA, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.check_code_write(self.address, self.field_specification)?;
+    computer.read_accumulator(Accumulator::A)?;
+    let mut word = computer.word_at(self.address)?;
+    store_operation(&computer.ra, &mut word, self.field_specification);
+    computer.store_field(self.address, word, self.field_specification)?;
+});
+
+create_instruction!(
+    /// Replaces field F of the word at M with the same number of bytes from the right of rX,
+    /// and its sign with that of rX when F includes byte 0.
+    StoreX, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.check_code_write(self.address, self.field_specification)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let mut word = computer.word_at(self.address)?;
+    store_operation(&computer.rx, &mut word, self.field_specification);
+    computer.store_field(self.address, word, self.field_specification)?;
+});
+
+create_instruction!(
+    /// Replaces field F of the word at M with the same number of bytes from the right of rIi,
+    /// and its sign with that of rIi when F includes byte 0.
+    StoreI, index: u8 where params::index, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.check_code_write(self.address, self.field_specification)?;
+    let mut word = computer.word_at(self.address)?;
+    let ri =  register_for_index(computer, self.index)?;
+    let reg_clone = ri.clone();
+    store_operation(
+        &reg_clone, 
+        &mut word, 
+        self.field_specification
+        );    
+    computer.store_field(self.address, word, self.field_specification)?;
+});
+
+create_instruction!(
+    /// Replaces field F of the word at M with the same number of bytes from the right of rJ,
+    /// and its sign with + when F includes byte 0. The field is normally (0:2), the address
+    /// of a word.
+    StoreJ, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.check_code_write(self.address, self.field_specification)?;
+    let mut word = computer.word_at(self.address)?;
+    store_operation(&computer.rj, &mut word, self.field_specification);
+    computer.store_field(self.address, word, self.field_specification)?;
+});
+
+create_instruction!(
+    /// Replaces field F of the word at M with zeros, and its sign with + when F includes
+    /// byte 0.
+    StoreZ, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.check_code_write(self.address, self.field_specification)?;
+    let zero = Word::default();
+    let mut word = computer.word_at(self.address)?;
+    store_operation(&zero, &mut word, self.field_specification);
+    computer.store_field(self.address, word, self.field_specification)?;
+});
+
+create_instruction!(
+    /// Adds field F of the word at M to rA. A sum too large for rA keeps its low five bytes
+    /// and turns the overflow toggle on.
+    Add affects [Overflow], address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let (value, overflow) = add_words_in_base(&computer.ra, &mem, self.field_specification, computer.byte_size());
+    copy_word_fields(&value, &mut computer.ra, self.field_specification);
+    computer.overflow_flag |= overflow;
+});
+
+create_instruction!(
+    /// Subtracts field F of the word at M from rA. A difference too large for rA keeps its low
+    /// five bytes and turns the overflow toggle on.
+    Sub affects [Overflow], address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let (value, overflow) = subtract_words_in_base(&computer.ra, &mem, self.field_specification, computer.byte_size());
+    copy_word_fields(&value, &mut computer.ra, self.field_specification);
+    computer.overflow_flag |= overflow;
+});
+
+create_instruction!(
+    /// Multiplies rA by field F of the word at M, leaving the ten-byte product in rA and rX,
+    /// most significant bytes in rA. Both registers take the sign of the product.
+    Mult, address: usize where params::address, field_specification: FieldSpec , (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let (upper_value, lower_value) = multiply_words_in_base(&computer.ra, &mem, self.field_specification, computer.byte_size());
+    copy_word_fields(&lower_value, &mut computer.rx, FieldSpec::WHOLE);
+    copy_word_fields(&upper_value, &mut computer.ra, FieldSpec::WHOLE);
+    computer.define_accumulator(Accumulator::X);
+});
+
+create_instruction!(
+    /// Divides rA and rX, taken together as a ten-byte number, by field F of the word at M,
+    /// leaving the quotient in rA and the remainder in rX. Division by zero, or a quotient
+    /// too large for rA, turns the overflow toggle on and leaves both registers undefined.
+    Div affects [Overflow], address: usize where params::address, field_specification: FieldSpec , (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let (dividend, remainder, overflow) = divide_words_in_base(&computer.ra, &computer.rx, &mem, self.field_specification, computer.byte_size());
+    copy_word_fields(&remainder, &mut computer.rx, FieldSpec::WHOLE);
+    copy_word_fields(&dividend, &mut computer.ra, FieldSpec::WHOLE);
+    computer.overflow_flag |= overflow;
+    if overflow { computer.poison_accumulators(); }
+});
+
+create_instruction!(
+    /// Sets rA to M, with the sign of the instruction word when M is zero. `ENNA` sets rA to
+    /// -M instead.
+    EntA, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    copy_word_fields(&word, &mut computer.ra, FieldSpec::WHOLE);
+    computer.define_accumulator(Accumulator::A);
+});
+
+create_instruction!(
+    /// Sets rX to M, with the sign of the instruction word when M is zero. `ENNX` sets rX to
+    /// -M instead.
+    EntX, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    copy_word_fields(&word, &mut computer.rx, FieldSpec::WHOLE);
+    computer.define_accumulator(Accumulator::X);
+});
+
+create_instruction!(
+    /// Sets rIi to M, with the sign of the instruction word when M is zero. `ENNi` sets rIi to
+    /// -M instead.
+    EntI, index: u8 where params::index, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    set_index_register(register_for_index(computer, self.index)?, &word);
+});
+
+create_instruction!(
+    /// Adds M to rA, or with `DECA` subtracts it. A result too large for rA keeps its low five
+    /// bytes and turns the overflow toggle on.
+    IncA affects [Overflow], value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    let (value, overflow) = add_words_in_base(&computer.ra, &word, FieldSpec::WHOLE, computer.byte_size());
+    copy_word_fields(&value, &mut computer.ra, FieldSpec::WHOLE);
+    computer.overflow_flag |= overflow;
+});
+
+create_instruction!(
+    /// Adds M to rX, or with `DECX` subtracts it. A result too large for rX keeps its low five
+    /// bytes and turns the overflow toggle on.
+    IncX affects [Overflow], value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    computer.read_accumulator(Accumulator::X)?;
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    let (value, overflow) = add_words_in_base(&computer.rx, &word, FieldSpec::WHOLE, computer.byte_size());
+    copy_word_fields(&value, &mut computer.rx, FieldSpec::WHOLE);
+    computer.overflow_flag |= overflow;
+});
+
+create_instruction!(
+    /// Adds M to rIi, or with `DECi` subtracts it. A result that doesn't fit the two bytes of
+    /// an index register is undefined: it faults, or is truncated with a warning, as the
+    /// computer's `UndefinedPolicy` says. The overflow toggle is never changed.
+    IncI, index: u8 where params::index, value: usize, entry_is_positive: bool, should_negate: bool, (self, computer) {
+    let mut word = Word::from_value_in_base(self.value as i64, computer.byte_size());
+    word.positive = if self.should_negate { !self.entry_is_positive } else { self.entry_is_positive };
+    let (pc, policy, byte_size) = (computer.pc, computer.undefined_policy, computer.byte_size());
+    let ri = register_for_index(computer, self.index)?;
+    let (value, overflow) = add_words_in_base(ri, &word, FieldSpec::WHOLE, byte_size);
+    // Knuth leaves a result past the two bytes of an index register undefined, as for
+    // LDi, and INCi and DECi never set overflow.
+    if overflow || value.bytes[..3] != [0, 0, 0] {
+        if policy == UndefinedPolicy::Fault {
+            return Err(MixError::IndexRegisterOverflow { pc, index: self.index, value });
+        }
+        computer.warn_undefined(UndefinedWarning {
+            pc,
+            operation: computer.current_operation(),
+            detail: format!("[{}] doesn't fit in an index register and was truncated", value),
+        });
+    }
+    set_index_register(register_for_index(computer, self.index)?, &value);
+});
+
+create_instruction!(
+    /// Compares field F of rA with field F of the word at M as signed numbers, setting the
+    /// comparison indicator to less, equal, or greater. +0 and -0 are equal.
+    CmpA affects [Comparison], address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let result = compare_words(&computer.ra, &mem, self.field_specification);
+    computer.comparison_flag = result;
+});
+
+create_instruction!(
+    /// Compares field F of rX with field F of the word at M as signed numbers, setting the
+    /// comparison indicator to less, equal, or greater. +0 and -0 are equal.
+    CmpX affects [Comparison], address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    computer.read_accumulator(Accumulator::X)?;
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let result = compare_words(&computer.rx, &mem, self.field_specification);
+    computer.comparison_flag = result;
+});
+
+create_instruction!(
+    /// Compares field F of rIi with field F of the word at M as signed numbers, setting the
+    /// comparison indicator to less, equal, or greater. +0 and -0 are equal.
+    CmpI affects [Comparison], index: u8 where params::index, address: usize where params::address, field_specification: FieldSpec, (self, computer) {
+    let mem = computer.read_field(self.address, self.field_specification)?;
+    let ri =  register_for_index(computer, self.index)?;
+    let result = compare_words(&ri, &mem, self.field_specification);
+    computer.comparison_flag = result;
+});
+
+create_instruction!(
+    /// Jumps to M, setting rJ to the address of the next instruction. `JSJ` jumps without
+    /// changing rJ.
+    Jmp, address: usize where params::address, save_address: bool, (self, computer) {
+    if self.save_address {
+        save_jump(computer);
+    }
+    jump_to(computer, self.address);
+});
+
+create_instruction!(
+    /// `JOV` jumps to M when the overflow toggle is on, and `JNOV` when it is off, setting rJ
+    /// when they jump. Either turns the toggle off.
+    JmpO affects [Overflow], address: usize where params::address, should_negate: bool, (self, computer) {
+    if computer.overflow_flag.clone() != self.should_negate {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+    computer.overflow_flag = false;
+});
+
+pub fn condition_match(op: u8, condition: ComparisonFlag) -> bool {
+    match RegisterCondition::try_from(op) {
+        Ok(RegisterCondition::Negative) => condition == ComparisonFlag::less,
+        Ok(RegisterCondition::Zero) => condition == ComparisonFlag::equal,
+        Ok(RegisterCondition::Positive) => condition == ComparisonFlag::greater,
+        Ok(RegisterCondition::NonNegative) => condition != ComparisonFlag::less,
+        Ok(RegisterCondition::NonZero) => condition != ComparisonFlag::equal,
+        Ok(RegisterCondition::NonPositive) => condition != ComparisonFlag::greater,
+        Err(_) => false,
+    }
+}
+
+create_instruction!(
+    /// Jumps to M when the comparison indicator is less (`JL`), equal (`JE`), greater (`JG`),
+    /// not less (`JGE`), not equal (`JNE`), or not greater (`JLE`), setting rJ when it jumps.
+    JmpC, address: usize where params::address, operation: u8 where params::comparison_jump, (self, computer) {
+    let condition = condition_match(self.operation - JumpCondition::Less.field(), computer.comparison_flag);
+    if condition {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+});
+
+create_instruction!(
+    /// Jumps to M when rA is negative (`JAN`), zero (`JAZ`), positive (`JAP`), nonnegative
+    /// (`JANN`), nonzero (`JANZ`), or nonpositive (`JANP`), setting rJ when it jumps. -0 is
+    /// zero.
+    JmpA, address: usize where params::address, operation: u8 where params::register_jump, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let zero = Word::default();
+    let result = compare_words(&computer.ra, &zero, FieldSpec::WHOLE);
+    let condition = condition_match(self.operation, result);
+    if condition {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+});
+
+create_instruction!(
+    /// Jumps to M when rX is negative (`JXN`), zero (`JXZ`), positive (`JXP`), nonnegative
+    /// (`JXNN`), nonzero (`JXNZ`), or nonpositive (`JXNP`), setting rJ when it jumps. -0 is
+    /// zero.
+    JmpX, address: usize where params::address, operation: u8 where params::register_jump, (self, computer) {
+    computer.read_accumulator(Accumulator::X)?;
+    let zero = Word::default();
+    let result = compare_words(&computer.rx, &zero, FieldSpec::WHOLE);
+    let condition = condition_match(self.operation, result);
+    if condition {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+});
+
+create_instruction!(
+    /// Jumps to M when rIi is negative (`JiN`), zero (`JiZ`), positive (`JiP`), nonnegative
+    /// (`JiNN`), nonzero (`JiNZ`), or nonpositive (`JiNP`), setting rJ when it jumps. -0 is
+    /// zero.
+    JmpI, index: u8 where params::index, address: usize where params::address, operation: u8 where params::register_jump, (self, computer) {
+    let zero = Word::default();
+    let ri =  register_for_index(computer, self.index)?;
+    let result = compare_words(&ri, &zero, FieldSpec::WHOLE);
+    let condition = condition_match(self.operation, result);
+    if condition {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA left by M, bringing in zeros at the right. The sign is unchanged.
+    SLA, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let r = computer.ra.clone();
+    computer.ra = single_word_left_shift(&r, self.amount, false);
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA right by M, bringing in zeros at the left. The sign is unchanged.
+    SRA, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let r = computer.ra.clone();
+    computer.ra = single_word_right_shift(&r, self.amount, false);
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA and rX, taken together as ten bytes with rA on the left, left by
+    /// M, bringing in zeros at the right. The signs are unchanged.
+    SLAX, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let a = computer.ra.clone();
+    let x = computer.rx.clone();
+    let (ra, rx) = double_word_left_shift(&a, &x, self.amount);
+    computer.ra = ra;
+    computer.rx = rx;
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA and rX, taken together as ten bytes with rA on the left, right
+    /// by M, bringing in zeros at the left. The signs are unchanged.
+    SRAX, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let a = computer.ra.clone();
+    let x = computer.rx.clone();
+    let (ra, rx) = double_word_right_shift(&a, &x, self.amount);
+    computer.ra = ra;
+    computer.rx = rx;
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA and rX, taken together as ten bytes with rA on the left, left by
+    /// M circularly, so that the bytes leaving rA on the left come back at the right of rX.
+    /// The signs are unchanged.
+    SLC, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let a = computer.ra;
+    let x = computer.rx;
+    let (ra, rx) = double_word_left_cycle(&a, &x, self.amount);
+    computer.ra = ra;
+    computer.rx = rx;
+});
+
+create_instruction!(
+    /// Shifts the bytes of rA and rX, taken together as ten bytes with rA on the left, right
+    /// by M circularly, so that the bytes leaving rX on the right come back at the left of rA.
+    /// The signs are unchanged.
+    SRC, amount: usize, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let a = computer.ra;
+    let x = computer.rx;
+    let (ra, rx) = double_word_right_cycle(&a, &x, self.amount);
+    computer.ra = ra;
+    computer.rx = rx;
+});
+
+create_instruction!(
+    /// Sets the magnitude of rA to the ten bytes of rA and rX read as the digits of a decimal
+    /// number, each byte standing for its last decimal digit, so that the character codes of
+    /// the digits, and those of the letters punched over them, read as the digits. A number
+    /// past the five bytes of rA keeps its remainder and sets overflow. The sign of rA and rX
+    /// are unchanged.
+    Num affects [Overflow], (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    computer.read_accumulator(Accumulator::X)?;
+    let number = computer.ra.bytes.iter().chain(computer.rx.bytes.iter())
+        .fold(0i128, |number, byte| number * 10 + (*byte % 10) as i128);
+    let modulus = (computer.byte_size() as i128).pow(5);
+    if number >= modulus {
+        computer.overflow_flag = true;
+    }
+    computer.ra.bytes = Word::from_value_in_base((number % modulus) as i64, computer.byte_size()).bytes;
+});
+
+create_instruction!(
+    /// Sets the ten bytes of rA and rX to the character codes of the last ten decimal digits
+    /// of the magnitude of rA, rA holding the first five. The sign of rA and rX are unchanged.
+    Char, (self, computer) {
+    computer.read_accumulator(Accumulator::A)?;
+    let mut number = computer.ra.field_value_in_base(FieldSpec::MAGNITUDE, computer.byte_size());
+    let mut codes = [0u8; 10];
+    for code in codes.iter_mut().rev() {
+        *code = DIGIT_ZERO + (number % 10) as u8;
+        number /= 10;
+    }
+    computer.ra.bytes.copy_from_slice(&codes[..5]);
+    computer.rx.bytes.copy_from_slice(&codes[5..]);
+    computer.define_accumulator(Accumulator::X);
+});
+
+create_instruction!(
+    /// Copies F words, one at a time, from those from M to those from the address in rI1,
+    /// then adds F to rI1. Copying one word at a time, a copy onto words it has yet to read
+    /// copies again the words it has just written. F is at most 63, and with F = 0 nothing
+    /// is copied and rI1 is left alone. Both ranges, the writes, and the sum in rI1 are
+    /// checked before the first word is copied, so a `MOVE` that faults copies none. A sum
+    /// that doesn't fit the two bytes of an index register is undefined: it faults, or is
+    /// truncated with a warning, as the computer's `UndefinedPolicy` says.
+    Move, address: usize where params::address, count: u8 where params::move_count, (self, computer) {
+    let (pc, count, size) = (computer.pc, self.count as usize, computer.memory_size());
+    if count == 0 {
+        return Ok(());
+    }
+    if self.address.checked_add(count).filter(|end| *end <= size).is_none() {
+        return Err(MixError::AddressOutOfRange { pc, address: self.address as i64 + count as i64 - 1 });
+    }
+    let destination = computer.ri1.field_value_in_base(FieldSpec::WHOLE, computer.byte_size());
+    let end = match usize::try_from(destination).ok().and_then(|destination| destination.checked_add(count)) {
+        Some(end) if end <= size => end,
+        _ => return Err(MixError::AddressOutOfRange { pc, address: destination + count as i64 - 1 }),
+    };
+    let block = BlockWrite::check(computer, end - count, count)?;
+    let increased = Word::from_value_in_base(end as i64, computer.byte_size());
+    let fits = increased.bytes[..3] == [0, 0, 0];
+    if !fits && computer.undefined_policy == UndefinedPolicy::Fault {
+        return Err(MixError::IndexRegisterOverflow { pc, index: 1, value: increased });
+    }
+    for (offset, destination) in block.range.clone().enumerate() {
+        computer.memory[destination] = computer.read_field(self.address + offset, FieldSpec::WHOLE)?;
+    }
+    block.finish(computer);
+    computer.words_moved = count as u64;
+    if !fits {
+        computer.warn_undefined(UndefinedWarning {
+            pc,
+            operation: "MOVE".to_string(),
+            detail: format!("[{}] doesn't fit in an index register and was truncated", increased),
+        });
+    }
+    set_index_register(&mut computer.ri1, &increased);
+});
+
+create_instruction!(
+    /// Jumps to M when unit F is busy, setting rJ. A unit with no device attached is never
+    /// busy under `UnattachedPolicy::Lenient`.
+    JumpBusy, unit: u8 where params::unit, address: usize where params::address, (self, computer) {
+    device_for_unit(computer, self.unit)?;
+    if computer.is_busy(self.unit) {
+        if computer.is_device_busy(self.unit) {
+            computer.block_on(self.unit);
+        }
+        save_jump(computer);
+        jump_to(computer, self.address);
+    }
+});
+
+create_instruction!(
+    /// Jumps to M when unit F is ready, setting rJ. A unit with no device attached is always
+    /// ready under `UnattachedPolicy::Lenient`.
+    JumpReady, unit: u8 where params::unit, address: usize where params::address, (self, computer) {
+    device_for_unit(computer, self.unit)?;
+    if !computer.is_busy(self.unit) {
+        save_jump(computer);
+        jump_to(computer, self.address);
+    } else if computer.is_device_busy(self.unit) {
+        computer.block_on(self.unit);
+    }
+});
+
+create_instruction!(
+    /// Waits for unit F to be ready, then sends it the control operation M: rewinding or
+    /// skipping on a tape, seeking on a disk, or a new page on the line printer. Does
+    /// nothing to a unit with no device attached under `UnattachedPolicy::Lenient`.
+    IoControl, unit: u8 where params::unit, amount: i64, (self, computer) {
+    let device = match device_for_unit(computer, self.unit)? {
+        Some(device) => device,
+        None => return Ok(()),
+    };
+    computer.wait_for(self.unit)?;
+    check_device_ready(computer, self.unit)?;
+    device.borrow_mut().set_time(computer.time());
+    device.borrow_mut().control(self.amount).map_err(|error| device_error(computer, self.unit, error))?;
+    computer.record_io(self.unit, Direction::Control, 0..0);
+});
+
+create_instruction!(
+    /// Waits for unit F to be ready, then starts reading a block from it into the words from
+    /// M, of as many words as the unit gives at a time. The unit is busy until the transfer
+    /// finishes. A unit with no device attached under `UnattachedPolicy::Lenient` gives a
+    /// block of +0 at once.
+    Input, unit: u8 where params::unit, address: usize where params::address, (self, computer) {
+    let device = match device_for_unit(computer, self.unit)? {
+        Some(device) => device,
+        None => {
+            let block = BlockWrite::check(computer, self.address, BLOCK_WORDS)?;
+            computer.memory[block.range.clone()].fill(Word::default());
+            block.finish_transfer(computer, self.unit, Direction::In);
+            return Ok(());
+        },
+    };
+    computer.wait_for(self.unit)?;
+    check_device_ready(computer, self.unit)?;
+    let words = device.borrow().block_words();
+    let block = BlockWrite::check(computer, self.address, words)?;
+    device.borrow_mut().set_time(computer.time());
+    let byte_size = computer.byte_size();
+    device.borrow_mut().read_block_into(&mut computer.memory[block.range.clone()], byte_size)
+        .map_err(|error| device_error(computer, self.unit, error))?;
+    block.finish_transfer(computer, self.unit, Direction::In);
+    computer.start_transfer(self.unit, words);
+});
+
+create_instruction!(
+    /// Waits for unit F to be ready, then starts writing the block of words from M to it, of
+    /// as many words as the unit takes at a time. The unit is busy until the transfer
+    /// finishes. Does nothing with a unit with no device attached under
+    /// `UnattachedPolicy::Lenient`.
+    Output, unit: u8 where params::unit, address: usize where params::address, (self, computer) {
+    let device = match device_for_unit(computer, self.unit)? {
+        Some(device) => device,
+        None => return Ok(()),
+    };
+    computer.wait_for(self.unit)?;
+    check_device_ready(computer, self.unit)?;
+    computer.count_block_written(self.unit)?;
+    let words = device.borrow().block_words();
+    let range = computer.block_range(self.address, words)?;
+    computer.start_output(self.unit, range)?;
+});
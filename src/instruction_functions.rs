@@ -1,97 +1,72 @@
+//! The word arithmetic, field copying, and device lookups the instructions are built
+//! from. They are details of how the instructions are implemented, visible to the crate
+//! alone: what a program can count on is what the instructions do, and `Word` has the
+//! arithmetic meant for use outside.
+
 use crate::word::Word;
+use crate::field::FieldSpec;
 use crate::computer::{Computer, ComparisonFlag};
-use std::convert::TryInto;
+use std::cmp::Ordering;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::error::MixError;
+#[cfg(test)]
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::peripherals::{Peripheral, TapeError};
 
-/// Provides a useful macro for checking conditions involving adjusted field 
-/// specifications. 
-/// 
-/// The `word_zero_condition` checks first if the value zero is included in 
-/// the field specification. If it is, the positive of the receiving word is adjusted 
-/// to match that of the sending word. After that, the macro checks if 
-/// zero is the *only* part of the adjusted field specification, in which 
-/// the function is told to terminate since there is no further action to be 
-/// taken.
+/// Copies the individual bytes from one word to another, given their field specification. 
 /// 
 /// ## Arguments
-/// 
-/// - `zero_included`: Boolean of whether or not the value 0 is included in the 
-/// adjusted field specification. 
-/// - `only_zero`: Boolean of whether or not the value 0 is the only value in 
-/// the adjusted field specification. 
-/// - `from_word`: The sending word in the comparison. This is used to get the 
-/// `positive` of the sending word to adjust the receiver if necessary. 
-/// - `to_word`: The receiving word in the comparison. This is necessary 
-/// so its `positive` value can be adjusted if zero is part of the adjusted field 
-/// specification.
-/// 
-macro_rules! word_zero_condition {
-    ($z:ident, $o:ident, $f:ident, $t:ident) => {
-        if $z {
-            $t.positive = $f.positive;
-            if $o {
-                return;
-            }
-        }
+/// - `from_word`: A reference to the sending word. 
+/// - `to_word`: A mutable reference to the receiving word.
+/// - `field_specification`: The field to copy, the sign only when it includes 0.
+pub(crate) fn copy_word_fields(from_word: &Word, to_word: &mut Word, field_specification: FieldSpec) {
+    to_word.copy_sign(from_word, field_specification);
+    for i in field_specification.byte_indices() {
+        to_word.bytes[i] = from_word.bytes[i];
     }
 }
 
-/// Provides a useful conversion for the field specification of a MIX command. 
-/// 
-/// In the documentation for MIX, a word is laid out from left to right as the 
-/// indices 0-6, with the 0-byte indicating the positive. In the program implementation,
-/// we instead have the positive byte as just a boolean value, while the rest of the 
-/// bytes are the 0-5 indices of the word. This function takes the given 
-/// field specification and gives us the tuple informing us of critical properties.
-/// 
+/// Loads a field of a word as the load instructions define it: the bytes of the field
+/// are moved to the right of the result, and the sign is taken from the word only when
+/// the field includes it, being + otherwise.
+///
 /// ## Arguments
-/// 
-/// - `field_specification`: An un-adjusted field specification, i.e. a field spec 
-/// not previously run through this function.
-/// 
-/// ## Returns 
-/// 
-/// - `(zero_is_included, only_zero, (left_adjusted_index, right_adjusted_index))`. 
-pub fn adjusted_field_specification(field_specification: (usize, usize)) -> (bool, bool, (usize, usize)) {
-    let (left, right) = field_specification;
-    let (l, r) = (left.max(1) - 1, right.max(1) - 1);
-    (left == 0, right == 0, (l, r))
+/// - `from_word`: A reference to the word being loaded from.
+/// - `field_specification`: The field to load.
+pub(crate) fn load_field(from_word: &Word, field_specification: FieldSpec) -> Word {
+    let mut word = Word::default();
+    word.copy_sign(from_word, field_specification);
+    word.bytes[(5 - field_specification.width())..].copy_from_slice(&from_word.bytes[field_specification.byte_indices()]);
+    word
 }
 
-/// Copies the individual bytes from one word to another, given their field specification. 
-/// 
-/// ## Arguments
-/// - `from_word`: A reference to the sending word. 
-/// - `to_word`: A mutable reference to the receiving word.
-/// - `field_specification`: An un-adjusted field specification for which fields should be copied.
-pub fn copy_word_fields(from_word: &Word, to_word: &mut Word, field_specification: (usize, usize)) {
-    let (zero_included, only_zero, (l, r)) = adjusted_field_specification(field_specification);
-    word_zero_condition!(zero_included, only_zero, from_word, to_word);
-    for i in (l)..=(r) {
-        to_word.bytes[i] = from_word.bytes[i];
-    }
+/// Places the sign and bytes 4 and 5 of `value` in an index register, which holds
+/// nothing else, dropping the rest of `value`.
+pub(crate) fn set_index_register(to_word: &mut Word, value: &Word) {
+    to_word.positive = value.positive;
+    to_word.bytes = [0, 0, 0, value.bytes[3], value.bytes[4]];
 }
 
-/// Copies the individual bytes from one word to another, given their field specification. 
-/// As per the description of index registers, the function fails if the field
-/// specification does not contain valid index register values (0, 4, or 5).
-/// 
+/// Loads a field of one word into an index register, as `LDi` does. The field is loaded
+/// as into any other register, and the result must then fit in the two bytes and sign
+/// of the index register.
+///
 /// ## Arguments
-/// - `from_word`: A reference to the sending word. 
-/// - `to_word`: A mutable reference to the receiving word.
-/// - `field_specification`: An un-adjusted field specification for which fields should be copied.
-/// 
-/// ## Panics 
-/// Panics whenever the field specification does not contain either 0, 4, or 5, since 
-/// those are necessary for the index registers.
-pub fn copy_word_fields_i(from_word: &Word, to_word: &mut Word, field_specification: (usize, usize)) {
-    let (zero_included, only_zero, (l, r)) = adjusted_field_specification(field_specification);
-    if (0..=2).contains(&l) && (0..=2).contains(&r) && !zero_included {
-        panic!("[Error copy_word_fields_i] Invalid field specification given for index. Must include either 0, 4, or 5 (Given: {:?}).", field_specification);
-    }
-    word_zero_condition!(zero_included, only_zero, from_word, to_word);
-    for i in (l.max(3))..=(r.min(4)) {
-        to_word.bytes[i] = from_word.bytes[i];
-    }
+/// - `from_word`: A reference to the word being loaded from.
+/// - `to_word`: A mutable reference to the receiving index register.
+/// - `field_specification`: The field to load.
+///
+/// ## Errors
+/// - The loaded word, leaving `to_word` untouched, when any of its bytes 1 to 3 isn't
+///   zero. Knuth leaves the result undefined in that case.
+pub(crate) fn copy_word_fields_i(from_word: &Word, to_word: &mut Word, field_specification: FieldSpec) -> Result<(), Word> {
+    let loaded = load_field(from_word, field_specification);
+    if loaded.bytes[..3] != [0, 0, 0] {
+        return Err(loaded);
+    }
+    set_index_register(to_word, &loaded);
+    Ok(())
 }
 
 /// Stores the individual bytes from one register to a word, given their field specification. 
@@ -104,185 +79,209 @@ pub fn copy_word_fields_i(from_word: &Word, to_word: &mut Word, field_specificat
 /// ## Arguments
 /// - `from_word`: A reference to the storing register. 
 /// - `to_word`: A mutable reference to the receiving word of memory.
-/// - `field_specification`: An un-adjusted field specification for which fields should be copied.
-pub fn store_operation(from_word: &Word, to_word: &mut Word,  field_specification: (usize, usize)) {
-    let (zero_included, only_zero, (l, r)) = adjusted_field_specification(field_specification);
-    word_zero_condition!(zero_included, only_zero, from_word, to_word);
-
-    let offset_l = 4 - (r - l);
-    for i in 0..=(r-l) {
-        to_word.bytes[l + i] = from_word.bytes[offset_l + i];
-    } 
+/// - `field_specification`: The field of the receiving word to store into.
+pub(crate) fn store_operation(from_word: &Word, to_word: &mut Word, field_specification: FieldSpec) {
+    to_word.copy_sign(from_word, field_specification);
+    let width = field_specification.width();
+    to_word.bytes[field_specification.byte_indices()].copy_from_slice(&from_word.bytes[(5 - width)..]);
 }
 
 /// Matches the `index` to the corresponding index register, and returns a mutable 
 /// reference to that register.
 /// 
-/// ## TODO
-/// - Make the return value a `Result<&mut Word, ErrorThing>` so that error handling 
-/// can be better managed by other functions. This will need a refactor but it 
-/// should make debugging simpler once we have actual MIXAL code.
-/// 
 /// ## Arguments
 /// - `computer`: A mutable reference to the computer we are retrieving the index 
 /// from.
 /// - `index`: The number corresponding to the index register that we are using.
 /// It must be in the range 1-6.
 /// 
-/// ## Returns 
-/// - A mutable reference to the corresponding index register, if it is found. Panics otherwise.
-/// 
-/// ## Panics
-/// Panics when the index given in the argument is not in the range 1-6.
-pub fn register_for_index(computer: &mut Computer, index: u8) -> &mut Word {
-    let word = match index {
-        1 => &mut computer.ri1,
-        2 => &mut computer.ri2,
-        3 => &mut computer.ri3,
-        4 => &mut computer.ri4,
-        5 => &mut computer.ri5,
-        6 => &mut computer.ri6,
-        _ => {
-            // Throw error 
-            panic!("[Error register_for_index] Invalid index given for decode. Must be in the range 1-6 (Given {}).", index);
-        }
-    };
-    word
+/// ## Errors
+/// - `InvalidInstruction` when `index` is not in the range 1-6, which `decode::check_word`
+///   rejects before an instruction is built, but an instruction built by hand may hold.
+pub(crate) fn register_for_index(computer: &mut Computer, index: u8) -> Result<&mut Word, MixError> { no_panic!({
+    match index {
+        1 => Ok(&mut computer.ri1),
+        2 => Ok(&mut computer.ri2),
+        3 => Ok(&mut computer.ri3),
+        4 => Ok(&mut computer.ri4),
+        5 => Ok(&mut computer.ri5),
+        6 => Ok(&mut computer.ri6),
+        _ => Err(computer.invalid_index(index)),
+    }
+})}
+
+/// Adds the fields of two words given by `field_specification`, digit by digit.
+///
+/// ## Returns
+/// - `(sum, overflow)`, where the sum holds the result in the same field, and `overflow`
+///   is set when the result doesn't fit in the field. A sum of zero has the sign of
+///   `word1`, so that `-5 + 5` is -0 and `+0 - 0` is +0. A field of the sign alone has
+///   the value zero, so the sum is `word1`.
+#[cfg(test)]
+pub(crate) fn add_words(word1: &Word, word2: &Word, field_specification: FieldSpec) -> (Word, bool) {
+    add_words_in_base(word1, word2, field_specification, CONFIGURED_BYTE_SIZE as u16)
 }
 
-/// Adds two words 
-/// TODO: Document this
-pub fn add_words(word1: &Word, word2: &Word, field_specification: (usize, usize)) -> (Word, bool) {
-    let word1_value = word1.field_value(field_specification).clone();
-    let word2_value = word2.field_value(field_specification);
-    let mut word = Word::default();
-    let mut sum : i64 = word1_value + word2_value;
-
-    let (zero_included, only_zero, (l, r)) = adjusted_field_specification(field_specification);
-    if only_zero {
-        panic!("[Error add_words] Can't add two numbers solely by their sign. (Input given {:#?})", field_specification);
-    }
-
-    if zero_included {
-        word.positive = if sum >= 0 { true } else { false };
-    }
-
-    sum = sum.abs();
-    for i in (l..=r).rev() {
-        word.bytes[i] = (sum % 256) as u8;
-        sum = sum >> 8;
-    }
-
-    if sum != 0 {
-        return (word, true);
-    }
+/// As `add_words`, for bytes holding `byte_size` values.
+pub(crate) fn add_words_in_base(word1: &Word, word2: &Word, field_specification: FieldSpec, byte_size: u16) -> (Word, bool) {
+    combine_words(word1, word2, field_specification, false, byte_size)
+}
 
-    (word, false)
+/// Subtracts the field of `word2` from that of `word1`, as `add_words` adds them.
+///
+/// The field of `word2` is read first, as + when it leaves out the sign, and only its
+/// value negated, so that a field without the sign is subtracted rather than added, and
+/// -0 is subtracted as +0 is. A difference of zero has the sign of `word1`. In a field
+/// without the sign, a negative difference keeps only its magnitude. Bytes hold
+/// `byte_size` values.
+pub(crate) fn subtract_words_in_base(word1: &Word, word2: &Word, field_specification: FieldSpec, byte_size: u16) -> (Word, bool) {
+    combine_words(word1, word2, field_specification, true, byte_size)
 }
 
-/// TODO: Document this
-pub fn multiply_words(word1: &Word, word2: &Word, field_specification: (usize, usize)) -> (Word, Word) {
-    let word1_value = word1.field_value((0,5)).clone();
-    let word2_value = word2.field_value(field_specification);
-    let mut word_lower = Word::default();
-    let mut word_upper = Word::default();
-    let mut product : i128 = (word1_value as i128) * (word2_value as i128);
-
-    let (zero_included, only_zero, _) = adjusted_field_specification(field_specification);
-    if only_zero {
-        panic!("[Error multiply_words] Can't multiply two numbers solely by their positive. (Input given {:#?})", field_specification);
-    }
+/// The sum of the fields of `word1` and `word2`, or their difference when `subtract`, in
+/// bytes holding `byte_size` values.
+fn combine_words(word1: &Word, word2: &Word, field_specification: FieldSpec, subtract: bool, byte_size: u16) -> (Word, bool) {
+    let mut word = Word::default();
+    if field_specification.is_sign_only() {
+        return (*word1, false);
+    }
+
+    // Right-align both fields so that the digit loops of `Word` can work on them.
+    let (zero_included, bytes) = (field_specification.includes_sign(), field_specification.byte_indices());
+    let width = field_specification.width();
+    let mut magnitude1 = Word::default();
+    let mut magnitude2 = Word::default();
+    magnitude1.bytes[(5 - width)..].copy_from_slice(&word1.bytes[bytes.clone()]);
+    magnitude2.bytes[(5 - width)..].copy_from_slice(&word2.bytes[bytes.clone()]);
+    let positive1 = word1.positive || !zero_included;
+    let positive2 = (word2.positive || !zero_included) != subtract;
+
+    let (sum, carry, positive) = if positive1 == positive2 {
+        let (sum, carry) = magnitude1.add_with_carry_in_base(&magnitude2, false, byte_size);
+        (sum, carry, positive1)
+    } else if magnitude1.cmp_magnitude(&magnitude2) == Ordering::Less {
+        let (difference, _) = magnitude2.sub_with_borrow_in_base(&magnitude1, false, byte_size);
+        (difference, false, positive2)
+    } else {
+        let (difference, _) = magnitude1.sub_with_borrow_in_base(&magnitude2, false, byte_size);
+        (difference, false, positive1)
+    };
 
+    // A zero result keeps the sign of the first word, as a register added to keeps its
+    // sign when the sum is zero.
     if zero_included {
-        word_lower.positive = if product >= 0 { true } else { false };
-        word_upper.positive = word_lower.positive;
+        word.positive = if sum.bytes == [0; 5] { positive1 } else { positive };
     }
+    word.bytes[bytes].copy_from_slice(&sum.bytes[(5 - width)..]);
 
-    product = product.abs();
-    for i in (0..=4).rev() {
-        word_lower.bytes[i] = (product % 256) as u8;
-        product = product >> 8;
-    }
-    for i in (0..=4).rev() {
-        word_upper.bytes[i] = (product % 256) as u8;
-        product = product >> 8;
-    }
-
-    if product != 0 {
-        return (word_upper, word_lower);
-    }
-
-    (word_upper, word_lower)
+    let overflow = carry || sum.bytes[..(5 - width)].iter().any(|byte| *byte != 0);
+    (word, overflow)
 }
 
-/// TODO: Document this
-pub fn divide_words(word1: &Word, word2: &Word, word3: &Word, field_specification: (usize, usize)) -> (Word, Word, bool) {
-    let mut word_rem = Word::default();
-    let mut word_div = Word::default();
-    let divisor_value = word3.field_value(field_specification) as i128;
-    if divisor_value == 0 {
-        return (word_rem, word_div, true);
+/// Splits `value` into `n_digits` digits in base `byte_size`, most significant first,
+/// dropping any higher digits.
+pub(crate) fn split_to_digits(value: u128, byte_size: u16, n_digits: usize) -> Vec<u8> {
+    let mut digits = vec![0; n_digits];
+    let mut rest = value;
+    for digit in digits.iter_mut().rev() {
+        *digit = (rest % byte_size as u128) as u8;
+        rest /= byte_size as u128;
     }
+    digits
+}
 
-    let word1_value = word1.field_value((1,5)).clone() as i128;
-    let word_value = (word1_value << 40) | (word2.field_value((1,5)).clone() as i128);
+/// The value of `digits` in base `byte_size`, most significant first. This is the
+/// inverse of `split_to_digits`.
+pub(crate) fn digits_to_value(digits: &[u8], byte_size: u16) -> u128 {
+    digits.iter().fold(0, |value, digit| value * byte_size as u128 + *digit as u128)
+}
 
-    let mut dividend : i64 = ((word_value) / (divisor_value)) as i64;
-    let mut remainder : i64 = ((word_value) % (divisor_value)) as i64;
+/// Multiplies rA by the field of a word, as `MUL` does.
+///
+/// ## Arguments
+/// - `word1`: The multiplicand, taken whole, as in rA.
+/// - `word2`: The word holding the multiplier.
+/// - `field_specification`: The field of `word2` to multiply by.
+///
+/// ## Returns
+/// - `(upper, lower)`, the ten digits of the product split into the words for rA and rX,
+///   both taking the algebraic sign of the product. A field of the sign alone has the
+///   value zero, and its sign.
+#[cfg(test)]
+pub(crate) fn multiply_words(word1: &Word, word2: &Word, field_specification: FieldSpec) -> (Word, Word) {
+    multiply_words_in_base(word1, word2, field_specification, CONFIGURED_BYTE_SIZE as u16)
+}
 
-    let (zero_included, only_zero, _) = adjusted_field_specification(field_specification);
-    if only_zero {
-        panic!("[Error divide_words] Can't divide two numbers solely by their positive. (Input given {:#?})", field_specification);
-    }
+/// As `multiply_words`, for bytes holding `byte_size` values.
+pub(crate) fn multiply_words_in_base(word1: &Word, word2: &Word, field_specification: FieldSpec, byte_size: u16) -> (Word, Word) {
+    let multiplier = digits_to_value(&word2.bytes[field_specification.byte_indices()], byte_size);
+
+    let product = digits_to_value(&word1.bytes, byte_size) * multiplier;
+    let digits = split_to_digits(product, byte_size, 10);
+    let positive = word1.positive == (word2.positive || !field_specification.includes_sign());
+    let mut word_upper = Word::new(positive, [0; 5]);
+    let mut word_lower = Word::new(positive, [0; 5]);
+    word_upper.bytes.copy_from_slice(&digits[..5]);
+    word_lower.bytes.copy_from_slice(&digits[5..]);
+    (word_upper, word_lower)
+}
 
-    word_rem.positive = word1.positive;
-    if zero_included {
-        word_div.positive = word1.positive == word3.positive;
-    }
+/// Divides rA and rX, taken together as one number, by the field of a word, as `DIV`
+/// does.
+///
+/// ## Arguments
+/// - `word1`: The upper half of the dividend, as in rA, whose sign is that of the dividend.
+/// - `word2`: The lower half of the dividend, as in rX, whose sign is ignored.
+/// - `word3`: The word holding the divisor.
+/// - `field_specification`: The field of `word3` to divide by.
+///
+/// ## Returns
+/// - `(quotient, remainder, overflow)`, the quotient for rA taking the algebraic sign and
+///   the remainder for rX taking the sign of the dividend. When the divisor is zero or
+///   the quotient doesn't fit in a word, `overflow` is set and both words are +0. A
+///   field of the sign alone has the value zero.
+#[cfg(test)]
+pub(crate) fn divide_words(word1: &Word, word2: &Word, word3: &Word, field_specification: FieldSpec) -> (Word, Word, bool) {
+    divide_words_in_base(word1, word2, word3, field_specification, CONFIGURED_BYTE_SIZE as u16)
+}
 
-    dividend = dividend;
-    remainder = remainder;
-    for i in (0..=4).rev() {
-        word_rem.bytes[i] = (remainder % 256) as u8;
-        word_div.bytes[i] = (dividend % 256) as u8;
-        remainder = remainder >> 8;
-        dividend = dividend >> 8;
+/// As `divide_words`, for bytes holding `byte_size` values.
+pub(crate) fn divide_words_in_base(word1: &Word, word2: &Word, word3: &Word, field_specification: FieldSpec, byte_size: u16) -> (Word, Word, bool) {
+    let divisor = digits_to_value(&word3.bytes[field_specification.byte_indices()], byte_size);
+    let dividend = digits_to_value(&[word1.bytes, word2.bytes].concat(), byte_size);
+    if divisor == 0 || dividend / divisor >= (byte_size as u128).pow(5) {
+        return (Word::default(), Word::default(), true);
     }
 
+    let mut word_div = Word::new(word1.positive == (word3.positive || !field_specification.includes_sign()), [0; 5]);
+    let mut word_rem = Word::new(word1.positive, [0; 5]);
+    word_div.bytes.copy_from_slice(&split_to_digits(dividend / divisor, byte_size, 5));
+    word_rem.bytes.copy_from_slice(&split_to_digits(dividend % divisor, byte_size, 5));
     (word_div, word_rem, false)
 }
 
-/// TODO: Document this
-pub fn compare_words(word1: &Word, word2: &Word, field_specification: (usize, usize)) -> ComparisonFlag {
-    let (zero_included, only_zero, (left, right)) = adjusted_field_specification(field_specification);
-
-    if only_zero {
-        return ComparisonFlag::equal;
-    }
-
-    if zero_included && word1.positive != word2.positive {
-        if word1.positive == true {
-            return ComparisonFlag::greater;
-        } else {
-            return ComparisonFlag::less;
-        }
-    }
-
-    for i in left..=right {
-        if word1.bytes[i] > word2.bytes[i] {
-            return ComparisonFlag::greater;
-        } else if word1.bytes[i] < word2.bytes[i] {
-            return ComparisonFlag::less;
-        }
+/// Compares the fields of two words given by `field_specification` as signed numbers, so
+/// that `+0` and `-0` are equal. The order is the same whatever the byte size, as long as
+/// every byte is below it.
+pub(crate) fn compare_words(word1: &Word, word2: &Word, field_specification: FieldSpec) -> ComparisonFlag {
+    match word1.field_value(field_specification).cmp(&word2.field_value(field_specification)) {
+        Ordering::Less => ComparisonFlag::less,
+        Ordering::Equal => ComparisonFlag::equal,
+        Ordering::Greater => ComparisonFlag::greater,
     }
+}
 
-    ComparisonFlag::equal
+/// Sets rJ to the address of the instruction after the jump being executed, which is
+/// where a subroutine it calls returns to.
+pub(crate) fn save_jump(computer: &mut Computer) {
+    computer.rj = Word::from_value_in_base((computer.pc + 1) as i64, computer.byte_size());
 }
 
-// TODO: Document this <12-03-21, yourname> //
-pub fn save_jump(computer: &mut Computer) {
-    let old_address = Word::from_value((computer.pc.clone() + 1) as i64);    
-    computer.rj = old_address;
+/// Goes on at `address` after the instruction being executed, rather than at the one
+/// after it.
+pub(crate) fn jump_to(computer: &mut Computer, address: usize) {
+    computer.pc = address;
+    computer.jumped = true;
 }
 /// Does a byte-wise left shift over a single word, performing the amount of shifts 
 /// specified by `amount` and only retaining a cyclical shift if `cycle` is set to true.
@@ -293,18 +292,18 @@ pub fn save_jump(computer: &mut Computer) {
 /// `amount` - The `usize` indicating how many bytes the word should be shifted over
 /// `cycle` - A `bool` value specifying whether or not shift should cycle bytes back to the
 /// beginning that have gone before the beginning of the word boundary
-pub fn single_word_left_shift(word: &Word, amount: usize, cycle: bool) -> Word {
+pub(crate) fn single_word_left_shift(word: &Word, amount: usize, cycle: bool) -> Word { no_panic!({
     let mut r_copy = word.clone();
     for i in 0..5 {
-        r_copy.bytes[i] = word.bytes[(amount + i) % 5];
+        r_copy.bytes[i] = word.bytes[(amount % 5 + i) % 5];
     }
     if !cycle {
-        for i in (5 - amount).max(0)..5 {
+        for i in 5 - amount.min(5)..5 {
             r_copy.bytes[i] = 0;
         }
     }
     r_copy
-}
+})}
 
 /// Does a byte-wise right shift over a single word, performing the amount of shifts 
 /// specified by `amount` and only retaining a cyclical shift if `cycle` is set to true.
@@ -315,10 +314,10 @@ pub fn single_word_left_shift(word: &Word, amount: usize, cycle: bool) -> Word {
 /// `amount` - The `usize` indicating how many bytes the word should be shifted over
 /// `cycle` - A `bool` value specifying whether or not shift should cycle bytes back to the
 /// beginning that have gone past the end of the word boundary
-pub fn single_word_right_shift(word: &Word, amount: usize, cycle: bool) -> Word {
+pub(crate) fn single_word_right_shift(word: &Word, amount: usize, cycle: bool) -> Word { no_panic!({
     let mut r_copy = word.clone();
     for i in 0..5 {
-        r_copy.bytes[(amount + i) % 5] = word.bytes[i];
+        r_copy.bytes[(amount % 5 + i) % 5] = word.bytes[i];
     }
     if !cycle {
         for i in 0..amount.min(5) {
@@ -326,7 +325,7 @@ pub fn single_word_right_shift(word: &Word, amount: usize, cycle: bool) -> Word
         }
     }
     r_copy
-}
+})}
 
 /// Does a byte-wise left shift over two words, performing the amount of shifts specified by
 /// `amount`. This acts on the two words by seeing each of their individual bytes as being
@@ -338,7 +337,7 @@ pub fn single_word_right_shift(word: &Word, amount: usize, cycle: bool) -> Word
 /// `word1` - The reference to the upper `Word` that is being shifted to the left
 /// `word2` - The reference to the lower `Word` that is being shifted to the left
 /// `amount` - The `usize` indicating how many bytes the word should be shifted over
-pub fn double_word_left_shift(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) {
+pub(crate) fn double_word_left_shift(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) { no_panic!({
     let mut w1_copy = word1.clone();
     let mut w2_copy = word2.clone();
     let mut vals = [0; 10];
@@ -360,16 +359,15 @@ pub fn double_word_left_shift(word1: &Word, word2: &Word, amount: usize) -> (Wor
     }
 
     // Set any excess bytes to 0
-    for i in (10 - amount).max(0)..10 {
-        vals_shifted[i] = 0;
+    for val in vals_shifted[10 - amount.min(10)..].iter_mut() {
+        *val = 0;
     }
 
-    // Slice the modulus shifted values into the byte arrays for each word, and set 
-    // each word to the corresponding value
-    w1_copy.bytes = vals_shifted[0..5].try_into().expect("Tried slice with incorrect length.");
-    w2_copy.bytes = vals_shifted[5..10].try_into().expect("Tried slice with incorrect length.");
+    // Copy the shifted values back into the bytes of each word
+    w1_copy.bytes.copy_from_slice(&vals_shifted[..5]);
+    w2_copy.bytes.copy_from_slice(&vals_shifted[5..]);
     (w1_copy, w2_copy)
-}
+})}
 
 /// Does a byte-wise right shift over two words, performing the amount of shifts specified by
 /// `amount`. This acts on the two words by seeing each of their individual bytes as being
@@ -381,7 +379,7 @@ pub fn double_word_left_shift(word1: &Word, word2: &Word, amount: usize) -> (Wor
 /// `word1` - The reference to the upper `Word` that is being shifted to the right
 /// `word2` - The reference to the lower `Word` that is being shifted to the right
 /// `amount` - The `usize` indicating how many bytes the word should be shifted over
-pub fn double_word_right_shift(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) {
+pub(crate) fn double_word_right_shift(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) { no_panic!({
     let mut w1_copy = word1.clone();
     let mut w2_copy = word2.clone();
     let mut vals = [0; 10];
@@ -407,9 +405,94 @@ pub fn double_word_right_shift(word1: &Word, word2: &Word, amount: usize) -> (Wo
         vals_shifted[i] = 0;
     }
 
-    // Slice the modulus shifted values into the byte arrays for each word, and set 
-    // each word to the corresponding value
-    w1_copy.bytes = vals_shifted[0..5].try_into().expect("Tried slice with incorrect length.");
-    w2_copy.bytes = vals_shifted[5..10].try_into().expect("Tried slice with incorrect length.");
+    // Copy the shifted values back into the bytes of each word
+    w1_copy.bytes.copy_from_slice(&vals_shifted[..5]);
+    w2_copy.bytes.copy_from_slice(&vals_shifted[5..]);
     (w1_copy, w2_copy)
+})}
+
+/// Does a byte-wise circular left shift over two words, performing the amount of shifts
+/// specified by `amount`. This acts on the two words by seeing each of their individual bytes as
+/// being consecutive, and rotating them as if this was a 10 byte word, so the bytes shifted out of
+/// the left of `word1` come back in at the right of `word2`. The sign of each word is not altered.
+///
+/// ## Arguments
+///
+/// `word1` - The reference to the upper `Word` that is being shifted to the left
+/// `word2` - The reference to the lower `Word` that is being shifted to the left
+/// `amount` - The `usize` indicating how many bytes the words should be rotated over
+pub(crate) fn double_word_left_cycle(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) { no_panic!({
+    let mut w1_copy = *word1;
+    let mut w2_copy = *word2;
+    let mut vals = [0; 10];
+    for i in 0..5 {
+        vals[i] = word1.bytes[i];
+        vals[i + 5] = word2.bytes[i];
+    }
+
+    // Copy the rotated values back into the bytes of each word
+    let offset = amount % 10;
+    for i in 0..5 {
+        w1_copy.bytes[i] = vals[(i + offset) % 10];
+        w2_copy.bytes[i] = vals[(i + 5 + offset) % 10];
+    }
+    (w1_copy, w2_copy)
+})}
+
+/// Does a byte-wise circular right shift over two words, performing the amount of shifts
+/// specified by `amount`. This acts on the two words by seeing each of their individual bytes as
+/// being consecutive, and rotating them as if this was a 10 byte word, so the bytes shifted out of
+/// the right of `word2` come back in at the left of `word1`. The sign of each word is not altered.
+///
+/// ## Arguments
+///
+/// `word1` - The reference to the upper `Word` that is being shifted to the right
+/// `word2` - The reference to the lower `Word` that is being shifted to the right
+/// `amount` - The `usize` indicating how many bytes the words should be rotated over
+pub(crate) fn double_word_right_cycle(word1: &Word, word2: &Word, amount: usize) -> (Word, Word) { no_panic!({
+    let mut w1_copy = *word1;
+    let mut w2_copy = *word2;
+    let mut vals = [0; 10];
+    for i in 0..5 {
+        vals[i] = word1.bytes[i];
+        vals[i + 5] = word2.bytes[i];
+    }
+
+    // Copy the rotated values back into the bytes of each word
+    let offset = 10 - amount % 10;
+    for i in 0..5 {
+        w1_copy.bytes[i] = vals[(i + offset) % 10];
+        w2_copy.bytes[i] = vals[(i + 5 + offset) % 10];
+    }
+    (w1_copy, w2_copy)
+})}
+
+/// Looks up the device attached as `unit` for the instruction being executed.
+///
+/// ## Returns
+/// - `None` when no device is attached as `unit` under `UnattachedPolicy::Lenient`, for
+///   the instruction to go on as that policy describes.
+///
+/// ## Errors
+/// - `UnitNotAttached` when no device is attached as `unit` under
+///   `UnattachedPolicy::Fault`.
+pub(crate) fn device_for_unit(computer: &mut Computer, unit: u8) -> Result<Option<Rc<RefCell<dyn Peripheral>>>, MixError> {
+    match computer.device(unit) {
+        Some(device) => Ok(Some(device)),
+        None => computer.use_unattached(unit).map(|_| None),
+    }
+}
+
+/// Wraps a failure of the device attached as `unit` for the instruction being executed.
+pub(crate) fn device_error(computer: &Computer, unit: u8, error: TapeError) -> MixError {
+    MixError::Device { pc: computer.pc, unit, message: error.to_string() }
+}
+
+/// Fails with `UnitBlocked` when the device attached as `unit` is busy for reasons
+/// outside of the computer, so that the instruction is retried later.
+pub(crate) fn check_device_ready(computer: &Computer, unit: u8) -> Result<(), MixError> {
+    if computer.is_device_busy(unit) {
+        return Err(MixError::UnitBlocked { pc: computer.pc, unit });
+    }
+    Ok(())
 }
@@ -0,0 +1,120 @@
+//! What must hold of a computer between any two steps, checked in one place so that a
+//! violation is caught at the step making it rather than thousands of steps later, where
+//! whatever it broke finally shows. `Computer::check_invariants` checks:
+//!
+//! - That rI1 to rI6 hold nothing but a sign and two bytes, and rJ a positive address of
+//!   two bytes.
+//! - The invariants of each subsystem in use, which registers its own check with
+//!   `Computer::register_invariant_check` when it starts, so that the checker knows
+//!   nothing of it: the shape of the occupancy bitset, and of the written positions while
+//!   they are tracked; the warning lists, trace, and heatmap holding no more than the
+//!   budget allows; and the transfers in progress lying in memory once a unit is attached.
+//!
+//! A check is given the addresses written since the last check, so that one looking at
+//! memory word by word scans only those, and registers and flags cost the same whatever
+//! the size of memory. Every byte is below the byte size by its type, since bytes are
+//! `u8` and the byte size 256, so nothing checks it.
+//!
+//! With `Computer::verify_invariants` on, the default in debug builds, every step checks
+//! them once it has executed, along with one thing only a step can: that the instruction
+//! changed no flag but those it `affects`. A violation fails the step with
+//! `MixError::InvariantViolated`, naming its `pc`.
+
+use std::fmt;
+use std::ops::Range;
+use crate::computer::Computer;
+use crate::instruction::Flag;
+use crate::word::Word;
+
+/// A check of the invariants of one subsystem, given the addresses written since the last
+/// check.
+pub type InvariantCheckFn = fn(&Computer, Range<usize>) -> Result<(), InvariantViolation>;
+
+/// A registered check, under the name of the subsystem it checks.
+#[derive(Copy, Clone)]
+pub struct InvariantCheck {
+    pub name: &'static str,
+    pub check: InvariantCheckFn,
+}
+
+/// The first invariant found not to hold.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InvariantViolation {
+    /// `register`, one of rI1 to rI6, holds `found`, with a byte other than 0 before its
+    /// last two.
+    IndexRegister { register: &'static str, found: Word },
+    /// rJ holds `found`, which is negative or has a byte other than 0 before its last two.
+    JumpRegister { found: Word },
+    /// `operation` changed `flag` from `before` to `after`, though it doesn't affect it.
+    FlagChanged { operation: String, flag: Flag, before: String, after: String },
+    /// The occupancy bitset has `found` blocks of 64 words for a memory needing `expected`.
+    OccupancyLength { found: usize, expected: usize },
+    /// The occupancy bitset marks `address`, past the end of a memory of `memory_size`
+    /// words.
+    OccupiedPastEnd { address: usize, memory_size: usize },
+    /// The written positions are tracked for `found` words of a memory of `expected`.
+    WrittenLength { found: usize, expected: usize },
+    /// The written positions of `address` are `found`, which has bits for no position.
+    WrittenMask { address: usize, found: u8 },
+    /// `list` holds `length` entries, more than the `max` of the budget.
+    OverBudget { list: &'static str, length: usize, max: usize },
+    /// The transfer in progress on `unit` moves the words of `range`, which runs past the
+    /// end of a memory of `memory_size` words.
+    TransferOutOfMemory { unit: u8, range: Range<usize>, memory_size: usize },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantViolation::IndexRegister { register, found } =>
+                write!(f, "{} holds [{}], but an index register holds only a sign and two bytes", register, found),
+            InvariantViolation::JumpRegister { found } =>
+                write!(f, "rJ holds [{}], but it holds only a positive address of two bytes", found),
+            InvariantViolation::FlagChanged { operation, flag, before, after } =>
+                write!(f, "{} changed the {} flag from {} to {}, but doesn't affect it", operation, flag.name(), before, after),
+            InvariantViolation::OccupancyLength { found, expected } =>
+                write!(f, "The occupancy bitset has {} blocks, but memory needs {}", found, expected),
+            InvariantViolation::OccupiedPastEnd { address, memory_size } =>
+                write!(f, "The occupancy bitset marks {}, past the end of a memory of {} words", address, memory_size),
+            InvariantViolation::WrittenLength { found, expected } =>
+                write!(f, "Written positions are tracked for {} words, but memory has {}", found, expected),
+            InvariantViolation::WrittenMask { address, found } =>
+                write!(f, "The written positions of {} are {:#010b}, which has bits for no position", address, found),
+            InvariantViolation::OverBudget { list, length, max } =>
+                write!(f, "{} holds {} entries, but the budget allows {}", list, length, max),
+            InvariantViolation::TransferOutOfMemory { unit, range, memory_size } =>
+                write!(f, "The transfer on unit {} moves {} to {}, past the end of a memory of {} words", unit, range.start, range.end, memory_size),
+        }
+    }
+}
+
+/// Checks rI1 to rI6 and rJ.
+pub(crate) fn check_registers(computer: &Computer) -> Result<(), InvariantViolation> {
+    let indexes = [
+        ("rI1", &computer.ri1), ("rI2", &computer.ri2), ("rI3", &computer.ri3),
+        ("rI4", &computer.ri4), ("rI5", &computer.ri5), ("rI6", &computer.ri6),
+    ];
+    for (register, word) in indexes {
+        if word.bytes[..3] != [0, 0, 0] {
+            return Err(InvariantViolation::IndexRegister { register, found: *word });
+        }
+    }
+    if !computer.rj.positive || computer.rj.bytes[..3] != [0, 0, 0] {
+        return Err(InvariantViolation::JumpRegister { found: computer.rj });
+    }
+    Ok(())
+}
+
+/// Checks that the flags of `computer` are as `before` left them, but for those in
+/// `affects`, the flags `operation` may change.
+pub(crate) fn check_flags(computer: &Computer, before: (bool, crate::computer::ComparisonFlag), operation: &str, affects: &[Flag]) -> Result<(), InvariantViolation> {
+    let changed = |flag: Flag, before: String, after: String| InvariantViolation::FlagChanged { operation: operation.to_string(), flag, before, after };
+    let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+    if before.0 != computer.overflow_flag && !affects.contains(&Flag::Overflow) {
+        return Err(changed(Flag::Overflow, on_off(before.0), on_off(computer.overflow_flag)));
+    }
+    if before.1 != computer.comparison_flag && !affects.contains(&Flag::Comparison) {
+        return Err(changed(Flag::Comparison, before.1.to_string(), computer.comparison_flag.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Named regions of memory, such as the code, data, and I/O buffers of a program, for the
+//! assembler, the loader, and reports to agree on. A layout needn't cover all of memory,
+//! but its regions never overlap. Layouts are made with `MemoryLayout::builder()`, adding
+//! each region by name with `region` before checking them all with `build`.
+
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Region {
+    pub name: String,
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LayoutError {
+    /// Two regions share at least one address.
+    Overlap { first: String, second: String },
+    /// Two regions have the same name.
+    DuplicateName(String),
+    /// The region holds no addresses.
+    Empty(String),
+    /// A program has a word at `address`, which is in no region.
+    Undeclared { address: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Overlap { first, second } => write!(f, "Regions {} and {} overlap", first, second),
+            LayoutError::DuplicateName(name) => write!(f, "Region {} is declared twice", name),
+            LayoutError::Empty(name) => write!(f, "Region {} is empty", name),
+            LayoutError::Undeclared { address } => write!(f, "Address {} is in no region", address),
+        }
+    }
+}
+
+/// What to do when a program puts a word outside every region of a layout.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LayoutPolicy {
+    /// Fail with `LayoutError::Undeclared`.
+    Error,
+    /// Load the word anyway and report its address.
+    Warn,
+}
+
+/// Regions of memory in order of address.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MemoryLayout {
+    regions: Vec<Region>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLayoutBuilder {
+    regions: Vec<Region>,
+}
+
+impl MemoryLayoutBuilder {
+    pub fn region(mut self, name: &str, range: Range<usize>) -> MemoryLayoutBuilder {
+        self.regions.push(Region { name: name.to_string(), range });
+        self
+    }
+
+    /// ## Errors
+    /// - `Empty` for the first region holding no addresses.
+    /// - `DuplicateName` for the first name given twice.
+    /// - `Overlap` for the lowest pair of regions sharing an address.
+    pub fn build(mut self) -> Result<MemoryLayout, LayoutError> {
+        if let Some(region) = self.regions.iter().find(|region| region.range.is_empty()) {
+            return Err(LayoutError::Empty(region.name.clone()));
+        }
+        for (i, region) in self.regions.iter().enumerate() {
+            if self.regions[..i].iter().any(|other| other.name == region.name) {
+                return Err(LayoutError::DuplicateName(region.name.clone()));
+            }
+        }
+        self.regions.sort_by_key(|region| region.range.start);
+        for pair in self.regions.windows(2) {
+            if pair[1].range.start < pair[0].range.end {
+                return Err(LayoutError::Overlap { first: pair[0].name.clone(), second: pair[1].name.clone() });
+            }
+        }
+        Ok(MemoryLayout { regions: self.regions })
+    }
+}
+
+impl MemoryLayout {
+    pub fn builder() -> MemoryLayoutBuilder {
+        MemoryLayoutBuilder::default()
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The region named `name`, if any.
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.iter().find(|region| region.name == name)
+    }
+
+    /// The region holding `address`, if any.
+    pub fn region_at(&self, address: usize) -> Option<&Region> {
+        let i = self.regions.partition_point(|region| region.range.end <= address);
+        self.regions.get(i).filter(|region| region.range.contains(&address))
+    }
+}
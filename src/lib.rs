@@ -0,0 +1,88 @@
+//! A MIX computer and the tools around it, after TAOCP volume 1: the machine itself
+//! (`computer`, `word`, `instruction`), its units (`peripherals`), the MIXAL assembler
+//! (`assembler`), and the debugger and analyses of the programs it runs. The `mixal`
+//! binary is a thin front end to this library, through `cli`.
+//!
+//! The core is always built, and everything else is behind the features described in
+//! `Cargo.toml`. A module needing two features that don't imply each other is declared
+//! under both, as `testing` is. The modules left private are the crate's own machinery:
+//! `no_panic` and the macros of `computer` and `instruction`, the helpers the
+//! instructions share in `instruction_functions`, the generators of the documents in
+//! `docs`, the benchmark harness, and the gate every outside word passes through in
+//! `sanitize`.
+
+#[macro_use]
+mod no_panic;
+pub mod word;
+pub mod field;
+pub mod parse;
+pub mod computer;
+#[macro_use]
+pub mod instruction;
+mod instruction_functions;
+pub mod portability;
+pub mod peripherals;
+pub mod error;
+pub mod timing;
+pub mod trace;
+pub mod phases;
+#[cfg(feature = "debugger")]
+pub mod explain;
+#[cfg(feature = "peripherals")]
+pub mod cluster;
+pub mod fingerprint;
+pub mod determinism;
+pub mod image;
+pub mod migration;
+mod sanitize;
+#[cfg(feature = "debugger")]
+pub mod checkpoint;
+#[cfg(feature = "debugger")]
+pub mod simulation;
+#[cfg(feature = "debugger")]
+pub mod lockstep;
+pub mod heatmap;
+pub mod budget;
+pub mod invariants;
+pub mod mirror;
+#[cfg(feature = "cli")]
+mod docgen;
+#[cfg(feature = "cli")]
+mod api;
+pub mod consts;
+pub mod opcodes;
+pub mod decode;
+pub mod dispatch;
+pub mod report;
+pub mod loader;
+pub mod layout;
+pub mod extensions;
+#[cfg(feature = "debugger")]
+pub mod run_report;
+pub mod charset;
+pub mod async_runner;
+#[cfg(feature = "assembler")]
+pub mod assembler;
+#[cfg(feature = "assembler")]
+pub mod codegen;
+#[cfg(feature = "analysis")]
+pub mod analysis;
+#[cfg(feature = "analysis")]
+pub mod harness;
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+pub mod testing;
+#[cfg(feature = "tui")]
+pub mod expression;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+pub mod script;
+#[cfg(feature = "mdk")]
+pub mod conformance;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(all(feature = "cli", feature = "json"))]
+mod bench;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,403 @@
+//! A minimal, hand-writable format for memory images. Each line is one of
+//!
+//! - `ADDR: ±AA II FF CC`, an instruction word given as its signed address part, index,
+//!   field, and opcode,
+//! - `ADDR: ±VALUE`, a data word given as its signed value,
+//! - `START ADDR`, where execution begins (0 when omitted),
+//!
+//! with every number in decimal, and anything after `#` ignored. The parts of an
+//! instruction and the value of a word are read in bytes of `CONFIGURED_BYTE_SIZE` values,
+//! or of the byte size given to `parse_raw_in_base`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use crate::computer::{Computer, DEFAULT_MEMORY_SIZE};
+use crate::word::Word;
+use crate::field::FieldSpec;
+use crate::decode::{self, DecodeError};
+use crate::layout::{LayoutError, LayoutPolicy, MemoryLayout};
+use crate::parse::{self, ParseDiag};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::migration::BYTE_SIZES;
+
+/// A memory image along with where execution begins. The words and source map are shared
+/// between clones, so that a program can be cloned cheaply, as out of a cache.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AssembledProgram {
+    pub words: Arc<BTreeMap<usize, Word>>,
+    pub start: usize,
+    pub source_map: Arc<SourceMap>,
+    /// Runs of consecutive addresses emitted one after another, in order of address.
+    pub regions: Vec<EmittedRegion>,
+    /// Words emitted to an address that already held one, when overlaps only warn.
+    pub overlaps: Vec<Overlap>,
+}
+
+/// A run of words emitted to consecutive addresses, as from a single `ORIG`, along with
+/// the source lines that produced its first and last words.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmittedRegion {
+    pub range: Range<usize>,
+    pub first_line: usize,
+    pub last_line: usize,
+}
+
+/// Two source lines emitting a word to the same address.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Overlap {
+    pub address: usize,
+    pub first_line: usize,
+    pub second_line: usize,
+}
+
+/// What to do when a word is emitted to an address already emitted to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OverlapPolicy {
+    /// Fail with `EmitError::Overlap`.
+    Error,
+    /// Keep the later word and record the overlap in `AssembledProgram::overlaps`.
+    Warn,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EmitError {
+    /// The word from `line` would land at `address`, outside of memory.
+    OutOfMemory { address: i64, line: usize },
+    Overlap(Overlap),
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmitError::OutOfMemory { address, line } =>
+                write!(f, "Line {}: address {} is outside of memory", line, address),
+            EmitError::Overlap(overlap) =>
+                write!(f, "Line {}: address {} was already emitted by line {}",
+                       overlap.second_line, overlap.address, overlap.first_line),
+        }
+    }
+}
+
+/// Collects the words of a program as they are emitted, checking that each fits in
+/// memory and, once finished, that no two land on the same address.
+pub struct ProgramBuilder {
+    memory_size: usize,
+    overlap_policy: OverlapPolicy,
+    program: AssembledProgram,
+    /// Every emission as `(address, line)`, in the order emitted.
+    emissions: Vec<(usize, usize)>,
+}
+
+impl ProgramBuilder {
+    pub fn new(memory_size: usize, overlap_policy: OverlapPolicy) -> ProgramBuilder {
+        ProgramBuilder { memory_size, overlap_policy, program: AssembledProgram::default(), emissions: Vec::new() }
+    }
+
+    pub fn set_start(&mut self, start: usize) {
+        self.program.start = start;
+    }
+
+    /// Emits `word` at `address` for the source line `line`, marking it as data when
+    /// `is_data` is set.
+    ///
+    /// ## Errors
+    /// - `OutOfMemory` when `address` isn't in memory.
+    pub fn emit(&mut self, address: i64, word: Word, line: usize, is_data: bool) -> Result<(), EmitError> {
+        if address < 0 || address as usize >= self.memory_size {
+            return Err(EmitError::OutOfMemory { address, line });
+        }
+        let address = address as usize;
+        Arc::make_mut(&mut self.program.words).insert(address, word);
+        let source_map = Arc::make_mut(&mut self.program.source_map);
+        if is_data {
+            source_map.data.insert(address);
+            source_map.code.remove(&address);
+        } else {
+            source_map.data.remove(&address);
+            source_map.code.insert(address);
+        }
+        self.emissions.push((address, line));
+        Ok(())
+    }
+
+    /// Finishes the program, working out its regions and overlaps by sorting the
+    /// emissions, so the cost grows with the number of words rather than with memory.
+    ///
+    /// ## Errors
+    /// - `Overlap` for the lowest address emitted twice, when overlaps are errors.
+    pub fn finish(mut self) -> Result<AssembledProgram, EmitError> {
+        let mut regions: Vec<EmittedRegion> = Vec::new();
+        for (address, line) in self.emissions.iter() {
+            match regions.last_mut() {
+                Some(region) if region.range.end == *address => {
+                    region.range.end += 1;
+                    region.last_line = *line;
+                },
+                _ => regions.push(EmittedRegion { range: *address..(*address + 1), first_line: *line, last_line: *line }),
+            }
+        }
+        regions.sort_by_key(|region| (region.range.start, region.first_line));
+
+        let mut sorted: Vec<(usize, usize, usize)> = self.emissions.iter().enumerate()
+            .map(|(order, (address, line))| (*address, order, *line))
+            .collect();
+        sorted.sort();
+        for pair in sorted.windows(2) {
+            let ((address, _, first_line), (next, _, second_line)) = (pair[0], pair[1]);
+            if address == next {
+                let overlap = Overlap { address, first_line, second_line };
+                match self.overlap_policy {
+                    OverlapPolicy::Error => return Err(EmitError::Overlap(overlap)),
+                    OverlapPolicy::Warn => self.program.overlaps.push(overlap),
+                }
+            }
+        }
+        self.program.regions = regions;
+        Ok(self.program)
+    }
+}
+
+/// What the source of a program said about its words, beyond their contents.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+    /// The addresses of words given as data rather than as instructions.
+    pub data: BTreeSet<usize>,
+    /// The addresses of words given as instructions.
+    pub code: BTreeSet<usize>,
+    /// The names of the labelled words of a program linked from modules, with each local
+    /// symbol qualified by its module, as `MAIN.LOOP`. A program assembled from a single
+    /// source leaves its names to its symbol table.
+    pub labels: BTreeMap<usize, String>,
+    /// The value of every symbol the program defines, by name, for the expressions of the
+    /// monitor (see `expression`). A linked program has its labels, by their qualified
+    /// names.
+    pub symbols: BTreeMap<String, i64>,
+}
+
+impl AssembledProgram {
+    /// Writes the program's words into the memory of `computer`, points its `pc` at the
+    /// start of the program, and hands it the program's source map.
+    pub fn load_into(&self, computer: &mut Computer) {
+        for (address, word) in self.words.iter() {
+            computer.write_mem(*address, *word);
+        }
+        computer.pc = self.start;
+        computer.source_map = Some(SourceMap::clone(&self.source_map));
+    }
+
+    /// Loads the program as `load_into` does after checking that every word lands in a
+    /// region of `layout`, and hands `computer` the layout.
+    ///
+    /// ## Returns
+    /// - The addresses of words outside every region, in order, when `policy` is `Warn`.
+    ///
+    /// ## Errors
+    /// - `Undeclared` for the lowest such address when `policy` is `Error`, in which case
+    ///   nothing is loaded.
+    pub fn load_into_layout(&self, computer: &mut Computer, layout: &MemoryLayout, policy: LayoutPolicy) -> Result<Vec<usize>, LayoutError> {
+        let undeclared: Vec<usize> = self.words.keys().copied()
+            .filter(|address| layout.region_at(*address).is_none())
+            .collect();
+        if let (Some(address), LayoutPolicy::Error) = (undeclared.first(), policy) {
+            return Err(LayoutError::Undeclared { address: *address });
+        }
+        self.load_into(computer);
+        computer.layout = Some(layout.clone());
+        Ok(undeclared)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RawErrorKind {
+    /// The line isn't a `START` line and has no `:` after its address.
+    MissingColon,
+    /// A part of the line that should be a number isn't one, or a location or value is
+    /// out of its range.
+    BadNumber(ParseDiag),
+    /// A word doesn't start with `+` or `-`.
+    BadSign(String),
+    /// An instruction line doesn't have exactly four parts.
+    WrongPartCount(usize),
+    /// A location is outside of memory.
+    AddressOutOfMemory(i64),
+    /// The named part of a word doesn't fit in the bytes that hold it.
+    OutOfRange { part: &'static str, value: i64 },
+    /// The field of an operation taking a field specification isn't a valid `(L:R)`.
+    InvalidField(i64),
+    /// The location was already given a word by `first_line`.
+    Overlap { address: usize, first_line: usize },
+    /// A second `START` line was given.
+    DuplicateStart,
+    /// The byte size to read the words in isn't one of `BYTE_SIZES`, reported against the
+    /// first line.
+    ByteSize(u16),
+}
+
+impl RawErrorKind {
+    /// A short, stable name for the kind of error, for tools that sort errors without
+    /// parsing their messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RawErrorKind::MissingColon => "missing-colon",
+            RawErrorKind::BadNumber(_) => "bad-number",
+            RawErrorKind::BadSign(_) => "bad-sign",
+            RawErrorKind::WrongPartCount(_) => "wrong-part-count",
+            RawErrorKind::AddressOutOfMemory(_) => "address-out-of-memory",
+            RawErrorKind::OutOfRange { .. } => "out-of-range",
+            RawErrorKind::InvalidField(_) => "invalid-field",
+            RawErrorKind::Overlap { .. } => "overlap",
+            RawErrorKind::DuplicateStart => "duplicate-start",
+            RawErrorKind::ByteSize(_) => "byte-size",
+        }
+    }
+}
+
+/// A problem with the line numbered `line`, counting from 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawParseError {
+    pub line: usize,
+    pub kind: RawErrorKind,
+}
+
+impl fmt::Display for RawParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: ", self.line)?;
+        match &self.kind {
+            RawErrorKind::MissingColon => write!(f, "expected ADDR: followed by a word"),
+            RawErrorKind::BadNumber(diag) => write!(f, "{}", diag),
+            RawErrorKind::BadSign(text) => write!(f, "{} must start with + or -", text),
+            RawErrorKind::WrongPartCount(count) => write!(f, "expected ±AA II FF CC but found {} parts", count),
+            RawErrorKind::AddressOutOfMemory(address) => write!(f, "{} is outside of memory", address),
+            RawErrorKind::OutOfRange { part, value } => write!(f, "{} {} doesn't fit", part, value),
+            RawErrorKind::InvalidField(field) => write!(f, "{} is not a valid field specification", field),
+            RawErrorKind::Overlap { address, first_line } =>
+                write!(f, "{} was already given a word on line {}", address, first_line),
+            RawErrorKind::DuplicateStart => write!(f, "START was already given"),
+            RawErrorKind::ByteSize(byte_size) =>
+                write!(f, "{} is not a byte size from {} to {}", byte_size, BYTE_SIZES.start(), BYTE_SIZES.end()),
+        }
+    }
+}
+
+/// Parses a memory image in the raw format described in the module docs.
+///
+/// ## Errors
+/// - A `RawParseError` naming the first line at fault.
+pub fn parse_raw(text: &str) -> Result<AssembledProgram, RawParseError> {
+    parse_raw_in_base(text, CONFIGURED_BYTE_SIZE as u16)
+}
+
+/// As `parse_raw`, for a computer whose bytes hold `byte_size` values.
+///
+/// ## Errors
+/// - A `RawParseError` naming the first line at fault, or `ByteSize` against line 1 when
+///   `byte_size` isn't one of `BYTE_SIZES`.
+pub fn parse_raw_in_base(text: &str, byte_size: u16) -> Result<AssembledProgram, RawParseError> {
+    if !BYTE_SIZES.contains(&byte_size) {
+        return Err(RawParseError { line: 1, kind: RawErrorKind::ByteSize(byte_size) });
+    }
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    let mut start_given = false;
+    for (i, line) in text.lines().enumerate() {
+        let error = |kind: RawErrorKind| RawParseError { line: i + 1, kind };
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(start) = line.strip_prefix("START") {
+            if start_given {
+                return Err(error(RawErrorKind::DuplicateStart));
+            }
+            builder.set_start(parse_location(start.trim()).map_err(error)?);
+            start_given = true;
+            continue;
+        }
+        let (address, word) = line.split_once(':').ok_or(error(RawErrorKind::MissingColon))?;
+        let address = parse_location(address.trim()).map_err(error)?;
+        let (word, is_data) = parse_word(word.trim(), byte_size).map_err(error)?;
+        builder.emit(address as i64, word, i + 1, is_data).map_err(raw_emit_error)?;
+    }
+    builder.finish().map_err(raw_emit_error)
+}
+
+/// Renders `program` in the raw format, one word per line in order of address, so that
+/// `parse_raw` gives the program back. Words the source map marks as data are written as
+/// values and every other word as an instruction.
+pub fn render_raw(program: &AssembledProgram) -> String {
+    render_raw_in_base(program, CONFIGURED_BYTE_SIZE as u16)
+}
+
+/// As `render_raw`, for a program whose bytes hold `byte_size` values, which
+/// `parse_raw_in_base` with the same byte size gives back.
+pub fn render_raw_in_base(program: &AssembledProgram, byte_size: u16) -> String {
+    let mut text = format!("START {}\n", program.start);
+    for (address, word) in program.words.iter() {
+        let sign = if word.positive { '+' } else { '-' };
+        if !program.source_map.data.contains(address) {
+            text.push_str(&format!("{}: {}{} {} {} {}\n", address, sign, word.address_in_base(byte_size), word.index(), word.field(), word.opcode()));
+        } else {
+            text.push_str(&format!("{}: {}{}\n", address, sign, word.field_value_in_base(FieldSpec::MAGNITUDE, byte_size)));
+        }
+    }
+    text
+}
+
+fn raw_emit_error(error: EmitError) -> RawParseError {
+    match error {
+        EmitError::OutOfMemory { address, line } => RawParseError { line, kind: RawErrorKind::AddressOutOfMemory(address) },
+        EmitError::Overlap(overlap) => RawParseError {
+            line: overlap.second_line,
+            kind: RawErrorKind::Overlap { address: overlap.address, first_line: overlap.first_line },
+        },
+    }
+}
+
+fn parse_number(text: &str) -> Result<i64, RawErrorKind> {
+    let (positive, magnitude) = parse::parse_integer(text).map_err(RawErrorKind::BadNumber)?;
+    let magnitude = i64::try_from(magnitude).map_err(|_| RawErrorKind::BadNumber(ParseDiag::new(text, "a number that fits".to_string())))?;
+    Ok(if positive { magnitude } else { -magnitude })
+}
+
+fn parse_location(text: &str) -> Result<usize, RawErrorKind> {
+    parse::parse_address(text, DEFAULT_MEMORY_SIZE).map_err(RawErrorKind::BadNumber)
+}
+
+fn check_range(part: &'static str, value: i64, limit: i64) -> Result<i64, RawErrorKind> {
+    if value < 0 || value >= limit {
+        return Err(RawErrorKind::OutOfRange { part, value });
+    }
+    Ok(value)
+}
+
+/// Parses a word with bytes of `byte_size` values, telling whether it was given as data.
+fn parse_word(text: &str, byte_size: u16) -> Result<(Word, bool), RawErrorKind> {
+    let positive = match text.chars().next() {
+        Some('+') => true,
+        Some('-') => false,
+        _ => return Err(RawErrorKind::BadSign(text.to_string())),
+    };
+    let parts: Vec<&str> = text[1..].split_whitespace().collect();
+    match parts.len() {
+        0 | 1 => {
+            let word = parse::parse_value(text, byte_size).map_err(RawErrorKind::BadNumber)?;
+            Ok((word, true))
+        },
+        4 => {
+            let byte_size = byte_size as i64;
+            let address = check_range("address", parse_number(parts[0])?, byte_size * byte_size)?;
+            let index = check_range("index", parse_number(parts[1])?, 7)? as u8;
+            let field = check_range("field", parse_number(parts[2])?, byte_size)? as u8;
+            let opcode = check_range("opcode", parse_number(parts[3])?, 64)? as u8;
+            let word = Word::new(positive, [(address / byte_size) as u8, (address % byte_size) as u8, index, field, opcode]);
+            match decode::check_word(&word) {
+                Ok(_) => Ok((word, false)),
+                Err(DecodeError::InvalidField { field, .. }) => Err(RawErrorKind::InvalidField(field as i64)),
+                Err(DecodeError::InvalidIndex(index)) => Err(RawErrorKind::OutOfRange { part: "index", value: index as i64 }),
+            }
+        },
+        count => Err(RawErrorKind::WrongPartCount(count)),
+    }
+}
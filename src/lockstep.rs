@@ -0,0 +1,307 @@
+//! Two computers run side by side, an instruction at a time, to find the first step at
+//! which they differ: the same program on machines configured differently, such as a
+//! strict and a lenient one, or one under each of two cost models, to see where a change
+//! to the emulator changes what a program does.
+//!
+//! After each step the two states are compared by `Computer::state_hash`, confirmed by
+//! `Computer::state_eq` when the hashes agree, and in full only when they don't, so that
+//! agreeing runs cost little more than running both. What the comparison may find different on purpose is declared by
+//! `Mask`s, each a `Component` of the state and `When` it may differ:
+//!
+//! - `When::Always`, for a difference expected throughout, such as the time under two
+//!   cost models.
+//! - `When::After`, for one expected once either machine executes an operation, such as
+//!   the registers a faulting `DIV` leaves undefined. The component is excused from that
+//!   step until the two agree on it again, so a difference left behind stays excused, but
+//!   a later one isn't excused by an old cause.
+//! - `When::AfterOverflow`, as `After` for a step of the operation that turns the
+//!   overflow toggle on.
+//!
+//! The run stops at the first difference no mask excuses, with both machines, the step,
+//! and the instruction each executed. How each step ended is compared as well, and never
+//! excused: once one machine fails or halts and the other doesn't, they can't be stepped
+//! together.
+
+use std::fmt;
+use std::ops::Range;
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::opcodes;
+use crate::word::Word;
+
+/// The registers, as `Computer::registers` orders them.
+const REGISTER_NAMES: [&str; 9] = ["rA", "rX", "rI1", "rI2", "rI3", "rI4", "rI5", "rI6", "rJ"];
+
+/// A part of the state the two machines are compared on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Component {
+    /// A register by name, `rA`, `rX`, `rI1` to `rI6`, or `rJ`.
+    Register(&'static str),
+    Overflow,
+    Comparison,
+    Pc,
+    /// The words of memory at these addresses.
+    Memory(Range<usize>),
+    /// The time, as `Computer::time` gives it.
+    Time,
+    /// How the step ended: whether it failed, with what error, and whether the machine
+    /// halted. Never excused.
+    Outcome,
+}
+
+impl Component {
+    /// Whether this component, as a mask names it, covers `other`, as a difference names it.
+    fn covers(&self, other: &Component) -> bool {
+        match (self, other) {
+            (Component::Memory(range), Component::Memory(address)) => range.start <= address.start && address.end <= range.end,
+            (Component::Outcome, _) => false,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Component::Register(name) => write!(f, "{}", name),
+            Component::Overflow => write!(f, "overflow"),
+            Component::Comparison => write!(f, "comparison"),
+            Component::Pc => write!(f, "pc"),
+            Component::Memory(range) if range.len() == 1 => write!(f, "mem {}", range.start),
+            Component::Memory(range) => write!(f, "mem {}..{}", range.start, range.end),
+            Component::Time => write!(f, "time"),
+            Component::Outcome => write!(f, "outcome"),
+        }
+    }
+}
+
+/// When a masked component may differ.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum When {
+    Always,
+    /// From a step executing the operation with this mnemonic on either machine, until
+    /// the machines agree on the component again.
+    After(&'static str),
+    /// As `After`, for a step of the operation turning the overflow toggle on.
+    AfterOverflow(&'static str),
+}
+
+/// A difference declared expected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mask {
+    pub component: Component,
+    pub when: When,
+}
+
+impl Mask {
+    pub fn always(component: Component) -> Mask {
+        Mask { component, when: When::Always }
+    }
+
+    pub fn after(component: Component, mnemonic: &'static str) -> Mask {
+        Mask { component, when: When::After(mnemonic) }
+    }
+
+    pub fn after_overflow(component: Component, mnemonic: &'static str) -> Mask {
+        Mask { component, when: When::AfterOverflow(mnemonic) }
+    }
+}
+
+/// The most steps `run` takes unless the options say otherwise.
+pub const DEFAULT_STEP_LIMIT: u64 = 1_000_000;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LockstepOptions {
+    pub masks: Vec<Mask>,
+    /// The most steps taken before giving up, counting a step of both machines as one.
+    pub step_limit: u64,
+}
+
+impl Default for LockstepOptions {
+    fn default() -> LockstepOptions {
+        LockstepOptions { masks: vec![], step_limit: DEFAULT_STEP_LIMIT }
+    }
+}
+
+/// A component the two machines hold differently, with the value each holds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Difference {
+    pub component: Component,
+    pub a: String,
+    pub b: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} / {}", self.component, self.a, self.b)
+    }
+}
+
+/// The first step after which the machines differ in a way no mask excuses.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+    /// The number of the step, counting from 1.
+    pub step: u64,
+    /// The address of the instruction each machine executed.
+    pub pc: (usize, usize),
+    /// The instruction each executed, disassembled, or as a word when it isn't one.
+    pub instructions: (String, String),
+    /// The differences no mask excuses, in the order of `Component`.
+    pub differences: Vec<Difference>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Diverged at step {}: a executed {} at {}, b executed {} at {}",
+                 self.step, self.instructions.0, self.pc.0, self.instructions.1, self.pc.1)?;
+        for difference in self.differences.iter() {
+            writeln!(f, "  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a lockstep run ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LockstepEnd {
+    /// Both machines halted after `steps` steps, differing only as excused.
+    Halted { steps: u64 },
+    /// Both failed at step `steps` with `error`.
+    Failed { steps: u64, error: MixError },
+    StepLimit,
+    Diverged(Divergence),
+}
+
+/// The end of a lockstep run, with the machines as it left them.
+pub struct LockstepResult {
+    pub a: Computer,
+    pub b: Computer,
+    pub end: LockstepEnd,
+    /// Every difference a mask excused, as its component and the first step it was seen
+    /// at, in order of step.
+    pub excused: Vec<(Component, u64)>,
+}
+
+/// Writes `inputs` to the memory of both `a` and `b`, and steps them together until both
+/// halt, they diverge, or `options.step_limit` is reached.
+pub fn run(mut a: Computer, mut b: Computer, inputs: &[(usize, Word)], options: &LockstepOptions) -> LockstepResult {
+    for (address, word) in inputs.iter() {
+        a.write_mem(*address, *word);
+        b.write_mem(*address, *word);
+    }
+    let mut excused: Vec<(Component, u64)> = vec![];
+    // The masks of `When::After` and `AfterOverflow` whose cause has come, by index.
+    let mut active: Vec<usize> = vec![];
+    let mut step = 0;
+    let end = loop {
+        if a.is_halted() && b.is_halted() {
+            break LockstepEnd::Halted { steps: step };
+        }
+        if step == options.step_limit {
+            break LockstepEnd::StepLimit;
+        }
+        step += 1;
+        let (pc_a, pc_b) = (a.pc(), b.pc());
+        let (word_a, word_b) = (word_at(&a, pc_a), word_at(&b, pc_b));
+        let (overflow_a, overflow_b) = (a.overflow_flag, b.overflow_flag);
+        let (result_a, result_b) = (a.step(), b.step());
+        let divergence = |differences| Divergence {
+            step,
+            pc: (pc_a, pc_b),
+            instructions: (instruction_text(word_a), instruction_text(word_b)),
+            differences,
+        };
+
+        let outcome_a = (result_a.as_ref().err(), a.is_halted());
+        let outcome_b = (result_b.as_ref().err(), b.is_halted());
+        if outcome_a != outcome_b {
+            let difference = Difference { component: Component::Outcome, a: outcome_text(outcome_a), b: outcome_text(outcome_b) };
+            break LockstepEnd::Diverged(divergence(vec![difference]));
+        }
+        if let Err(error) = result_a {
+            break LockstepEnd::Failed { steps: step, error };
+        }
+
+        for (i, mask) in options.masks.iter().enumerate() {
+            let caused = |word: Option<Word>, before: bool, after: bool| match mask.when {
+                When::Always => false,
+                When::After(mnemonic) => mnemonic_of(word) == Some(mnemonic),
+                When::AfterOverflow(mnemonic) => mnemonic_of(word) == Some(mnemonic) && !before && after,
+            };
+            if (caused(word_a, overflow_a, a.overflow_flag) || caused(word_b, overflow_b, b.overflow_flag)) && !active.contains(&i) {
+                active.push(i);
+            }
+        }
+        let differences = if a.state_hash() == b.state_hash() && a.state_eq(&b) { time_difference(&a, &b).into_iter().collect() } else { differences(&a, &b) };
+        active.retain(|&i| differences.iter().any(|difference| options.masks[i].component.covers(&difference.component)));
+        let (allowed, unexplained): (Vec<Difference>, Vec<Difference>) = differences.into_iter().partition(|difference| {
+            options.masks.iter().enumerate().any(|(i, mask)| {
+                mask.component.covers(&difference.component) && (mask.when == When::Always || active.contains(&i))
+            })
+        });
+        for difference in allowed {
+            if !excused.iter().any(|(component, _)| *component == difference.component) {
+                excused.push((difference.component, step));
+            }
+        }
+        if !unexplained.is_empty() {
+            break LockstepEnd::Diverged(divergence(unexplained));
+        }
+    };
+    LockstepResult { a, b, end, excused }
+}
+
+fn word_at(computer: &Computer, pc: usize) -> Option<Word> {
+    computer.memory().get(pc).copied()
+}
+
+fn mnemonic_of(word: Option<Word>) -> Option<&'static str> {
+    word.as_ref().and_then(opcodes::operation_of).map(|operation| operation.mnemonic)
+}
+
+fn instruction_text(word: Option<Word>) -> String {
+    match word {
+        Some(word) => opcodes::disassemble(&word).unwrap_or_else(|| format!("[{}]", word)),
+        None => "nothing, being outside memory".to_string(),
+    }
+}
+
+fn outcome_text((error, halted): (Option<&MixError>, bool)) -> String {
+    match (error, halted) {
+        (Some(error), _) => format!("failed: {}", error),
+        (None, true) => "halted".to_string(),
+        (None, false) => "ran".to_string(),
+    }
+}
+
+/// Every component `a` and `b` hold differently, a word of memory at a time.
+fn differences(a: &Computer, b: &Computer) -> Vec<Difference> {
+    let mut differences = vec![];
+    let mut push = |component: Component, value_a: String, value_b: String| {
+        if value_a != value_b {
+            differences.push(Difference { component, a: value_a, b: value_b });
+        }
+    };
+    for ((name, register_a), register_b) in REGISTER_NAMES.iter().zip(a.registers().iter()).zip(b.registers().iter()) {
+        push(Component::Register(name), register_a.to_string(), register_b.to_string());
+    }
+    push(Component::Overflow, a.overflow_flag.to_string(), b.overflow_flag.to_string());
+    push(Component::Comparison, a.comparison_flag.to_string(), b.comparison_flag.to_string());
+    push(Component::Pc, a.pc().to_string(), b.pc().to_string());
+    let words = a.memory_size().max(b.memory_size());
+    for address in 0..words {
+        let (word_a, word_b) = (a.memory().get(address), b.memory().get(address));
+        if word_a != word_b {
+            let text = |word: Option<&Word>| word.map_or_else(|| "outside memory".to_string(), |word| format!("[{}]", word));
+            push(Component::Memory(address..address + 1), text(word_a), text(word_b));
+        }
+    }
+    differences.extend(time_difference(a, b));
+    differences
+}
+
+/// The difference in time of `a` and `b`, which `Computer::state_hash` leaves out.
+fn time_difference(a: &Computer, b: &Computer) -> Option<Difference> {
+    Some(Difference { component: Component::Time, a: a.time().to_string(), b: b.time().to_string() })
+        .filter(|difference| difference.a != difference.b)
+}
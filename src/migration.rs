@@ -0,0 +1,290 @@
+//! Carrying words and images from a machine with one byte size to a machine with another,
+//! as when programs written for this emulator's 256-value bytes move to a machine with
+//! the 64-value bytes of the book. The same bytes mean different things under the two, so
+//! each word is rewritten for what it held:
+//!
+//! - An instruction, a word `decode::check_word` reads as one, keeps its fields: its
+//!   address is split into the two bytes of the new size, and its index, field, and
+//!   opcode bytes are kept as they are.
+//! - Anything else is data, and keeps its value, rewritten in five bytes of the new size.
+//!
+//! A word of data can look like an instruction, as `CON 300` does under 256-value bytes,
+//! and nothing in the word says which it is, so `convert_data` rewrites a word known to be
+//! data by its value whatever its bytes, and `migrate_image` takes the addresses known to
+//! be data, as a source map marks them. Words that can't be rewritten are flagged rather
+//! than guessed at: data whose value needs more than five bytes, and instructions whose
+//! address needs more than two, for which the instruction with the nearest address is
+//! suggested, or with an index, field, or opcode byte too large for the new size, which are
+//! left for review.
+//!
+//! `mixal migrate-image OLD --from-byte-size FROM --to TO -o NEW [--data START..END]...`
+//! migrates an image with the `cli` feature, listing the words flagged.
+
+use std::fmt;
+use std::ops::Range;
+use crate::decode;
+use crate::image::{ImageError, MachineImage};
+use crate::instruction_functions::{digits_to_value, split_to_digits};
+use crate::word::Word;
+
+/// The byte sizes words migrate between: at least the 64 values a MIX byte holds, and at
+/// most the 256 of a `u8`.
+pub const BYTE_SIZES: std::ops::RangeInclusive<u16> = 64..=256;
+
+/// A word as rewritten for the new byte size, or why it couldn't be.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MigrationOutcome {
+    /// Data, with its value kept.
+    Data(Word),
+    /// An instruction, with its fields kept.
+    Instruction(Word),
+    /// Data whose magnitude `value` needs more than five bytes, which hold below `capacity`.
+    ValueTooLarge { value: u128, capacity: u128 },
+    /// An instruction whose `address` needs more than two bytes, which hold below
+    /// `capacity`. `suggestion` is the instruction with the largest address that fits.
+    AddressTooLarge { address: usize, capacity: usize, suggestion: Word },
+    /// An instruction that can't be rewritten for the reason given.
+    ManualReview { reason: String },
+}
+
+impl MigrationOutcome {
+    /// The word rewritten, or `None` when it was flagged.
+    pub fn word(&self) -> Option<Word> {
+        match self {
+            MigrationOutcome::Data(word) | MigrationOutcome::Instruction(word) => Some(*word),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MigrationOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationOutcome::Data(word) => write!(f, "data [{}]", word),
+            MigrationOutcome::Instruction(word) => write!(f, "instruction [{}]", word),
+            MigrationOutcome::ValueTooLarge { value, capacity } =>
+                write!(f, "{} needs more than five bytes, which hold below {}", value, capacity),
+            MigrationOutcome::AddressTooLarge { address, capacity, suggestion } =>
+                write!(f, "Address {} needs more than two bytes, which hold below {}; the nearest is [{}]", address, capacity, suggestion),
+            MigrationOutcome::ManualReview { reason } => write!(f, "Needs review: {}", reason),
+        }
+    }
+}
+
+/// Errors raised while migrating an image.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MigrationError {
+    /// A byte size outside of `BYTE_SIZES`.
+    ByteSize(u16),
+    /// The image can't be read under the old byte size.
+    Image(ImageError),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::ByteSize(size) =>
+                write!(f, "Bytes of {} values can't be migrated; they must hold {} to {}", size, BYTE_SIZES.start(), BYTE_SIZES.end()),
+            MigrationError::Image(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Checks that both byte sizes are in `BYTE_SIZES`.
+fn check_byte_sizes(from: u16, to: u16) -> Result<(), MigrationError> {
+    match [from, to].iter().copied().find(|size| !BYTE_SIZES.contains(size)) {
+        Some(size) => Err(MigrationError::ByteSize(size)),
+        None => Ok(()),
+    }
+}
+
+/// Rewrites `old`, whose bytes hold `from` values, for bytes holding `to` values: as an
+/// instruction when it decodes as one, and as data otherwise.
+///
+/// ## Panics
+/// - When either byte size isn't in `BYTE_SIZES`.
+pub fn convert_word(old: Word, from: u16, to: u16) -> MigrationOutcome {
+    if let Err(error) = check_byte_sizes(from, to) {
+        panic!("[Error convert_word] {}", error);
+    }
+    match decode::check_word(&old) {
+        Ok(Some(_)) => convert_instruction(old, from, to),
+        _ => convert_data(old, from, to),
+    }
+}
+
+/// Rewrites `old` as `convert_word` does data, keeping its value whatever its bytes.
+///
+/// ## Panics
+/// - When either byte size isn't in `BYTE_SIZES`.
+pub fn convert_data(old: Word, from: u16, to: u16) -> MigrationOutcome {
+    if let Err(error) = check_byte_sizes(from, to) {
+        panic!("[Error convert_data] {}", error);
+    }
+    let value = digits_to_value(&old.bytes, from);
+    let capacity = (to as u128).pow(5);
+    if value >= capacity {
+        return MigrationOutcome::ValueTooLarge { value, capacity };
+    }
+    let mut bytes = [0; 5];
+    bytes.copy_from_slice(&split_to_digits(value, to, 5));
+    MigrationOutcome::Data(Word::new(old.positive, bytes))
+}
+
+fn convert_instruction(old: Word, from: u16, to: u16) -> MigrationOutcome {
+    let names = ["index", "field", "opcode"];
+    if let Some(i) = (0..3).find(|i| old.bytes[2 + i] as u16 >= to) {
+        return MigrationOutcome::ManualReview {
+            reason: format!("its {} {} doesn't fit a byte of {} values", names[i], old.bytes[2 + i], to),
+        };
+    }
+    let address = old.address_in_base(from);
+    let capacity = to as usize * to as usize;
+    let encode = |address: usize| Word::new(old.positive, [
+        (address / to as usize) as u8, (address % to as usize) as u8, old.bytes[2], old.bytes[3], old.bytes[4],
+    ]);
+    if address >= capacity {
+        return MigrationOutcome::AddressTooLarge { address, capacity, suggestion: encode(capacity - 1) };
+    }
+    MigrationOutcome::Instruction(encode(address))
+}
+
+/// An image rewritten for a new byte size.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImageMigration {
+    /// The image with every word rewritten, and the words flagged replaced by the
+    /// suggestion for them when there is one, or by +0.
+    pub image: MachineImage,
+    /// The words of memory flagged, by address, in order.
+    pub flagged: Vec<(usize, MigrationOutcome)>,
+    /// The registers flagged, by name.
+    pub flagged_registers: Vec<(&'static str, MigrationOutcome)>,
+}
+
+impl ImageMigration {
+    pub fn is_clean(&self) -> bool {
+        self.flagged.is_empty() && self.flagged_registers.is_empty()
+    }
+
+    /// Writes the flagged words one to a line, registers first.
+    pub fn write_text(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        let words = self.image.memory.len() + self.image.registers.len();
+        writeln!(out, "Migrated {} words, {} flagged", words, self.flagged.len() + self.flagged_registers.len())?;
+        for (register, outcome) in self.flagged_registers.iter() {
+            writeln!(out, "  {}: {}", register, outcome)?;
+        }
+        for (address, outcome) in self.flagged.iter() {
+            writeln!(out, "  {:04}: {}", address, outcome)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of the registers of an image, in order.
+const REGISTERS: [&str; 9] = ["rA", "rX", "rI1", "rI2", "rI3", "rI4", "rI5", "rI6", "rJ"];
+
+/// Rewrites every word of `image` from bytes of `from` values to bytes of `to` values:
+/// the registers and the words at addresses in one of `data` as data, and the rest of
+/// memory as `convert_word` does.
+///
+/// ## Errors
+/// - `ByteSize` when either byte size isn't in `BYTE_SIZES`.
+pub fn migrate_image(image: &MachineImage, from: u16, to: u16, data: &[Range<usize>]) -> Result<ImageMigration, MigrationError> {
+    check_byte_sizes(from, to)?;
+    let mut migrated = image.clone();
+    let mut flagged = Vec::new();
+    let mut flagged_registers = Vec::new();
+    let replacement = |outcome: &MigrationOutcome| match outcome {
+        MigrationOutcome::AddressTooLarge { suggestion, .. } => *suggestion,
+        outcome => outcome.word().unwrap_or(Word::default()),
+    };
+    for (register, word) in REGISTERS.iter().zip(migrated.registers.iter_mut()) {
+        let outcome = convert_data(*word, from, to);
+        *word = replacement(&outcome);
+        if outcome.word().is_none() {
+            flagged_registers.push((*register, outcome));
+        }
+    }
+    for (address, word) in migrated.memory.iter_mut().enumerate() {
+        let outcome = if data.iter().any(|range| range.contains(&address)) {
+            convert_data(*word, from, to)
+        } else {
+            convert_word(*word, from, to)
+        };
+        *word = replacement(&outcome);
+        if outcome.word().is_none() {
+            flagged.push((address, outcome));
+        }
+    }
+    Ok(ImageMigration { image: migrated, flagged, flagged_registers })
+}
+
+/// Reads the image `bytes` under bytes of `from` values and migrates it as
+/// `migrate_image` does.
+///
+/// ## Errors
+/// - `ByteSize` when either byte size isn't in `BYTE_SIZES`.
+/// - `Image` when the image can't be read, or has a byte of `from` or more.
+pub fn migrate_image_bytes(bytes: &[u8], from: u16, to: u16, data: &[Range<usize>]) -> Result<ImageMigration, MigrationError> {
+    check_byte_sizes(from, to)?;
+    let image = MachineImage::from_bytes_with(bytes, crate::sanitize::IngestPolicy::Reject, from).map_err(MigrationError::Image)?;
+    migrate_image(&image, from, to, data)
+}
+
+/// The exit code of `migrate-image` when the image was written with words flagged.
+#[cfg(feature = "cli")]
+pub const EXIT_FLAGGED: i32 = 2;
+
+/// Runs `migrate-image` with `args`, those after the subcommand, writing the new image in
+/// the format of the old one and listing the words flagged.
+///
+/// ## Returns
+/// - 0 when no word was flagged, `EXIT_FLAGGED` when some were, and 1 on an error.
+#[cfg(feature = "cli")]
+pub fn main(args: &[String]) -> i32 {
+    match run(args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("[Error migrate-image] {}", message);
+            1
+        },
+    }
+}
+
+#[cfg(feature = "cli")]
+fn run(args: &[String]) -> Result<i32, String> {
+    let usage = "Usage: migrate-image OLD --from-byte-size FROM --to TO -o NEW [--data START..END]...";
+    let (mut old, mut new, mut from, mut to, mut data) = (None, None, None, None, Vec::new());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        let byte_size = |value: String| value.parse::<u16>().map_err(|_| format!("{} isn't a byte size", value));
+        match arg.as_str() {
+            "--from-byte-size" => from = Some(byte_size(value()?)?),
+            "--to" => to = Some(byte_size(value()?)?),
+            "-o" => new = Some(value()?),
+            "--data" => data.push(parse_range(&value()?)?),
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            _ if old.is_none() => old = Some(arg.clone()),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let (Some(old), Some(new), Some(from), Some(to)) = (old, new, from, to) else { return Err(usage.to_string()) };
+    let bytes = std::fs::read(&old).map_err(|error| format!("Couldn't read {}: {}", old, error))?;
+    let migration = migrate_image_bytes(&bytes, from, to, &data).map_err(|error| error.to_string())?;
+    std::fs::write(&new, migration.image.to_bytes(bytes[4])).map_err(|error| format!("Couldn't write {}: {}", new, error))?;
+    let mut text = String::new();
+    migration.write_text(&mut text).map_err(|error| error.to_string())?;
+    print!("{}", text);
+    Ok(if migration.is_clean() { 0 } else { EXIT_FLAGGED })
+}
+
+/// `START..END` as a range of addresses.
+#[cfg(feature = "cli")]
+fn parse_range(text: &str) -> Result<Range<usize>, String> {
+    let (start, end) = text.split_once("..").ok_or_else(|| format!("{} isn't a range START..END", text))?;
+    match (start.parse(), end.parse()) {
+        (Ok(start), Ok(end)) => Ok(start..end),
+        _ => Err(format!("{} isn't a range START..END", text)),
+    }
+}
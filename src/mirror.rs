@@ -0,0 +1,225 @@
+//! A read-only view of a running computer for other threads. The run loop owns the
+//! computer, so a display or a monitor can't borrow it, and sharing it behind a lock
+//! would make every step take the lock. Instead, while a mirror is enabled (see
+//! `Computer::enable_mirror`), the computer publishes a `MirrorState` between steps: the
+//! registers, flags, `pc`, clock, a window of memory around `pc`, and the words at the
+//! addresses being watched, never the whole memory. Readers load the latest state without
+//! waiting, through an `ArcSwap`, and get a consistent view of one moment of the run,
+//! however far the run has since moved on. Its `sequence` tells a reader whether it has
+//! seen it already.
+//!
+//! Publishing is double-buffered: the state a publish replaces is filled in again by the
+//! next one unless a reader still holds it, so a mirror no reader is looking at copies
+//! a few dozen words every `every_steps` steps and allocates nothing. `mirror_benchmark`
+//! compares a run with the mirror on, off, and never enabled.
+//!
+//! Readers change the watched addresses through `MirrorReader::watch` and `unwatch`,
+//! which queue the change for the computer to take up at its next publish.
+//!
+//! Unstable: what a `MirrorState` holds follows the displays reading it, and may change
+//! in any release.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use arc_swap::ArcSwap;
+use crate::computer::{ComparisonFlag, Computer};
+use crate::word::Word;
+
+/// The steps between publishes unless the options say otherwise.
+pub const DEFAULT_PUBLISH_EVERY: u64 = 1024;
+
+/// The words on each side of `pc` in the window unless the options say otherwise.
+pub const DEFAULT_WINDOW_RADIUS: usize = 8;
+
+/// When a mirror publishes and what it shows.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MirrorOptions {
+    /// Publish after every this many steps, or never by count when 0.
+    pub every_steps: u64,
+    /// Publish after every step that doesn't go on to the next instruction: a jump, a
+    /// halt, a wait on a busy unit, or a failure. A halt or failure is published even
+    /// when this is off, so that readers see how the run ended.
+    pub on_events: bool,
+    /// The words on each side of `pc` in the window.
+    pub window_radius: usize,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> MirrorOptions {
+        MirrorOptions { every_steps: DEFAULT_PUBLISH_EVERY, on_events: false, window_radius: DEFAULT_WINDOW_RADIUS }
+    }
+}
+
+/// The state of the computer at one publish.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MirrorState {
+    /// The number of the publish, from 0 for the one made by `enable_mirror`. Every
+    /// publish has a larger number than the one before.
+    pub sequence: u64,
+    /// The steps taken since the mirror was enabled.
+    pub steps: u64,
+    pub pc: usize,
+    pub ra: Word,
+    pub rx: Word,
+    /// rI1 to rI6.
+    pub ri: [Word; 6],
+    pub rj: Word,
+    pub overflow: bool,
+    pub comparison: ComparisonFlag,
+    /// As `Computer::time`.
+    pub time: u64,
+    pub halted: bool,
+    /// The address of the first word of `window`.
+    pub window_start: usize,
+    /// The words from `window_radius` before `pc` to as many after it, cut short at the
+    /// ends of memory.
+    pub window: Vec<Word>,
+    /// The words at the watched addresses inside memory, in order of address.
+    pub watched: Vec<(usize, Word)>,
+}
+
+impl MirrorState {
+    fn empty() -> MirrorState {
+        MirrorState {
+            sequence: 0, steps: 0, pc: 0,
+            ra: Word::default(), rx: Word::default(), ri: [Word::default(); 6], rj: Word::default(),
+            overflow: false, comparison: ComparisonFlag::equal, time: 0, halted: false,
+            window_start: 0, window: Vec::new(), watched: Vec::new(),
+        }
+    }
+
+    /// The word at `address`, when the window or the watched addresses hold it.
+    pub fn word_at(&self, address: usize) -> Option<Word> {
+        match address.checked_sub(self.window_start).and_then(|offset| self.window.get(offset)) {
+            Some(word) => Some(*word),
+            None => self.watched.iter().find(|(watched, _)| *watched == address).map(|(_, word)| *word),
+        }
+    }
+}
+
+enum WatchCommand {
+    Watch(usize),
+    Unwatch(usize),
+}
+
+/// A handle for reading the states a computer publishes, which may be cloned and sent to
+/// other threads. It keeps the last state published after the mirror is disabled or the
+/// computer dropped.
+#[derive(Clone)]
+pub struct MirrorReader {
+    shared: Arc<ArcSwap<MirrorState>>,
+    commands: Sender<WatchCommand>,
+}
+
+impl MirrorReader {
+    /// The latest state published, without waiting on the computer.
+    pub fn load(&self) -> Arc<MirrorState> {
+        self.shared.load_full()
+    }
+
+    /// Adds `address` to the watched addresses from the next publish on.
+    ///
+    /// ## Returns
+    /// - `false` when the computer no longer publishes to this mirror.
+    pub fn watch(&self, address: usize) -> bool {
+        self.commands.send(WatchCommand::Watch(address)).is_ok()
+    }
+
+    /// Removes `address` from the watched addresses from the next publish on, as `watch`.
+    pub fn unwatch(&self, address: usize) -> bool {
+        self.commands.send(WatchCommand::Unwatch(address)).is_ok()
+    }
+}
+
+/// The computer's end of a mirror.
+pub(crate) struct Publisher {
+    options: MirrorOptions,
+    shared: Arc<ArcSwap<MirrorState>>,
+    /// The state the last publish replaced, to fill in again when no reader holds it.
+    spare: Option<Arc<MirrorState>>,
+    commands: Receiver<WatchCommand>,
+    watched: BTreeSet<usize>,
+    sequence: u64,
+    steps: u64,
+    /// The steps left until a publish by count.
+    countdown: u64,
+}
+
+impl Publisher {
+    /// A mirror of `computer`, with its first state published, and a reader of it.
+    pub(crate) fn new(computer: &Computer, options: MirrorOptions) -> (Publisher, MirrorReader) {
+        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::new(ArcSwap::from_pointee(MirrorState::empty()));
+        let mut publisher = Publisher {
+            options,
+            shared: shared.clone(),
+            spare: None,
+            commands: receiver,
+            watched: BTreeSet::new(),
+            sequence: 0,
+            steps: 0,
+            countdown: options.every_steps,
+        };
+        publisher.publish(computer);
+        (publisher, MirrorReader { shared, commands: sender })
+    }
+
+    /// Counts a step of `computer`, publishing when the options call for it. `event` is
+    /// whether the step didn't go on to the next instruction, and `ended` whether it
+    /// halted or failed.
+    pub(crate) fn after_step(&mut self, computer: &Computer, event: bool, ended: bool) {
+        self.steps += 1;
+        let due = self.countdown == 1;
+        if self.options.every_steps > 0 {
+            self.countdown = if due { self.options.every_steps } else { self.countdown - 1 };
+        }
+        if due || ended || (event && self.options.on_events) {
+            self.publish(computer);
+        }
+    }
+
+    fn publish(&mut self, computer: &Computer) {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                WatchCommand::Watch(address) => self.watched.insert(address),
+                WatchCommand::Unwatch(address) => self.watched.remove(&address),
+            };
+        }
+        let mut next = self.spare.take()
+            .filter(|spare| Arc::strong_count(spare) == 1)
+            .unwrap_or_else(|| Arc::new(MirrorState::empty()));
+        let state = Arc::get_mut(&mut next).expect("[Error Publisher::publish] A new or unshared state has no other owner");
+        let size = computer.memory_size();
+        let start = computer.pc.saturating_sub(self.options.window_radius).min(size);
+        let end = computer.pc.saturating_add(self.options.window_radius + 1).min(size).max(start);
+        state.sequence = self.sequence;
+        state.steps = self.steps;
+        state.pc = computer.pc;
+        state.ra = computer.ra;
+        state.rx = computer.rx;
+        state.ri = [computer.ri1, computer.ri2, computer.ri3, computer.ri4, computer.ri5, computer.ri6];
+        state.rj = computer.rj;
+        state.overflow = computer.overflow_flag;
+        state.comparison = computer.comparison_flag;
+        state.time = computer.time();
+        state.halted = computer.is_halted();
+        state.window_start = start;
+        state.window.clear();
+        state.window.extend_from_slice(&computer.memory[start..end]);
+        state.watched.clear();
+        state.watched.extend(self.watched.iter().filter(|address| **address < size).map(|address| (*address, computer.memory[*address])));
+        self.sequence += 1;
+        self.spare = Some(self.shared.swap(next));
+    }
+}
+
+/// Where a computer keeps its mirror. A clone of the computer doesn't publish to it.
+#[derive(Default)]
+pub(crate) struct MirrorSlot(pub(crate) Option<Box<Publisher>>);
+
+impl Clone for MirrorSlot {
+    fn clone(&self) -> MirrorSlot {
+        MirrorSlot(None)
+    }
+}
@@ -0,0 +1,44 @@
+//! A link-time check that functions on the path of every step can't panic, in the way of
+//! the `no-panic` crate. A function marked with `no_panic!` holds a `Guard` while its body
+//! runs and forgets it once the body returns. Dropping the guard calls a function that is
+//! declared but never defined, so that if the body can unwind, the unwinding path drops
+//! the guard and the build fails to link, naming the symbol below. When the optimizer
+//! proves the body can't panic, that path is removed and the symbol is never referred to.
+//!
+//! The check only means something in an optimized build, so it is only made under the
+//! `no-panic-audit` feature in a build without debug assertions, by `cargo build --release
+//! --features no-panic-audit`, which `no_panic_audit_links` runs. Otherwise `no_panic!`
+//! leaves the body alone, so that a debug build with `--all-features` still links.
+//!
+//! A marked body must end by returning its value: a `return` or `?` drops the guard on its
+//! way out, and fails the check as a panic would. An error built whether or not it is
+//! returned, as by `ok_or`, is dropped on the path that doesn't return it, which the
+//! optimizer may not prove can't unwind, so marked bodies build errors with `ok_or_else`.
+
+/// Wraps the body of a function that must not panic, checked under `no-panic-audit` in
+/// an optimized build.
+macro_rules! no_panic {
+    ($body:block) => {{
+        #[cfg(all(feature = "no-panic-audit", not(debug_assertions)))]
+        let guard = crate::no_panic::Guard;
+        let value = $body;
+        #[cfg(all(feature = "no-panic-audit", not(debug_assertions)))]
+        std::mem::forget(guard);
+        value
+    }};
+}
+
+/// Held while a body marked with `no_panic!` runs; see the module.
+#[cfg(all(feature = "no-panic-audit", not(debug_assertions)))]
+pub(crate) struct Guard;
+
+#[cfg(all(feature = "no-panic-audit", not(debug_assertions)))]
+impl Drop for Guard {
+    fn drop(&mut self) {
+        extern "C" {
+            #[link_name = "\n\n[Error no_panic] A function marked with no_panic! may panic.\n\n"]
+            fn a_marked_function_may_panic() -> !;
+        }
+        unsafe { a_marked_function_may_panic() }
+    }
+}
@@ -0,0 +1,431 @@
+//! The MIX operation table, shared by anything that turns mnemonics into instruction
+//! words or back. Each entry gives the normal field of the operation, following the
+//! instruction summary of TAOCP section 1.3.1.
+
+use std::convert::TryFrom;
+use std::fmt;
+use crate::consts::{EnterOp, JumpCondition, Opcode, RegisterCondition, ShiftOp, SpecialOp};
+use crate::parse::{self, ParseDiag};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::word::Word;
+
+/// How the field of an operation is chosen.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FieldRule {
+    /// A field specification `8L + R`, defaulting to the given value when omitted.
+    Default(u8),
+    /// The field selects the operation within its opcode and can't be given.
+    Fixed(u8),
+    /// The field is a unit number and must be given.
+    Unit,
+    /// The field is a word count, defaulting to the given value when omitted.
+    Count(u8),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Operation {
+    pub mnemonic: &'static str,
+    pub code: u8,
+    pub field: FieldRule,
+}
+
+impl Operation {
+    /// The field used when a statement doesn't give one, if any.
+    pub fn default_field(&self) -> Option<u8> {
+        match self.field {
+            FieldRule::Default(field) | FieldRule::Fixed(field) | FieldRule::Count(field) => Some(field),
+            FieldRule::Unit => None,
+        }
+    }
+
+    /// The opcode, by name.
+    pub fn opcode(&self) -> Opcode {
+        Opcode::try_from(self.code).expect("[Error Operation::opcode] Every operation of the table has an opcode below 64")
+    }
+
+    /// Whether the operation refers to memory at its address after indexing, `M`.
+    pub fn references_memory(&self) -> bool {
+        use Opcode::*;
+        let opcode = self.opcode();
+        matches!(opcode, ADD | SUB | MUL | DIV | IN | OUT) || (MOVE..=STZ).contains(&opcode) || (CMPA..=CMPX).contains(&opcode)
+    }
+
+    /// Whether the operation may change `pc` other than by moving on to the next word.
+    pub fn may_jump(&self) -> bool {
+        let opcode = self.opcode();
+        opcode == Opcode::JBUS || (Opcode::JRED..=Opcode::JX).contains(&opcode)
+    }
+
+    /// The memory the operation may write.
+    pub fn writes(&self) -> Writes {
+        match self.opcode() {
+            Opcode::MOVE => Writes::Moved,
+            opcode if (Opcode::STA..=Opcode::STZ).contains(&opcode) => Writes::Word,
+            Opcode::IN => Writes::Block,
+            _ => Writes::Nothing,
+        }
+    }
+
+    /// The codes (see `MixError::code`) of the errors executing the operation may raise,
+    /// other than `unimplemented-instruction` for an operation the computer doesn't
+    /// implement, and `invalid-instruction`, which any word may raise.
+    pub fn errors(&self) -> &'static [&'static str] {
+        const ARITHMETIC: &[&str] = &["address-out-of-range", "poisoned-read"];
+        const LOAD_INDEX: &[&str] = &["address-out-of-range", "index-register-overflow"];
+        const STORE: &[&str] = &["address-out-of-range", "code-write"];
+        const IO: &[&str] = &["address-out-of-range", "unit-not-attached", "device", "unit-blocked", "block-out-of-range"];
+        const OUTPUT: &[&str] = &["address-out-of-range", "unit-not-attached", "device", "unit-blocked", "block-out-of-range", "output-limit-exceeded"];
+        const CONTROL: &[&str] = &["unit-not-attached", "device", "unit-blocked"];
+        use Opcode::*;
+        let opcode = self.opcode();
+        let increments = matches!(self.field, FieldRule::Fixed(field) if field <= EnterOp::Decrease.field());
+        match opcode {
+            ADD | SUB | MUL | DIV | CMPA | CMPX => ARITHMETIC,
+            SHIFT | JA | JX => &["poisoned-read"],
+            ENTA | ENTX if increments => &["poisoned-read"],
+            MOVE => STORE,
+            LDA | LDX | LDAN | LDXN => &["address-out-of-range"],
+            _ if (CMP1..=CMP6).contains(&opcode) => &["address-out-of-range"],
+            _ if (LD1..=LD6).contains(&opcode) || (LD1N..=LD6N).contains(&opcode) => LOAD_INDEX,
+            _ if (STA..=STZ).contains(&opcode) => STORE,
+            IOC => CONTROL,
+            JBUS | JRED => &["unit-not-attached"],
+            IN => IO,
+            OUT => OUTPUT,
+            _ if (ENT1..=ENT6).contains(&opcode) => &["index-register-overflow"],
+            _ => &[],
+        }
+    }
+}
+
+/// The memory an operation may write, relative to its address after indexing, `M`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Writes {
+    Nothing,
+    /// The word at `M`, as the stores do.
+    Word,
+    /// The block starting at `M`, as `IN` does.
+    Block,
+    /// The words starting at the address in rI1, as many as the field, as `MOVE` does.
+    Moved,
+}
+
+pub const OPCODES: [Operation; 144] = [
+    Operation { mnemonic: "NOP", code: Opcode::NOP.code(), field: FieldRule::Default(0) },
+    Operation { mnemonic: "ADD", code: Opcode::ADD.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "SUB", code: Opcode::SUB.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "MUL", code: Opcode::MUL.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "DIV", code: Opcode::DIV.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "NUM", code: Opcode::SPECIAL.code(), field: FieldRule::Fixed(SpecialOp::Num.field()) },
+    Operation { mnemonic: "CHAR", code: Opcode::SPECIAL.code(), field: FieldRule::Fixed(SpecialOp::Char.field()) },
+    Operation { mnemonic: "HLT", code: Opcode::SPECIAL.code(), field: FieldRule::Fixed(SpecialOp::Halt.field()) },
+    Operation { mnemonic: "SLA", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::LeftA.field()) },
+    Operation { mnemonic: "SRA", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::RightA.field()) },
+    Operation { mnemonic: "SLAX", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::LeftAX.field()) },
+    Operation { mnemonic: "SRAX", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::RightAX.field()) },
+    Operation { mnemonic: "SLC", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::LeftCircular.field()) },
+    Operation { mnemonic: "SRC", code: Opcode::SHIFT.code(), field: FieldRule::Fixed(ShiftOp::RightCircular.field()) },
+    Operation { mnemonic: "MOVE", code: Opcode::MOVE.code(), field: FieldRule::Count(1) },
+    Operation { mnemonic: "LDA", code: Opcode::LDA.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD1", code: Opcode::LD1.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD2", code: Opcode::LD2.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD3", code: Opcode::LD3.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD4", code: Opcode::LD4.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD5", code: Opcode::LD5.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD6", code: Opcode::LD6.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LDX", code: Opcode::LDX.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LDAN", code: Opcode::LDAN.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD1N", code: Opcode::LD1N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD2N", code: Opcode::LD2N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD3N", code: Opcode::LD3N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD4N", code: Opcode::LD4N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD5N", code: Opcode::LD5N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LD6N", code: Opcode::LD6N.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "LDXN", code: Opcode::LDXN.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "STA", code: Opcode::STA.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST1", code: Opcode::ST1.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST2", code: Opcode::ST2.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST3", code: Opcode::ST3.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST4", code: Opcode::ST4.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST5", code: Opcode::ST5.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "ST6", code: Opcode::ST6.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "STX", code: Opcode::STX.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "STJ", code: Opcode::STJ.code(), field: FieldRule::Default(2) },
+    Operation { mnemonic: "STZ", code: Opcode::STZ.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "JBUS", code: Opcode::JBUS.code(), field: FieldRule::Unit },
+    Operation { mnemonic: "IOC", code: Opcode::IOC.code(), field: FieldRule::Unit },
+    Operation { mnemonic: "IN", code: Opcode::IN.code(), field: FieldRule::Unit },
+    Operation { mnemonic: "OUT", code: Opcode::OUT.code(), field: FieldRule::Unit },
+    Operation { mnemonic: "JRED", code: Opcode::JRED.code(), field: FieldRule::Unit },
+    Operation { mnemonic: "JMP", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::Always.field()) },
+    Operation { mnemonic: "JSJ", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::SaveJ.field()) },
+    Operation { mnemonic: "JOV", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::Overflow.field()) },
+    Operation { mnemonic: "JNOV", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::NoOverflow.field()) },
+    Operation { mnemonic: "JL", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::Less.field()) },
+    Operation { mnemonic: "JE", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::Equal.field()) },
+    Operation { mnemonic: "JG", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::Greater.field()) },
+    Operation { mnemonic: "JGE", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::GreaterOrEqual.field()) },
+    Operation { mnemonic: "JNE", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::NotEqual.field()) },
+    Operation { mnemonic: "JLE", code: Opcode::JMP.code(), field: FieldRule::Fixed(JumpCondition::LessOrEqual.field()) },
+    Operation { mnemonic: "JAN", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "JAZ", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "JAP", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "JANN", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "JANZ", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "JANP", code: Opcode::JA.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J1N", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J1Z", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J1P", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J1NN", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J1NZ", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J1NP", code: Opcode::J1.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J2N", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J2Z", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J2P", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J2NN", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J2NZ", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J2NP", code: Opcode::J2.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J3N", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J3Z", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J3P", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J3NN", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J3NZ", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J3NP", code: Opcode::J3.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J4N", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J4Z", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J4P", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J4NN", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J4NZ", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J4NP", code: Opcode::J4.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J5N", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J5Z", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J5P", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J5NN", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J5NZ", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J5NP", code: Opcode::J5.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "J6N", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "J6Z", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "J6P", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "J6NN", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "J6NZ", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "J6NP", code: Opcode::J6.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "JXN", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::Negative.field()) },
+    Operation { mnemonic: "JXZ", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::Zero.field()) },
+    Operation { mnemonic: "JXP", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::Positive.field()) },
+    Operation { mnemonic: "JXNN", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::NonNegative.field()) },
+    Operation { mnemonic: "JXNZ", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::NonZero.field()) },
+    Operation { mnemonic: "JXNP", code: Opcode::JX.code(), field: FieldRule::Fixed(RegisterCondition::NonPositive.field()) },
+    Operation { mnemonic: "INCA", code: Opcode::ENTA.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DECA", code: Opcode::ENTA.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENTA", code: Opcode::ENTA.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENNA", code: Opcode::ENTA.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC1", code: Opcode::ENT1.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC1", code: Opcode::ENT1.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT1", code: Opcode::ENT1.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN1", code: Opcode::ENT1.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC2", code: Opcode::ENT2.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC2", code: Opcode::ENT2.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT2", code: Opcode::ENT2.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN2", code: Opcode::ENT2.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC3", code: Opcode::ENT3.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC3", code: Opcode::ENT3.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT3", code: Opcode::ENT3.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN3", code: Opcode::ENT3.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC4", code: Opcode::ENT4.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC4", code: Opcode::ENT4.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT4", code: Opcode::ENT4.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN4", code: Opcode::ENT4.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC5", code: Opcode::ENT5.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC5", code: Opcode::ENT5.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT5", code: Opcode::ENT5.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN5", code: Opcode::ENT5.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INC6", code: Opcode::ENT6.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DEC6", code: Opcode::ENT6.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENT6", code: Opcode::ENT6.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENN6", code: Opcode::ENT6.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "INCX", code: Opcode::ENTX.code(), field: FieldRule::Fixed(EnterOp::Increase.field()) },
+    Operation { mnemonic: "DECX", code: Opcode::ENTX.code(), field: FieldRule::Fixed(EnterOp::Decrease.field()) },
+    Operation { mnemonic: "ENTX", code: Opcode::ENTX.code(), field: FieldRule::Fixed(EnterOp::Enter.field()) },
+    Operation { mnemonic: "ENNX", code: Opcode::ENTX.code(), field: FieldRule::Fixed(EnterOp::EnterNegative.field()) },
+    Operation { mnemonic: "CMPA", code: Opcode::CMPA.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP1", code: Opcode::CMP1.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP2", code: Opcode::CMP2.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP3", code: Opcode::CMP3.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP4", code: Opcode::CMP4.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP5", code: Opcode::CMP5.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMP6", code: Opcode::CMP6.code(), field: FieldRule::Default(5) },
+    Operation { mnemonic: "CMPX", code: Opcode::CMPX.code(), field: FieldRule::Default(5) },
+];
+
+/// Errors raised while encoding a statement into an instruction word.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EncodeError {
+    UnknownMnemonic(String),
+    /// An I/O operation was given without its unit number.
+    MissingUnit(String),
+    /// A field was given for an operation whose field selects the operation itself.
+    FixedField(String),
+    /// A statement isn't of the form `OP ADDRESS,I(F)`.
+    Syntax(String),
+    /// A field written `L:R` isn't a field specification.
+    BadField(ParseDiag),
+    /// The address doesn't fit in two bytes of `byte_size` values.
+    AddressOutOfRange { address: i64, byte_size: u16 },
+    /// The index or field, named by `part`, doesn't fit in a byte of `byte_size` values.
+    ByteOutOfRange { part: &'static str, value: u8, byte_size: u16 },
+}
+
+impl EncodeError {
+    /// A short, stable name for the kind of error, for tools that sort errors without
+    /// parsing their messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            EncodeError::UnknownMnemonic(_) => "unknown-mnemonic",
+            EncodeError::MissingUnit(_) => "missing-unit",
+            EncodeError::FixedField(_) => "fixed-field",
+            EncodeError::Syntax(_) => "syntax",
+            EncodeError::BadField(_) => "bad-field",
+            EncodeError::AddressOutOfRange { .. } => "address",
+            EncodeError::ByteOutOfRange { part, .. } => part,
+        }
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::UnknownMnemonic(mnemonic) => write!(f, "Unknown operation {}", mnemonic),
+            EncodeError::MissingUnit(mnemonic) => write!(f, "{} needs a unit number in its field", mnemonic),
+            EncodeError::FixedField(mnemonic) => write!(f, "{} can't be given a field", mnemonic),
+            EncodeError::Syntax(statement) => write!(f, "{} is not of the form OP ADDRESS,I(F)", statement),
+            EncodeError::BadField(diag) => write!(f, "{}", diag),
+            EncodeError::AddressOutOfRange { address, byte_size } =>
+                write!(f, "Address {} doesn't fit in two bytes of {} values", address, byte_size),
+            EncodeError::ByteOutOfRange { part, value, byte_size } =>
+                write!(f, "The {} {} doesn't fit in a byte of {} values", part, value, byte_size),
+        }
+    }
+}
+
+/// Looks up the operation named by `mnemonic`.
+pub fn lookup(mnemonic: &str) -> Option<&'static Operation> {
+    OPCODES.iter().find(|opcode| opcode.mnemonic == mnemonic)
+}
+
+/// Encodes the statement `mnemonic address,index(field)`, using the operation's normal
+/// field when `field` is `None`.
+///
+/// ## Errors
+/// - `UnknownMnemonic` when `mnemonic` isn't in `OPCODES`.
+/// - `MissingUnit` when an I/O operation is given no field.
+/// - `FixedField` when a field is given to an operation whose field is fixed.
+/// - Any other error of `encode_in_base`.
+pub fn encode(mnemonic: &str, address: i64, index: u8, field: Option<u8>) -> Result<Word, EncodeError> {
+    encode_in_base(mnemonic, address, index, field, CONFIGURED_BYTE_SIZE as u16)
+}
+
+/// As `encode`, with bytes holding `byte_size` values.
+///
+/// ## Errors
+/// - Any error of `encode`.
+/// - `AddressOutOfRange` when the address doesn't fit in two bytes.
+/// - `ByteOutOfRange` when the index or field doesn't fit in a byte.
+pub fn encode_in_base(mnemonic: &str, address: i64, index: u8, field: Option<u8>, byte_size: u16) -> Result<Word, EncodeError> {
+    let opcode = lookup(mnemonic).ok_or_else(|| EncodeError::UnknownMnemonic(mnemonic.to_string()))?;
+    let field = match (opcode.field, field) {
+        (FieldRule::Fixed(_), Some(_)) => return Err(EncodeError::FixedField(mnemonic.to_string())),
+        (FieldRule::Unit, None) => return Err(EncodeError::MissingUnit(mnemonic.to_string())),
+        (_, Some(field)) => field,
+        (_, None) => opcode.default_field().unwrap_or(0),
+    };
+    if address.unsigned_abs() >= byte_size as u64 * byte_size as u64 {
+        return Err(EncodeError::AddressOutOfRange { address, byte_size });
+    }
+    for (part, value) in [("index", index), ("field", field)] {
+        if value as u16 >= byte_size {
+            return Err(EncodeError::ByteOutOfRange { part, value, byte_size });
+        }
+    }
+    Ok(Word::from_instruction_in_base(address, index, field, opcode.code, byte_size))
+}
+
+/// Parses and encodes a single statement `OP ADDRESS,I(F)`, the form written by
+/// `disassemble`. The address is a signed number, `+0` when omitted; the index and field
+/// are optional, and the field is either `L:R` or a number. Numbers and fields are read
+/// as `parse` reads them.
+///
+/// ## Errors
+/// - `Syntax` when the statement isn't of that form.
+/// - `BadField` when the field is written `L:R` but isn't a field specification.
+/// - Any error of `encode`.
+pub fn parse_one(statement: &str) -> Result<Word, EncodeError> {
+    let syntax = || EncodeError::Syntax(statement.to_string());
+    let number = |text: &str| -> Result<i64, EncodeError> {
+        let (positive, magnitude) = parse::parse_integer(text).map_err(|_| syntax())?;
+        let magnitude = i64::try_from(magnitude).map_err(|_| syntax())?;
+        Ok(if positive { magnitude } else { -magnitude })
+    };
+    let statement = statement.trim();
+    let (mnemonic, operands) = match statement.find(char::is_whitespace) {
+        Some(split) => (&statement[..split], statement[split..].trim()),
+        None => (statement, ""),
+    };
+    let (operands, field) = match operands.find('(') {
+        Some(open) => {
+            let spec = operands[open + 1..].strip_suffix(')').ok_or_else(syntax)?;
+            let field = match spec.find(':') {
+                Some(_) => parse::parse_field_spec(spec).map_err(EncodeError::BadField)?.to_byte() as i64,
+                None => number(spec)?,
+            };
+            (&operands[..open], Some(u8::try_from(field).map_err(|_| syntax())?))
+        },
+        None => (operands, None),
+    };
+    let (address, index) = match operands.find(',') {
+        Some(comma) => (&operands[..comma], u8::try_from(number(&operands[comma + 1..])?).map_err(|_| syntax())?),
+        None => (operands, 0),
+    };
+    let mut word = encode(mnemonic, if address.trim().is_empty() { 0 } else { number(address)? }, index, field)?;
+    // `-0` is a distinct address, which the sign of `number` can't carry.
+    word.positive = !address.trim().starts_with('-');
+    Ok(word)
+}
+
+/// Finds the operation an instruction word encodes, preferring the operation whose
+/// field is fixed to the word's field.
+pub fn operation_of(word: &Word) -> Option<&'static Operation> {
+    let (code, field) = (word.opcode(), word.field());
+    OPCODES.iter().find(|opcode| opcode.code == code && opcode.field == FieldRule::Fixed(field))
+        .or_else(|| OPCODES.iter().find(|opcode| opcode.code == code && !matches!(opcode.field, FieldRule::Fixed(_))))
+}
+
+/// Writes an instruction word as a MIXAL statement, leaving out the index when it is 0
+/// and the field when it is the operation's normal field.
+///
+/// ## Returns
+/// - `None` when the word doesn't encode a known operation.
+pub fn disassemble(word: &Word) -> Option<String> {
+    disassemble_in_base(word, CONFIGURED_BYTE_SIZE as u16)
+}
+
+/// As `disassemble`, reading the address in bytes of `byte_size` values.
+///
+/// ## Returns
+/// - `None` when the word doesn't encode a known operation.
+pub fn disassemble_in_base(word: &Word, byte_size: u16) -> Option<String> {
+    let opcode = operation_of(word)?;
+    let address = word.address_in_base(byte_size) as i64;
+    let address = if word.positive { address } else { -address };
+    let mut statement = format!("{} {}", opcode.mnemonic, address);
+    if word.index() != 0 {
+        statement.push_str(&format!(",{}", word.index()));
+    }
+    let field = word.field();
+    match opcode.field {
+        FieldRule::Default(normal) if field != normal => statement.push_str(&format!("({}:{})", field / 8, field % 8)),
+        FieldRule::Count(normal) if field != normal => statement.push_str(&format!("({})", field)),
+        FieldRule::Unit => statement.push_str(&format!("({})", field)),
+        _ => {},
+    }
+    Some(statement)
+}
@@ -0,0 +1,148 @@
+//! The syntax numbers, addresses, field specifications, and words are read in from a
+//! user, wherever the binary reads them: the raw loader, the monitor and its scripts, the
+//! expressions of the monitor, conditional breaks, and single statements. Reading them
+//! all here keeps what one tool accepts the same as what another does, and what it says
+//! when it doesn't accept something.
+//!
+//! A number is decimal, with an optional `+` or `-` that whitespace may follow, and may
+//! have whitespace around it. A leading zero doesn't make it octal, and there are no
+//! other bases. `-0` is a number: an address of zero, and the negative zero as a value.
+//!
+//! Everything read here fails with a `ParseDiag`, which reads "'TEXT' is not EXPECTED",
+//! naming what was expected with its range, such as "an address from 0 to 3999".
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use crate::field::FieldSpec;
+use crate::word::Word;
+
+/// What a field specification is expected to be.
+const FIELD_SPEC: &str = "a field specification L:R or (L:R), with L <= R <= 5";
+
+/// What a word is expected to be.
+const WORD: &str = "a word, such as + 0 100 0 5 8";
+
+/// Text that isn't what was expected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDiag {
+    /// The text as given.
+    pub input: String,
+    /// The bytes of `input` at fault, without the whitespace around them.
+    pub span: Range<usize>,
+    /// What was expected, as "an address from 0 to 3999".
+    pub expected: String,
+}
+
+impl ParseDiag {
+    /// The diagnostic for all of `input` not being `expected`.
+    pub fn new(input: &str, expected: String) -> ParseDiag {
+        let start = input.len() - input.trim_start().len();
+        let end = input.trim_end().len().max(start);
+        ParseDiag { input: input.to_string(), span: start..end, expected }
+    }
+
+    /// The text at fault.
+    pub fn text(&self) -> &str {
+        &self.input[self.span.clone()]
+    }
+}
+
+impl fmt::Display for ParseDiag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not {}", self.text(), self.expected)
+    }
+}
+
+/// Reads a number, as its sign and magnitude, so that `-0` keeps its sign.
+///
+/// ## Errors
+/// - When `text` isn't a decimal number, or one too large for a `u64`.
+pub fn parse_integer(text: &str) -> Result<(bool, u64), ParseDiag> {
+    sign_and_magnitude(text).ok_or_else(|| ParseDiag::new(text, "a decimal number".to_string()))
+}
+
+fn sign_and_magnitude(text: &str) -> Option<(bool, u64)> {
+    let text = text.trim();
+    let (positive, digits) = match text.chars().next()? {
+        '+' => (true, &text[1..]),
+        '-' => (false, &text[1..]),
+        _ => (true, text),
+    };
+    let digits = digits.trim_start();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((positive, digits.parse().ok()?))
+}
+
+/// Reads an address of a memory of `memory_size` words. `-0` is the address 0.
+///
+/// ## Errors
+/// - When `text` isn't a number, or is one outside memory.
+pub fn parse_address(text: &str, memory_size: usize) -> Result<usize, ParseDiag> {
+    sign_and_magnitude(text)
+        .filter(|(positive, magnitude)| *positive || *magnitude == 0)
+        .and_then(|(_, magnitude)| usize::try_from(magnitude).ok())
+        .filter(|address| *address < memory_size)
+        .ok_or_else(|| ParseDiag::new(text, address_range(memory_size)))
+}
+
+/// What an address of a memory of `memory_size` words is expected to be.
+pub fn address_range(memory_size: usize) -> String {
+    format!("an address from 0 to {}", memory_size.saturating_sub(1))
+}
+
+/// Reads a value as the word holding it in bytes of `byte_size` values, `-0` being the
+/// negative zero.
+///
+/// ## Errors
+/// - When `text` isn't a number, or is one too large for five bytes.
+/// - When `Word::largest_magnitude` has no answer for `byte_size`.
+pub fn parse_value(text: &str, byte_size: u16) -> Result<Word, ParseDiag> {
+    let largest = Word::largest_magnitude(byte_size)
+        .ok_or_else(|| ParseDiag::new(text, "a byte size from 1 to 7131".to_string()))?;
+    let (positive, magnitude) = sign_and_magnitude(text).filter(|(_, magnitude)| *magnitude <= largest)
+        .ok_or_else(|| ParseDiag::new(text, format!("a value from -{0} to +{0}", largest)))?;
+    let mut word = Word::from_value_in_base(magnitude as i64, byte_size);
+    word.positive = positive;
+    Ok(word)
+}
+
+/// Reads a field specification, written `L:R` or `(L:R)`.
+///
+/// ## Errors
+/// - When `text` isn't of that form, or names no field of a word.
+pub fn parse_field_spec(text: &str) -> Result<FieldSpec, ParseDiag> {
+    let diag = || ParseDiag::new(text, FIELD_SPEC.to_string());
+    let trimmed = text.trim();
+    let inner = match trimmed.strip_prefix('(') {
+        Some(rest) => rest.strip_suffix(')').ok_or_else(diag)?,
+        None => trimmed,
+    };
+    let (left, right) = inner.split_once(':').ok_or_else(diag)?;
+    let position = |text: &str| Some(text.trim()).filter(|text| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|text| text.parse::<usize>().ok());
+    match (position(left), position(right)) {
+        (Some(left), Some(right)) => FieldSpec::new(left, right).map_err(|_| diag()),
+        _ => Err(diag()),
+    }
+}
+
+/// Reads a word in MIX notation, as `Word::from_mix_notation` does.
+///
+/// ## Errors
+/// - When `text` isn't a sign and five bytes.
+pub fn parse_word(text: &str) -> Result<Word, ParseDiag> {
+    Word::from_mix_notation(text).ok_or_else(|| ParseDiag::new(text, WORD.to_string()))
+}
+
+/// Reads a word in MIX notation, or a value as `parse_value` does.
+///
+/// ## Errors
+/// - When `text` is neither, naming both.
+pub fn parse_word_or_value(text: &str, byte_size: u16) -> Result<Word, ParseDiag> {
+    parse_word(text).or_else(|_| parse_value(text, byte_size)).map_err(|value| {
+        ParseDiag::new(text, format!("{}, or {}", WORD, value.expected))
+    })
+}
@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::word::Word;
+use super::{ingest_block, Peripheral, TapeError, BLOCK_WORDS};
+use crate::sanitize::IngestPolicy;
+
+/// A bounded first-in first-out queue of blocks connecting a unit on one machine to a
+/// unit on another, as if one machine's output tape were mounted as the other's input.
+pub struct Channel {
+    blocks: VecDeque<[Word; BLOCK_WORDS]>,
+    depth: usize,
+    block_words: usize,
+}
+
+impl Channel {
+    /// Creates an empty channel holding at most `depth` blocks, and at least one.
+    pub fn new(depth: usize) -> Rc<RefCell<Channel>> {
+        Channel::with_block_words(depth, BLOCK_WORDS)
+    }
+
+    /// Creates an empty channel holding at most `depth` blocks of `block_words` words each,
+    /// such as the lines of a printer. Blocks hold from one word to `BLOCK_WORDS`.
+    pub fn with_block_words(depth: usize, block_words: usize) -> Rc<RefCell<Channel>> {
+        let block_words = block_words.clamp(1, BLOCK_WORDS);
+        Rc::new(RefCell::new(Channel { blocks: VecDeque::new(), depth: depth.max(1), block_words }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.blocks.len() >= self.depth
+    }
+
+    /// The number of words of each block.
+    pub fn block_words(&self) -> usize {
+        self.block_words
+    }
+
+    /// The blocks queued, oldest first.
+    pub fn blocks(&self) -> impl Iterator<Item = &[Word]> {
+        self.blocks.iter().map(move |block| &block[..self.block_words])
+    }
+}
+
+/// One end of a `Channel`. The producing end is busy while the channel is full and the
+/// consuming end is busy while it is empty.
+pub struct ChannelEnd {
+    channel: Rc<RefCell<Channel>>,
+    producer: bool,
+}
+
+impl ChannelEnd {
+    pub fn producer(channel: &Rc<RefCell<Channel>>) -> ChannelEnd {
+        ChannelEnd { channel: Rc::clone(channel), producer: true }
+    }
+
+    pub fn consumer(channel: &Rc<RefCell<Channel>>) -> ChannelEnd {
+        ChannelEnd { channel: Rc::clone(channel), producer: false }
+    }
+}
+
+impl Peripheral for ChannelEnd {
+    fn read_block_into(&mut self, dst: &mut [Word], byte_size: u16) -> Result<(), TapeError> {
+        let mut channel = self.channel.borrow_mut();
+        if self.producer {
+            return Err(TapeError::Io(std::io::Error::other("can't read from the producing end of a channel")));
+        }
+        if dst.len() != channel.block_words {
+            return Err(TapeError::WrongBlockLength(dst.len()));
+        }
+        let block = channel.len();
+        let words = channel.blocks.front().ok_or(TapeError::EndOfTape { block })?;
+        ingest_block(&words[..dst.len()], dst, IngestPolicy::Reject, byte_size)?;
+        channel.blocks.pop_front();
+        Ok(())
+    }
+
+    /// Queues a copy of `src`. The channel keeps its blocks inline, so once it has held
+    /// `depth` blocks no further transfer allocates.
+    fn write_block(&mut self, src: &[Word]) -> Result<(), TapeError> {
+        let mut channel = self.channel.borrow_mut();
+        if !self.producer {
+            return Err(TapeError::Io(std::io::Error::other("can't write to the consuming end of a channel")));
+        }
+        if src.len() != channel.block_words {
+            return Err(TapeError::WrongBlockLength(src.len()));
+        }
+        if channel.is_full() {
+            return Err(TapeError::Io(std::io::Error::other("channel is full")));
+        }
+        let mut block = [Word::default(); BLOCK_WORDS];
+        block[..src.len()].copy_from_slice(src);
+        channel.blocks.push_back(block);
+        Ok(())
+    }
+
+    /// Channels can't be repositioned, so control operations do nothing.
+    fn control(&mut self, _amount: i64) -> Result<(), TapeError> {
+        Ok(())
+    }
+
+    fn is_busy(&self) -> bool {
+        let channel = self.channel.borrow();
+        if self.producer { channel.is_full() } else { channel.is_empty() }
+    }
+
+    fn block_words(&self) -> usize {
+        self.channel.borrow().block_words
+    }
+}
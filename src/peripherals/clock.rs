@@ -0,0 +1,234 @@
+//! A clock a program can read the time from, for interactive demos such as a running
+//! clock on the typewriter. Everything else a computer does follows from its program and
+//! its inputs, but a clock reading the host's time doesn't, so a `ClockDevice` is outside
+//! of the crate's determinism guarantees: a run reading one can do something different
+//! every time it runs.
+//!
+//! `IN` from the clock transfers one word, the reading: the milliseconds since the
+//! clock's epoch, which is when it was made and which `IOC` resets, or the seconds since
+//! the Unix epoch, as its `ClockMode` says. Where the time comes from is a `TimeSource`:
+//!
+//! - `SystemTimeSource`, the host's clock, for real use.
+//! - `ManualTimeSource`, a clock that moves only when advanced, by hand or with the cycles
+//!   of the computer at a given ratio, so that tests get the same readings every run.
+//!
+//! A run with the host's time can still be replayed. `ClockDevice::record` keeps every
+//! reading the program is given, and a clock made by `ClockDevice::replay` from them gives
+//! the same readings in the same order, whatever the time. Since a recording is the only
+//! way to run such a program twice alike, a clock with a source that isn't deterministic
+//! records only once told that its readings come from the host.
+//!
+//! Unstable: there is no unit number a clock is meant to be attached as, nor a settled
+//! format for its readings, and either may change in any release.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::word::Word;
+use super::{Peripheral, TapeError};
+
+/// Where a clock gets the time. The trait is open, for sources such as a simulated clock
+/// shared between machines.
+pub trait TimeSource {
+    /// The time since the Unix epoch, when the computer's time is `time` cycles.
+    fn now(&mut self, time: u64) -> Duration;
+
+    /// Whether the times given depend only on the calls made, so that a run reading them
+    /// gives the same readings every time.
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// The host's clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    /// The host's time, or the epoch itself for a host clock set before it.
+    fn now(&mut self, _time: u64) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+}
+
+/// A clock that moves only when advanced. Clones share the time, so a test can keep one
+/// to advance while the clock it gave a `ClockDevice` is attached.
+#[derive(Clone, Debug)]
+pub struct ManualTimeSource {
+    now: Rc<Cell<Duration>>,
+    cycles_per_millisecond: Option<u64>,
+}
+
+impl ManualTimeSource {
+    /// A clock at `start` that moves only with `advance`.
+    pub fn new(start: Duration) -> ManualTimeSource {
+        ManualTimeSource { now: Rc::new(Cell::new(start)), cycles_per_millisecond: None }
+    }
+
+    /// A clock at `start` when the computer's time is 0, which moves a millisecond with
+    /// every `cycles_per_millisecond` cycles of the computer, as well as with `advance`.
+    ///
+    /// ## Panics
+    /// - When `cycles_per_millisecond` is 0.
+    pub fn following_cycles(start: Duration, cycles_per_millisecond: u64) -> ManualTimeSource {
+        assert!(cycles_per_millisecond > 0, "[Error ManualTimeSource::following_cycles] A millisecond takes at least one cycle");
+        ManualTimeSource { now: Rc::new(Cell::new(start)), cycles_per_millisecond: Some(cycles_per_millisecond) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&mut self, time: u64) -> Duration {
+        let cycles = self.cycles_per_millisecond.map_or(Duration::ZERO, |cycles| Duration::from_millis(time / cycles));
+        self.now.get() + cycles
+    }
+}
+
+/// What a clock's reading counts.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ClockMode {
+    /// The milliseconds since the clock's epoch.
+    #[default]
+    ElapsedMilliseconds,
+    /// The seconds since the Unix epoch.
+    EpochSeconds,
+}
+
+/// The readings a clock gave a run, in order, for replaying it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClockRecording {
+    pub mode: ClockMode,
+    pub readings: Vec<u64>,
+}
+
+/// Errors raised while starting a recording.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClockError {
+    /// The clock reads the host's time, and recording wasn't acknowledged to take it.
+    NondeterministicSource,
+}
+
+impl fmt::Display for ClockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClockError::NondeterministicSource =>
+                write!(f, "The clock reads the host's time, which a run can't be replayed from unless it is acknowledged"),
+        }
+    }
+}
+
+/// Where a clock's readings come from.
+enum Readings {
+    Source { source: Box<dyn TimeSource>, epoch: Duration, recorded: Option<Vec<u64>> },
+    Replayed(VecDeque<u64>),
+}
+
+/// A unit giving the time, one word at a time.
+pub struct ClockDevice {
+    mode: ClockMode,
+    readings: Readings,
+    /// The computer's time at the operation being issued.
+    time: u64,
+}
+
+impl ClockDevice {
+    /// A clock reading `source`, whose epoch is its time now.
+    pub fn new(mut source: Box<dyn TimeSource>, mode: ClockMode) -> ClockDevice {
+        let epoch = source.now(0);
+        ClockDevice { mode, readings: Readings::Source { source, epoch, recorded: None }, time: 0 }
+    }
+
+    /// A clock giving the readings of `recording` in order, and failing once they run out.
+    pub fn replay(recording: ClockRecording) -> ClockDevice {
+        ClockDevice { mode: recording.mode, readings: Readings::Replayed(recording.readings.into()), time: 0 }
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    /// Keeps every reading from now on, for `recording`. Acknowledging the source is
+    /// needed only when it isn't deterministic, and does nothing for a clock replaying.
+    ///
+    /// ## Errors
+    /// - `NondeterministicSource` when the source reads the host's time and
+    ///   `acknowledge_nondeterminism` isn't set.
+    pub fn record(&mut self, acknowledge_nondeterminism: bool) -> Result<(), ClockError> {
+        if let Readings::Source { source, recorded, .. } = &mut self.readings {
+            if !source.is_deterministic() && !acknowledge_nondeterminism {
+                return Err(ClockError::NondeterministicSource);
+            }
+            recorded.get_or_insert_with(Vec::new);
+        }
+        Ok(())
+    }
+
+    /// The readings given since `record`, or `None` when the clock isn't recording.
+    pub fn recording(&self) -> Option<ClockRecording> {
+        match &self.readings {
+            Readings::Source { recorded: Some(readings), .. } => Some(ClockRecording { mode: self.mode, readings: readings.clone() }),
+            _ => None,
+        }
+    }
+
+    /// The next reading, as `mode` counts it.
+    fn read(&mut self) -> Result<u64, TapeError> {
+        match &mut self.readings {
+            Readings::Source { source, epoch, recorded } => {
+                let now = source.now(self.time);
+                let reading = match self.mode {
+                    ClockMode::ElapsedMilliseconds => now.saturating_sub(*epoch).as_millis() as u64,
+                    ClockMode::EpochSeconds => now.as_secs(),
+                };
+                if let Some(recorded) = recorded {
+                    recorded.push(reading);
+                }
+                Ok(reading)
+            },
+            Readings::Replayed(readings) => readings.pop_front().ok_or(TapeError::EndOfTape { block: 0 }),
+        }
+    }
+}
+
+impl Peripheral for ClockDevice {
+    /// Gives the reading as the one word of the block, or the largest word when it is
+    /// larger.
+    fn read_block_into(&mut self, dst: &mut [Word], byte_size: u16) -> Result<(), TapeError> {
+        if dst.len() != 1 {
+            return Err(TapeError::WrongBlockLength(dst.len()));
+        }
+        let largest = Word::largest_magnitude(byte_size).unwrap_or(u64::MAX);
+        dst[0] = Word::from_value_in_base(self.read()?.min(largest) as i64, byte_size);
+        Ok(())
+    }
+
+    fn write_block(&mut self, _src: &[Word]) -> Result<(), TapeError> {
+        Err(TapeError::Io(std::io::Error::other("can't write to a clock")))
+    }
+
+    /// Resets the epoch to now, whatever `amount`. A clock replaying gives its readings as
+    /// recorded, resets and all.
+    fn control(&mut self, _amount: i64) -> Result<(), TapeError> {
+        if let Readings::Source { source, epoch, .. } = &mut self.readings {
+            *epoch = source.now(self.time);
+        }
+        Ok(())
+    }
+
+    fn block_words(&self) -> usize {
+        1
+    }
+
+    fn set_time(&mut self, time: u64) {
+        self.time = time;
+    }
+}
@@ -1,15 +1,190 @@
-use crate::word::Word;
-
-pub struct MagneticTapeUnit {
-    unit_number: u8,
-    block: [Word; 100],
-}
-
-impl MagneticTapeUnit {
-    pub fn new(number: u8, contents: [Word; 100]) -> MagneticTapeUnit {
-        MagneticTapeUnit {
-            unit_number: number,
-            block: contents,
-        }
-    }
-}
\ No newline at end of file
+use crate::word::Word;
+use super::{ingest_block, Peripheral};
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::sanitize::IngestPolicy;
+
+pub use super::{TapeError, BLOCK_WORDS};
+#[cfg(feature = "peripherals-file")]
+pub use super::tape_stream::{TapeMedium, ENCODED_BLOCK_BYTES};
+
+/// Where the blocks of a tape are kept: in memory, or encoded on a stream with
+/// `peripherals-file` (see `tape_stream`).
+pub(super) trait TapeStorage {
+    /// Reads block `block` into `dst` through `ingest_block`, leaving `dst` untouched on
+    /// failure.
+    fn read_block_into(&mut self, block: usize, dst: &mut [Word], policy: IngestPolicy, byte_size: u16) -> Result<(), TapeError>;
+
+    /// Writes `words`, exactly one block, as block `block`.
+    fn write_block(&mut self, block: usize, words: &[Word]) -> Result<(), TapeError>;
+
+    /// Block `block`, when it can be given back without moving.
+    fn block(&self, _block: usize) -> Option<Vec<Word>> {
+        None
+    }
+
+    fn flush(&mut self) -> Result<(), TapeError> {
+        Ok(())
+    }
+}
+
+/// Every block held in memory.
+struct MemoryStorage(Vec<[Word; BLOCK_WORDS]>);
+
+impl TapeStorage for MemoryStorage {
+    fn read_block_into(&mut self, block: usize, dst: &mut [Word], policy: IngestPolicy, byte_size: u16) -> Result<(), TapeError> {
+        let words = self.0.get(block).ok_or(TapeError::EndOfTape { block })?;
+        ingest_block(words, dst, policy, byte_size)
+    }
+
+    fn write_block(&mut self, block: usize, words: &[Word]) -> Result<(), TapeError> {
+        let blocks = &mut self.0;
+        blocks.resize(blocks.len().max(block + 1), [Word::default(); BLOCK_WORDS]);
+        blocks[block].copy_from_slice(words);
+        Ok(())
+    }
+
+    fn block(&self, block: usize) -> Option<Vec<Word>> {
+        self.0.get(block).map(|block| block.to_vec())
+    }
+}
+
+pub struct MagneticTapeUnit {
+    unit_number: u8,
+    storage: Box<dyn TapeStorage>,
+    position: usize,
+    /// How blocks read with a byte too large are handled, `Reject` unless set.
+    pub ingest_policy: IngestPolicy,
+}
+
+impl MagneticTapeUnit {
+    /// Creates an in-memory tape holding the single block `contents`, positioned at the
+    /// start of the tape.
+    pub fn new(number: u8, contents: [Word; 100]) -> MagneticTapeUnit {
+        MagneticTapeUnit {
+            unit_number: number,
+            storage: Box::new(MemoryStorage(vec![contents])),
+            position: 0,
+            ingest_policy: IngestPolicy::Reject,
+        }
+    }
+
+    /// Creates a tape backed by `medium`, positioned at the start of the tape. The
+    /// medium is expected to hold blocks in the format written by `write_block`, or to
+    /// be empty.
+    #[cfg(feature = "peripherals-file")]
+    pub fn from_stream<M: TapeMedium + 'static>(number: u8, medium: M) -> MagneticTapeUnit {
+        MagneticTapeUnit {
+            unit_number: number,
+            storage: Box::new(super::tape_stream::StreamStorage::new(medium)),
+            position: 0,
+            ingest_policy: IngestPolicy::Reject,
+        }
+    }
+
+    pub fn unit_number(&self) -> u8 {
+        self.unit_number
+    }
+
+    /// The index of the block the next read or write will act on.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Reads the block under the head and advances past it.
+    ///
+    /// ## Errors
+    /// - `EndOfTape` when no block has been written at the current position.
+    /// - `CorruptBlock` when a stream-backed block fails its header or sign checks.
+    pub fn read_block(&mut self) -> Result<[Word; BLOCK_WORDS], TapeError> {
+        let mut words = [Word::default(); BLOCK_WORDS];
+        self.read_block_into(&mut words, CONFIGURED_BYTE_SIZE as u16)?;
+        Ok(words)
+    }
+
+    /// Reads the block under the head into `dst` and advances past it. The block passes
+    /// through the gate of `sanitize` against `byte_size` under `ingest_policy`, and `dst`
+    /// is left untouched on failure.
+    ///
+    /// ## Errors
+    /// As `read_block`, and also
+    /// - `WrongBlockLength` when `dst` doesn't hold exactly one block.
+    /// - `InvalidWord` when a word has a byte of `byte_size` or more, under `Reject`.
+    pub fn read_block_into(&mut self, dst: &mut [Word], byte_size: u16) -> Result<(), TapeError> {
+        if dst.len() != BLOCK_WORDS {
+            return Err(TapeError::WrongBlockLength(dst.len()));
+        }
+        self.storage.read_block_into(self.position, dst, self.ingest_policy, byte_size)?;
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Writes `words` to the block under the head and advances past it. Blocks further
+    /// along the tape are left as they were.
+    ///
+    /// ## Errors
+    /// - `WrongBlockLength` when `words` isn't exactly one block.
+    pub fn write_block(&mut self, words: &[Word]) -> Result<(), TapeError> {
+        if words.len() != BLOCK_WORDS {
+            return Err(TapeError::WrongBlockLength(words.len()));
+        }
+        self.storage.write_block(self.position, words)?;
+        self.position += 1;
+        Ok(())
+    }
+
+    /// The block just behind the head, for an in-memory tape whose last operation wrote
+    /// it. `None` for a stream-backed tape, whose blocks can't be read without seeking.
+    pub fn last_block_written(&self) -> Option<Vec<Word>> {
+        self.storage.block(self.position.checked_sub(1)?)
+    }
+
+    /// Moves the head by `blocks`, forwards when positive and backwards when negative.
+    pub fn skip(&mut self, blocks: i64) -> Result<(), TapeError> {
+        let position = self.position as i64 + blocks;
+        if position < 0 {
+            return Err(TapeError::BeforeStart);
+        }
+        self.position = position as usize;
+        Ok(())
+    }
+
+    /// Moves the head back to the first block.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    /// Flushes any buffered writes to the underlying stream.
+    pub fn flush(&mut self) -> Result<(), TapeError> {
+        self.storage.flush()
+    }
+}
+
+impl Drop for MagneticTapeUnit {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Peripheral for MagneticTapeUnit {
+    fn read_block_into(&mut self, dst: &mut [Word], byte_size: u16) -> Result<(), TapeError> {
+        MagneticTapeUnit::read_block_into(self, dst, byte_size)
+    }
+
+    fn write_block(&mut self, src: &[Word]) -> Result<(), TapeError> {
+        MagneticTapeUnit::write_block(self, src)
+    }
+
+    /// Rewinds the tape when `amount` is 0 and otherwise skips `amount` blocks.
+    fn control(&mut self, amount: i64) -> Result<(), TapeError> {
+        if amount == 0 {
+            self.rewind();
+            Ok(())
+        } else {
+            self.skip(amount)
+        }
+    }
+
+    fn last_block_written(&self) -> Option<Vec<Word>> {
+        MagneticTapeUnit::last_block_written(self)
+    }
+}
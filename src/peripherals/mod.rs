@@ -1,4 +1,186 @@
-pub use magnetic_tape::MagneticTapeUnit;
-pub use disk_drum::DiskDrumUnit;
-
-mod peripherals;
\ No newline at end of file
+//! The units a computer drives with `IN`, `OUT`, `IOC`, `JBUS`, and `JRED`. The core
+//! holds what the computer needs to drive any unit: the `Peripheral` trait, `TapeError`,
+//! the block size, and the output limits. The units themselves, magnetic tapes, channels,
+//! the disk and drum, and the clock, come with the `peripherals` feature, and tapes
+//! streamed from a file with `peripherals-file`.
+
+#[cfg(feature = "peripherals")]
+pub use magnetic_tape::MagneticTapeUnit;
+#[cfg(feature = "peripherals")]
+pub use disk_drum::DiskDrumUnit;
+#[cfg(feature = "peripherals")]
+pub use channel::{Channel, ChannelEnd};
+#[cfg(feature = "peripherals")]
+pub use clock::{ClockDevice, ClockError, ClockMode, ClockRecording, ManualTimeSource, SystemTimeSource, TimeSource};
+
+#[cfg(feature = "peripherals")]
+pub mod magnetic_tape;
+#[cfg(feature = "peripherals-file")]
+mod tape_stream;
+#[cfg(feature = "peripherals")]
+pub mod channel;
+#[cfg(feature = "peripherals")]
+mod disk_drum;
+#[cfg(feature = "peripherals")]
+pub mod clock;
+#[cfg(feature = "peripherals")]
+mod standard;
+
+use std::fmt;
+use std::io;
+use crate::word::Word;
+use crate::sanitize::{self, IngestError, IngestPolicy};
+use crate::consts::UnitNumber;
+use std::convert::TryFrom;
+
+/// The number of words in a single block of tape.
+pub const BLOCK_WORDS: usize = 100;
+
+/// The number of words of a punched card, its 80 columns at five characters to a word. A
+/// card reader transfers blocks of `BLOCK_WORDS`, so it gives a card as the first words of
+/// a block, which `IN` reads whole.
+pub const CARD_WORDS: usize = 16;
+
+/// The number of words of a line of the line printer, its 120 characters at five to a word.
+pub const PRINTER_WORDS: usize = 24;
+
+/// The unit number of the card punch.
+pub const CARD_PUNCH_UNIT: u8 = UnitNumber::CardPunch.number();
+
+/// The unit number of the line printer.
+pub const PRINTER_UNIT: u8 = UnitNumber::Printer.number();
+
+/// Errors raised while reading, writing, or positioning a tape.
+#[derive(Debug)]
+pub enum TapeError {
+    /// The underlying stream failed.
+    Io(io::Error),
+    /// The block at `block` could not be decoded.
+    CorruptBlock { block: usize, reason: String },
+    /// There is no block at `block` to read.
+    EndOfTape { block: usize },
+    /// A backwards skip would move before the start of the tape.
+    BeforeStart,
+    /// A block transfer was given a buffer of the wrong number of words.
+    WrongBlockLength(usize),
+    /// The word at `index` of an incoming block has a byte too large for the machine.
+    InvalidWord { index: usize, word: Word },
+}
+
+impl fmt::Display for TapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TapeError::Io(error) => write!(f, "I/O error on tape: {}", error),
+            TapeError::CorruptBlock { block, reason } => write!(f, "Corrupt tape block {}: {}", block, reason),
+            TapeError::EndOfTape { block } => write!(f, "No block {} on tape", block),
+            TapeError::BeforeStart => write!(f, "Skipped before the start of the tape"),
+            TapeError::WrongBlockLength(length) => write!(f, "Blocks hold {} words, not {}", BLOCK_WORDS, length),
+            TapeError::InvalidWord { index, word } => write!(f, "Word {} of the block [{}] doesn't fit the byte size", index, word),
+        }
+    }
+}
+
+impl From<io::Error> for TapeError {
+    fn from(error: io::Error) -> TapeError {
+        TapeError::Io(error)
+    }
+}
+
+impl From<IngestError> for TapeError {
+    fn from(error: IngestError) -> TapeError {
+        TapeError::InvalidWord { index: error.index, word: error.word }
+    }
+}
+
+/// The most blocks a run may write to each kind of unit, so that a program stuck in an
+/// output loop stops instead of filling its devices without end. `None` is unlimited.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct OutputLimits {
+    /// Lines written to the line printer, unit 18.
+    pub max_printed_lines: Option<usize>,
+    /// Cards written to the card punch, unit 17.
+    pub max_punched_cards: Option<usize>,
+    /// Blocks written to each magnetic tape, units 0 to 7.
+    pub max_tape_blocks_written: Option<usize>,
+}
+
+impl OutputLimits {
+    /// No limits, for interactive use where someone is watching the run.
+    pub fn unlimited() -> Self {
+        OutputLimits::default()
+    }
+
+    /// Limits well beyond what any reasonable program writes, for unattended runs.
+    pub fn batch() -> Self {
+        OutputLimits {
+            max_printed_lines: Some(100_000),
+            max_punched_cards: Some(10_000),
+            max_tape_blocks_written: Some(100_000),
+        }
+    }
+
+    /// The most blocks that may be written to `unit`.
+    pub fn limit_for(&self, unit: u8) -> Option<usize> {
+        match UnitNumber::try_from(unit) {
+            Ok(UnitNumber::Tape(_)) => self.max_tape_blocks_written,
+            Ok(UnitNumber::CardPunch) => self.max_punched_cards,
+            Ok(UnitNumber::Printer) => self.max_printed_lines,
+            _ => None,
+        }
+    }
+}
+
+/// A block-oriented unit that can be attached to a `Computer` and driven by the `IN`,
+/// `OUT`, `IOC`, `JBUS`, and `JRED` instructions. The trait is open, for units outside the
+/// crate, and a method added to it will have a default.
+pub trait Peripheral {
+    /// Reads the next block from the unit straight into `dst`, which is usually a slice of
+    /// the computer's memory. Implementations pass the block through the gate of
+    /// `sanitize` against `byte_size` (see `ingest_block`), so that a failed read leaves
+    /// `dst` untouched.
+    fn read_block_into(&mut self, dst: &mut [Word], byte_size: u16) -> Result<(), TapeError>;
+
+    /// Writes `src`, usually a slice of the computer's memory, as the next block of the unit.
+    fn write_block(&mut self, src: &[Word]) -> Result<(), TapeError>;
+
+    /// Performs the unit's control operation for `IOC`, where `amount` is the instruction's
+    /// address.
+    fn control(&mut self, amount: i64) -> Result<(), TapeError>;
+
+    /// Whether the unit itself can't accept an operation right now, independent of any
+    /// transfer the computer has in progress on it. A busy unit stays busy until something
+    /// outside the computer, such as another machine, acts on it.
+    fn is_busy(&self) -> bool {
+        false
+    }
+
+    /// The number of words `IN` reads from the unit, and `OUT` writes to it, at a time.
+    fn block_words(&self) -> usize {
+        BLOCK_WORDS
+    }
+
+    /// Tells the unit the computer's time before each `IN` or `IOC` issued on it, for
+    /// units whose blocks depend on when they are read.
+    fn set_time(&mut self, _time: u64) {}
+
+    /// The block most recently written by `write_block`, for units that can give it back
+    /// without moving, so that the computer can check its trace against the unit.
+    fn last_block_written(&self) -> Option<Vec<Word>> {
+        None
+    }
+}
+
+/// Copies the block `words` into `dst` through the gate of `sanitize`, with the words
+/// with a byte of `byte_size` or more handled as `policy` says.
+///
+/// ## Errors
+/// - `InvalidWord` under `Reject` for the first word with a byte too large, in which
+///   case `dst` is left untouched.
+pub fn ingest_block(words: &[Word], dst: &mut [Word], policy: IngestPolicy, byte_size: u16) -> Result<(), TapeError> {
+    if policy == IngestPolicy::Reject {
+        sanitize::check_words(words, byte_size)?;
+    }
+    dst.copy_from_slice(words);
+    sanitize::ingest_in_place(dst, policy, byte_size)?;
+    Ok(())
+}
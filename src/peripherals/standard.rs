@@ -0,0 +1,33 @@
+//! The devices a program from the book expects, attached in one call.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::computer::{Computer, STANDARD_OUTPUT_DEPTH};
+use crate::consts::UnitNumber;
+use crate::word::Word;
+use super::{Channel, ChannelEnd, MagneticTapeUnit, BLOCK_WORDS, CARD_PUNCH_UNIT, PRINTER_UNIT, PRINTER_WORDS};
+
+impl Computer {
+    /// Attaches blank tapes as units 0 to 7, and the card punch and line printer as
+    /// channels holding up to `STANDARD_OUTPUT_DEPTH` blocks each. The disks and the input
+    /// units are left unattached, since there is nothing to read from them.
+    ///
+    /// ## Returns
+    /// - The channel of the line printer, for reading what was printed.
+    pub fn attach_standard_devices(&mut self) -> Rc<RefCell<Channel>> {
+        for unit in 0..UnitNumber::UNITS_OF_A_KIND {
+            self.attach_tape(MagneticTapeUnit::new(UnitNumber::Tape(unit).number(), [Word::default(); BLOCK_WORDS]));
+        }
+        let punch = Channel::new(STANDARD_OUTPUT_DEPTH);
+        self.attach(CARD_PUNCH_UNIT, ChannelEnd::producer(&punch));
+        let printer = Channel::with_block_words(STANDARD_OUTPUT_DEPTH, PRINTER_WORDS);
+        self.attach(PRINTER_UNIT, ChannelEnd::producer(&printer));
+        printer
+    }
+
+    /// Attaches `tape` as the unit given by its unit number.
+    pub fn attach_tape(&mut self, tape: MagneticTapeUnit) -> Rc<RefCell<MagneticTapeUnit>> {
+        let unit = tape.unit_number();
+        self.attach(unit, tape)
+    }
+}
@@ -0,0 +1,133 @@
+//! Magnetic tapes kept on a seekable stream, in practice a `File`, rather than in memory,
+//! so that the memory a tape uses is independent of its length. Blocks are read and
+//! written one at a time, each encoded with a header naming it.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use crate::word::Word;
+use crate::sanitize::IngestPolicy;
+use super::{ingest_block, TapeError, BLOCK_WORDS};
+use super::magnetic_tape::TapeStorage;
+
+/// Marks the start of every block on a stream-backed tape.
+const BLOCK_MAGIC: [u8; 4] = *b"MIXT";
+
+/// The size of the header preceding each block: the magic bytes followed by the block's
+/// sequence number as a big-endian `u32`.
+const HEADER_BYTES: usize = 8;
+
+/// Each word is stored as a sign byte (0 for +, 1 for -) followed by its five bytes.
+const WORD_BYTES: usize = 6;
+
+/// The size of a block on a stream-backed tape, including its header.
+pub const ENCODED_BLOCK_BYTES: usize = HEADER_BYTES + BLOCK_WORDS * WORD_BYTES;
+
+/// Anything a tape can be streamed from and to, in practice a `File`. Every stream that
+/// reads, writes, and seeks is one already, so the trait is sealed: it is a name for those
+/// bounds, not something to implement. Outside the crate, implementing it doesn't compile,
+/// even for a stream:
+///
+/// ```compile_fail,E0119
+/// use std::io::{self, Read, Seek, SeekFrom, Write};
+/// use mixal::peripherals::magnetic_tape::TapeMedium;
+///
+/// struct Blank;
+///
+/// impl Read for Blank {
+///     fn read(&mut self, _: &mut [u8]) -> io::Result<usize> { Ok(0) }
+/// }
+///
+/// impl Write for Blank {
+///     fn write(&mut self, bytes: &[u8]) -> io::Result<usize> { Ok(bytes.len()) }
+///     fn flush(&mut self) -> io::Result<()> { Ok(()) }
+/// }
+///
+/// impl Seek for Blank {
+///     fn seek(&mut self, _: SeekFrom) -> io::Result<u64> { Ok(0) }
+/// }
+///
+/// impl TapeMedium for Blank {}
+/// ```
+pub trait TapeMedium: Read + Write + Seek + sealed::Sealed {}
+
+impl<T: Read + Write + Seek> TapeMedium for T {}
+
+mod sealed {
+    use std::io::{Read, Seek, Write};
+
+    pub trait Sealed {}
+
+    impl<T: Read + Write + Seek> Sealed for T {}
+}
+
+/// Blocks encoded on a stream.
+pub(super) struct StreamStorage(Box<dyn TapeMedium>);
+
+impl StreamStorage {
+    pub(super) fn new<M: TapeMedium + 'static>(medium: M) -> StreamStorage {
+        StreamStorage(Box::new(medium))
+    }
+}
+
+impl TapeStorage for StreamStorage {
+    fn read_block_into(&mut self, block: usize, dst: &mut [Word], policy: IngestPolicy, byte_size: u16) -> Result<(), TapeError> {
+        let mut encoded = [0u8; ENCODED_BLOCK_BYTES];
+        self.0.seek(SeekFrom::Start((block * ENCODED_BLOCK_BYTES) as u64))?;
+        match self.0.read_exact(&mut encoded) {
+            Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(TapeError::EndOfTape { block });
+            },
+            result => result?,
+        }
+        let words = decode_block(&encoded, block)?;
+        ingest_block(&words, dst, policy, byte_size)
+    }
+
+    fn write_block(&mut self, block: usize, words: &[Word]) -> Result<(), TapeError> {
+        self.0.seek(SeekFrom::Start((block * ENCODED_BLOCK_BYTES) as u64))?;
+        self.0.write_all(&encode_block(words, block))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), TapeError> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+/// Encodes a block along with its header for storage on a stream.
+fn encode_block(words: &[Word], block: usize) -> [u8; ENCODED_BLOCK_BYTES] {
+    let mut encoded = [0u8; ENCODED_BLOCK_BYTES];
+    encoded[..4].copy_from_slice(&BLOCK_MAGIC);
+    encoded[4..HEADER_BYTES].copy_from_slice(&(block as u32).to_be_bytes());
+    for (i, word) in words.iter().enumerate() {
+        let offset = HEADER_BYTES + i * WORD_BYTES;
+        encoded[offset] = if word.positive { 0 } else { 1 };
+        encoded[(offset + 1)..(offset + WORD_BYTES)].copy_from_slice(&word.bytes);
+    }
+    encoded
+}
+
+/// Decodes a block read from a stream, checking that its header belongs to `block`.
+fn decode_block(encoded: &[u8; ENCODED_BLOCK_BYTES], block: usize) -> Result<[Word; BLOCK_WORDS], TapeError> {
+    let corrupt = |reason: String| TapeError::CorruptBlock { block, reason };
+    if encoded[..4] != BLOCK_MAGIC {
+        return Err(corrupt(format!("bad header {:?}", &encoded[..4])));
+    }
+    let mut sequence = [0u8; 4];
+    sequence.copy_from_slice(&encoded[4..HEADER_BYTES]);
+    let sequence = u32::from_be_bytes(sequence) as usize;
+    if sequence != block {
+        return Err(corrupt(format!("header has sequence number {}", sequence)));
+    }
+    let mut words = [Word::default(); BLOCK_WORDS];
+    for (i, word) in words.iter_mut().enumerate() {
+        let offset = HEADER_BYTES + i * WORD_BYTES;
+        word.positive = match encoded[offset] {
+            0 => true,
+            1 => false,
+            sign => return Err(corrupt(format!("word {} has sign byte {}", i, sign))),
+        };
+        word.bytes.copy_from_slice(&encoded[(offset + 1)..(offset + WORD_BYTES)]);
+    }
+    Ok(words)
+}
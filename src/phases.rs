@@ -0,0 +1,125 @@
+//! The fetch, decode, and execute phases of each step, for showing the instruction cycle
+//! one phase at a time. An observer set with `Computer::set_phase_observer` is called
+//! with every phase of every step, in order, and each step gives it exactly one of:
+//!
+//! - `Fetched`, `Decoded`, then `Executed`, for an instruction that decodes, whether or
+//!   not executing it succeeds.
+//! - `Fetched`, then `DecodeFailed`, for one that doesn't.
+//!
+//! A step that can't fetch, because `pc` is outside memory, gives no phases at all.
+
+use crate::computer::{ComparisonFlag, Computer};
+use crate::decode::DecodedInstruction;
+use crate::error::MixError;
+use crate::word::Word;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// The word at `pc` was read.
+    Fetched { pc: usize, word: Word },
+    /// The word was decoded into its parts and an operation. The mnemonic is `?` for an
+    /// extension instruction outside the MIX operation table.
+    Decoded { pc: usize, word: Word, breakdown: DecodedInstruction, mnemonic: String },
+    /// The word doesn't decode to an instruction the computer executes.
+    DecodeFailed { pc: usize, word: Word, error: MixError },
+    /// The instruction was executed, changing the state by `changes`.
+    Executed { pc: usize, changes: StateDelta, control: ControlFlow },
+}
+
+/// Where control went after an instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ControlFlow {
+    /// On to the next instruction.
+    Next,
+    /// To `to`, by a jump.
+    Jump { to: usize },
+    Halted,
+    /// Nowhere yet, as the instruction waits on `unit`.
+    Blocked { unit: u8 },
+    /// Nowhere, as the instruction failed.
+    Failed(MixError),
+}
+
+/// What an instruction changed, each as its value before and after.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateDelta {
+    /// The registers changed, in the order rA, rX, rI1 to rI6, rJ.
+    pub registers: Vec<(&'static str, Word, Word)>,
+    /// The words of memory changed, in order of address.
+    pub memory: Vec<(usize, Word, Word)>,
+    pub overflow: Option<(bool, bool)>,
+    pub comparison: Option<(ComparisonFlag, ComparisonFlag)>,
+}
+
+impl StateDelta {
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.memory.is_empty() && self.overflow.is_none() && self.comparison.is_none()
+    }
+}
+
+/// What changed since the program was loaded, one change to a line.
+pub(crate) fn render_delta(delta: &StateDelta) -> String {
+    if delta.is_empty() {
+        return "Nothing changed since the program was loaded".to_string();
+    }
+    let mut lines = vec!["Changes since the program was loaded:".to_string()];
+    for (name, before, after) in delta.registers.iter() {
+        lines.push(format!("  {:<4} [{}] -> [{}]", name, before, after));
+    }
+    for (address, before, after) in delta.memory.iter() {
+        lines.push(format!("  {:<4} [{}] -> [{}]", address, before, after));
+    }
+    if let Some((before, after)) = delta.overflow {
+        lines.push(format!("  OV   {} -> {}", before, after));
+    }
+    if let Some((before, after)) = delta.comparison {
+        lines.push(format!("  CI   {} -> {}", before, after));
+    }
+    lines.join("\n")
+}
+
+/// The state an instruction may change, taken before it executes.
+pub(crate) struct Snapshot {
+    registers: [(&'static str, Word); 9],
+    memory: Vec<Word>,
+    overflow: bool,
+    comparison: ComparisonFlag,
+}
+
+impl Snapshot {
+    pub(crate) fn of(computer: &Computer) -> Snapshot {
+        Snapshot {
+            registers: registers(computer),
+            memory: computer.memory.clone(),
+            overflow: computer.overflow_flag,
+            comparison: computer.comparison_flag,
+        }
+    }
+
+    /// What changed from this snapshot to `computer`.
+    pub(crate) fn delta(&self, computer: &Computer) -> StateDelta {
+        let registers = self.registers.iter().zip(registers(computer).iter())
+            .filter(|(before, after)| before.1 != after.1)
+            .map(|(before, after)| (before.0, before.1, after.1))
+            .collect();
+        let memory = self.memory.iter().zip(computer.memory.iter()).enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(address, (before, after))| (address, *before, *after))
+            .collect();
+        StateDelta {
+            registers,
+            memory,
+            overflow: Some((self.overflow, computer.overflow_flag)).filter(|(before, after)| before != after),
+            comparison: Some((self.comparison, computer.comparison_flag)).filter(|(before, after)| before != after),
+        }
+    }
+}
+
+fn registers(computer: &Computer) -> [(&'static str, Word); 9] {
+    [
+        ("rA", computer.ra), ("rX", computer.rx),
+        ("rI1", computer.ri1), ("rI2", computer.ri2), ("rI3", computer.ri3),
+        ("rI4", computer.ri4), ("rI5", computer.ri5), ("rI6", computer.ri6),
+        ("rJ", computer.rj),
+    ]
+}
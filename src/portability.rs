@@ -0,0 +1,317 @@
+//! Warnings for programs that count on more of this machine than a portable MIX program
+//! may, such as a byte of more than 64 values.
+
+use crate::word::{Word};
+use crate::field::FieldSpec;
+use crate::computer::Computer;
+use crate::consts::{EnterOp, Opcode, ShiftOp};
+use std::convert::TryFrom;
+
+/// The byte size every MIX program must be prepared to run under. Knuth only
+/// guarantees that a byte holds at least 64 distinct values, so any operation whose
+/// outcome changes when the bytes shrink to 64 values is not portable.
+pub const PORTABLE_BYTE_SIZE: i128 = 64;
+
+/// The byte size of a computer unless set otherwise with `Computer::set_byte_size`, and
+/// the one taken by the functions of words and programs that aren't given a byte size.
+pub const CONFIGURED_BYTE_SIZE: i128 = 256;
+
+/// Records an operation whose result would have been different had the program been
+/// run on a machine with 64-value bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PortabilityWarning {
+    pub pc: usize,
+    pub operation: String,
+    pub detail: String,
+}
+
+/// The inputs of an instruction, captured before it executes, which are needed to
+/// recompute its outcome under a different byte size.
+enum ShadowOperation {
+    Load { word: Word, field_specification: FieldSpec },
+    Store { register: Word, field_specification: FieldSpec },
+    /// The sum of the fields of the register and operand, or their difference when
+    /// `subtract`.
+    Add { register: Word, operand: Word, field_specification: FieldSpec, subtract: bool },
+    Increment { register: Word, delta: i128, width: u32 },
+    Mult { register: Word, operand: Word, field_specification: FieldSpec },
+    Div { ra: Word, rx: Word, operand: Word, field_specification: FieldSpec },
+    Compare { register: Word, operand: Word, field_specification: FieldSpec },
+    Shift { ra: Word, rx: Word, amount: usize, kind: u8 },
+}
+
+/// How a word produced on one machine is carried over to a machine with a different
+/// byte size. A word built from a number (by `ENTA` or arithmetic, say) keeps its value,
+/// whereas a word built from characters or packed fields keeps its bytes; the emulator
+/// can't know which one a program meant, so an operation is only considered unportable
+/// when it diverges under both readings.
+#[derive(Copy, Clone)]
+enum Interpretation {
+    Value,
+    Bytes,
+}
+
+/// Whether a field covers the whole magnitude of a word.
+fn is_whole(field_specification: FieldSpec) -> bool {
+    field_specification.left() <= 1 && field_specification.right() == 5
+}
+
+/// Splits a magnitude into `n` digits of the given byte size, most significant first.
+///
+/// ## Returns
+/// - `None` when the magnitude does not fit in `n` bytes of that size.
+fn digits(magnitude: i128, byte_size: i128, n: usize) -> Option<Vec<i128>> {
+    let mut remaining = magnitude.abs();
+    let mut result = vec![0; n];
+    for i in (0..n).rev() {
+        result[i] = remaining % byte_size;
+        remaining /= byte_size;
+    }
+    if remaining != 0 { None } else { Some(result) }
+}
+
+/// Splits a magnitude into the five bytes of a word of the given byte size.
+fn word_digits(magnitude: i128, byte_size: i128) -> Result<Vec<i128>, String> {
+    digits(magnitude, byte_size, 5)
+        .ok_or_else(|| format!("the value {} does not fit in five bytes of size {}", magnitude, byte_size))
+}
+
+/// Gives the five bytes `word` would hold on a machine with the given byte size.
+fn carried_bytes(word: &Word, byte_size: i128, interpretation: Interpretation) -> Result<Vec<i128>, String> {
+    match interpretation {
+        Interpretation::Value => word_digits(word.field_value(FieldSpec::MAGNITUDE) as i128, byte_size),
+        Interpretation::Bytes => {
+            let bytes: Vec<i128> = word.bytes.iter().map(|b| *b as i128).collect();
+            match bytes.iter().position(|b| *b >= byte_size) {
+                Some(i) => Err(format!("byte {} holds {}, which does not fit in a byte of size {}", i + 1, bytes[i], byte_size)),
+                None => Ok(bytes),
+            }
+        },
+    }
+}
+
+/// Reads the field `(l:r)` of `word` as it would be read on a machine with the given
+/// byte size.
+fn read_field(word: &Word, field_specification: FieldSpec, byte_size: i128, interpretation: Interpretation) -> Result<i128, String> {
+    if field_specification.is_sign_only() { return Ok(0); }
+    let bytes = carried_bytes(word, byte_size, interpretation)?;
+    let magnitude = bytes[field_specification.byte_indices()].iter().fold(0, |acc, b| acc * byte_size + b);
+    Ok(if field_specification.includes_sign() && !word.positive { -magnitude } else { magnitude })
+}
+
+/// Describes a value written to the field `(l:r)` in a way that is comparable across
+/// byte sizes: by value, or by the digits that land in the field's bytes.
+fn written_field(value: i128, field_specification: FieldSpec, byte_size: i128, interpretation: Interpretation) -> Result<Vec<i128>, String> {
+    if field_specification.is_sign_only() {
+        return Ok(vec![]);
+    }
+    if let Interpretation::Value = interpretation {
+        return Ok(vec![value]);
+    }
+    let width = field_specification.width();
+    let mut written = digits(value, byte_size, width)
+        .ok_or_else(|| format!("the value {} does not fit in {} bytes of size {}", value, width, byte_size))?;
+    if field_specification.includes_sign() { written.push(value.signum()); }
+    Ok(written)
+}
+
+impl ShadowOperation {
+
+    /// Evaluates the operation on a machine with the given byte size, returning the
+    /// observable values it produces (written fields, flags, shifted bytes).
+    fn evaluate(&self, byte_size: i128, interpretation: Interpretation) -> Result<Vec<i128>, String> {
+        let word_capacity = byte_size.pow(5);
+        let read = |word: &Word, field_specification| read_field(word, field_specification, byte_size, interpretation);
+        let written = |value, field_specification| written_field(value, field_specification, byte_size, interpretation);
+        match *self {
+            ShadowOperation::Load { ref word, field_specification } => {
+                written(read(word, field_specification)?, field_specification)
+            },
+            ShadowOperation::Store { ref register, field_specification } => {
+                if field_specification.is_sign_only() { return Ok(vec![]); }
+                // A store writes the rightmost bytes of the register into the field
+                let source = if is_whole(field_specification) { FieldSpec::WHOLE } else { FieldSpec::of(6 - field_specification.width(), 5) };
+                let mut value = read(register, source)?;
+                if field_specification.includes_sign() && !register.positive { value = -value.abs(); }
+                written(value, field_specification)
+            },
+            ShadowOperation::Add { ref register, ref operand, field_specification, subtract } => {
+                let operand = read(operand, field_specification)?;
+                let sum = read(register, field_specification)? + if subtract { -operand } else { operand };
+                let capacity = byte_size.pow(field_specification.width() as u32);
+                // A field without the sign keeps the magnitude of a negative difference.
+                let kept = if field_specification.includes_sign() { sum % capacity } else { (sum % capacity).abs() };
+                let mut outcome = written(kept, field_specification)?;
+                outcome.push((sum.abs() >= capacity) as i128);
+                Ok(outcome)
+            },
+            ShadowOperation::Increment { ref register, delta, width } => {
+                let sum = read(register, FieldSpec::WHOLE)? + delta;
+                let capacity = byte_size.pow(width);
+                Ok(vec![sum % capacity, (sum.abs() >= capacity) as i128])
+            },
+            ShadowOperation::Mult { ref register, ref operand, field_specification } => {
+                let product = read(register, FieldSpec::WHOLE)? * read(operand, field_specification)?;
+                Ok(vec![product / word_capacity, product % word_capacity])
+            },
+            ShadowOperation::Div { ref ra, ref rx, ref operand, field_specification } => {
+                let (a, x) = (read(ra, FieldSpec::MAGNITUDE)?, read(rx, FieldSpec::MAGNITUDE)?);
+                let divisor = read(operand, field_specification)?;
+                if divisor == 0 || a >= divisor.abs() {
+                    return Ok(vec![1]);
+                }
+                let dividend = a * word_capacity + x;
+                Ok(vec![0, dividend / divisor, dividend % divisor])
+            },
+            ShadowOperation::Compare { ref register, ref operand, field_specification } => {
+                Ok(vec![(read(register, field_specification)? - read(operand, field_specification)?).signum()])
+            },
+            ShadowOperation::Shift { ref ra, ref rx, amount, kind } => {
+                let mut bytes = carried_bytes(ra, byte_size, interpretation)?;
+                bytes.extend(carried_bytes(rx, byte_size, interpretation)?);
+                let n = if kind == 2 || kind == 3 { 10 } else { 5 };
+                let amount = amount.min(n);
+                let shifted: Vec<i128> = match kind {
+                    0 | 2 => (0..n).map(|i| if i + amount < n { bytes[i + amount] } else { 0 }).collect(),
+                    1 | 3 => (0..n).map(|i| if i >= amount { bytes[i - amount] } else { 0 }).collect(),
+                    4 => (0..n).map(|i| bytes[(i + amount) % n]).collect(),
+                    _ => (0..n).map(|i| bytes[(i + n - amount % n) % n]).collect(),
+                };
+                match interpretation {
+                    Interpretation::Bytes => Ok(shifted),
+                    Interpretation::Value => Ok(shifted.chunks(5).map(|c| c.iter().fold(0, |acc, b| acc * byte_size + b)).collect()),
+                }
+            },
+        }
+    }
+
+    /// Explains the divergence between the outcomes under the two byte sizes.
+    fn describe(&self, byte_size: i128, configured: &[i128], portable: &[i128]) -> String {
+        let subject = match *self {
+            ShadowOperation::Load { .. } | ShadowOperation::Store { .. } => "the field written",
+            ShadowOperation::Add { .. } | ShadowOperation::Increment { .. } => "the sum and overflow",
+            ShadowOperation::Mult { .. } => "the product (rA, rX)",
+            ShadowOperation::Div { .. } => "the overflow, quotient and remainder",
+            ShadowOperation::Compare { .. } => "the comparison",
+            ShadowOperation::Shift { .. } => "the shifted registers",
+        };
+        format!("{} is {:?} with byte size {} but {:?} with byte size {}",
+            subject, configured, byte_size, portable, PORTABLE_BYTE_SIZE)
+    }
+
+    /// Compares the outcome of the operation under `byte_size`, the computer's, and the
+    /// portable byte size with one interpretation of its operands.
+    ///
+    /// ## Returns
+    /// - `None` when both outcomes agree, or the reason they don't otherwise.
+    fn divergence(&self, byte_size: i128, interpretation: Interpretation) -> Option<String> {
+        let configured = self.evaluate(byte_size, interpretation).ok()?;
+        match self.evaluate(PORTABLE_BYTE_SIZE, interpretation) {
+            Ok(ref portable) if *portable == configured => None,
+            Ok(portable) => Some(self.describe(byte_size, &configured, &portable)),
+            Err(reason) => Some(reason),
+        }
+    }
+}
+
+/// Gives the name of a register as used in mnemonics, where 0 is rA, 1-6 are the index
+/// registers, and 7 is rX.
+fn register_name(register: u8) -> String {
+    match register {
+        0 => String::from("A"),
+        7 => String::from("X"),
+        _ => register.to_string(),
+    }
+}
+
+/// Reads a register without requiring mutable access to the computer, using the same
+/// numbering as `register_name`.
+fn register_word(computer: &Computer, register: u8) -> Word {
+    match register {
+        0 => computer.ra,
+        1 => computer.ri1,
+        2 => computer.ri2,
+        3 => computer.ri3,
+        4 => computer.ri4,
+        5 => computer.ri5,
+        6 => computer.ri6,
+        _ => computer.rx,
+    }
+}
+
+/// Captures the inputs of `instruction` from the current state of `computer`, if the
+/// instruction is one whose outcome can depend on the byte size.
+///
+/// ## Arguments
+/// - `computer`: The computer, in its state just before the instruction executes.
+/// - `instruction`: The instruction word about to be executed.
+/// - `address`: The effective (indexed) address of the instruction.
+///
+/// ## Returns
+/// - The mnemonic of the instruction along with its shadow operation, or `None` when
+///   the instruction cannot diverge (or addresses memory outside of the machine).
+fn capture(computer: &Computer, instruction: &Word, address: usize) -> Option<(String, ShadowOperation)> {
+    let (field, opcode) = (instruction.field(), instruction.opcode());
+    let field_specification = FieldSpec::from_byte(field).ok()?;
+    let operand = computer.memory.get(address).cloned();
+
+    let opcode = Opcode::try_from(opcode).ok()?;
+    let captured = match opcode {
+        Opcode::ADD => (String::from("ADD"), ShadowOperation::Add { register: computer.ra, operand: operand?, field_specification, subtract: false }),
+        Opcode::SUB => (String::from("SUB"), ShadowOperation::Add { register: computer.ra, operand: operand?, field_specification, subtract: true }),
+        Opcode::MUL => (String::from("MUL"), ShadowOperation::Mult { register: computer.ra, operand: operand?, field_specification }),
+        Opcode::DIV => (String::from("DIV"), ShadowOperation::Div { ra: computer.ra, rx: computer.rx, operand: operand?, field_specification }),
+        Opcode::SHIFT if field <= ShiftOp::RightCircular.field() => {
+            let names = ["SLA", "SRA", "SLAX", "SRAX", "SLC", "SRC"];
+            (String::from(names[field as usize]), ShadowOperation::Shift { ra: computer.ra, rx: computer.rx, amount: address, kind: field })
+        },
+        _ if (Opcode::LDA..=Opcode::LDXN).contains(&opcode) => {
+            let negative = if opcode >= Opcode::LDAN { "N" } else { "" };
+            let register = opcode.register_from(Opcode::LDA).or_else(|| opcode.register_from(Opcode::LDAN))?;
+            let name = format!("LD{}{}", register_name(register), negative);
+            (name, ShadowOperation::Load { word: operand?, field_specification })
+        },
+        _ if (Opcode::STA..=Opcode::STJ).contains(&opcode) => {
+            operand?;
+            let (name, register) = match opcode.register_from(Opcode::STA) {
+                Some(register) => (format!("ST{}", register_name(register)), register_word(computer, register)),
+                None => (String::from("STJ"), computer.rj),
+            };
+            (name, ShadowOperation::Store { register, field_specification })
+        },
+        _ if (Opcode::ENTA..=Opcode::ENTX).contains(&opcode) && field <= EnterOp::Decrease.field() => {
+            let register = opcode.register_from(Opcode::ENTA)?;
+            let increase = field == EnterOp::Increase.field();
+            let name = format!("{}{}", if increase { "INC" } else { "DEC" }, register_name(register));
+            let delta = (address as i128) * if instruction.positive == increase { 1 } else { -1 };
+            let width = if register == 0 || register == 7 { 5 } else { 2 };
+            (name, ShadowOperation::Increment { register: register_word(computer, register), delta, width })
+        },
+        _ if (Opcode::CMPA..=Opcode::CMPX).contains(&opcode) => {
+            let register = opcode.register_from(Opcode::CMPA)?;
+            let name = format!("CMP{}", register_name(register));
+            (name, ShadowOperation::Compare { register: register_word(computer, register), operand: operand?, field_specification })
+        },
+        _ => return None,
+    };
+    Some(captured)
+}
+
+/// Checks whether `instruction` would produce a different outcome with 64-value bytes
+/// than with the configured byte size, by running a shadow computation of just this
+/// operation under both sizes.
+///
+/// ## Arguments
+/// - `computer`: The computer, in its state just before the instruction executes.
+/// - `instruction`: The instruction word about to be executed.
+/// - `address`: The effective (indexed) address of the instruction.
+///
+/// ## Returns
+/// - A warning describing the divergence, or `None` when the operation is portable.
+pub fn check_instruction(computer: &Computer, instruction: &Word, address: usize) -> Option<PortabilityWarning> {
+    let (operation, shadow) = capture(computer, instruction, address)?;
+    let byte_size = computer.byte_size() as i128;
+    let by_value = shadow.divergence(byte_size, Interpretation::Value)?;
+    let by_bytes = shadow.divergence(byte_size, Interpretation::Bytes)?;
+    Some(PortabilityWarning { pc: computer.pc, operation, detail: format!("{}; read as a number, {}", by_bytes, by_value) })
+}
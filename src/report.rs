@@ -0,0 +1,206 @@
+//! Text renderings of what a run produced. Every rendering sorts its entries by a fixed
+//! key before display, so that running the same program twice renders byte-identical
+//! text whatever order the entries were collected in:
+//!
+//! - `render_portability_warnings`: by pc.
+//! - `render_memory`, `render_memory_as`: by address.
+//! - `render_memory_diff`: by address.
+//! - `render_listing`: by address.
+//! - `render_blocked_machines`, with `peripherals`: by the order the machines were added to the cluster.
+//!
+//! The emulator itself reads no clocks and no randomness, and keeps its own tables in
+//! ordered maps, so none of these depend on anything but the program and its inputs.
+//!
+//! Words of memory are written in a `WordFormat`, which is bytes unless asked otherwise.
+//!
+//! The renderings that grow with the program, `render_memory_as` and `render_listing`,
+//! each have a `write_` form writing to any `fmt::Write` a line at a time, so that a large
+//! one can go straight to a file through `IoWriter` without being held in a string.
+
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use crate::word::Word;
+use crate::field::FieldSpec;
+use crate::extensions::float::MixFloat;
+use crate::computer::Computer;
+use crate::portability::PortabilityWarning;
+#[cfg(feature = "peripherals")]
+use crate::cluster::BlockedMachine;
+use crate::decode::{self, DecodedItem};
+use crate::opcodes;
+
+/// One line per warning, `pc OPERATION: detail`, in order of pc.
+pub fn render_portability_warnings(warnings: &[PortabilityWarning]) -> String {
+    let mut sorted: Vec<&PortabilityWarning> = warnings.iter().collect();
+    sorted.sort_by_key(|warning| warning.pc);
+    sorted.iter()
+        .map(|warning| format!("{:04} {}: {}\n", warning.pc, warning.operation, warning.detail))
+        .collect()
+}
+
+/// The significant digits of a float written in `WordFormat::Float`, a little more than
+/// the precision of a fraction of four bytes.
+pub const FLOAT_DIGITS: usize = 10;
+
+/// How a word is written in renderings of memory.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum WordFormat {
+    /// The sign and each byte, as `Word` displays.
+    #[default]
+    Bytes,
+    /// The signed value of the whole word, at the configured byte size.
+    SignedValue,
+    /// The bytes, then the signed value.
+    Both,
+    /// The MIXAL statement for the word, or `Both` when it doesn't decode cleanly to a
+    /// known operation (see `decode::check_word`) or the source map gives it as data.
+    Instruction,
+    /// The value of the word as a float of the floating point attachment, in decimal.
+    Float,
+}
+
+impl WordFormat {
+    /// The format named `name`, one of `bytes`, `value`, `both`, `inst`, and `float`.
+    pub fn from_name(name: &str) -> Option<WordFormat> {
+        match name {
+            "bytes" => Some(WordFormat::Bytes),
+            "value" => Some(WordFormat::SignedValue),
+            "both" => Some(WordFormat::Both),
+            "inst" => Some(WordFormat::Instruction),
+            "float" => Some(WordFormat::Float),
+            _ => None,
+        }
+    }
+}
+
+/// `word` written in `format`. Values are right-aligned in 14 columns, wide enough for
+/// any word at 256 values to a byte, so that lines of a rendering stay aligned.
+pub fn format_word(word: &Word, format: WordFormat) -> String {
+    match format {
+        WordFormat::Bytes => word.to_string(),
+        WordFormat::SignedValue => format!("{:>14}", word.field_value(FieldSpec::WHOLE)),
+        WordFormat::Both => format!("{}  {:>14}", word, word.field_value(FieldSpec::WHOLE)),
+        WordFormat::Instruction => match decode::check_word(word) {
+            Ok(Some(_)) => opcodes::disassemble(word).unwrap_or_default(),
+            _ => format_word(word, WordFormat::Both),
+        },
+        WordFormat::Float => format!("{:>16}", MixFloat::from_word(word).to_decimal_string(FLOAT_DIGITS)),
+    }
+}
+
+/// The word at `address` of `computer` written in `format`, as by `format_word` but
+/// writing words the source map gives as data in `Both` rather than as instructions.
+pub fn format_word_at(computer: &Computer, address: usize, format: WordFormat) -> String {
+    let is_data = computer.source_map.as_ref().is_some_and(|map| map.data.contains(&address));
+    match format {
+        WordFormat::Instruction if is_data => format_word(&computer.memory[address], WordFormat::Both),
+        format => format_word(&computer.memory[address], format),
+    }
+}
+
+/// `address` as four digits, followed by the name of its region when the computer has a
+/// layout and the address is in one of its regions.
+fn address_label(computer: &Computer, address: usize) -> String {
+    match computer.layout.as_ref().and_then(|layout| layout.region_at(address)) {
+        Some(region) => format!("{:04} {}", address, region.name),
+        None => format!("{:04}", address),
+    }
+}
+
+/// One line per occupied word of memory in `range`, `address: word`, in order of address.
+/// Here and in `render_listing`, each address is followed by the name of its region when
+/// the computer has a layout.
+pub fn render_memory(computer: &Computer, range: Range<usize>) -> String {
+    render_memory_as(computer, range, WordFormat::Bytes)
+}
+
+/// `render_memory` with each word written in `format`.
+pub fn render_memory_as(computer: &Computer, range: Range<usize>, format: WordFormat) -> String {
+    let mut text = String::new();
+    write_memory_as(computer, range, format, &mut text).expect("[Error render_memory_as] Writing to a String can't fail");
+    text
+}
+
+/// Writes `render_memory_as` to `out` a line at a time.
+pub fn write_memory_as(computer: &Computer, range: Range<usize>, format: WordFormat, out: &mut dyn fmt::Write) -> fmt::Result {
+    for (address, _) in computer.iter_range(range) {
+        writeln!(out, "{}: {}", address_label(computer, address), format_word_at(computer, address, format))?;
+    }
+    Ok(())
+}
+
+/// One line per word of `range` that differs between `before` and `after`, in order of
+/// address: `address: old bytes -> new bytes`, with the old and new values in a second
+/// column for checking against hand calculations.
+pub fn render_memory_diff(before: &Computer, after: &Computer, range: Range<usize>) -> String {
+    range.filter(|address| before.memory[*address] != after.memory[*address])
+        .map(|address| {
+            let (old, new) = (&before.memory[address], &after.memory[address]);
+            format!("{}: {} -> {}  {} -> {}\n", address_label(after, address),
+                format_word(old, WordFormat::Bytes), format_word(new, WordFormat::Bytes),
+                old.field_value(FieldSpec::WHOLE), new.field_value(FieldSpec::WHOLE))
+        })
+        .collect()
+}
+
+/// One line per occupied word of memory in `range`, in order of address, giving what
+/// `Computer::decode_range` makes of it: a MIXAL statement for an instruction, `CON` and
+/// the value for data, and otherwise why the word isn't an instruction.
+pub fn render_listing(computer: &Computer, range: Range<usize>) -> String {
+    let mut text = String::new();
+    write_listing(computer, range, &mut text).expect("[Error render_listing] Writing to a String can't fail");
+    text
+}
+
+/// Writes `render_listing` to `out` a line at a time.
+pub fn write_listing(computer: &Computer, range: Range<usize>, out: &mut dyn fmt::Write) -> fmt::Result {
+    for (address, item) in computer.decode_range(range) {
+        write!(out, "{}: ", address_label(computer, address))?;
+        match item {
+            DecodedItem::Instruction(instruction) => match instruction.operation {
+                Some(_) => out.write_str(&opcodes::disassemble(&computer.memory[address]).unwrap_or_default())?,
+                None => write!(out, "extension C={} F={}", instruction.opcode, instruction.field)?,
+            },
+            DecodedItem::Data(word) => write!(out, "CON {}", word.field_value(FieldSpec::WHOLE))?,
+            DecodedItem::Unimplemented { opcode, field } => write!(out, "unimplemented C={} F={}", opcode, field)?,
+            DecodedItem::Invalid(error) => write!(out, "invalid, {}", error)?,
+        }
+        out.write_str("\n")?;
+    }
+    Ok(())
+}
+
+/// Lets the `write_` renderings write to an `io::Write`, such as a file or stdout.
+///
+/// ## Returns
+/// - From `fmt::Write`, `fmt::Error` when the writer fails, which keeps the `io::Error`
+///   in `error`.
+pub struct IoWriter<W: io::Write> {
+    pub inner: W,
+    pub error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> IoWriter<W> {
+        IoWriter { inner, error: None }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// One line per blocked machine, `machine waits on unit U (channel)`, in the order given,
+/// which for a `ClusterError::Deadlock` is the order the machines were added.
+#[cfg(feature = "peripherals")]
+pub fn render_blocked_machines(blocked: &[BlockedMachine]) -> String {
+    blocked.iter()
+        .map(|machine| format!("{} waits on unit {} ({})\n", machine.machine, machine.unit, machine.channel))
+        .collect()
+}
@@ -0,0 +1,283 @@
+//! Machine-readable summaries of a run, for scripts that would otherwise parse the
+//! `Display` output of the computer and its errors. With the `json` feature enabled,
+//! both reports serialize to and from JSON. The reports of assembling need the
+//! `assembler` feature, the lines printed need `peripherals`, and `run_source` both.
+//!
+//! ## Schema
+//! Every report carries `schema_version`, currently `SCHEMA_VERSION`. Within a major
+//! version the schema only grows:
+//! - Fields are never removed, renamed, or given a different type or meaning.
+//! - New fields may be added, and readers must ignore fields they don't know.
+//! - New `code`s may be added to diagnostics, and readers must accept codes they don't
+//!   know.
+//!
+//! Anything else bumps `SCHEMA_VERSION`. The checked-in `fixtures/run_report_v1.json`
+//! must keep deserializing with the current types.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "assembler")]
+use crate::assembler::AssembleError;
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+use crate::assembler::cache::Assembler;
+#[cfg(feature = "peripherals")]
+use crate::charset::CharsetMapping;
+use crate::checkpoint::CheckpointRing;
+use crate::computer::{Computer, ComparisonFlag, UnattachedPolicy};
+use crate::error::MixError;
+use crate::loader::RawParseError;
+#[cfg(feature = "peripherals")]
+use crate::peripherals::Channel;
+use crate::word::Word;
+use crate::field::FieldSpec;
+
+/// The version of the schema written by this code.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A register as both a signed value and its sign and bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct RegisterReport {
+    pub value: i64,
+    pub positive: bool,
+    pub bytes: [u8; 5],
+}
+
+impl From<&Word> for RegisterReport {
+    fn from(word: &Word) -> Self {
+        RegisterReport { value: word.field_value(FieldSpec::WHOLE), positive: word.positive, bytes: word.bytes }
+    }
+}
+
+/// A warning or error, with the source line it concerns if there is one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    pub code: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// The state of a computer once it has stopped running.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct RunReport {
+    pub schema_version: u32,
+    pub halted: bool,
+    pub pc: usize,
+    pub time: u64,
+    /// Keyed by `rA`, `rX`, `rI1` to `rI6`, and `rJ`.
+    pub registers: BTreeMap<String, RegisterReport>,
+    pub overflow: bool,
+    /// One of `less`, `equal`, or `greater`.
+    pub comparison: String,
+    /// Pages of lines written to the line printer.
+    pub printer: Vec<Vec<String>>,
+    pub warnings: Vec<Diagnostic>,
+    /// Whether the computer's warnings, trace, or heatmap dropped entries for want of
+    /// budget (see `budget`), so that `warnings` may be partial. Absent from reports
+    /// written before it was added, which read as `false`.
+    #[cfg_attr(feature = "json", serde(default))]
+    pub truncated: bool,
+}
+
+impl RunReport {
+    /// Summarizes `computer` as it stands, with its portability warnings, then the undefined
+    /// cases it went past, the code it overwrote, and the units it used with no device
+    /// attached, as warnings.
+    pub fn from_computer(computer: &Computer) -> Self {
+        let registers = [
+            ("rA", &computer.ra), ("rX", &computer.rx),
+            ("rI1", &computer.ri1), ("rI2", &computer.ri2), ("rI3", &computer.ri3),
+            ("rI4", &computer.ri4), ("rI5", &computer.ri5), ("rI6", &computer.ri6),
+            ("rJ", &computer.rj),
+        ].iter().map(|(name, word)| (name.to_string(), RegisterReport::from(*word))).collect();
+        let comparison = match computer.comparison_flag {
+            ComparisonFlag::less => "less",
+            ComparisonFlag::equal => "equal",
+            ComparisonFlag::greater => "greater",
+        };
+        let mut warnings: Vec<&_> = computer.portability_warnings.iter().collect();
+        warnings.sort_by_key(|warning| warning.pc);
+        RunReport {
+            schema_version: SCHEMA_VERSION,
+            halted: computer.is_halted(),
+            pc: computer.pc,
+            time: computer.time(),
+            registers,
+            overflow: computer.overflow_flag,
+            comparison: comparison.to_string(),
+            printer: Vec::new(),
+            warnings: warnings.iter().map(|warning| Diagnostic {
+                code: "portability".to_string(),
+                line: None,
+                message: format!("{:04} {}: {}", warning.pc, warning.operation, warning.detail),
+            }).chain(computer.undefined_warnings.iter().map(|warning| Diagnostic {
+                code: "undefined".to_string(),
+                line: None,
+                message: format!("{:04} {}: {}", warning.pc, warning.operation, warning.detail),
+            })).chain(computer.code_write_warnings.iter().map(|warning| Diagnostic {
+                code: "code-write".to_string(),
+                line: None,
+                message: format!("{:04} {}: ({}) overwrites the opcode of the instruction at {}",
+                    warning.pc, warning.operation, warning.field, warning.address),
+            })).chain(computer.unattached_warnings.iter().map(|warning| Diagnostic {
+                code: "unit-not-attached".to_string(),
+                line: None,
+                message: format!("{:04} {}: unit {} is not attached, so it was treated as always ready and empty",
+                    warning.pc, warning.operation, warning.unit),
+            })).collect(),
+            truncated: computer.truncated.any()
+                || computer.trace.as_ref().is_some_and(|trace| trace.truncated)
+                || computer.access_heatmap().is_some_and(|heatmap| heatmap.is_truncated()),
+        }
+    }
+}
+
+/// Why a program couldn't be loaded or didn't finish running.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct FaultReport {
+    pub schema_version: u32,
+    /// `assemble` if the program couldn't be loaded, `run` if it faulted while running.
+    pub stage: String,
+    /// The location of the instruction at fault, for faults while running.
+    pub pc: Option<usize>,
+    /// The region of the layout holding `pc`, when the computer has a layout.
+    #[cfg_attr(feature = "json", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub region: Option<String>,
+    pub errors: Vec<Diagnostic>,
+    /// The checkpoints the run took up to the fault, for rolling back to shortly before
+    /// it. They stay with the report in memory and aren't serialized.
+    #[cfg_attr(feature = "json", serde(skip))]
+    pub checkpoints: Option<CheckpointRing>,
+}
+
+impl FaultReport {
+    /// A fault while loading a program in the raw format.
+    pub fn from_parse_error(error: &RawParseError) -> Self {
+        FaultReport {
+            schema_version: SCHEMA_VERSION,
+            stage: "assemble".to_string(),
+            pc: None,
+            region: None,
+            errors: vec![Diagnostic {
+                code: error.kind.code().to_string(),
+                line: Some(error.line),
+                message: error.to_string(),
+            }],
+            checkpoints: None,
+        }
+    }
+
+    /// A fault while assembling a program.
+    #[cfg(feature = "assembler")]
+    pub fn from_assemble_error(error: &AssembleError) -> Self {
+        FaultReport {
+            schema_version: SCHEMA_VERSION,
+            stage: "assemble".to_string(),
+            pc: None,
+            region: None,
+            errors: vec![Diagnostic { code: "assemble".to_string(), line: Some(error.line), message: error.kind.to_string() }],
+            checkpoints: None,
+        }
+    }
+
+    /// A fault while running.
+    pub fn from_run_error(error: &MixError) -> Self {
+        FaultReport {
+            schema_version: SCHEMA_VERSION,
+            stage: "run".to_string(),
+            pc: error.pc(),
+            region: None,
+            errors: vec![Diagnostic { code: error.code().to_string(), line: None, message: error.to_string() }],
+            checkpoints: None,
+        }
+    }
+
+    /// A fault while running `computer`, naming the region of its layout holding the
+    /// instruction at fault.
+    pub fn from_run_error_on(error: &MixError, computer: &Computer) -> Self {
+        let mut report = FaultReport::from_run_error(error);
+        report.region = report.pc
+            .and_then(|pc| computer.layout.as_ref()?.region_at(pc))
+            .map(|region| region.name.clone());
+        report
+    }
+
+    /// The report with the checkpoints the run took before faulting.
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointRing) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+}
+
+/// Each block written to `printer` as a line of text in `mapping`, without trailing
+/// spaces. A program's lines are rendered in the mapping it was assembled with, so that
+/// what it prints reads as its `ALF`s were written.
+#[cfg(feature = "peripherals")]
+pub fn printed_lines(printer: &Channel, mapping: CharsetMapping) -> Vec<String> {
+    printer.blocks()
+        .map(|block| {
+            let line: String = block.iter()
+                .flat_map(|word| word.bytes.iter())
+                .map(|code| mapping.char_for(*code).unwrap_or('?'))
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Assembles and runs `source` on a computer with the standard devices attached (see
+/// `Computer::attach_standard_devices`), so that a program from the book runs as it is,
+/// with what it printed given as a single page.
+///
+/// ## Arguments
+/// - `policy`: What the program meets on the units left unattached.
+///
+/// ## Errors
+/// - The `FaultReport` of the source failing to assemble, or of the run faulting, boxed as
+///   it holds the run's checkpoints.
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+pub fn run_source(source: &str, policy: UnattachedPolicy) -> Result<RunReport, Box<FaultReport>> {
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).map_err(|error| Box::new(FaultReport::from_assemble_error(&error)))?;
+    let mut computer = Computer::default();
+    computer.unattached_policy = policy;
+    let printer = computer.attach_standard_devices();
+    program.load_into(&mut computer);
+    computer.run().map_err(|error| Box::new(FaultReport::from_run_error_on(&error, &computer)))?;
+    let mut report = RunReport::from_computer(&computer);
+    let lines = printed_lines(&printer.borrow(), assembler.options.charset);
+    if !lines.is_empty() {
+        report.printer.push(lines);
+    }
+    Ok(report)
+}
+
+#[cfg(feature = "json")]
+impl RunReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("[Error to_json] RunReport always serializes")
+    }
+
+    /// ## Errors
+    /// - The JSON error if `json` isn't a report of this schema.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(feature = "json")]
+impl FaultReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("[Error to_json] FaultReport always serializes")
+    }
+
+    /// ## Errors
+    /// - The JSON error if `json` isn't a report of this schema.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
@@ -0,0 +1,96 @@
+//! The one gate every word read from outside the process passes through on its way into a
+//! computer. A machine image or tape file that was damaged, or written to do harm, can
+//! hold words with a byte of the byte size or more, which no instruction could make and
+//! arithmetic would misread. Every byte fits the 256 values this machine computes with,
+//! but not the 64 a portable program may count on (see `portability`), so the loaders
+//! reading words as bytes take the byte size to hold them to and hand them to
+//! `ingest_words` or `ingest_in_place`, which under `IngestPolicy`:
+//!
+//! - `Reject`, the default, fails on the first such word, naming its index, the byte, and
+//!   its position, so that the loader can report where in the file it is.
+//! - `ClampBytes` lowers each byte too large to the largest that fits.
+//! - `ZeroInvalid` replaces each word with a byte too large by +0.
+//!
+//! The loaders going through it are `MachineImage::from_bytes_with`, for images and the
+//! snapshots saved as images, and the reads of `MagneticTapeUnit`, from memory or a file,
+//! under its `ingest_policy`, and of `Channel`, which always rejects. Raw decks, MIXAL, and MIX notation give words as numbers, which
+//! their parsers range-check part by part, so they never hold a byte too large.
+//!
+//! A word that passes is canonical as it is: its sign is a flag rather than a byte, so
+//! the ways a format may write a sign come to the same word. -0 stays apart from +0, as
+//! the machine keeps them apart.
+
+use std::fmt;
+use crate::word::Word;
+
+/// What to do with a word holding a byte too large.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum IngestPolicy {
+    /// Fail on it.
+    #[default]
+    Reject,
+    /// Lower each byte too large to the byte size less 1.
+    ClampBytes,
+    /// Replace the word by +0.
+    ZeroInvalid,
+}
+
+/// The first word with a byte too large, under `Reject`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IngestError {
+    /// The place of the word among those ingested, its address when they are memory.
+    pub index: usize,
+    pub word: Word,
+    /// The byte too large, from 1 to 5.
+    pub position: usize,
+    pub byte: u8,
+    pub byte_size: u16,
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Word {} has byte {} at position {}, but bytes must be below {}", self.index, self.byte, self.position, self.byte_size)
+    }
+}
+
+/// Checks that every byte of `words` is below `byte_size`.
+///
+/// ## Errors
+/// - `IngestError` for the first word with a byte too large.
+pub fn check_words(words: &[Word], byte_size: u16) -> Result<(), IngestError> {
+    for (index, word) in words.iter().enumerate() {
+        if let Some(position) = word.bytes.iter().position(|byte| *byte as u16 >= byte_size) {
+            return Err(IngestError { index, word: *word, position: position + 1, byte: word.bytes[position], byte_size });
+        }
+    }
+    Ok(())
+}
+
+/// Makes every word of `words` fit `byte_size` as `policy` says.
+///
+/// ## Errors
+/// - `IngestError` under `Reject` for the first word with a byte too large, in which case
+///   `words` is left as it was.
+pub fn ingest_in_place(words: &mut [Word], policy: IngestPolicy, byte_size: u16) -> Result<(), IngestError> {
+    let largest = byte_size.saturating_sub(1).min(u8::MAX as u16) as u8;
+    match policy {
+        IngestPolicy::Reject => check_words(words, byte_size)?,
+        IngestPolicy::ClampBytes => for word in words.iter_mut() {
+            for byte in word.bytes.iter_mut() {
+                *byte = (*byte).min(largest);
+            }
+        },
+        IngestPolicy::ZeroInvalid => for word in words.iter_mut() {
+            if word.bytes.iter().any(|byte| *byte as u16 >= byte_size) {
+                *word = Word::default();
+            }
+        },
+    }
+    Ok(())
+}
+
+/// `words` made to fit `byte_size` as `policy` says, as `ingest_in_place`.
+pub fn ingest_words(mut words: Vec<Word>, policy: IngestPolicy, byte_size: u16) -> Result<Vec<Word>, IngestError> {
+    ingest_in_place(&mut words, policy, byte_size)?;
+    Ok(words)
+}
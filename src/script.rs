@@ -0,0 +1,372 @@
+//! Scripts of front panel commands, run against a fresh computer by `mixal script`, so
+//! that a scenario can be written as plain text and checked from a shell. Each line is
+//! one of:
+//!
+//! - A key of the front panel, with its argument, as typed at the panel (see `tui`). `q`
+//!   ends the script early.
+//! - `find VALUE`, `findb PATTERN` or `refs ADDRESS`: searches memory as the panel does,
+//!   writing the first page of the addresses found to the output, as `n` writes the next.
+//! - `load PATH`: assembles the MIXAL source at `PATH`, or reads it as a raw deck unless
+//!   it ends in `.mixal`, and loads it. `PATH` is relative to the script.
+//! - `echo TEXT`: writes `TEXT` to the output.
+//! - `print ADDRESS`: writes the value of `ADDRESS` to the output, with the word there
+//!   when it is in memory.
+//! - `explain`: turns explanations on or off, as `x` does on the panel. While they are on,
+//!   each `s` writes what the instruction did to the output.
+//! - `checkpoint CYCLES COUNT`: from now on, steps and runs take a checkpoint every
+//!   `CYCLES` cycles, keeping the newest `COUNT` (see `checkpoint`).
+//! - `rollback N`: goes back to the `N`th newest checkpoint, 1 being the newest, and
+//!   traces every step from there.
+//! - `trace N`: writes the last `N` steps traced since the rollback to the output, one
+//!   per line, as their address and instruction.
+//! - `heatmap CYCLES WORDS`: from now on, counts the accesses to memory in buckets of
+//!   `CYCLES` cycles and lines of `WORDS` words (see `heatmap`). `heatmap` alone writes
+//!   the accesses counted so far to the output, drawn as `AccessHeatmap::to_ascii` draws
+//!   them in `HEATMAP_WIDTH` by `HEATMAP_HEIGHT` characters.
+//! - `assert REGISTER = WORD`, for `rA`, `rX`, `rI1` to `rI6`, and `rJ`.
+//! - `assert mem ADDRESS = WORD`.
+//! - `assert pc = ADDRESS`.
+//! - `assert halted` or `assert running`.
+//! - `assert output contains "TEXT"`, for the lines printed on the printer, unit 18.
+//! - `set REGISTER = WORD`, `set mem ADDRESS = WORD` and `set pc = ADDRESS`, which change
+//!   the machine as the assertions of the same form check it, and
+//!   `set overflow = on|off` and `set comparison = less|equal|greater`.
+//!
+//! Words are written as `parse::parse_word_or_value` reads them, in MIX notation, such as
+//! `+ 0 0 0 9 1`, or as a signed value, and addresses as the panel reads them, with the
+//! symbols of the program loaded (see `expression`). Blank lines are skipped and `#`
+//! starts a comment. The script stops at the first line that fails, with its line number,
+//! and for a failed assertion with what changed since the program was loaded.
+
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use crate::assembler;
+use crate::assembler::cache::AssemblyOptions;
+use crate::checkpoint::{CheckpointRing, RunOptions};
+use crate::computer::{ComparisonFlag, Computer};
+use crate::expression;
+use crate::heatmap::HeatmapOptions;
+use crate::loader;
+use crate::peripherals::{Channel, ChannelEnd, PRINTER_UNIT};
+use crate::phases::{self, Snapshot};
+use crate::run_report;
+use crate::opcodes;
+use crate::parse;
+use crate::trace::{Trace, TraceConfig, TraceOptions};
+use crate::tui::{self, Command, Panel};
+use crate::word::Word;
+
+/// The most blocks the printer holds before the program waits on it.
+pub const PRINTER_DEPTH: usize = 10_000;
+
+/// The size `heatmap` draws the accesses in.
+pub const HEATMAP_WIDTH: usize = 64;
+pub const HEATMAP_HEIGHT: usize = 16;
+
+/// The line of a script that failed, and why.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScriptFailure {
+    /// The line, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// A fresh computer with a printer, and the panel the keys of the script drive.
+struct Session {
+    computer: Computer,
+    panel: Panel,
+    printer: Rc<RefCell<Channel>>,
+    /// The state as the last program was loaded, or as the script started.
+    loaded: Snapshot,
+}
+
+/// Runs the script `text` against a fresh computer, writing what it echoes to `out`.
+///
+/// ## Arguments
+/// - `base`: The directory `load` reads paths relative to.
+///
+/// ## Errors
+/// - The first line that can't be carried out, or whose assertion doesn't hold.
+pub fn run_script(text: &str, base: &Path, out: &mut dyn Write) -> Result<(), ScriptFailure> {
+    let mut computer = Computer::default();
+    let printer = Channel::new(PRINTER_DEPTH);
+    computer.attach(PRINTER_UNIT, ChannelEnd::producer(&printer));
+    let loaded = Snapshot::of(&computer);
+    let mut session = Session { computer, panel: Panel::default(), printer, loaded };
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let failure = |message: String| ScriptFailure { line: i + 1, message };
+        let (keyword, rest) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+        let rest = rest.trim();
+        match keyword {
+            "echo" => writeln!(out, "{}", rest).map_err(|error| failure(error.to_string()))?,
+            "print" => {
+                let printed = session.print(rest).map_err(failure)?;
+                writeln!(out, "{}", printed).map_err(|error| failure(error.to_string()))?;
+            },
+            "load" => session.load(&base.join(rest)).map_err(failure)?,
+            "assert" => session.check(rest).map_err(failure)?,
+            "set" => session.set(rest).map_err(failure)?,
+            "explain" => {
+                tui::update(&mut session.computer, &mut session.panel, Command::ToggleExplain);
+            },
+            "checkpoint" => session.checkpoint(rest).map_err(failure)?,
+            "rollback" => session.rollback(rest).map_err(failure)?,
+            "trace" => {
+                for line in session.traced(rest).map_err(failure)? {
+                    writeln!(out, "{}", line).map_err(|error| failure(error.to_string()))?;
+                }
+            },
+            "heatmap" if rest.is_empty() => {
+                let heatmap = session.computer.access_heatmap().ok_or_else(|| failure("No heatmap is being recorded".to_string()))?;
+                write!(out, "{}", heatmap.to_ascii(HEATMAP_WIDTH, HEATMAP_HEIGHT)).map_err(|error| failure(error.to_string()))?;
+            },
+            "heatmap" => session.record_heatmap(rest).map_err(failure)?,
+            _ => {
+                let command = tui::command_for_word(keyword, rest, &session.computer).map_err(failure)?;
+                if command == Command::Quit {
+                    break;
+                }
+                let shown = match command {
+                    Command::Step => session.panel.explain,
+                    Command::Search(_) | Command::NextPage => true,
+                    _ => false,
+                };
+                tui::update(&mut session.computer, &mut session.panel, command);
+                if shown {
+                    writeln!(out, "{}", session.panel.message).map_err(|error| failure(error.to_string()))?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Runs the script at `path` for `mixal script`, writing what it echoes to `out` and why
+/// it failed to `err`.
+///
+/// ## Returns
+/// - The exit code: 0 when the script ran to its end, or 1.
+pub fn run_file(path: &Path, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let result = std::fs::read_to_string(path)
+        .map_err(|error| format!("{}: {}", path.display(), error))
+        .and_then(|text| {
+            let base = path.parent().unwrap_or_else(|| Path::new("."));
+            run_script(&text, base, out).map_err(|failure| format!("{}:{}: {}", path.display(), failure.line, failure.message))
+        });
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            let _ = writeln!(err, "{}", message);
+            1
+        },
+    }
+}
+
+impl Session {
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let program = if path.extension().is_some_and(|extension| extension == "mixal") {
+            assembler::assemble(&text).map_err(|error| format!("{}: {}", path.display(), error))?
+        } else {
+            loader::parse_raw(&text).map_err(|error| format!("{}: {}", path.display(), error))?
+        };
+        program.load_into(&mut self.computer);
+        self.panel.cursor = self.computer.pc;
+        self.loaded = Snapshot::of(&self.computer);
+        Ok(())
+    }
+
+    /// Checks the assertion `text`, the line after `assert`.
+    fn check(&self, text: &str) -> Result<(), String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let failed = |subject: String, actual: String, expected: String| {
+            format!("assert {} failed: {} is {}, not {}\n{}", text, subject, actual, expected, phases::render_delta(&self.loaded.delta(&self.computer)))
+        };
+        match words.as_slice() {
+            ["halted"] | ["running"] => {
+                let actual = if self.computer.is_halted() { "halted" } else { "running" };
+                if actual != words[0] {
+                    return Err(failed("the computer".to_string(), actual.to_string(), words[0].to_string()));
+                }
+            },
+            ["pc", "=", ..] => {
+                let expected = parse_address(&words[2..].join(" "), &self.computer)?;
+                if self.computer.pc != expected {
+                    return Err(failed("pc".to_string(), self.computer.pc.to_string(), expected.to_string()));
+                }
+            },
+            ["mem", address, "=", ..] => {
+                let address = parse_address(address, &self.computer)?;
+                let expected = parse_word(&words[3..].join(" "), &self.computer)?;
+                let actual = self.computer.memory[address];
+                if actual != expected {
+                    return Err(failed(address.to_string(), format!("[{}]", actual), format!("[{}]", expected)));
+                }
+            },
+            ["output", "contains", ..] => {
+                let rest = text["output".len()..].trim_start()["contains".len()..].trim();
+                let expected = rest.strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+                    .ok_or(format!("{} is not text in double quotes", rest))?;
+                let printed = self.printed();
+                if !printed.iter().any(|line| line.contains(expected)) {
+                    return Err(failed("the output".to_string(), format!("{:?}", printed), format!("text containing \"{}\"", expected)));
+                }
+            },
+            [register, "=", ..] => {
+                let actual = self.register(register).ok_or(format!("{} is not a register, one of rA, rX, rI1 to rI6, or rJ", register))?;
+                let expected = parse_word(&words[2..].join(" "), &self.computer)?;
+                if actual != expected {
+                    return Err(failed(register.to_string(), format!("[{}]", actual), format!("[{}]", expected)));
+                }
+            },
+            _ => return Err(format!("'{}' is not an assertion", text)),
+        }
+        Ok(())
+    }
+
+    /// Makes the change `text`, the line after `set`.
+    fn set(&mut self, text: &str) -> Result<(), String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let computer = &mut self.computer;
+        match words.as_slice() {
+            ["pc", "=", ..] => computer.pc = parse_address(&words[2..].join(" "), computer)?,
+            ["overflow", "=", "on"] => computer.overflow_flag = true,
+            ["overflow", "=", "off"] => computer.overflow_flag = false,
+            ["comparison", "=", "less"] => computer.comparison_flag = ComparisonFlag::less,
+            ["comparison", "=", "equal"] => computer.comparison_flag = ComparisonFlag::equal,
+            ["comparison", "=", "greater"] => computer.comparison_flag = ComparisonFlag::greater,
+            ["mem", address, "=", ..] => {
+                let address = parse_address(address, computer)?;
+                computer.write_mem(address, parse_word(&words[3..].join(" "), computer)?);
+            },
+            [register, "=", ..] => {
+                let word = parse_word(&words[2..].join(" "), computer)?;
+                *self.register_mut(register).ok_or(format!("{} is not a register, one of rA, rX, rI1 to rI6, or rJ", register))? = word;
+            },
+            _ => return Err(format!("'{}' is not a change", text)),
+        }
+        Ok(())
+    }
+
+    /// The value of the address `text`, with the word there when it is in memory.
+    fn print(&self, text: &str) -> Result<String, String> {
+        let symbols = self.computer.source_map.as_ref().map(|map| map.symbols.clone()).unwrap_or_default();
+        let value = expression::evaluate(text, &symbols).map_err(|error| error.to_string())?;
+        let word = usize::try_from(value).ok().and_then(|address| self.computer.memory.get(address));
+        Ok(match word {
+            Some(word) => format!("{} = {} [{}]", text, value, word),
+            None => format!("{} = {}", text, value),
+        })
+    }
+
+    fn checkpoint(&mut self, text: &str) -> Result<(), String> {
+        let numbers: Vec<&str> = text.split_whitespace().collect();
+        let (every, count) = match numbers.as_slice() {
+            [every, count] => (every.parse::<u64>().ok().filter(|every| *every > 0), count.parse::<usize>().ok().filter(|count| *count > 0)),
+            _ => (None, None),
+        };
+        match (every, count) {
+            (Some(every), Some(count)) => {
+                let options = RunOptions { checkpoint_every_cycles: every, checkpoint_ring_size: count, ..RunOptions::default() };
+                self.panel.checkpoints = CheckpointRing::new(options);
+                Ok(())
+            },
+            _ => Err(format!("'{}' is not a number of cycles and of checkpoints, both above 0", text)),
+        }
+    }
+
+    fn record_heatmap(&mut self, text: &str) -> Result<(), String> {
+        let numbers: Vec<&str> = text.split_whitespace().collect();
+        let (bucket_cycles, line_words) = match numbers.as_slice() {
+            [cycles, words] => (cycles.parse::<u64>().ok().filter(|cycles| *cycles > 0), words.parse::<usize>().ok().filter(|words| *words > 0)),
+            _ => (None, None),
+        };
+        match (bucket_cycles, line_words) {
+            (Some(bucket_cycles), Some(line_words)) => {
+                self.computer.record_access_heatmap(HeatmapOptions { bucket_cycles, line_words });
+                Ok(())
+            },
+            _ => Err(format!("'{}' is not a number of cycles and of words, both above 0", text)),
+        }
+    }
+
+    fn rollback(&mut self, text: &str) -> Result<(), String> {
+        let back = text.parse().map_err(|_| format!("'{}' is not a number of checkpoints", text))?;
+        let time = self.panel.checkpoints.rollback(back, &mut self.computer).map_err(|error| error.to_string())?;
+        self.computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+        self.panel.message = format!("Rolled back to cycle {}", time.time());
+        Ok(())
+    }
+
+    /// The last `text` steps traced, as their address and instruction.
+    fn traced(&self, text: &str) -> Result<Vec<String>, String> {
+        let count: usize = text.parse().map_err(|_| format!("'{}' is not a number of steps", text))?;
+        let trace = self.computer.trace.as_ref().ok_or("Nothing is traced before a rollback")?;
+        let steps: Vec<_> = trace.steps().collect();
+        Ok(steps[steps.len().saturating_sub(count)..].iter()
+            .map(|step| format!("{:04} {}", step.pc, opcodes::disassemble(&step.word).unwrap_or_else(|| step.word.to_string())))
+            .collect())
+    }
+
+    fn register_mut(&mut self, name: &str) -> Option<&mut Word> {
+        let computer = &mut self.computer;
+        match name.to_ascii_lowercase().as_str() {
+            "ra" => Some(&mut computer.ra),
+            "rx" => Some(&mut computer.rx),
+            "ri1" => Some(&mut computer.ri1),
+            "ri2" => Some(&mut computer.ri2),
+            "ri3" => Some(&mut computer.ri3),
+            "ri4" => Some(&mut computer.ri4),
+            "ri5" => Some(&mut computer.ri5),
+            "ri6" => Some(&mut computer.ri6),
+            "rj" => Some(&mut computer.rj),
+            _ => None,
+        }
+    }
+
+    fn register(&self, name: &str) -> Option<Word> {
+        let computer = &self.computer;
+        match name.to_ascii_lowercase().as_str() {
+            "ra" => Some(computer.ra),
+            "rx" => Some(computer.rx),
+            "ri1" => Some(computer.ri1),
+            "ri2" => Some(computer.ri2),
+            "ri3" => Some(computer.ri3),
+            "ri4" => Some(computer.ri4),
+            "ri5" => Some(computer.ri5),
+            "ri6" => Some(computer.ri6),
+            "rj" => Some(computer.rj),
+            _ => None,
+        }
+    }
+
+    /// Each block printed so far as a line of text, without trailing spaces, in the
+    /// mapping `load` assembles with.
+    fn printed(&self) -> Vec<String> {
+        run_report::printed_lines(&self.printer.borrow(), AssemblyOptions::default().charset)
+    }
+}
+
+/// Reads an address, with the symbols of the program loaded into `computer`.
+fn parse_address(text: &str, computer: &Computer) -> Result<usize, String> {
+    expression::address(text, computer.source_map.as_ref(), computer.memory_size())
+}
+
+/// Reads a word in MIX notation or as a signed value, in the byte size of `computer`.
+fn parse_word(text: &str, computer: &Computer) -> Result<Word, String> {
+    parse::parse_word_or_value(text, computer.byte_size()).map_err(|diag| diag.to_string())
+}
@@ -0,0 +1,365 @@
+//! Following discrete-event simulations, programs like the elevator of section 2.2.5
+//! that keep a simulated clock in a word of memory and a list of the events waiting on
+//! it. Such a run takes many steps for each event, so a `Simulation` reads the run
+//! through the clock instead:
+//!
+//! - `trace` records only the steps changing the clock, and `events` reads them back as
+//!   `ClockEvent`s, each with the time it set.
+//! - `run_until` steps until a `BreakCondition`, such as `mem[CLOCK] >= 250`, holds.
+//! - `event_list` walks the list of waiting events from its head, reading each node as a
+//!   `NodeLayout` says, and `render_event_list` writes it a line to a node.
+//!
+//! None of this is new to the computer: the events are a trace filtered on the clock's
+//! word changing, and the condition is checked between steps, as the front panel checks
+//! its breakpoints.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use crate::computer::Computer;
+use crate::error::MixError;
+use crate::field::FieldSpec;
+use crate::parse;
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::trace::{Trace, TraceConfig, TraceOptions};
+use crate::word::Word;
+
+/// A field of a node, `field` of the word `offset` words past its address.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NodeField {
+    pub offset: usize,
+    pub field: FieldSpec,
+}
+
+impl NodeField {
+    pub fn new(offset: usize, field: FieldSpec) -> NodeField {
+        NodeField { offset, field }
+    }
+
+    fn value(&self, computer: &Computer, node: usize) -> Option<i64> {
+        computer.memory().get(node + self.offset).map(|word| word.field_value_in_base(self.field, computer.byte_size()))
+    }
+}
+
+/// How the nodes of an event list are laid out.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeLayout {
+    /// The link to the next node, which the head has as well.
+    pub next: NodeField,
+    /// The fields shown for each node, by name.
+    pub fields: Vec<(String, NodeField)>,
+}
+
+/// A list of events kept in memory, starting from the node at `head`. The list ends at a
+/// link back to the head, as the book's lists with a head node do, or at a link of 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventList {
+    pub head: usize,
+    pub layout: NodeLayout,
+}
+
+/// A node of an event list as it is now.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventNode {
+    pub address: usize,
+    /// The fields of the layout, in its order.
+    pub values: Vec<(String, i64)>,
+}
+
+impl fmt::Display for EventNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.address)?;
+        for (name, value) in self.values.iter() {
+            write!(f, " {}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised while walking an event list.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SimulationError {
+    /// No event list was given.
+    NoEventList,
+    /// The node at `from` links to `link`, past the end of memory.
+    LinkOutOfRange { from: usize, link: i64 },
+    /// The node at `from` links back to `to`, a node already visited other than the head.
+    Cycle { from: usize, to: usize },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::NoEventList => write!(f, "The simulation has no event list"),
+            SimulationError::LinkOutOfRange { from, link } => write!(f, "The node at {} links to {}, outside of memory", from, link),
+            SimulationError::Cycle { from, to } => write!(f, "The node at {} links back to {} without reaching the head", from, to),
+        }
+    }
+}
+
+/// How a `BreakCondition` compares a word with its value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// The comparisons by symbol, the two-character ones first so that they are matched
+    /// before their first character.
+    const SYMBOLS: [(&'static str, Comparison); 6] = [
+        ("<=", Comparison::LessOrEqual), (">=", Comparison::GreaterOrEqual), ("!=", Comparison::NotEqual),
+        ("==", Comparison::Equal), ("<", Comparison::Less), (">", Comparison::Greater),
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        Comparison::SYMBOLS.iter().find(|(_, comparison)| comparison == self).map(|(symbol, _)| *symbol).unwrap()
+    }
+
+    fn holds(&self, left: i64, right: i64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// A condition on a word of memory, `mem[ADDRESS] OP VALUE`, for stopping a run when it
+/// holds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BreakCondition {
+    pub address: usize,
+    pub comparison: Comparison,
+    pub value: i64,
+}
+
+/// Why text isn't a `BreakCondition`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConditionError {
+    /// The text isn't of the form `mem[ADDRESS] OP VALUE`.
+    Syntax(String),
+    /// The address is a name `resolve` doesn't know.
+    UnknownSymbol(String),
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConditionError::Syntax(text) => write!(f, "{:?} isn't of the form mem[ADDRESS] OP VALUE", text),
+            ConditionError::UnknownSymbol(name) => write!(f, "{} isn't a known symbol", name),
+        }
+    }
+}
+
+impl BreakCondition {
+    /// Reads `mem[ADDRESS] OP VALUE`, optionally after `break when`, where `OP` is one of
+    /// `<`, `<=`, `==`, `!=`, `>=`, and `>`, and `ADDRESS` is a number or a name, given to
+    /// `resolve`, such as a symbol of the program.
+    ///
+    /// ## Errors
+    /// - `Syntax` when the text isn't of that form.
+    /// - `UnknownSymbol` when `resolve` gives `None` for the address.
+    pub fn parse<F: Fn(&str) -> Option<usize>>(text: &str, resolve: F) -> Result<BreakCondition, ConditionError> {
+        BreakCondition::parse_in_base(text, resolve, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `parse`, for a computer whose bytes hold `byte_size` values, such as
+    /// `Computer::byte_size`.
+    ///
+    /// ## Errors
+    /// - Any error of `parse`.
+    pub fn parse_in_base<F: Fn(&str) -> Option<usize>>(text: &str, resolve: F, byte_size: u16) -> Result<BreakCondition, ConditionError> {
+        let syntax = || ConditionError::Syntax(text.to_string());
+        let rest = text.trim();
+        let rest = rest.strip_prefix("break when").unwrap_or(rest).trim_start();
+        let rest = rest.strip_prefix("mem[").ok_or_else(syntax)?;
+        let (address, rest) = rest.split_once(']').ok_or_else(syntax)?;
+        let address = address.trim();
+        let address = match parse::parse_address(address, usize::MAX) {
+            Ok(address) => address,
+            Err(_) if !address.is_empty() => resolve(address).ok_or_else(|| ConditionError::UnknownSymbol(address.to_string()))?,
+            Err(_) => return Err(syntax()),
+        };
+        let rest = rest.trim_start();
+        let (symbol, comparison) = Comparison::SYMBOLS.iter().find(|(symbol, _)| rest.starts_with(symbol)).ok_or_else(syntax)?;
+        let value = parse::parse_value(&rest[symbol.len()..], byte_size).map_err(|_| syntax())?
+            .field_value_in_base(FieldSpec::WHOLE, byte_size);
+        Ok(BreakCondition { address, comparison: *comparison, value })
+    }
+
+    /// Whether the condition holds of `computer` now, which it never does of an address
+    /// past the end of memory.
+    pub fn holds(&self, computer: &Computer) -> bool {
+        computer.memory().get(self.address)
+            .is_some_and(|word| self.comparison.holds(word.field_value_in_base(FieldSpec::WHOLE, computer.byte_size()), self.value))
+    }
+}
+
+impl fmt::Display for BreakCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mem[{}] {} {}", self.address, self.comparison.symbol(), self.value)
+    }
+}
+
+/// A step that changed the clock.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ClockEvent {
+    /// The number of the step, as the trace counts them.
+    pub step: u64,
+    /// The address of the instruction changing the clock.
+    pub pc: usize,
+    pub instruction: Word,
+    /// The simulated time the step set.
+    pub time: i64,
+}
+
+impl fmt::Display for ClockEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} at {}: clock {}", self.step, self.pc, self.time)
+    }
+}
+
+/// Why `run_until` stopped.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SimulationStop {
+    /// The condition held after `steps` steps, with the clock at `time` and `pc` at the
+    /// next instruction.
+    Reached { steps: u64, pc: usize, time: i64 },
+    /// The machine halted after `steps` steps without the condition holding.
+    Halted { steps: u64 },
+    /// The step limit was reached.
+    StepLimit,
+}
+
+/// A simulation whose clock is the word at `clock`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Simulation {
+    pub clock: usize,
+    pub event_list: Option<EventList>,
+}
+
+impl Simulation {
+    pub fn new(clock: usize) -> Simulation {
+        Simulation { clock, event_list: None }
+    }
+
+    /// The simulation with the list of waiting events at `head`, laid out as `layout`.
+    pub fn with_event_list(mut self, head: usize, layout: NodeLayout) -> Simulation {
+        self.event_list = Some(EventList { head, layout });
+        self
+    }
+
+    /// The simulated time of `computer`, or 0 when the clock is past the end of memory.
+    pub fn time(&self, computer: &Computer) -> i64 {
+        computer.memory().get(self.clock).map_or(0, |word| word.field_value_in_base(FieldSpec::WHOLE, computer.byte_size()))
+    }
+
+    /// The config recording the steps that change the clock, and no others.
+    pub fn trace_config(&self) -> TraceConfig {
+        TraceConfig { on_change_of_words: vec![self.clock], ..TraceConfig::default() }
+    }
+
+    /// A trace recording the steps that change the clock, as well as transfers, for
+    /// `Computer::trace`.
+    pub fn trace(&self, options: TraceOptions) -> Trace {
+        Trace::with_steps(options, self.trace_config())
+    }
+
+    /// The steps of `trace` that changed the clock, oldest first, when the trace was made
+    /// by `trace`.
+    pub fn events(&self, trace: &Trace) -> Vec<ClockEvent> {
+        let index = trace.steps.as_ref().and_then(|config| config.on_change_of_words.iter().position(|&address| address == self.clock));
+        let index = match index {
+            Some(index) => index,
+            None => return vec![],
+        };
+        trace.steps().map(|record| ClockEvent {
+            step: record.step,
+            pc: record.pc,
+            instruction: record.word,
+            time: record.words[index].field_value(FieldSpec::WHOLE),
+        }).collect()
+    }
+
+    /// The condition `mem[CLOCK] >= time`, stopping a run once the clock reaches `time`.
+    pub fn at_time(&self, time: i64) -> BreakCondition {
+        BreakCondition { address: self.clock, comparison: Comparison::GreaterOrEqual, value: time }
+    }
+
+    /// Steps `computer` until `condition` holds after a step, the machine halts, or it has
+    /// taken `step_limit` steps. The condition is checked after each step and not before
+    /// the first, so a run stopped by a condition that still holds goes on by a step.
+    ///
+    /// ## Errors
+    /// - The error of the step failing.
+    pub fn run_until(&self, computer: &mut Computer, condition: &BreakCondition, step_limit: u64) -> Result<SimulationStop, MixError> {
+        let mut steps = 0;
+        while steps < step_limit {
+            if computer.is_halted() {
+                return Ok(SimulationStop::Halted { steps });
+            }
+            computer.step()?;
+            steps += 1;
+            if condition.holds(computer) {
+                return Ok(SimulationStop::Reached { steps, pc: computer.pc(), time: self.time(computer) });
+            }
+        }
+        Ok(if computer.is_halted() { SimulationStop::Halted { steps } } else { SimulationStop::StepLimit })
+    }
+
+    /// The nodes of the event list of `computer`, in the order of their links, leaving out
+    /// the head.
+    ///
+    /// ## Errors
+    /// - `NoEventList` when no list was given.
+    /// - `LinkOutOfRange` when a link leads outside of memory.
+    /// - `Cycle` when the links lead back to a node other than the head.
+    pub fn event_list(&self, computer: &Computer) -> Result<Vec<EventNode>, SimulationError> {
+        let list = self.event_list.as_ref().ok_or(SimulationError::NoEventList)?;
+        let link = |from: usize| -> Result<usize, SimulationError> {
+            match list.layout.next.value(computer, from) {
+                Some(link) if link >= 0 && (link as usize) < computer.memory_size() => Ok(link as usize),
+                Some(link) => Err(SimulationError::LinkOutOfRange { from, link }),
+                None => Err(SimulationError::LinkOutOfRange { from, link: (from + list.layout.next.offset) as i64 }),
+            }
+        };
+        let mut nodes = vec![];
+        let mut visited = BTreeSet::new();
+        let mut from = list.head;
+        let mut node = link(from)?;
+        while node != list.head && node != 0 {
+            if !visited.insert(node) {
+                return Err(SimulationError::Cycle { from, to: node });
+            }
+            let values = list.layout.fields.iter()
+                .map(|(name, field)| field.value(computer, node)
+                    .map(|value| (name.clone(), value))
+                    .ok_or(SimulationError::LinkOutOfRange { from, link: node as i64 }))
+                .collect::<Result<Vec<_>, _>>()?;
+            nodes.push(EventNode { address: node, values });
+            from = node;
+            node = link(from)?;
+        }
+        Ok(nodes)
+    }
+
+    /// The event list of `computer` a line to a node, after a line with the clock, such as
+    /// `clock 10` followed by `3025: time=10 delay=5`.
+    ///
+    /// ## Errors
+    /// - As `event_list`.
+    pub fn render_event_list(&self, computer: &Computer) -> Result<String, SimulationError> {
+        let mut text = format!("clock {}\n", self.time(computer));
+        for node in self.event_list(computer)? {
+            text.push_str(&format!("{}\n", node));
+        }
+        Ok(text)
+    }
+}
@@ -0,0 +1,449 @@
+//! Running MIXAL from a Rust test, as a test of a compiler emitting MIXAL would, without
+//! putting the assembler, computer, devices, and checks together by hand. `run_mixal`
+//! takes the source and gives a `TestRun`, on which cards for the reader and switches to
+//! set are given first and assertions chained after. The first assertion assembles and
+//! runs the program, on a computer with the standard devices attached (see
+//! `Computer::attach_standard_devices`), and every assertion after it checks the same run.
+//!
+//! An assertion that fails panics with a message meant to be read in the output of
+//! `cargo test`: what was expected and what was found, the source lines around the
+//! instruction at fault, the nearest symbol to each address it names, and what the
+//! program changed since it was loaded. Printed output is cut to the lines around the
+//! first difference. `TestRun::check` gives the same message as an error rather than a
+//! panic.
+//!
+//! ```text
+//! #[test]
+//! fn sums_are_printed() {
+//!     let source = toy::compile("print 2 + 3 + 4");
+//!     testing::run_mixal(&source)
+//!         .assert_halts()
+//!         .assert_prints(&["9"])
+//!         .assert_cycles_at_most(100);
+//! }
+//! ```
+
+use std::fmt::Write;
+use crate::analysis::{self, Location, ProgramMap, SymbolKind};
+use crate::assembler::AssemblyState;
+use crate::charset::CharsetMapping;
+use crate::computer::{Computer, UnattachedPolicy};
+use crate::error::MixError;
+use crate::peripherals::{MagneticTapeUnit, BLOCK_WORDS};
+use crate::consts::UnitNumber;
+use crate::phases::{self, Snapshot};
+use crate::run_report;
+use crate::word::Word;
+use crate::field::FieldSpec;
+
+/// The most instructions a program may execute before it is taken to hang. One caught in
+/// a tight loop is taken to hang as soon as its state comes round again unchanged.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// The source lines quoted on each side of the line at fault.
+pub const LISTING_CONTEXT: usize = 2;
+
+/// The most printed lines a failure quotes, and the characters quoted of each.
+pub const PRINTED_LINES_SHOWN: usize = 8;
+pub const PRINTED_WIDTH_SHOWN: usize = 60;
+
+/// What an assertion expects of the run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expectation {
+    /// The program halts within the step limit.
+    Halts,
+    /// rA holds the value, read as a signed number.
+    Ra(i64),
+    /// The word at the address holds the value, read as a signed number.
+    Mem(usize, i64),
+    /// The printer, unit 18, printed exactly these lines, without trailing spaces.
+    Prints(Vec<String>),
+    /// The run took at most this many time units.
+    CyclesAtMost(u64),
+}
+
+/// How the run ended.
+enum Ending {
+    Halted,
+    Failed(MixError),
+    Hung,
+    /// The state came round again unchanged, so the run could only go on forever.
+    Looped,
+}
+
+/// Watches a run for a tight loop: the same state, as `Computer::state_hash` and
+/// `Computer::state_eq` see it, at a jump back to the same address while no unit is
+/// busy. A run in one can only go round it forever, so it is taken to hang without
+/// waiting for the step limit.
+#[derive(Default)]
+struct LoopDetector {
+    /// The address last jumped back to, and the hash of the state then.
+    last: Option<(usize, u64)>,
+    /// The state the last two jumps back agreed on, to confirm the next one against.
+    repeated: Option<Computer>,
+}
+
+impl LoopDetector {
+    /// Whether `computer`, just after the step of the instruction at `pc`, is in a state
+    /// it was in when it last jumped back.
+    fn looped(&mut self, pc: usize, computer: &Computer) -> bool {
+        if computer.pc > pc || computer.is_halted() || computer.any_busy() {
+            return false;
+        }
+        let hash = computer.state_hash();
+        if self.last != Some((computer.pc, hash)) {
+            self.last = Some((computer.pc, hash));
+            self.repeated = None;
+            return false;
+        }
+        match &self.repeated {
+            Some(repeated) if repeated.state_eq(computer) => true,
+            _ => {
+                self.repeated = Some(computer.clone());
+                false
+            },
+        }
+    }
+}
+
+/// The run, once an assertion has asked for it.
+struct Outcome {
+    computer: Computer,
+    loaded: Snapshot,
+    printed: Vec<String>,
+    steps: usize,
+    ending: Ending,
+}
+
+/// A program to run and check, built up from `run_mixal`.
+pub struct TestRun {
+    state: AssemblyState,
+    cards: Vec<String>,
+    switches: Vec<(Location, i64)>,
+    step_limit: usize,
+    outcome: Option<Result<Outcome, String>>,
+}
+
+/// `source`, to be assembled and run by its first assertion.
+pub fn run_mixal(source: &str) -> TestRun {
+    TestRun {
+        state: AssemblyState::new(source),
+        cards: Vec::new(),
+        switches: Vec::new(),
+        step_limit: STEP_LIMIT,
+        outcome: None,
+    }
+}
+
+impl TestRun {
+    /// Puts `cards` in the card reader, unit 16, one card to a line of text in the
+    /// character set of MDK, after any put there before. The reader holds them as a tape
+    /// holds its blocks, so a program reading past the last card fails.
+    ///
+    /// ## Panics
+    /// - After an assertion has run the program.
+    /// - When a card has a character outside the character set, or more than a block
+    ///   holds.
+    pub fn with_cards(mut self, cards: &[&str]) -> TestRun {
+        assert!(self.outcome.is_none(), "[Error TestRun::with_cards] Cards must be given before the first assertion.");
+        self.cards.extend(cards.iter().map(|card| card.to_string()));
+        self
+    }
+
+    /// Sets each location to its value before the run, as an operator would from the
+    /// console's switches, after the program is loaded.
+    ///
+    /// ## Panics
+    /// - After an assertion has run the program.
+    pub fn with_switches(mut self, switches: &[(Location, i64)]) -> TestRun {
+        assert!(self.outcome.is_none(), "[Error TestRun::with_switches] Switches must be set before the first assertion.");
+        self.switches.extend_from_slice(switches);
+        self
+    }
+
+    /// Takes the program to hang after `steps` instructions in place of `STEP_LIMIT`.
+    ///
+    /// ## Panics
+    /// - After an assertion has run the program.
+    pub fn with_step_limit(mut self, steps: usize) -> TestRun {
+        assert!(self.outcome.is_none(), "[Error TestRun::with_step_limit] The step limit must be set before the first assertion.");
+        self.step_limit = steps;
+        self
+    }
+
+    pub fn assert_halts(self) -> TestRun {
+        self.assert(Expectation::Halts)
+    }
+
+    pub fn assert_ra(self, value: i64) -> TestRun {
+        self.assert(Expectation::Ra(value))
+    }
+
+    pub fn assert_mem(self, address: usize, value: i64) -> TestRun {
+        self.assert(Expectation::Mem(address, value))
+    }
+
+    pub fn assert_prints(self, lines: &[&str]) -> TestRun {
+        self.assert(Expectation::Prints(lines.iter().map(|line| line.to_string()).collect()))
+    }
+
+    pub fn assert_cycles_at_most(self, cycles: u64) -> TestRun {
+        self.assert(Expectation::CyclesAtMost(cycles))
+    }
+
+    /// ## Panics
+    /// - With the failure message of `check` when `expectation` isn't met.
+    pub fn assert(mut self, expectation: Expectation) -> TestRun {
+        if let Err(message) = self.check(&expectation) {
+            panic!("{}", message);
+        }
+        self
+    }
+
+    /// Runs the program unless an assertion already has, and checks `expectation`
+    /// against the run.
+    ///
+    /// ## Errors
+    /// - The failure message, when the source doesn't assemble or `expectation` isn't met.
+    pub fn check(&mut self, expectation: &Expectation) -> Result<(), String> {
+        if self.outcome.is_none() {
+            self.outcome = Some(self.run());
+        }
+        let outcome = match self.outcome.as_ref() {
+            Some(Ok(outcome)) => outcome,
+            Some(Err(message)) => return Err(message.clone()),
+            None => unreachable!("[Error TestRun::check] The run was just made"),
+        };
+        let map = analysis::program_map(&self.state).expect("[Error TestRun::check] A program that ran assembled");
+        let failure = match expectation {
+            Expectation::Halts => match outcome.ending {
+                Ending::Halted => None,
+                _ => Some("Expected the program to halt".to_string()),
+            },
+            Expectation::Ra(value) => Some(outcome.computer.ra)
+                .filter(|word| word.field_value(FieldSpec::WHOLE) != *value)
+                .map(|word| format!("Expected rA to be {}, but it is {} [{}]", value, word.field_value(FieldSpec::WHOLE), word)),
+            Expectation::Mem(address, value) => match outcome.computer.memory.get(*address) {
+                None => Some(format!("Expected the word at {} to be {}, but memory ends at {}",
+                                     address, value, outcome.computer.memory_size())),
+                Some(word) if word.field_value(FieldSpec::WHOLE) != *value => Some(format!(
+                    "Expected the word at {} to be {}, but it is {} [{}]",
+                    self.describe(&map, *address), value, word.field_value(FieldSpec::WHOLE), word)),
+                Some(_) => None,
+            },
+            Expectation::Prints(lines) => {
+                if outcome.printed == *lines {
+                    None
+                } else {
+                    Some(printed_difference(lines, &outcome.printed))
+                }
+            },
+            Expectation::CyclesAtMost(cycles) => Some(outcome.computer.time())
+                .filter(|time| time > cycles)
+                .map(|time| format!("Expected the run to take at most {} cycles, but it took {} in {}", cycles, time, steps(outcome.steps))),
+        };
+        match failure {
+            None => Ok(()),
+            Some(message) => Err(self.report(&map, outcome, message, matches!(expectation, Expectation::Prints(_)))),
+        }
+    }
+
+    /// Assembles the program, sets up the computer, and runs it.
+    ///
+    /// ## Errors
+    /// - The failure message every assertion gives when the source doesn't assemble.
+    fn run(&self) -> Result<Outcome, String> {
+        let program = match (&self.state.program, self.state.diagnostics.first()) {
+            (Some(program), None) => program,
+            (_, Some(error)) => {
+                let mut message = format!("The source didn't assemble: {}", error);
+                for more in self.state.diagnostics.iter().skip(1) {
+                    write!(message, "\nAnd then: {}", more).unwrap();
+                }
+                return Err(format!("{}\n{}", message, self.excerpt(error.line)));
+            },
+            (None, None) => unreachable!("[Error TestRun::run] An assembly without problems has a program"),
+        };
+        let mut computer = Computer::default();
+        computer.unattached_policy = UnattachedPolicy::Fault;
+        let printer = computer.attach_standard_devices();
+        if !self.cards.is_empty() {
+            let mut deck = MagneticTapeUnit::new(UnitNumber::CardReader.number(), [Word::default(); BLOCK_WORDS]);
+            for card in self.cards.iter() {
+                deck.write_block(&card_block(card)).expect("[Error TestRun::run] A card is one block");
+            }
+            deck.rewind();
+            computer.attach_tape(deck);
+        }
+        program.load_into(&mut computer);
+        for (location, value) in self.switches.iter() {
+            let word = Word { positive: *value >= 0, ..Word::from_value(value.abs()) };
+            analysis::set_location(&mut computer, *location, word);
+        }
+
+        let loaded = Snapshot::of(&computer);
+        let mut detector = LoopDetector::default();
+        let mut steps = 0;
+        let ending = loop {
+            if computer.is_halted() {
+                break Ending::Halted;
+            }
+            if steps == self.step_limit {
+                break Ending::Hung;
+            }
+            let pc = computer.pc;
+            if let Err(error) = computer.step() {
+                break Ending::Failed(error);
+            }
+            steps += 1;
+            if let Some(unit) = computer.blocked_on() {
+                break Ending::Failed(MixError::UnitBlocked { pc, unit });
+            }
+            if detector.looped(pc, &computer) {
+                break Ending::Looped;
+            }
+        };
+        let printed = run_report::printed_lines(&printer.borrow(), self.state.charset());
+        Ok(Outcome { computer, loaded, printed, steps, ending })
+    }
+
+    /// `message`, then how the run ended unless it halted, then what changed, which
+    /// `printed` failures leave out as beside the point.
+    fn report(&self, map: &ProgramMap, outcome: &Outcome, message: String, printed: bool) -> String {
+        let mut report = message;
+        let pc = match &outcome.ending {
+            Ending::Halted => None,
+            Ending::Failed(error) => {
+                let pc = error.pc().unwrap_or(outcome.computer.pc);
+                write!(report, "\nThe program failed after {} at {}: {}", steps(outcome.steps), self.describe(map, pc), error).unwrap();
+                if let Some(address) = faulting_address(error).filter(|address| *address < outcome.computer.memory_size()) {
+                    write!(report, "\nIt refers to {}", self.describe(map, address)).unwrap();
+                }
+                Some(pc)
+            },
+            Ending::Hung => {
+                let pc = outcome.computer.pc;
+                write!(report, "\nThe program was still running after {}, at {}", steps(outcome.steps), self.describe(map, pc)).unwrap();
+                Some(pc)
+            },
+            Ending::Looped => {
+                let pc = outcome.computer.pc;
+                write!(report, "\nThe program was in a tight loop after {}, back at {} with nothing changed", steps(outcome.steps), self.describe(map, pc)).unwrap();
+                Some(pc)
+            },
+        };
+        if let Some(line) = pc.and_then(|pc| map.entries.iter().find(|entry| entry.address == pc)).map(|entry| entry.line) {
+            write!(report, "\n{}", self.excerpt(line)).unwrap();
+        }
+        if !printed {
+            write!(report, "\n{}", phases::render_delta(&outcome.loaded.delta(&outcome.computer))).unwrap();
+        }
+        report
+    }
+
+    /// `address`, followed by the nearest label of a word at or before it, such as
+    /// `1002 (TOTAL+2)`, or after it when there is none before.
+    fn describe(&self, map: &ProgramMap, address: usize) -> String {
+        let address_value = address as i64;
+        let mut labels = map.symbols.iter().filter(|symbol| symbol.kind != SymbolKind::Constant);
+        let nearest = labels.clone().rfind(|symbol| symbol.value <= address_value)
+            .or_else(|| labels.find(|symbol| symbol.value > address_value));
+        match nearest {
+            Some(symbol) if symbol.value == address_value => format!("{} ({})", address, symbol.name),
+            Some(symbol) => format!("{} ({}{:+})", address, symbol.name, address_value - symbol.value),
+            None => address.to_string(),
+        }
+    }
+
+    /// The source lines around `line`, counting from 1, numbered and with `line` marked.
+    fn excerpt(&self, line: usize) -> String {
+        let lines: Vec<&str> = self.state.source().lines().collect();
+        let first = line.saturating_sub(LISTING_CONTEXT).max(1);
+        let last = (line + LISTING_CONTEXT).min(lines.len());
+        (first..=last)
+            .map(|number| {
+                let marker = if number == line { '>' } else { ' ' };
+                format!("{} {:>4} | {}", marker, number, lines[number - 1]).trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// The address other than `pc` an error concerns, if it has one.
+fn faulting_address(error: &MixError) -> Option<usize> {
+    match error {
+        MixError::InvalidStatusWord { address, .. }
+        | MixError::CodeWrite { address, .. }
+        | MixError::BlockOutOfRange { address, .. } => Some(*address),
+        MixError::AddressOutOfRange { address, .. } if *address >= 0 => Some(*address as usize),
+        _ => None,
+    }
+}
+
+/// `card` as a block of the reader, padded with spaces.
+///
+/// ## Panics
+/// - When `card` has a character outside the character set of MDK or doesn't fit a block.
+pub(crate) fn card_block(card: &str) -> [Word; BLOCK_WORDS] {
+    let mut codes = CharsetMapping::mdk().encode(card)
+        .unwrap_or_else(|error| panic!("[Error TestRun::with_cards] The card {:?} can't be read: {}", card, error));
+    assert!(codes.len() <= BLOCK_WORDS * 5, "[Error TestRun::with_cards] The card {:?} is longer than {} characters.", card, BLOCK_WORDS * 5);
+    codes.resize(BLOCK_WORDS * 5, 0);
+    let mut block = [Word::default(); BLOCK_WORDS];
+    for (word, bytes) in block.iter_mut().zip(codes.chunks(5)) {
+        word.bytes.copy_from_slice(bytes);
+    }
+    block
+}
+
+/// How the lines `printed` differ from those `expected`, quoting the printed lines
+/// around the first difference.
+fn printed_difference(expected: &[String], printed: &[String]) -> String {
+    let first = expected.iter().zip(printed.iter()).position(|(expected, printed)| expected != printed)
+        .unwrap_or_else(|| expected.len().min(printed.len()));
+    let mut message = format!("Expected the printer to print {}, but it printed {}", count(expected.len()), count(printed.len()));
+    if printed.is_empty() {
+        return message;
+    }
+    write!(message, "; they first differ at line {}:", first + 1).unwrap();
+    write!(message, "\n  expected: {}", expected.get(first).map_or("(no line)".to_string(), |line| shown(line))).unwrap();
+    write!(message, "\n  printed:  {}", printed.get(first).map_or("(no line)".to_string(), |line| shown(line))).unwrap();
+    let start = first.saturating_sub(2).min(printed.len().saturating_sub(PRINTED_LINES_SHOWN));
+    let end = (start + PRINTED_LINES_SHOWN).min(printed.len());
+    message.push_str("\nPrinted:");
+    if start > 0 {
+        write!(message, "\n  ({} before)", count(start)).unwrap();
+    }
+    for (number, line) in printed.iter().enumerate().take(end).skip(start) {
+        let marker = if number == first { '>' } else { ' ' };
+        write!(message, "\n{} {:>4} | {}", marker, number + 1, shown(line)).unwrap();
+    }
+    if end < printed.len() {
+        write!(message, "\n  ({} after)", count(printed.len() - end)).unwrap();
+    }
+    message
+}
+
+fn count(lines: usize) -> String {
+    match lines {
+        0 => "nothing".to_string(),
+        1 => "1 line".to_string(),
+        _ => format!("{} lines", lines),
+    }
+}
+
+fn steps(steps: usize) -> String {
+    match steps {
+        1 => "1 step".to_string(),
+        _ => format!("{} steps", steps),
+    }
+}
+
+/// `line` cut to `PRINTED_WIDTH_SHOWN` characters, with `...` standing for the rest.
+fn shown(line: &str) -> String {
+    match line.char_indices().nth(PRINTED_WIDTH_SHOWN) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
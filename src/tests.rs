@@ -1,677 +1,7934 @@
-use crate::word::{Word};
-use crate::computer::*;
-use crate::instruction::*;
-use crate::instruction_functions::*;
-use rand::Rng;
-
-const ADDRESS: usize = 2000;
-
-fn sample_mem() -> Word { Word::new(false, [1,2,3,4,5]) }
-fn sample_reg() -> Word { Word::new(true, [0,0,0,9,1]) }
-
-#[test] 
-fn copy_word_fields_full() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let mut word2 = Word::default();
-    let left = 0;
-    let right = 5;
-    copy_word_fields(&word1, &mut word2, (left, right));
-    println!("[{}] [{}]", word1, word2);
-    assert_eq!(word1, word2);
-}
-
-#[test]
-fn copy_word_fields_partial() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let mut word2 = Word::default();
-    let left = 0;
-    let right = 2;
-    copy_word_fields(&word1, &mut word2, (left, right));
-
-    assert_eq!(word1.positive, word2.positive);
-    for i in 0..=1 {
-        assert_eq!(word1.bytes[i], word2.bytes[i]);
-    }
-    for i in 2..=4 {
-        assert_ne!(word1.bytes[i], word2.bytes[i]);
-    }
-}
-
-#[test]
-fn copy_word_fields_single() {
-    let word1 = Word::new(false, [1,1,1,1,1]);
-    let mut word2 = Word::default();
-    let left = 2;
-    let right = 2;
-    copy_word_fields(&word1, &mut word2, (left, right));
-
-    assert_ne!(word1.positive, word2.positive);
-    for i in 0..=0 {
-        assert_ne!(word1.bytes[i], word2.bytes[i]);
-    }
-    assert_eq!(word1.bytes[1], word2.bytes[1]);
-    for i in 2..=4 {
-        assert_ne!(word1.bytes[i], word2.bytes[i]);
-    }
-}
-
-fn excluse_bits_equivalent_full(word1: &Word, word2: &Word, (begin, end): (usize, usize)) {
-    if begin == 0 {
-        assert_eq!(word1.positive, word2.positive);
-    } else {
-        assert_ne!(word1.positive, word2.positive);
-    }
-    for i in 0..=4 {
-        if (begin..=end).contains(&(i + 1)) {
-            assert_eq!(word1.bytes[i], word2.bytes[i]);
-        } else {
-            assert_ne!(word1.bytes[i], word2.bytes[i]);
-        }
-    }
-}
-
-
-fn excluse_bits_equivalent_index(word1: &Word, word2: &Word, (begin, end): (usize, usize)) {
-    let (l, r) = (begin.max(1) - 1, end.max(1) - 1);
-    if begin == 0 {
-        assert_eq!(word1.positive, word2.positive);
-    } else {
-        assert_ne!(word1.positive, word2.positive);
-    }
-
-    for i in 0..=2 {
-        assert_ne!(word1.bytes[i], word2.bytes[i]);
-    }
-
-    if (l..=r).contains(&3) {
-        assert_eq!(word1.bytes[3], word2.bytes[3]);
-    } else {
-        assert_ne!(word1.bytes[3], word2.bytes[3]);
-    }
-
-    if (l..=r).contains(&4) {
-        assert_eq!(word1.bytes[4], word2.bytes[4]);
-    } else {
-        assert_ne!(word1.bytes[4], word2.bytes[4]);
-    }
-    
-}
-
-fn rand_fill_range(word: &mut Word, begin: usize, end: usize) {
-    let mut gen = rand::thread_rng();
-    for i in begin..=end {
-        word.bytes[i] = gen.gen_range(1,100);
-    }
-}
-
-fn test_load_a_with_range(begin: usize, end: usize) {
-    
-    let computer = &mut Computer::default();
-    let load = LoadA {
-        address: ADDRESS,
-        field_specification: (begin, end),
-        negative: false
-    };
-    rand_fill_range(&mut computer.memory[ADDRESS], 0, 4);
-    computer.memory[ADDRESS].positive = false;
-    load.execute_on(computer);
-    println!("[{}] [{}]", computer.memory[ADDRESS], computer.ra);
-    excluse_bits_equivalent_full(&computer.ra, &computer.memory[ADDRESS], load.field_specification);
-}
-
-fn test_load_i_with_range(begin: usize, end: usize) {
-    let load = LoadI {
-        index: 1,
-        address: ADDRESS,
-        field_specification: (begin, end),
-        negative: false,
-    };
-    let computer = &mut Computer::default();
-    rand_fill_range(&mut computer.memory[ADDRESS], 0, 4);
-    computer.memory[ADDRESS].positive = false;
-    load.execute_on(computer);
-    println!("[{}] [{}]", computer.memory[ADDRESS], computer.ri1);
-    excluse_bits_equivalent_index(&computer.ri1, &computer.memory[ADDRESS], load.field_specification);
-}
-
-#[test]
-fn load_a_full() {
-    test_load_a_with_range(0, 5);
-}
-
-#[test]
-fn load_a_1_4() {
-    test_load_a_with_range(1, 5);
-}
-
-#[test]
-fn load_a_3_5() {
-    test_load_a_with_range(3, 5);
-}
-
-#[test]
-fn load_a_0_3() {
-    test_load_a_with_range(0, 3);
-}
-
-#[test]
-fn load_a_2_2() {
-    test_load_a_with_range(2, 2);
-}
-
-#[test]
-fn load_i_full() {
-    test_load_i_with_range(0, 5);
-}
-
-#[test]
-fn load_i_3_5() {
-    test_load_i_with_range(3, 5);
-}
-
-#[test]
-fn load_i_0_3() {
-    test_load_i_with_range(0, 3);
-}
-
-#[test]
-#[should_panic]
-fn load_i_1_3() {
-    test_load_i_with_range(1, 3);
-}
-
-
-
-fn make_store_a_with_range(begin: usize, end: usize) -> StoreA {
-    StoreA::new(ADDRESS, (begin, end))
-}
-
-fn store_a_test_setup(begin: usize, end: usize) -> Computer { 
-    let mut computer = Computer::default();
-    computer.ra = sample_reg();
-    computer.memory[ADDRESS] =  sample_mem();
-    let store = make_store_a_with_range(begin, end);
-    store.execute_on(&mut computer);
-    computer
-}
-
-#[test]
-fn store_a_full() {
-    let computer = store_a_test_setup(0, 5);
-    let should_be = Word::new(true, [0,0,0,9,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_a_1_5() {
-    let computer = store_a_test_setup(1, 5);
-    let should_be = Word::new(false, [0,0,0,9,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_a_5_5() {
-    let computer = store_a_test_setup(5, 5);
-    let should_be = Word::new(false, [1,2,3,4,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_a_2_2() {
-    let computer = store_a_test_setup(2, 2);
-    let should_be = Word::new(false, [1,1,3,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_a_2_3() {
-    let computer = store_a_test_setup(2, 3);
-    let should_be = Word::new(false, [1,9,1,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_a_0_1() {
-    let computer = store_a_test_setup(0, 1);
-    let should_be = Word::new(true, [1,2,3,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-fn make_store_i_with_range(begin: usize, end: usize) -> StoreI {
-    StoreI::new(1, ADDRESS, (begin, end))
-}
-
-fn store_i_test_setup(begin: usize, end: usize) -> Computer { 
-    let mut computer = Computer::default();
-    computer.ri1 = sample_reg();
-    computer.memory[ADDRESS] =  sample_mem();
-    let store = make_store_i_with_range(begin, end);
-    store.execute_on(&mut computer);
-    computer
-}
-
-#[test]
-fn store_i_full() {
-    let computer = store_i_test_setup(0, 5);
-    let should_be = Word::new(true, [0,0,0,9,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_i_1_5() {
-    let computer = store_i_test_setup(1, 5);
-    let should_be = Word::new(false, [0,0,0,9,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_i_5_5() {
-    let computer = store_i_test_setup(5, 5);
-    let should_be = Word::new(false, [1,2,3,4,1]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_i_2_2() {
-    let computer = store_i_test_setup(2, 2);
-    let should_be = Word::new(false, [1,1,3,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_i_2_3() {
-    let computer = store_i_test_setup(2, 3);
-    let should_be = Word::new(false, [1,9,1,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-#[test]
-fn store_i_0_1() {
-    let computer = store_i_test_setup(0, 1);
-    let should_be = Word::new(true, [1,2,3,4,5]);
-    println!("{} {}", computer.memory[ADDRESS], should_be);
-    assert_eq!(computer.memory[ADDRESS], should_be);
-}
-
-fn make_add_with_range(begin: usize, end: usize) -> Add {
-    Add::new(ADDRESS, (begin, end))
-}
-fn add_test_setup(begin: usize, end: usize) -> Computer { 
-    let mut computer = Computer::default();
-    computer.ra = sample_reg();
-    computer.memory[ADDRESS] =  sample_mem();
-    let store = make_add_with_range(begin, end);
-    store.execute_on(&mut computer);
-    computer
-}
-
-#[test]
-fn add_full() {
-    let computer = add_test_setup(0, 5);
-    let should_be = Word::new(false, [1,2,2,251,4]);
-    println!("{} {} {} {}", 
-        computer.ra, 
-        should_be, 
-        computer.ra.field_value((0,5)), 
-        should_be.field_value((0,5))
-    );
-    assert_eq!(computer.ra, should_be);
-}
-
-#[test]
-fn add_1_5() {
-    let computer = add_test_setup(1, 5);
-    let should_be = Word::new(true, [1,2,3,13,6]);
-    println!("{} {}", computer.ra, should_be);
-    assert_eq!(computer.ra, should_be);
-}
-
-#[test]
-fn add_0_3() {
-    let computer = add_test_setup(0, 3);
-    let should_be = Word::new(false, [1,2,3,9,1]);
-    println!("{} {}", computer.ra, should_be);
-    assert_eq!(computer.ra, should_be);
-}
-
-#[test]
-fn add_4_5() {
-    let computer = add_test_setup(4, 5);
-    let should_be = Word::new(true, [0,0,0,13,6]);
-    println!("{} {}", computer.ra, should_be);
-    assert_eq!(computer.ra, should_be);
-}
-
-#[test]
-#[should_panic]
-fn add_0_0() {
-    let computer = add_test_setup(0, 0);
-    let should_be = Word::new(true, [0,0,0,13,6]);
-    println!("{} {}", computer.ra, should_be);
-    assert_eq!(computer.ra, should_be);
-}
-
-
-#[test]
-fn mult_full() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let word2 = Word::new(true, [1,1,1,1,1]);
-    let output = multiply_words(&word1, &word2, (0, 5));
-    let should_be = (Word::new(true, [0,1,2,3,4]), Word::new(true, [5,4,3,2,1]));
-    println!("{:#?} {:#?}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn mult_neg() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let word2 = Word::new(false, [1,1,1,1,1]);
-    let output = multiply_words(&word1, &word2, (0, 5));
-    let should_be = (Word::new(false, [0,1,2,3,4]), Word::new(false, [5,4,3,2,1]));
-    println!("{:#?} {:#?}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn mult_2_2() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let word2 = Word::new(true, [1,2,1,1,1]);
-    let output = multiply_words(&word1, &word2, (2, 2));
-    let should_be = (Word::new(true, [0,0,0,0,0]), Word::new(true, [2,2,2,2,2]));
-    println!("{:#?} {:#?}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn div_full() {
-    let word_a = Word::new(true, [0,0,0,0,0]);
-    let word_x = Word::new(false, [0,0,0,0,17]);
-    let word_div = Word::new(true, [0,0,0,0,3]);
-    let output = divide_words(&word_a, &word_x, &word_div, (0,5));
-    let should_be = (Word::new(true, [0,0,0,0,5]), Word::new(true, [0,0,0,0,2]), false);
-    println!("{:#?} {:#?}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn enta_1000() {
-    let mut computer = Computer::default();
-    let instruction = EntA::new(1000, true, false);
-    instruction.execute_on(&mut computer);
-    println!("{:#?} {:#?}", Word::new(true, [0,0,0,3,232]), computer.ra);
-    assert_eq!(Word::new(true, [0,0,0,3,232]), computer.ra);
-}
-
-
-#[test]
-fn enta_neg_0() {
-    let mut computer = Computer::default();
-    let instruction = EntA::new(0, false, false);
-    instruction.execute_on(&mut computer);
-    println!("{:#?} {:#?}", Word::new(false, [0,0,0,0,0]), computer.ra);
-    assert_eq!(Word::new(false, [0,0,0,0,0]), computer.ra);
-}
-
-#[test]
-fn inc_1_1() {
-    let mut computer = Computer::default();
-    let instruction = IncI::new(1, 1, true, false);
-    instruction.execute_on(&mut computer);
-    println!("{:#?} {:#?}", Word::new(true, [0,0,0,0,1]), computer.ri1);
-    assert_eq!(Word::new(true, [0,0,0,0,1]), computer.ri1);
-}
-
-#[test]
-fn dec_1_1() {
-    let mut computer = Computer::default();
-    let instruction = IncI::new(1, 1, true, true);
-    instruction.execute_on(&mut computer);
-    println!("{:#?} {:#?}", Word::new(false, [0,0,0,0,1]), computer.ri1);
-    assert_eq!(Word::new(false, [0,0,0,0,1]), computer.ri1);
-}
-
-#[test]
-fn cmpa_0_0() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let word2 = Word::new(false, [1,1,1,1,1]);
-    let output = compare_words(&word1, &word2, (0, 0));
-    let should_be = ComparisonFlag::equal;
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn cmpa_full() {
-    let word1 = Word::new(true, [1,1,1,1,1]);
-    let word2 = Word::new(false, [1,1,1,1,1]);
-    let output = compare_words(&word1, &word2, (0, 5));
-    let should_be = ComparisonFlag::greater;
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn cmpa_full_less() {
-    let word2 = Word::new(true, [1,1,1,1,1]);
-    let word1 = Word::new(false, [1,1,1,1,1]);
-    let output = compare_words(&word1, &word2, (0, 5));
-    let should_be = ComparisonFlag::less;
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn cmpa_1_5() {
-    let word1 = Word::new(false, [1,1,1,1,1]);
-    let word2 = Word::new(true, [1,1,1,1,1]);
-    let output = compare_words(&word1, &word2, (1, 5));
-    let should_be = ComparisonFlag::equal;
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-
-#[test]
-fn single_word_left_shift_1() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 1, false);
-    let should_be = Word::new(false, [2,3,4,5,0]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_left_shift_3() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 3, false);
-    let should_be = Word::new(false, [4,5,0,0,0]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_left_shift_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 5, false);
-    let should_be = Word::default();
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_left_shift_cycle_1() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 1, true);
-    let should_be = Word::new(false, [2,3,4,5,1]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_left_shift_cycle_3() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 3, true);
-    let should_be = Word::new(false, [4,5,1,2,3]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_left_shift_cycle_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let output = single_word_left_shift(&word1, 5, true);
-    let should_be = word1.clone();
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_1() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 1, false);
-    let should_be = Word::new(false, [0,1,2,3,4]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_3() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 3, false);
-    let should_be = Word::new(false, [0,0,0,1,2]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 5, false);
-    let should_be = Word::default();
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_cycle_1() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 1, true);
-    let should_be = Word::new(false, [5,1,2,3,4]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_cycle_3() {
-    let word1 = Word::new(false, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 3, true);
-    let should_be = Word::new(false, [3,4,5,1,2]);
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn single_word_right_shift_cycle_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let output = single_word_right_shift(&word1, 5, true);
-    let should_be = word1.clone();
-    println!("{:} {:}", output, should_be);
-    assert_eq!(output, should_be);
-}
-
-#[test]
-fn save_jump_test() {
-    let mut computer = Computer::default();
-    computer.pc = 20;
-    save_jump(&mut computer);
-    assert_eq!(Word::new(true, [0,0,0,0,21]), computer.rj);
-}
-
-#[test]
-fn double_word_left_shift_1() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_left_shift(&word1, &word2, 1);
-    let should_be1 = Word::new(true, [2,3,4,5,6]);
-    let should_be2 = Word::new(true, [7,8,9,0,0]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
-
-#[test]
-fn double_word_left_shift_3() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_left_shift(&word1, &word2, 3);
-    let should_be1 = Word::new(true, [4,5,6,7,8]);
-    let should_be2 = Word::new(true, [9,0,0,0,0]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
-
-#[test]
-fn double_word_left_shift_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_left_shift(&word1, &word2, 5);
-    let should_be1 = Word::new(true, [6,7,8,9,0]);
-    let should_be2 = Word::new(true, [0,0,0,0,0]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
-
-#[test]
-fn double_word_right_shift_1() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_right_shift(&word1, &word2, 1);
-    let should_be1 = Word::new(true, [0,1,2,3,4]);
-    let should_be2 = Word::new(true, [5,6,7,8,9]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
-
-#[test]
-fn double_word_right_shift_3() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_right_shift(&word1, &word2, 3);
-    let should_be1 = Word::new(true, [0,0,0,1,2]);
-    let should_be2 = Word::new(true, [3,4,5,6,7]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
-
-#[test]
-fn double_word_right_shift_5() {
-    let word1 = Word::new(true, [1,2,3,4,5]);
-    let word2 = Word::new(true, [6,7,8,9,0]);
-    let (output1, output2) = double_word_right_shift(&word1, &word2, 5);
-    let should_be1 = Word::new(true, [0,0,0,0,0]);
-    let should_be2 = Word::new(true, [1,2,3,4,5]);
-    println!("{:} {:}", output1, should_be1);
-    println!("{:} {:}", output2, should_be2);
-    assert_eq!(output1, should_be1);
-    assert_eq!(output2, should_be2);
-}
+use crate::word::{Word, WordOverflow};
+use crate::field::{FieldError, FieldSpec};
+use crate::computer::*;
+use crate::instruction::*;
+use crate::instruction_functions::*;
+use crate::peripherals::PRINTER_UNIT;
+#[cfg(feature = "peripherals")]
+use crate::peripherals::{Channel, ChannelEnd, MagneticTapeUnit, OutputLimits};
+use crate::error::{InstructionParamError, MixError};
+use crate::extensions::{float, stack, status_flags};
+use crate::extensions::float::MixFloat;
+use crate::extensions::status_flags::StoreFlags;
+#[cfg(feature = "mixb")]
+use crate::extensions::mixb;
+use crate::phases::{ControlFlow, Phase, StateDelta};
+#[cfg(any(feature = "assembler", feature = "debugger"))]
+use crate::phases::Snapshot;
+#[cfg(feature = "assembler")]
+use crate::phases::render_delta;
+#[cfg(feature = "debugger")]
+use crate::explain;
+use crate::trace::{Trace, TraceConfig, TraceOptions, REGISTER_A};
+#[cfg(feature = "peripherals")]
+use crate::trace::Direction;
+#[cfg(feature = "cli")]
+use crate::docgen::{Reference, MARKDOWN_TITLE};
+#[cfg(feature = "cli")]
+use crate::api::{self, LISTING_TITLE};
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+use crate::lockstep::{self, Component, Difference, LockstepEnd, LockstepOptions, Mask};
+#[cfg(feature = "debugger")]
+use crate::simulation::BreakCondition;
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+use crate::simulation::{Comparison, ConditionError, NodeField, NodeLayout, Simulation, SimulationError, SimulationStop};
+use crate::heatmap::{Access, AccessHeatmap, HeatmapOptions};
+#[cfg(feature = "assembler")]
+use crate::heatmap::CSV_HEADER;
+#[cfg(feature = "assembler")]
+use crate::mirror::{MirrorOptions, MirrorState};
+use crate::timing::{CostModel, CostModelError};
+#[cfg(feature = "peripherals")]
+use crate::timing::{IoTimingModel, Transfer};
+use crate::opcodes::{self, EncodeError};
+use crate::consts::{EnterOp, JumpCondition, Opcode, RegisterCondition, ShiftOp, SpecialOp, UnitNumber, UnknownValue};
+use std::convert::TryFrom;
+use crate::charset::{self, CharsetError, CharsetMapping};
+#[cfg(feature = "analysis")]
+use crate::codegen;
+use crate::parse::ParseDiag;
+#[cfg(feature = "debugger")]
+use crate::parse;
+#[cfg(feature = "assembler")]
+use crate::assembler::{assemble, assemble_incremental, assemble_with_layout, AssembleErrorKind, AssemblyState, Span, TextEdit};
+use crate::report::{self, WordFormat};
+#[cfg(feature = "analysis")]
+use crate::analysis::{self, EquivalenceError, EquivalenceResult, Location, RoutineOutcome, RoutineSpec, Strategy, SymbolKind};
+#[cfg(feature = "analysis")]
+use crate::harness::{Harness, HarnessError, Linkage};
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+use crate::testing::{self, Expectation};
+#[cfg(feature = "assembler")]
+use crate::assembler::cache::{assemble_cached, Assembler, CacheError};
+#[cfg(feature = "assembler")]
+use crate::assembler::link::{self, assemble_relocatable, LinkError};
+use crate::layout::{LayoutError, MemoryLayout};
+#[cfg(feature = "assembler")]
+use crate::layout::LayoutPolicy;
+use crate::async_runner::{AsyncRunner, CancellationToken, RunOutcome, RunnerOptions};
+#[cfg(feature = "tui")]
+use crate::tui;
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+use crate::script::{self, ScriptFailure};
+#[cfg(feature = "mdk")]
+use crate::conformance;
+#[cfg(all(feature = "cli", feature = "json"))]
+use crate::bench::{self, BenchEntry, BenchError, Environment, History, WorkloadResult};
+use crate::decode::{self, DecodeError, DecodedInstruction, DecodedItem};
+#[cfg(feature = "debugger")]
+use crate::run_report::FaultReport;
+#[cfg(all(feature = "debugger", any(feature = "assembler", feature = "json")))]
+use crate::run_report::RunReport;
+#[cfg(all(feature = "debugger", feature = "json"))]
+use crate::run_report::SCHEMA_VERSION;
+#[cfg(all(feature = "debugger", feature = "assembler", feature = "peripherals"))]
+use crate::run_report;
+#[cfg(feature = "debugger")]
+use crate::checkpoint::{CheckpointRing, RunOptions};
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+use crate::checkpoint::{ReplayError, RollbackError};
+#[cfg(feature = "debugger")]
+use crate::budget::InstrumentationBudget;
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+use crate::budget::Truncation;
+use crate::invariants::{self, InvariantViolation};
+use crate::loader::{self, EmitError, EmittedRegion, Overlap, OverlapPolicy, ProgramBuilder, RawErrorKind, RawParseError};
+#[cfg(feature = "assembler")]
+use crate::loader::AssembledProgram;
+use crate::loader::SourceMap;
+#[cfg(feature = "peripherals")]
+use crate::cluster::{Cluster, ChannelDepth, ClusterError, RoundRobinLimits};
+use crate::peripherals::{TapeError, BLOCK_WORDS};
+#[cfg(feature = "peripherals-file")]
+use crate::peripherals::magnetic_tape::ENCODED_BLOCK_BYTES;
+use crate::peripherals::Peripheral;
+#[cfg(feature = "assembler")]
+use std::time::Duration;
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+use crate::peripherals::{ClockDevice, ClockError, ClockMode, ClockRecording, ManualTimeSource, SystemTimeSource};
+use crate::image::{ImageError, MachineImage, FORMAT_COMPRESSED, FORMAT_PLAIN};
+#[cfg(feature = "assembler")]
+use crate::migration::{self, MigrationError, MigrationOutcome};
+use crate::sanitize::{self, IngestPolicy};
+#[cfg(feature = "peripherals-file")]
+use crate::sanitize::IngestError;
+use crate::dispatch::Dispatch;
+use crate::determinism::Prng;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+const ADDRESS: usize = 2000;
+
+fn sample_mem() -> Word { Word::new(false, [1,2,3,4,5]) }
+fn sample_reg() -> Word { Word::new(true, [0,0,0,9,1]) }
+
+#[test]
+fn values_round_trip_through_words() {
+    let largest = (crate::portability::CONFIGURED_BYTE_SIZE as i64).pow(5) - 1;
+    for value in [0, 1, -1, 1000, -1000, 12977700, -12977699, largest, -largest].iter() {
+        let word = Word::from_value(*value);
+        assert_eq!(word.field_value(FieldSpec::WHOLE), *value);
+        assert_eq!(word.is_positive(), *value >= 0, "{}", value);
+        assert_eq!(Word::try_from_value(*value), Ok(word));
+    }
+    assert_eq!(Word::from_value(-1000), Word::new(false, [0, 0, 0, 3, 232]));
+
+    // Past five bytes, the magnitude wraps, or isn't taken.
+    assert_eq!(Word::from_value(largest + 2), Word::from_value(1));
+    assert_eq!(Word::from_value(-largest - 2), Word::from_value(-1));
+    assert!(!Word::from_value(i64::MIN).is_positive());
+    for value in [largest + 1, -largest - 1, i64::MAX, i64::MIN].iter() {
+        assert_eq!(Word::try_from_value(*value), Err(WordOverflow { value: *value, byte_size: 256 }));
+    }
+    assert_eq!(WordOverflow { value: -largest - 1, byte_size: 256 }.to_string(), "-1099511627776 doesn't fit in five bytes of 256 values");
+}
+
+#[test]
+fn words_compute_in_either_byte_size() {
+    for byte_size in [64u16, 256].iter().copied() {
+        let largest = Word::largest_magnitude(byte_size).unwrap() as i64;
+        let top = Word::from_value_in_base(largest, byte_size);
+        assert_eq!(top.bytes(), [(byte_size - 1) as u8; 5], "{}", byte_size);
+        assert_eq!(top.field_value_in_base(FieldSpec::WHOLE, byte_size), largest);
+        assert_eq!(Word::from_value_in_base(-largest, byte_size).field_value_in_base(FieldSpec::WHOLE, byte_size), -largest);
+        assert_eq!(Word::try_from_value_in_base(largest + 1, byte_size), Err(WordOverflow { value: largest + 1, byte_size }));
+        assert_eq!(Word::from_value_in_base(largest + 1, byte_size), Word::default());
+
+        // The largest word and one more overflows to +0, and back again by subtracting.
+        let one = Word::from_value_in_base(1, byte_size);
+        assert_eq!(add_words_in_base(&top, &one, FieldSpec::WHOLE, byte_size), (Word::default(), true));
+        assert_eq!(subtract_words_in_base(&top, &top.negate(), FieldSpec::WHOLE, byte_size),
+                   (Word::from_value_in_base(largest - 1, byte_size), true));
+        assert_eq!(subtract_words_in_base(&top, &one, FieldSpec::WHOLE, byte_size), (Word::from_value_in_base(largest - 1, byte_size), false));
+
+        // The square of the largest word fills rA and rX, and a dividend of rA = 1 is one
+        // too many for a quotient of five bytes.
+        let (upper, lower) = multiply_words_in_base(&top, &top, FieldSpec::WHOLE, byte_size);
+        assert_eq!((upper.field_value_in_base(FieldSpec::WHOLE, byte_size), lower.field_value_in_base(FieldSpec::WHOLE, byte_size)), (largest - 1, 1));
+        assert!(divide_words_in_base(&one, &Word::default(), &one, FieldSpec::WHOLE, byte_size).2);
+        let (quotient, remainder, overflow) = divide_words_in_base(&Word::default(), &top, &Word::from_value_in_base(2, byte_size), FieldSpec::WHOLE, byte_size);
+        assert_eq!((quotient.field_value_in_base(FieldSpec::WHOLE, byte_size), remainder.field_value_in_base(FieldSpec::WHOLE, byte_size), overflow),
+                   (largest / 2, 1, false));
+    }
+    assert_eq!(Word::largest_magnitude(64), Some(1_073_741_823));
+    assert_eq!(Word::largest_magnitude(7131), Some(7131u64.pow(5) - 1));
+    for byte_size in [0, 7132, u16::MAX].iter().copied() {
+        assert_eq!(Word::largest_magnitude(byte_size), None);
+        assert_eq!(Word::try_from_value_in_base(1, byte_size), Err(WordOverflow { value: 1, byte_size }));
+    }
+    assert_eq!(Word::from_instruction_in_base(1000, 2, 5, 8, 64).address_in_base(64), 1000);
+}
+
+// A computer with bytes of 64 values, with `program` encoded for them from address 0.
+fn computer_in_base_64(program: &[(&str, i64, u8, Option<u8>)]) -> Computer {
+    let mut computer = Computer::default();
+    computer.set_byte_size(64).unwrap();
+    for (address, (mnemonic, operand, index, field)) in program.iter().enumerate() {
+        computer.memory[address] = opcodes::encode_in_base(mnemonic, *operand, *index, *field, 64).unwrap();
+    }
+    computer
+}
+
+// FLOT then FIX on rA, with bytes of 64 values.
+fn flot_fix_computer_in_base_64() -> Computer {
+    let mut computer = computer_in_base_64(&[("NOP", 0, 0, None), ("NOP", 0, 0, None), ("HLT", 0, 0, None)]);
+    float::register(&mut computer).unwrap();
+    computer.memory[0] = Word::from_instruction_in_base(0, 0, 6, Opcode::SPECIAL.code(), 64);    // FLOT
+    computer.memory[1] = Word::from_instruction_in_base(0, 0, 7, Opcode::SPECIAL.code(), 64);    // FIX
+    computer
+}
+
+#[test]
+fn byte_size_is_checked_and_kept_on_reset() {
+    let mut computer = Computer::default();
+    assert_eq!(computer.byte_size(), 256);
+    for byte_size in [0u16, 63, 257, 1000].iter().copied() {
+        assert_eq!(computer.set_byte_size(byte_size), Err(MixError::UnsupportedByteSize { byte_size }));
+    }
+    assert_eq!(computer.byte_size(), 256);
+    computer.set_byte_size(100).unwrap();
+    #[cfg(feature = "mixb")]
+    assert_eq!(mixb::register(&mut computer), Err(MixError::UnsupportedByteSize { byte_size: 100 }));
+    computer.set_byte_size(64).unwrap();
+    computer.reset();
+    assert_eq!(computer.byte_size(), 64);
+}
+
+#[test]
+fn programs_run_with_bytes_of_64() {
+    let largest = 1_073_741_823;
+
+    // The largest word plus one overflows to +0, by ADD as by INCA; one less doesn't.
+    let mut computer = computer_in_base_64(&[("LDA", 3000, 0, None), ("ADD", 3001, 0, None), ("HLT", 0, 0, None)]);
+    computer.memory[3000] = Word::from_value_in_base(largest, 64);
+    computer.memory[3001] = Word::from_value_in_base(1, 64);
+    computer.run().unwrap();
+    assert_eq!((computer.ra, computer.overflow_flag), (Word::default(), true));
+    let mut computer = computer_in_base_64(&[("LDA", 3000, 0, None), ("ADD", 3001, 0, None), ("HLT", 0, 0, None)]);
+    computer.memory[3000] = Word::from_value_in_base(largest - 1, 64);
+    computer.memory[3001] = Word::from_value_in_base(1, 64);
+    computer.run().unwrap();
+    assert_eq!((computer.ra.bytes(), computer.overflow_flag), ([63; 5], false));
+    let mut computer = computer_in_base_64(&[("INCA", 1, 0, None), ("HLT", 0, 0, None)]);
+    computer.ra = Word::from_value_in_base(largest, 64);
+    computer.run().unwrap();
+    assert_eq!((computer.ra, computer.overflow_flag), (Word::default(), true));
+
+    // Addresses and index registers take two bytes of 64, so LDA 0,1 with rI1 = 3000
+    // loads word 3000, and rJ holds the address after the jump.
+    let mut computer = computer_in_base_64(&[("ENT1", 3000, 0, None), ("LDA", 0, 1, None), ("JMP", 3, 0, None), ("HLT", 0, 0, None)]);
+    computer.memory[3000] = Word::from_value_in_base(-77, 64);
+    computer.run().unwrap();
+    assert_eq!(computer.ri1.bytes(), [0, 0, 0, 46, 56]);
+    assert_eq!(computer.ra.field_value_in_base(FieldSpec::WHOLE, 64), -77);
+    assert_eq!(computer.rj.bytes(), [0, 0, 0, 0, 3]);
+
+    // The square of the largest word fills rA and rX, and dividing it back is exact.
+    let mut computer = computer_in_base_64(&[("LDA", 3000, 0, None), ("MUL", 3000, 0, None), ("DIV", 3000, 0, None), ("HLT", 0, 0, None)]);
+    computer.memory[3000] = Word::from_value_in_base(largest, 64);
+    computer.step().unwrap();
+    computer.step().unwrap();
+    assert_eq!(computer.ra.field_value_in_base(FieldSpec::WHOLE, 64), largest - 1);
+    assert_eq!(computer.rx.field_value_in_base(FieldSpec::WHOLE, 64), 1);
+    computer.run().unwrap();
+    assert_eq!(computer.ra.field_value_in_base(FieldSpec::WHOLE, 64), largest);
+    assert_eq!(computer.rx.field_value_in_base(FieldSpec::WHOLE, 64), 0);
+    assert!(!computer.overflow_flag);
+
+    // CHAR gives the ten digits of the largest word, and NUM of those of one more, made by
+    // adding one to the code of the last digit, overflows to +0.
+    let mut computer = computer_in_base_64(&[("CHAR", 0, 0, None), ("INCX", 1, 0, None), ("NUM", 0, 0, None), ("HLT", 0, 0, None)]);
+    computer.ra = Word::from_value_in_base(largest, 64);
+    computer.step().unwrap();
+    let digits: Vec<u8> = computer.ra.bytes().iter().chain(computer.rx.bytes().iter()).map(|code| b'0' + code - 30).collect();
+    assert_eq!(String::from_utf8(digits).unwrap(), "1073741823");
+    computer.run().unwrap();
+    assert_eq!((computer.ra, computer.overflow_flag), (Word::default(), true));
+
+    // FLOT rounds the largest word up to 64^5, which FIX can't give back, and rounds the
+    // fifth digit of 64^4 + 5 × 64 + 33 into the fourth, its exponent in excess 32.
+    let mut computer = flot_fix_computer_in_base_64();
+    computer.ra = Word::from_value_in_base(largest, 64);
+    computer.step().unwrap();
+    assert_eq!(computer.ra.bytes(), [32 + 6, 1, 0, 0, 0]);
+    assert_eq!(MixFloat::from_word_in_base(&computer.ra, 64).to_integer_in_base(64), None);
+    computer.run().unwrap();
+    assert_eq!((computer.ra.bytes(), computer.overflow_flag), ([32 + 6, 1, 0, 0, 0], true));
+    let mut computer = flot_fix_computer_in_base_64();
+    computer.ra = Word::from_value_in_base(-(64 * 64 * 64 * 64 + 64 * 5 + 33), 64);
+    computer.step().unwrap();
+    assert_eq!(computer.ra, Word::new(false, [32 + 5, 1, 0, 0, 6]));
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value_in_base(-(64 * 64 * 64 * 64 + 64 * 6), 64));
+}
+
+#[test]
+fn field_specs_are_checked_on_construction() {
+    for left in 0..=7 {
+        for right in 0..=7 {
+            let expected = if right > 5 {
+                Err(FieldError::PastEnd { left, right })
+            } else if left > right {
+                Err(FieldError::Reversed { left, right })
+            } else {
+                Ok((left, right))
+            };
+            let field = FieldSpec::new(left, right);
+            assert_eq!(field.map(|field| (field.left(), field.right())), expected);
+            assert_eq!(FieldSpec::from_byte((8 * left + right) as u8), field);
+            if let Ok(field) = field {
+                assert_eq!(field.to_byte() as usize, 8 * left + right);
+                assert_eq!(field.to_string(), format!("{}:{}", left, right));
+            }
+        }
+    }
+    assert_eq!(FieldSpec::new(7, 3).unwrap_err().to_string(), "7:3 starts after it ends");
+    assert_eq!(FieldSpec::new(0, 6).unwrap_err().to_string(), "0:6 runs past position 5");
+    assert_eq!((FieldSpec::of(0, 5), FieldSpec::of(1, 5), FieldSpec::of(0, 0)), (FieldSpec::WHOLE, FieldSpec::MAGNITUDE, FieldSpec::SIGN));
+}
+
+#[test]
+fn field_specs_give_their_positions() {
+    // (field, includes the sign, sign only, byte indices, width, mask)
+    let cases = [
+        (FieldSpec::WHOLE, true, false, 0..=4, 5, 0b111111),
+        (FieldSpec::MAGNITUDE, false, false, 0..=4, 5, 0b111110),
+        (FieldSpec::SIGN, true, true, std::ops::RangeInclusive::new(1, 0), 0, 0b000001),
+        (FieldSpec::of(0, 2), true, false, 0..=1, 2, 0b000111),
+        (FieldSpec::of(3, 3), false, false, 2..=2, 1, 0b001000),
+        (FieldSpec::of(4, 5), false, false, 3..=4, 2, 0b110000),
+    ];
+    for (field, includes_sign, is_sign_only, byte_indices, width, mask) in cases.iter().cloned() {
+        assert_eq!(field.includes_sign(), includes_sign, "{}", field);
+        assert_eq!(field.is_sign_only(), is_sign_only, "{}", field);
+        assert_eq!(field.byte_indices(), byte_indices, "{}", field);
+        assert_eq!(field.width(), width, "{}", field);
+        assert_eq!(field.mask(), mask, "{}", field);
+    }
+    for byte in 0..=45 {
+        if let Ok(field) = FieldSpec::from_byte(byte) {
+            assert_eq!(field.byte_indices().count(), field.width(), "{}", field);
+        }
+    }
+}
+
+#[test] 
+fn copy_word_fields_full() {
+    let word1 = Word::new(true, [1,1,1,1,1]);
+    let mut word2 = Word::default();
+    let left = 0;
+    let right = 5;
+    copy_word_fields(&word1, &mut word2, FieldSpec::of(left, right));
+    println!("[{}] [{}]", word1, word2);
+    assert_eq!(word1, word2);
+}
+
+#[test]
+fn copy_word_fields_partial() {
+    let word1 = Word::new(true, [1,1,1,1,1]);
+    let mut word2 = Word::default();
+    let left = 0;
+    let right = 2;
+    copy_word_fields(&word1, &mut word2, FieldSpec::of(left, right));
+
+    assert_eq!(word1.positive, word2.positive);
+    for i in 0..=1 {
+        assert_eq!(word1.bytes[i], word2.bytes[i]);
+    }
+    for i in 2..=4 {
+        assert_ne!(word1.bytes[i], word2.bytes[i]);
+    }
+}
+
+#[test]
+fn copy_word_fields_single() {
+    let word1 = Word::new(false, [1,1,1,1,1]);
+    let mut word2 = Word::default();
+    let left = 2;
+    let right = 2;
+    copy_word_fields(&word1, &mut word2, FieldSpec::of(left, right));
+
+    assert_ne!(word1.positive, word2.positive);
+    for i in 0..=0 {
+        assert_ne!(word1.bytes[i], word2.bytes[i]);
+    }
+    assert_eq!(word1.bytes[1], word2.bytes[1]);
+    for i in 2..=4 {
+        assert_ne!(word1.bytes[i], word2.bytes[i]);
+    }
+}
+
+/// A generator for the random fixtures of the test `name`, seeded from `MIXAL_TEST_SEED`
+/// when it is set and from 0 otherwise, mixed with the name so that each test draws its
+/// own numbers. The seed is printed, so that a failure shows how to repeat it.
+fn test_prng(name: &str) -> Prng {
+    use std::hash::Hasher;
+    let seed: u64 = std::env::var("MIXAL_TEST_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or(0);
+    println!("{} draws from MIXAL_TEST_SEED={}", name, seed);
+    let mut hasher = crate::fingerprint::Fnv1a::new();
+    hasher.write(name.as_bytes());
+    hasher.write_u64(seed);
+    Prng::new(hasher.finish())
+}
+
+fn rand_fill_range(word: &mut Word, begin: usize, end: usize) {
+    let mut gen = test_prng("rand_fill_range");
+    for i in begin..=end {
+        word.bytes[i] = gen.in_range(1..=99) as u8;
+    }
+}
+
+fn load_i_setup(field_specification: FieldSpec, policy: UndefinedPolicy) -> (Computer, Result<(), MixError>) {
+    let load = LoadI::try_new(1, ADDRESS, field_specification, false).unwrap();
+    let mut computer = Computer::default();
+    computer.undefined_policy = policy;
+    rand_fill_range(&mut computer.memory[ADDRESS], 0, 4);
+    computer.memory[ADDRESS].positive = false;
+    computer.ri1 = Word::new(true, [0, 0, 0, 7, 7]);
+    let result = load.execute_on(&mut computer);
+    println!("[{}] [{}]", computer.memory[ADDRESS], computer.ri1);
+    (computer, result)
+}
+
+#[test]
+fn load_i_1_3() {
+    let (computer, result) = load_i_setup(FieldSpec::of(1, 3), UndefinedPolicy::Fault);
+    let mem = computer.memory[ADDRESS];
+    let loaded = Word::new(true, [0, 0, mem.bytes[0], mem.bytes[1], mem.bytes[2]]);
+    assert_eq!(result, Err(MixError::IndexRegisterOverflow { pc: 0, index: 1, value: loaded }));
+    assert_eq!(computer.ri1, Word::new(true, [0, 0, 0, 7, 7]));
+
+    let (computer, result) = load_i_setup(FieldSpec::of(1, 3), UndefinedPolicy::Truncate);
+    let mem = computer.memory[ADDRESS];
+    assert_eq!(result, Ok(()));
+    assert_eq!(computer.ri1, Word::new(true, [0, 0, 0, mem.bytes[1], mem.bytes[2]]));
+    assert_eq!(computer.undefined_warnings.len(), 1);
+    assert_eq!(computer.undefined_warnings[0].operation, "LD1");
+}
+
+#[test]
+fn load_i_small_value_any_field() {
+    let mut computer = Computer::default();
+    computer.memory[ADDRESS] = Word::new(false, [3, 4, 0, 9, 9]);
+    LoadI::try_new(1, ADDRESS, FieldSpec::of(1, 2), false).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.ri1, Word::new(true, [0, 0, 0, 3, 4]));
+    LoadI::try_new(2, ADDRESS, FieldSpec::of(0, 2), true).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.ri2, Word::new(true, [0, 0, 0, 3, 4]));
+    let error = LoadI::try_new(3, ADDRESS, FieldSpec::of(1, 5), false).unwrap().execute_on(&mut computer).unwrap_err();
+    println!("{}", error);
+    assert_eq!(error.code(), "index-register-overflow");
+    assert_eq!(computer.ri3, Word::default());
+}
+
+#[test]
+fn load_i_ent_unaffected() {
+    let mut computer = Computer::default();
+    EntI::try_new(1, 300, false, false).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.ri1, Word::new(false, [0, 0, 0, 1, 44]));
+    EntI::try_new(2, 300, true, false).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.ri2.field_value(FieldSpec::of(4, 5)), 300);
+    assert!(computer.undefined_warnings.is_empty());
+}
+
+#[test]
+fn instruction_parameters_checked_on_construction() {
+    let error = StoreA::try_new(9999, FieldSpec::WHOLE).err().unwrap();
+    println!("{}", error);
+    assert_eq!(error.to_string(), "StoreA can't be constructed with address 9999, which must be below 4096");
+    assert!(StoreA::try_new(4095, FieldSpec::of(5, 5)).is_ok());
+
+    let rejected = |error: Option<InstructionParamError>| error.map(|error| (error.instruction, error.parameter, error.value, error.allowed));
+    assert_eq!(rejected(LoadI::try_new(7, 100, FieldSpec::WHOLE, false).err()), Some(("LoadI", "index", "7".to_string(), "1 to 6".to_string())));
+    assert_eq!(rejected(EntI::try_new(0, 100, true, false).err()), Some(("EntI", "index", "0".to_string(), "1 to 6".to_string())));
+    assert_eq!(rejected(CmpX::try_new(4096, FieldSpec::WHOLE).err()), Some(("CmpX", "address", "4096".to_string(), "below 4096".to_string())));
+    assert_eq!(rejected(JmpC::try_new(100, 3).err()), Some(("JmpC", "operation", "3".to_string(), "4 to 9".to_string())));
+    assert_eq!(rejected(JmpA::try_new(100, 6).err()), Some(("JmpA", "operation", "6".to_string(), "0 to 5".to_string())));
+    assert_eq!(rejected(JmpI::try_new(9, 100, 0).err()), Some(("JmpI", "index", "9".to_string(), "1 to 6".to_string())));
+    assert_eq!(rejected(Input::try_new(21, 100).err()), Some(("Input", "unit", "21".to_string(), "0 to 20".to_string())));
+    assert_eq!(rejected(StoreFlags::try_new(5000).err()), Some(("StoreFlags", "address", "5000".to_string(), "below 4096".to_string())));
+    // Shift amounts and entered values aren't checked.
+    assert!(SLAX::try_new(1000).is_ok());
+    assert!(EntA::try_new(9999, true, false).is_ok());
+    assert!(JmpC::try_new(100, 9).is_ok() && JmpA::try_new(100, 0).is_ok() && Input::try_new(20, 0).is_ok());
+}
+
+fn make_add_with_range(begin: usize, end: usize) -> Add {
+    Add::try_new(ADDRESS, FieldSpec::of(begin, end)).unwrap()
+}
+fn add_test_setup(begin: usize, end: usize) -> Computer { 
+    let mut computer = Computer::default();
+    computer.ra = sample_reg();
+    computer.memory[ADDRESS] =  sample_mem();
+    let store = make_add_with_range(begin, end);
+    store.execute_on(&mut computer).unwrap();
+    computer
+}
+
+#[test]
+fn add_0_0() {
+    // The sign alone is the value zero, which leaves rA as it was.
+    let computer = add_test_setup(0, 0);
+    let should_be = sample_reg();
+    println!("{} {}", computer.ra, should_be);
+    assert_eq!(computer.ra, should_be);
+}
+
+/// rA after `SUB ADDRESS(F)` with rA and the word at `ADDRESS` as given, and the overflow
+/// toggle.
+fn sub_on(ra: Word, mem: Word, field_specification: FieldSpec) -> (Word, bool) {
+    let mut computer = Computer::default();
+    computer.ra = ra;
+    computer.memory[ADDRESS] = mem;
+    Sub::try_new(ADDRESS, field_specification).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.memory[ADDRESS], mem);
+    (computer.ra, computer.overflow_flag)
+}
+
+#[test]
+fn sub_keeps_signs_fields_and_overflow() {
+    let max = Word::new(true, [255; 5]);
+    let cases = [
+        // A difference of zero keeps the sign rA had, whatever the sign of the word at M.
+        (Word::new(false, [0, 0, 0, 0, 7]), Word::new(false, [0, 0, 0, 0, 7]), FieldSpec::WHOLE, Word::new(false, [0; 5]), false),
+        (Word::new(true, [0, 0, 0, 0, 7]), Word::new(true, [0, 0, 0, 0, 7]), FieldSpec::WHOLE, Word::new(true, [0; 5]), false),
+        (Word::new(false, [0; 5]), Word::new(true, [0; 5]), FieldSpec::WHOLE, Word::new(false, [0; 5]), false),
+        (Word::new(true, [0, 0, 0, 0, 5]), Word::new(true, [0, 0, 0, 0, 7]), FieldSpec::WHOLE, Word::new(false, [0, 0, 0, 0, 2]), false),
+        // -0 is subtracted as +0 is.
+        (Word::new(true, [0, 0, 0, 0, 5]), Word::new(false, [0; 5]), FieldSpec::WHOLE, Word::new(true, [0, 0, 0, 0, 5]), false),
+        (Word::new(true, [0, 0, 0, 0, 5]), Word::new(true, [0; 5]), FieldSpec::WHOLE, Word::new(true, [0, 0, 0, 0, 5]), false),
+        (Word::new(true, [0; 5]), Word::new(false, [0; 5]), FieldSpec::WHOLE, Word::new(true, [0; 5]), false),
+        // Overflow keeps the low five bytes, as ADD does.
+        (max, Word::new(false, [0, 0, 0, 0, 1]), FieldSpec::WHOLE, Word::new(true, [0; 5]), true),
+        (max.negate(), Word::new(true, [0, 0, 0, 0, 1]), FieldSpec::WHOLE, Word::new(false, [0; 5]), true),
+        // A field without the sign is a positive value, subtracted and not added; a field
+        // with it takes the sign of M.
+        (Word::new(false, [9, 9, 9, 1, 2]), Word::new(false, [7, 7, 7, 0, 1]), FieldSpec::of(4, 5), Word::new(false, [9, 9, 9, 1, 1]), false),
+        (Word::new(true, [9, 9, 9, 0, 1]), Word::new(true, [7, 7, 7, 0, 3]), FieldSpec::of(4, 5), Word::new(true, [9, 9, 9, 0, 2]), false),
+        (Word::new(true, [0, 5, 9, 9, 9]), Word::new(false, [0, 3, 7, 7, 7]), FieldSpec::of(0, 2), Word::new(true, [0, 8, 9, 9, 9]), false),
+        (Word::new(true, [0, 5, 9, 9, 9]), Word::new(true, [0, 7, 7, 7, 7]), FieldSpec::of(0, 2), Word::new(false, [0, 2, 9, 9, 9]), false),
+        // The sign alone is the value zero.
+        (sample_reg(), sample_mem(), FieldSpec::of(0, 0), sample_reg(), false),
+    ];
+    for (ra, mem, field_specification, should_be, overflow) in cases.iter() {
+        assert_eq!(sub_on(*ra, *mem, *field_specification), (*should_be, *overflow), "[{}] - [{}]", ra, mem);
+    }
+
+    // Decoded from opcode 2, SUB lands in rA and leaves rX alone.
+    let mut computer = Computer::default();
+    computer.ra = Word::from_value(5);
+    computer.memory[ADDRESS] = Word::from_value(7);
+    computer.memory[0] = Word::from_instruction(ADDRESS as i64, 0, 5, Opcode::SUB.code());
+    computer.step().unwrap();
+    assert_eq!((computer.ra, computer.rx, computer.overflow_flag), (sign_magnitude(false, 2), Word::default(), false));
+    assert_eq!(computer.memory[ADDRESS], Word::from_value(7));
+
+    // Negating a word gives a copy.
+    let word = sample_mem();
+    assert_eq!((word.negate(), word), (Word::new(true, [1, 2, 3, 4, 5]), sample_mem()));
+}
+
+
+#[test]
+fn mult_full() {
+    let word1 = Word::new(true, [1,1,1,1,1]);
+    let word2 = Word::new(true, [1,1,1,1,1]);
+    let output = multiply_words(&word1, &word2, FieldSpec::WHOLE);
+    let should_be = (Word::new(true, [0,1,2,3,4]), Word::new(true, [5,4,3,2,1]));
+    println!("{:#?} {:#?}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn mult_neg() {
+    let word1 = Word::new(true, [1,1,1,1,1]);
+    let word2 = Word::new(false, [1,1,1,1,1]);
+    let output = multiply_words(&word1, &word2, FieldSpec::WHOLE);
+    let should_be = (Word::new(false, [0,1,2,3,4]), Word::new(false, [5,4,3,2,1]));
+    println!("{:#?} {:#?}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn mult_2_2() {
+    let word1 = Word::new(true, [1,1,1,1,1]);
+    let word2 = Word::new(true, [1,2,1,1,1]);
+    let output = multiply_words(&word1, &word2, FieldSpec::of(2, 2));
+    let should_be = (Word::new(true, [0,0,0,0,0]), Word::new(true, [2,2,2,2,2]));
+    println!("{:#?} {:#?}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn div_full() {
+    let word_a = Word::new(true, [0,0,0,0,0]);
+    let word_x = Word::new(false, [0,0,0,0,17]);
+    let word_div = Word::new(true, [0,0,0,0,3]);
+    let output = divide_words(&word_a, &word_x, &word_div, FieldSpec::WHOLE);
+    let should_be = (Word::new(true, [0,0,0,0,5]), Word::new(true, [0,0,0,0,2]), false);
+    println!("{:#?} {:#?}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn div_signs_overflow_and_fields() {
+    let seventeen = Word::new(true, [0,0,0,0,17]);
+    let three = Word::new(true, [0,0,0,0,3]);
+
+    // A zero divisor, or a field of the sign alone, overflows and leaves both words +0.
+    assert_eq!(divide_words(&Word::default(), &seventeen, &Word::default(), FieldSpec::WHOLE), (Word::default(), Word::default(), true));
+    assert_eq!(divide_words(&Word::default(), &seventeen, &three.negate(), FieldSpec::of(0, 0)), (Word::default(), Word::default(), true));
+
+    // The quotient doesn't fit in a word just when |rA| >= |V|.
+    assert!(divide_words(&three, &seventeen, &three, FieldSpec::WHOLE).2);
+    assert!(divide_words(&Word::new(false, [0,0,0,0,4]), &seventeen, &three, FieldSpec::WHOLE).2);
+    assert!(!divide_words(&Word::new(true, [0,0,0,0,2]), &seventeen, &three, FieldSpec::WHOLE).2);
+
+    // The quotient takes the algebraic sign and the remainder the sign of rA, whatever
+    // the sign of rX.
+    assert_eq!(divide_words(&Word::new(false, [0; 5]), &seventeen, &three, FieldSpec::WHOLE),
+               (Word::new(false, [0,0,0,0,5]), Word::new(false, [0,0,0,0,2]), false));
+    assert_eq!(divide_words(&Word::new(true, [0; 5]), &seventeen.negate(), &three.negate(), FieldSpec::WHOLE),
+               (Word::new(false, [0,0,0,0,5]), Word::new(true, [0,0,0,0,2]), false));
+    assert_eq!(divide_words(&Word::new(false, [0; 5]), &seventeen, &three.negate(), FieldSpec::WHOLE),
+               (Word::new(true, [0,0,0,0,5]), Word::new(false, [0,0,0,0,2]), false));
+
+    // The divisor is the field of V, taking its sign only when the field includes it.
+    let v = Word::new(false, [0,0,3,0,7]);
+    assert_eq!(divide_words(&Word::default(), &seventeen, &v, FieldSpec::of(3, 3)),
+               (Word::new(true, [0,0,0,0,5]), Word::new(true, [0,0,0,0,2]), false));
+    assert_eq!(divide_words(&Word::default(), &seventeen, &v, FieldSpec::of(0, 3)),
+               (Word::new(false, [0,0,0,0,5]), Word::new(true, [0,0,0,0,2]), false));
+    assert_eq!(divide_words(&Word::default(), &seventeen, &v, FieldSpec::of(4, 5)),
+               (Word::new(true, [0,0,0,0,2]), Word::new(true, [0,0,0,0,3]), false));
+}
+
+#[test]
+fn mult_div_maximal_operands_in_base() {
+    for byte_size in [64u16, 100, 256].iter() {
+        let top = (*byte_size - 1) as u8;
+        let max = Word::new(true, [top; 5]);
+        let (upper, lower) = multiply_words_in_base(&max, &max, FieldSpec::WHOLE, *byte_size);
+        println!("{}: [{}] [{}]", byte_size, upper, lower);
+        // (b^5 - 1)^2 = (b^5 - 2) b^5 + 1
+        assert_eq!(upper, Word::new(true, [top, top, top, top, top - 1]));
+        assert_eq!(lower, Word::new(true, [0, 0, 0, 0, 1]));
+        let b5 = (*byte_size as u128).pow(5);
+        assert_eq!(digits_to_value(&[upper.bytes, lower.bytes].concat(), *byte_size), (b5 - 1) * (b5 - 1));
+        assert_eq!(divide_words_in_base(&upper, &lower, &max.negate(), FieldSpec::WHOLE, *byte_size), (max.negate(), Word::default(), false));
+        assert!(divide_words_in_base(&max, &lower, &max, FieldSpec::WHOLE, *byte_size).2);
+    }
+}
+
+#[test]
+fn mult_div_register_boundary_base_100() {
+    // 10 * 100^3 times 100^2 is 10 * 100^5: 10 in the last digit of rA, and rX all zero.
+    let a = Word::new(true, [0, 10, 0, 0, 0]);
+    let v = Word::new(true, [0, 0, 1, 0, 0]);
+    let (upper, lower) = multiply_words_in_base(&a, &v, FieldSpec::WHOLE, 100);
+    assert_eq!(upper, Word::new(true, [0, 0, 0, 0, 10]));
+    assert_eq!(lower, Word::new(true, [0, 0, 0, 0, 0]));
+    let (quotient, remainder, overflow) = divide_words_in_base(&upper, &Word::new(true, [0, 0, 0, 0, 7]), &v, FieldSpec::WHOLE, 100);
+    assert_eq!((quotient, remainder, overflow), (a, Word::new(true, [0, 0, 0, 0, 7]), false));
+    assert_eq!(split_to_digits(123_456, 100, 4), vec![0, 12, 34, 56]);
+    assert_eq!(digits_to_value(&[12, 34, 56], 100), 123_456);
+}
+
+#[test]
+fn single_word_left_shift_cycle_1() {
+    let word1 = Word::new(false, [1,2,3,4,5]);
+    let output = single_word_left_shift(&word1, 1, true);
+    let should_be = Word::new(false, [2,3,4,5,1]);
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn single_word_left_shift_cycle_3() {
+    let word1 = Word::new(false, [1,2,3,4,5]);
+    let output = single_word_left_shift(&word1, 3, true);
+    let should_be = Word::new(false, [4,5,1,2,3]);
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn single_word_left_shift_cycle_5() {
+    let word1 = Word::new(true, [1,2,3,4,5]);
+    let output = single_word_left_shift(&word1, 5, true);
+    let should_be = word1.clone();
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn single_word_right_shift_cycle_1() {
+    let word1 = Word::new(false, [1,2,3,4,5]);
+    let output = single_word_right_shift(&word1, 1, true);
+    let should_be = Word::new(false, [5,1,2,3,4]);
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn single_word_right_shift_cycle_3() {
+    let word1 = Word::new(false, [1,2,3,4,5]);
+    let output = single_word_right_shift(&word1, 3, true);
+    let should_be = Word::new(false, [3,4,5,1,2]);
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn single_word_right_shift_cycle_5() {
+    let word1 = Word::new(true, [1,2,3,4,5]);
+    let output = single_word_right_shift(&word1, 5, true);
+    let should_be = word1.clone();
+    println!("{:} {:}", output, should_be);
+    assert_eq!(output, should_be);
+}
+
+#[test]
+fn double_word_left_cycle_wraps_into_rx() {
+    let (high, low) = (Word::new(true, [1,2,3,4,5]), Word::new(false, [6,7,8,9,10]));
+    assert_eq!(double_word_left_cycle(&high, &low, 3), (Word::new(true, [4,5,6,7,8]), Word::new(false, [9,10,1,2,3])));
+    assert_eq!(double_word_left_cycle(&high, &low, 10), (high, low));
+    assert_eq!(double_word_left_cycle(&high, &low, 13), double_word_left_cycle(&high, &low, 3));
+}
+
+#[test]
+fn double_word_right_cycle_wraps_into_ra() {
+    let (high, low) = (Word::new(true, [1,2,3,4,5]), Word::new(false, [6,7,8,9,10]));
+    assert_eq!(double_word_right_cycle(&high, &low, 3), (Word::new(true, [8,9,10,1,2]), Word::new(false, [3,4,5,6,7])));
+    assert_eq!(double_word_right_cycle(&high, &low, 10), (high, low));
+    assert_eq!(double_word_right_cycle(&high, &low, usize::MAX), double_word_right_cycle(&high, &low, usize::MAX % 10));
+}
+
+// The example of TAOCP 1.3.1, where each shift starts from the registers the one before it left.
+#[test]
+fn shifts_follow_knuth_example() {
+    let mut computer = Computer::default();
+    computer.ra = Word::new(true, [1,2,3,4,5]);
+    computer.rx = Word::new(false, [6,7,8,9,10]);
+    computer.ri1 = Word::from_value(500);
+    let steps = [
+        (Word::from_instruction(1, 0, ShiftOp::RightAX.field(), Opcode::SHIFT.code()), [0,1,2,3,4], [5,6,7,8,9]),
+        (Word::from_instruction(2, 0, ShiftOp::LeftA.field(), Opcode::SHIFT.code()), [2,3,4,0,0], [5,6,7,8,9]),
+        (Word::from_instruction(4, 0, ShiftOp::RightCircular.field(), Opcode::SHIFT.code()), [6,7,8,9,2], [3,4,0,0,5]),
+        (Word::from_instruction(2, 0, ShiftOp::RightA.field(), Opcode::SHIFT.code()), [0,0,6,7,8], [3,4,0,0,5]),
+        // SLC 501, spelt as 1,1 so that the amount comes from the index register.
+        (Word::from_instruction(1, 1, ShiftOp::LeftCircular.field(), Opcode::SHIFT.code()), [0,6,7,8,3], [4,0,0,5,0]),
+    ];
+    for (word, ra, rx) in steps.iter() {
+        computer.execute(word).unwrap();
+        assert_eq!(computer.ra, Word::new(true, *ra), "{}", word);
+        assert_eq!(computer.rx, Word::new(false, *rx), "{}", word);
+    }
+}
+
+fn portability_test_setup(program: &[Word]) -> Computer {
+    let mut computer = Computer::default();
+    for (i, word) in program.iter().enumerate() {
+        computer.memory[i] = *word;
+    }
+    computer.check_portability = true;
+    computer.run().unwrap();
+    computer
+}
+
+#[test]
+fn portability_byte_of_200() {
+    let computer = portability_test_setup(&[
+        Word::from_instruction(200, 0, 2, Opcode::ENTA.code()),     // ENTA 200
+        Word::from_instruction(1000, 0, 45, Opcode::STA.code()),    // STA 1000(5:5)
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ]);
+    println!("{:#?}", computer.portability_warnings);
+    assert_eq!(computer.memory[1000], Word::new(true, [0,0,0,0,200]));
+    assert_eq!(computer.portability_warnings.len(), 1);
+    assert_eq!(computer.portability_warnings[0].pc, 1);
+    assert_eq!(computer.portability_warnings[0].operation, "STA");
+}
+
+#[test]
+fn portability_in_the_portable_byte_size() {
+    let mut computer = Computer::default();
+    computer.set_byte_size(64).unwrap();
+    computer.memory[0] = Word::from_instruction_in_base(200, 0, 2, Opcode::ENTA.code(), 64);    // ENTA 200
+    computer.memory[1] = Word::from_instruction_in_base(1000, 0, 45, Opcode::STA.code(), 64);   // STA 1000(5:5)
+    computer.memory[2] = Word::from_instruction_in_base(0, 0, 2, Opcode::SPECIAL.code(), 64);   // HLT
+    computer.check_portability = true;
+    computer.run().unwrap();
+    assert_eq!(computer.memory[1000], Word::new(true, [0,0,0,0,8]));
+    assert!(computer.portability_warnings.is_empty());
+}
+
+#[test]
+fn portability_clean_program() {
+    let mut program = vec![
+        Word::from_instruction(1000, 0, 2, Opcode::ENTA.code()),    // ENTA 1000
+        Word::from_instruction(2000, 0, 5, Opcode::STA.code()),     // STA 2000
+        Word::from_instruction(2000, 0, 37, Opcode::LDA.code()),    // LDA 2000(4:5)
+        Word::from_instruction(2000, 0, 5, Opcode::ADD.code()),     // ADD 2000
+        Word::from_instruction(2000, 0, 5, Opcode::CMPA.code()),    // CMPA 2000
+        Word::from_instruction(2001, 0, 5, Opcode::LDA.code()),     // LDA 2001
+        Word::from_instruction(1, 0, 3, Opcode::SHIFT.code()),      // SRAX 1
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ];
+    program.resize(2002, Word::default());
+    program[2001] = Word::new(true, [8,5,13,13,16]);
+    let computer = portability_test_setup(&program);
+    println!("{:#?}", computer.portability_warnings);
+    assert_eq!(computer.ra, Word::new(true, [0,8,5,13,13]));
+    assert_eq!(computer.rx, Word::new(true, [16,0,0,0,0]));
+    assert!(computer.portability_warnings.is_empty());
+}
+
+#[test]
+fn portability_disabled() {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(200, 0, 2, 48);
+    computer.memory[1] = Word::from_instruction(1000, 0, 45, 24);
+    computer.memory[2] = Word::from_instruction(0, 0, 2, 5);
+    computer.run().unwrap();
+    assert!(computer.portability_warnings.is_empty());
+}
+
+fn sparse_test_setup() -> (Computer, Vec<usize>) {
+    let mut computer = Computer::default();
+    let program: Vec<Word> = (0..30).map(|i| Word::from_instruction(2000 + i, 0, 5, 8)).collect();
+    computer.load(100, &program[..20]);
+    computer.load(3990, &program[20..]);
+    let addresses = (100..120).chain(3990..DEFAULT_MEMORY_SIZE).collect();
+    (computer, addresses)
+}
+
+#[test]
+fn iter_nonzero_program() {
+    let (computer, addresses) = sparse_test_setup();
+    let found: Vec<usize> = computer.iter_nonzero().map(|(address, _)| address).collect();
+    assert_eq!(found, addresses);
+    assert_eq!(computer.iter_nonzero().next(), Some((100, &Word::from_instruction(2000, 0, 5, 8))));
+}
+
+#[test]
+fn iter_nonzero_positive_zero_still_occupied() {
+    let (mut computer, addresses) = sparse_test_setup();
+    StoreZ::try_new(105, FieldSpec::WHOLE).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.memory[105], Word::default());
+    let found: Vec<usize> = computer.iter_nonzero().map(|(address, _)| address).collect();
+    assert_eq!(found, addresses);
+}
+
+#[test]
+fn iter_nonzero_clear_range() {
+    let (mut computer, _) = sparse_test_setup();
+    computer.clear_range(105..110);
+    let found: Vec<usize> = computer.iter_nonzero().map(|(address, _)| address).collect();
+    let should_be: Vec<usize> = (100..105).chain(110..120).chain(3990..DEFAULT_MEMORY_SIZE).collect();
+    assert_eq!(found, should_be);
+    computer.reset();
+    assert_eq!(computer.iter_nonzero().count(), 0);
+}
+
+#[test]
+fn iter_range_and_find() {
+    let (computer, _) = sparse_test_setup();
+    let found: Vec<usize> = computer.iter_range(115..3995).map(|(address, _)| address).collect();
+    let should_be: Vec<usize> = (115..120).chain(3990..3995).collect();
+    assert_eq!(found, should_be);
+    assert_eq!(computer.find(|word| word.address() == 2025), Some(3995));
+    assert_eq!(computer.find(|word| word.address() == 1), None);
+}
+
+#[test]
+fn iter_nonzero_scans_occupancy_only() {
+    // Words written behind the bookkeeping's back are invisible to the sparse view,
+    // which shows the iterator never visits the unoccupied part of memory
+    let (mut computer, addresses) = sparse_test_setup();
+    computer.memory[2000] = sample_mem();
+    let found: Vec<usize> = computer.iter_nonzero().map(|(address, _)| address).collect();
+    assert_eq!(found, addresses);
+
+    let mut image = vec![Word::default(); DEFAULT_MEMORY_SIZE];
+    image[7] = sample_mem();
+    image[DEFAULT_MEMORY_SIZE - 1] = sample_reg();
+    let loaded = Computer::new(image, 0);
+    let found: Vec<usize> = loaded.iter_nonzero().map(|(address, _)| address).collect();
+    assert_eq!(found, vec![7, DEFAULT_MEMORY_SIZE - 1]);
+}
+
+fn tape_block(seed: usize) -> [Word; BLOCK_WORDS] {
+    let mut block = [Word::default(); BLOCK_WORDS];
+    for (i, word) in block.iter_mut().enumerate() {
+        let value = (seed * BLOCK_WORDS + i) as i64;
+        *word = Word::from_value(value);
+        word.positive = seed % 2 == 0;
+    }
+    block
+}
+
+#[cfg(feature = "peripherals-file")]
+fn tape_file(name: &str) -> (std::path::PathBuf, std::fs::File) {
+    let path = std::env::temp_dir().join(format!("mixal-{}-{}.tape", name, std::process::id()));
+    let file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+    (path, file)
+}
+
+#[cfg(feature = "peripherals-file")]
+#[test]
+fn tape_stream_large() {
+    let (path, file) = tape_file("large");
+    let mut tape = MagneticTapeUnit::from_stream(1, file);
+    for i in 0..10000 {
+        tape.write_block(&tape_block(i)).unwrap();
+    }
+    tape.rewind();
+    assert_eq!(tape.read_block().unwrap(), tape_block(0));
+    tape.skip(4998).unwrap();
+    assert_eq!(tape.read_block().unwrap(), tape_block(4999));
+    tape.skip(-2).unwrap();
+    assert_eq!(tape.read_block().unwrap(), tape_block(4998));
+    tape.skip(5000).unwrap();
+    assert_eq!(tape.read_block().unwrap(), tape_block(9999));
+    match tape.read_block() {
+        Err(TapeError::EndOfTape { block }) => assert_eq!(block, 10000),
+        other => panic!("Expected the end of the tape, got {:?}", other.map(|_| ())),
+    }
+    drop(tape);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), (10000 * ENCODED_BLOCK_BYTES) as u64);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "peripherals-file")]
+#[test]
+fn tape_stream_corrupt_header() {
+    use std::io::{Seek, SeekFrom, Write};
+    let (path, file) = tape_file("corrupt");
+    let mut tape = MagneticTapeUnit::from_stream(1, file);
+    for i in 0..10 {
+        tape.write_block(&tape_block(i)).unwrap();
+    }
+    tape.flush().unwrap();
+
+    let mut damage = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    damage.seek(SeekFrom::Start((7 * ENCODED_BLOCK_BYTES) as u64)).unwrap();
+    damage.write_all(b"XXXX").unwrap();
+    drop(damage);
+
+    tape.rewind();
+    tape.skip(6).unwrap();
+    assert_eq!(tape.read_block().unwrap(), tape_block(6));
+    let error = tape.read_block().unwrap_err();
+    println!("{}", error);
+    match error {
+        TapeError::CorruptBlock { block, .. } => assert_eq!(block, 7),
+        other => panic!("Expected a corrupt block, got {:?}", other),
+    }
+    drop(tape);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "peripherals-file")]
+#[test]
+fn tape_mixed_backends() {
+    let mut memory_tape = MagneticTapeUnit::new(0, tape_block(0));
+    let mut stream_tape = MagneticTapeUnit::from_stream(1, std::io::Cursor::new(Vec::new()));
+    memory_tape.skip(1).unwrap();
+    for i in 1..4 {
+        memory_tape.write_block(&tape_block(i)).unwrap();
+    }
+    memory_tape.rewind();
+    for _ in 0..4 {
+        let block = memory_tape.read_block().unwrap();
+        stream_tape.write_block(&block).unwrap();
+    }
+    stream_tape.rewind();
+    for i in 0..4 {
+        assert_eq!(stream_tape.read_block().unwrap(), tape_block(i));
+    }
+    assert!(memory_tape.read_block().is_err());
+}
+
+fn status_flags_test_setup() -> Computer {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(1000, 0, 8, Opcode::SPECIAL.code());    // STF 1000
+    computer.memory[1] = Word::from_instruction(1001, 0, 5, Opcode::CMPA.code());       // CMPA 1001
+    computer.memory[2] = Word::from_instruction(1001, 0, 5, Opcode::ADD.code());        // ADD 1001
+    computer.memory[3] = Word::from_instruction(1000, 0, 9, Opcode::SPECIAL.code());    // LDF 1000
+    computer.memory[4] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());       // HLT
+    computer.memory[1001] = Word::new(true, [255,255,255,255,255]);
+    computer.ra = Word::new(true, [255,255,255,255,255]);
+    computer.comparison_flag = ComparisonFlag::less;
+    computer.overflow_flag = false;
+    computer
+}
+
+#[test]
+fn status_flags_restored() {
+    let mut computer = status_flags_test_setup();
+    status_flags::register(&mut computer).unwrap();
+    computer.run().unwrap();
+    println!("{} {}", computer.memory[1000], computer.comparison_flag);
+    assert_eq!(computer.memory[1000], Word::new(true, [0,0,0,0,0]));
+    assert_eq!(computer.comparison_flag, ComparisonFlag::less);
+    assert!(!computer.overflow_flag);
+}
+
+#[test]
+fn status_flags_unregistered() {
+    let mut computer = status_flags_test_setup();
+    computer.run().unwrap();
+    assert_eq!(computer.comparison_flag, ComparisonFlag::equal);
+    assert!(computer.overflow_flag);
+
+    let mut computer = status_flags_test_setup();
+    computer.unimplemented_policy = UnimplementedPolicy::Fault;
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::UnimplementedInstruction { pc: 0, opcode: 5, field: 8 });
+}
+
+#[test]
+fn status_flags_invalid_word() {
+    let mut computer = status_flags_test_setup();
+    status_flags::register(&mut computer).unwrap();
+    computer.memory[0] = Word::from_instruction(1001, 0, 9, Opcode::SPECIAL.code());    // LDF 1001
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::InvalidStatusWord {
+        pc: 0,
+        address: 1001,
+        word: Word::new(true, [255,255,255,255,255]),
+    });
+}
+
+#[test]
+fn extension_conflicts() {
+    let mut computer = Computer::default();
+    status_flags::register(&mut computer).unwrap();
+    assert_eq!(status_flags::register(&mut computer), Err(MixError::ExtensionConflict { opcode: 5, field: 8 }));
+    assert_eq!(computer.register_extension(8, 5, |_, _| Box::new(NoOperation::new())),
+               Err(MixError::ExtensionConflict { opcode: 8, field: 5 }));
+}
+
+#[cfg(feature = "peripherals")]
+fn io_timing_test_setup(model: IoTimingModel, first: Word) -> Computer {
+    let mut computer = Computer::default();
+    computer.io_timing = model;
+    computer.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    computer.memory[0] = first;
+    computer.memory[1] = Word::from_instruction(300, 0, 2, Opcode::ENT1.code());     // ENT1 300
+    computer.memory[2] = Word::from_instruction(1, 0, 1, Opcode::ENT1.code());       // DEC1 1
+    computer.memory[3] = Word::from_instruction(2, 0, 2, Opcode::J1.code());         // J1P 2
+    computer.memory[4] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.load(1000, &tape_block(5));
+    computer
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn cycle_stealing_charges_transfer() {
+    let out = Word::from_instruction(1000, 0, 0, Opcode::OUT.code());    // OUT 1000(0)
+    let mut with_out = io_timing_test_setup(IoTimingModel::CycleStealing, out);
+    let tape = with_out.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    let mut without_out = io_timing_test_setup(IoTimingModel::CycleStealing, Word::default());
+    with_out.steal_cost = 3;
+    without_out.steal_cost = 3;
+    with_out.run().unwrap();
+    without_out.run().unwrap();
+    println!("{} {}", with_out.time(), without_out.time());
+    assert_eq!(with_out.time(), with_out.instruction_time() + with_out.stolen_time());
+    assert_eq!(without_out.time(), without_out.instruction_time() + without_out.stolen_time());
+    assert_eq!(with_out.instruction_time(), without_out.instruction_time());
+    assert_eq!(with_out.time() - without_out.time(), (BLOCK_WORDS as u64) * 3);
+    assert!(!with_out.is_busy(0));
+
+    tape.borrow_mut().rewind();
+    assert_eq!(tape.borrow_mut().read_block().unwrap(), tape_block(5));
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn overlapped_transfer_is_free() {
+    let out = Word::from_instruction(1000, 0, 0, Opcode::OUT.code());    // OUT 1000(0)
+    let mut with_out = io_timing_test_setup(IoTimingModel::Overlapped, out);
+    let mut without_out = io_timing_test_setup(IoTimingModel::Overlapped, Word::default());
+    with_out.run().unwrap();
+    without_out.run().unwrap();
+    assert_eq!(with_out.time(), without_out.time());
+    assert_eq!(with_out.stolen_time(), 0);
+    assert_eq!(with_out.time(), 2 + 300 * 2 + 1);
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn jbus_waits_for_transfer() {
+    for model in [IoTimingModel::Instant, IoTimingModel::Overlapped, IoTimingModel::CycleStealing].iter() {
+        let mut computer = io_timing_test_setup(*model, Word::from_instruction(1000, 0, 0, 37));
+        computer.memory[1] = Word::from_instruction(1, 0, 0, Opcode::JBUS.code());       // JBUS 1(0)
+        computer.memory[2] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+        computer.run().unwrap();
+        println!("{:?} {} {}", model, computer.instruction_time(), computer.stolen_time());
+        assert!(!computer.is_busy(0));
+        assert_eq!(computer.time(), computer.instruction_time() + computer.stolen_time());
+        match model {
+            IoTimingModel::Instant => assert_eq!(computer.time(), 3),
+            IoTimingModel::Overlapped => assert_eq!(computer.time(), 2 * BLOCK_WORDS as u64 + 2),
+            IoTimingModel::CycleStealing => {
+                assert_eq!(computer.stolen_time(), BLOCK_WORDS as u64);
+                assert!(computer.time() > 2 * BLOCK_WORDS as u64 + 2);
+            },
+        }
+    }
+}
+
+fn cost_model_loop(opcode: u8, model: CostModel) -> u64 {
+    let mut computer = Computer::default();
+    computer.set_cost_model(model);
+    computer.memory[0] = Word::from_instruction(100, 0, 2, Opcode::ENT1.code());    // ENT1 100
+    computer.memory[1] = Word::from_instruction(1000, 0, 5, opcode);
+    computer.memory[2] = Word::from_instruction(1, 0, 1, Opcode::ENT1.code());       // DEC1 1
+    computer.memory[3] = Word::from_instruction(1, 0, 2, Opcode::J1.code());         // J1P 1
+    computer.memory[4] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.run().unwrap();
+    computer.time()
+}
+
+#[test]
+fn cost_model_swaps_at_runtime() {
+    let double_mul = CostModel::from_text(include_str!("../fixtures/double_mul.cost")).unwrap();
+    assert_eq!(double_mul.cost_of("MUL"), Some(20));
+    assert_eq!(double_mul.cost_of("LD3N"), CostModel::knuth().cost_of("LD3N"));
+
+    let (knuth_mul, double_mul_mul) = (cost_model_loop(3, CostModel::knuth()), cost_model_loop(3, double_mul.clone()));
+    let (knuth_add, double_mul_add) = (cost_model_loop(1, CostModel::knuth()), cost_model_loop(1, double_mul));
+    println!("{} {} {} {}", knuth_mul, double_mul_mul, knuth_add, double_mul_add);
+    assert_eq!(knuth_mul, 1 + 100 * (10 + 1 + 1) + 1);
+    assert_eq!(double_mul_mul - knuth_mul, 100 * 10);
+    assert_eq!(double_mul_add, knuth_add);
+
+    match CostModel::from_text("MUL = 20\nmove_word = 2") {
+        Err(CostModelError::Missing(missing)) => {
+            assert!(missing.contains(&"ADD".to_string()));
+            assert!(missing.contains(&"JXNP".to_string()));
+            assert!(!missing.contains(&"MUL".to_string()));
+            assert!(missing.contains(&"NUM".to_string()));
+        },
+        other => panic!("expected missing operations, found {:?}", other),
+    }
+    assert_eq!(CostModel::from_text("MULT = 20"), Err(CostModelError::UnknownKey { line: 1, key: "MULT".to_string() }));
+    assert_eq!(CostModel::from_text("\nMUL 20"), Err(CostModelError::Syntax { line: 2, text: "MUL 20".to_string() }));
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn tape_io_instructions() {
+    let mut computer = Computer::default();
+    let tape = MagneticTapeUnit::new(3, tape_block(0));
+    computer.attach_tape(tape);
+    computer.memory[0] = Word::from_instruction(1000, 0, 3, Opcode::OUT.code());     // OUT 1000(3)
+    computer.memory[1] = Word::from_instruction(-1, 0, 3, Opcode::IOC.code());       // IOC -1(3)
+    computer.memory[2] = Word::from_instruction(2000, 0, 3, Opcode::IN.code());      // IN 2000(3)
+    computer.memory[3] = Word::from_instruction(0, 0, 3, Opcode::IOC.code());        // IOC 0(3)
+    computer.memory[4] = Word::from_instruction(3000, 0, 3, Opcode::IN.code());      // IN 3000(3)
+    computer.memory[5] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.load(1000, &tape_block(7));
+    computer.run().unwrap();
+    assert_eq!(computer.memory[2000..2100], tape_block(7)[..]);
+    assert_eq!(computer.memory[3000..3100], tape_block(7)[..]);
+
+    computer.reset();
+    computer.memory[0] = Word::from_instruction(2000, 0, 4, Opcode::IN.code());    // IN 2000(4)
+    assert_eq!(computer.run(), Err(MixError::UnitNotAttached { pc: 0, unit: 4, operation: "IN".to_string(), attached: vec![3] }));
+}
+
+#[cfg(feature = "peripherals")]
+fn channel_producer() -> Computer {
+    let mut computer = Computer::default();
+    computer.load(0, &[
+        Word::from_instruction(50, 0, 2, Opcode::ENT1.code()),      // ENT1 50
+        Word::from_instruction(1, 0, 7, Opcode::JBUS.code()),       // JBUS 1(7)
+        Word::from_instruction(1000, 0, 5, Opcode::ST1.code()),     // ST1 1000
+        Word::from_instruction(1000, 0, 7, Opcode::OUT.code()),     // OUT 1000(7)
+        Word::from_instruction(1, 0, 1, Opcode::ENT1.code()),       // DEC1 1
+        Word::from_instruction(1, 0, 2, Opcode::J1.code()),         // J1P 1
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ]);
+    computer
+}
+
+#[cfg(feature = "peripherals")]
+fn channel_consumer() -> Computer {
+    let mut computer = Computer::default();
+    computer.load(0, &[
+        Word::from_instruction(50, 0, 2, Opcode::ENT2.code()),      // ENT2 50
+        Word::from_instruction(1, 0, 8, Opcode::JBUS.code()),       // JBUS 1(8)
+        Word::from_instruction(2000, 0, 8, Opcode::IN.code()),      // IN 2000(8)
+        Word::from_instruction(3000, 0, 5, Opcode::LDA.code()),     // LDA 3000
+        Word::from_instruction(2000, 0, 5, Opcode::ADD.code()),     // ADD 2000
+        Word::from_instruction(3000, 0, 5, Opcode::STA.code()),     // STA 3000
+        Word::from_instruction(1, 0, 1, Opcode::ENT2.code()),       // DEC2 1
+        Word::from_instruction(1, 0, 2, Opcode::J2.code()),         // J2P 1
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ]);
+    computer
+}
+
+#[cfg(feature = "peripherals")]
+fn channel_cluster(depth: usize) -> Cluster {
+    let mut cluster = Cluster::new();
+    cluster.add("producer", channel_producer()).unwrap();
+    cluster.add("consumer", channel_consumer()).unwrap();
+    cluster.connect(("producer", 7), ("consumer", 8), ChannelDepth(depth)).unwrap();
+    cluster
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn cluster_producer_consumer() {
+    let limits = RoundRobinLimits { instructions_per_turn: 3, max_rounds: 10000 };
+    for depth in [4, 1].iter() {
+        let mut cluster = channel_cluster(*depth);
+        let rounds = cluster.run_round_robin(limits).unwrap();
+        let consumer = cluster.machine("consumer").unwrap();
+        println!("depth {} took {} rounds, sum {}", depth, rounds, consumer.memory[3000]);
+        assert_eq!(consumer.memory[3000].field_value(FieldSpec::WHOLE), (1..=50).sum::<i64>());
+        assert!(cluster.machine("producer").unwrap().is_halted());
+    }
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn cluster_deadlock() {
+    let mut cluster = Cluster::new();
+    let mut first = channel_consumer();
+    first.memory[1] = Word::default();
+    let mut second = channel_consumer();
+    second.memory[1] = Word::default();
+    cluster.add("first", first).unwrap();
+    cluster.add("second", second).unwrap();
+    cluster.connect(("first", 9), ("second", 8), ChannelDepth(2)).unwrap();
+    cluster.connect(("second", 9), ("first", 8), ChannelDepth(2)).unwrap();
+    assert_eq!(cluster.connect(("first", 9), ("second", 10), ChannelDepth(2)),
+               Err(ClusterError::UnitInUse { machine: "first".to_string(), unit: 9 }));
+
+    let error = cluster.run_round_robin(RoundRobinLimits { instructions_per_turn: 3, max_rounds: 100 }).unwrap_err();
+    println!("{}", error);
+    match error {
+        ClusterError::Deadlock(blocked) => {
+            assert_eq!(blocked.len(), 2);
+            assert_eq!(blocked[0].machine, "first");
+            assert_eq!(blocked[0].unit, 8);
+            assert_eq!(blocked[0].channel, "second:9 -> first:8, empty");
+            assert_eq!(blocked[1].machine, "second");
+            assert_eq!(blocked[1].channel, "first:9 -> second:8, empty");
+        },
+        other => panic!("Expected a deadlock, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn blocked_unit_outside_cluster() {
+    let mut computer = channel_consumer();
+    let channel = crate::peripherals::Channel::new(1);
+    computer.attach(8, crate::peripherals::ChannelEnd::consumer(&channel));
+    assert_eq!(computer.run(), Err(MixError::UnitBlocked { pc: 1, unit: 8 }));
+}
+
+fn index_sentinel(k: u8) -> Word {
+    Word::new(true, [0, 0, 0, k, 10 * k])
+}
+
+fn index_registers(computer: &mut Computer) -> [Word; 6] {
+    [computer.ri1, computer.ri2, computer.ri3, computer.ri4, computer.ri5, computer.ri6]
+}
+
+// Runs one instruction of `family` aimed at index register `k`, on a machine whose index
+// registers hold distinct sentinels, and checks that only register `k` was used.
+fn index_register_case(family: &str, k: u8) {
+    let mut computer = Computer::default();
+    for j in 1..=6 {
+        *register_for_index(&mut computer, j).unwrap() = index_sentinel(j);
+    }
+    computer.memory[1000] = Word::new(true, [0, 0, 0, 1, 2]);
+    computer.comparison_flag = ComparisonFlag::less;
+    let k_value = index_sentinel(k).field_value(FieldSpec::of(4, 5)) as usize;
+
+    let (instruction, expected) = match family {
+        "LD" => (Word::from_instruction(1000, 0, 5, 8 + k), Some(Word::new(true, [0, 0, 0, 1, 2]))),
+        "LDN" => (Word::from_instruction(1000, 0, 5, 16 + k), Some(Word::new(false, [0, 0, 0, 1, 2]))),
+        "ST" => (Word::from_instruction(1000, 0, 5, 24 + k), None),
+        "ENT" => (Word::from_instruction(7, 0, 2, 48 + k), Some(Word::new(true, [0, 0, 0, 0, 7]))),
+        "ENN" => (Word::from_instruction(7, 0, 3, 48 + k), Some(Word::new(false, [0, 0, 0, 0, 7]))),
+        "INC" => (Word::from_instruction(5, 0, 0, 48 + k), Some(Word::new(true, [0, 0, 0, k, 10 * k + 5]))),
+        "DEC" => (Word::from_instruction(5, 0, 1, 48 + k), Some(Word::new(true, [0, 0, 0, k, 10 * k - 5]))),
+        "CMP" => {
+            computer.memory[1001] = index_sentinel(k);
+            (Word::from_instruction(1001, 0, 5, 56 + k), None)
+        },
+        "J" => {
+            *register_for_index(&mut computer, k).unwrap() = Word::default();
+            (Word::from_instruction(500, 0, 1, 40 + k), None)
+        },
+        "INDEX" => {
+            computer.memory[2000 + k_value] = Word::new(true, [0, 0, 0, 0, 99]);
+            (Word::from_instruction(2000, k, 5, 8), None)
+        },
+        _ => panic!("Unknown family {}", family),
+    };
+    let before = index_registers(&mut computer);
+    computer.memory[0] = instruction;
+    computer.step().unwrap();
+
+    let after = index_registers(&mut computer);
+    for j in 1..=6 {
+        let should_be = if j == k { expected.unwrap_or(before[j as usize - 1]) } else { before[j as usize - 1] };
+        println!("{} {} rI{}: {} {}", family, k, j, after[j as usize - 1], should_be);
+        assert_eq!(after[j as usize - 1], should_be);
+    }
+    match family {
+        "ST" => assert_eq!(computer.memory[1000], index_sentinel(k)),
+        "CMP" => assert_eq!(computer.comparison_flag, ComparisonFlag::equal),
+        "J" => assert_eq!(computer.rj, Word::from_value(1)),
+        "INDEX" => assert_eq!(computer.ra, Word::new(true, [0, 0, 0, 0, 99])),
+        _ => {},
+    }
+}
+
+#[test]
+fn index_register_coverage() {
+    let families = ["LD", "LDN", "ST", "ENT", "ENN", "INC", "DEC", "CMP", "J", "INDEX"];
+    for family in families.iter() {
+        for k in 1..=6 {
+            index_register_case(family, k);
+        }
+    }
+}
+
+fn state_hash_program(words: usize) -> Computer {
+    let mut computer = Computer::default();
+    let program: Vec<Word> = (0..words).map(|i| Word::from_instruction(i as i64, 0, 5, 8)).collect();
+    computer.load(0, &program);
+    computer.ra = Word::new(false, [1, 2, 3, 4, 5]);
+    computer.ri3 = Word::new(true, [0, 0, 0, 3, 3]);
+    computer
+}
+
+#[test]
+fn state_hash_equal_states() {
+    let first = state_hash_program(5);
+    let second = state_hash_program(5);
+    println!("{:x} {:x}", first.state_hash(), second.state_hash());
+    assert!(first.state_eq(&second));
+    assert_eq!(first.state_hash(), second.state_hash());
+
+    let mut flipped = state_hash_program(5);
+    let word = flipped.memory[3].negate();
+    flipped.write_mem(3, word);
+    assert!(!first.state_eq(&flipped));
+    assert_ne!(first.state_hash(), flipped.state_hash());
+
+    let mut negative_zero = state_hash_program(5);
+    negative_zero.write_mem(100, Word::new(false, [0; 5]));
+    let mut positive_zero = state_hash_program(5);
+    positive_zero.write_mem(100, Word::default());
+    assert!(positive_zero.state_eq(&first));
+    assert_eq!(positive_zero.state_hash(), first.state_hash());
+    assert_ne!(negative_zero.state_hash(), first.state_hash());
+}
+
+#[test]
+fn state_hash_visits_occupied_words() {
+    let (_, small) = state_hash_program(5).state_hash_counted();
+    let (_, large) = state_hash_program(3999).state_hash_counted();
+    assert_eq!(small, 5);
+    assert_eq!(large, 3999);
+}
+
+// The opcode and normal field of every operation, from the instruction summary in TAOCP
+// section 1.3.1, with - marking the I/O operations that need a unit number.
+const NORMAL_FIELDS: &str = "
+        NOP 0 0, ADD 1 5, SUB 2 5, MUL 3 5, DIV 4 5, NUM 5 0, CHAR 5 1, HLT 5 2,
+        SLA 6 0, SRA 6 1, SLAX 6 2, SRAX 6 3, SLC 6 4, SRC 6 5, MOVE 7 1, LDA 8 5,
+        LD1 9 5, LD2 10 5, LD3 11 5, LD4 12 5, LD5 13 5, LD6 14 5, LDX 15 5, LDAN 16 5,
+        LD1N 17 5, LD2N 18 5, LD3N 19 5, LD4N 20 5, LD5N 21 5, LD6N 22 5, LDXN 23 5, STA 24 5,
+        ST1 25 5, ST2 26 5, ST3 27 5, ST4 28 5, ST5 29 5, ST6 30 5, STX 31 5, STJ 32 2,
+        STZ 33 5, JBUS 34 -, IOC 35 -, IN 36 -, OUT 37 -, JRED 38 -, JMP 39 0, JSJ 39 1,
+        JOV 39 2, JNOV 39 3, JL 39 4, JE 39 5, JG 39 6, JGE 39 7, JNE 39 8, JLE 39 9,
+        JAN 40 0, JAZ 40 1, JAP 40 2, JANN 40 3, JANZ 40 4, JANP 40 5, J1N 41 0, J1Z 41 1,
+        J1P 41 2, J1NN 41 3, J1NZ 41 4, J1NP 41 5, J2N 42 0, J2Z 42 1, J2P 42 2, J2NN 42 3,
+        J2NZ 42 4, J2NP 42 5, J3N 43 0, J3Z 43 1, J3P 43 2, J3NN 43 3, J3NZ 43 4, J3NP 43 5,
+        J4N 44 0, J4Z 44 1, J4P 44 2, J4NN 44 3, J4NZ 44 4, J4NP 44 5, J5N 45 0, J5Z 45 1,
+        J5P 45 2, J5NN 45 3, J5NZ 45 4, J5NP 45 5, J6N 46 0, J6Z 46 1, J6P 46 2, J6NN 46 3,
+        J6NZ 46 4, J6NP 46 5, JXN 47 0, JXZ 47 1, JXP 47 2, JXNN 47 3, JXNZ 47 4, JXNP 47 5,
+        INCA 48 0, DECA 48 1, ENTA 48 2, ENNA 48 3, INC1 49 0, DEC1 49 1, ENT1 49 2, ENN1 49 3,
+        INC2 50 0, DEC2 50 1, ENT2 50 2, ENN2 50 3, INC3 51 0, DEC3 51 1, ENT3 51 2, ENN3 51 3,
+        INC4 52 0, DEC4 52 1, ENT4 52 2, ENN4 52 3, INC5 53 0, DEC5 53 1, ENT5 53 2, ENN5 53 3,
+        INC6 54 0, DEC6 54 1, ENT6 54 2, ENN6 54 3, INCX 55 0, DECX 55 1, ENTX 55 2, ENNX 55 3,
+        CMPA 56 5, CMP1 57 5, CMP2 58 5, CMP3 59 5, CMP4 60 5, CMP5 61 5, CMP6 62 5, CMPX 63 5,
+";
+
+#[test]
+fn opcode_normal_fields() {
+    let entries: Vec<&str> = NORMAL_FIELDS.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()).collect();
+    assert_eq!(entries.len(), opcodes::OPCODES.len());
+    for entry in entries {
+        let parts: Vec<&str> = entry.split_whitespace().collect();
+        let (mnemonic, code) = (parts[0], parts[1].parse::<u8>().unwrap());
+        let encoded = opcodes::encode(mnemonic, 1000, 0, None);
+        println!("{} {:?}", entry, encoded);
+        match parts[2] {
+            "-" => assert_eq!(encoded, Err(EncodeError::MissingUnit(mnemonic.to_string()))),
+            field => {
+                let field = field.parse::<u8>().unwrap();
+                assert_eq!(encoded, Ok(Word::from_instruction(1000, 0, field, code)));
+                assert_eq!(opcodes::disassemble(&encoded.unwrap()), Some(format!("{} 1000", mnemonic)));
+            },
+        }
+    }
+}
+
+#[test]
+fn opcode_explicit_fields() {
+    let load = opcodes::encode("LDA", -20, 3, Some(11)).unwrap();
+    assert_eq!(load, Word::from_instruction(-20, 3, 11, 8));
+    assert_eq!(opcodes::disassemble(&load).unwrap(), "LDA -20,3(1:3)");
+    let output = opcodes::encode("OUT", 1000, 0, Some(18)).unwrap();
+    assert_eq!(opcodes::disassemble(&output).unwrap(), "OUT 1000(18)");
+    let store = opcodes::encode("STJ", 1000, 0, Some(5)).unwrap();
+    assert_eq!(opcodes::disassemble(&store).unwrap(), "STJ 1000(0:5)");
+    let moved = opcodes::encode("MOVE", 1000, 0, Some(10)).unwrap();
+    assert_eq!(opcodes::disassemble(&moved).unwrap(), "MOVE 1000(10)");
+    assert_eq!(opcodes::encode("HLT", 0, 0, Some(5)), Err(EncodeError::FixedField("HLT".to_string())));
+    assert_eq!(opcodes::encode("LDQ", 0, 0, None), Err(EncodeError::UnknownMnemonic("LDQ".to_string())));
+}
+
+fn assert_deterministic<F: Fn() -> String>(f: F, runs: usize) {
+    let first = f();
+    println!("{}", first);
+    assert!(!first.is_empty());
+    for _ in 1..runs {
+        assert_eq!(f(), first);
+    }
+}
+
+#[test]
+fn deterministic_portability_report() {
+    assert_deterministic(|| {
+        let computer = portability_test_setup(&[
+            Word::from_instruction(200, 0, 2, Opcode::ENTA.code()),     // ENTA 200
+            Word::from_instruction(1000, 0, 45, Opcode::STA.code()),    // STA 1000(5:5)
+            Word::from_instruction(1001, 0, 45, Opcode::STA.code()),    // STA 1001(5:5)
+            Word::from_instruction(1000, 0, 5, Opcode::LDA.code()),     // LDA 1000
+            Word::from_instruction(1, 0, 2, Opcode::SHIFT.code()),      // SLAX 1
+            Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+        ]);
+        assert!(computer.portability_warnings.len() > 1);
+        report::render_portability_warnings(&computer.portability_warnings)
+    }, 5);
+}
+
+const WORD_FORMATS: &str = include_str!("../fixtures/word_formats.raw");
+
+#[test]
+fn word_formats_render_fixture() {
+    let mut computer = Computer::default();
+    loader::parse_raw(WORD_FORMATS).unwrap().load_into(&mut computer);
+    // A word stored at run time, which the source map says nothing of, and which doesn't
+    // decode cleanly for its index.
+    computer.write_mem(3003, Word::new(false, [0, 0, 9, 0, 8]));
+    let render = |format| {
+        let text = report::render_memory_as(&computer, 3000..3004, format);
+        print!("{:?}\n{}", format, text);
+        text
+    };
+    assert_eq!(render(WordFormat::Bytes), report::render_memory(&computer, 3000..3004));
+    assert_eq!(render(WordFormat::Bytes), "\
+3000:  +    7  208    1    3    8
+3001:  -    0    0    0   48   57
+3002:  +    0   59  154  202    0
+3003:  -    0    0    9    0    8
+");
+    assert_eq!(render(WordFormat::SignedValue), "\
+3000:    33554498312
+3001:         -12345
+3002:     1000000000
+3003:        -589832
+");
+    assert_eq!(render(WordFormat::Both), "\
+3000:  +    7  208    1    3    8     33554498312
+3001:  -    0    0    0   48   57          -12345
+3002:  +    0   59  154  202    0      1000000000
+3003:  -    0    0    9    0    8         -589832
+");
+    assert_eq!(render(WordFormat::Instruction), "\
+3000: LDA 2000,1(0:3)
+3001:  -    0    0    0   48   57          -12345
+3002:  +    0   59  154  202    0      1000000000
+3003:  -    0    0    9    0    8         -589832
+");
+
+    let mut after = Computer::default();
+    loader::parse_raw(WORD_FORMATS).unwrap().load_into(&mut after);
+    after.memory[3001] = Word::from_value(12345);
+    after.memory[3003] = Word::from_value(1);
+    let diff = report::render_memory_diff(&computer, &after, 3000..3004);
+    print!("{}", diff);
+    assert_eq!(diff, "\
+3001:  -    0    0    0   48   57 ->  +    0    0    0   48   57  -12345 -> 12345
+3003:  -    0    0    9    0    8 ->  +    0    0    0    0    1  -589832 -> 1
+");
+}
+
+#[test]
+fn deterministic_memory_report() {
+    assert_deterministic(|| {
+        let (computer, _) = sparse_test_setup();
+        report::render_memory(&computer, 0..computer.memory_size())
+    }, 5);
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn deterministic_deadlock_report() {
+    assert_deterministic(|| {
+        let mut cluster = Cluster::new();
+        for name in ["third", "first", "second"].iter() {
+            let mut computer = channel_consumer();
+            computer.memory[1] = Word::default();
+            cluster.add(name, computer).unwrap();
+        }
+        cluster.connect(("first", 9), ("second", 8), ChannelDepth(1)).unwrap();
+        cluster.connect(("second", 9), ("third", 8), ChannelDepth(1)).unwrap();
+        cluster.connect(("third", 9), ("first", 8), ChannelDepth(1)).unwrap();
+        match cluster.run_round_robin(RoundRobinLimits { instructions_per_turn: 2, max_rounds: 100 }) {
+            Err(ClusterError::Deadlock(blocked)) => report::render_blocked_machines(&blocked),
+            other => panic!("Expected a deadlock, got {:?}", other),
+        }
+    }, 5);
+}
+
+const RAW_DECK: &str = "
+# Adds two numbers and counts down rI1.
+START 10
+10: +1000 0 5 8      # LDA 1000
+11: +1001 0 5 1      # ADD 1001
+12: +1002 0 5 24     # STA 1002
+13: +3 0 2 49        # ENT1 3
+14: +0 0 2 5         # HLT
+1000: +1200
+1001: -200
+";
+
+#[test]
+fn raw_deck_runs() {
+    let program = loader::parse_raw(RAW_DECK).unwrap();
+    assert_eq!(program.start, 10);
+    assert_eq!(program.words.len(), 7);
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value(1000));
+    assert_eq!(computer.memory[1002], Word::from_value(1000));
+    assert_eq!(computer.ri1, Word::new(true, [0, 0, 0, 0, 3]));
+}
+
+#[test]
+fn raw_deck_errors() {
+    let past_end = format!("\n\n{}: +5", DEFAULT_MEMORY_SIZE);
+    let cases = [
+        ("0: +0 0 2 5\n1: 1000 0 5 8", 2, RawErrorKind::BadSign("1000 0 5 8".to_string())),
+        ("0: +1000 0 46 8", 1, RawErrorKind::InvalidField(46)),
+        (&past_end[..], 3, RawErrorKind::BadNumber(ParseDiag::new(&DEFAULT_MEMORY_SIZE.to_string(), "an address from 0 to 3999".to_string()))),
+        ("0: +65536 0 5 8", 1, RawErrorKind::OutOfRange { part: "address", value: 65536 }),
+        ("0: +1000 7 5 8", 1, RawErrorKind::OutOfRange { part: "index", value: 7 }),
+        ("0: +1000 0 5", 1, RawErrorKind::WrongPartCount(3)),
+        ("0 +5", 1, RawErrorKind::MissingColon),
+        ("0: +x", 1, RawErrorKind::BadNumber(ParseDiag::new("+x", "a value from -1099511627775 to +1099511627775".to_string()))),
+        ("0: +1000 0 5 0x8", 1, RawErrorKind::BadNumber(ParseDiag::new("0x8", "a decimal number".to_string()))),
+        ("0: +5\n# again\n0: -5", 3, RawErrorKind::Overlap { address: 0, first_line: 1 }),
+        ("START 1\nSTART 2", 2, RawErrorKind::DuplicateStart),
+    ];
+    for (text, line, kind) in cases.iter() {
+        let error = loader::parse_raw(text).unwrap_err();
+        println!("{}", error);
+        assert_eq!(error, RawParseError { line: *line, kind: kind.clone() });
+    }
+}
+
+#[test]
+fn raw_deck_round_trip() {
+    let program = loader::parse_raw(RAW_DECK).unwrap();
+    let rendered = loader::render_raw(&program);
+    println!("{}", rendered);
+    let parsed = loader::parse_raw(&rendered).unwrap();
+    assert_eq!(parsed.words, program.words);
+    assert_eq!(parsed.start, program.start);
+    assert_eq!(parsed.source_map, program.source_map);
+}
+
+#[test]
+fn raw_deck_in_base() {
+    let program = loader::parse_raw_in_base("10: +1000 0 5 8\n11: +0 0 2 5\n1000: +4095\n", 64).unwrap();
+    assert_eq!(program.words[&10], Word::from_instruction_in_base(1000, 0, 5, 8, 64));
+    assert_eq!(program.words[&1000], Word::from_value_in_base(4095, 64));
+    let mut computer = Computer::default();
+    computer.set_byte_size(64).unwrap();
+    program.load_into(&mut computer);
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value_in_base(4095, 64));
+
+    let rendered = loader::render_raw_in_base(&program, 64);
+    assert!(rendered.contains("10: +1000 0 5 8"), "{}", rendered);
+    assert_eq!(loader::parse_raw_in_base(&rendered, 64).unwrap().words, program.words);
+    assert_eq!(loader::parse_raw_in_base(&rendered, 63).unwrap_err(), RawParseError { line: 1, kind: RawErrorKind::ByteSize(63) });
+}
+
+fn magnitude_in_base(word: &Word, byte_size: u16) -> i128 {
+    word.bytes.iter().fold(0, |value, byte| value * byte_size as i128 + *byte as i128)
+}
+
+fn random_word_in_base(rng: &mut Prng, byte_size: u16) -> Word {
+    let mut word = Word::default();
+    for byte in word.bytes.iter_mut() {
+        *byte = rng.below(byte_size as u64) as u8;
+    }
+    word
+}
+
+#[test]
+fn add_with_carry_matches_reference() {
+    let mut rng = test_prng("add_with_carry_matches_reference");
+    for byte_size in [64u16, 256].iter() {
+        let modulus = (*byte_size as i128).pow(5);
+        for _ in 0..1000 {
+            let word1 = random_word_in_base(&mut rng, *byte_size);
+            let word2 = random_word_in_base(&mut rng, *byte_size);
+            let carry_in = rng.bool();
+            let (sum, carry) = word1.add_with_carry_in_base(&word2, carry_in, *byte_size);
+            let reference = magnitude_in_base(&word1, *byte_size) + magnitude_in_base(&word2, *byte_size) + carry_in as i128;
+            assert_eq!(magnitude_in_base(&sum, *byte_size), reference % modulus);
+            assert_eq!(carry, reference >= modulus);
+
+            let (difference, borrow) = word1.sub_with_borrow_in_base(&word2, carry_in, *byte_size);
+            let reference = magnitude_in_base(&word1, *byte_size) - magnitude_in_base(&word2, *byte_size) - carry_in as i128;
+            assert_eq!(magnitude_in_base(&difference, *byte_size), reference.rem_euclid(modulus));
+            assert_eq!(borrow, reference < 0);
+
+            let ordering = magnitude_in_base(&word1, *byte_size).cmp(&magnitude_in_base(&word2, *byte_size));
+            assert_eq!(word1.cmp_magnitude(&word2), ordering);
+        }
+
+        let max = Word::new(true, [(*byte_size - 1) as u8; 5]);
+        let (sum, carry) = max.add_with_carry_in_base(&Word::default(), true, *byte_size);
+        assert_eq!((sum, carry), (Word::default(), true));
+        let (difference, borrow) = Word::default().sub_with_borrow_in_base(&Word::default(), true, *byte_size);
+        assert_eq!((difference, borrow), (max, true));
+    }
+}
+
+#[test]
+fn add_words_signs_and_fields() {
+    let cases = [
+        (Word::new(true, [0, 0, 0, 1, 0]), Word::new(false, [0, 0, 0, 0, 1]), FieldSpec::WHOLE, Word::new(true, [0, 0, 0, 0, 255]), false),
+        (Word::new(false, [0, 0, 0, 1, 0]), Word::new(true, [0, 0, 0, 0, 1]), FieldSpec::WHOLE, Word::new(false, [0, 0, 0, 0, 255]), false),
+        (Word::new(false, [0, 0, 0, 0, 7]), Word::new(true, [0, 0, 0, 0, 7]), FieldSpec::WHOLE, Word::new(false, [0, 0, 0, 0, 0]), false),
+        (Word::new(true, [255; 5]), Word::new(true, [0, 0, 0, 0, 1]), FieldSpec::WHOLE, Word::new(true, [0; 5]), true),
+        (Word::new(false, [9, 9, 9, 255, 255]), Word::new(false, [9, 9, 9, 0, 1]), FieldSpec::of(4, 5), Word::new(true, [0, 0, 0, 0, 0]), true),
+        (Word::new(false, [9, 9, 9, 1, 2]), Word::new(false, [9, 9, 9, 3, 4]), FieldSpec::of(4, 5), Word::new(true, [0, 0, 0, 4, 6]), false),
+    ];
+    for (word1, word2, field_specification, should_be, overflow) in cases.iter() {
+        let (result, result_overflow) = add_words(word1, word2, *field_specification);
+        println!("{} {}", result, should_be);
+        assert_eq!(result, *should_be);
+        assert_eq!(result_overflow, *overflow);
+    }
+}
+
+/// A word of `magnitude` with the sign given, so that -0 can be written.
+fn sign_magnitude(positive: bool, magnitude: i64) -> Word {
+    Word { positive, ..Word::from_value(magnitude) }
+}
+
+#[test]
+fn increments_keep_sign_of_zero_results() {
+    // (register, operation, start, delta, expected, overflow), each word as (positive, magnitude).
+    let max = (1i64 << 40) - 1;
+    let rows = [
+        ("rA", "INC", (true, 0), (true, 0), (true, 0), false),
+        ("rA", "INC", (false, 0), (true, 0), (false, 0), false),
+        ("rA", "DEC", (true, 0), (true, 0), (true, 0), false),
+        ("rA", "DEC", (false, 0), (true, 0), (false, 0), false),
+        ("rA", "INC", (false, 5), (true, 5), (false, 0), false),
+        ("rA", "DEC", (true, 5), (true, 5), (true, 0), false),
+        ("rA", "DEC", (true, 0), (true, 1), (false, 1), false),
+        ("rA", "INC", (false, 0), (true, 1), (true, 1), false),
+        ("rA", "INC", (true, 2), (true, 3), (true, 5), false),
+        ("rA", "ADD", (false, 7), (true, 7), (false, 0), false),
+        ("rA", "SUB", (true, 7), (true, 7), (true, 0), false),
+        ("rA", "SUB", (false, 7), (false, 7), (false, 0), false),
+        ("rA", "ADD", (true, max), (true, 1), (true, 0), true),
+        ("rA", "ADD", (false, max), (false, 1), (false, 0), true),
+        ("rI1", "DEC", (true, 0), (true, 1), (false, 1), false),
+        ("rI1", "DEC", (true, 0), (true, 0), (true, 0), false),
+        ("rI1", "INC", (false, 0), (true, 0), (false, 0), false),
+        ("rI1", "INC", (false, 5), (true, 5), (false, 0), false),
+        ("rI1", "DEC", (true, 5), (true, 5), (true, 0), false),
+        ("rI1", "DEC", (false, 3), (true, 4), (false, 7), false),
+        ("rI1", "INC", (true, 65534), (true, 1), (true, 65535), false),
+    ];
+    for (register, operation, start, delta, expected, overflow) in rows.iter() {
+        let mut computer = Computer::default();
+        let start = sign_magnitude(start.0, start.1);
+        let delta_word = sign_magnitude(delta.0, delta.1);
+        computer.write_mem(ADDRESS, delta_word);
+        let result = match (*register, *operation) {
+            ("rA", "INC") | ("rA", "DEC") => {
+                computer.ra = start;
+                IncA::try_new(delta.1 as usize, delta.0, *operation == "DEC").unwrap().execute_on(&mut computer).unwrap();
+                computer.ra
+            },
+            ("rA", "ADD") => {
+                computer.ra = start;
+                Add::try_new(ADDRESS, FieldSpec::WHOLE).unwrap().execute_on(&mut computer).unwrap();
+                computer.ra
+            },
+            ("rA", "SUB") => {
+                computer.ra = start;
+                Sub::try_new(ADDRESS, FieldSpec::WHOLE).unwrap().execute_on(&mut computer).unwrap();
+                computer.ra
+            },
+            _ => {
+                computer.ri1 = start;
+                IncI::try_new(1, delta.1 as usize, delta.0, *operation == "DEC").unwrap().execute_on(&mut computer).unwrap();
+                computer.ri1
+            },
+        };
+        println!("{} {} {} by {} = {}", register, operation, start, delta_word, result);
+        assert_eq!(result, sign_magnitude(expected.0, expected.1));
+        assert_eq!(computer.overflow_flag, *overflow);
+    }
+
+    // Past the two bytes of an index register the result is undefined, as for LDi, and
+    // overflow is never set.
+    let mut computer = Computer::default();
+    computer.ri1 = sign_magnitude(true, 65535);
+    let error = IncI::try_new(1, 1, true, false).unwrap().execute_on(&mut computer).unwrap_err();
+    assert_eq!(error, MixError::IndexRegisterOverflow { pc: 0, index: 1, value: Word::from_value(65536) });
+    assert_eq!(computer.ri1, sign_magnitude(true, 65535));
+    computer.undefined_policy = UndefinedPolicy::Truncate;
+    computer.ri1 = sign_magnitude(false, 65535);
+    IncI::try_new(1, 2, true, true).unwrap().execute_on(&mut computer).unwrap();
+    assert_eq!(computer.ri1, sign_magnitude(false, 1));
+    assert!(!computer.overflow_flag);
+    assert_eq!(computer.undefined_warnings.len(), 1);
+}
+
+#[test]
+fn written_bytes_tracked_through_partial_fields() {
+    let mut computer = Computer::default();
+    computer.track_written_bytes();
+    computer.ra = signed_word(-123456);
+    computer.load(0, &[
+        Word::from_instruction(2000, 0, 11, Opcode::STA.code()),    // STA 2000(1:3)
+        Word::from_instruction(2000, 0, 11, Opcode::LDA.code()),    // LDA 2000(1:3)
+        Word::from_instruction(2000, 0, 37, Opcode::LDA.code()),    // LDA 2000(4:5)
+        Word::from_instruction(2000, 0, 0, Opcode::LDX.code()),     // LDX 2000(0:0)
+        Word::from_instruction(2000, 0, 5, Opcode::CMPA.code()),    // CMPA 2000
+        Word::from_instruction(2000, 0, 0, Opcode::STZ.code()),     // STZ 2000(0:0)
+        Word::from_instruction(2000, 0, 3, Opcode::ADD.code()),     // ADD 2000(0:3)
+        Word::from_instruction(2001, 0, 5, Opcode::LD1.code()),     // LD1 2001
+    ]);
+    computer.write_mem(2001, Word::default());
+    for _ in 0..8 {
+        computer.step().unwrap();
+    }
+    for read in computer.uninitialized_reads.iter() {
+        println!("{:?}", read);
+    }
+    let reads: Vec<_> = computer.uninitialized_reads.iter().map(|read| (read.pc, read.operation.as_str(), read.address, read.positions.clone())).collect();
+    assert_eq!(reads, vec![
+        (2, "LDA", 2000, vec![4, 5]),
+        (3, "LDX", 2000, vec![0]),
+        (4, "CMPA", 2000, vec![0, 4, 5]),
+    ]);
+
+    // Without tracking nothing is recorded, and a reset keeps tracking with every word
+    // unwritten again.
+    let mut untracked = Computer::default();
+    untracked.memory[0] = Word::from_instruction(2000, 0, 5, 8);
+    untracked.step().unwrap();
+    assert!(untracked.uninitialized_reads.is_empty() && !untracked.tracks_written_bytes());
+    computer.reset();
+    assert!(computer.tracks_written_bytes() && computer.uninitialized_reads.is_empty());
+    computer.memory[0] = Word::from_instruction(1000, 0, 5, 8);
+    computer.step().unwrap();
+    assert_eq!(computer.uninitialized_reads[0].positions, vec![0, 1, 2, 3, 4, 5]);
+
+    // Words occupied when tracking starts, and words an input block fills, count as
+    // wholly written.
+    let mut computer = Computer::default();
+    computer.write_mem(1000, signed_word(5));
+    computer.track_written_bytes();
+    computer.mark_occupied(1500..1600);
+    computer.memory[0] = Word::from_instruction(1000, 0, 5, 8);
+    computer.memory[1] = Word::from_instruction(1599, 0, 5, 8);
+    computer.memory[2] = Word::from_instruction(1600, 0, 5, 8);
+    for _ in 0..3 {
+        computer.step().unwrap();
+    }
+    assert_eq!(computer.uninitialized_reads.iter().map(|read| read.address).collect::<Vec<_>>(), vec![1600]);
+}
+
+#[test]
+fn search_value_and_bytes() {
+    let (mut computer, _) = sparse_test_setup();
+    let constant = Word::from_value(31415).negate();
+    for address in [150, 2000, 3999].iter() {
+        computer.write_mem(*address, constant);
+    }
+    assert_eq!(computer.search_value(-31415, 0..DEFAULT_MEMORY_SIZE), vec![150, 2000, 3999]);
+    assert_eq!(computer.search_value(-31415, 0..3999), vec![150, 2000]);
+    assert_eq!(computer.search_value(31415, 0..DEFAULT_MEMORY_SIZE), Vec::<usize>::new());
+
+    let pattern = [None, None, None, Some(constant.bytes[3]), Some(constant.bytes[4])];
+    assert_eq!(computer.search_bytes(&pattern, Some(false), 0..DEFAULT_MEMORY_SIZE), vec![150, 2000, 3999]);
+    assert_eq!(computer.search_bytes(&pattern, Some(true), 0..DEFAULT_MEMORY_SIZE), Vec::<usize>::new());
+    let loads = [Some(7), None, Some(0), Some(5), Some(8)];
+    assert_eq!(computer.search_bytes(&loads, None, 0..DEFAULT_MEMORY_SIZE).len(), 29);
+}
+
+#[test]
+fn search_references_skips_data() {
+    let deck = "
+        START 0
+        0: +20 0 5 8      # LDA 20
+        1: +20 0 4 39     # JE 20
+        2: +20 0 0 40     # JAN 20
+        3: +0 0 2 5       # HLT
+        4: +335544320     # CON with 20 in its address part
+        5: -20 0 0 39     # JMP -20
+        20: +0 0 2 5      # HLT
+    ";
+    let program = loader::parse_raw(deck).unwrap();
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    assert_eq!(computer.memory[4].address(), 20);
+    assert_eq!(computer.search_references(20, 0..DEFAULT_MEMORY_SIZE), vec![0, 1, 2]);
+    assert_eq!(computer.search_references(20, 1..DEFAULT_MEMORY_SIZE), vec![1, 2]);
+
+    computer.source_map = None;
+    assert_eq!(computer.search_references(20, 0..DEFAULT_MEMORY_SIZE), vec![0, 1, 2, 4]);
+}
+
+#[test]
+fn searches_read_the_byte_size() {
+    let mut computer = Computer::default();
+    computer.set_byte_size(64).unwrap();
+    computer.write_mem(10, Word::from_value_in_base(64, 64));
+    computer.write_mem(11, Word::new(true, [1, 36, 0, 5, 8]));     // LDA 100, in base 64
+    assert_eq!(computer.search_value(64, 0..DEFAULT_MEMORY_SIZE), vec![10]);
+    assert_eq!(computer.search_references(100, 0..DEFAULT_MEMORY_SIZE), vec![11]);
+    assert_eq!(computer.search_references(292, 0..DEFAULT_MEMORY_SIZE), Vec::<usize>::new());
+}
+
+fn emit_block(builder: &mut ProgramBuilder, origin: i64, words: i64, first_line: usize) -> Result<(), EmitError> {
+    for i in 0..words {
+        builder.emit(origin + i, Word::from_value(i), first_line + i as usize, true)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn emitted_overlaps() {
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    emit_block(&mut builder, 100, 10, 1).unwrap();
+    emit_block(&mut builder, 105, 10, 20).unwrap();
+    assert_eq!(builder.finish(), Err(EmitError::Overlap(Overlap { address: 105, first_line: 6, second_line: 20 })));
+
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Warn);
+    emit_block(&mut builder, 100, 10, 1).unwrap();
+    emit_block(&mut builder, 105, 2, 20).unwrap();
+    let program = builder.finish().unwrap();
+    assert_eq!(program.overlaps.len(), 2);
+    assert_eq!(program.words[&105], Word::from_value(0));
+
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    emit_block(&mut builder, 100, 10, 1).unwrap();
+    emit_block(&mut builder, 110, 10, 20).unwrap();
+    assert!(builder.finish().is_ok());
+
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    assert_eq!(emit_block(&mut builder, 3995, 10, 1), Err(EmitError::OutOfMemory { address: DEFAULT_MEMORY_SIZE as i64, line: 6 }));
+}
+
+#[test]
+fn emitted_regions() {
+    let mut builder = ProgramBuilder::new(DEFAULT_MEMORY_SIZE, OverlapPolicy::Error);
+    emit_block(&mut builder, 3000, 5, 1).unwrap();
+    emit_block(&mut builder, 0, 3, 10).unwrap();
+    emit_block(&mut builder, 3005, 2, 20).unwrap();
+    emit_block(&mut builder, 1000, 1, 30).unwrap();
+    let program = builder.finish().unwrap();
+    assert_eq!(program.regions, vec![
+        EmittedRegion { range: 0..3, first_line: 10, last_line: 12 },
+        EmittedRegion { range: 1000..1001, first_line: 30, last_line: 30 },
+        EmittedRegion { range: 3000..3005, first_line: 1, last_line: 5 },
+        EmittedRegion { range: 3005..3007, first_line: 20, last_line: 21 },
+    ]);
+}
+
+// Counts the allocations made by each thread, and the bytes they hold at most, so that a
+// test can check a code path doesn't allocate regardless of what other tests are doing in
+// parallel.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        let _ = LIVE_BYTES.try_with(|live| {
+            live.set(live.get() + layout.size() as isize);
+            let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+        });
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE_BYTES.try_with(|live| live.set(live.get() - layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Runs `f`, returning what it returns and the most bytes this thread held meanwhile
+/// beyond what it held before.
+#[cfg(any(all(feature = "assembler", feature = "debugger"), feature = "analysis"))]
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = LIVE_BYTES.with(|live| live.get());
+    PEAK_BYTES.with(|peak| peak.set(before));
+    let result = f();
+    let peak = PEAK_BYTES.with(|peak| peak.get());
+    (result, (peak - before) as usize)
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn block_transfers_do_not_allocate() {
+    let mut computer = Computer::default();
+    computer.load(0, &tape_block(1));
+    let mut tape = MagneticTapeUnit::new(0, tape_block(0));
+    let channel = crate::peripherals::Channel::new(1);
+    let mut producer = crate::peripherals::ChannelEnd::producer(&channel);
+    let mut consumer = crate::peripherals::ChannelEnd::consumer(&channel);
+    producer.write_block(&computer.memory[0..100]).unwrap();
+    consumer.read_block_into(&mut computer.memory[100..200], 256).unwrap();
+
+    let before = allocations();
+    for _ in 0..10000 {
+        Peripheral::write_block(&mut tape, &computer.memory[0..100]).unwrap();
+        tape.rewind();
+        Peripheral::read_block_into(&mut tape, &mut computer.memory[200..300], 256).unwrap();
+        tape.rewind();
+        producer.write_block(&computer.memory[200..300]).unwrap();
+        consumer.read_block_into(&mut computer.memory[300..400], 256).unwrap();
+    }
+    assert_eq!(allocations(), before);
+    assert_eq!(computer.memory[300..400], tape_block(1)[..]);
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn failed_block_read_leaves_memory_untouched() {
+    let mut block = [Word::default(); BLOCK_WORDS];
+    for (i, word) in block.iter_mut().enumerate() {
+        *word = Word::new(true, [0, 0, 0, 1, (i % 64) as u8]);
+    }
+    block[BLOCK_WORDS - 1] = Word::new(true, [0, 0, 0, 0, 200]);
+    let mut tape = MagneticTapeUnit::new(0, block);
+    let mut memory = [Word::new(false, [1, 1, 1, 1, 1]); BLOCK_WORDS];
+    let error = Peripheral::read_block_into(&mut tape, &mut memory, 64).unwrap_err();
+    println!("{}", error);
+    match error {
+        TapeError::InvalidWord { index, .. } => assert_eq!(index, BLOCK_WORDS - 1),
+        other => panic!("Expected an invalid word, got {:?}", other),
+    }
+    assert!(memory.iter().all(|word| *word == Word::new(false, [1, 1, 1, 1, 1])));
+    assert_eq!(tape.position(), 0);
+
+    let channel = crate::peripherals::Channel::new(1);
+    let mut producer = crate::peripherals::ChannelEnd::producer(&channel);
+    let mut consumer = crate::peripherals::ChannelEnd::consumer(&channel);
+    producer.write_block(&block).unwrap();
+    assert!(consumer.read_block_into(&mut memory, 64).is_err());
+    assert!(memory.iter().all(|word| *word == Word::new(false, [1, 1, 1, 1, 1])));
+    assert!(consumer.read_block_into(&mut memory, 256).is_ok());
+    assert_eq!(memory, block);
+}
+
+/// A word with byte 70 at position 3, too large for 64-value bytes, and -0, which is
+/// valid and must come through as it is.
+#[cfg(feature = "peripherals-file")]
+fn malformed_words() -> [Word; 2] {
+    [Word::new(true, [1, 2, 70, 3, 4]), Word::new(false, [0; 5])]
+}
+
+/// Every loader reading words as bytes, each loading `malformed_words` under a policy
+/// from its format and giving back what it made of them.
+#[cfg(feature = "peripherals-file")]
+fn ingestion_points() -> Vec<(&'static str, fn(IngestPolicy) -> Result<Vec<Word>, String>)> {
+    fn image(format: u8, policy: IngestPolicy) -> Result<Vec<Word>, String> {
+        let mut image = MachineImage::of(&Computer::default());
+        image.memory[10..12].copy_from_slice(&malformed_words());
+        let image = MachineImage::from_bytes_with(&image.to_bytes(format), policy, 64).map_err(|error| error.to_string())?;
+        Ok(image.memory[10..12].to_vec())
+    }
+    fn read(mut tape: MagneticTapeUnit, policy: IngestPolicy) -> Result<Vec<Word>, String> {
+        tape.ingest_policy = policy;
+        let mut block = [Word::default(); BLOCK_WORDS];
+        Peripheral::read_block_into(&mut tape, &mut block, 64).map_err(|error| error.to_string())?;
+        Ok(block[10..12].to_vec())
+    }
+    fn block() -> [Word; BLOCK_WORDS] {
+        let mut block = [Word::default(); BLOCK_WORDS];
+        block[10..12].copy_from_slice(&malformed_words());
+        block
+    }
+    vec![
+        ("plain image", |policy| image(FORMAT_PLAIN, policy)),
+        ("compressed image", |policy| image(FORMAT_COMPRESSED, policy)),
+        ("image registers", |policy| {
+            let mut image = MachineImage::of(&Computer::default());
+            image.registers[1..3].copy_from_slice(&malformed_words());
+            let image = MachineImage::from_bytes_with(&image.to_bytes(FORMAT_PLAIN), policy, 64).map_err(|error| error.to_string())?;
+            Ok(image.registers[1..3].to_vec())
+        }),
+        ("tape in memory", |policy| read(MagneticTapeUnit::new(0, block()), policy)),
+        ("tape file", |policy| {
+            let mut tape = MagneticTapeUnit::from_stream(0, std::io::Cursor::new(Vec::new()));
+            tape.write_block(&block()).unwrap();
+            tape.rewind();
+            read(tape, policy)
+        }),
+    ]
+}
+
+#[cfg(feature = "peripherals-file")]
+#[test]
+fn every_loader_ingests_through_the_gate() {
+    let [bad, negative_zero] = malformed_words();
+    for (name, load) in ingestion_points() {
+        let error = load(IngestPolicy::Reject).map(|words| panic!("{} let {:?} in", name, words)).unwrap_err();
+        println!("{}: {}", name, error);
+        assert!(error.contains(" 70 "), "{}: {}", name, error);
+        assert_eq!(load(IngestPolicy::ClampBytes), Ok(vec![Word::new(true, [1, 2, 63, 3, 4]), negative_zero]), "{}", name);
+        assert_eq!(load(IngestPolicy::ZeroInvalid), Ok(vec![Word::default(), negative_zero]), "{}", name);
+    }
+
+    let mut image = MachineImage::of(&Computer::default());
+    image.memory[10] = bad;
+    assert_eq!(MachineImage::from_bytes(&image.to_bytes(FORMAT_COMPRESSED)).unwrap().memory[10], bad);
+    let error = MachineImage::from_bytes_with(&image.to_bytes(FORMAT_COMPRESSED), IngestPolicy::Reject, 64).unwrap_err();
+    let expected = IngestError { index: 10, word: bad, position: 3, byte: 70, byte_size: 64 };
+    assert_eq!(error, ImageError::InvalidWord { section: "memory", error: expected.clone() });
+    assert_eq!(sanitize::check_words(&[Word::default(); 10].iter().copied().chain([bad]).collect::<Vec<_>>(), 64),
+               Err(IngestError { index: 10, ..expected }));
+}
+
+#[test]
+fn ingestion_gate_policies() {
+    let words = vec![Word::new(false, [63, 64, 0, 255, 1]), Word::new(false, [0, 0, 0, 0, 5]), Word::new(true, [0, 0, 0, 0, 64])];
+    let error = sanitize::ingest_words(words.clone(), IngestPolicy::Reject, 64).unwrap_err();
+    assert_eq!((error.index, error.position, error.byte), (0, 2, 64));
+    assert_eq!(error.to_string(), "Word 0 has byte 64 at position 2, but bytes must be below 64");
+    assert_eq!(sanitize::ingest_words(words.clone(), IngestPolicy::ClampBytes, 64).unwrap(),
+               vec![Word::new(false, [63, 63, 0, 63, 1]), words[1], Word::new(true, [0, 0, 0, 0, 63])]);
+    assert_eq!(sanitize::ingest_words(words.clone(), IngestPolicy::ZeroInvalid, 64).unwrap(),
+               vec![Word::default(), words[1], Word::default()]);
+    assert_eq!(sanitize::ingest_words(words.clone(), IngestPolicy::Reject, 256), Ok(words.clone()));
+
+    // A rejected block leaves memory as it was.
+    let mut memory = words.clone();
+    assert!(sanitize::ingest_in_place(&mut memory, IngestPolicy::Reject, 64).is_err());
+    assert_eq!(memory, words);
+}
+
+#[test]
+#[cfg(all(feature = "debugger", feature = "json"))]
+fn run_report_round_trip() {
+    let mut computer = Computer::default();
+    loader::parse_raw(RAW_DECK).unwrap().load_into(&mut computer);
+    computer.run().unwrap();
+    let report = RunReport::from_computer(&computer);
+    let json = report.to_json();
+    println!("{}", json);
+    assert_eq!(RunReport::from_json(&json).unwrap(), report);
+    assert_eq!(report.schema_version, SCHEMA_VERSION);
+    assert!(report.halted);
+    assert_eq!(report.time, computer.time());
+    assert_eq!(report.registers["rA"].value, 1000);
+    assert_eq!(report.registers["rA"].bytes, [0, 0, 0, 3, 232]);
+    assert_eq!(report.registers["rI1"].value, 3);
+    assert_eq!(report.comparison, "equal");
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    for field in ["schema_version", "halted", "pc", "time", "registers", "overflow", "comparison", "printer", "warnings"].iter() {
+        assert!(value.get(field).is_some(), "missing {}", field);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "debugger", feature = "json"))]
+fn fault_report_for_parse_error() {
+    let error = loader::parse_raw("START 0\n0: +0 0 5 ?\n").unwrap_err();
+    let report = FaultReport::from_parse_error(&error);
+    let json = report.to_json();
+    println!("{}", json);
+    assert_eq!(FaultReport::from_json(&json).unwrap(), report);
+    assert_eq!(report.stage, "assemble");
+    assert_eq!(report.pc, None);
+    assert_eq!(report.errors[0].code, "bad-number");
+    assert_eq!(report.errors[0].line, Some(2));
+}
+
+#[test]
+#[cfg(all(feature = "debugger", feature = "json"))]
+fn fault_report_for_run_error() {
+    let mut computer = Computer::default();
+    loader::parse_raw("START 0\n0: +1000 0 18 37 # OUT 1000(18)\n").unwrap().load_into(&mut computer);
+    let error = computer.run().unwrap_err();
+    let report = FaultReport::from_run_error(&error);
+    let json = report.to_json();
+    println!("{}", json);
+    assert_eq!(FaultReport::from_json(&json).unwrap(), report);
+    assert_eq!(report.stage, "run");
+    assert_eq!(report.pc, Some(0));
+    assert_eq!(report.errors[0].code, "unit-not-attached");
+    assert_eq!(report.errors[0].line, None);
+}
+
+#[test]
+#[cfg(all(feature = "debugger", feature = "json"))]
+fn run_report_v1_fixture() {
+    let report = RunReport::from_json(include_str!("../fixtures/run_report_v1.json")).unwrap();
+    assert_eq!(report.schema_version, 1);
+    assert_eq!(report.registers["rA"].value, 1000);
+    assert_eq!(report.registers["rA"].bytes, [0, 0, 0, 3, 232]);
+    let report = FaultReport::from_json(include_str!("../fixtures/fault_report_v1.json")).unwrap();
+    assert_eq!(report.schema_version, 1);
+    assert_eq!(report.errors[0].code, "unit-not-attached");
+}
+
+#[test]
+#[cfg(feature = "mixb")]
+fn mixb_shift_across_registers() {
+    let mut computer = Computer::default();
+    mixb::register_in_base(&mut computer, 64).unwrap();
+    computer.memory[0] = Word::from_instruction(3, 0, 6, Opcode::SHIFT.code());    // SLB 3
+    computer.memory[1] = Word::from_instruction(3, 0, 7, Opcode::SHIFT.code());    // SRB 3
+    computer.ra = Word::new(true, [0, 0, 0, 0, 1]);
+    computer.rx = Word::new(false, [63, 0, 0, 0, 0]);
+    computer.step().unwrap();
+    println!("{} {}", computer.ra, computer.rx);
+    assert_eq!(computer.ra, Word::new(true, [0, 0, 0, 0, 15]));
+    assert_eq!(computer.rx, Word::new(false, [56, 0, 0, 0, 0]));
+    computer.step().unwrap();
+    assert_eq!(computer.ra, Word::new(true, [0, 0, 0, 0, 1]));
+    assert_eq!(computer.rx, Word::new(false, [63, 0, 0, 0, 0]));
+}
+
+#[test]
+#[cfg(feature = "mixb")]
+fn mixb_mask_extracts_field() {
+    let mut computer = Computer::default();
+    mixb::register(&mut computer).unwrap();
+    let original = Word::new(false, [12, 34, 56, 78, 90]);
+    computer.memory[0] = Word::from_instruction(1000, 0, 10, Opcode::SPECIAL.code());    // AND 1000
+    computer.memory[1] = Word::from_instruction(16, 0, 7, Opcode::SHIFT.code());         // SRB 16
+    computer.memory[2] = Word::from_instruction(1001, 0, 11, Opcode::SPECIAL.code());    // OR 1001
+    computer.memory[3] = Word::from_instruction(1001, 0, 12, Opcode::SPECIAL.code());    // XOR 1001
+    computer.memory[1000] = Word::new(true, [0, 255, 255, 0, 0]);
+    computer.memory[1001] = Word::new(true, [1, 0, 0, 0, 0]);
+    computer.ra = original;
+    computer.step().unwrap();
+    computer.step().unwrap();
+    println!("{}", computer.ra);
+    assert_eq!(computer.ra.field_value(FieldSpec::MAGNITUDE), original.field_value(FieldSpec::of(2, 3)));
+    assert!(!computer.ra.positive);
+    computer.step().unwrap();
+    assert_eq!(computer.ra, Word::new(false, [1, 0, 0, 34, 56]));
+    computer.step().unwrap();
+    assert_eq!(computer.ra, Word::new(false, [0, 0, 0, 34, 56]));
+}
+
+#[test]
+#[cfg(feature = "mixb")]
+fn mixb_decimal_machine() {
+    let mut computer = Computer::default();
+    assert_eq!(mixb::register_in_base(&mut computer, 100), Err(MixError::UnsupportedByteSize { byte_size: 100 }));
+    computer.unimplemented_policy = UnimplementedPolicy::Fault;
+    computer.memory[0] = Word::from_instruction(3, 0, 6, Opcode::SHIFT.code());    // SLB 3
+    assert!(computer.run().is_err());
+    assert_eq!(Word::new(true, [1, 2, 3, 4, 5]).to_bits(8), 0x0102030405);
+    assert_eq!(Word::from_bits(false, 0x0102030405, 8), Word::new(false, [1, 2, 3, 4, 5]));
+}
+
+/// A computer printing the block at 1000 in a loop, `times` times or forever when `None`,
+/// to a printer that keeps every line.
+#[cfg(feature = "peripherals")]
+fn print_loop(times: Option<u8>) -> (Computer, Rc<RefCell<Channel>>) {
+    let mut computer = Computer::default();
+    let printer = Channel::new(1000);
+    computer.attach(PRINTER_UNIT, ChannelEnd::producer(&printer));
+    computer.memory[0] = Word::from_instruction(times.unwrap_or(0) as i64, 0, 2, 49);    // ENT1 times
+    computer.memory[1] = Word::from_instruction(1000, 0, 18, Opcode::OUT.code());    // OUT 1000(18)
+    computer.memory[2] = match times {
+        Some(_) => Word::from_instruction(1, 0, 1, Opcode::ENT1.code()),    // DEC1 1
+        None => Word::from_instruction(0, 0, 0, Opcode::NOP.code()),        // NOP
+    };
+    computer.memory[3] = match times {
+        Some(_) => Word::from_instruction(1, 0, 2, Opcode::J1.code()),    // J1P 1
+        None => Word::from_instruction(1, 0, 0, Opcode::JMP.code()),      // JMP 1
+    };
+    computer.memory[4] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    (computer, printer)
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn output_limit_stops_print_loop() {
+    let (mut computer, printer) = print_loop(None);
+    computer.output_limits.max_printed_lines = Some(5);
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::OutputLimitExceeded { pc: 1, unit: 18, limit: 5 });
+    assert_eq!(printer.borrow().len(), 5);
+    assert_eq!(computer.blocks_written(PRINTER_UNIT), 5);
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn output_unlimited_by_default() {
+    let (mut computer, printer) = print_loop(None);
+    assert_eq!(computer.output_limits, OutputLimits::unlimited());
+    for _ in 0..600 {
+        computer.step().unwrap();
+    }
+    // The 200th block is still moving, and reaches the printer when it completes.
+    assert_eq!(computer.blocks_written(PRINTER_UNIT), 200);
+    assert_eq!(printer.borrow().len(), 199);
+    assert!(!computer.is_halted());
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn output_limit_reached_exactly() {
+    let (mut computer, printer) = print_loop(Some(3));
+    computer.output_limits = OutputLimits { max_printed_lines: Some(3), ..OutputLimits::batch() };
+    computer.run().unwrap();
+    assert_eq!(printer.borrow().len(), 3);
+    computer.reset();
+    assert_eq!(computer.blocks_written(PRINTER_UNIT), 0);
+    assert_eq!(computer.output_limits.max_printed_lines, Some(3));
+}
+
+// Conformance cases, written as data in the files under tests/spec. Each case sets up a
+// fresh computer, runs a single instruction, and checks the state it leaves behind:
+//
+//     case lda_field_1_5          names the case; the lines up to the next case belong to it
+//     set 2000 - 1 2 3 4 5        sets a word: rA, rX, rI1 to rI6, rJ, or an address
+//     set rA -1000                words are given as sign and five bytes, or a signed value
+//     set overflow on             the flags: overflow on/off, comparison less/equal/greater
+//     set pc 20                   where the instruction goes, 0 unless given
+//     do LDA 2000(1:5)            the instruction, in the form read by opcodes::parse_one
+//     want rA + 1 2 3 4 5         the expected state, given like set
+//     want time 2                 the expected time taken
+//     want fault unit-not-attached    the expected MixError::code
+//     want state halted           the expected MachineState, running or halted
+//
+// Everything the case doesn't `want` must be left as it was set, except the pc, which is
+// only checked when wanted. `#` starts a comment.
+const SPEC_FILES: [(&str, &str); 6] = [
+    ("load.spec", include_str!("../tests/spec/load.spec")),
+    ("store.spec", include_str!("../tests/spec/store.spec")),
+    ("arithmetic.spec", include_str!("../tests/spec/arithmetic.spec")),
+    ("address.spec", include_str!("../tests/spec/address.spec")),
+    ("compare.spec", include_str!("../tests/spec/compare.spec")),
+    ("shift.spec", include_str!("../tests/spec/shift.spec")),
+];
+
+struct SpecCase {
+    name: String,
+    location: String,
+    sets: Vec<(String, String)>,
+    statement: Option<String>,
+    wants: Vec<(String, String)>,
+}
+
+fn parse_spec(file: &str, text: &str) -> Result<Vec<SpecCase>, String> {
+    let mut cases: Vec<SpecCase> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let location = format!("{}:{}", file, i + 1);
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (keyword, rest) = line.split_at(line.find(' ').unwrap_or(line.len()));
+        let rest = rest.trim();
+        if keyword == "case" {
+            cases.push(SpecCase { name: rest.to_string(), location, sets: Vec::new(), statement: None, wants: Vec::new() });
+            continue;
+        }
+        let case = cases.last_mut().ok_or_else(|| format!("{}: {} before the first case", location, keyword))?;
+        let (target, value) = rest.split_at(rest.find(' ').unwrap_or(rest.len()));
+        let entry = (target.to_string(), value.trim().to_string());
+        match keyword {
+            "set" => case.sets.push(entry),
+            "want" => case.wants.push(entry),
+            "do" => case.statement = Some(rest.to_string()),
+            _ => return Err(format!("{}: unknown keyword {}", location, keyword)),
+        }
+    }
+    Ok(cases)
+}
+
+fn parse_spec_word(text: &str) -> Result<Word, String> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let number = |part: &str| part.parse::<i64>().map_err(|_| format!("{} is not a number", part));
+    match parts.as_slice() {
+        [sign, bytes @ ..] if bytes.len() == 5 && (*sign == "+" || *sign == "-") => {
+            let mut word = Word::new(*sign == "+", [0; 5]);
+            for (byte, part) in word.bytes.iter_mut().zip(bytes) {
+                *byte = number(part)? as u8;
+            }
+            Ok(word)
+        },
+        [value] if value.starts_with('+') || value.starts_with('-') => {
+            let mut word = Word::from_value(number(&value[1..])?);
+            word.positive = value.starts_with('+');
+            Ok(word)
+        },
+        _ => Err(format!("{} is not a word", text)),
+    }
+}
+
+fn spec_word<'a>(computer: &'a mut Computer, target: &str) -> Option<&'a mut Word> {
+    match target {
+        "rA" => Some(&mut computer.ra),
+        "rX" => Some(&mut computer.rx),
+        "rI1" => Some(&mut computer.ri1),
+        "rI2" => Some(&mut computer.ri2),
+        "rI3" => Some(&mut computer.ri3),
+        "rI4" => Some(&mut computer.ri4),
+        "rI5" => Some(&mut computer.ri5),
+        "rI6" => Some(&mut computer.ri6),
+        "rJ" => Some(&mut computer.rj),
+        _ => target.parse::<usize>().ok().filter(|address| *address < computer.memory_size()).map(move |address| &mut computer.memory[address]),
+    }
+}
+
+fn apply_spec_entry(computer: &mut Computer, target: &str, value: &str) -> Result<(), String> {
+    match target {
+        "overflow" => computer.overflow_flag = match value {
+            "on" => true,
+            "off" => false,
+            _ => return Err(format!("overflow {} is not on or off", value)),
+        },
+        "comparison" => computer.comparison_flag = match value {
+            "less" => ComparisonFlag::less,
+            "equal" => ComparisonFlag::equal,
+            "greater" => ComparisonFlag::greater,
+            _ => return Err(format!("comparison {} is not less, equal, or greater", value)),
+        },
+        "pc" => computer.pc = value.parse().map_err(|_| format!("pc {} is not a number", value))?,
+        _ => *spec_word(computer, target).ok_or_else(|| format!("{} is not a register or address", target))? = parse_spec_word(value)?,
+    }
+    Ok(())
+}
+
+/// Lists every register, flag, and word of memory where `actual` differs from `expected`.
+fn state_diff(actual: &Computer, expected: &Computer) -> Vec<String> {
+    let mut diff = Vec::new();
+    let registers = [
+        ("rA", actual.ra, expected.ra), ("rX", actual.rx, expected.rx),
+        ("rI1", actual.ri1, expected.ri1), ("rI2", actual.ri2, expected.ri2), ("rI3", actual.ri3, expected.ri3),
+        ("rI4", actual.ri4, expected.ri4), ("rI5", actual.ri5, expected.ri5), ("rI6", actual.ri6, expected.ri6),
+        ("rJ", actual.rj, expected.rj),
+    ];
+    for (name, actual, expected) in registers.iter() {
+        if actual != expected {
+            diff.push(format!("{}: expected [{}], found [{}]", name, expected, actual));
+        }
+    }
+    if actual.overflow_flag != expected.overflow_flag {
+        diff.push(format!("overflow: expected {}, found {}", expected.overflow_flag, actual.overflow_flag));
+    }
+    if actual.comparison_flag != expected.comparison_flag {
+        diff.push(format!("comparison: expected {}, found {}", expected.comparison_flag, actual.comparison_flag));
+    }
+    for address in 0..actual.memory_size() {
+        if actual.memory[address] != expected.memory[address] {
+            diff.push(format!("{:04}: expected [{}], found [{}]", address, expected.memory[address], actual.memory[address]));
+        }
+    }
+    diff
+}
+
+fn run_spec_case(case: &SpecCase) -> Result<(), Vec<String>> {
+    let mut computer = Computer::default();
+    computer.set_cost_model(CostModel::knuth());
+    for (target, value) in case.sets.iter() {
+        apply_spec_entry(&mut computer, target, value).map_err(|error| vec![error])?;
+    }
+    let statement = case.statement.as_ref().ok_or_else(|| vec!["no instruction given".to_string()])?;
+    let instruction = opcodes::parse_one(statement).map_err(|error| vec![error.to_string()])?;
+    let pc = computer.pc;
+    computer.memory[pc] = instruction;
+
+    let mut expected = computer.clone();
+    let (mut time, mut fault, mut state) = (None, None, None);
+    for (target, value) in case.wants.iter() {
+        match target.as_str() {
+            "time" => time = Some(value.parse::<u64>().map_err(|_| vec![format!("time {} is not a number", value)])?),
+            "state" => state = Some(match value.as_str() {
+                "running" => MachineState::Running,
+                "halted" => MachineState::Halted,
+                _ => return Err(vec![format!("state {} is not running or halted", value)]),
+            }),
+            "fault" => fault = Some(value.clone()),
+            _ => apply_spec_entry(&mut expected, target, value).map_err(|error| vec![error])?,
+        }
+    }
+
+    let result = computer.step();
+    let mut diff = state_diff(&computer, &expected);
+    match (&result, &fault) {
+        (Err(error), None) => diff.push(format!("fault: expected none, found {}", error.code())),
+        (Err(error), Some(code)) if error.code() != code => diff.push(format!("fault: expected {}, found {}", code, error.code())),
+        (Ok(()), Some(code)) => diff.push(format!("fault: expected {}, found none", code)),
+        _ => {},
+    }
+    if case.wants.iter().any(|(target, _)| target == "pc") && computer.pc != expected.pc {
+        diff.push(format!("pc: expected {}, found {}", expected.pc, computer.pc));
+    }
+    if let Some(state) = state {
+        if computer.state() != state {
+            diff.push(format!("state: expected {:?}, found {:?}", state, computer.state()));
+        }
+    }
+    if let Some(time) = time {
+        if computer.time() != time {
+            diff.push(format!("time: expected {}, found {}", time, computer.time()));
+        }
+    }
+    if diff.is_empty() { Ok(()) } else { Err(diff) }
+}
+
+#[test]
+fn spec_cases() {
+    let mut failures = Vec::new();
+    let mut names = std::collections::BTreeSet::new();
+    for (file, text) in SPEC_FILES.iter() {
+        let cases = parse_spec(file, text).unwrap();
+        for case in cases.iter() {
+            assert!(names.insert(case.name.clone()), "{}: case {} is given twice", case.location, case.name);
+            if let Err(diff) = run_spec_case(case) {
+                failures.push(format!("{} {}:\n    {}", case.location, case.name, diff.join("\n    ")));
+            }
+        }
+    }
+    println!("{} cases, {} failed", names.len(), failures.len());
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn charset_round_trips() {
+    let codes: Vec<u8> = (0..charset::CHARACTER_CODES as u8).collect();
+    for mapping in [CharsetMapping::mdk(), CharsetMapping::unicode()].iter() {
+        let text = mapping.decode(&codes).unwrap();
+        println!("{}", text);
+        assert_eq!(text.chars().count(), charset::CHARACTER_CODES);
+        assert_eq!(mapping.encode(&text).unwrap(), codes);
+    }
+    let ascii: Vec<u8> = codes.iter().copied().filter(|code| ![charset::DELTA, charset::SIGMA, charset::PI].contains(code)).collect();
+    let strict = CharsetMapping::strict_ascii();
+    assert_eq!(strict.encode(&strict.decode(&ascii).unwrap()).unwrap(), ascii);
+}
+
+#[test]
+fn charset_alf_same_words() {
+    let mdk = Word::from_chars("{A}#9", &CharsetMapping::mdk()).unwrap();
+    let unicode = Word::from_chars("ΔAΣΠ9", &CharsetMapping::unicode()).unwrap();
+    assert_eq!(mdk, unicode);
+    assert_eq!(mdk, Word::new(true, [10, 1, 20, 21, 39]));
+    assert_eq!(Word::from_chars("AB", &CharsetMapping::mdk()).unwrap(), Word::new(true, [1, 2, 0, 0, 0]));
+    assert_eq!(Word::from_chars("TOO LONG", &CharsetMapping::mdk()), Err(CharsetError::TooLong(8)));
+}
+
+#[test]
+fn charset_printing_delta() {
+    let word = Word::new(true, [8, 9, 10, 0, 1]);
+    assert_eq!(word.as_chars(&CharsetMapping::mdk()).unwrap(), "HI{ A");
+    assert_eq!(word.as_chars(&CharsetMapping::unicode()).unwrap(), "HIΔ A");
+    let error = word.as_chars(&CharsetMapping::strict_ascii()).unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, CharsetError::Unrepresentable { code: 10, position: 2 });
+    assert_eq!(Word::new(true, [0, 56, 0, 0, 0]).as_chars(&CharsetMapping::unicode()),
+        Err(CharsetError::InvalidCode { code: 56, position: 1 }));
+    assert_eq!(CharsetMapping::strict_ascii().encode("AΔ"), Err(CharsetError::UnknownCharacter { character: 'Δ', position: 1 }));
+}
+
+#[cfg(feature = "assembler")]
+const ASSEMBLY_SAMPLE: &str = "* Sums the table\n\
+START\tENTA\t0\t\tclear the sum — rA ← 0\n\
+LOOP\tADD\tTABLE,1\tadd an entry ✓\n\
+\tDEC1\t1\n\
+\tJ1P\tLOOP\t\tloop back\n\
+\tJMP\tLOOP\n\
+\tJANZ\tLOOP\n\
+\tHLT\n\
+TABLE\tCON\t7\n\
+\tEND\tSTART\n";
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_spans_byte_accurate() {
+    let state = AssemblyState::new(ASSEMBLY_SAMPLE);
+    assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+    let span_of = |text: &str, from: usize| {
+        let start = ASSEMBLY_SAMPLE[from..].find(text).unwrap() + from;
+        Span::new(start, start + text.len())
+    };
+    let table = state.symbols.get("TABLE").unwrap();
+    let start = span_of("TABLE\tCON", 0).start;
+    assert_eq!(table.definition, Span::new(start, start + 5));
+    assert_eq!(table.line, 9);
+    assert_eq!(table.value, 7);
+    let add = span_of("TABLE,1", 0);
+    assert_eq!(&ASSEMBLY_SAMPLE[add.start..add.start + 5], "TABLE");
+
+    // A bad operand after a line with wide characters in its remarks.
+    let source = ASSEMBLY_SAMPLE.replace("\tDEC1\t1", "\tDEC1\t1+?");
+    let error = assemble(&source).unwrap_err();
+    println!("{}", error);
+    assert_eq!(error.line, 4);
+    let question = source.find('?').unwrap();
+    assert_eq!(error.span, Span::new(question, question + 1));
+    let error = assemble(&ASSEMBLY_SAMPLE.replace("\tHLT", "\tHLT\tNOWHERE")).unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::UndefinedSymbol("NOWHERE".to_string()));
+    assert_eq!(&ASSEMBLY_SAMPLE.replace("\tHLT", "\tHLT\tNOWHERE")[error.span.start..error.span.end], "NOWHERE");
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_references_and_lookup() {
+    let state = AssemblyState::new(ASSEMBLY_SAMPLE);
+    let references = state.symbols.references("LOOP");
+    assert_eq!(references.len(), 3);
+    for span in references.iter() {
+        assert_eq!(&ASSEMBLY_SAMPLE[span.start..span.end], "LOOP");
+        assert_eq!(state.symbols.lookup_at(span.start + 2).unwrap().name, "LOOP");
+    }
+    let definition = state.symbols.get("LOOP").unwrap().definition;
+    assert!(!references.contains(&definition));
+    assert_eq!(state.symbols.lookup_at(definition.start).unwrap().value, 1);
+    assert_eq!(state.symbols.lookup_at(ASSEMBLY_SAMPLE.find("HLT").unwrap()), None);
+
+    let program = state.program.unwrap();
+    assert_eq!(program.start, 0);
+    assert_eq!(program.words[&1], opcodes::encode("ADD", 7, 1, None).unwrap());
+    assert_eq!(program.words[&7], Word::new(true, [0, 0, 0, 0, 7]));
+    assert!(program.source_map.data.contains(&7));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_incremental_matches_full() {
+    let edit = |source: &str, find: &str, text: &str| {
+        let start = source.find(find).unwrap();
+        TextEdit { range: start..start + find.len(), text: text.to_string() }
+    };
+    let mut state = AssemblyState::new(ASSEMBLY_SAMPLE);
+    let script: Vec<(&str, &str)> = vec![
+        ("ENTA\t0", "ENTA\t1"), ("TABLE,1", "TABLE"), ("TABLE\tCON\t7", "TABLE\tCON\t-7"),
+        ("\tHLT\n", "\tHLT\n\tNOP\n"), ("LOOP\tADD", "LOOPX\tADD"), ("LOOPX\tADD", "LOOP\tADD"),
+        ("DEC1\t1", "DEC1\t1+"), ("DEC1\t1+", "DEC1\t2"), ("* Sums", "* Adds up"),
+        ("\tJMP\tLOOP\n", ""), ("START\t", "BEGIN\t"), ("END\tSTART", "END\tBEGIN"),
+        ("\tNOP\n", "\tNOP\n\tNOP\n\tNOP\n"), ("CON\t-7", "CON\t1:5"), ("\tEND", "X\tEND"),
+        ("X\tEND", "\tEND"), ("ENTA\t1", "ENTA\t1\r"), ("ENTA\t1\r", "ENTA\t*+1"),
+        ("\tNOP\n\tNOP\n\tNOP\n", "\tORIG\t*+10\n"), ("BEGIN", "START\tNOP\nBEGIN"),
+    ];
+    assert_eq!(script.len(), 20);
+    for (find, text) in script {
+        let edit = edit(state.source(), find, text);
+        state = assemble_incremental(&state, edit);
+        let full = AssemblyState::new(state.source());
+        println!("{:?} -> {:?}: {} of {} lines parsed, {} diagnostics", find, text,
+                 state.lines_parsed(), full.lines_parsed(), full.diagnostics.len());
+        assert_eq!(state.diagnostics, full.diagnostics);
+        assert_eq!(state.program, full.program);
+        assert_eq!(state.symbols, full.symbols);
+        assert!(state.lines_parsed() <= 4);
+    }
+}
+
+#[cfg(feature = "assembler")]
+fn assembly_benchmark_source() -> String {
+    let mut source = String::from("\tORIG\t100\n");
+    for i in 0..998 {
+        source.push_str(&format!("L{}\tLDA\tL{},1(1:5)\tline {} of the benchmark\n", i, (i * 7) % 998, i));
+    }
+    source.push_str("\tEND\tL0");
+    source
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+#[ignore]
+fn assembler_incremental_benchmark() {
+    let source = assembly_benchmark_source();
+    let state = AssemblyState::new(&source);
+    assert!(state.diagnostics.is_empty());
+    let runs = 200;
+    let start = std::time::Instant::now();
+    for _ in 0..runs {
+        AssemblyState::new(&source);
+    }
+    let full = start.elapsed() / runs;
+    let offset = source.find("L500\tLDA\tL").unwrap() + "L500\tLDA\t".len();
+    let start = std::time::Instant::now();
+    for _ in 0..runs {
+        assemble_incremental(&state, TextEdit { range: offset..offset + 1, text: "L".to_string() });
+    }
+    let incremental = start.elapsed() / runs;
+    println!("1,000 lines: full {:?}, incremental {:?} per single-line edit", full, incremental);
+    assert!(incremental < full);
+}
+
+#[test]
+fn pc_jump_past_memory_faults() {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(DEFAULT_MEMORY_SIZE as i64, 0, 0, Opcode::JMP.code());    // JMP just past memory
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert!(matches!(error, MixError::ProgramCounterOutOfRange { memory_size: DEFAULT_MEMORY_SIZE, .. }));
+    assert_eq!(error.code(), "program-counter-out-of-range");
+    assert!(error.pc().unwrap() >= DEFAULT_MEMORY_SIZE);
+    assert_eq!(computer.state(), MachineState::Running);
+}
+
+#[test]
+fn pc_larger_memory_runs_high_program() {
+    let mut computer = Computer::with_memory_size(5000);
+    let program = [
+        Word::from_instruction(4900, 0, 5, Opcode::LDA.code()),     // LDA 4900
+        Word::from_instruction(4901, 0, 5, Opcode::STA.code()),     // STA 4901
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ];
+    computer.load(4500, &program);
+    computer.write_mem(4900, Word::new(true, [0, 0, 0, 1, 2]));
+    computer.pc = 4500;
+    computer.run().unwrap();
+    assert!(computer.is_halted());
+    assert_eq!(computer.pc, 4503);
+    assert_eq!(computer.memory[4901], Word::new(true, [0, 0, 0, 1, 2]));
+    computer.reset();
+    assert_eq!(computer.memory_size(), 5000);
+}
+
+const FACTORIAL_STACK: &str = include_str!("../fixtures/factorial_stack.raw");
+
+fn stack_factorial(region: Option<std::ops::Range<usize>>) -> (Computer, Result<(), MixError>) {
+    let mut computer = Computer::default();
+    computer.unimplemented_policy = UnimplementedPolicy::Fault;
+    if let Some(region) = region {
+        stack::register(&mut computer, region).unwrap();
+    }
+    loader::parse_raw(FACTORIAL_STACK).unwrap().load_into(&mut computer);
+    let result = computer.run();
+    (computer, result)
+}
+
+#[test]
+fn stack_recursive_factorial() {
+    let (computer, result) = stack_factorial(Some(300..310));
+    result.unwrap();
+    assert_eq!(computer.memory[200], Word::new(true, [0, 0, 0, 0, 120]));
+    assert_eq!(computer.ri6, Word::new(true, [0, 0, 0, 1, 53]));
+    assert_eq!(computer.memory[300], Word::new(true, [0, 0, 0, 0, 1]));
+}
+
+#[test]
+fn stack_overflow_faults_at_push() {
+    let (computer, result) = stack_factorial(Some(302..310));
+    let error = result.unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::StackOverflow { pc: 114, bottom: 302 });
+    assert_eq!(computer.pc, 114);
+    assert_eq!(computer.ri6, Word::new(true, [0, 0, 0, 1, 45]));
+    assert_eq!(computer.memory[301], Word::default());
+}
+
+#[test]
+fn stack_pop_empty_underflows() {
+    let mut computer = Computer::default();
+    stack::register(&mut computer, 300..310).unwrap();
+    computer.ri6 = Word::new(true, [0, 0, 0, 1, 53]);
+    computer.memory[0] = Word::from_instruction(0, 0, 14, Opcode::SPECIAL.code());    // POP
+    assert_eq!(computer.step(), Err(MixError::StackUnderflow { pc: 0, top: 310 }));
+    assert_eq!(stack::register(&mut computer, 0..10), Err(MixError::ExtensionConflict { opcode: 5, field: 13 }));
+}
+
+#[test]
+fn stack_without_extension_unimplemented() {
+    let (_, result) = stack_factorial(None);
+    assert_eq!(result, Err(MixError::UnimplementedInstruction { pc: 114, opcode: 5, field: 13 }));
+}
+
+fn signed_word(value: i64) -> Word {
+    let mut word = Word::from_value(value.abs());
+    word.positive = value >= 0;
+    word
+}
+
+fn float_of(exponent: u8, fraction: u32) -> MixFloat {
+    MixFloat { positive: true, exponent, fraction }
+}
+
+// FLOT then FIX on rA.
+fn flot_fix_computer(ra: Word) -> Computer {
+    let mut computer = Computer::default();
+    float::register(&mut computer).unwrap();
+    computer.memory[0] = Word::from_instruction(0, 0, 6, Opcode::SPECIAL.code());    // FLOT
+    computer.memory[1] = Word::from_instruction(0, 0, 7, Opcode::SPECIAL.code());    // FIX
+    computer.memory[2] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.ra = ra;
+    computer
+}
+
+#[test]
+fn float_flot_fix_round_trip() {
+    let exact = [0, 1, -1, 255, 256, -12345678, 1 << 31, (1 << 32) - 1, -((1 << 32) - 1)];
+    for value in exact.iter() {
+        let mut computer = flot_fix_computer(signed_word(*value));
+        computer.step().unwrap();
+        println!("{} -> {:?}", value, MixFloat::from_word(&computer.ra));
+        assert_eq!(computer.ra, MixFloat::from_integer(*value).to_word());
+        assert!(*value == 0 || computer.ra.bytes[1] != 0);
+        computer.run().unwrap();
+        assert_eq!(computer.ra, signed_word(*value));
+        assert!(!computer.overflow_flag);
+    }
+
+    // Five-byte integers keep four bytes, rounded to nearest with ties to even.
+    let four_bytes: i64 = 1 << 32;
+    for (value, rounded) in [(four_bytes + 1, four_bytes), (four_bytes + 128, four_bytes), (four_bytes + 384, four_bytes + 512)].iter() {
+        let mut computer = flot_fix_computer(signed_word(*value));
+        computer.run().unwrap();
+        assert_eq!(computer.ra, signed_word(*rounded));
+    }
+    // The largest five-byte integer rounds up into a sixth byte.
+    assert_eq!(MixFloat::from_integer((1 << 40) - 1), float_of(134, 0x01000000));
+
+    // FIX rounds to nearest with ties to even, and keeps the sign of a zero.
+    assert_eq!(float_of(129, 0x01800000).to_integer(), Some(2));
+    assert_eq!(float_of(129, 0x02800000).to_integer(), Some(2));
+    assert_eq!(float_of(129, 0x02800001).to_integer(), Some(3));
+    let mut computer = flot_fix_computer(MixFloat { positive: false, ..float_of(128, 0x80000000) }.to_word());
+    computer.pc = 1;
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::new(false, [0; 5]));
+
+    // 256^5 needs a sixth byte.
+    let too_big = float_of(134, 0x01000000).to_word();
+    let mut computer = flot_fix_computer(too_big);
+    computer.pc = 1;
+    computer.run().unwrap();
+    assert!(computer.overflow_flag);
+    assert_eq!(computer.ra, too_big);
+    assert_eq!(float_of(133, 0xFFFFFFFF).to_integer(), Some(0xFFFFFFFF * 256));
+    assert_eq!(float_of(255, 0xFFFFFFFF).to_integer(), None);
+}
+
+#[test]
+fn float_decimal_rendering() {
+    let cases = [
+        (float_of(129, 0x80000000), 5, "1.2800E+2"),
+        (float_of(128, 0x40000000), 4, "2.500E-1"),
+        (float_of(127, 0x01000000), 6, "1.52588E-5"),
+        (float_of(127, 0x01000000), 11, "1.5258789062E-5"),
+        (float_of(127, 0x01000000), 12, "1.52587890625E-5"),
+        (MixFloat { positive: false, ..float_of(130, 0x01000000) }, 3, "-2.56E+2"),
+        (MixFloat::from_integer(99999), 3, "1.00E+5"),
+        (MixFloat::from_integer(7), 1, "7E+0"),
+        (float_of(0, 0), 3, "0.00E+0"),
+    ];
+    for (float, digits, text) in cases.iter() {
+        println!("{:?} to {} digits: {}", float, digits, float.to_decimal_string(*digits));
+        assert_eq!(float.to_decimal_string(*digits), *text);
+        if float.fraction != 0 && *digits == 12 {
+            assert_eq!(MixFloat::from_decimal_str(text), Some(*float));
+        }
+    }
+    assert_eq!(MixFloat::from_decimal_str("128"), Some(float_of(129, 0x80000000)));
+    assert_eq!(MixFloat::from_decimal_str("0.25"), Some(float_of(128, 0x40000000)));
+    assert_eq!(MixFloat::from_decimal_str("-2.56e2"), Some(MixFloat { positive: false, ..float_of(130, 0x01000000) }));
+    assert_eq!(MixFloat::from_decimal_str("1.5.2"), None);
+    assert_eq!(MixFloat::from_decimal_str("1E400"), None);
+    // The extreme exponents, to more digits than identify an f64.
+    assert_eq!(float_of(0, 0x01000000).to_decimal_string(20), "2.1729236899484388507E-311");
+    assert_eq!(float_of(255, 0xFFFFFFFF).to_decimal_string(20), "7.0222388064209291330E+305");
+    assert_eq!(report::format_word(&MixFloat::from_integer(128).to_word(), WordFormat::Float), "  1.280000000E+2");
+}
+
+#[test]
+fn float_decimal_avoids_double_rounding() {
+    // 2^32 + 128 + 2^-22 is just above the midpoint between two floats, so it rounds up.
+    // Read through f64 first, the 2^-22 is lost and the tie goes to the even fraction.
+    let text = "4294967424.0000002384185791015625";
+    assert_eq!(MixFloat::from_decimal_str(text), Some(float_of(133, 0x01000001)));
+    let via_f64 = text.parse::<f64>().unwrap();
+    assert_eq!(via_f64, 4294967424.0);
+    assert_eq!(MixFloat::from_integer(via_f64 as i64), float_of(133, 0x01000000));
+}
+
+const DECODE_MIX: &str = include_str!("../fixtures/decode_mix.raw");
+
+fn decode_mix_computer() -> Computer {
+    let mut computer = Computer::default();
+    stack::register(&mut computer, 300..310).unwrap();
+    loader::parse_raw(DECODE_MIX).unwrap().load_into(&mut computer);
+    computer.write_mem(105, Word::from_instruction(200, 0, 53, 8));       // LDA 200(6:5)
+    computer
+}
+
+#[test]
+fn decode_range_classifies_fixture() {
+    let computer = decode_mix_computer();
+    let items: Vec<(usize, DecodedItem)> = computer.decode_range(0..DEFAULT_MEMORY_SIZE).collect();
+    let kinds: Vec<(usize, &str)> = items.iter().map(|(address, item)| (*address, match item {
+        DecodedItem::Instruction(instruction) => instruction.operation.map_or("extension", |operation| operation.mnemonic),
+        DecodedItem::Data(_) => "data",
+        DecodedItem::Unimplemented { .. } => "unimplemented",
+        DecodedItem::Invalid(_) => "invalid",
+    })).collect();
+    assert_eq!(kinds, vec![(100, "LDA"), (101, "extension"), (102, "unimplemented"), (103, "STA"),
+                           (104, "HLT"), (105, "invalid"), (200, "data")]);
+    assert_eq!(items[0].1, DecodedItem::Instruction(DecodedInstruction {
+        operation: opcodes::lookup("LDA"), address: 200, index: 0, field: 5, opcode: 8,
+    }));
+    assert_eq!(items[2].1, DecodedItem::Unimplemented { opcode: 5, field: 8 });
+    assert_eq!(items[5].1, DecodedItem::Invalid(DecodeError::InvalidField { opcode: 8, field: 53 }));
+    assert_eq!(items[6].1, DecodedItem::Data(Word::new(true, [8, 5, 13, 13, 16])));
+
+    let before = allocations();
+    assert_eq!(computer.decode_range(0..DEFAULT_MEMORY_SIZE).count(), 7);
+    assert_eq!(allocations(), before);
+
+    let mut computer = computer;
+    computer.source_map = None;
+    let (_, alf) = computer.decode_range(200..201).next().unwrap();
+    assert_eq!(alf, DecodedItem::Invalid(DecodeError::InvalidIndex(13)));
+}
+
+#[test]
+fn decode_consumers_agree() {
+    let computer = decode_mix_computer();
+    let listing = report::render_listing(&computer, 0..DEFAULT_MEMORY_SIZE);
+    println!("{}", listing);
+    assert_eq!(listing, "0100: LDA 200\n\
+                         0101: extension C=5 F=13\n\
+                         0102: unimplemented C=5 F=8\n\
+                         0103: STA 200\n\
+                         0104: HLT 0\n\
+                         0105: invalid, 6:5 is not a field for opcode 8\n\
+                         0200: CON 34444479760\n");
+
+    // Only the words the listing shows as instructions count as references, so neither
+    // the unimplemented word nor the invalid LDA does.
+    assert_eq!(computer.search_references(200, 0..DEFAULT_MEMORY_SIZE), vec![100, 103]);
+
+    // The loader refuses the invalid word for the same reason the listing gives.
+    let error = loader::parse_raw("105: +200 0 53 8").unwrap_err();
+    assert_eq!(error.kind, RawErrorKind::InvalidField(53));
+    assert_eq!(decode::check_word(&computer.memory[105]), Err(DecodeError::InvalidField { opcode: 8, field: 53 }));
+    for address in [100, 101, 102, 103, 104] {
+        assert!(decode::check_word(&computer.memory[address]).is_ok());
+    }
+}
+
+// rA = 5 and rX = 0 divided by 2 overflows, leaving rA and rX undefined.
+fn div_overflow_computer(policy: PoisonPolicy, rest: &[Word]) -> Computer {
+    let mut computer = Computer::default();
+    computer.poison_policy = policy;
+    computer.ra = Word::from_value(5);
+    computer.memory[100] = Word::from_value(2);
+    computer.memory[0] = Word::from_instruction(100, 0, 5, Opcode::DIV.code());    // DIV 100
+    for (i, word) in rest.iter().enumerate() {
+        computer.memory[i + 1] = *word;
+    }
+    computer
+}
+
+#[test]
+fn poison_div_overflow_then_sta_faults() {
+    let sta = Word::from_instruction(101, 0, 5, Opcode::STA.code());    // STA 101
+    let mut computer = div_overflow_computer(PoisonPolicy::Fault, &[sta, Word::from_instruction(0, 0, 2, 5)]);
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::PoisonedRead { pc: 1, register: Accumulator::A });
+    assert!(computer.overflow_flag);
+    assert_eq!(computer.ra, POISON);
+    assert_eq!(computer.rx, POISON);
+    assert_eq!(computer.memory[101], Word::default());
+}
+
+#[test]
+fn poison_cleared_by_enta() {
+    let program = [
+        Word::from_instruction(0, 0, 2, Opcode::ENTA.code()),       // ENTA 0
+        Word::from_instruction(101, 0, 5, Opcode::STA.code()),      // STA 101
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ];
+    let mut computer = div_overflow_computer(PoisonPolicy::Fault, &program);
+    computer.run().unwrap();
+    assert!(computer.is_halted());
+    assert!(!computer.is_poisoned(Accumulator::A));
+    assert!(computer.is_poisoned(Accumulator::X));
+    assert_eq!(computer.memory[101], Word::default());
+}
+
+#[test]
+fn poison_never_escapes_lenient_runs() {
+    let program = [
+        Word::from_instruction(101, 0, 5, Opcode::STA.code()),      // STA 101
+        Word::from_instruction(102, 0, 5, Opcode::STX.code()),      // STX 102
+        Word::from_instruction(101, 0, 5, Opcode::STA.code()),      // STA 101
+        Word::from_instruction(1, 0, 0, Opcode::ENTA.code()),       // INCA 1
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),    // HLT
+    ];
+    let mut off = div_overflow_computer(PoisonPolicy::Off, &program);
+    let mut warn = div_overflow_computer(PoisonPolicy::Warn, &program);
+    off.run().unwrap();
+    warn.run().unwrap();
+    assert!(off.state_eq(&warn));
+    assert_eq!(off.state_hash(), warn.state_hash());
+    assert_eq!(off.ra, Word::from_value(1));
+    assert!(off.undefined_warnings.is_empty());
+
+    // Each register is reported the first time it is read, and not again.
+    let reported: Vec<(usize, &str)> = warn.undefined_warnings.iter()
+        .map(|warning| (warning.pc, warning.operation.as_str()))
+        .collect();
+    assert_eq!(reported, vec![(1, "STA"), (2, "STX")]);
+    println!("{}", warn.undefined_warnings[0].detail);
+}
+
+#[cfg(feature = "tui")]
+fn tui_computer() -> Computer {
+    let deck = "
+        START 100
+        100: +200 0 5 8      # LDA 200
+        101: +201 0 5 24     # STA 201
+        102: +0 0 2 5        # HLT
+        200: +7
+    ";
+    let mut computer = Computer::default();
+    loader::parse_raw(deck).unwrap().load_into(&mut computer);
+    computer
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn tui_renders_known_state() {
+    let mut computer = tui_computer();
+    let mut panel = tui::Panel { cursor: 100, ..tui::Panel::default() };
+    assert!(tui::update(&mut computer, &mut panel, tui::Command::Step));
+    assert!(tui::update(&mut computer, &mut panel, tui::Command::CursorDown));
+    assert!(tui::update(&mut computer, &mut panel, tui::Command::ToggleBreakpoint(None)));
+
+    let mut grid = tui::Grid::new(80, 14);
+    tui::render(&computer, &panel, &mut grid);
+    print!("{}", grid.text());
+    assert_eq!(grid.text(), "\
+rA   +    0    0    0    0    7   rX   +    0    0    0    0    0
+rI1  +    0    0    0    0    0   rI2  +    0    0    0    0    0
+rI3  +    0    0    0    0    0   rI4  +    0    0    0    0    0
+rI5  +    0    0    0    0    0   rI6  +    0    0    0    0    0
+rJ   +    0    0    0    0    0   OV off  CI Equal
+pc  0101  running
+
+   0100  +    0  200    0    5    8  LDA 200
+   0101  +    0  201    0    5   24  STA 201
+>* 0102  +    0    0    0    2    5  HLT 0
+   0103  +    0    0    0    0    0  NOP 0
+   0104  +    0    0    0    0    0  NOP 0
+Stepped to 101
+s:step r:run b:break w:watch e:edit g:go to k:up j:down f:format x:why q:quit
+");
+    assert!(grid.is_highlighted(8));
+    assert!(!grid.is_highlighted(9));
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn tui_keys_dispatch_to_computer() {
+    let documented = ['s', 'r', 'b', 'w', 'e', 'g', 'k', 'j', 'f', 'x', 'q'];
+    assert_eq!(tui::KEYS.iter().map(|(key, _)| *key).collect::<Vec<char>>(), documented);
+    let argument = |key: char| match key { 'e' => "+ 0 9 0 0 0", 'g' => "102", 'f' => "value", _ => "" };
+
+    for (key, _) in tui::KEYS.iter() {
+        let mut computer = tui_computer();
+        let mut panel = tui::Panel { cursor: 101, ..tui::Panel::default() };
+        panel.breakpoints.insert(101);
+        let command = tui::command_for(*key, argument(*key), &computer).unwrap();
+        let open = tui::update(&mut computer, &mut panel, command);
+        println!("{} -> {:?}", key, panel);
+        match key {
+            's' => assert_eq!((computer.pc, computer.ra), (101, Word::from_value(7))),
+            'r' => assert_eq!((computer.pc, panel.message.as_str()), (101, "Breakpoint at 101")),
+            'b' => assert!(panel.breakpoints.is_empty()),
+            'w' => assert!(panel.watches.contains(&101)),
+            'e' => assert_eq!(computer.memory[101], Word::new(true, [0, 9, 0, 0, 0])),
+            'g' => assert_eq!(panel.cursor, 102),
+            'k' => assert_eq!(panel.cursor, 100),
+            'j' => assert_eq!(panel.cursor, 102),
+            'f' => assert_eq!(panel.format, WordFormat::SignedValue),
+            'x' => assert!(panel.explain),
+            'q' => assert!(!open),
+            _ => unreachable!(),
+        }
+        assert_eq!(open, *key != 'q');
+    }
+
+    // Running on from the breakpoint reaches HLT, after which nothing more is executed.
+    let mut computer = tui_computer();
+    let mut panel = tui::Panel::default();
+    tui::update(&mut computer, &mut panel, tui::Command::RunUntilBreak);
+    assert!(computer.is_halted());
+    assert_eq!(computer.memory[201], Word::from_value(7));
+    tui::update(&mut computer, &mut panel, tui::Command::Step);
+    assert_eq!((computer.pc, panel.message.as_str()), (103, "Halted"));
+    assert!(tui::command_for('z', "", &computer).is_err());
+    assert!(tui::command_for('e', "+ 0 256 0 0 0", &computer).is_err());
+    assert!(tui::command_for('f', "octal", &computer).is_err());
+    assert!(tui::command_for('g', "4000", &computer).is_err());
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn tui_searches_page_through_found() {
+    let mut computer = tui_computer();
+    for address in 1000..1025 {
+        computer.write_mem(address, Word::from_value(9));
+    }
+    let mut panel = tui::Panel::default();
+    let enter = |computer: &mut Computer, panel: &mut tui::Panel, word: &str, argument: &str| {
+        let command = tui::command_for_word(word, argument, computer).unwrap();
+        tui::update(computer, panel, command);
+        panel.message.clone()
+    };
+    assert_eq!(enter(&mut computer, &mut panel, "find", "9"),
+               "Found 25, 1 to 10: 1000 1001 1002 1003 1004 1005 1006 1007 1008 1009 (n: more)");
+    assert_eq!(panel.cursor, 1000);
+    assert_eq!(enter(&mut computer, &mut panel, "n", ""),
+               "Found 25, 11 to 20: 1010 1011 1012 1013 1014 1015 1016 1017 1018 1019 (n: more)");
+    assert_eq!(enter(&mut computer, &mut panel, "n", ""), "Found 25, 21 to 25: 1020 1021 1022 1023 1024");
+    assert_eq!(panel.cursor, 1020);
+    assert_eq!(enter(&mut computer, &mut panel, "n", "").split(':').next(), Some("Found 25, 1 to 10"));
+
+    // STA 201 at 101, and LDA 200 matched on all but its address.
+    assert_eq!(enter(&mut computer, &mut panel, "refs", "201"), "Found 1, 1 to 1: 101");
+    assert_eq!(enter(&mut computer, &mut panel, "findb", "+ 0 * 0 5 8"), "Found 1, 1 to 1: 100");
+    assert_eq!(enter(&mut computer, &mut panel, "findb", "- * * * * *"), "Found nothing");
+    assert_eq!(enter(&mut computer, &mut panel, "n", ""), "Nothing found to page through");
+
+    assert_eq!(tui::command_for_word("findb", "+ 0 64 0 5 8", &computer), Ok(tui::Command::Search(tui::Search::Bytes {
+        sign: Some(true), pattern: [Some(0), Some(64), Some(0), Some(5), Some(8)],
+    })));
+    assert!(tui::command_for_word("findb", "+ 0 256 0 5 8", &computer).is_err());
+    assert!(tui::command_for_word("findb", "0 0 5 8", &computer).is_err());
+    assert!(tui::command_for_word("find", "SEVEN", &computer).is_err());
+    assert!(tui::command_for_word("refs", "4000", &computer).is_err());
+    assert_eq!(tui::command_for_word("g", "102", &computer), Ok(tui::Command::Goto(102)));
+    assert_eq!(tui::command_for_word("go", "102", &computer), Err("'go' is not a command".to_string()));
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn tui_switches_word_format() {
+    let mut computer = Computer::default();
+    loader::parse_raw(WORD_FORMATS).unwrap().load_into(&mut computer);
+    let mut panel = tui::Panel { cursor: 3001, ..tui::Panel::default() };
+    let window = |computer: &Computer, panel: &tui::Panel| {
+        let mut grid = tui::Grid::new(80, 13);
+        tui::render(computer, panel, &mut grid);
+        (7..11).map(|row| grid.line(row)).collect::<Vec<String>>()
+    };
+    let mut outputs = Vec::new();
+    for name in ["value", "inst", "both", "bytes"].iter() {
+        let command = tui::command_for('f', name, &computer).unwrap();
+        assert!(tui::update(&mut computer, &mut panel, command));
+        outputs.push(window(&computer, &panel));
+        println!("f {}\n{}", name, outputs.last().unwrap().join("\n"));
+    }
+    assert_eq!(outputs[0], [
+        "   2999              0  NOP 0",
+        "   3000    33554498312  LDA 2000,1(0:3)",
+        ">  3001         -12345  CON -12345",
+        "   3002     1000000000  CON 1000000000",
+    ]);
+    assert_eq!(outputs[1], [
+        "   2999 NOP 0",
+        "   3000 LDA 2000,1(0:3)",
+        ">  3001  -    0    0    0   48   57          -12345  CON -12345",
+        "   3002  +    0   59  154  202    0      1000000000  CON 1000000000",
+    ]);
+    assert_eq!(outputs[2], [
+        "   2999  +    0    0    0    0    0               0  NOP 0",
+        "   3000  +    7  208    1    3    8     33554498312  LDA 2000,1(0:3)",
+        ">  3001  -    0    0    0   48   57          -12345  CON -12345",
+        "   3002  +    0   59  154  202    0      1000000000  CON 1000000000",
+    ]);
+    assert_eq!(outputs[3], [
+        "   2999  +    0    0    0    0    0  NOP 0",
+        "   3000  +    7  208    1    3    8  LDA 2000,1(0:3)",
+        ">  3001  -    0    0    0   48   57  CON -12345",
+        "   3002  +    0   59  154  202    0  CON 1000000000",
+    ]);
+}
+
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+#[test]
+fn tui_explains_steps() {
+    let mut computer = tui_computer();
+    let mut panel = tui::Panel::default();
+    tui::update(&mut computer, &mut panel, tui::Command::ToggleExplain);
+    assert_eq!(panel.message, "Explanations on");
+    tui::update(&mut computer, &mut panel, tui::Command::Step);
+    assert_eq!(panel.message, "LDA 200: loaded all of memory cell 200 into rA, which is now +0 0 0 0 7");
+    tui::update(&mut computer, &mut panel, tui::Command::ToggleExplain);
+    tui::update(&mut computer, &mut panel, tui::Command::Step);
+    assert_eq!(panel.message, "Stepped to 102");
+
+    let mut out = Vec::new();
+    script::run_script("explain\ns\ns\nexplain\ns", std::path::Path::new("."), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "NOP 0: did nothing\nNOP 0: did nothing\n");
+}
+
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+#[test]
+fn script_draws_heatmap() {
+    let mut out = Vec::new();
+    let failure = script::run_script("heatmap", std::path::Path::new("."), &mut out).unwrap_err();
+    assert_eq!(failure, ScriptFailure { line: 1, message: "No heatmap is being recorded".to_string() });
+    script::run_script("heatmap 0 16", std::path::Path::new("."), &mut out).unwrap_err();
+
+    script::run_script("heatmap 1 1\ns\ns\ns\nheatmap", std::path::Path::new("."), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "@  \n @ \n  @\n");
+}
+
+// Calls a subroutine which saves its return jump with STJ EXIT(field), the usual idiom
+// being the default (0:2).
+fn stj_subroutine(field: u8, policy: CodeWritePolicy) -> Computer {
+    let deck = format!("
+        START 100
+        100: +110 0 0 39     # JMP SUB
+        101: +0 0 2 5        # HLT
+        110: +112 0 {} 32    # SUB STJ EXIT
+        111: +1 0 2 48       # ENTA 1
+        112: +0 0 0 39       # EXIT JMP *
+    ", field);
+    let mut computer = Computer::default();
+    computer.code_write_policy = policy;
+    loader::parse_raw(&deck).unwrap().load_into(&mut computer);
+    computer
+}
+
+#[test]
+fn code_write_stj_default_field_is_silent() {
+    for policy in [CodeWritePolicy::Warn, CodeWritePolicy::Fault] {
+        let mut computer = stj_subroutine(2, policy);
+        computer.run().unwrap();
+        assert_eq!(computer.pc, 102);
+        assert_eq!(computer.ra, Word::from_value(1));
+        assert_eq!(computer.memory[112], Word::from_instruction(101, 0, 0, 39));
+        assert!(computer.code_write_warnings.is_empty());
+    }
+}
+
+#[test]
+fn code_write_stj_whole_word_into_code() {
+    let mut computer = stj_subroutine(5, CodeWritePolicy::Fault);
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::CodeWrite { pc: 110, address: 112, field: FieldSpec::WHOLE });
+    assert_eq!(computer.memory[112], Word::from_instruction(0, 0, 0, 39));
+
+    let mut computer = stj_subroutine(5, CodeWritePolicy::Warn);
+    for _ in 0..2 {
+        computer.step().unwrap();
+    }
+    assert_eq!(computer.code_write_warnings, vec![CodeWriteWarning {
+        pc: 110, operation: "STJ".to_string(), address: 112, field: FieldSpec::WHOLE,
+    }]);
+    assert_eq!(computer.memory[112], Word::new(true, [0, 0, 0, 0, 101]));
+
+    // Nothing is caught without a source map saying where the instructions are.
+    let mut computer = stj_subroutine(5, CodeWritePolicy::Fault);
+    computer.source_map = None;
+    for _ in 0..2 {
+        computer.step().unwrap();
+    }
+}
+
+const SELF_MODIFYING: &str = include_str!("../fixtures/self_modifying.raw");
+
+#[test]
+fn code_write_allowed_self_modification() {
+    for policy in [CodeWritePolicy::Allow, CodeWritePolicy::Warn, CodeWritePolicy::Fault] {
+        let mut computer = Computer::default();
+        computer.code_write_policy = policy;
+        loader::parse_raw(SELF_MODIFYING).unwrap().load_into(&mut computer);
+        computer.allow_code_write(103);
+        computer.run().unwrap();
+        assert_eq!(computer.rx, Word::from_value(42));
+        assert!(computer.code_write_warnings.is_empty());
+    }
+
+    let mut computer = Computer::default();
+    computer.code_write_policy = CodeWritePolicy::Fault;
+    loader::parse_raw(SELF_MODIFYING).unwrap().load_into(&mut computer);
+    assert_eq!(computer.run(), Err(MixError::CodeWrite { pc: 101, address: 103, field: FieldSpec::WHOLE }));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_w_values() {
+    let source = "\
+\tORIG\t600\n\
+START\tNOP\n\
+\tHLT\n\
+BOOK\tCON\t1(1:2),66(4:5)\n\
+LAYERED\tCON\t1(1:5),2(3:3),-3(0:1)\n\
+LATER\tCON\t7(4:5),9(5:5)\n\
+BIG\tEQU\t1(1:1),2(5:5)\n\
+\tCON\tBIG\n\
+\tCON\t-0\n\
+\tEND\t2(4:4),88(5:5)\n";
+    let program = assemble(source).unwrap();
+    // The book's word + 0 1 0 1 2, in its bytes of 64; 66 fits in one byte of 256.
+    assert_eq!(program.words[&602], Word::new(true, [0, 1, 0, 0, 66]));
+    assert_eq!(program.words[&603], Word::new(false, [3, 0, 2, 0, 1]));
+    assert_eq!(program.words[&604], Word::new(true, [0, 0, 0, 0, 9]));
+    assert_eq!(program.words[&605], Word::new(true, [1, 0, 0, 0, 2]));
+    assert_eq!(program.words[&606], Word::new(false, [0; 5]));
+    assert_eq!(AssemblyState::new(source).symbols.get("BIG").unwrap().value, 256i64.pow(4) + 2);
+    assert!(program.source_map.data.contains(&602));
+    // END's W-value is 2 * 256 + 88.
+    assert_eq!(program.start, 600);
+
+    let bad = source.replace("LATER\tCON\t7(4:5),9(5:5)", "LATER\tCON\t7(4:5),300(5:5)");
+    let error = assemble(&bad).unwrap_err();
+    println!("{}", error);
+    assert_eq!(error.line, 6);
+    assert_eq!(error.kind, AssembleErrorKind::PartOutOfRange { part: 2, value: 300, field: FieldSpec::of(5, 5) });
+    assert_eq!(&bad[error.span.start..error.span.end], "300");
+
+    let error = assemble(&source.replace("2(3:3)", "2(3:2)")).unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::OutOfRange { part: "field", value: 26 });
+    let program = assemble(&source.replace("9(5:5)", "9(5:5) remarks")).unwrap();
+    assert_eq!(program.words[&604], Word::new(true, [0, 0, 0, 0, 9]));
+    let error = assemble(&source.replace("9(5:5)", "9(5:5)1")).unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::Syntax("expected , or the end of the operand".to_string()));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_future_reference_in_equ_and_orig() {
+    let source = "\
+SIZE\tEQU\tLIMIT-1\n\
+LIMIT\tEQU\t100\n\
+\tORIG\tBUF\n\
+BUF\tEQU\t2000\n\
+\tHLT\n\
+\tEND\t0\n";
+    let state = AssemblyState::new(source);
+    for error in state.diagnostics.iter() {
+        println!("{}", error);
+    }
+    let kinds: Vec<_> = state.diagnostics.iter().map(|error| (error.line, error.kind.clone())).collect();
+    assert_eq!(kinds, vec![
+        (1, AssembleErrorKind::FutureReference { name: "LIMIT".to_string(), definition: Span::new(17, 22) }),
+        (3, AssembleErrorKind::FutureReference { name: "BUF".to_string(), definition: Span::new(41, 44) }),
+    ]);
+    assert_eq!(&source[17..22], "LIMIT");
+
+    // Only EQU and ORIG are held to earlier symbols, and a symbol never defined is just
+    // undefined.
+    let program = assemble("\tLDA\tLATER\n\tCON\tLATER\nLATER\tEQU\t5\n\tEND\tLATER\n").unwrap();
+    assert_eq!(program.words[&0], Word::from_instruction(5, 0, 5, 8));
+    assert_eq!(program.words[&1], Word::from_value(5));
+    let error = assemble("X\tEQU\tNOWHERE\n").unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::UndefinedSymbol("NOWHERE".to_string()));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_program_m_byte_for_byte() {
+    let program = assemble(include_str!("../examples/maximum.mixal")).unwrap();
+    let expected = [
+        (100, Word::from_instruction(3000, 0, 0, Opcode::JMP.code())),     // DRIVER JMP MAXIMUM
+        (101, Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code())),    // HLT
+        (3000, Word::from_instruction(3009, 0, 2, Opcode::STJ.code())),    // MAXIMUM STJ EXIT
+        (3001, Word::from_instruction(0, 1, 2, Opcode::ENT3.code())),      // INIT ENT3 0,1
+        (3002, Word::from_instruction(3005, 0, 0, Opcode::JMP.code())),    // JMP CHANGEM
+        (3003, Word::from_instruction(1000, 3, 5, Opcode::CMPA.code())),   // LOOP CMPA X,3
+        (3004, Word::from_instruction(3007, 0, 7, Opcode::JMP.code())),    // JGE SKIP
+        (3005, Word::from_instruction(0, 3, 2, Opcode::ENT2.code())),      // CHANGEM ENT2 0,3
+        (3006, Word::from_instruction(1000, 3, 5, Opcode::LDA.code())),    // LDA X,3
+        (3007, Word::from_instruction(1, 0, 1, Opcode::ENT3.code())),      // SKIP DEC3 1
+        (3008, Word::from_instruction(3003, 0, 2, Opcode::J3.code())),     // J3P LOOP
+        (3009, Word::from_instruction(3009, 0, 0, Opcode::JMP.code())),    // EXIT JMP *
+    ];
+    assert_eq!(program.words.iter().map(|(address, word)| (*address, *word)).collect::<Vec<_>>(), expected);
+    assert_eq!(program.start, 100);
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_literals_local_symbols_and_alf() {
+    let program = assemble("
+        ORIG 1000
+START   LDA  =10=
+2H      DECA 1
+        JAP  2B
+        JMP  2F
+2H      LDX  =1(1:1),2(5:5)=
+        CMPX =-3=,1
+2H      ALF  HELLO
+        ALF  \"A B\"
+        ALF
+        JMP  2B
+        END  START
+").unwrap();
+    assert_eq!(program.words[&1000], Word::from_instruction(1010, 0, 5, Opcode::LDA.code()));
+    assert_eq!(program.words[&1002], Word::from_instruction(1001, 0, 2, Opcode::JA.code()));
+    assert_eq!(program.words[&1003], Word::from_instruction(1004, 0, 0, Opcode::JMP.code()));
+    assert_eq!(program.words[&1004], Word::from_instruction(1011, 0, 5, Opcode::LDX.code()));
+    assert_eq!(program.words[&1005], Word::from_instruction(1012, 1, 5, Opcode::CMPX.code()));
+    assert_eq!(program.words[&1006], Word::new(true, [8, 5, 13, 13, 16]));
+    assert_eq!(program.words[&1007], Word::new(true, [1, 0, 2, 0, 0]));
+    assert_eq!(program.words[&1008], Word::new(true, [0; 5]));
+    assert_eq!(program.words[&1009], Word::from_instruction(1006, 0, 0, Opcode::JMP.code()));
+    // The literals follow the last word, in order of the source.
+    assert_eq!(program.words[&1010], Word::from_value(10));
+    assert_eq!(program.words[&1011], Word::new(true, [1, 0, 0, 0, 2]));
+    assert_eq!(program.words[&1012], Word::from_value(-3));
+    assert_eq!(program.words.len(), 13);
+
+    // Errors name the line and the offending token.
+    let error = assemble(" ORIG 0\n LDA =5\n").unwrap_err();
+    assert_eq!((error.line, error.kind), (2, AssembleErrorKind::Syntax("expected , or =".to_string())));
+    let source = " JMP 2F\n JMP 2B\n";
+    let error = assemble(source).unwrap_err();
+    assert_eq!((error.line, error.kind, &source[error.span.start..error.span.end]), (1, AssembleErrorKind::UndefinedSymbol("2F".to_string()), "2F"));
+    let error = assemble("2B NOP\n").unwrap_err();
+    assert_eq!((error.line, error.kind), (1, AssembleErrorKind::InvalidSymbol("2B".to_string())));
+    let source = " NOP\n ALF TOOLONG\n";
+    let error = assemble(source).unwrap_err();
+    assert_eq!((error.line, error.kind, &source[error.span.start..error.span.end]), (2, AssembleErrorKind::Charset(CharsetError::TooLong(7)), "TOOLONG"));
+    let error = assemble(" ALF a\n").unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::Charset(CharsetError::UnknownCharacter { character: 'a', position: 0 }));
+}
+
+#[cfg(any(all(feature = "assembler", feature = "peripherals"), feature = "analysis"))]
+const PROGRAM_MAP_SOURCE: &str = include_str!("../fixtures/program_map.mixal");
+
+#[cfg(feature = "analysis")]
+#[test]
+fn program_map_renders_fixture() {
+    let state = AssemblyState::new(PROGRAM_MAP_SOURCE);
+    let map = analysis::program_map(&state).unwrap();
+    let text = map.to_text();
+    println!("{}", text);
+    assert_eq!(text, include_str!("../fixtures/program_map.txt"));
+    let kinds: Vec<_> = map.symbols.iter().filter(|symbol| ["N", "SUM", "TABLE"].contains(&symbol.name.as_str())).map(|symbol| symbol.kind).collect();
+    assert_eq!(kinds, vec![SymbolKind::Constant, SymbolKind::Code, SymbolKind::Data]);
+    assert_eq!(map.unreachable, vec![3006]);
+    assert_eq!(map.unreferenced, vec![3104]);
+    assert_eq!(map.dynamic_jumps, vec![3013]);
+}
+
+/// Counts what is written to it without keeping any of it.
+#[cfg(feature = "analysis")]
+#[derive(Default)]
+struct CountingWriter {
+    bytes: usize,
+    lines: usize,
+}
+
+#[cfg(feature = "analysis")]
+impl std::fmt::Write for CountingWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.bytes += s.len();
+        self.lines += s.matches('\n').count();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn program_map_streams_in_bounded_memory() {
+    let mut source = String::from("START\tNOP\n");
+    for i in 0..1975 {
+        source.push_str(&format!("C{}\tLDA\tD{}\n", i, i));
+    }
+    for i in 0..1975 {
+        source.push_str(&format!("D{}\tCON\t{}\n", i, i));
+    }
+    source.push_str("\tEND\tSTART\n");
+    let map = analysis::program_map(&AssemblyState::new(&source)).unwrap();
+    assert_eq!(map.entries.len(), 3951);
+
+    let mut counter = CountingWriter::default();
+    let (written, peak) = peak_allocation(|| map.write_text(&mut counter));
+    written.unwrap();
+    println!("{} bytes in {} lines, at most {} bytes allocated", counter.bytes, counter.lines, peak);
+    assert!(counter.bytes > 200_000);
+    assert!(peak < counter.bytes / 100);
+    assert_eq!(map.to_text().len(), counter.bytes);
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn streamed_renderings_match_strings() {
+    let map = analysis::program_map(&AssemblyState::new(PROGRAM_MAP_SOURCE)).unwrap();
+    let mut out = report::IoWriter::new(Vec::new());
+    map.write_text(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out.inner).unwrap(), map.to_text());
+
+    let mut computer = Computer::default();
+    assemble(PROGRAM_MAP_SOURCE).unwrap().load_into(&mut computer);
+    let mut out = report::IoWriter::new(Vec::new());
+    report::write_listing(&computer, 0..4000, &mut out).unwrap();
+    let listing = String::from_utf8(out.inner).unwrap();
+    assert_eq!(listing, report::render_listing(&computer, 0..4000));
+    assert!(listing.lines().count() > 10);
+    let mut text = String::new();
+    report::write_memory_as(&computer, 0..4000, WordFormat::Instruction, &mut text).unwrap();
+    assert_eq!(text, report::render_memory_as(&computer, 0..4000, WordFormat::Instruction));
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn program_map_stops_at_jumps_that_never_fall_through() {
+    let source = "\tJSJ\tDONE\n\tHLT\n\tJMP\tDONE\nDONE\tHLT\nDATA\tCON\t1\n\tEND\t0\n";
+    let map = analysis::program_map(&AssemblyState::new(source)).unwrap();
+    println!("{}", map.to_text());
+    // JMP may be a call that returns, but nothing reaches it.
+    assert_eq!(map.unreachable, vec![1, 2]);
+    assert_eq!(map.unreferenced, vec![4]);
+    let incoming: Vec<_> = map.entries.iter().map(|entry| entry.incoming.clone()).collect();
+    assert_eq!(incoming, vec![vec![], vec![], vec![], vec![0, 2], vec![]]);
+
+    let error = analysis::program_map(&AssemblyState::new("\tLDA\tNOWHERE\n")).unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::UndefinedSymbol("NOWHERE".to_string()));
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn program_map_indexed_jump_reaches_its_table() {
+    let source = "\tENT1\t1\n\tJMP\tTABLE,1\nTABLE\tJMP\tA\n\tJMP\tB\nA\tHLT\nB\tHLT\nC\tHLT\n\tEND\t0\n";
+    let map = analysis::program_map(&AssemblyState::new(source)).unwrap();
+    println!("{}", map.to_text());
+    let targets: Vec<_> = map.dynamic_targets.iter().map(|(target, sources)| (*target, sources.clone())).collect();
+    assert_eq!(targets, vec![(2, vec![1]), (3, vec![1])]);
+    assert_eq!(map.unreachable, vec![6]);
+}
+
+/// A main module counting to 3 in `LOOP`, calling `DOUBLE` from `LIB_MODULE` to add 30
+/// in a `LOOP` of its own, and halting once it comes back to `BACK`. Jump targets are
+/// `NOP`s.
+#[cfg(feature = "assembler")]
+const MAIN_MODULE: &str = "\tPUBL\tBACK\nSTART\tENT1\t3\nLOOP\tNOP\n\tINCA\t1\n\tDEC1\t1\n\tJ1P\tLOOP\n\tJMP\tDOUBLE\nBACK\tNOP\n\tHLT\n\tEND\tSTART\n";
+#[cfg(feature = "assembler")]
+const LIB_MODULE: &str = "DOUBLE\tNOP\n\tENT2\t3\nLOOP\tNOP\n\tINCA\t10\n\tDEC2\t1\n\tJ2P\tLOOP\n\tJMP\tBACK\n";
+
+#[cfg(feature = "assembler")]
+#[test]
+fn modules_with_the_same_local_labels_link_and_run() {
+    let main = assemble_relocatable("MAIN", MAIN_MODULE, &[]).unwrap();
+    let lib = assemble_relocatable("LIB", LIB_MODULE, &["DOUBLE"]).unwrap();
+    assert_eq!(main.exports.iter().collect::<Vec<_>>(), vec!["BACK"]);
+    assert_eq!(main.imports.iter().map(|(name, line)| (name.as_str(), *line)).collect::<Vec<_>>(), vec![("DOUBLE", 7)]);
+    assert_eq!(lib.imports.keys().collect::<Vec<_>>(), vec!["BACK"]);
+
+    let linked = link::link(&[main, lib], 100).unwrap();
+    assert_eq!(linked.modules.iter().map(|module| (module.name.as_str(), module.base)).collect::<Vec<_>>(), vec![("MAIN", 100), ("LIB", 108)]);
+    let labels: Vec<_> = linked.program.source_map.labels.iter().map(|(address, label)| (*address, label.as_str())).collect();
+    assert_eq!(labels, vec![(100, "MAIN.START"), (101, "MAIN.LOOP"), (106, "BACK"), (108, "DOUBLE"), (110, "LIB.LOOP")]);
+    assert_eq!(linked.program.start, 100);
+
+    let mut computer = Computer::default();
+    linked.program.load_into(&mut computer);
+    computer.run().unwrap();
+    assert!(computer.is_halted());
+    assert_eq!(computer.ra, Word::from_value(33));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn modules_exporting_the_same_symbol_collide() {
+    let a = assemble_relocatable("A", "\tPUBL\tTEMP\nTEMP\tCON\t1\n", &[]).unwrap();
+    let b = assemble_relocatable("B", "TEMP\tCON\t2\n", &["TEMP"]).unwrap();
+    let error = link::link(&[a.clone(), b.clone()], 0).unwrap_err();
+    assert_eq!(error, LinkError::DuplicateExport { name: "TEMP".to_string(), first: "A".to_string(), second: "B".to_string() });
+    assert_eq!(error.to_string(), "TEMP is exported by both A and B");
+
+    // Locals of the same name don't meet.
+    let c = assemble_relocatable("C", "TEMP\tCON\t3\n", &[]).unwrap();
+    let linked = link::link(&[a.clone(), c], 0).unwrap();
+    assert_eq!(linked.program.source_map.labels.values().collect::<Vec<_>>(), vec!["TEMP", "C.TEMP"]);
+    assert_eq!(link::link(&[a.clone(), a], 0).unwrap_err(), LinkError::DuplicateModule("A".to_string()));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn references_to_other_modules_need_exports() {
+    let main = assemble_relocatable("MAIN", "\tNOP\n\tJMP\tHELPER\n\tHLT\n", &[]).unwrap();
+    let lib = assemble_relocatable("LIB", "HELPER\tHLT\n", &[]).unwrap();
+    let error = link::link(&[main, lib.clone()], 0).unwrap_err();
+    assert_eq!(error.to_string(), "Module MAIN, line 2: HELPER exists in LIB but is not exported");
+
+    let main = assemble_relocatable("MAIN", "\tLDA\tNOWHERE\n", &[]).unwrap();
+    let error = link::link(&[main, lib], 0).unwrap_err();
+    assert_eq!(error, LinkError::Unresolved { module: "MAIN".to_string(), name: "NOWHERE".to_string(), line: 1 });
+    assert_eq!(error.to_string(), "Module MAIN, line 1: NOWHERE is not defined in any module");
+
+    let error = assemble_relocatable("LIB", "HELPER\tHLT\n", &["HELPR"]).unwrap_err();
+    assert_eq!(error.to_string(), "Module LIB: HELPR is exported but not defined");
+    let error = assemble_relocatable("LIB", "\tPUBL\tHELPR\nHELPER\tHLT\n", &[]).unwrap_err();
+    assert_eq!(error.to_string(), "Module LIB: Line 1: HELPR is not defined");
+    // EQU and ORIG are evaluated before any module is placed.
+    let error = assemble_relocatable("LIB", "SIZE\tEQU\tLENGTH\n", &[]).unwrap_err();
+    assert_eq!(error.to_string(), "Module LIB: Line 1: LENGTH is not defined");
+    assert_eq!(assemble_relocatable("lib", "", &[]).unwrap_err(), LinkError::InvalidModuleName("lib".to_string()));
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn linked_program_map_qualifies_locals() {
+    let main = assemble_relocatable("MAIN", MAIN_MODULE, &[]).unwrap();
+    let lib = assemble_relocatable("LIB", LIB_MODULE, &["DOUBLE"]).unwrap();
+    let linked = link::link(&[main, lib], 0).unwrap();
+    let text = analysis::linked_program_map(&linked).to_text();
+    println!("{}", text);
+    assert_eq!(text, "\
+PROGRAM MAP, START 0
+
+SYMBOLS
+  MAIN.START      0  code
+  MAIN.LOOP       1  code
+  BACK            6  code
+  DOUBLE          8  code
+  LIB.LOOP       10  code
+
+LISTING
+  0000 MAIN.START ENT1 3
+  0001 MAIN.LOOP  NOP 0                from 4
+  0002            INCA 1
+  0003            DEC1 1
+  0004            J1P 1                refs MAIN.LOOP
+  0005            JMP 8                refs DOUBLE
+  0006 BACK       NOP 0                from 14
+  0007            HLT 0
+  0008 DOUBLE     NOP 0                from 5
+  0009            ENT2 3
+  0010 LIB.LOOP   NOP 0                from 13
+  0011            INCA 10
+  0012            DEC2 1
+  0013            J2P 10               refs LIB.LOOP
+  0014            JMP 6                refs BACK
+
+DYNAMIC
+
+UNREACHABLE CODE
+
+UNREFERENCED DATA
+
+RUNS OFF THE END OF MEMORY
+");
+}
+
+#[cfg(feature = "mdk")]
+const GENERATED_PROGRAM: &str = "\
+* A generated program, straight-line so that jumps play no part
+START\tENTX\t5
+\tENT1\t3
+\tINC1\t2
+\tENTA\t1
+\tSTA\tTEMP
+\tLDX\tTEMP(0:5)
+\tSLA\t4
+\tENT2\t7,1
+\tDEC2\t1
+\tCMPA\tTEMP
+\tHLT\t0\tdone
+TEMP\tCON\t0
+\tEND\tSTART
+";
+
+// Whether rA ends with a value no word of bytes of 64 values can hold, which MDK can't
+// reproduce, so the program is sure to diverge.
+#[cfg(feature = "mdk")]
+fn beyond_mdk_words(source: &str) -> bool {
+    conformance::run_here(source).is_ok_and(|outcome| outcome.registers["rA"].abs() >= 64i64.pow(5))
+}
+
+#[cfg(feature = "mdk")]
+#[test]
+fn conformance_minimize_keeps_divergence() {
+    let mut tries = 0;
+    let minimal = conformance::minimize(GENERATED_PROGRAM, |source| { tries += 1; beyond_mdk_words(source) });
+    println!("{} tries:\n{}", tries, minimal);
+    assert!(beyond_mdk_words(&minimal));
+    assert!(minimal.lines().count() < 10);
+    assert!(minimal.lines().count() < GENERATED_PROGRAM.lines().count() - 5);
+    assert!(minimal.contains("\tSLA\t4"));
+    assert!(!minimal.contains("done"));
+
+    let directory = std::env::temp_dir().join(format!("mixal-reproducers-{}", std::process::id()));
+    let path = conformance::save_reproducer(&directory, &minimal).unwrap();
+    assert_eq!(conformance::save_reproducer(&directory, &minimal).unwrap(), path);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), minimal);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "mdk")]
+#[test]
+fn conformance_normalizes_both_sides() {
+    let dump = "Running ...\n\
+        rA: + 00 00 00 01 05 (0000000069)\n\
+        rX: - 00 00 00 00 02 (0000000002)\n\
+        rJ: + 00 00 (0000)\trI1: + 00 05 (0005)\trI2: + 00 00 (0000)\n\
+        rI3: + 00 00 (0000)\trI4: + 00 00 (0000)\trI5: + 00 00 (0000)\trI6: + 00 00 (0000)\n\
+        Overflow: F\n\
+        Cmp: G\n";
+    let theirs = conformance::parse_mdk_dump(dump, true).unwrap();
+    assert_eq!(theirs.registers["rA"], 69);
+    assert_eq!(theirs.registers["rX"], -2);
+    assert_eq!(theirs.registers["rI1"], 5);
+    assert_eq!(theirs.comparison, ComparisonFlag::greater);
+    assert!(matches!(conformance::parse_mdk_dump("rA: + 00 00 00 00 01", true), Err(conformance::MdkError::Dump(_))));
+
+    let source = "START\tENTA\t69\n\tENTX\t-2\n\tENT1\t5\n\tCMPA\tZERO\n\tHLT\nZERO\tCON\t0\n\tEND\tSTART\n";
+    let ours = conformance::run_here(source).unwrap();
+    assert_eq!(ours.first_divergence(&theirs), None);
+    let mut halted_differently = theirs.clone();
+    halted_differently.halted = false;
+    assert_eq!(ours.first_divergence(&halted_differently), Some("halted: true here, false in MDK".to_string()));
+    let mut rx_differs = theirs;
+    rx_differs.registers.insert("rX".to_string(), 2);
+    assert_eq!(ours.first_divergence(&rx_differs), Some("rX: Some(-2) here, Some(2) in MDK".to_string()));
+}
+
+#[cfg(feature = "mdk")]
+#[test]
+fn conformance_against_mdk() {
+    if !conformance::mdk_available() {
+        println!("mixasm and mixvm aren't on PATH, skipping");
+        return;
+    }
+    let equivalent = "START\tENTA\t69\n\tENTX\t-2\n\tENT1\t5\n\tCMPA\tZERO\n\tHLT\nZERO\tCON\t0\n\tEND\tSTART\n";
+    assert_eq!(conformance::compare(equivalent).unwrap(), None);
+    assert_eq!(conformance::compare(HIGH_MEMORY).unwrap(), None);
+
+    let diverges = |source: &str| matches!(conformance::compare(source), Ok(Some(_)));
+    assert!(diverges(GENERATED_PROGRAM));
+    let minimal = conformance::minimize(GENERATED_PROGRAM, diverges);
+    println!("{}", minimal);
+    assert!(minimal.lines().count() < 10);
+}
+
+/// A future that is pending the first time it is polled, as a host's yield would be.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Polls `future` to completion on this thread, returning its output and the number of
+/// times it was pending.
+fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+    let mut future = Box::pin(future);
+    let mut context = Context::from_waker(Waker::noop());
+    let mut pending = 0;
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return (output, pending),
+            Poll::Pending => pending += 1,
+        }
+    }
+}
+
+fn async_runner_program() -> Computer {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(500, 0, 2, Opcode::ENT1.code());     // ENT1 500
+    computer.memory[1] = Word::from_instruction(3, 0, 0, Opcode::ENTA.code());       // INCA 3
+    computer.memory[2] = Word::from_instruction(1000, 0, 5, Opcode::MUL.code());     // MUL 1000
+    computer.memory[3] = Word::from_instruction(1, 0, 1, Opcode::ENT1.code());       // DEC1 1
+    computer.memory[4] = Word::from_instruction(1, 0, 2, Opcode::J1.code());         // J1P 1
+    computer.memory[5] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.memory[1000] = Word::from_value(7);
+    computer
+}
+
+#[test]
+fn async_runner_matches_run() {
+    let mut expected = async_runner_program();
+    expected.run().unwrap();
+
+    let options = RunnerOptions { steps_per_yield: 7, ..RunnerOptions::new(|| YieldOnce(false)) };
+    let mut runner = AsyncRunner::new(async_runner_program(), options);
+    let (outcome, pending) = block_on(runner.run());
+    println!("{:?} after {} steps and {} yields", outcome, runner.steps(), pending);
+    assert_eq!(outcome, RunOutcome::Halted);
+    assert_eq!(pending, (runner.steps() - 1) / 7);
+    assert!(runner.computer.state_eq(&expected));
+    assert_eq!(runner.computer.time(), expected.time());
+    assert_eq!(runner.computer.pc, expected.pc);
+}
+
+#[test]
+fn async_runner_cancels_within_a_slice() {
+    let yields = Rc::new(Cell::new(0));
+    let counter = yields.clone();
+    let cancellation = CancellationToken::new();
+    let token = cancellation.clone();
+    let yield_now = move || {
+        counter.set(counter.get() + 1);
+        if counter.get() == 3 {
+            token.cancel();
+        }
+        YieldOnce(false)
+    };
+    let options = RunnerOptions { steps_per_yield: 10, yield_now, cancellation };
+    let mut runner = AsyncRunner::new(async_runner_program(), options);
+    let (outcome, _) = block_on(runner.run());
+    assert_eq!(outcome, RunOutcome::Cancelled);
+    assert_eq!(yields.get(), 3);
+    assert_eq!(runner.steps(), 30);
+    assert!(!runner.computer.is_halted());
+    assert_eq!(runner.computer.pc, 2);
+    assert_eq!(runner.computer.ri1.field_value(FieldSpec::WHOLE), 500 - 7);
+
+    let (outcome, _) = block_on(runner.run());
+    assert_eq!(outcome, RunOutcome::Cancelled);
+    assert_eq!(runner.steps(), 30);
+}
+
+fn traced_tape_program(options: TraceOptions) -> Computer {
+    let mut computer = Computer::default();
+    computer.trace = Some(Trace::new(options));
+    computer.memory[0] = Word::from_instruction(1000, 0, 0, Opcode::OUT.code());     // OUT 1000(0)
+    computer.memory[1] = Word::from_instruction(1100, 0, 0, Opcode::OUT.code());     // OUT 1100(0)
+    computer.memory[2] = Word::from_instruction(1200, 0, 0, Opcode::OUT.code());     // OUT 1200(0)
+    computer.memory[3] = Word::from_instruction(0, 0, 0, Opcode::IOC.code());        // IOC 0(0)
+    computer.memory[4] = Word::from_instruction(1300, 0, 0, Opcode::IN.code());      // IN 1300(0)
+    computer.memory[5] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    for seed in 0..3 {
+        computer.load(1000 + 100 * seed, &tape_block(seed + 1));
+    }
+    computer
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn io_trace_reconstructs_tape() {
+    let mut computer = traced_tape_program(TraceOptions { include_blocks: true });
+    let tape = computer.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    computer.run().unwrap();
+    let trace = computer.trace.as_ref().unwrap();
+
+    let outs: Vec<_> = trace.in_direction(Direction::Out).collect();
+    assert_eq!(outs.len(), 3);
+    assert_eq!(outs.iter().map(|transfer| transfer.pc).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(outs[0].range, 1000..1100);
+    assert_eq!(outs.iter().map(|transfer| transfer.completes_at).collect::<Vec<_>>(), vec![200, 400, 600]);
+    assert_eq!(trace.for_unit(0).count(), 5);
+    assert_eq!(trace.in_direction(Direction::Control).next().unwrap().range, 0..0);
+    let input = trace.in_direction(Direction::In).next().unwrap();
+    assert_eq!(input.block.as_deref(), Some(&tape_block(1)[..]));
+    assert_eq!(&computer.memory[1300..1400], &tape_block(1)[..]);
+
+    let mut tape = tape.borrow_mut();
+    tape.rewind();
+    let final_blocks: Vec<Vec<Word>> = (0..3).map(|_| tape.read_block().unwrap().to_vec()).collect();
+    assert!(tape.read_block().is_err());
+    let written = trace.blocks_written(0).unwrap();
+    println!("{} blocks written, {} on tape", written.len(), final_blocks.len());
+    assert_eq!(written, final_blocks);
+
+    let mut without_blocks = traced_tape_program(TraceOptions::default());
+    without_blocks.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    without_blocks.run().unwrap();
+    let trace = without_blocks.trace.as_ref().unwrap();
+    assert!(trace.transfers().all(|transfer| transfer.block.is_none()));
+    assert_eq!(trace.blocks_written(0), None);
+}
+
+/// A unit that keeps every block it is given with the sign of the first word flipped.
+struct CorruptingUnit(Vec<Vec<Word>>);
+
+impl Peripheral for CorruptingUnit {
+    fn read_block_into(&mut self, _dst: &mut [Word], _byte_size: u16) -> Result<(), TapeError> {
+        Err(TapeError::EndOfTape { block: 0 })
+    }
+
+    fn write_block(&mut self, src: &[Word]) -> Result<(), TapeError> {
+        let mut block = src.to_vec();
+        block[0] = block[0].negate();
+        self.0.push(block);
+        Ok(())
+    }
+
+    fn control(&mut self, _amount: i64) -> Result<(), TapeError> {
+        Ok(())
+    }
+
+    fn last_block_written(&self) -> Option<Vec<Word>> {
+        self.0.last().cloned()
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "holds a different block")]
+fn io_trace_catches_unit_mismatch() {
+    let mut computer = traced_tape_program(TraceOptions::default());
+    computer.attach(0, CorruptingUnit(Vec::new()));
+    let _ = computer.run();
+}
+
+/// A computer tracing steps as `config` says, with `program` at 0.
+fn traced_steps(program: &[(&str, i64, u8)], config: TraceConfig) -> Computer {
+    let mut computer = Computer::default();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), config));
+    for (i, (mnemonic, address, index)) in program.iter().enumerate() {
+        computer.memory[i] = opcodes::encode(mnemonic, *address, *index, None).unwrap();
+    }
+    computer
+}
+
+// Counts rI1 down from 6, with a loop body at 1..5 of which only INCA 1 changes rA.
+const COUNTDOWN_LOOP: [(&str, i64, u8); 6] = [("ENT1", 6, 0), ("INCA", 0, 0), ("INCA", 1, 0), ("DEC1", 1, 0), ("J1P", 1, 0), ("HLT", 0, 0)];
+
+#[test]
+fn step_trace_samples_every_nth_step() {
+    let config = TraceConfig { every_nth_step: 1000, ..TraceConfig::default() };
+    let mut computer = traced_steps(&[("NOP", 0, 0), ("INCA", 1, 0), ("JMP", 1, 0)], config);
+    for _ in 0..100_000 {
+        computer.step().unwrap();
+    }
+    let trace = computer.trace.as_ref().unwrap();
+    let steps: Vec<u64> = trace.steps().map(|record| record.step).collect();
+    println!("{} records, {} steps", steps.len(), trace.steps_taken());
+    assert_eq!(steps, (1..=100).map(|i| i * 1000).collect::<Vec<u64>>());
+    assert!(trace.steps().all(|record| record.pc == 1));
+    assert_eq!(trace.step(5000).unwrap().registers[0].field_value(FieldSpec::WHOLE), 2500);
+    assert_eq!(trace.step(5001), None);
+    assert_eq!(trace.steps_in(2500..5001).map(|record| record.step).collect::<Vec<u64>>(), vec![3000, 4000, 5000]);
+    assert_eq!(trace.transfers().count(), 0);
+
+    computer.reset();
+    let trace = computer.trace.as_ref().unwrap();
+    assert_eq!(trace.steps.as_ref().unwrap().every_nth_step, 1000);
+    assert_eq!((trace.steps().count(), trace.steps_taken()), (0, 0));
+}
+
+#[test]
+fn step_trace_filters_on_pc_ranges() {
+    let config = TraceConfig { only_pc_ranges: vec![1..3, 3..5], ..TraceConfig::default() };
+    let mut computer = traced_steps(&COUNTDOWN_LOOP, config);
+    computer.run().unwrap();
+    let trace = computer.trace.as_ref().unwrap();
+    assert_eq!(trace.steps_taken(), 26);
+    assert_eq!(trace.steps().count(), 24);
+    assert!(trace.steps().all(|record| (1..5).contains(&record.pc)));
+    assert_eq!(trace.steps().next().unwrap().step, 2);
+
+    let config = TraceConfig { only_instruction_families: Some(1 << 1), ..TraceConfig::default() };
+    let mut jumps = traced_steps(&COUNTDOWN_LOOP, config);
+    jumps.run().unwrap();
+    assert_eq!(jumps.trace.as_ref().unwrap().steps().map(|record| record.pc).collect::<Vec<usize>>(), vec![4; 6]);
+}
+
+#[test]
+fn step_trace_on_change_of_matches_full_trace() {
+    let mut full = traced_steps(&COUNTDOWN_LOOP, TraceConfig::default());
+    full.run().unwrap();
+    let mut before = Word::default();
+    let mut changed = Vec::new();
+    for record in full.trace.as_ref().unwrap().steps() {
+        if record.registers[0] != before {
+            changed.push(record.step);
+        }
+        before = record.registers[0];
+    }
+    println!("rA changed at steps {:?}", changed);
+    assert_eq!(changed.len(), 6);
+
+    let config = TraceConfig { on_change_of: Some(REGISTER_A), ..TraceConfig::default() };
+    let mut sampled = traced_steps(&COUNTDOWN_LOOP, config);
+    sampled.run().unwrap();
+    let trace = sampled.trace.as_ref().unwrap();
+    assert_eq!(trace.steps().map(|record| record.step).collect::<Vec<u64>>(), changed);
+    assert!(trace.steps().all(|record| record.pc == 2));
+}
+
+/// Runs a program that prints its own code, then patches the instruction at 4 while the
+/// block is still moving and waits for the printer, returning the computer, the memory it
+/// started with, and the block printed.
+#[cfg(feature = "peripherals")]
+fn print_own_code(io_timing: IoTimingModel) -> (Computer, Vec<Word>, Vec<Word>) {
+    let mut computer = Computer::default();
+    computer.io_timing = io_timing;
+    computer.track_written_bytes();
+    computer.trace = Some(Trace::with_steps(TraceOptions { include_blocks: true }, TraceConfig::default()));
+    let printer = Channel::new(10);
+    computer.attach(PRINTER_UNIT, ChannelEnd::producer(&printer));
+    let program = [
+        opcodes::encode("OUT", 0, 0, Some(18)).unwrap(),
+        opcodes::encode("LDA", 10, 0, None).unwrap(),
+        opcodes::encode("STA", 4, 0, None).unwrap(),
+        opcodes::encode("NOP", 0, 0, None).unwrap(),
+        opcodes::encode("NOP", 0, 0, None).unwrap(),                  // patched to ENTA 7
+        opcodes::encode("JBUS", 5, 0, Some(18)).unwrap(),             // waits on itself while busy
+        opcodes::encode("HLT", 0, 0, None).unwrap(),
+    ];
+    computer.load(0, &program);
+    computer.load(10, &[opcodes::encode("ENTA", 7, 0, None).unwrap()]);
+    let original = computer.memory[0..100].to_vec();
+    computer.run().unwrap();
+    let printed = printer.borrow().blocks().next().unwrap().to_vec();
+    (computer, original, printed)
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn output_of_own_code_captured_by_timing_model() {
+    let (instant, original, at_issue) = print_own_code(IoTimingModel::Instant);
+    let (overlapped, _, at_completion) = print_own_code(IoTimingModel::Overlapped);
+    println!("word 4 at issue [{}], at completion [{}]", at_issue[4], at_completion[4]);
+    assert_eq!(at_issue, original);
+    assert_eq!(at_completion, overlapped.memory[0..100].to_vec());
+    assert_eq!(at_completion[4], opcodes::encode("ENTA", 7, 0, None).unwrap());
+    let differing: Vec<usize> = (0..100).filter(|&i| at_issue[i] != at_completion[i]).collect();
+    assert_eq!(differing, vec![4]);
+    assert_eq!(instant.ra.field_value(FieldSpec::WHOLE), 7);
+    assert_eq!(overlapped.ra.field_value(FieldSpec::WHOLE), 7);
+
+    for (computer, printed) in [(&instant, &at_issue), (&overlapped, &at_completion)] {
+        let trace = computer.trace.as_ref().unwrap();
+        let transfer = trace.transfers().next().unwrap();
+        assert_eq!((transfer.pc, transfer.range.clone()), (0, 0..100));
+        assert_eq!(transfer.block.as_ref(), Some(printed));
+        // The printer reading code and unwritten words is neither a step nor a read.
+        assert_eq!(trace.steps().count() as u64, trace.steps_taken());
+        assert!(trace.steps().all(|record| record.pc <= 6));
+        assert_eq!(computer.uninitialized_reads, vec![]);
+    }
+    let trace = overlapped.trace.as_ref().unwrap();
+    assert!(trace.steps().filter(|record| record.pc == 5).count() > 1);
+    assert_eq!(trace.transfers().next().unwrap().completes_at, 200);
+}
+
+#[cfg(feature = "assembler")]
+const STUB_CALLER: &str = include_str!("../fixtures/stub_caller.mixal");
+
+#[cfg(feature = "assembler")]
+#[test]
+fn stubbed_subroutine_answers_calls() {
+    let state = AssemblyState::new(STUB_CALLER);
+    let printnum = state.symbols.get("PRINTNUM").unwrap().value as usize;
+    let exit = state.symbols.get("EXIT").unwrap().value as usize;
+    let program = state.program.as_ref().unwrap();
+    let mut computer = Computer::default();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    program.load_into(&mut computer);
+    let printed = Rc::new(RefCell::new(Vec::new()));
+    let captured = printed.clone();
+    computer.stub_subroutine(printnum, Box::new(move |computer| {
+        captured.borrow_mut().push(computer.ra.field_value(FieldSpec::WHOLE));
+        Ok(())
+    }));
+    computer.run().unwrap();
+    assert_eq!(*printed.borrow(), vec![7, 42, -5]);
+    let trace = computer.trace.as_ref().unwrap();
+    assert!(trace.steps().all(|record| !(printnum..=exit).contains(&record.pc)));
+    let calls: Vec<_> = trace.stub_calls().map(|call| (call.entry, call.caller, call.returned_to)).collect();
+    assert_eq!(calls, vec![(printnum, 1, 2), (printnum, 3, 4), (printnum, 5, 6)]);
+    // The STJ saved the last return address into the exit, as the subroutine would have.
+    assert_eq!(computer.memory[exit].address(), 6);
+
+    // Returning to rJ stores nothing.
+    computer.reset();
+    program.load_into(&mut computer);
+    let captured = printed.clone();
+    computer.stub_subroutine_with(printnum, StubLinkage::Raw, Box::new(move |computer| {
+        captured.borrow_mut().push(computer.ra.field_value(FieldSpec::WHOLE));
+        Ok(())
+    }));
+    computer.run().unwrap();
+    assert_eq!(printed.borrow().len(), 6);
+    assert_eq!(computer.memory[exit].address(), exit);
+
+    // Without the stub, the jump enters the subroutine.
+    computer.reset();
+    program.load_into(&mut computer);
+    assert!(computer.unstub_subroutine(printnum));
+    assert!(!computer.unstub_subroutine(printnum));
+    for _ in 0..3 {
+        computer.step().unwrap();
+    }
+    let trace = computer.trace.as_ref().unwrap();
+    assert!(trace.steps().any(|record| (printnum..=exit).contains(&record.pc)));
+    assert_eq!(trace.stub_calls().count(), 0);
+    assert_eq!(printed.borrow().len(), 6);
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn stub_checks_its_linkage() {
+    let state = AssemblyState::new(STUB_CALLER);
+    let mut computer = Computer::default();
+    state.program.as_ref().unwrap().load_into(&mut computer);
+    let store = state.symbols.get("PRINTNUM").unwrap().value as usize + 1;
+    computer.memory[1] = opcodes::encode("JMP", store as i64, 0, None).unwrap();
+    computer.stub_subroutine(store, Box::new(|_| Ok(())));
+    computer.step().unwrap();
+    let error = computer.step().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::StubLinkage { pc: 1, entry: store, word: computer.memory[store] });
+}
+
+// Reads a block from tape into 1000..1100, the 50th word of which the source map gives as
+// an instruction.
+#[cfg(feature = "peripherals")]
+fn block_into_code(policy: CodeWritePolicy, address: usize) -> (Computer, Rc<RefCell<MagneticTapeUnit>>) {
+    let mut computer = Computer::default();
+    computer.code_write_policy = policy;
+    computer.trace = Some(Trace::new(TraceOptions::default()));
+    computer.source_map = Some(SourceMap { code: vec![0, 1, 1049].into_iter().collect(), ..SourceMap::default() });
+    computer.memory[0] = Word::from_instruction(address as i64, 0, 0, Opcode::IN.code());    // IN address(0)
+    computer.memory[1] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());            // HLT
+    for i in 1000..1100 {
+        computer.memory[i] = Word::from_value(-1);
+    }
+    let tape = computer.attach_tape(MagneticTapeUnit::new(0, tape_block(3)));
+    (computer, tape)
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn block_write_checked_before_writing() {
+    let (mut computer, tape) = block_into_code(CodeWritePolicy::Fault, 1000);
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::CodeWrite { pc: 0, address: 1049, field: FieldSpec::WHOLE });
+    assert!(computer.memory[1000..1100].iter().all(|word| *word == Word::from_value(-1)));
+    assert_eq!(tape.borrow().position(), 0);
+    assert!(computer.trace.as_ref().unwrap().records.is_empty());
+
+    let (mut computer, _) = block_into_code(CodeWritePolicy::Warn, 1000);
+    computer.run().unwrap();
+    assert_eq!(&computer.memory[1000..1100], &tape_block(3)[..]);
+    assert_eq!(computer.code_write_warnings, vec![CodeWriteWarning {
+        pc: 0, operation: "IN".to_string(), address: 1049, field: FieldSpec::WHOLE,
+    }]);
+    let transfers: Vec<_> = computer.trace.as_ref().unwrap().transfers().collect();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].range, 1000..1100);
+
+    let (mut computer, tape) = block_into_code(CodeWritePolicy::Allow, 3950);
+    let error = computer.run().unwrap_err();
+    println!("{}", error);
+    assert_eq!(error, MixError::BlockOutOfRange { pc: 0, address: 3950, words: 100 });
+    assert_eq!(error.code(), "block-out-of-range");
+    assert_eq!(tape.borrow().position(), 0);
+}
+
+#[test]
+fn move_is_a_block_write() {
+    // MOVE 1000(10) onto 2000..2010, the 5th word of which the source map gives as an
+    // instruction.
+    let moved = |policy: CodeWritePolicy, destination: i64| {
+        let mut computer = Computer::default();
+        computer.code_write_policy = policy;
+        computer.trace = Some(Trace::new(TraceOptions::default()));
+        computer.source_map = Some(SourceMap { code: vec![0, 2004].into_iter().collect(), ..SourceMap::default() });
+        computer.memory[0] = Word::from_instruction(1000, 0, 10, Opcode::MOVE.code());    // MOVE 1000(10)
+        for i in 1000..1010 {
+            computer.memory[i] = Word::from_value(i as i64);
+        }
+        computer.ri1 = Word::from_value(destination);
+        let result = computer.step();
+        (computer, result)
+    };
+
+    let (computer, result) = moved(CodeWritePolicy::Fault, 2000);
+    assert_eq!(result.unwrap_err(), MixError::CodeWrite { pc: 0, address: 2004, field: FieldSpec::WHOLE });
+    assert!(computer.memory[2000..2010].iter().all(|word| *word == Word::default()));
+    assert_eq!(computer.ri1, Word::from_value(2000));
+
+    let (computer, result) = moved(CodeWritePolicy::Warn, 2000);
+    result.unwrap();
+    assert_eq!(computer.memory[2000..2010], computer.memory[1000..1010]);
+    assert_eq!(computer.code_write_warnings, vec![CodeWriteWarning {
+        pc: 0, operation: "MOVE".to_string(), address: 2004, field: FieldSpec::WHOLE,
+    }]);
+    assert_eq!(computer.trace.as_ref().unwrap().transfers().count(), 0);
+    assert!(computer.is_occupied(2009));
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn input_over_code_is_fetched_fresh() {
+    // IN replaces the instruction after it, which must run as read rather than as it was.
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(1, 0, 0, Opcode::IN.code());         // IN 1(0)
+    computer.memory[1] = Word::from_instruction(1, 0, 2, Opcode::ENTA.code());       // ENTA 1
+    computer.memory[2] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    let mut block = [Word::default(); BLOCK_WORDS];
+    block[0] = Word::from_instruction(2, 0, 2, Opcode::ENTA.code());       // ENTA 2
+    block[1] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.attach_tape(MagneticTapeUnit::new(0, block));
+    computer.step().unwrap();
+    assert_eq!(computer.memory[1], block[0]);
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value(2));
+}
+
+fn phase_program() -> Computer {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(5, 0, 2, Opcode::ENTA.code());       // ENTA 5
+    computer.memory[1] = Word::from_instruction(100, 0, 5, Opcode::STA.code());      // STA 100
+    computer.memory[2] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer
+}
+
+type PhaseLog = Rc<RefCell<Vec<Phase>>>;
+
+/// An observer keeping every phase it is given, and the phases it has kept.
+fn phase_recorder() -> (Box<dyn FnMut(Phase)>, PhaseLog) {
+    let phases = Rc::new(RefCell::new(Vec::new()));
+    let kept = phases.clone();
+    (Box::new(move |phase| kept.borrow_mut().push(phase)), phases)
+}
+
+fn phase_name(phase: &Phase) -> &'static str {
+    match phase {
+        Phase::Fetched { .. } => "fetched",
+        Phase::Decoded { .. } => "decoded",
+        Phase::DecodeFailed { .. } => "decode failed",
+        Phase::Executed { .. } => "executed",
+    }
+}
+
+fn phase_pc(phase: &Phase) -> usize {
+    match phase {
+        Phase::Fetched { pc, .. } | Phase::Decoded { pc, .. } | Phase::DecodeFailed { pc, .. } | Phase::Executed { pc, .. } => *pc,
+    }
+}
+
+#[test]
+fn phases_of_each_step() {
+    let mut computer = phase_program();
+    let (observer, phases) = phase_recorder();
+    computer.set_phase_observer(observer);
+    computer.run().unwrap();
+    let phases = phases.borrow();
+    for phase in phases.iter() {
+        println!("{:?}", phase);
+    }
+    assert_eq!(phases.len(), 9);
+    for (step, phases) in phases.chunks(3).enumerate() {
+        assert_eq!(phases.iter().map(phase_name).collect::<Vec<_>>(), ["fetched", "decoded", "executed"]);
+        assert!(phases.iter().all(|phase| phase_pc(phase) == step));
+    }
+
+    assert_eq!(phases[0], Phase::Fetched { pc: 0, word: Word::from_instruction(5, 0, 2, 48) });
+    match &phases[4] {
+        Phase::Decoded { breakdown, mnemonic, .. } => {
+            assert_eq!(mnemonic, "STA");
+            assert_eq!((breakdown.address, breakdown.index, breakdown.field, breakdown.opcode), (100, 0, 5, 24));
+        },
+        phase => panic!("{:?}", phase),
+    }
+    assert_eq!(phases[2], Phase::Executed {
+        pc: 0,
+        changes: StateDelta { registers: vec![("rA", Word::default(), Word::from_value(5))], ..StateDelta::default() },
+        control: ControlFlow::Next,
+    });
+    assert_eq!(phases[5], Phase::Executed {
+        pc: 1,
+        changes: StateDelta { memory: vec![(100, Word::default(), Word::from_value(5))], ..StateDelta::default() },
+        control: ControlFlow::Next,
+    });
+    match &phases[8] {
+        Phase::Executed { changes, control, .. } => assert!(changes.is_empty() && *control == ControlFlow::Halted),
+        phase => panic!("{:?}", phase),
+    }
+}
+
+#[test]
+fn phases_stop_at_decode_failure() {
+    let mut computer = phase_program();
+    computer.unimplemented_policy = UnimplementedPolicy::Fault;
+    computer.memory[1] = Word::from_instruction(100, 0, 8, Opcode::SPECIAL.code());    // C=5 F=8
+    let (observer, phases) = phase_recorder();
+    computer.set_phase_observer(observer);
+    let error = computer.run().unwrap_err();
+    let phases = phases.borrow();
+    assert_eq!(phases.iter().map(phase_name).collect::<Vec<_>>(), ["fetched", "decoded", "executed", "fetched", "decode failed"]);
+    assert_eq!(phases[4], Phase::DecodeFailed { pc: 1, word: Word::from_instruction(100, 0, 8, 5), error });
+}
+
+#[test]
+fn phase_observer_swaps_between_steps() {
+    let mut computer = phase_program();
+    let (first, first_phases) = phase_recorder();
+    let (second, second_phases) = phase_recorder();
+    computer.set_phase_observer(first);
+    computer.step().unwrap();
+    computer.set_phase_observer(second);
+    computer.run().unwrap();
+    println!("{} then {} phases", first_phases.borrow().len(), second_phases.borrow().len());
+    assert_eq!(first_phases.borrow().iter().map(phase_pc).collect::<Vec<_>>(), [0, 0, 0]);
+    assert_eq!(second_phases.borrow().iter().map(phase_pc).collect::<Vec<_>>(), [1, 1, 1, 2, 2, 2]);
+
+    // Observing a run doesn't change where it ends.
+    assert!(computer.clear_phase_observer());
+    let mut unobserved = phase_program();
+    unobserved.run().unwrap();
+    assert_eq!((unobserved.pc, unobserved.memory[100]), (computer.pc, computer.memory[100]));
+}
+
+fn sample_layout() -> MemoryLayout {
+    MemoryLayout::builder()
+        .region("CODE", 100..200)
+        .region("DATA", 200..203)
+        .region("BUFS", 3000..3100)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn layout_rejects_bad_regions() {
+    let overlapping = MemoryLayout::builder().region("CODE", 100..3000).region("BUFS", 2900..3600).build();
+    assert_eq!(overlapping, Err(LayoutError::Overlap { first: "CODE".to_string(), second: "BUFS".to_string() }));
+    let duplicate = MemoryLayout::builder().region("CODE", 0..10).region("CODE", 10..20).build();
+    assert_eq!(duplicate, Err(LayoutError::DuplicateName("CODE".to_string())));
+    assert_eq!(MemoryLayout::builder().region("NONE", 5..5).build(), Err(LayoutError::Empty("NONE".to_string())));
+
+    let layout = sample_layout();
+    assert_eq!(layout.region_at(150).unwrap().name, "CODE");
+    assert_eq!(layout.region_at(202).unwrap().name, "DATA");
+    assert!(layout.region_at(203).is_none());
+    assert!(layout.region_at(0).is_none());
+    assert_eq!(layout.region("BUFS").unwrap().range, 3000..3100);
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn layout_region_overflow_at_assembly() {
+    let source = " ORIG 100\n LDA 200\n HLT\n ORIG 200\n CON 1\n CON 2\n CON 3\n CON 4\n CON 5\n END 100\n";
+    assert!(assemble(source).is_ok());
+    let error = assemble_with_layout(source, &sample_layout()).unwrap_err();
+    println!("{}", error);
+    assert_eq!(error.line, 8);
+    assert_eq!(error.kind, AssembleErrorKind::RegionOverflow { region: "DATA".to_string(), overflow: 2 });
+    assert_eq!(error.to_string(), "Line 8: region DATA overflows by 2 words");
+
+    let fitting = " ORIG 100\n LDA 200\n HLT\n ORIG 200\n CON 1\n END 100\n";
+    let program = assemble_with_layout(fitting, &sample_layout()).unwrap();
+    let mut computer = Computer::default();
+    assert_eq!(program.load_into_layout(&mut computer, &sample_layout(), LayoutPolicy::Error), Ok(Vec::new()));
+    assert_eq!(report::render_memory(&computer, 200..201), format!("0200 DATA: {}\n", Word::from_value(1)));
+    assert_eq!(report::render_listing(&computer, 101..102), "0101 CODE: HLT 0\n");
+
+    let stray = assemble(" ORIG 5\n CON 1\n ORIG 100\n HLT\n END 100\n").unwrap();
+    let mut computer = Computer::default();
+    assert_eq!(stray.load_into_layout(&mut computer, &sample_layout(), LayoutPolicy::Error), Err(LayoutError::Undeclared { address: 5 }));
+    assert_eq!(computer.memory[100], Word::default());
+    assert_eq!(stray.load_into_layout(&mut computer, &sample_layout(), LayoutPolicy::Warn), Ok(vec![5]));
+    assert_eq!(computer.memory[5], Word::from_value(1));
+}
+
+#[cfg(feature = "debugger")]
+#[test]
+fn layout_names_fault_region() {
+    let mut computer = Computer::default();
+    computer.layout = Some(sample_layout());
+    computer.pc = 3050;
+    computer.memory[3050] = Word::from_instruction(0, 0, 5, Opcode::OUT.code());    // OUT 0(5), no unit attached
+    let error = computer.run().unwrap_err();
+    let report = FaultReport::from_run_error_on(&error, &computer);
+    assert_eq!(report.pc, Some(3050));
+    assert_eq!(report.region.as_deref(), Some("BUFS"));
+    assert_eq!(FaultReport::from_run_error(&error).region, None);
+    #[cfg(feature = "json")]
+    assert_eq!(FaultReport::from_json(&report.to_json()).unwrap(), report);
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_cache_counts_and_keys() {
+    let source = " ORIG 100\n LDA 200\n HLT\n ORIG 200\n CON 1\n CON 2\n CON 3\n CON 4\n END 100\n";
+    let mut assembler = Assembler::with_cache(2);
+    let first = assembler.assemble(source);
+    assert_eq!(assembler.assemble(source), first);
+    assert_eq!((assembler.hits(), assembler.misses()), (1, 1));
+
+    // Every option is part of the key.
+    assembler.options.layout = Some(sample_layout());
+    let with_layout = assembler.assemble(source);
+    assert_eq!((assembler.hits(), assembler.misses()), (1, 2));
+    assert_eq!(with_layout, assemble_with_layout(source, &sample_layout()));
+    assert_ne!(with_layout, first);
+    assembler.options.layout = None;
+    assert_eq!(assembler.assemble(source), first);
+    assert_eq!((assembler.hits(), assembler.misses()), (2, 2));
+
+    // Errors are cached, and the least recently used entry goes first.
+    assert!(assembler.assemble(" FOO 1\n").is_err());
+    assert!(assembler.assemble(" FOO 1\n").is_err());
+    assert_eq!((assembler.hits(), assembler.misses()), (3, 3));
+    assembler.options.layout = Some(sample_layout());
+    assembler.assemble(source).unwrap_err();
+    assert_eq!((assembler.hits(), assembler.misses()), (3, 4));
+
+    let mut uncached = Assembler::new();
+    uncached.assemble(source).unwrap();
+    uncached.assemble(source).unwrap();
+    assert_eq!((uncached.hits(), uncached.misses()), (0, 2));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_byte_size() {
+    let source = " ORIG 100\n LDA 200\n HLT\n ORIG 200\n CON 4095\n END 100\n";
+    let mut assembler = Assembler::new();
+    assembler.options.byte_size = 64;
+    let program = assembler.assemble(source).unwrap();
+    assert_ne!(Ok(program.clone()), assemble(source));
+    let mut computer = Computer::default();
+    computer.set_byte_size(64).unwrap();
+    program.load_into(&mut computer);
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value_in_base(4095, 64));
+
+    assembler.options.byte_size = 300;
+    let error = assembler.assemble(source).unwrap_err();
+    assert_eq!(error.kind, AssembleErrorKind::ByteSize(300));
+}
+
+/// `ALF` is read in the charset of the options, which the cache keys on, and what the
+/// program prints is rendered back in it.
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_charset() {
+    let source = " ORIG 100\n ALF \"{}#\"\n END 100\n";
+    let mut assembler = Assembler::with_cache(4);
+    assert!(matches!(assembler.assemble(source).unwrap_err().kind, AssembleErrorKind::Charset(CharsetError::UnknownCharacter { character: '{', .. })));
+    assembler.options.charset = CharsetMapping::mdk();
+    let program = assembler.assemble(source).unwrap();
+    assert_eq!(program.words[&100].bytes, [charset::DELTA, charset::SIGMA, charset::PI, 0, 0]);
+    assert_eq!(assembler.misses(), 2);
+    assembler.assemble(source).unwrap();
+    assert_eq!((assembler.hits(), assembler.misses()), (1, 2));
+
+    let state = AssemblyState::with_options(source, &assembler.options);
+    let edited = assemble_incremental(&state, TextEdit { range: 0..0, text: "* A remark\n".to_string() });
+    assert_eq!(edited.charset(), CharsetMapping::mdk());
+    assert_eq!(edited.program.unwrap().words[&100], program.words[&100]);
+}
+
+/// A random MIXAL program of a few lines, some of which may be wrong.
+#[cfg(feature = "assembler")]
+fn random_mixal_program(rng: &mut Prng) -> String {
+    let mut source = format!(" ORIG {}\n", rng.below(3990));
+    for i in 0..rng.in_range(1..=11) {
+        let line = match rng.below(8) {
+            0 => format!("L{} EQU {}", i, rng.in_range(-100..=3999)),
+            1 => format!(" CON {}", rng.in_range(-100_000..=99_999)),
+            2 => format!(" ORIG {}", rng.below(4010)),
+            3 => format!(" LDA {},{}({}:{})", rng.in_range(-10..=3999), rng.below(8), rng.below(6), rng.below(6)),
+            4 => format!(" ADD L{}", rng.below(12)),
+            5 => format!("X{} JMP *+{}", i, rng.below(5)),
+            6 => " HLT".to_string(),
+            _ => format!(" STA {}", rng.below(4000)),
+        };
+        source.push_str(&line);
+        source.push('\n');
+    }
+    source.push_str(" END 0\n");
+    source
+}
+
+/// The memory image of `program` as a listing, with the image's start and the way it
+/// split into regions.
+#[cfg(feature = "assembler")]
+fn program_listing(program: &AssembledProgram) -> String {
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    format!("{} {:?}\n{}", program.start, program.regions, report::render_listing(&computer, 0..computer.memory_size()))
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn assembler_cache_matches_uncached() {
+    let mut rng = test_prng("assembler_cache_matches_uncached");
+    let directory = std::env::temp_dir().join(format!("mixal-assembly-cache-{}", std::process::id()));
+    let mut assembler = Assembler::with_cache(8);
+    let mut assembled = 0;
+    for n in 0..50 {
+        let source = random_mixal_program(&mut rng);
+        let uncached = assemble(&source);
+        for _ in 0..2 {
+            let cached = assembler.assemble(&source);
+            assert_eq!(cached, uncached, "{}", source);
+            if let (Ok(cached), Ok(uncached)) = (&cached, &uncached) {
+                assert_eq!(program_listing(cached), program_listing(uncached));
+            }
+        }
+
+        let path = directory.join(format!("program{}.mixal", n));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&path, &source).unwrap();
+        for _ in 0..2 {
+            match (assemble_cached(&path, &directory.join("cache")), &uncached) {
+                (Ok(cached), Ok(uncached)) => {
+                    assert_eq!(&cached, uncached, "{}", source);
+                    assert_eq!(program_listing(&cached), program_listing(uncached));
+                },
+                (Err(CacheError::Assemble(error)), Err(expected)) => assert_eq!(&error, expected),
+                (other, expected) => panic!("{:?} from the cache, {:?} without\n{}", other, expected, source),
+            }
+        }
+        assembled += uncached.is_ok() as usize;
+    }
+    println!("{} of 50 programs assembled, {} hits", assembled, assembler.hits());
+    assert_eq!(assembler.hits(), 50);
+
+    // Freshness goes by content, not by the time on the file.
+    let path = directory.join("edited.mixal");
+    std::fs::write(&path, " ORIG 10\n CON 1\n END 10\n").unwrap();
+    assert_eq!(assemble_cached(&path, &directory.join("cache")).unwrap().words[&10], Word::from_value(1));
+    std::fs::write(&path, " ORIG 10\n CON 2\n END 10\n").unwrap();
+    assert_eq!(assemble_cached(&path, &directory.join("cache")).unwrap().words[&10], Word::from_value(2));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_passes_and_fails_with_exit_codes() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(script::run_file(&fixtures.join("script_pass.mon"), &mut out, &mut err), 0);
+    assert_eq!(String::from_utf8(out).unwrap(), "loaded\ndone\n");
+    assert!(err.is_empty());
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(script::run_file(&fixtures.join("script_fail.mon"), &mut out, &mut err), 1);
+    let message = String::from_utf8(err).unwrap();
+    println!("{}", message);
+    assert!(out.is_empty());
+    let lines: Vec<&str> = message.lines().collect();
+    assert!(lines[0].ends_with("script_fail.mon:5: assert rA = 13 failed: rA is [ +    0    0    0    0   12], not [ +    0    0    0    0   13]"));
+    assert_eq!(&lines[1..], [
+        "Changes since the program was loaded:",
+        "  rA   [ +    0    0    0    0    0] -> [ +    0    0    0    0   12]",
+        "  2007 [ +    0    0    0    0    0] -> [ +    0    0    0    0   12]",
+    ]);
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(script::run_file(&fixtures.join("no_such_script.mon"), &mut out, &mut err), 1);
+}
+
+#[test]
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_searches_memory() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let script = "
+        load script_program.mixal
+        find TWO-START+4
+        refs RESULT
+        findb * * * * * 24
+        n
+        find -1
+        n
+    ";
+    let mut out = Vec::new();
+    script::run_script(script, &fixtures, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+Found 1, 1 to 1: 2005
+Found 1, 1 to 1: 2002
+Found 1, 1 to 1: 2002
+Found 1, 1 to 1: 2002
+Found nothing
+Nothing found to page through
+");
+    let failure = script::run_script("findb + 0 0", &fixtures, &mut Vec::new()).unwrap_err();
+    assert_eq!(failure.message, "'+ 0 0' is not a sign and five bytes, each a number below 256 or *");
+}
+
+#[test]
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_addresses_take_symbols() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let script = "
+        load watch_symbols.mixal
+        print BUF+3
+        print LIMIT-START
+        assert mem BUF+3 = 7
+        set mem BUF+1 = 9
+        assert mem 1001 = 9
+        w LIMIT
+        r
+        assert pc = START+3
+        assert mem LIMIT = 501
+        b START+4
+        r
+        assert pc = 2004
+    ";
+    let mut out = Vec::new();
+    script::run_script(script, &fixtures, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "BUF+3 = 1003 [ +    0    0    0    0    7]\nLIMIT-START = 5 [ +    0    0    0    0    0]\n");
+
+    for (line, message) in [
+        ("print LIMT", "'LIMT' is not a symbol of the program; did you mean LIMIT?"),
+        ("g BUFF+1", "'BUFF' is not a symbol of the program; did you mean BUF?"),
+        ("w =500=", "=500= is a literal, which the assembler doesn't pool; give the constant a label with CON"),
+        ("assert mem BUF+ = 0", "'BUF+' is not an address, a symbol, or a sum of them"),
+        ("set pc = START+2000", "'START+2000' is not an address from 0 to 3999; it is 4000"),
+    ] {
+        let script = format!("load watch_symbols.mixal\n{}", line);
+        let failure = script::run_script(&script, &fixtures, &mut Vec::new()).unwrap_err();
+        assert_eq!(failure, ScriptFailure { line: 2, message: message.to_string() });
+    }
+}
+
+#[test]
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_rolls_back_to_checkpoints() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let script = "
+        checkpoint 10000 8
+        load checkpoint_fault.mixal
+        r
+        assert pc = 5
+        rollback 2
+        assert running
+        r
+        assert pc = 5
+        assert mem 7 = 10100
+        trace 3
+    ";
+    let mut out = Vec::new();
+    script::run_script(script, &fixtures, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "0002 ST2 7\n0003 DEC1 1\n0004 J1P 1\n");
+
+    for (script, message) in [
+        ("checkpoint 0 8", "'0 8' is not a number of cycles and of checkpoints, both above 0"),
+        ("checkpoint 100", "'100' is not a number of cycles and of checkpoints, both above 0"),
+        ("rollback 1", "There is no checkpoint 1 back, as there are 0"),
+        ("trace 1", "Nothing is traced before a rollback"),
+    ] {
+        let failure = script::run_script(script, &fixtures, &mut Vec::new()).unwrap_err();
+        assert_eq!(failure.message, message);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_rejects_malformed_lines() {
+    let cases = [
+        ("assert rA = + 0 0 0 0 999", "'+ 0 0 0 0 999' is not a word, such as + 0 100 0 5 8, or a value from -1099511627775 to +1099511627775"),
+        ("assert rA = 1099511627776", "'1099511627776' is not a word, such as + 0 100 0 5 8, or a value"),
+        ("assert rQ = 0", "rQ is not a register, one of rA, rX, rI1 to rI6, or rJ"),
+        ("assert mem 20x0 = 0", "'20x0' is not a symbol of the program"),
+        ("assert mem 5000 = 0", "'5000' is not an address from 0 to 3999"),
+        ("assert pc = -1", "'-1' is not an address from 0 to 3999"),
+        ("assert output contains PRIME", "PRIME is not text in double quotes"),
+        ("assert stopped", "'stopped' is not an assertion"),
+        ("step", "'step' is not a command"),
+        ("e 12", "'12' is not a word, such as + 0 100 0 5 8"),
+        ("load no_such_program.mixal", "no_such_program.mixal"),
+    ];
+    for (line, message) in cases.iter() {
+        let text = format!("# A comment, then a blank line.\n\necho first\n{}\necho never\n", line);
+        let mut out = Vec::new();
+        let failure = script::run_script(&text, std::path::Path::new(""), &mut out).unwrap_err();
+        println!("{}", failure);
+        assert_eq!(failure.line, 4);
+        assert!(failure.message.starts_with(message), "{}", failure.message);
+        assert_eq!(out, b"first\n");
+    }
+    // The assertions that hold on a fresh computer, and q, which ends the script.
+    let text = "assert rA = +0\nassert rX = + 0 0 0 0 0\nassert pc = 0\nassert running\nq\nassert halted\n";
+    assert_eq!(script::run_script(text, std::path::Path::new(""), &mut Vec::new()), Ok(()));
+    let failure = script::run_script("assert halted\n", std::path::Path::new(""), &mut Vec::new()).unwrap_err();
+    assert_eq!(failure, ScriptFailure { line: 1, message: "assert halted failed: the computer is running, not halted\nNothing changed since the program was loaded".to_string() });
+}
+
+const ENCODING_CORPUS: &str = include_str!("../fixtures/encoding_corpus.txt");
+const CORPUS_BYTE_SIZES: [u16; 3] = [64, 100, 256];
+
+/// One line of the encoding corpus: the parts to encode, and the outcome for each byte size.
+struct CorpusLine {
+    line: usize,
+    mnemonic: String,
+    address: i64,
+    negative_zero: bool,
+    index: u8,
+    field: Option<u8>,
+    outcomes: Vec<String>,
+}
+
+fn parse_corpus(text: &str) -> Result<Vec<CorpusLine>, String> {
+    let mut lines = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", i + 1, message);
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() != 4 + CORPUS_BYTE_SIZES.len() {
+            return Err(error(format!("expected {} columns, found {}", 4 + CORPUS_BYTE_SIZES.len(), columns.len())));
+        }
+        let number = |text: &str| text.parse::<i64>().map_err(|_| error(format!("{} is not a number", text)));
+        let byte = |text: &str| text.parse::<u8>().map_err(|_| error(format!("{} is not a byte", text)));
+        let outcomes: Vec<String> = columns[4..].iter().map(|outcome| outcome.to_string()).collect();
+        for outcome in outcomes.iter() {
+            let known = ["ok", "address", "index", "field", "fixed-field", "missing-unit", "unknown-mnemonic"];
+            if !known.contains(&outcome.as_str()) {
+                return Err(error(format!("{} is not an outcome", outcome)));
+            }
+        }
+        lines.push(CorpusLine {
+            line: i + 1,
+            mnemonic: columns[0].to_string(),
+            address: number(columns[1])?,
+            negative_zero: columns[1] == "-0",
+            index: byte(columns[2])?,
+            field: if columns[3] == "-" { None } else { Some(byte(columns[3])?) },
+            outcomes,
+        });
+    }
+    Ok(lines)
+}
+
+#[test]
+fn corpus_encodes_and_decodes_in_three_byte_sizes() {
+    let corpus = parse_corpus(ENCODING_CORPUS).unwrap();
+    println!("{} corpus lines", corpus.len());
+    assert!(corpus.len() > 200);
+    let mut mismatches = Vec::new();
+    for entry in corpus.iter() {
+        for (byte_size, expected) in CORPUS_BYTE_SIZES.iter().zip(entry.outcomes.iter()) {
+            let encoded = opcodes::encode_in_base(&entry.mnemonic, entry.address, entry.index, entry.field, *byte_size);
+            let mut word = match (encoded, expected.as_str()) {
+                (Ok(word), "ok") => word,
+                (Err(error), expected) if error.code() == expected => continue,
+                (outcome, _) => {
+                    mismatches.push(format!("line {} in base {}: {:?}, expected {}", entry.line, byte_size, outcome, expected));
+                    continue;
+                },
+            };
+            if entry.negative_zero {
+                word.positive = false;
+            }
+            let decoded = DecodedInstruction::of_in_base(&word, *byte_size);
+            let mnemonic = decoded.operation.map(|operation| operation.mnemonic);
+            let field = entry.field.unwrap_or_else(|| opcodes::lookup(&entry.mnemonic).unwrap().default_field().unwrap_or(0));
+            if mnemonic != Some(entry.mnemonic.as_str()) || decoded.address != entry.address || decoded.index != entry.index
+                || decoded.field != field || word.positive == (entry.negative_zero || entry.address < 0) {
+                mismatches.push(format!("line {} in base {}: decoded {:?}", entry.line, byte_size, decoded));
+            }
+        }
+    }
+    assert_eq!(mismatches, Vec::<String>::new());
+}
+
+#[test]
+fn corpus_parser_reports_bad_lines() {
+    assert_eq!(parse_corpus("LDA 0 0 - ok ok").err(), Some("line 1: expected 7 columns, found 6".to_string()));
+    assert_eq!(parse_corpus("# comment\nLDA x 0 - ok ok ok").err(), Some("line 2: x is not a number".to_string()));
+    assert_eq!(parse_corpus("LDA 0 0 - ok ok maybe").err(), Some("line 1: maybe is not an outcome".to_string()));
+    assert_eq!(parse_corpus("LDA 0 0 300 ok ok ok").err(), Some("line 1: 300 is not a byte".to_string()));
+}
+
+const PLAIN_IMAGE: &[u8] = include_bytes!("../fixtures/plain_image.mixi");
+
+/// A 4000-word computer with a few words in use, as `plain_image.mixi` holds.
+fn imaged_computer() -> Computer {
+    let mut computer = Computer::default();
+    computer.write_mem(0, Word::new(true, [7, 208, 0, 5, 8]));
+    computer.write_mem(1, Word::new(true, [0, 0, 0, 2, 5]));
+    computer.write_mem(2000, Word::new(false, [0, 0, 0, 0, 42]));
+    computer.ra = Word::new(false, [0, 0, 0, 0, 42]);
+    computer.ri1 = Word::new(true, [0, 0, 0, 0, 7]);
+    computer.rj = Word::new(true, [0, 0, 0, 0, 2]);
+    computer.pc = 3;
+    computer.overflow_flag = true;
+    computer.comparison_flag = ComparisonFlag::greater;
+    computer
+}
+
+#[test]
+fn compressed_images_round_trip_deterministically() {
+    let computer = imaged_computer();
+    let image = MachineImage::of(&computer);
+    let bytes = image.to_bytes(FORMAT_COMPRESSED);
+    println!("{} bytes compressed, {} plain", bytes.len(), image.to_bytes(FORMAT_PLAIN).len());
+    assert!(bytes.len() < 128);
+    assert_eq!(MachineImage::of(&imaged_computer()).to_bytes(FORMAT_COMPRESSED), bytes);
+    assert_eq!(MachineImage::from_bytes(&bytes).unwrap(), image);
+
+    let mut restored = Computer::default();
+    MachineImage::from_bytes(&bytes).unwrap().restore_into(&mut restored).unwrap();
+    assert_eq!(MachineImage::of(&restored), image);
+    assert_eq!(restored.state_hash(), computer.state_hash());
+    assert_eq!(MachineImage::of(&restored).to_bytes(FORMAT_COMPRESSED), bytes);
+
+    // Repeated words other than +0 are runs too, and short repeats stay literal.
+    let mut busy = Computer::default();
+    for address in 100..1100 {
+        busy.write_mem(address, Word::from_value(address as i64 / 2));
+    }
+    let image = MachineImage::of(&busy);
+    assert_eq!(MachineImage::from_bytes(&image.to_bytes(FORMAT_COMPRESSED)).unwrap(), image);
+}
+
+#[test]
+fn damaged_images_detected() {
+    let bytes = MachineImage::of(&imaged_computer()).to_bytes(FORMAT_COMPRESSED);
+    for i in [0, 4, 10, bytes.len() / 2, bytes.len() - 1] {
+        let mut damaged = bytes.clone();
+        damaged[i] ^= 0x10;
+        let error = MachineImage::from_bytes(&damaged).unwrap_err();
+        println!("byte {}: {}", i, error);
+        match (i, error) {
+            (0, ImageError::BadMagic) | (4, ImageError::UnknownFormat(0x11)) | (_, ImageError::Integrity { .. }) => {},
+            (_, error) => panic!("byte {} gave {:?}", i, error),
+        }
+    }
+    let mut damaged = bytes.clone();
+    damaged[20] ^= 1;
+    let found = crate::fingerprint::crc32(&damaged[..damaged.len() - 4]);
+    let stored = &bytes[bytes.len() - 4..];
+    let expected = u32::from_be_bytes([stored[0], stored[1], stored[2], stored[3]]);
+    assert_eq!(MachineImage::from_bytes(&damaged), Err(ImageError::Integrity { expected, found }));
+    assert!(MachineImage::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err().to_string().contains("checksum"));
+    assert_eq!(crate::fingerprint::crc32(b"123456789"), 0xcbf4_3926);
+}
+
+#[test]
+fn plain_images_still_load() {
+    let image = MachineImage::from_bytes(PLAIN_IMAGE).unwrap();
+    assert_eq!(image, MachineImage::of(&imaged_computer()));
+    assert_eq!(image.to_bytes(FORMAT_PLAIN), PLAIN_IMAGE);
+    assert_eq!(MachineImage::from_bytes(&PLAIN_IMAGE[..PLAIN_IMAGE.len() - 1]), Err(ImageError::Truncated { offset: PLAIN_IMAGE.len() - 1 }));
+    let mut small = Computer::with_memory_size(100);
+    assert_eq!(image.restore_into(&mut small), Err(ImageError::MemorySize { image: 4000, computer: 100 }));
+}
+
+const DISPATCHES: [Dispatch; 3] = [Dispatch::Enum, Dispatch::Boxed, Dispatch::Table];
+
+/// A machine with every register and the first 200 words set, for running one word on.
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+fn seeded_machine(dispatch: Dispatch) -> Computer {
+    let mut computer = Computer::default();
+    computer.dispatch = dispatch;
+    computer.verify_invariants = true;
+    for address in 0..200 {
+        computer.write_mem(address, Word::new(address % 3 != 0, [0, 1, (address % 7) as u8, (address * 5 % 64) as u8, (address % 64) as u8]));
+    }
+    computer.ra = Word::new(false, [0, 0, 3, 2, 1]);
+    computer.rx = Word::new(true, [1, 2, 3, 4, 5]);
+    computer.ri1 = Word::new(true, [0, 0, 0, 0, 3]);
+    computer.ri2 = Word::new(false, [0, 0, 0, 0, 1]);
+    computer.ri3 = Word::new(true, [0, 0, 0, 0, 4]);
+    computer.ri4 = Word::new(false, [0, 0, 0, 0, 1]);
+    computer.ri5 = Word::new(true, [0, 0, 0, 0, 5]);
+    computer.ri6 = Word::new(false, [0, 0, 0, 0, 9]);
+    computer.rj = Word::new(true, [0, 0, 0, 0, 77]);
+    computer.pc = 500;
+    computer
+}
+
+/// How a run under one dispatcher ended: its result, steps, and machine.
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+fn run_dispatched(mut computer: Computer, steps: usize) -> (Result<(), MixError>, usize, MachineImage) {
+    let mut taken = 0;
+    let mut result = Ok(());
+    while taken < steps && !computer.is_halted() && result.is_ok() {
+        result = computer.step();
+        taken += 1;
+    }
+    (result, taken, MachineImage::of(&computer))
+}
+
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+#[test]
+fn dispatch_parity() {
+    let mut words = 0;
+    for opcode in 0..64u8 {
+        for field in 0..64u8 {
+            let word = Word::new(field % 2 == 0, [0, 40 + field, field % 7, field, opcode]);
+            // Some words panic in the instruction functions; every dispatcher must panic alike.
+            let outcomes: Vec<_> = DISPATCHES.iter().map(|dispatch| {
+                let mut computer = seeded_machine(*dispatch);
+                computer.write_mem(500, word);
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run_dispatched(computer, 1))).ok()
+            }).collect();
+            assert!(outcomes.iter().all(|outcome| *outcome == outcomes[0]), "[{}] differs: {:?}", word, outcomes.iter().map(|outcome| outcome.as_ref().map(|outcome| &outcome.0)).collect::<Vec<_>>());
+            words += 1;
+        }
+    }
+    println!("{} words agree", words);
+
+    type Setup = Box<dyn Fn(&mut Computer)>;
+    let programs: Vec<(&str, Setup)> = vec![
+        ("factorial_stack", Box::new(|computer| {
+            stack::register(computer, 300..310).unwrap();
+            loader::parse_raw(FACTORIAL_STACK).unwrap().load_into(computer);
+        })),
+        ("self_modifying", Box::new(|computer| loader::parse_raw(SELF_MODIFYING).unwrap().load_into(computer))),
+        ("decode_mix", Box::new(|computer| loader::parse_raw(DECODE_MIX).unwrap().load_into(computer))),
+        ("program_map", Box::new(|computer| assemble(PROGRAM_MAP_SOURCE).unwrap().load_into(computer))),
+        ("stub_caller", Box::new(|computer| {
+            let printer = Channel::new(10);
+            computer.attach(PRINTER_UNIT, ChannelEnd::producer(&printer));
+            assemble(STUB_CALLER).unwrap().load_into(computer);
+        })),
+    ];
+    for (name, setup) in programs.iter() {
+        let outcomes: Vec<_> = DISPATCHES.iter().map(|dispatch| {
+            let mut computer = Computer::default();
+            computer.dispatch = *dispatch;
+            setup(&mut computer);
+            run_dispatched(computer, 10_000)
+        }).collect();
+        println!("{}: {:?} after {} steps", name, outcomes[0].0, outcomes[0].1);
+        assert!(outcomes.iter().all(|outcome| *outcome == outcomes[0]), "{} differs", name);
+    }
+
+    let mut computer = Computer::default();
+    computer.dispatch = Dispatch::Table;
+    computer.reset();
+    assert_eq!(computer.dispatch, Dispatch::Table);
+}
+
+/// The three workloads of `dispatch_benchmark`, each looping `n` times: arithmetic, a
+/// memory copy, and jumps through a chain of comparisons.
+fn dispatch_workloads(n: i64) -> Vec<(&'static str, Vec<Word>)> {
+    let encode = |mnemonic: &str, address: i64, index: u8, field: Option<u8>| opcodes::encode(mnemonic, address, index, field).unwrap();
+    let arithmetic = vec![
+        encode("ENT1", n, 0, None),
+        encode("ADD", 100, 0, None),                // 1
+        encode("SUB", 101, 0, None),
+        encode("INCA", 3, 0, None),
+        encode("CMPA", 100, 0, None),
+        encode("DEC1", 1, 0, None),
+        encode("J1P", 1, 0, None),
+        encode("HLT", 0, 0, None),
+    ];
+    let copy = vec![
+        encode("ENT1", n, 0, None),
+        encode("LDA", 1000, 1, None),               // 1
+        encode("STA", 2000, 1, None),
+        encode("LDX", 1000, 1, Some(13)),
+        encode("STX", 3000, 1, Some(45)),
+        encode("DEC1", 1, 0, None),
+        encode("J1P", 1, 0, None),
+        encode("HLT", 0, 0, None),
+    ];
+    let jumps = vec![
+        encode("ENT1", n, 0, None),
+        encode("ENTA", 0, 1, None),                 // 1: rA = rI1
+        encode("CMPA", 100, 0, None),
+        encode("JL", 6, 0, None),
+        encode("JMP", 8, 0, None),
+        encode("JE", 8, 0, None),
+        encode("JMP", 8, 0, None),                  // 6
+        encode("JMP", 9, 0, None),
+        encode("DEC1", 1, 0, None),                 // 8
+        encode("J1P", 1, 0, None),                  // 9
+        encode("HLT", 0, 0, None),
+    ];
+    vec![("arithmetic", arithmetic), ("memory copy", copy), ("jumps", jumps)]
+}
+
+/// Times the three dispatchers on each workload of `dispatch_workloads`. The dispatchers
+/// take turns over twenty rounds, and each is reported by its fastest round, which keeps
+/// the numbers steady on a busy machine.
+#[test]
+#[ignore]
+fn dispatch_benchmark() {
+    for (name, program) in dispatch_workloads(900).iter() {
+        let mut best = [f64::MAX; 3];
+        for _ in 0..20 {
+            for (i, dispatch) in DISPATCHES.iter().enumerate() {
+                let mut computer = Computer::default();
+                computer.dispatch = *dispatch;
+                let mut steps = 0u64;
+                let start = std::time::Instant::now();
+                for _ in 0..10 {
+                    computer.reset();
+                    computer.load(0, program);
+                    computer.write_mem(100, Word::from_value(500));
+                    computer.write_mem(101, Word::from_value(2));
+                    while !computer.is_halted() {
+                        computer.step().unwrap();
+                        steps += 1;
+                    }
+                }
+                best[i] = best[i].min(start.elapsed().as_nanos() as f64 / steps as f64);
+            }
+        }
+        println!("{:<12} Enum {:>6.1} ns/step, Boxed {:>6.1} ns/step, Table {:>6.1} ns/step", name, best[0], best[1], best[2]);
+    }
+}
+
+/// A machine state and an instruction to execute on it as the word at `pc`, for
+/// `instructions_keep_invariants`.
+#[derive(Clone, Debug, PartialEq)]
+struct InstructionCase {
+    /// rA, rX, rI1 to rI6 and rJ.
+    registers: [Word; 9],
+    overflow: bool,
+    comparison: ComparisonFlag,
+    /// The words of memory other than +0.
+    memory: Vec<(usize, Word)>,
+    /// Whether a tape is attached as unit 0.
+    tape: bool,
+    pc: usize,
+    word: Word,
+}
+
+const REGISTER_NAMES: [&str; 9] = ["rA", "rX", "rI1", "rI2", "rI3", "rI4", "rI5", "rI6", "rJ"];
+
+impl InstructionCase {
+    fn machine(&self) -> Computer {
+        let mut computer = Computer::default();
+        let [ra, rx, ri1, ri2, ri3, ri4, ri5, ri6, rj] = self.registers;
+        computer.ra = ra;
+        computer.rx = rx;
+        computer.ri1 = ri1;
+        computer.ri2 = ri2;
+        computer.ri3 = ri3;
+        computer.ri4 = ri4;
+        computer.ri5 = ri5;
+        computer.ri6 = ri6;
+        computer.rj = rj;
+        computer.overflow_flag = self.overflow;
+        computer.comparison_flag = self.comparison;
+        for (address, word) in self.memory.iter() {
+            computer.write_mem(*address, *word);
+        }
+        #[cfg(feature = "peripherals")]
+        if self.tape {
+            computer.attach_tape(MagneticTapeUnit::new(0, [Word::from_value(7); BLOCK_WORDS]));
+        }
+        computer.pc = self.pc;
+        computer
+    }
+
+    /// The case as monitor commands, which set the machine up and step the instruction.
+    fn set_commands(&self) -> String {
+        let notation = |word: &Word| format!("{} {}", if word.positive { '+' } else { '-' },
+                                             word.bytes.iter().map(|byte| byte.to_string()).collect::<Vec<_>>().join(" "));
+        let mut commands = String::new();
+        for (name, register) in REGISTER_NAMES.iter().zip(self.registers.iter()) {
+            if *register != Word::default() {
+                commands.push_str(&format!("set {} = {}\n", name, notation(register)));
+            }
+        }
+        if self.overflow {
+            commands.push_str("set overflow = on\n");
+        }
+        if self.comparison != ComparisonFlag::equal {
+            commands.push_str(&format!("set comparison = {:?}\n", self.comparison));
+        }
+        for (address, word) in self.memory.iter() {
+            commands.push_str(&format!("set mem {} = {}\n", address, notation(word)));
+        }
+        commands.push_str(&format!("set mem {} = {}\nset pc = {}\ns\n", self.pc, notation(&self.word), self.pc));
+        commands
+    }
+
+    /// Simpler cases, each differing from this one in one part.
+    fn simplifications(&self) -> Vec<InstructionCase> {
+        let mut simpler = Vec::new();
+        let mut with = |change: &dyn Fn(&mut InstructionCase)| {
+            let mut case = self.clone();
+            change(&mut case);
+            if case != *self {
+                simpler.push(case);
+            }
+        };
+        for i in 0..9 {
+            with(&|case| case.registers[i] = Word::default());
+            with(&|case| case.registers[i].bytes[..3].copy_from_slice(&[0, 0, 0]));
+            with(&|case| case.registers[i].positive = true);
+        }
+        with(&|case| case.overflow = false);
+        with(&|case| case.comparison = ComparisonFlag::equal);
+        for i in 0..self.memory.len() {
+            with(&|case| { case.memory.remove(i); });
+        }
+        with(&|case| case.tape = false);
+        with(&|case| case.pc = 0);
+        with(&|case| case.word.positive = true);
+        with(&|case| case.word.bytes[..2].copy_from_slice(&[0, 0]));
+        with(&|case| case.word.bytes[2] = 0);
+        simpler
+    }
+}
+
+/// Shrinks `case`, for which `fails` gives a reason, to a case no simplification of which
+/// still fails.
+fn shrink_case(mut case: InstructionCase, fails: impl Fn(&InstructionCase) -> Option<String>) -> (InstructionCase, String) {
+    let mut reason = fails(&case).expect("the case to shrink fails");
+    'shrinking: loop {
+        for simpler in case.simplifications() {
+            if let Some(simpler_reason) = fails(&simpler) {
+                case = simpler;
+                reason = simpler_reason;
+                continue 'shrinking;
+            }
+        }
+        return (case, reason);
+    }
+}
+
+/// A word of random bytes, or one near the edges: zero, or one, or the last address.
+fn arbitrary_word(rng: &mut Prng) -> Word {
+    let positive = rng.bool();
+    match rng.below(4) {
+        0 => Word::new(positive, [0; 5]),
+        1 => Word::new(positive, [0, 0, 0, 0, 1]),
+        2 => Word { positive, ..Word::from_value(DEFAULT_MEMORY_SIZE as i64 - 1) },
+        _ => {
+            let mut word = Word::new(positive, [0; 5]);
+            for byte in word.bytes.iter_mut() {
+                *byte = rng.below(crate::portability::CONFIGURED_BYTE_SIZE as u64) as u8;
+            }
+            word
+        },
+    }
+}
+
+/// A random state and a random word of `operation`, with addresses near the ends of
+/// memory and index registers whose unused bytes may be dirty.
+fn arbitrary_case(rng: &mut Prng, operation: &opcodes::Operation) -> InstructionCase {
+    let size = DEFAULT_MEMORY_SIZE as i64;
+    let largest = (crate::portability::CONFIGURED_BYTE_SIZE * crate::portability::CONFIGURED_BYTE_SIZE - 1) as i64;
+    let mut registers = [Word::default(); 9];
+    for register in registers.iter_mut() {
+        *register = arbitrary_word(rng);
+    }
+    for ri in registers[2..8].iter_mut() {
+        let value: i64 = [0, 1, size - 1, size, rng.in_range(0..=largest)][rng.below(5) as usize];
+        ri.bytes[3..].copy_from_slice(&Word::from_value(value).bytes[3..]);
+        if rng.bool() {
+            ri.bytes[..3].copy_from_slice(&[0, 0, 0]);
+        }
+    }
+    let address: i64 = [0, 1, size - 1, size, largest, rng.in_range(0..=largest)][rng.below(6) as usize];
+    let index = if rng.below(16) == 0 { rng.in_range(7..=63) as u8 } else { rng.below(7) as u8 };
+    let field = match operation.field {
+        opcodes::FieldRule::Default(_) if rng.below(8) > 0 => {
+            let right = rng.below(6) as u8;
+            rng.below(right as u64 + 1) as u8 * 8 + right
+        },
+        opcodes::FieldRule::Fixed(field) => field,
+        opcodes::FieldRule::Unit if rng.below(8) > 0 => rng.below(21) as u8,
+        _ => rng.below(64) as u8,
+    };
+    let mut word = Word::from_instruction_in_base(address, index, field, operation.code, crate::portability::CONFIGURED_BYTE_SIZE as u16);
+    word.positive = rng.bool();
+    let pc = [0, size - 1, rng.in_range(0..=size - 1)][rng.below(3) as usize] as usize;
+    let mut memory = Vec::new();
+    let referenced = DecodedInstruction::of(&word).address + if (1..=6).contains(&index) {
+        let ri = registers[1 + index as usize];
+        ri.field_value(FieldSpec::of(4, 5)) * if ri.positive { 1 } else { -1 }
+    } else { 0 };
+    for address in [referenced, referenced + 1, rng.in_range(0..=size - 1)].iter() {
+        if (0..size).contains(address) && *address != pc as i64 && memory.iter().all(|(taken, _)| *taken != *address as usize) {
+            memory.push((*address as usize, arbitrary_word(rng)));
+        }
+    }
+    let comparison = *rng.choose(&[ComparisonFlag::less, ComparisonFlag::equal, ComparisonFlag::greater]);
+    InstructionCase { registers, overflow: rng.bool(), comparison, memory, tape: rng.bool(), pc, word }
+}
+
+/// Executes the word of `case` on its machine, and gives the first invariant it breaks.
+fn broken_invariant(case: &InstructionCase, operation: &opcodes::Operation) -> Option<String> {
+    let before = case.machine();
+    let executed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut computer = before.clone();
+        let result = computer.execute(&case.word);
+        (computer, result)
+    }));
+    let (after, result) = match executed {
+        Ok(executed) => executed,
+        Err(panic) => {
+            let message = panic.downcast_ref::<String>().cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+                .unwrap_or_default();
+            return Some(format!("panicked: {}", message));
+        },
+    };
+    if before.check_invariants().is_ok() {
+        if let Err(violation) = after.check_invariants() {
+            return Some(violation.to_string());
+        }
+    }
+    if let Err(error) = &result {
+        let unimplemented = !Computer::core_implements(case.word.opcode(), case.word.field());
+        let documented = error.code() == "invalid-instruction"
+            || (unimplemented && error.code() == "unimplemented-instruction")
+            || operation.errors().contains(&error.code());
+        if !documented {
+            return Some(format!("{} isn't an error {} documents", error.code(), operation.mnemonic));
+        }
+    }
+    if after.pc != before.pc && !operation.may_jump() {
+        return Some(format!("{} moved pc to {}", operation.mnemonic, after.pc));
+    }
+    let address = DecodedInstruction::of(&case.word).address + match case.word.index() {
+        index @ 1..=6 => {
+            let ri = case.registers[1 + index as usize];
+            ri.field_value(FieldSpec::of(4, 5)) * if ri.positive { 1 } else { -1 }
+        },
+        _ => 0,
+    };
+    let writable = match operation.writes() {
+        opcodes::Writes::Nothing => 0..0,
+        opcodes::Writes::Word => address..address + 1,
+        opcodes::Writes::Block => address..address + BLOCK_WORDS as i64,
+        opcodes::Writes::Moved => {
+            let from = before.ri1.field_value(FieldSpec::of(4, 5));
+            from..from + case.word.field() as i64
+        },
+    };
+    for (address, (was, is)) in before.memory.iter().zip(after.memory.iter()).enumerate() {
+        if was != is && !writable.contains(&(address as i64)) {
+            return Some(format!("{} wrote {}, outside of {:?}", operation.mnemonic, address, writable));
+        }
+        if was != is && is.bytes.iter().any(|byte| *byte as i128 >= crate::portability::CONFIGURED_BYTE_SIZE) {
+            return Some(format!("{} wrote [{}] to {}", operation.mnemonic, is, address));
+        }
+    }
+    let registers = [after.ra, after.rx, after.ri1, after.ri2, after.ri3, after.ri4, after.ri5, after.ri6, after.rj];
+    for (name, register) in REGISTER_NAMES.iter().zip(registers.iter()) {
+        if register.bytes.iter().any(|byte| *byte as i128 >= crate::portability::CONFIGURED_BYTE_SIZE) {
+            return Some(format!("{} left [{}] in {}", operation.mnemonic, register, name));
+        }
+    }
+    None
+}
+
+/// How many cases `instructions_keep_invariants` tries for each operation: a few in the
+/// normal suite, and without end under the `fuzz` feature.
+const INSTRUCTION_CASES: usize = if cfg!(feature = "fuzz") { usize::MAX } else { 48 };
+
+#[test]
+fn instructions_keep_invariants() {
+    let mut rng = test_prng("instructions_keep_invariants");
+    for n in 0..INSTRUCTION_CASES {
+        for operation in opcodes::OPCODES.iter() {
+            let case = arbitrary_case(&mut rng, operation);
+            if broken_invariant(&case, operation).is_some() {
+                let (minimal, reason) = shrink_case(case, |case| broken_invariant(case, operation));
+                panic!("{} breaks an invariant: {}\n{}", operation.mnemonic, reason, minimal.set_commands());
+            }
+        }
+        if cfg!(feature = "fuzz") && n % 1000 == 999 {
+            println!("{} cases of each operation", n + 1);
+        }
+    }
+}
+
+/// How many arbitrary machines `steps_never_panic` steps: enough in the normal suite to
+/// reach every opcode and field many times over, and without end under the `fuzz` feature.
+const STEP_CASES: usize = if cfg!(feature = "fuzz") { usize::MAX } else { 100_000 };
+
+/// A machine with every register, the flags, `pc`, and the words around `pc` and the
+/// addresses near the ends of memory arbitrary, and an arbitrary word at `pc`, stepped
+/// once on a computer with every extension registered, never panics.
+#[test]
+fn steps_never_panic() {
+    let mut rng = test_prng("steps_never_panic");
+    let mut base = Computer::default();
+    status_flags::register(&mut base).unwrap();
+    stack::register(&mut base, 3900..4000).unwrap();
+    float::register(&mut base).unwrap();
+    #[cfg(feature = "mixb")]
+    mixb::register(&mut base).unwrap();
+    #[cfg(feature = "peripherals")]
+    base.attach_tape(MagneticTapeUnit::new(0, [Word::from_value(7); BLOCK_WORDS]));
+    let size = DEFAULT_MEMORY_SIZE;
+    for n in 0..STEP_CASES {
+        let mut computer = base.clone();
+        for register in vec![&mut computer.ra, &mut computer.rx, &mut computer.ri1, &mut computer.ri2, &mut computer.ri3,
+                         &mut computer.ri4, &mut computer.ri5, &mut computer.ri6, &mut computer.rj] {
+            *register = arbitrary_word(&mut rng);
+            if rng.bool() {
+                register.bytes[..3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+        computer.overflow_flag = rng.bool();
+        computer.comparison_flag = *rng.choose(&[ComparisonFlag::less, ComparisonFlag::equal, ComparisonFlag::greater]);
+        let anywhere = [rng.below(size as u64) as usize, rng.below(size as u64) as usize];
+        for address in [0, 1, size - 2, size - 1, anywhere[0], anywhere[1]].iter() {
+            computer.memory[*address] = arbitrary_word(&mut rng);
+        }
+        let mut word = Word::new(rng.bool(), [0; 5]);
+        for byte in word.bytes.iter_mut() {
+            *byte = rng.below(crate::portability::CONFIGURED_BYTE_SIZE as u64) as u8;
+        }
+        if rng.bool() {
+            // An address near the ends of memory, for indexing to carry it past them.
+            let address = *rng.choose(&[0, 1, size - 1, size, 4095]);
+            word.bytes[..2].copy_from_slice(&Word::from_value(address as i64).bytes[3..]);
+        }
+        if rng.below(16) > 0 {
+            word.bytes[2] = rng.below(7) as u8;
+        }
+        if rng.below(4) == 0 {
+            // The opcode the extensions share, whose addresses aren't checked before they run.
+            word.bytes[4] = Opcode::SPECIAL.code();
+        }
+        computer.pc = *rng.choose(&[0, size - 1, size, anywhere[0]]);
+        if let Some(at_pc) = computer.memory.get_mut(computer.pc) {
+            *at_pc = word;
+        }
+        let stepped = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| computer.clone().step()));
+        assert!(stepped.is_ok(), "Stepping [{}] at {} panicked, with rI1 to rI6 {:?}", word, computer.pc,
+                [computer.ri1, computer.ri2, computer.ri3, computer.ri4, computer.ri5, computer.ri6]);
+        if cfg!(feature = "fuzz") && n % 10_000 == 9_999 {
+            println!("{} machines stepped", n + 1);
+        }
+    }
+}
+
+/// Builds the audited release binary, which fails to link when a function marked with
+/// `no_panic!` may panic. Takes a minute, in a target directory of its own: `cargo test
+/// -- --ignored no_panic_audit_links`.
+#[test]
+#[ignore]
+fn no_panic_audit_links() {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = std::process::Command::new(&cargo)
+        .args(["build", "--quiet", "--release", "--features", "no-panic-audit"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("CARGO_TARGET_DIR", concat!(env!("CARGO_MANIFEST_DIR"), "/target/no-panic-audit"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn former_panics_are_errors() {
+    let mut computer = Computer::default();
+    computer.pc = 12;
+    computer.memory[12] = Word::from_instruction(5, 0, 2, 48);
+
+    // An index outside rI1 to rI6 in an instruction built by hand.
+    let invalid_index = |index| MixError::InvalidInstruction { pc: 12, word: Word::from_instruction(5, 0, 2, 48), error: DecodeError::InvalidIndex(index) };
+    assert_eq!(register_for_index(&mut computer, 7).unwrap_err(), invalid_index(7));
+    assert_eq!(register_for_index(&mut computer, 0).unwrap_err(), invalid_index(0));
+    assert_eq!(EntI::new_unchecked(9, 5, true, false).execute_on(&mut computer), Err(invalid_index(9)));
+
+    // Addresses outside of memory, read, written, and stored into.
+    let outside = MixError::AddressOutOfRange { pc: 12, address: 4000 };
+    assert_eq!(computer.read_field(4000, FieldSpec::WHOLE), Err(outside.clone()));
+    assert_eq!(computer.store_field(4000, Word::default(), FieldSpec::WHOLE), Err(outside.clone()));
+    assert_eq!(computer.word_at(4000), Err(outside.clone()));
+    assert_eq!(StoreA::new_unchecked(4000, FieldSpec::WHOLE).execute_on(&mut computer), Err(outside.clone()));
+    assert_eq!(LoadA::new_unchecked(4000, FieldSpec::WHOLE, false).execute_on(&mut computer), Err(outside));
+    assert_eq!(computer.block_range(usize::MAX, BLOCK_WORDS), Err(MixError::BlockOutOfRange { pc: 12, address: usize::MAX, words: BLOCK_WORDS }));
+
+    // Extensions aren't checked for their addresses before they run, so indexing past
+    // the end of memory reaches them.
+    status_flags::register(&mut computer).unwrap();
+    #[cfg(feature = "mixb")]
+    mixb::register(&mut computer).unwrap();
+    computer.ri1 = Word::from_value(5);
+    #[cfg_attr(not(feature = "mixb"), allow(unused_mut))]
+    let mut extensions = vec![(status_flags::OPCODE, status_flags::STF_FIELD), (status_flags::OPCODE, status_flags::LDF_FIELD)];
+    #[cfg(feature = "mixb")]
+    extensions.push((mixb::LOGIC_OPCODE, mixb::AND_FIELD));
+    for (opcode, field) in extensions {
+        let word = Word::from_instruction(3999, 1, field, opcode);
+        assert_eq!(computer.execute(&word), Err(MixError::AddressOutOfRange { pc: 12, address: 4004 }), "{}", word);
+    }
+
+    // Shifts of any amount, which once overflowed or sliced.
+    let (high, low) = (Word::new(false, [1, 2, 3, 4, 5]), Word::new(true, [6, 7, 8, 9, 10]));
+    assert_eq!(single_word_left_shift(&high, usize::MAX, true), single_word_left_shift(&high, usize::MAX % 5, true));
+    assert_eq!(single_word_right_shift(&high, usize::MAX, true), single_word_right_shift(&high, usize::MAX % 5, true));
+    assert_eq!(single_word_left_shift(&high, usize::MAX, false), Word::new(false, [0; 5]));
+    assert_eq!(double_word_left_cycle(&high, &low, usize::MAX), double_word_left_cycle(&high, &low, usize::MAX % 10));
+    assert_eq!(double_word_left_shift(&high, &low, usize::MAX), (Word::new(false, [0; 5]), Word::new(true, [0; 5])));
+    assert_eq!(double_word_right_shift(&high, &low, 3), (Word::new(false, [0, 0, 0, 1, 2]), Word::new(true, [3, 4, 5, 6, 7])));
+    assert_eq!(double_word_left_shift(&high, &low, 7), (Word::new(false, [8, 9, 10, 0, 0]), Word::new(true, [0; 5])));
+}
+
+#[test]
+fn load_outside_memory_reports_pc_and_address() {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(0, 0, 2, Opcode::NOP.code());         // NOP
+    computer.memory[1] = Word::from_instruction(4500, 0, 5, Opcode::LDA.code());      // LDA 4500
+    assert_eq!(computer.run(), Err(MixError::AddressOutOfRange { pc: 1, address: 4500 }));
+    assert_eq!(computer.pc, 1);
+
+    // The index register takes an address in memory outside of it.
+    let mut computer = Computer::default();
+    computer.ri2 = Word::from_value(1500);
+    computer.memory[0] = Word::from_instruction(3000, 2, 5, Opcode::LDA.code());      // LDA 3000,2
+    let error = computer.run().unwrap_err();
+    assert_eq!(error, MixError::AddressOutOfRange { pc: 0, address: 4500 });
+    assert_eq!(error.pc(), Some(0));
+}
+
+#[test]
+fn instruction_failures_shrink_to_set_commands() {
+    let case = InstructionCase {
+        registers: [Word::new(false, [1, 2, 3, 4, 5]), Word::new(false, [0, 0, 0, 0, 9]), Word::new(true, [5, 5, 5, 0, 3]),
+                    Word::default(), Word::default(), Word::default(), Word::default(), Word::default(), Word::new(true, [0, 0, 0, 1, 2])],
+        overflow: true,
+        comparison: ComparisonFlag::less,
+        memory: vec![(6, Word::from_value(12)), (3999, Word::new(false, [0, 0, 0, 0, 1]))],
+        tape: true,
+        pc: 3999,
+        word: opcodes::encode("LDA", 3, 1, None).unwrap(),
+    };
+    // Stands in for a broken invariant: the load doesn't reach the word at 6.
+    let fails = |case: &InstructionCase| {
+        let mut computer = case.machine();
+        computer.execute(&case.word).ok()?;
+        (computer.ra == Word::from_value(12) && computer.rx.field_value(FieldSpec::WHOLE) < 0).then(|| "loaded 12".to_string())
+    };
+    let (minimal, reason) = shrink_case(case, fails);
+    assert_eq!(reason, "loaded 12");
+    let commands = minimal.set_commands();
+    println!("{}", commands);
+    assert_eq!(commands, "\
+set rX = - 0 0 0 0 9
+set rI1 = + 0 0 0 0 3
+set mem 6 = + 0 0 0 0 12
+set mem 0 = + 0 3 1 5 8
+set pc = 0
+s
+");
+
+    #[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+    {
+        let script = format!("{}assert rA = 12\nassert pc = 1\n", commands);
+        assert_eq!(script::run_script(&script, std::path::Path::new("."), &mut Vec::new()), Ok(()));
+        let failure = script::run_script("set rQ = 1\n", std::path::Path::new("."), &mut Vec::new()).unwrap_err();
+        assert_eq!(failure.message, "rQ is not a register, one of rA, rX, rI1 to rI6, or rJ");
+    }
+}
+
+/// Explains the instruction `word` executed at 0 on `computer`.
+#[cfg(feature = "debugger")]
+fn explained(mut computer: Computer, word: Word) -> String {
+    computer.memory[0] = word;
+    computer.pc = 0;
+    let instruction = DecodedInstruction::of(&word);
+    let before = Snapshot::of(&computer);
+    computer.step().unwrap();
+    explain::explain(&instruction, &before.delta(&computer), &computer)
+}
+
+#[cfg(feature = "debugger")]
+#[test]
+fn explanations_of_each_family() {
+    let mut computer = Computer::default();
+    computer.ri3 = Word::from_value(531);
+    computer.memory[2531] = Word::new(false, [0, 0, 0, 9, 1]);
+    computer.memory[1000] = Word::from_value(7);
+    computer.ra = Word::from_value(5);
+    computer.rx = Word::from_value(3);
+    computer.ri1 = Word::from_value(3000);
+    computer.overflow_flag = true;
+    computer.comparison_flag = ComparisonFlag::greater;
+    let cases = [
+        (Word::from_instruction(0, 0, 0, 0), "NOP 0: did nothing"),
+        (Word::from_instruction(2000, 3, 13, 8), "LDA 2000,3(1:5): loaded bytes 1–5 of memory cell 2531 (effective address 2000 + rI3=531) into rA, which is now +0 0 0 9 1; the sign was not copied because the field excludes position 0"),
+        (Word::from_instruction(1000, 0, 5, 1), "ADD 1000: added all of memory cell 1000 to rA, which is now +0 0 0 0 12"),
+        (Word::from_instruction(0, 0, 1, 5), "CHAR 0: converted the number in rA to the character codes +30 30 30 30 30 and +30 30 30 30 35 in rA and rX"),
+        (Word::from_instruction(0, 0, 8, 5), "opcode 5 with field 8: an extension instruction, which has no explanation"),
+        (Word::from_instruction(0, 0, 2, 5), "HLT 0: halted the machine"),
+        (Word::from_instruction(1, 0, 2, 6), "SLAX 1: shifted rA and rX left together by 1 byte; they are now +0 0 0 5 0 and +0 0 0 3 0"),
+        (Word::from_instruction(1000, 0, 2, 7), "MOVE 1000(2): copied 2 words from memory cell 1000 to the cells from rI1=3000"),
+        (Word::from_instruction(1000, 0, 29, 24), "STA 1000(3:5): stored the rightmost 3 bytes of rA into bytes 3–5 of memory cell 1000, which is now +0 0 0 0 5"),
+        (Word::from_instruction(1000, 0, 5, 33), "STZ 1000: cleared all of memory cell 1000, which is now +0 0 0 0 0"),
+        (Word::from_instruction(100, 0, 0, 39), "JMP 100: jumped to 100, saving the address to return to, 1, in rJ"),
+        (Word::from_instruction(100, 0, 2, 39), "JOV 100: jumped to 100 because the overflow toggle was on, and turned it off"),
+        (Word::from_instruction(100, 0, 4, 39), "JL 100: didn't jump because the comparison indicator is greater, not less"),
+        (Word::from_instruction(100, 0, 2, 40), "JAP 100: jumped to 100 because rA is positive (it is +0 0 0 0 5)"),
+        (Word::from_instruction(-4, 0, 0, 49), "INC1 -4: added -4 to rI1, which is now +0 0 0 11 180"),
+        (Word::from_instruction(1000, 0, 5, 56), "CMPA 1000: compared all of rA with all of memory cell 1000, so the comparison indicator is less"),
+    ];
+    for (word, expected) in cases.iter() {
+        let explanation = explained(computer.clone(), *word);
+        println!("{}", explanation);
+        assert_eq!(&explanation, expected);
+    }
+}
+
+#[cfg(all(feature = "debugger", feature = "peripherals"))]
+#[test]
+fn explanations_of_overflow_and_input_output() {
+    let mut computer = Computer::default();
+    computer.ra = Word::new(true, [255; 5]);
+    computer.memory[1000] = Word::from_value(1);
+    assert_eq!(explained(computer.clone(), Word::from_instruction(1000, 0, 5, 1)),
+               "ADD 1000: added all of memory cell 1000 to rA, which is now +0 0 0 0 0; the sum didn't fit, so the overflow toggle was turned on");
+    assert_eq!(explained(computer.clone(), Word::from_instruction(1000, 0, 5, 4)),
+               "DIV 1000: couldn't divide rA and rX by all of memory cell 1000: the divisor is zero or the quotient doesn't fit in rA, so the overflow toggle was turned on");
+
+    let mut computer = Computer::default();
+    computer.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    for (word, expected) in [
+        (Word::from_instruction(100, 0, 0, 34), "JBUS 100(0): didn't jump because unit 0 was ready"),
+        (Word::from_instruction(0, 0, 0, 35), "IOC 0(0): sent the control 0 to unit 0"),
+        (Word::from_instruction(1000, 0, 0, 37), "OUT 1000(0): started writing memory cells 1000–1099 to unit 0"),
+        (Word::from_instruction(1000, 0, 0, 36), "IN 1000(0): started reading a block from unit 0 into memory cells 1000–1099"),
+        (Word::from_instruction(100, 0, 0, 38), "JRED 100(0): jumped to 100 because unit 0 was ready"),
+    ].iter() {
+        assert_eq!(&explained(computer.clone(), *word), expected);
+    }
+}
+
+#[cfg(feature = "debugger")]
+#[test]
+fn every_operation_has_an_explanation() {
+    let missing: Vec<&str> = opcodes::OPCODES.iter()
+        .filter(|operation| explain::template_for(operation).is_none())
+        .map(|operation| operation.mnemonic)
+        .collect();
+    assert!(missing.is_empty(), "no explanation for {:?}", missing);
+}
+
+/// A spec for a maximum routine of `source`, called with n in rI1 and X[1] to X[8] at
+/// 1001 to 1008, leaving the maximum in rA and its index in rI2.
+#[cfg(feature = "analysis")]
+fn maximum_spec(source: &str) -> RoutineSpec {
+    let mut spec = RoutineSpec::new(assemble(source).unwrap(), 100);
+    spec.inputs = std::iter::once(Location::Register("rI1")).chain((1001..=1008).map(Location::Memory)).collect();
+    spec.outputs = vec![Location::Register("rA"), Location::Register("rI2")];
+    spec
+}
+
+#[cfg(feature = "analysis")]
+fn maximum_ranges(values: std::ops::RangeInclusive<i64>) -> Vec<std::ops::RangeInclusive<i64>> {
+    std::iter::once(1..=8).chain(std::iter::repeat_n(values, 8)).collect()
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn maximum_routines_are_equivalent() {
+    let original = maximum_spec(include_str!("../examples/maximum.mixal"));
+    let rotated = maximum_spec(include_str!("../examples/maximum_rotated.mixal"));
+    let strategy = Strategy::Randomized { cases: 500, seed: 1, ranges: maximum_ranges(-3..=3) };
+    assert_eq!(analysis::check_equivalence(original.clone(), rotated.clone(), strategy), Ok(EquivalenceResult::Equivalent { cases: 500 }));
+    let strategy = Strategy::Randomized { cases: 200, seed: 2, ranges: maximum_ranges(-1_000_000_000..=1_000_000_000) };
+    assert_eq!(analysis::check_equivalence(original, rotated, strategy), Ok(EquivalenceResult::Equivalent { cases: 200 }));
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn broken_optimization_gives_counterexample() {
+    let original = maximum_spec(include_str!("../examples/maximum.mixal"));
+    let broken = maximum_spec(include_str!("../fixtures/maximum_broken.mixal"));
+    let strategy = Strategy::Randomized { cases: 500, seed: 1, ranges: maximum_ranges(0..=2) };
+    let counterexample = match analysis::check_equivalence(original.clone(), broken.clone(), strategy.clone()).unwrap() {
+        EquivalenceResult::Differs(counterexample) => counterexample,
+        result => panic!("{:?}", result),
+    };
+    println!("{}", counterexample);
+    assert_eq!(analysis::check_equivalence(original, broken, strategy).unwrap(), EquivalenceResult::Differs(counterexample.clone()));
+
+    // The maximum is right, but the index of a tie is not.
+    let (a, b) = match (&counterexample.a, &counterexample.b) {
+        (RoutineOutcome::Finished { outputs: a, .. }, RoutineOutcome::Finished { outputs: b, .. }) => (a.clone(), b.clone()),
+        outcomes => panic!("{:?}", outcomes),
+    };
+    assert_eq!(a[0], b[0]);
+    let n = counterexample.inputs[0].field_value(FieldSpec::WHOLE) as usize;
+    let x = &counterexample.inputs[1..=n];
+    let index = |output: &[Word]| output[1].field_value(FieldSpec::WHOLE) as usize;
+    assert_eq!((x[index(&a) - 1], x[index(&b) - 1]), (a[0], a[0]));
+    assert!(index(&b) < index(&a));
+}
+
+/// The maximum routine of `examples/maximum.mixal` on X = 3, 9, -2, 9, 5, with its exit
+/// word, `EXIT JMP *`, at 3009.
+#[cfg(feature = "analysis")]
+fn maximum_harness(preserved: &[&'static str], index: &'static str) -> Harness {
+    [3, 9, -2, 9, 5].iter().enumerate()
+        .fold(Harness::for_routine(3000), |harness, (k, value)| harness.input_mem(1001 + k, Word::from_value(*value)))
+        .input(Location::Register("rI1"), Word::from_value(5))
+        .linkage(Linkage::Stj { exit_word: 3009 })
+        .output(Location::Register("rA"))
+        .output(Location::Register(index))
+        .preserve(preserved)
+        .expect_exit_within(100)
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn harness_runs_maximum_routine() {
+    let program = assemble(include_str!("../examples/maximum.mixal")).unwrap();
+    let result = maximum_harness(&["rX", "rI1", "rI4", "rI5", "rI6"], "rI2").run(&program).unwrap();
+    assert_eq!(result.outputs, vec![Word::from_value(9), Word::from_value(4)]);
+    assert!(result.preserved());
+    assert_eq!((result.steps, result.cycles), (26, 33));
+
+    // Called as the driver calls it, the routine returns to the HLT after the call.
+    assert!(matches!(Harness::for_routine(100).run(&program), Err(HarnessError::Halted { pc: 101, .. })));
+    assert_eq!(maximum_harness(&[], "rI2").expect_exit_within(10).run(&program), Err(HarnessError::Hung { steps: 10 }));
+    assert!(matches!(maximum_harness(&[], "rI2").linkage(Linkage::Stj { exit_word: 3008 }).run(&program),
+                     Err(HarnessError::NotAnExit { exit_word: 3008, .. })));
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn harness_detects_clobbered_registers() {
+    // Leaving the index in rI4 keeps rI2 intact.
+    let source = include_str!("../examples/maximum.mixal");
+    let in_ri4 = assemble(&source.replace("ENT2 0,3", "ENT4 0,3")).unwrap();
+    let result = maximum_harness(&["rI2"], "rI4").run(&in_ri4).unwrap();
+    assert_eq!(result.outputs, vec![Word::from_value(9), Word::from_value(4)]);
+    assert!(result.preserved());
+
+    // The book's routine leaves it in rI2, which a caller expecting rI2 preserved loses.
+    let book = assemble(source).unwrap();
+    let mut computer = Computer::default();
+    book.load_into(&mut computer);
+    computer.ri2 = Word::from_value(77);
+    let result = maximum_harness(&["rX", "rI2"], "rI4").run_on(&computer).unwrap();
+    assert_eq!(result.clobbered, vec![("rI2", Word::from_value(77), Word::from_value(4))]);
+    assert!(!result.preserved());
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn exhaustive_equivalence_and_hangs() {
+    // rA + rX, two ways.
+    let routine = |source: &str| {
+        let mut spec = RoutineSpec::new(assemble(source).unwrap(), 0);
+        spec.inputs = vec![Location::Register("rA"), Location::Memory(100)];
+        spec.outputs = vec![Location::Register("rA"), Location::Overflow, Location::Comparison];
+        spec
+    };
+    let add = routine(" ORIG 0\n ADD 100\n HLT\n END 0");
+    let twice = routine(" ORIG 0\n STA 101\n LDA 100\n ADD 101\n HLT\n END 0");
+    let domain: Vec<Word> = [-2, -1, 0, 1, 2].iter().map(|value| Word::from_value(*value)).collect();
+    let strategy = Strategy::Exhaustive { domains: vec![domain.clone(), vec![Word::from_value(3), Word::from_value(4)]] };
+    assert_eq!(analysis::check_equivalence(add.clone(), twice.clone(), strategy), Ok(EquivalenceResult::Equivalent { cases: 10 }));
+    // A zero sum takes the sign of rA, which is M in the second, so -2 + 2 tells them apart.
+    let strategy = Strategy::Exhaustive { domains: vec![domain.clone(), domain.clone()] };
+    match analysis::check_equivalence(add.clone(), twice.clone(), strategy).unwrap() {
+        EquivalenceResult::Differs(counterexample) => {
+            assert_eq!(counterexample.inputs, vec![Word::from_value(-2), Word::from_value(2)]);
+        },
+        result => panic!("{:?}", result),
+    }
+
+    // The overflow toggle isn't an input, so it is random, and only one of them clears it.
+    let clears = routine(" ORIG 0\n JOV DONE\nDONE NOP\n ADD 100\n HLT\n END 0");
+    let strategy = Strategy::Exhaustive { domains: vec![domain.clone(), vec![Word::default()]] };
+    match analysis::check_equivalence(add.clone(), clears, strategy.clone()).unwrap() {
+        EquivalenceResult::Differs(counterexample) => assert_ne!(counterexample.a, counterexample.b),
+        result => panic!("{:?}", result),
+    }
+
+    let mut hangs = routine(" ORIG 0\nLOOP NOP\n JMP LOOP\n END 0");
+    hangs.step_limit = 50;
+    match analysis::check_equivalence(add.clone(), hangs, strategy.clone()).unwrap() {
+        EquivalenceResult::Differs(counterexample) => assert_eq!(counterexample.b, RoutineOutcome::Hung { steps: 50 }),
+        result => panic!("{:?}", result),
+    }
+
+    assert_eq!(analysis::check_equivalence(add.clone(), twice.clone(), Strategy::Exhaustive { domains: vec![] }),
+               Err(EquivalenceError::DomainCount { inputs: 2, domains: 0 }));
+    assert_eq!(analysis::check_equivalence(add.clone(), twice.clone(), Strategy::Exhaustive { domains: vec![domain, vec![]] }),
+               Err(EquivalenceError::EmptyDomain { input: 1 }));
+    let mut fewer = twice;
+    fewer.outputs.pop();
+    assert_eq!(analysis::check_equivalence(add, fewer, strategy), Err(EquivalenceError::Mismatch { outputs: true, a: 3, b: 2 }));
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn checkpoint_fault_computer() -> Computer {
+    let mut computer = Computer::default();
+    assemble(include_str!("../fixtures/checkpoint_fault.mixal")).unwrap().load_into(&mut computer);
+    computer
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn rollback_reruns_to_the_same_fault() {
+    let mut computer = checkpoint_fault_computer();
+    let mut checkpoints = CheckpointRing::new(RunOptions { checkpoint_every_cycles: 10_000, checkpoint_ring_size: 8, ..RunOptions::default() });
+    let error = checkpoints.run(&mut computer).unwrap_err();
+    let (fault_time, faulted) = (computer.time(), MachineImage::of(&computer));
+    let times: Vec<u64> = checkpoints.times().map(|time| time.time()).collect();
+    println!("{} at {}, checkpoints at {:?}", error, fault_time, times);
+    assert!(fault_time > 50_000);
+    assert_eq!(times.iter().map(|time| time / 10_000).collect::<Vec<_>>(), (1..=fault_time / 10_000).collect::<Vec<_>>());
+
+    // Back to cycle 40,000 through the report, then on to the fault again, tracing.
+    let report = FaultReport::from_run_error(&error).with_checkpoints(checkpoints);
+    let mut checkpoints = report.checkpoints.unwrap();
+    let back = times.len() - times.iter().position(|time| time / 10_000 == 4).unwrap();
+    let time = checkpoints.rollback(back, &mut computer).unwrap();
+    assert_eq!((time.time() / 10_000, computer.time()), (4, time.time()));
+    assert_eq!(checkpoints.len(), times.len() - back + 1);
+    assert_eq!(computer.memory[7], Word::from_value(computer.ri2.field_value(FieldSpec::WHOLE)));
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    assert_eq!(checkpoints.run(&mut computer), Err(error));
+    assert_eq!((computer.time(), MachineImage::of(&computer)), (fault_time, faulted));
+    let traced = computer.trace.as_ref().unwrap().steps().count() as u64;
+    assert!(traced > 0 && traced <= fault_time - time.time());
+
+    assert_eq!(checkpoints.rollback(0, &mut computer), Err(RollbackError::NoCheckpoint { back: 0, held: checkpoints.len() }));
+    assert_eq!(checkpoints.rollback(9, &mut computer), Err(RollbackError::NoCheckpoint { back: 9, held: checkpoints.len() }));
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn replays_from_checkpoints_are_verified() {
+    let mut computer = checkpoint_fault_computer();
+    let mut checkpoints = CheckpointRing::new(RunOptions { checkpoint_every_cycles: 10_000, ..RunOptions::default() });
+    while computer.time() < 35_000 {
+        computer.step().unwrap();
+        checkpoints.after_step(&computer);
+    }
+    assert_eq!(checkpoints.len(), 3);
+    for back in 1..=3 {
+        assert_eq!(checkpoints.verify_replay(back, &computer), Ok(()));
+    }
+    assert_eq!(checkpoints.len(), 3);
+    assert_eq!(checkpoints.verify_replay(4, &computer), Err(ReplayError::Rollback(RollbackError::NoCheckpoint { back: 4, held: 3 })));
+
+    computer.memory[7] = computer.memory[7].negate();
+    assert_eq!(checkpoints.verify_replay(1, &computer), Err(ReplayError::Diverged { time: computer.time() }));
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn checkpoint_ring_stays_bounded() {
+    let mut computer = checkpoint_fault_computer();
+    let image_bytes = computer.memory_size() * std::mem::size_of::<Word>();
+    let mut checkpoints = CheckpointRing::new(RunOptions { checkpoint_every_cycles: 1_000, checkpoint_ring_size: 16, ..RunOptions::default() });
+    let (result, peak) = peak_allocation(|| checkpoints.run(&mut computer));
+    println!("{} checkpoints, peak {} bytes, {} per image", checkpoints.len(), peak, image_bytes);
+    assert!(result.is_err());
+    assert_eq!(checkpoints.len(), 16);
+    // The oldest and newest in full, the two a debug build rebuilds to check each
+    // checkpoint against, and the deltas, which hold a word each here.
+    assert!(peak < 5 * image_bytes, "{} bytes", peak);
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn checkpoints_off_cost_nothing() {
+    let mut computer = checkpoint_fault_computer();
+    let mut checkpoints = CheckpointRing::new(RunOptions::default());
+    let before = allocations();
+    assert!(checkpoints.run(&mut computer).is_err());
+    assert_eq!(allocations(), before);
+    assert!(checkpoints.is_empty());
+}
+
+/// Compares the time per step of `Computer::run`, of a run with checkpoints off, and of
+/// one taking a checkpoint every 10,000 cycles. Run with `cargo test --release --
+/// --ignored checkpoint_benchmark --nocapture`.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+#[ignore]
+fn checkpoint_benchmark() {
+    let options = [None, Some(RunOptions::default()), Some(RunOptions { checkpoint_every_cycles: 10_000, checkpoint_ring_size: 8, ..RunOptions::default() })];
+    let mut best = [f64::MAX; 3];
+    for _ in 0..20 {
+        for (i, options) in options.iter().enumerate() {
+            let mut computer = checkpoint_fault_computer();
+            let start = std::time::Instant::now();
+            let result = match options {
+                Some(options) => CheckpointRing::new(*options).run(&mut computer),
+                None => computer.run(),
+            };
+            assert!(result.is_err());
+            best[i] = best[i].min(start.elapsed().as_nanos() as f64 / computer.instruction_time() as f64);
+        }
+    }
+    println!("run {:>6.2} ns/cycle, checkpoints off {:>6.2} ns/cycle, on {:>6.2} ns/cycle", best[0], best[1], best[2]);
+}
+
+/// Counts rA up 20,000 times, storing each count at 2000.
+#[cfg(feature = "assembler")]
+const MIRROR_LOOP: &str = "
+        ORIG 100
+START   ENT1 20000
+LOOP    INCA 1
+        STA  2000
+        DEC1 1
+        J1P  LOOP
+        HLT
+        END  START";
+
+#[cfg(feature = "assembler")]
+fn mirror_loop_computer() -> Computer {
+    let mut computer = Computer::default();
+    assemble(MIRROR_LOOP).unwrap().load_into(&mut computer);
+    computer
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn mirror_readers_see_consistent_run() {
+    let mut computer = mirror_loop_computer();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    let reader = computer.enable_mirror(MirrorOptions { every_steps: 16, ..MirrorOptions::default() });
+    let polling = reader.clone();
+    let poller = std::thread::spawn(move || {
+        let mut states: Vec<std::sync::Arc<MirrorState>> = Vec::new();
+        loop {
+            let state = polling.load();
+            if states.last().is_none_or(|last| last.sequence != state.sequence) {
+                states.push(state.clone());
+            }
+            if state.halted {
+                return states;
+            }
+        }
+    });
+    computer.run().unwrap();
+    let states = poller.join().unwrap();
+    println!("{} of {} states seen", states.len(), states.last().unwrap().sequence + 1);
+
+    let trace = computer.trace.as_ref().unwrap();
+    let steps: Vec<_> = trace.steps().collect();
+    for pair in states.windows(2) {
+        assert!(pair[0].sequence <= pair[1].sequence && pair[0].steps <= pair[1].steps);
+    }
+    for state in states.iter().filter(|state| state.steps > 0) {
+        let step = steps[state.steps as usize - 1];
+        assert_eq!((state.ra, state.ri[0]), (step.registers[0], step.registers[2]));
+        match steps.get(state.steps as usize) {
+            Some(next) => assert_eq!(state.pc, next.pc),
+            None => assert!(state.halted),
+        }
+        assert_eq!(state.word_at(state.pc), Some(computer.memory[state.pc]));
+    }
+    let last = states.last().unwrap();
+    assert_eq!((last.steps, last.ra), (trace.steps_taken(), Word::from_value(20_000)));
+    assert!(std::sync::Arc::ptr_eq(&reader.load(), last));
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn mirror_watches_addresses_readers_ask_for() {
+    let mut computer = mirror_loop_computer();
+    let reader = computer.enable_mirror(MirrorOptions { every_steps: 4, window_radius: 2, ..MirrorOptions::default() });
+    let first = reader.load();
+    assert_eq!((first.sequence, first.steps, first.pc, first.window_start, first.window.len()), (0, 0, 100, 98, 5));
+    for _ in 0..10 {
+        computer.step().unwrap();
+    }
+    assert_eq!(reader.load().sequence, 2);
+    assert_eq!(reader.load().word_at(2000), None);
+
+    assert!(reader.watch(2000));
+    assert!(reader.watch(DEFAULT_MEMORY_SIZE));
+    for _ in 0..4 {
+        computer.step().unwrap();
+    }
+    let state = reader.load();
+    assert_eq!(state.watched, vec![(2000, computer.memory[2000])]);
+    assert_eq!(state.word_at(2000), Some(Word::from_value(3)));
+
+    assert!(reader.unwatch(2000));
+    for _ in 0..4 {
+        computer.step().unwrap();
+    }
+    assert!(reader.load().watched.is_empty());
+
+    // Disabled, the mirror keeps its last state and takes no more requests.
+    assert!(computer.disable_mirror());
+    let last = reader.load();
+    for _ in 0..100 {
+        computer.step().unwrap();
+    }
+    assert_eq!(reader.load(), last);
+    assert!(!reader.watch(2000));
+    assert!(!computer.disable_mirror());
+
+    // Clones don't publish, but a reset computer does.
+    let reader = computer.enable_mirror(MirrorOptions { every_steps: 1, ..MirrorOptions::default() });
+    computer.clone().step().unwrap();
+    assert_eq!(reader.load().sequence, 0);
+    computer.reset();
+    computer.step().unwrap();
+    assert_eq!(reader.load().sequence, 1);
+}
+
+/// Compares the time per step of a run with the mirror never enabled, on at its
+/// defaults, and enabled and then disabled, which should cost nothing. Run with `cargo
+/// test --release -- --ignored mirror_benchmark --nocapture`.
+#[cfg(feature = "assembler")]
+#[test]
+#[ignore]
+fn mirror_benchmark() {
+    let mut best = [f64::MAX; 3];
+    for _ in 0..20 {
+        for (i, best) in best.iter_mut().enumerate() {
+            let mut computer = mirror_loop_computer();
+            if i > 0 {
+                let _reader = computer.enable_mirror(MirrorOptions::default());
+                if i == 2 {
+                    computer.disable_mirror();
+                }
+            }
+            let start = std::time::Instant::now();
+            computer.run().unwrap();
+            *best = best.min(start.elapsed().as_nanos() as f64 / computer.instruction_time() as f64);
+        }
+    }
+    println!("never enabled {:>6.2} ns/cycle, on {:>6.2} ns/cycle, disabled {:>6.2} ns/cycle", best[0], best[1], best[2]);
+    assert!(best[1] < best[0] * 1.25);
+    assert!(best[2] < best[0] * 1.05);
+}
+
+#[test]
+fn prng_is_splitmix64() {
+    let mut prng = Prng::new(0);
+    assert_eq!([prng.next_u64(), prng.next_u64(), prng.next_u64()], [0xe220_a839_7b1d_cdaf, 0x6e78_9e6a_a1b9_65f4, 0x06c4_5d18_8009_454f]);
+
+    let draw = |seed| {
+        let mut prng = Prng::new(seed);
+        let values: Vec<i64> = (0..100).map(|n| match n % 4 {
+            0 => prng.below(7) as i64,
+            1 => prng.in_range(-3..=3),
+            2 => prng.in_range(i64::MIN..=i64::MAX),
+            _ => prng.bool() as i64,
+        }).collect();
+        (values, prng.word_in_base(64), *prng.choose(&[1, 2, 3]))
+    };
+    assert_eq!(draw(7), draw(7));
+    assert_ne!(draw(7), draw(8));
+    let (values, _, _) = draw(7);
+    assert!(values.iter().step_by(4).all(|value| (0..7).contains(value)));
+    assert!(values.iter().skip(1).step_by(4).all(|value| (-3..=3).contains(value)));
+}
+
+#[test]
+fn no_rand_dependency() {
+    let manifest = include_str!("../Cargo.toml");
+    let lock = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.lock")).unwrap_or_default();
+    for line in manifest.lines().chain(lock.lines()) {
+        let mut words = line.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'));
+        assert!(!words.any(|word| word == "rand" || word.starts_with("rand_")),
+                "Randomness goes through determinism::Prng, not the crate of {:?}", line);
+    }
+}
+
+/// The features of the manifest but `default`, in the order it declares them, and the
+/// features `default` turns on.
+fn manifest_features() -> (Vec<String>, Vec<String>) {
+    let manifest = include_str!("../Cargo.toml");
+    let section = manifest.split("[features]").nth(1).unwrap().split("\n[").next().unwrap();
+    let mut features = Vec::new();
+    let mut default = Vec::new();
+    for line in section.lines().filter(|line| !line.starts_with('#')) {
+        if let Some((name, enabled)) = line.split_once(" = ") {
+            if name == "default" {
+                default = enabled.split('"').skip(1).step_by(2).map(String::from).collect();
+            } else {
+                features.push(name.to_string());
+            }
+        }
+    }
+    (features, default)
+}
+
+#[test]
+fn default_features_are_all_but_the_optional_ones() {
+    let (features, default) = manifest_features();
+    let optional = ["tui", "mdk", "dispatch-bench", "fuzz", "no-panic-audit"];
+    let expected: Vec<&String> = features.iter().filter(|feature| !optional.contains(&feature.as_str())).collect();
+    assert_eq!(default.iter().collect::<Vec<_>>(), expected);
+}
+
+/// Checks the crate, its tests with it, with no features, every feature, each feature on
+/// its own, and every pair of features, so that no feature leans on one it doesn't name.
+/// Takes some minutes, in a target directory of its own: `cargo test -- --ignored
+/// feature_matrix_checks`.
+#[test]
+#[ignore]
+fn feature_matrix_checks() {
+    let (features, _) = manifest_features();
+    let mut sets = vec![String::new(), features.join(",")];
+    sets.extend(features.iter().cloned());
+    for (i, first) in features.iter().enumerate() {
+        sets.extend(features[i + 1..].iter().map(|second| format!("{},{}", first, second)));
+    }
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let failed: Vec<&String> = sets.iter().filter(|set| {
+        let status = std::process::Command::new(&cargo)
+            .args(["check", "--quiet", "--all-targets", "--no-default-features", "--features", set.as_str()])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .env("CARGO_TARGET_DIR", concat!(env!("CARGO_MANIFEST_DIR"), "/target/feature-matrix"))
+            .status();
+        !status.map(|status| status.success()).unwrap_or(false)
+    }).collect();
+    assert!(failed.is_empty(), "These feature sets don't build: {:?}", failed);
+}
+
+#[test]
+fn opcode_conversions_cover_the_byte() {
+    for code in 0..=255u8 {
+        match Opcode::try_from(code) {
+            Ok(opcode) => {
+                assert!(code < 64);
+                assert_eq!(u8::from(opcode), code);
+                assert_eq!(Opcode::ALL[code as usize], opcode);
+            },
+            Err(error) => {
+                assert!(code >= 64);
+                assert_eq!(error, UnknownValue { kind: "opcode", value: code });
+            },
+        }
+    }
+    assert_eq!(Opcode::LDA.code(), 8);
+    assert_eq!(Opcode::LDA.name(), "LDA");
+    assert_eq!(Opcode::from_mnemonic("LDA"), Some(Opcode::LDA));
+    assert_eq!(Opcode::from_mnemonic("HLT"), Some(Opcode::SPECIAL));
+    assert_eq!(Opcode::from_mnemonic("JGE"), Some(Opcode::JMP));
+    assert_eq!(Opcode::from_mnemonic("LDB"), None);
+    assert_eq!(Opcode::LD4N.register_from(Opcode::LDAN), Some(4));
+    assert_eq!(Opcode::LDAN.register_from(Opcode::LDA), None);
+    assert_eq!(Opcode::LDA.register_from(Opcode::LDAN), None);
+}
+
+#[test]
+fn field_conversions_round_trip() {
+    fn round_trips<T>(all: &[T], last: u8) where T: Copy + Into<u8> + TryFrom<u8, Error = UnknownValue> + PartialEq + std::fmt::Debug {
+        for &value in all {
+            assert_eq!(T::try_from(value.into()), Ok(value));
+        }
+        for number in 0..=255u8 {
+            assert_eq!(T::try_from(number).is_ok(), all.iter().any(|&value| value.into() == number));
+        }
+        assert!(T::try_from(last + 1).is_err());
+    }
+    round_trips(SpecialOp::ALL, SpecialOp::Pop.field());
+    round_trips(ShiftOp::ALL, ShiftOp::RightBinary.field());
+    round_trips(JumpCondition::ALL, JumpCondition::LessOrEqual.field());
+    round_trips(RegisterCondition::ALL, RegisterCondition::NonPositive.field());
+    round_trips(EnterOp::ALL, EnterOp::EnterNegative.field());
+    assert_eq!(SpecialOp::Halt.field(), 2);
+    assert_eq!(JumpCondition::GreaterOrEqual.field(), 7);
+    assert!(SpecialOp::try_from(3).is_err());
+}
+
+#[test]
+fn unit_numbers_follow_the_standard_assignments() {
+    for number in 0..=UnitNumber::LAST {
+        let unit = UnitNumber::try_from(number).unwrap();
+        assert_eq!(u8::from(unit), number);
+    }
+    assert_eq!(UnitNumber::try_from(UnitNumber::LAST + 1), Err(UnknownValue { kind: "unit number", value: 21 }));
+    assert_eq!(UnitNumber::try_from(3), Ok(UnitNumber::Tape(3)));
+    assert_eq!(UnitNumber::try_from(15), Ok(UnitNumber::Disk(7)));
+    assert_eq!(UnitNumber::Printer.number(), PRINTER_UNIT);
+    assert_eq!(UnitNumber::CardReader.number(), 16);
+    assert_eq!(UnitNumber::PaperTape.number(), 20);
+}
+
+#[test]
+fn operation_table_agrees_with_consts() {
+    for operation in opcodes::OPCODES.iter() {
+        let opcode = operation.opcode();
+        match operation.mnemonic {
+            mnemonic if opcode.name() == mnemonic => {},
+            "NUM" | "CHAR" | "HLT" => assert_eq!(opcode, Opcode::SPECIAL),
+            "SLA" | "SRA" | "SLAX" | "SRAX" | "SLC" | "SRC" => assert_eq!(opcode, Opcode::SHIFT),
+            mnemonic if mnemonic.starts_with('J') && operation.code == Opcode::JMP.code() =>
+                assert!(JumpCondition::try_from(operation.default_field().unwrap()).is_ok()),
+            mnemonic if mnemonic.starts_with('J') => {
+                assert!(opcode.register_from(Opcode::JA).is_some());
+                assert!(RegisterCondition::try_from(operation.default_field().unwrap()).is_ok());
+            },
+            _ => {
+                assert!(opcode.register_from(Opcode::ENTA).is_some(), "{}", operation.mnemonic);
+                assert!(EnterOp::try_from(operation.default_field().unwrap()).is_ok());
+            },
+        }
+    }
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn heatmap_counts_a_sweep() {
+    let program = assemble(" ORIG 0\n ENT1 999\nLOOP STZ 1000,1\n DEC1 1\n J1NN LOOP\n HLT\n END 0\n").unwrap();
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    assert!(computer.access_heatmap().is_none());
+    computer.record_access_heatmap(HeatmapOptions { bucket_cycles: 400, line_words: 16 });
+    computer.run().unwrap();
+    let heatmap = computer.access_heatmap().unwrap();
+    assert_eq!(heatmap.totals().writes, 1000);
+    assert_eq!(heatmap.totals().reads, 0);
+    assert_eq!(heatmap.totals().fetches, 1 + 3 * 1000 + 1);
+
+    // The k-th STZ stores into 1999 - k once ENT1 and k passes of the loop have taken
+    // 1 + 4k cycles.
+    let mut expected = std::collections::BTreeMap::new();
+    for k in 0..1000u64 {
+        *expected.entry(((1 + 4 * k) / 400 * 400, (1999 - k as usize) / 16 * 16)).or_insert(0) += 1;
+    }
+    let writes: std::collections::BTreeMap<(u64, usize), u64> = heatmap.cells()
+        .filter(|(_, _, counts)| counts.writes > 0)
+        .map(|(cycle, address, counts)| ((cycle, address), counts.writes))
+        .collect();
+    assert_eq!(writes, expected);
+
+    computer.reset();
+    assert!(computer.access_heatmap().unwrap().is_empty());
+    assert_eq!(computer.take_access_heatmap().unwrap().options.line_words, 16);
+    assert!(computer.access_heatmap().is_none());
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn heatmap_csv_round_trips() {
+    let program = assemble(" ORIG 0\n ENT1 999\nLOOP STZ 1000,1\n LDA 1000,1\n DEC1 1\n J1NN LOOP\n HLT\n END 0\n").unwrap();
+    let mut computer = Computer::default();
+    program.load_into(&mut computer);
+    computer.record_access_heatmap(HeatmapOptions { bucket_cycles: 1000, line_words: 64 });
+    computer.run().unwrap();
+    let heatmap = computer.access_heatmap().unwrap();
+    let mut csv = String::new();
+    heatmap.to_csv(&mut csv).unwrap();
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(CSV_HEADER));
+    let parsed: Vec<(u64, usize, u64, u64, u64)> = lines.map(|line| {
+        let fields: Vec<u64> = line.split(',').map(|field| field.parse().unwrap()).collect();
+        assert_eq!(fields.len(), 5);
+        (fields[0], fields[1] as usize, fields[2], fields[3], fields[4])
+    }).collect();
+    let cells: Vec<(u64, usize, u64, u64, u64)> = heatmap.cells()
+        .map(|(cycle, address, counts)| (cycle, address, counts.reads, counts.writes, counts.fetches))
+        .collect();
+    assert_eq!(parsed, cells);
+    assert_eq!(parsed.iter().map(|cell| cell.2).sum::<u64>(), 1000);
+}
+
+#[test]
+fn heatmap_ascii_matches_golden() {
+    let mut heatmap = AccessHeatmap::new(HeatmapOptions { bucket_cycles: 1, line_words: 1 });
+    assert_eq!(heatmap.to_ascii(10, 10), "");
+    heatmap.record(0, 0..1, Access::Write, usize::MAX);
+    heatmap.record(1, 1..2, Access::Write, usize::MAX);
+    for _ in 0..3 {
+        heatmap.record(2, 2..3, Access::Write, usize::MAX);
+    }
+    heatmap.record(2, 0..1, Access::Read, usize::MAX);
+    assert_eq!(heatmap.to_ascii(10, 10), "- -\n - \n  @\n");
+    assert_eq!(heatmap.to_ascii(2, 1), "+@\n");
+    assert_eq!(heatmap.to_ascii(0, 5), "");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn instruction_reference_is_up_to_date() {
+    let generated = Reference::generate().to_markdown();
+    assert!(generated == include_str!("../docs/instructions.md"),
+            "docs/instructions.md is stale; run `cargo run -- isa-docs > docs/instructions.md`");
+    assert!(generated.starts_with(MARKDOWN_TITLE));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn instruction_reference_covers_every_operation() {
+    let reference = Reference::generate();
+    let mnemonics: Vec<&str> = reference.instructions.iter().map(|doc| doc.mnemonic).collect();
+    assert_eq!(mnemonics, opcodes::OPCODES.iter().map(|operation| operation.mnemonic).collect::<Vec<_>>());
+    for doc in reference.instructions.iter() {
+        let operation = opcodes::lookup(doc.mnemonic).unwrap();
+        assert_eq!(doc.implemented, Computer::core_implements(operation.code, operation.default_field().unwrap_or(0)), "{}", doc.mnemonic);
+        assert_eq!(doc.implemented, doc.semantics.as_ref().map_or(false, |semantics| !semantics.is_empty()), "{}", doc.mnemonic);
+    }
+    let unimplemented: Vec<&str> = reference.instructions.iter().filter(|doc| !doc.implemented).map(|doc| doc.mnemonic).collect();
+    assert!(unimplemented.is_empty(), "{:?}", unimplemented);
+
+    let add = &reference.instructions[1];
+    assert_eq!((add.mnemonic, add.opcode, add.field, add.time), ("ADD", 1, Some(5), 2));
+    assert_eq!(add.affects, ["overflow"]);
+    assert_eq!(reference.instructions.iter().find(|doc| doc.mnemonic == "CMP3").unwrap().affects, ["comparison"]);
+    assert_eq!(reference.instructions.iter().find(|doc| doc.mnemonic == "MOVE").unwrap().time_per_word, Some(2));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn public_api_listing_is_up_to_date() {
+    let sources = api::read_sources(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src")).unwrap();
+    let generated = api::listing(&sources);
+    assert!(generated == include_str!("../docs/public_api.txt"),
+            "docs/public_api.txt is stale; run `cargo run -- public-api > docs/public_api.txt` and check the change is meant");
+    assert!(generated.starts_with(LISTING_TITLE));
+    assert!(sources.iter().all(|(module, _)| module != "tests" && module != "main"));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn public_api_listing_marks_stability() {
+    let source = "//! A module.\n//!\n//! Unstable: still moving.\n\n\
+                  pub struct Thing {\n    #[doc(hidden)]\n    pub raw: u8,\n    private: u8,\n}\n\n\
+                  impl Thing {\n    pub fn new() -> Thing { todo!() }\n    fn helper(&self) {}\n    #[deprecated(note = \"no\")]\n    pub const fn old(&self) {}\n}\n\n\
+                  impl fmt::Display for Thing {\n    fn fmt(&self) {}\n}\n\n\
+                  pub(crate) fn internal() {}\n\n\
+                  #[non_exhaustive]\npub enum Kind {\n    One,\n    Two { value: u8 },\n}\n\n\
+                  pub trait Closed: Read + sealed::Sealed {\n    fn required(&self);\n    fn provided(&self) {}\n}\n\n\
+                  pub use other::{A, B};\n";
+    let listing = api::listing(&[("thing".to_string(), source.to_string()), ("empty".to_string(), "fn main() {}\n".to_string())]);
+    let items: Vec<&str> = listing.lines().skip_while(|line| !line.starts_with("mod ")).collect();
+    assert_eq!(items, vec![
+        "mod thing (unstable)",
+        "    struct Thing",
+        "    field Thing.raw (hidden)",
+        "    fn Thing::new",
+        "    fn Thing::old (deprecated)",
+        "    enum Kind (non_exhaustive)",
+        "    variant Kind::One",
+        "    variant Kind::Two",
+        "    trait Closed (sealed)",
+        "    fn Closed::required",
+        "    fn Closed::provided",
+        "    use other::{A, B}",
+    ]);
+}
+
+#[test]
+fn word_and_computer_accessors_read_the_fields() {
+    let word = Word::new(false, [1, 2, 3, 4, 5]);
+    assert!(!word.is_positive());
+    assert_eq!(word.bytes(), [1, 2, 3, 4, 5]);
+    let mut computer = Computer::with_memory_size(10);
+    computer.write_mem(3, word);
+    computer.ra = Word::from_value(1);
+    computer.ri6 = Word::from_value(6);
+    computer.rj = Word::from_value(9);
+    computer.pc = 7;
+    assert_eq!(computer.memory().len(), 10);
+    assert_eq!(computer.memory()[3], word);
+    assert_eq!(computer.pc(), 7);
+    let registers = computer.registers();
+    assert_eq!((registers[0], registers[7], registers[8]), (Word::from_value(1), Word::from_value(6), Word::from_value(9)));
+}
+
+#[test]
+fn extension_instructions_carry_semantics() {
+    let instructions: Vec<Box<dyn Instruction>> = vec![
+        Box::new(float::Float::new()),
+        Box::new(float::Fix::new()),
+        Box::new(stack::Push::new_unchecked(0..10)),
+        Box::new(stack::Pop::new_unchecked(0..10)),
+        Box::new(StoreFlags::new_unchecked(0)),
+        Box::new(status_flags::LoadFlags::new_unchecked(0)),
+    ];
+    for instruction in instructions.iter() {
+        assert!(!instruction.semantics().trim().is_empty());
+    }
+    assert_eq!(instructions[1].affects(), [Flag::Overflow]);
+    assert_eq!(instructions[5].affects(), [Flag::Overflow, Flag::Comparison]);
+    assert!(instructions[4].affects().is_empty());
+}
+
+#[cfg(all(feature = "cli", feature = "json"))]
+#[test]
+fn instruction_reference_json_lists_operations_in_table_order() {
+    let json: serde_json::Value = serde_json::from_str(&Reference::generate().to_json()).unwrap();
+    let instructions = json["instructions"].as_array().unwrap();
+    assert_eq!(instructions.len(), opcodes::OPCODES.len());
+    assert_eq!(instructions[0]["mnemonic"], "NOP");
+    assert_eq!(instructions[1]["affects"], serde_json::json!(["overflow"]));
+    assert_eq!(instructions[14]["mnemonic"], "MOVE");
+    assert_eq!(instructions[14]["implemented"], true);
+    assert!(instructions[14]["semantics"].is_string());
+}
+
+/// A computer with tape 3 attached, about to execute `word` on unit 5, which isn't.
+#[cfg(feature = "peripherals")]
+fn unattached_unit_setup(word: Word, policy: UnattachedPolicy) -> Computer {
+    let mut computer = Computer::default();
+    computer.unattached_policy = policy;
+    computer.attach_tape(MagneticTapeUnit::new(3, tape_block(0)));
+    computer.memory[0] = word;
+    computer.memory[1] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.memory[2] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer.memory[4] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());    // HLT
+    computer
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn unattached_units_fault_in_strict_mode() {
+    for (mnemonic, address) in [("IN", 1000), ("OUT", 1000), ("IOC", 0), ("JBUS", 3), ("JRED", 3)].iter() {
+        let word = opcodes::encode(mnemonic, *address, 0, Some(5)).unwrap();
+        let mut computer = unattached_unit_setup(word, UnattachedPolicy::Fault);
+        let error = computer.run().unwrap_err();
+        assert_eq!(error, MixError::UnitNotAttached { pc: 0, unit: 5, operation: mnemonic.to_string(), attached: vec![3] });
+        assert_eq!(error.to_string(), format!("Unit 5 used by {} at 0 is not attached; the attached units are 3", mnemonic));
+        assert!(computer.unattached_warnings.is_empty());
+    }
+
+    let mut computer = Computer::default();
+    computer.memory[0] = opcodes::encode("OUT", 1000, 0, Some(18)).unwrap();
+    assert_eq!(computer.run().unwrap_err().to_string(), "Unit 18 used by OUT at 0 is not attached; no units are attached");
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn unattached_units_are_lenient() {
+    let mut computer = unattached_unit_setup(opcodes::encode("IN", 1000, 0, Some(5)).unwrap(), UnattachedPolicy::Lenient);
+    computer.load(1000, &tape_block(1));
+    computer.run().unwrap();
+    assert!(computer.memory[1000..1100].iter().all(|word| *word == Word::default()));
+    assert!(!computer.is_busy(5));
+
+    let mut computer = unattached_unit_setup(opcodes::encode("OUT", 1000, 0, Some(5)).unwrap(), UnattachedPolicy::Lenient);
+    computer.load(1000, &tape_block(1));
+    computer.run().unwrap();
+    assert_eq!(computer.memory[1000..1100], tape_block(1)[..]);
+    assert_eq!((computer.blocks_written(5), computer.is_busy(5), computer.time()), (0, false, 2));
+
+    let mut computer = unattached_unit_setup(opcodes::encode("IOC", 0, 0, Some(5)).unwrap(), UnattachedPolicy::Lenient);
+    computer.run().unwrap();
+    assert_eq!(computer.pc, 2);
+
+    // JBUS never jumps, so runs into the HLT after it; JRED always does, to the HLT at 4.
+    let mut computer = unattached_unit_setup(opcodes::encode("JBUS", 4, 0, Some(5)).unwrap(), UnattachedPolicy::Lenient);
+    computer.run().unwrap();
+    assert_eq!((computer.pc, computer.rj.field_value(FieldSpec::WHOLE)), (2, 0));
+    let mut computer = unattached_unit_setup(opcodes::encode("JRED", 4, 0, Some(5)).unwrap(), UnattachedPolicy::Lenient);
+    computer.run().unwrap();
+    assert_eq!((computer.pc, computer.rj.field_value(FieldSpec::WHOLE)), (5, 1));
+    assert_eq!(computer.unattached_warnings, vec![UnattachedWarning { pc: 0, unit: 5, operation: "JRED".to_string() }]);
+}
+
+#[test]
+fn unattached_warnings_are_recorded_once_per_unit_and_operation() {
+    let mut computer = Computer::default();
+    computer.unattached_policy = UnattachedPolicy::Lenient;
+    computer.load(0, &[
+        Word::from_instruction(10, 0, 2, Opcode::ENT1.code()),               // ENT1 10
+        opcodes::encode("OUT", 1000, 0, Some(18)).unwrap(),                  // OUT 1000(18)
+        opcodes::encode("OUT", 1000, 0, Some(17)).unwrap(),                  // OUT 1000(17)
+        opcodes::encode("IOC", 0, 0, Some(18)).unwrap(),                     // IOC 0(18)
+        Word::from_instruction(1, 0, 1, Opcode::ENT1.code()),                // DEC1 1
+        Word::from_instruction(1, 0, 2, Opcode::J1.code()),                  // J1P 1
+        Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()),             // HLT
+    ]);
+    computer.run().unwrap();
+    let seen: Vec<(usize, u8, &str)> = computer.unattached_warnings.iter()
+        .map(|warning| (warning.pc, warning.unit, warning.operation.as_str()))
+        .collect();
+    assert_eq!(seen, [(1, 18, "OUT"), (2, 17, "OUT"), (3, 18, "IOC")]);
+
+    computer.reset();
+    assert!(computer.unattached_warnings.is_empty());
+    assert_eq!(computer.unattached_policy, UnattachedPolicy::Lenient);
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger", feature = "peripherals"))]
+#[test]
+fn run_report_surfaces_unattached_warnings() {
+    let source = include_str!("../fixtures/script_program.mixal");
+    let report = run_report::run_source(source, UnattachedPolicy::Fault).unwrap();
+    assert!(report.halted);
+    assert_eq!(report.printer, vec![vec!["PRIME".to_string()]]);
+    assert!(report.warnings.is_empty());
+
+    let reader = source.replace("OUT  MSG(18)", "IN   MSG(16)");
+    let fault = run_report::run_source(&reader, UnattachedPolicy::Fault).unwrap_err();
+    assert_eq!((fault.stage.as_str(), fault.pc, fault.errors[0].code.as_str()), ("run", Some(2003), "unit-not-attached"));
+    assert!(fault.errors[0].message.ends_with("the attached units are 0, 1, 2, 3, 4, 5, 6, 7, 17, 18"), "{}", fault.errors[0].message);
+
+    let report = run_report::run_source(&reader, UnattachedPolicy::Lenient).unwrap();
+    assert!(report.halted);
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].code, "unit-not-attached");
+    assert!(report.warnings[0].message.starts_with("2003 IN: unit 16 is not attached"), "{}", report.warnings[0].message);
+
+    let broken = run_report::run_source("START    LDA  NOWHERE\n         END  START\n", UnattachedPolicy::Fault).unwrap_err();
+    assert_eq!((broken.stage.as_str(), broken.errors[0].line), ("assemble", Some(1)));
+}
+
+/// A line of the printer is 24 words, so a line at the end of memory prints, and each line
+/// printed is one block.
+#[cfg(all(feature = "assembler", feature = "debugger", feature = "peripherals"))]
+#[test]
+fn printer_lines_are_24_words() {
+    let source = "         ORIG 3976\nMSG      ALF  HELLO\n         ORIG 3000\nSTART    OUT  MSG(18)\n         OUT  MSG(18)\n         HLT\n         END  START\n";
+    let report = run_report::run_source(source, UnattachedPolicy::Fault).unwrap();
+    assert!(report.halted);
+    assert_eq!(report.printer, vec![vec!["HELLO".to_string(), "HELLO".to_string()]]);
+
+    let greek = source.replace("ALF  HELLO", "ALF  \"ΔΣΠ\"");
+    let report = run_report::run_source(&greek, UnattachedPolicy::Fault).unwrap();
+    assert_eq!(report.printer, vec![vec!["ΔΣΠ".to_string(), "ΔΣΠ".to_string()]]);
+
+    let mut computer = Computer::default();
+    let printer = computer.attach_standard_devices();
+    assert_eq!(printer.borrow().block_words(), crate::peripherals::PRINTER_WORDS);
+    computer.memory[3000] = Word::from_instruction(3977, 0, 18, Opcode::OUT.code());    // OUT 3977(18)
+    computer.pc = 3000;
+    assert!(matches!(computer.step(), Err(MixError::BlockOutOfRange { address: 3977, words: 24, .. })));
+}
+
+#[test]
+#[cfg(all(feature = "cli", feature = "assembler", feature = "debugger", feature = "peripherals"))]
+fn cli_runs_and_assembles_sources() {
+    use crate::cli::{assemble_program, run_program, Output};
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let program = fixtures.join("script_program.mixal");
+    let program = program.to_str().unwrap();
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(run_program(program, Output::Text, &mut out, &mut err), 0);
+    assert_eq!(String::from_utf8(out).unwrap(), "PRIME\n");
+    assert!(err.is_empty());
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(assemble_program(program, Output::Text, &mut out, &mut err), 0);
+    let expected = loader::render_raw(&assemble(include_str!("../fixtures/script_program.mixal")).unwrap());
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+    assert!(err.is_empty());
+
+    let directory = std::env::temp_dir().join(format!("mixal-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let broken = directory.join("broken.mixal");
+    std::fs::write(&broken, "START    LDA  NOWHERE\n         END  START\n").unwrap();
+    let broken = broken.to_str().unwrap();
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(assemble_program(broken, Output::Text, &mut out, &mut err), 1);
+    assert!(out.is_empty());
+    let message = String::from_utf8(err).unwrap();
+    assert!(message.starts_with(&format!("{}:1: error [", broken)), "{}", message);
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(run_program(broken, Output::Text, &mut out, &mut err), 1);
+    assert!(out.is_empty());
+    assert_eq!(String::from_utf8(err).unwrap(), message);
+
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    assert_eq!(run_program(&format!("{}/missing.mixal", directory.display()), Output::Text, &mut out, &mut err), 1);
+    assert!(out.is_empty() && !err.is_empty());
+
+    #[cfg(feature = "json")]
+    {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert_eq!(run_program(program, Output::Json, &mut out, &mut err), 0);
+        let report = RunReport::from_json(&String::from_utf8(out).unwrap()).unwrap();
+        assert!(report.halted);
+        assert_eq!(report.printer, vec![vec!["PRIME".to_string()]]);
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert_eq!(assemble_program(program, Output::Json, &mut out, &mut err), 0);
+        let report = FaultReport::from_json(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(report.stage, "assemble");
+        assert!(report.errors.is_empty());
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert_eq!(run_program(broken, Output::Json, &mut out, &mut err), 1);
+        let report = FaultReport::from_json(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!((report.stage.as_str(), report.errors[0].line), ("assemble", Some(1)));
+        assert_eq!(String::from_utf8(err).unwrap(), message);
+    }
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+/// A compiler of `print A + B + ...`, for sums below 10, to MIXAL, as a downstream crate
+/// testing its code generator through `testing` would have.
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+fn compile_print_sum(statement: &str) -> String {
+    let terms = statement.trim_start_matches("print").split('+').map(|term| term.trim());
+    let mut source = "* Generated by compile_print_sum.\n         ORIG 2000\nSTART    ENTA 0\n".to_string();
+    for term in terms {
+        source.push_str(&format!("         INCA {}\n", term));
+    }
+    source.push_str("         INCA 30\n         STA  LINE(1:1)\n         OUT  LINE(18)\n         HLT\nLINE     CON  0\n         END  START\n");
+    source
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+#[test]
+fn testing_checks_a_compiled_program() {
+    let source = compile_print_sum("print 2 + 3 + 4");
+    testing::run_mixal(&source)
+        .assert_halts()
+        .assert_ra(39)
+        .assert_prints(&["9"])
+        .assert_cycles_at_most(100);
+
+    let failure = std::panic::catch_unwind(|| {
+        testing::run_mixal(&compile_print_sum("print 2 + 3")).assert_prints(&["9"]);
+    });
+    let message = failure.unwrap_err().downcast::<String>().unwrap();
+    assert_eq!(*message, "\
+Expected the printer to print 1 line, but it printed 1 line; they first differ at line 1:
+  expected: 9
+  printed:  5
+Printed:
+>    1 | 5");
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+const TESTING_SUM: &str = "\
+* Sums the table backwards into TOTAL.
+         ORIG 3000
+START    ENT1 3
+LOOP     ADD  TABLE-1,1
+         DEC1 1
+         J1P  LOOP
+         STA  TOTAL
+         HLT
+TABLE    CON  2
+         CON  3
+         CON  4
+TOTAL    CON  0
+         END  START
+";
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+#[test]
+fn testing_failure_messages() {
+    let changes = "\
+Changes since the program was loaded:
+  rA   [ +    0    0    0    0    0] -> [ +    0    0    0    0    9]
+  rJ   [ +    0    0    0    0    0] -> [ +    0    0    0   11  188]
+  3009 [ +    0    0    0    0    0] -> [ +    0    0    0    0    9]";
+    let mut run = testing::run_mixal(TESTING_SUM);
+    assert_eq!(run.check(&Expectation::Halts), Ok(()));
+    assert_eq!(run.check(&Expectation::Ra(10)).unwrap_err(),
+               format!("Expected rA to be 10, but it is 9 [ +    0    0    0    0    9]\n{}", changes));
+    assert_eq!(run.check(&Expectation::Mem(3010, 9)).unwrap_err(),
+               format!("Expected the word at 3010 (TOTAL+1) to be 9, but it is 0 [ +    0    0    0    0    0]\n{}", changes));
+    assert_eq!(run.check(&Expectation::Mem(3004, 9)).unwrap_err().lines().next(),
+               Some("Expected the word at 3004 (LOOP+3) to be 9, but it is 50482644248 [ +   11  193    0    5   24]"));
+    assert_eq!(run.check(&Expectation::Mem(4000, 9)).unwrap_err().lines().next(),
+               Some("Expected the word at 4000 to be 9, but memory ends at 4000"));
+    assert_eq!(run.check(&Expectation::CyclesAtMost(10)).unwrap_err(),
+               format!("Expected the run to take at most 10 cycles, but it took 16 in 12 steps\n{}", changes));
+    assert_eq!(run.check(&Expectation::Prints(vec!["9".to_string()])).unwrap_err(),
+               "Expected the printer to print 1 line, but it printed nothing");
+
+    let mut run = testing::run_mixal(&TESTING_SUM.replace("TABLE-1,1", "3999,1"));
+    assert_eq!(run.check(&Expectation::Halts).unwrap_err(), "\
+Expected the program to halt
+The program failed after 1 step at 3001 (LOOP): The instruction at 3001 refers to 4002, outside of memory
+     2 |          ORIG 3000
+     3 | START    ENT1 3
+>    4 | LOOP     ADD  3999,1
+     5 |          DEC1 1
+     6 |          J1P  LOOP
+Changes since the program was loaded:
+  rI1  [ +    0    0    0    0    0] -> [ +    0    0    0    0    3]");
+
+    let mut run = testing::run_mixal(&TESTING_SUM.replace("DEC1 1", "DEC1 0")).with_step_limit(50);
+    assert_eq!(run.check(&Expectation::Ra(9)).unwrap_err(), "\
+Expected rA to be 9, but it is 68 [ +    0    0    0    0   68]
+The program was still running after 50 steps, at 3002 (LOOP+1)
+     3 | START    ENT1 3
+     4 | LOOP     ADD  TABLE-1,1
+>    5 |          DEC1 0
+     6 |          J1P  LOOP
+     7 |          STA  TOTAL
+Changes since the program was loaded:
+  rA   [ +    0    0    0    0    0] -> [ +    0    0    0    0   68]
+  rI1  [ +    0    0    0    0    0] -> [ +    0    0    0    0    3]
+  rJ   [ +    0    0    0    0    0] -> [ +    0    0    0   11  188]");
+
+    let mut run = testing::run_mixal(&TESTING_SUM.replace("DEC1 1", "DEC1 ONE"));
+    let message = "\
+The source didn't assemble: Line 5: ONE is not defined
+     3 | START    ENT1 3
+     4 | LOOP     ADD  TABLE-1,1
+>    5 |          DEC1 ONE
+     6 |          J1P  LOOP
+     7 |          STA  TOTAL";
+    assert_eq!(run.check(&Expectation::Halts).unwrap_err(), message);
+    assert_eq!(run.check(&Expectation::Ra(9)).unwrap_err(), message);
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+#[test]
+fn testing_stops_tight_loops() {
+    let mut run = testing::run_mixal(" ORIG 100\nSTART JMP START\n END START\n");
+    let message = run.check(&Expectation::Halts).unwrap_err();
+    assert!(message.contains("The program was in a tight loop after 3 steps, back at 100 (START) with nothing changed"), "{}", message);
+
+    // A loop changing the state each time round runs to the step limit, and one waiting on
+    // the printer is let finish.
+    let mut run = testing::run_mixal(" ORIG 100\nSTART INC1 1\n JMP START\n END START\n").with_step_limit(100);
+    assert!(run.check(&Expectation::Halts).unwrap_err().contains("still running after 100 steps"));
+    testing::run_mixal(" ORIG 100\nSTART OUT 200(18)\n JBUS *(18)\n HLT\n END START\n").assert_halts();
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+const TESTING_CARDS: &str = "\
+* Prints each card it reads, for as many cards as rI1 says.
+         ORIG 1000
+LOOP     IN   BUF(16)
+         JBUS *(16)
+         OUT  BUF(18)
+         JBUS *(18)
+         DEC1 1
+         J1P  LOOP
+         HLT
+BUF      ORIG *+100
+         END  LOOP
+";
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+#[test]
+fn testing_cards_switches_and_printed_output() {
+    let cards: Vec<String> = (1..=12).map(|i| if i == 7 { "LONG".repeat(20) } else { format!("CARD {}", i) }).collect();
+    let cards: Vec<&str> = cards.iter().map(|card| card.as_str()).collect();
+    testing::run_mixal(TESTING_CARDS)
+        .with_cards(&cards)
+        .with_switches(&[(Location::Register("rI1"), 12)])
+        .assert_halts()
+        .assert_prints(&cards);
+
+    let mut expected = cards.clone();
+    expected[8] = "CARD 0";
+    let mut run = testing::run_mixal(TESTING_CARDS).with_cards(&cards).with_switches(&[(Location::Register("rI1"), 12)]);
+    assert_eq!(run.check(&Expectation::Prints(expected.iter().map(|line| line.to_string()).collect())).unwrap_err(), "\
+Expected the printer to print 12 lines, but it printed 12 lines; they first differ at line 9:
+  expected: CARD 0
+  printed:  CARD 9
+Printed:
+  (4 lines before)
+     5 | CARD 5
+     6 | CARD 6
+     7 | LONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONG...
+     8 | CARD 8
+>    9 | CARD 9
+    10 | CARD 10
+    11 | CARD 11
+    12 | CARD 12");
+    assert_eq!(run.check(&Expectation::Prints(cards[..2].iter().map(|line| line.to_string()).collect())).unwrap_err().lines().skip(4).collect::<Vec<_>>(),
+               ["     1 | CARD 1", "     2 | CARD 2", ">    3 | CARD 3", "     4 | CARD 4", "     5 | CARD 5",
+                "     6 | CARD 6", "     7 | LONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONGLONG...", "     8 | CARD 8", "  (4 lines after)"]);
+
+    let mut run = testing::run_mixal(TESTING_CARDS).with_cards(&cards[..2]).with_switches(&[(Location::Register("rI1"), 3)]);
+    let message = run.check(&Expectation::Halts).unwrap_err();
+    assert_eq!(message.lines().take(7).collect::<Vec<_>>(), [
+        "Expected the program to halt",
+        "The program failed after 504 steps at 1000 (LOOP): Unit 16 failed at 1000: No block 2 on tape",
+        "     1 | * Prints each card it reads, for as many cards as rI1 says.",
+        "     2 |          ORIG 1000",
+        ">    3 | LOOP     IN   BUF(16)",
+        "     4 |          JBUS *(16)",
+        "     5 |          OUT  BUF(18)",
+    ]);
+}
+
+/// A loop of 50 rounds meeting an undefined case, a read of a word nothing wrote, and a
+/// store into code on every round.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+const BUDGET_LOOP: &str = "\
+* Warns three ways on each of 50 rounds.
+EMPTY    EQU  3000
+         ORIG 100
+START    ENT2 50
+LOOP     NOP
+         LD1  BIG
+         LDA  EMPTY
+         STA  LOOP(5:5)
+         DEC2 1
+         J2P  LOOP
+         HLT
+BIG      CON  1000000
+         END  START
+";
+
+/// A computer with `BUDGET_LOOP` loaded and every kind of recording on.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn budget_loop_computer() -> Computer {
+    let mut computer = Computer::default();
+    computer.undefined_policy = UndefinedPolicy::Truncate;
+    computer.code_write_policy = CodeWritePolicy::Warn;
+    computer.track_written_bytes();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    computer.record_access_heatmap(HeatmapOptions { bucket_cycles: 1, line_words: 1 });
+    assemble(BUDGET_LOOP).unwrap().load_into(&mut computer);
+    computer
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn instrumentation_budget_saturates_each_structure() {
+    let mut computer = budget_loop_computer();
+    CheckpointRing::new(RunOptions::default()).run(&mut computer).unwrap();
+    assert_eq!((computer.undefined_warnings.len(), computer.uninitialized_reads.len(), computer.code_write_warnings.len()), (50, 50, 50));
+    assert_eq!(computer.truncated, Truncation::default());
+    assert!(!computer.trace.as_ref().unwrap().truncated);
+    assert!(!computer.access_heatmap().unwrap().is_truncated());
+    assert!(!RunReport::from_computer(&computer).truncated);
+
+    let budget = InstrumentationBudget { max_warnings: 5, max_uninitialized_reads: 7, max_trace_records: 20, max_heatmap_cells: 30 };
+    let mut computer = budget_loop_computer();
+    CheckpointRing::new(RunOptions { budget, ..RunOptions::default() }).run(&mut computer).unwrap();
+    assert!(computer.is_halted());
+    assert_eq!((computer.undefined_warnings.len(), computer.uninitialized_reads.len(), computer.code_write_warnings.len()), (5, 7, 5));
+    assert_eq!(computer.undefined_warnings[4].pc, 102, "the oldest entries are kept");
+    assert_eq!(computer.truncated, Truncation { undefined_warnings: true, uninitialized_reads: true, code_write_warnings: true, ..Truncation::default() });
+    let trace = computer.trace.as_ref().unwrap();
+    assert_eq!((trace.records.len(), trace.truncated, trace.steps_taken()), (20, true, 302));
+    let heatmap = computer.access_heatmap().unwrap();
+    assert_eq!((heatmap.cells().count(), heatmap.is_truncated()), (30, true));
+    assert!(RunReport::from_computer(&computer).truncated);
+
+    computer.reset();
+    assert_eq!((computer.budget, computer.truncated), (budget, Truncation::default()));
+    assert!(!computer.trace.as_ref().unwrap().truncated && !computer.access_heatmap().unwrap().is_truncated());
+}
+
+/// Runs random instructions, drawn as `arbitrary_case` draws them, on one computer with
+/// every kind of recording on, for `MIXAL_SOAK_SECONDS` seconds or three minutes, and
+/// checks that the memory this thread holds stops growing once the budget fills. Run it
+/// in release: `cargo test --release soak -- --ignored --nocapture`.
+#[cfg(feature = "debugger")]
+#[test]
+#[ignore]
+fn soak_instrumentation_stays_bounded() {
+    let seconds: u64 = std::env::var("MIXAL_SOAK_SECONDS").ok().and_then(|seconds| seconds.parse().ok()).unwrap_or(180);
+    let budget = InstrumentationBudget { max_warnings: 1_000, max_uninitialized_reads: 1_000, max_trace_records: 10_000, max_heatmap_cells: 10_000 };
+    let options = RunOptions { checkpoint_every_cycles: 10_000, checkpoint_ring_size: 8, budget };
+    let operations: Vec<&opcodes::Operation> = opcodes::OPCODES.iter().filter(|operation| operation.mnemonic != "HLT").collect();
+    let mut rng = test_prng("soak_instrumentation_stays_bounded");
+
+    let mut computer = Computer::default();
+    computer.check_portability = true;
+    computer.undefined_policy = UndefinedPolicy::Truncate;
+    computer.poison_policy = PoisonPolicy::Warn;
+    computer.code_write_policy = CodeWritePolicy::Warn;
+    computer.unattached_policy = UnattachedPolicy::Lenient;
+    computer.verify_invariants = true;
+    computer.track_written_bytes();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    computer.record_access_heatmap(HeatmapOptions { bucket_cycles: 100, line_words: 16 });
+    computer.source_map = Some(SourceMap { code: (0..DEFAULT_MEMORY_SIZE).collect(), ..SourceMap::default() });
+    computer.budget = options.budget;
+    let mut checkpoints = CheckpointRing::new(options);
+
+    let live = || LIVE_BYTES.with(|live| live.get());
+    let start = std::time::Instant::now();
+    let (mut settled, mut highest, mut steps) = (None, 0, 0u64);
+    while start.elapsed().as_secs() < seconds {
+        for _ in 0..64 {
+            let operation = *rng.choose(&operations[..]);
+            let word = arbitrary_case(&mut rng, operation).word;
+            computer.memory[rng.below(DEFAULT_MEMORY_SIZE as u64) as usize] = word;
+        }
+        computer.pc = rng.below(DEFAULT_MEMORY_SIZE as u64) as usize;
+        for _ in 0..10_000 {
+            steps += 1;
+            match computer.step() {
+                Err(MixError::InvariantViolated { pc, violation }) => panic!("the step at {} broke an invariant: {}", pc, violation),
+                Err(_) => break,
+                Ok(()) => {},
+            }
+            checkpoints.after_step(&computer);
+        }
+        match settled {
+            None if start.elapsed().as_secs() * 4 >= seconds => settled = Some(live()),
+            None => {},
+            Some(_) => highest = highest.max(live()),
+        }
+    }
+
+    let settled = settled.expect("The soak ran past its first quarter");
+    println!("{} steps in {} s; {} bytes held after the first quarter, at most {} after", steps, seconds, settled, highest);
+    assert!(computer.truncated.any() && computer.trace.as_ref().unwrap().truncated && computer.access_heatmap().unwrap().is_truncated(),
+            "the budget filled: {:?}", computer.truncated);
+    assert!(highest - settled < 4 << 20, "grew by {} bytes after the first quarter", highest - settled);
+}
+
+#[test]
+fn num_char_and_move() {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(0, 0, 1, Opcode::SPECIAL.code());    // CHAR
+    computer.memory[1] = Word::from_instruction(0, 0, 0, Opcode::SPECIAL.code());    // NUM
+    computer.memory[2] = Word::from_instruction(1000, 0, 3, Opcode::MOVE.code());    // MOVE 1000(3)
+    computer.ra = Word::from_value(1234567890);
+    computer.step().unwrap();
+    assert_eq!(computer.ra.bytes, [31, 32, 33, 34, 35]);
+    assert_eq!(computer.rx.bytes, [36, 37, 38, 39, 30]);
+    computer.step().unwrap();
+    assert_eq!(computer.ra, Word::from_value(1234567890));
+    assert!(!computer.overflow_flag);
+
+    computer.memory[1000..1003].copy_from_slice(&[Word::from_value(1), Word::from_value(2), Word::from_value(3)]);
+    computer.ri1 = Word::from_value(2000);
+    computer.step().unwrap();
+    assert_eq!(computer.memory[2000..2003], computer.memory[1000..1003]);
+    assert_eq!(computer.ri1, Word::from_value(2003));
+
+    computer.pc = 2;
+    computer.ri1 = Word::from_value(3998);
+    assert_eq!(computer.step().unwrap_err(), MixError::AddressOutOfRange { pc: 2, address: 4000 });
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn jumps_go_on_at_their_targets() {
+    // A forward jump over a word, a loop jumping back, and a call returning through the
+    // JMP its STJ sets, each going on at the word it names.
+    let program = assemble("
+         ORIG 100
+START    ENT1 2
+         JMP  SKIP
+         ENTA 99
+SKIP     INCA 1
+         DEC1 1
+         J1P  SKIP
+         JMP  SUB
+         HLT
+SUB      STJ  EXIT
+         INCX 1
+EXIT     JMP  *
+         END  START
+").unwrap();
+    let mut computer = Computer::default();
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    program.load_into(&mut computer);
+    computer.run().unwrap();
+    let pcs: Vec<usize> = computer.trace.as_ref().unwrap().steps().map(|record| record.pc).collect();
+    assert_eq!(pcs, [100, 101, 103, 104, 105, 103, 104, 105, 106, 108, 109, 110, 107]);
+    assert_eq!((computer.ra, computer.rx), (Word::from_value(2), Word::from_value(1)));
+    assert_eq!((computer.rj, computer.pc), (Word::from_value(111), 108));
+
+    // JMP * runs forever, and a jump to the word after it goes on as if it hadn't jumped.
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(0, 0, 0, Opcode::JMP.code());    // JMP 0
+    for _ in 0..3 {
+        computer.step().unwrap();
+        assert_eq!(computer.pc, 0);
+    }
+    computer.memory[0] = Word::from_instruction(1, 0, 0, Opcode::JMP.code());    // JMP 1
+    computer.step().unwrap();
+    assert_eq!(computer.pc, 1);
+
+    // A jump to a negative address faults when the word there is fetched.
+    let mut computer = Computer::default();
+    computer.ri1 = Word::from_value(5).negate();
+    computer.memory[0] = Word::from_instruction(1, 1, 0, Opcode::JMP.code());    // JMP 1,1
+    computer.step().unwrap();
+    assert!(matches!(computer.step(), Err(MixError::ProgramCounterOutOfRange { .. })));
+}
+
+#[test]
+fn register_and_comparison_jumps_run_to_their_targets() {
+    // Runs the jump at 0 to 10 with rJ cleared, halting at 1 when it falls through and
+    // at 10 when it jumps, and gives whether it jumped.
+    let jumped = |computer: &mut Computer, jump: Word| {
+        computer.memory[0] = jump;
+        computer.memory[1] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());      // HLT
+        computer.memory[10] = Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code());     // HLT
+        computer.pc = 0;
+        computer.rj = Word::default();
+        computer.run().unwrap_or_else(|error| panic!("{}: {}", jump, error));
+        let jumped = computer.pc == 11;
+        assert!(jumped || computer.pc == 2, "{} stopped with pc {}", jump, computer.pc);
+        assert_eq!(computer.rj, Word::from_value(if jumped { 1 } else { 0 }));
+        jumped
+    };
+
+    // JrN, JrZ, JrP, JrNN, JrNZ, and JrNP, on -5, -0, +0, and +5. -0 is zero.
+    let conditions: [fn(i64, bool) -> bool; 6] = [
+        |value, _| value < 0, |value, _| value == 0, |value, _| value > 0,
+        |value, _| value >= 0, |value, _| value != 0, |value, _| value <= 0,
+    ];
+    for opcode in Opcode::JA.code()..=Opcode::JX.code() {
+        for (field, condition) in conditions.iter().enumerate() {
+            for (positive, magnitude) in [(false, 5), (false, 0), (true, 0), (true, 5)].iter().copied() {
+                let mut computer = Computer::default();
+                let value = sign_magnitude(positive, magnitude);
+                match opcode - Opcode::JA.code() {
+                    0 => computer.ra = value,
+                    7 => computer.rx = value,
+                    index => *register_for_index(&mut computer, index).unwrap() = value,
+                }
+                let signed = if positive { magnitude } else { -magnitude };
+                let jump = Word::from_instruction(10, 0, field as u8, opcode);
+                assert_eq!(jumped(&mut computer, jump), condition(signed, positive), "{} on {}", opcodes::disassemble(&jump).unwrap(), value);
+            }
+        }
+    }
+
+    // The address is indexed as any other: J1P 4,2 with rI2 = 6 jumps to 10.
+    let mut computer = Computer::default();
+    computer.ri1 = Word::from_value(1);
+    computer.ri2 = Word::from_value(6);
+    assert!(jumped(&mut computer, Word::from_instruction(4, 2, 2, Opcode::J1.code())));
+
+    // JMP always jumps, and JOV and JNOV on the overflow toggle, which they clear.
+    assert!(jumped(&mut Computer::default(), Word::from_instruction(10, 0, 0, Opcode::JMP.code())));
+    for overflow in [false, true].iter().copied() {
+        for (field, jumps) in [(2, overflow), (3, !overflow)].iter().copied() {
+            let mut computer = Computer::default();
+            computer.overflow_flag = overflow;
+            assert_eq!(jumped(&mut computer, Word::from_instruction(10, 0, field, Opcode::JMP.code())), jumps);
+            assert!(!computer.overflow_flag);
+        }
+    }
+
+    // JSJ jumps leaving rJ as it was.
+    let mut computer = Computer::default();
+    computer.rj = Word::from_value(7);
+    computer.memory[0] = Word::from_instruction(10, 0, 1, Opcode::JMP.code());
+    computer.step().unwrap();
+    assert_eq!((computer.pc, computer.rj), (10, Word::from_value(7)));
+
+    // JL, JE, JG, JGE, JNE, and JLE, fields 4 to 9, on each setting of the comparison.
+    let comparisons = [ComparisonFlag::less, ComparisonFlag::equal, ComparisonFlag::greater];
+    let expected = [[true, false, false], [false, true, false], [false, false, true],
+                    [false, true, true], [true, false, true], [true, true, false]];
+    for (field, row) in (4..=9).zip(expected.iter()) {
+        for (comparison, jumps) in comparisons.iter().zip(row.iter()) {
+            let mut computer = Computer::default();
+            computer.comparison_flag = *comparison;
+            let jump = Word::from_instruction(10, 0, field, Opcode::JMP.code());
+            assert_eq!(jumped(&mut computer, jump), *jumps, "{} on {:?}", opcodes::disassemble(&jump).unwrap(), comparison);
+        }
+    }
+}
+
+/// The example of TAOCP 1.3.1: NUM, INCA 1, CHAR.
+#[test]
+fn num_and_char_convert_the_taocp_example() {
+    let mut computer = Computer::default();
+    computer.memory[0] = Word::from_instruction(0, 0, 0, Opcode::SPECIAL.code());    // NUM
+    computer.memory[1] = Word::from_instruction(1, 0, 0, Opcode::ENTA.code());       // INCA 1
+    computer.memory[2] = Word::from_instruction(0, 0, 1, Opcode::SPECIAL.code());    // CHAR
+    computer.ra = Word::new(false, [0, 0, 31, 32, 39]);
+    computer.rx = Word::new(true, [37, 57, 47, 30, 30]);
+    computer.step().unwrap();
+    assert_eq!(computer.ra.field_value(FieldSpec::WHOLE), -12977700);
+    assert_eq!(computer.rx, Word::new(true, [37, 57, 47, 30, 30]));
+    computer.step().unwrap();
+    assert_eq!(computer.ra.field_value(FieldSpec::WHOLE), -12977699);
+    computer.step().unwrap();
+    assert_eq!((computer.ra, computer.rx), (Word::new(false, [30, 30, 31, 32, 39]), Word::new(true, [37, 37, 36, 39, 39])));
+    assert!(!computer.overflow_flag);
+}
+
+#[test]
+fn move_is_bounded_by_its_count_memory_and_ri1() {
+    let moving = |source: i64, destination: i64, count: u8| {
+        let mut computer = Computer::default();
+        for (i, word) in computer.memory.iter_mut().enumerate().skip(1) {
+            *word = Word::from_value(i as i64);
+        }
+        computer.memory[0] = Word::from_instruction(source, 0, count, Opcode::MOVE.code());
+        computer.ri1 = Word::from_value(destination.abs());
+        computer.ri1.positive = destination >= 0;
+        computer
+    };
+
+    // 63 words from the top of memory fit exactly, and cost 1 + 2 * 63.
+    let mut computer = moving(3937, 100, 63);
+    computer.step().unwrap();
+    assert_eq!(computer.memory[100..163], computer.memory[3937..4000]);
+    assert_eq!((computer.ri1, computer.time()), (Word::from_value(163), 127));
+    // One word further runs past memory, at either end, and faults before moving any,
+    // costing the MOVE alone.
+    for (source, destination) in [(3938, 100), (100, 3938)] {
+        let mut computer = moving(source, destination, 63);
+        let before = computer.memory.clone();
+        assert_eq!(computer.step().unwrap_err(), MixError::AddressOutOfRange { pc: 0, address: 4000 });
+        assert_eq!((&computer.memory, computer.ri1, computer.time()), (&before, Word::from_value(destination), 1));
+    }
+
+    // A count of 0 moves nothing and leaves rI1 alone, even pointing outside memory.
+    let mut computer = moving(100, -5, 0);
+    computer.step().unwrap();
+    assert_eq!((computer.ri1.field_value(FieldSpec::WHOLE), computer.time()), (-5, 1));
+
+    // rI1 may reach 65535, the most its two bytes hold, and no further.
+    let at_capacity = |count: u8| {
+        let mut computer = Computer::with_memory_size(1 << 17);
+        computer.memory[0] = Word::from_instruction(1000, 0, count, Opcode::MOVE.code());
+        computer.memory[1000] = Word::from_value(7);
+        computer.ri1 = Word::from_value(65525);
+        computer
+    };
+    let mut computer = at_capacity(10);
+    computer.step().unwrap();
+    assert_eq!((computer.ri1, computer.memory[65525]), (Word::from_value(65535), Word::from_value(7)));
+    let mut computer = at_capacity(11);
+    assert_eq!(computer.step().unwrap_err(), MixError::IndexRegisterOverflow { pc: 0, index: 1, value: Word::from_value(65536) });
+    assert_eq!((computer.memory[65525], computer.time()), (Word::default(), 1));
+    let mut computer = at_capacity(11);
+    computer.undefined_policy = UndefinedPolicy::Truncate;
+    computer.step().unwrap();
+    assert_eq!((computer.ri1, computer.memory[65525], computer.time()), (Word::from_value(0), Word::from_value(7), 23));
+    assert_eq!(computer.undefined_warnings[0].detail, format!("[{}] doesn't fit in an index register and was truncated", Word::from_value(65536)));
+
+    // A count above 63 can't be constructed, and a word with one isn't a core MOVE.
+    let error = Move::try_new(1000, 100).err().unwrap();
+    assert_eq!((error.parameter, error.allowed.as_str()), ("count", "0 to 63"));
+    assert!(Move::try_new(1000, 63).is_ok());
+    assert!(!Computer::core_implements(Opcode::MOVE.code(), 64));
+    let mut computer = moving(100, 200, 100);
+    computer.step().unwrap();
+    assert_eq!((computer.memory[200], computer.ri1, computer.time()), (Word::from_value(200), Word::from_value(200), 1));
+    computer.pc = 0;
+    computer.unimplemented_policy = UnimplementedPolicy::Fault;
+    assert_eq!(computer.step().unwrap_err(), MixError::UnimplementedInstruction { pc: 0, opcode: Opcode::MOVE.code(), field: 100 });
+}
+
+#[test]
+fn move_copies_overlapping_ranges_a_word_at_a_time() {
+    let moved = |source: i64, destination: i64, count: u8| {
+        let mut computer = Computer::default();
+        for address in 1000..1010 {
+            computer.memory[address] = Word::from_value(address as i64);
+        }
+        computer.memory[0] = Word::from_instruction(source, 0, count, Opcode::MOVE.code());
+        computer.ri1 = Word::from_value(destination);
+        computer.step().unwrap();
+        let values: Vec<i64> = computer.memory[1000..1010].iter().map(|word| word.field_value(FieldSpec::WHOLE)).collect();
+        (values, computer.ri1.field_value(FieldSpec::WHOLE))
+    };
+
+    // Onto the words before it, each word is read before it is written over.
+    assert_eq!(moved(1002, 1000, 5), (vec![1002, 1003, 1004, 1005, 1006, 1005, 1006, 1007, 1008, 1009], 1005));
+    // Onto the words after it, the first word is copied again and again.
+    assert_eq!(moved(1000, 1001, 5), (vec![1000, 1000, 1000, 1000, 1000, 1000, 1006, 1007, 1008, 1009], 1006));
+    // Onto itself, nothing changes but rI1.
+    assert_eq!(moved(1000, 1000, 10), ((1000..1010).collect(), 1010));
+    // Apart, the source is left as it was.
+    assert_eq!(moved(1000, 1005, 3), (vec![1000, 1001, 1002, 1003, 1004, 1000, 1001, 1002, 1008, 1009], 1008));
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+const CARD_LOADER: &str = include_str!("../fixtures/loader.mixal");
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+const LOADER_PAYLOAD: &str = include_str!("../fixtures/loader_payload.mixal");
+
+/// The cards of `loader` followed by `payload` in the format the loader reads, as
+/// `fixtures/loader.deck` holds them: the loader's words as characters, sixteen to a
+/// card; then up to seven words to a card, with their count in column 6, the address of
+/// the first in columns 7-10, and each word as ten digits, the last overpunched when it
+/// is negative; then a transfer card giving the start in columns 7-10.
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+fn punch_loader_deck(loader: &AssembledProgram, payload: &AssembledProgram) -> Vec<String> {
+    let mapping = CharsetMapping::mdk();
+    let mut deck = Vec::new();
+    for card in 0..2 {
+        let mut text = String::new();
+        for address in card * 16..(card + 1) * 16 {
+            let word = loader.words.get(&address).copied().unwrap_or(Word::default());
+            assert!(word.positive, "Loader word {} has no punched sign", address);
+            text.extend(word.bytes.iter().map(|byte| mapping.char_for(*byte).unwrap()));
+        }
+        deck.push(text.trim_end().to_string());
+    }
+    let words: Vec<(usize, Word)> = payload.words.iter().map(|(address, word)| (*address, *word)).collect();
+    let mut rest = &words[..];
+    while let Some((first, _)) = rest.first() {
+        let run = rest.iter().enumerate().take_while(|(i, (address, _))| *address == first + i).count().min(7);
+        let mut text = format!("     {}{:04}", run, first);
+        for (_, word) in rest[..run].iter() {
+            let digits = format!("{:010}", word.field_value(FieldSpec::MAGNITUDE));
+            text.push_str(&digits[..9]);
+            let last = digits.as_bytes()[9] - b'0';
+            let code = if !word.positive { charset::DELTA + last } else { charset::DIGIT_ZERO + last };
+            text.push(mapping.char_for(code).unwrap());
+        }
+        deck.push(text);
+        rest = &rest[run..];
+    }
+    deck.push(format!("TRANS0{:04}", payload.start));
+    deck
+}
+
+#[cfg(all(feature = "analysis", feature = "peripherals", feature = "debugger"))]
+#[test]
+fn go_button_runs_the_card_loader() {
+    // The deck depends on each of these; a change to one of them breaks it.
+    //
+    // - GO reads one card, and only the card, into 0-15, so that the loader's second card
+    //   can't have been read yet, and goes to 0 with rJ +0 once the reader is done.
+    // - The reader gives 100-word blocks, of which IN stores all 100, so IN 16(16) puts
+    //   the second card in 16-31 and blanks from 32 on, and each IN BUFF(16) blanks the
+    //   rest of the buffer. The payload, at 200, is clear of both.
+    // - IN stores its block when it is issued, so the second card is in place by the time
+    //   the word after IN 16(16) runs, and JBUS only waits out the reader's time.
+    // - Every loader word is positive with bytes below 56, so that it punches as five
+    //   characters of MDK.
+    // - NUM takes each byte of rA and rX modulo 10 as a digit, so it reads digits from
+    //   their character codes 30-39 and overpunched ones from codes 10-19.
+    // - STA LOC writes the address of the card over the loader's first word, which only
+    //   ever runs once.
+    // - MOVE 0,1(2) moves two words from rI1 onto themselves, adding 2 to rI1.
+    // - A partial field loads and subtracts in place rather than shifted to the right, so
+    //   the loader can't take the book's constants from the (0:2) fields of NUM 30 and
+    //   SLA 1, or clear rI1 with LD1 0(0:0). It subtracts with DECA, clears with ENT1 0,
+    //   and loads the last character of a word as (5:5) into rA cleared by ENTA 0. These
+    //   take more words than the book's, so the loader needs both of its cards.
+    // - A word of 256-value bytes above 9999999999 can't be punched as ten digits, so the
+    //   payload keeps every address below 512.
+    let loader = assemble(CARD_LOADER).unwrap();
+    let payload = assemble(LOADER_PAYLOAD).unwrap();
+    let deck = punch_loader_deck(&loader, &payload);
+    assert_eq!(include_str!("../fixtures/loader.deck").lines().collect::<Vec<_>>(), deck);
+
+    let mut reader = MagneticTapeUnit::new(UnitNumber::CardReader.number(), [Word::default(); BLOCK_WORDS]);
+    for card in deck.iter() {
+        reader.write_block(&testing::card_block(card)).unwrap();
+    }
+    reader.rewind();
+    let mut computer = Computer::default();
+    computer.attach(UnitNumber::CardReader.number(), reader);
+    computer.memory[16] = Word::from_value(99);
+    computer.press_go().unwrap();
+    assert_eq!(computer.pc, 0);
+    assert_eq!(computer.rj, Word::default());
+    for address in 0..16 {
+        assert_eq!(computer.memory[address], loader.words[&address], "word {}", address);
+    }
+    assert_eq!(computer.memory[16], Word::from_value(99));
+
+    computer.run().unwrap();
+    assert_eq!(computer.memory[200..204], [Word::from_value(1000).negate(), Word::from_value(25), Word::from_value(3).negate(), Word::from_value(7000)]);
+    assert_eq!(computer.memory[204], Word::from_value(6022));
+    assert!(computer.is_halted());
+}
+
+#[cfg(all(feature = "cli", feature = "json"))]
+fn bench_entry(commit: &str, cpu: &str, rates: &[(&str, f64)]) -> BenchEntry {
+    BenchEntry {
+        commit: commit.to_string(),
+        dirty: false,
+        recorded_at: 1_700_000_000,
+        environment: Environment { cpu: cpu.to_string(), os: "linux".to_string(), arch: "x86_64".to_string(), profile: "release".to_string() },
+        results: rates.iter().map(|(name, rate)| WorkloadResult {
+            name: name.to_string(),
+            seed: 7,
+            steps: 1000,
+            warm_up_runs: 2,
+            samples: 5,
+            steps_per_second: *rate,
+            median_steps_per_second: *rate * 0.9,
+        }).collect(),
+    }
+}
+
+#[test]
+#[cfg(all(feature = "cli", feature = "json"))]
+fn bench_history_round_trips() {
+    let path = std::env::temp_dir().join(format!("mixal-bench-{}", std::process::id())).join("history.json");
+    assert_eq!(History::load(&path).unwrap(), History::default());
+
+    let mut history = History::default();
+    history.record(bench_entry("aaaa1111", "cpu", &[("arithmetic", 1000.0)]));
+    history.record(bench_entry("aaaa2222", "cpu", &[("arithmetic", 2000.0)]));
+    history.record(bench_entry("bbbb3333", "cpu", &[("arithmetic", 3000.0)]));
+    history.record(bench_entry("aaaa1111", "cpu", &[("arithmetic", 1500.0)]));
+    assert_eq!(history.entries.iter().map(|entry| entry.commit.as_str()).collect::<Vec<_>>(), ["aaaa2222", "bbbb3333", "aaaa1111"]);
+    history.save(&path).unwrap();
+    let loaded = History::load(&path).unwrap();
+    assert_eq!(loaded, history);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    assert_eq!(loaded.find("bbbb").unwrap().results[0].steps_per_second, 3000.0);
+    assert_eq!(loaded.find("aaaa1").unwrap().results[0].steps_per_second, 1500.0);
+    assert!(matches!(loaded.find("aaaa"), Err(BenchError::Ambiguous(_))));
+    assert!(matches!(loaded.find("cccc"), Err(BenchError::NoEntry(_))));
+    assert!(matches!(loaded.find(""), Err(BenchError::NoEntry(_))));
+    let later = loaded.to_json().replace("\"schema_version\": 1", "\"schema_version\": 2");
+    assert!(matches!(History::from_json(&later), Err(BenchError::UnknownVersion(2))));
+    assert!(History::from_json(include_str!("../benches/baseline.json")).unwrap().entries.len() == 1);
+}
+
+#[test]
+#[cfg(all(feature = "cli", feature = "json"))]
+fn bench_comparison_and_exit_codes() {
+    let baseline = bench_entry("base", "cpu", &[("arithmetic", 1000.0), ("memory", 2000.0)]);
+    let current = bench_entry("new", "cpu", &[("arithmetic", 900.0), ("memory", 2200.0)]);
+    let comparison = bench::compare(&baseline, &current, 5.0);
+    assert!(comparison.mismatches.is_empty());
+    assert_eq!(comparison.deltas.iter().map(|delta| (delta.name.as_str(), delta.percent.round())).collect::<Vec<_>>(), [("arithmetic", -10.0), ("memory", 10.0)]);
+    assert_eq!(comparison.regressions().len(), 1);
+    assert_eq!(comparison.exit_code(), bench::EXIT_REGRESSED);
+    let mut text = String::new();
+    comparison.write_text(&mut text).unwrap();
+    assert!(text.contains("-10.00%  REGRESSED"), "{}", text);
+    assert!(text.ends_with("1 workload(s) slower than the baseline by more than 5%\n"), "{}", text);
+
+    assert_eq!(bench::compare(&baseline, &current, 10.5).exit_code(), 0);
+    assert_eq!(bench::compare(&baseline, &baseline, 0.0).exit_code(), 0);
+
+    let elsewhere = bench_entry("new", "other cpu", &[("arithmetic", 5000.0), ("memory", 5000.0)]);
+    let comparison = bench::compare(&baseline, &elsewhere, 5.0);
+    assert_eq!(comparison.exit_code(), bench::EXIT_NOT_COMPARABLE);
+    assert_eq!(comparison.mismatches, ["The CPU differs: cpu against other cpu"]);
+    let mut text = String::new();
+    comparison.write_text(&mut text).unwrap();
+    assert_eq!(text, "new against base\nNot comparable:\n  The CPU differs: cpu against other cpu\n");
+
+    let mut longer = current.clone();
+    longer.results[1].steps = 2000;
+    longer.results.push(bench_entry("", "", &[("seeded", 1.0)]).results.remove(0));
+    assert_eq!(bench::compare(&baseline, &longer, 50.0).mismatches, ["memory was run differently", "seeded wasn't run for base"]);
+    assert_eq!(bench::compare(&baseline, &longer, 50.0).exit_code(), bench::EXIT_NOT_COMPARABLE);
+    assert_eq!(bench::compare(&bench_entry("a", "unknown", &[]), &bench_entry("b", "unknown", &[]), 5.0).exit_code(), bench::EXIT_NOT_COMPARABLE);
+}
+
+#[test]
+#[cfg(all(feature = "cli", feature = "json"))]
+fn bench_workloads_run_pinned() {
+    for workload in bench::WORKLOADS.iter() {
+        let result = bench::run_workload(workload, 10_000, 1, 3);
+        assert_eq!((result.name.as_str(), result.seed, result.steps, result.samples), (workload.name, workload.seed, 10_000, 3));
+        assert!(result.steps_per_second >= result.median_steps_per_second && result.median_steps_per_second > 0.0);
+    }
+    let mut first = Computer::default();
+    let mut second = Computer::default();
+    (bench::WORKLOADS[2].setup)(&mut first, bench::SEED);
+    (bench::WORKLOADS[2].setup)(&mut second, bench::SEED);
+    assert_eq!(first.memory[1000..2000], second.memory[1000..2000]);
+}
+
+// A loop of a hundred thousand or so steps, for a hot path that copies the computer or
+// its memory to show up as a slow test. Moving a computer leaves its memory in place.
+#[test]
+fn long_loop_runs_in_place() {
+    let mut computer = Computer::default();
+    computer.load(0, &[
+        opcodes::encode("ENT2", 100, 0, None).unwrap(),
+        opcodes::encode("ENT1", 500, 0, None).unwrap(),         // 1
+        opcodes::encode("INCA", 1, 0, None).unwrap(),           // 2
+        opcodes::encode("DEC1", 1, 0, None).unwrap(),
+        opcodes::encode("J1P", 2, 0, None).unwrap(),
+        opcodes::encode("DEC2", 1, 0, None).unwrap(),
+        opcodes::encode("J2P", 1, 0, None).unwrap(),
+        opcodes::encode("HLT", 0, 0, None).unwrap(),
+    ]);
+    computer.run().unwrap();
+    assert_eq!(computer.ra, Word::from_value(50_000));
+    // Each of these instructions takes one unit of time, so this counts the steps.
+    assert_eq!(computer.time(), 1 + 100 * (1 + 500 * 3 + 2) + 1);
+
+    let memory = computer.memory.as_ptr();
+    let moved = Box::new(computer);
+    assert_eq!(moved.memory.as_ptr(), memory);
+    assert!(std::mem::size_of::<Computer>() < std::mem::size_of::<Word>() * moved.memory.len());
+}
+
+#[test]
+fn invariants_name_each_violation() {
+    let mut computer = Computer::default();
+    assert_eq!(computer.check_invariants(), Ok(()));
+    computer.ri3 = Word::new(true, [0, 0, 1, 0, 0]);
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::IndexRegister { register: "rI3", found: computer.ri3 }));
+    computer.ri3 = Word::new(false, [0, 0, 0, 255, 255]);
+    computer.rj = Word::new(false, [0, 0, 0, 0, 1]);
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::JumpRegister { found: computer.rj }));
+    computer.rj = Word::new(true, [1, 0, 0, 0, 1]);
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::JumpRegister { found: computer.rj }));
+    computer.rj = Word::default();
+
+    computer.overflow_flag = true;
+    let changed = invariants::check_flags(&computer, (false, ComparisonFlag::equal), "LDA", &[Flag::Comparison]).unwrap_err();
+    assert_eq!(changed, InvariantViolation::FlagChanged { operation: "LDA".to_string(), flag: Flag::Overflow, before: "off".to_string(), after: "on".to_string() });
+    assert_eq!(changed.to_string(), "LDA changed the overflow flag from off to on, but doesn't affect it");
+    assert_eq!(invariants::check_flags(&computer, (false, ComparisonFlag::equal), "ADD", &[Flag::Overflow]), Ok(()));
+
+    computer.occupancy_mut().push(0);
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::OccupancyLength { found: 64, expected: 63 }));
+    let mut small = Computer::with_memory_size(100);
+    small.occupancy_mut()[1] |= 1 << 40;
+    assert_eq!(small.check_invariants(), Err(InvariantViolation::OccupiedPastEnd { address: 104, memory_size: 100 }));
+
+    let mut computer = Computer::default();
+    computer.track_written_bytes();
+    computer.written_mut().unwrap()[7] = 0b100_0001;
+    let violation = InvariantViolation::WrittenMask { address: 7, found: 0b100_0001 };
+    assert_eq!(computer.check_invariants(), Err(violation.clone()));
+    // A failed check leaves the writes it scanned to be scanned again.
+    assert_eq!(computer.check_invariants(), Err(violation));
+    computer.written_mut().unwrap()[7] = 1;
+    assert_eq!(computer.check_invariants(), Ok(()));
+    computer.written_mut().unwrap().pop();
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::WrittenLength { found: 3999, expected: 4000 }));
+
+    #[cfg(feature = "peripherals")]
+    {
+        let mut computer = Computer::default();
+        computer.attach_tape(MagneticTapeUnit::new(0, [Word::default(); BLOCK_WORDS]));
+        computer.transfers_mut().push(Transfer { unit: 0, words_remaining: 100, next_word_at: 0, output: Some(3950..4050), pc: 0 });
+        assert_eq!(computer.check_invariants(), Err(InvariantViolation::TransferOutOfMemory { unit: 0, range: 3950..4050, memory_size: 4000 }));
+    }
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+fn subsystems_register_their_invariants() {
+    let mut computer = Computer::default();
+    assert_eq!(computer.invariant_checks(), ["occupancy", "budget"]);
+    computer.track_written_bytes();
+    computer.record_access_heatmap(HeatmapOptions::default());
+    computer.record_access_heatmap(HeatmapOptions::default());
+    computer.attach_tape(MagneticTapeUnit::new(0, [Word::default(); BLOCK_WORDS]));
+    assert_eq!(computer.invariant_checks(), ["occupancy", "budget", "written", "heatmap", "transfers"]);
+    computer.reset();
+    assert_eq!(computer.invariant_checks(), ["occupancy", "budget", "written", "heatmap", "transfers"]);
+
+    computer.step().unwrap();
+    computer.budget.max_heatmap_cells = 0;
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::OverBudget { list: "heatmap", length: 1, max: 0 }));
+    computer.budget.max_heatmap_cells = 1;
+    computer.trace = Some(Trace::with_steps(TraceOptions::default(), TraceConfig::default()));
+    computer.step().unwrap();
+    computer.budget.max_trace_records = 0;
+    assert_eq!(computer.check_invariants(), Err(InvariantViolation::OverBudget { list: "trace", length: 1, max: 0 }));
+}
+
+#[test]
+fn steps_report_the_invariant_they_break() {
+    let mut computer = Computer::default();
+    computer.verify_invariants = true;
+    computer.pc = 10;
+    computer.ri1 = Word::new(true, [0, 3, 0, 0, 1]);
+    let error = computer.step().unwrap_err();
+    assert_eq!(error, MixError::InvariantViolated { pc: 10, violation: InvariantViolation::IndexRegister { register: "rI1", found: computer.ri1 } });
+    assert_eq!((error.code(), error.pc()), ("invariant-violated", Some(10)));
+    assert_eq!(error.to_string(), "The instruction at 10 broke an invariant: rI1 holds [ +    0    3    0    0    1], but an index register holds only a sign and two bytes");
+
+    computer.verify_invariants = false;
+    assert_eq!(computer.step(), Ok(()));
+}
+
+/// Reads the clock on unit 20 into 1000 to 1002, resetting its epoch between the second
+/// reading and the third.
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+const CLOCK_PROGRAM: &str = " ORIG 100\nSTART IN 1000(20)\n IN 1001(20)\n IOC 0(20)\n IN 1002(20)\n HLT\n END START\n";
+
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+#[test]
+fn clock_reads_manual_time() {
+    let time = ManualTimeSource::new(Duration::from_secs(1_700_000_000));
+    let mut computer = Computer::default();
+    computer.attach(20, ClockDevice::new(Box::new(time.clone()), ClockMode::ElapsedMilliseconds));
+    assemble(" ORIG 100\nSTART IN 1000(20)\n IN 1001(20)\n IN 1002(20)\n HLT\n END START\n").unwrap().load_into(&mut computer);
+    computer.write_mem(1003, Word::from_value(9));
+    for milliseconds in [250, 500, 1000] {
+        time.advance(Duration::from_millis(milliseconds));
+        computer.step().unwrap();
+    }
+    computer.run().unwrap();
+    assert_eq!(computer.memory[1000..1004], [Word::from_value(250), Word::from_value(750), Word::from_value(1750), Word::from_value(9)]);
+
+    let mut clock = ClockDevice::new(Box::new(ManualTimeSource::following_cycles(Duration::from_secs(1000), 2)), ClockMode::ElapsedMilliseconds);
+    let mut word = [Word::default()];
+    assert_eq!(clock.block_words(), 1);
+    clock.set_time(4000);
+    clock.read_block_into(&mut word, 256).unwrap();
+    assert_eq!(word, [Word::from_value(2000)]);
+    clock.control(0).unwrap();
+    clock.set_time(5000);
+    clock.read_block_into(&mut word, 256).unwrap();
+    assert_eq!(word, [Word::from_value(500)]);
+    assert!(clock.write_block(&word).is_err());
+    assert!(matches!(clock.read_block_into(&mut [Word::default(); 2], 256), Err(TapeError::WrongBlockLength(2))));
+
+    let mut clock = ClockDevice::new(Box::new(ManualTimeSource::new(Duration::from_millis(1_700_000_000_999))), ClockMode::EpochSeconds);
+    clock.read_block_into(&mut word, 256).unwrap();
+    assert_eq!(word, [Word::from_value(1_700_000_000)]);
+}
+
+#[cfg(all(feature = "assembler", feature = "peripherals"))]
+#[test]
+fn clock_replays_recorded_host_time() {
+    let program = assemble(CLOCK_PROGRAM).unwrap();
+    let mut computer = Computer::default();
+    let clock = computer.attach(20, ClockDevice::new(Box::new(SystemTimeSource), ClockMode::EpochSeconds));
+    assert_eq!(clock.borrow_mut().record(false), Err(ClockError::NondeterministicSource));
+    assert_eq!(clock.borrow().recording(), None);
+    clock.borrow_mut().record(true).unwrap();
+    program.load_into(&mut computer);
+    computer.run().unwrap();
+    let recording = clock.borrow().recording().unwrap();
+    assert_eq!(recording.mode, ClockMode::EpochSeconds);
+    assert_eq!(recording.readings.len(), 3);
+    assert!(recording.readings.iter().all(|seconds| *seconds >= 1_700_000_000));
+    let read: Vec<Word> = recording.readings.iter().map(|seconds| Word::from_value(*seconds as i64)).collect();
+    assert_eq!(computer.memory[1000..1003], read[..]);
+
+    let mut replayed = Computer::default();
+    let clock = replayed.attach(20, ClockDevice::replay(recording.clone()));
+    assert_eq!(clock.borrow_mut().record(false), Ok(()));
+    program.load_into(&mut replayed);
+    replayed.run().unwrap();
+    assert_eq!(replayed.memory[1000..1003], read[..]);
+    assert!(matches!(clock.borrow_mut().read_block_into(&mut [Word::default()], 256), Err(TapeError::EndOfTape { .. })));
+
+    let mut manual = ClockDevice::new(Box::new(ManualTimeSource::new(Duration::ZERO)), ClockMode::ElapsedMilliseconds);
+    manual.record(false).unwrap();
+    assert_eq!(manual.recording(), Some(ClockRecording { mode: ClockMode::ElapsedMilliseconds, readings: vec![] }));
+}
+
+/// The image of `fixtures/migration.mixal` loaded and ready to run.
+#[cfg(feature = "assembler")]
+fn migration_image() -> MachineImage {
+    let mut computer = Computer::default();
+    assemble(include_str!("../fixtures/migration.mixal")).unwrap().load_into(&mut computer);
+    MachineImage::of(&computer)
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn migration_converts_words() {
+    let lda = Word::from_instruction(1000, 2, 5, 8);
+    assert_eq!(migration::convert_word(lda, 256, 64), MigrationOutcome::Instruction(Word::new(true, [15, 40, 2, 5, 8])));
+    assert_eq!(migration::convert_word(Word::new(true, [15, 40, 2, 5, 8]), 64, 256), MigrationOutcome::Instruction(lda));
+    assert_eq!(migration::convert_word(Word::from_value(70000), 256, 64), MigrationOutcome::Data(Word::new(true, [0, 0, 17, 5, 48])));
+    assert_eq!(migration::convert_word(Word::from_value(70000).negate(), 256, 64), MigrationOutcome::Data(Word::new(false, [0, 0, 17, 5, 48])));
+    // 300 is J4Z 0,0(0:1) under 256-value bytes; only knowing it is data keeps its value.
+    assert_eq!(migration::convert_word(Word::from_value(300), 256, 64), MigrationOutcome::Instruction(Word::new(true, [0, 0, 0, 1, 44])));
+    assert_eq!(migration::convert_data(Word::from_value(300), 256, 64), MigrationOutcome::Data(Word::new(true, [0, 0, 0, 4, 44])));
+
+    assert_eq!(migration::convert_word(Word::new(true, [1, 0, 0, 0, 200]), 256, 64),
+               MigrationOutcome::ValueTooLarge { value: (1 << 32) + 200, capacity: 1 << 30 });
+    let jump = migration::convert_word(Word::from_instruction(5000, 0, 0, 39), 256, 64);
+    assert_eq!(jump, MigrationOutcome::AddressTooLarge { address: 5000, capacity: 4096, suggestion: Word::new(true, [63, 63, 0, 0, 39]) });
+    assert_eq!(jump.to_string(), format!("Address 5000 needs more than two bytes, which hold below 4096; the nearest is [{}]", Word::new(true, [63, 63, 0, 0, 39])));
+    assert_eq!(migration::convert_word(Word::from_instruction(1000, 0, 100, 7), 256, 64),
+               MigrationOutcome::ManualReview { reason: "its field 100 doesn't fit a byte of 64 values".to_string() });
+    assert_eq!(migration::migrate_image(&migration_image(), 256, 63, &[]), Err(MigrationError::ByteSize(63)));
+}
+
+#[cfg(all(feature = "assembler", feature = "cli"))]
+#[test]
+fn migrated_images_flag_and_keep_results() {
+    let mut image = migration_image();
+    image.memory[3000] = Word::new(true, [1, 0, 0, 0, 200]);
+    image.memory[3001] = Word::from_instruction(5000, 0, 0, 39);
+    image.memory[3002] = Word::from_instruction(1000, 0, 100, 7);
+    image.registers[1] = Word::new(false, [200, 0, 0, 0, 0]);
+    let migration = migration::migrate_image(&image, 256, 64, &[1000..1004]).unwrap();
+    assert_eq!(migration.flagged.iter().map(|(address, _)| *address).collect::<Vec<_>>(), [3000, 3001, 3002]);
+    assert_eq!(migration.flagged_registers.iter().map(|(register, _)| *register).collect::<Vec<_>>(), ["rX"]);
+    assert_eq!(migration.image.memory[1000..1003], [Word::new(true, [0, 0, 0, 4, 44]), Word::new(true, [0, 0, 17, 5, 48]), Word::from_value(5)]);
+    assert_eq!(migration.image.memory[2000], Word::new(true, [15, 40, 0, 5, 8]));
+    assert_eq!(migration.image.memory[3000..3003], [Word::default(), Word::new(true, [63, 63, 0, 0, 39]), Word::default()]);
+    assert_eq!(migration.image.registers[1], Word::default());
+    let mut text = String::new();
+    migration.write_text(&mut text).unwrap();
+    assert_eq!(text.lines().take(3).collect::<Vec<_>>(), [
+        "Migrated 4009 words, 4 flagged",
+        "  rX: 858993459200 needs more than five bytes, which hold below 1073741824",
+        "  3000: 4294967496 needs more than five bytes, which hold below 1073741824",
+    ]);
+
+    // This machine computes only with bytes of 256 values, so the migrated program runs
+    // here once migrated back, and must do what the original did.
+    let original = migration_image();
+    let there = migration::migrate_image(&original, 256, 64, &[1000..1004]).unwrap();
+    assert!(there.is_clean());
+    let back = migration::migrate_image(&there.image, 64, 256, &[1000..1004]).unwrap();
+    assert_eq!(back.image, original);
+    let mut computer = Computer::default();
+    original.restore_into(&mut computer).unwrap();
+    computer.run().unwrap();
+    let mut migrated = Computer::default();
+    back.image.restore_into(&mut migrated).unwrap();
+    migrated.run().unwrap();
+    assert_eq!(computer.memory[1003], Word::from_value(70305));
+    assert_eq!(MachineImage::of(&migrated), MachineImage::of(&computer));
+    let unmarked = migration::migrate_image(&original, 256, 64, &[]).unwrap();
+    assert_eq!(unmarked.image.memory[1000], Word::new(true, [0, 0, 0, 1, 44]));
+
+    let old = std::env::temp_dir().join(format!("mixal-migrate-{}-old.mixi", std::process::id()));
+    let new = old.with_file_name(format!("mixal-migrate-{}-new.mixi", std::process::id()));
+    std::fs::write(&old, image.to_bytes(FORMAT_COMPRESSED)).unwrap();
+    let args: Vec<String> = [old.to_str().unwrap(), "--from-byte-size", "256", "--to", "64", "-o", new.to_str().unwrap(), "--data", "1000..1004"]
+        .iter().map(|arg| arg.to_string()).collect();
+    assert_eq!(migration::main(&args), migration::EXIT_FLAGGED);
+    let written = MachineImage::from_bytes_with(&std::fs::read(&new).unwrap(), IngestPolicy::Reject, 64).unwrap();
+    assert_eq!(written, migration.image);
+    assert!(matches!(migration::migrate_image_bytes(&std::fs::read(&old).unwrap(), 64, 256, &[]), Err(MigrationError::Image(ImageError::InvalidWord { .. }))));
+    std::fs::remove_file(old).unwrap();
+    std::fs::remove_file(new).unwrap();
+}
+
+#[cfg(feature = "peripherals")]
+#[test]
+#[allow(deprecated)]
+fn legacy_peripherals_follow_the_devices() {
+    let mut computer = Computer::default();
+    computer.io_timing = IoTimingModel::Overlapped;
+    assert_eq!(computer.peripherals_legacy(), [0; 21]);
+    computer.attach_tape(MagneticTapeUnit::new(0, tape_block(0)));
+    let channel = Channel::new(1);
+    computer.attach(8, ChannelEnd::consumer(&channel));
+    let status = computer.peripherals_legacy();
+    assert_eq!((status[0], status[8], status[1]), (UNIT_ATTACHED, UNIT_ATTACHED | UNIT_BUSY, 0));
+
+    computer.memory[0] = Word::from_instruction(1000, 0, 0, Opcode::OUT.code());    // OUT 1000(0)
+    computer.step().unwrap();
+    assert_eq!(computer.peripherals_legacy()[0], UNIT_ATTACHED | UNIT_BUSY);
+    let mut steps = 0;
+    while computer.peripherals_legacy()[0] & UNIT_BUSY != 0 {
+        assert!(computer.is_busy(0));
+        computer.step().unwrap();
+        steps += 1;
+    }
+    assert!(!computer.is_busy(0));
+    // The last word moves two units a word after the OUT, less the unit the OUT took.
+    assert_eq!(steps, BLOCK_WORDS * 2 - 1);
+    assert_eq!(computer.peripherals_legacy()[0], UNIT_ATTACHED);
+
+    ChannelEnd::producer(&channel).write_block(&[Word::default(); BLOCK_WORDS]).unwrap();
+    assert_eq!(computer.peripherals_legacy()[8], UNIT_ATTACHED);
+}
+
+#[cfg(feature = "assembler")]
+const HIGH_MEMORY: &str = include_str!("../fixtures/high_memory.mixal");
+
+#[cfg(feature = "assembler")]
+#[test]
+fn high_memory_routines_run_at_the_boundary() {
+    let program = assemble(HIGH_MEMORY).unwrap();
+    for memory_size in [DEFAULT_MEMORY_SIZE, 4096].iter().copied() {
+        let mut computer = Computer::with_memory_size(memory_size);
+        computer.verify_invariants = true;
+        program.load_into(&mut computer);
+        assert_eq!(computer.iter_nonzero().map(|(address, _)| address).collect::<Vec<_>>(), program.words.keys().copied().collect::<Vec<_>>());
+        computer.run().unwrap();
+        assert!(computer.is_halted());
+        assert_eq!(computer.ra.field_value(FieldSpec::WHOLE), 84);
+        assert_eq!(computer.rj.field_value(FieldSpec::WHOLE), 104);
+        // The HLT in the last word of memory stopped it, leaving pc past the end.
+        assert_eq!(computer.pc, DEFAULT_MEMORY_SIZE);
+
+        // Without the HLT, the routine runs on through whatever memory is left.
+        let mut computer = Computer::with_memory_size(memory_size);
+        computer.verify_invariants = true;
+        program.load_into(&mut computer);
+        computer.write_mem(DEFAULT_MEMORY_SIZE - 1, Word::default());
+        assert_eq!(computer.run(), Err(MixError::ProgramCounterOutOfRange { pc: memory_size, memory_size }));
+    }
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn program_map_flags_code_running_off_the_end() {
+    let runs_off_end = |source: &str| analysis::program_map(&AssemblyState::new(source)).unwrap().runs_off_end;
+    assert_eq!(runs_off_end(HIGH_MEMORY), None);
+    assert_eq!(runs_off_end(PROGRAM_MAP_SOURCE), None);
+
+    // EDGE runs on past its last word, and so does a loop ending in a conditional jump.
+    assert_eq!(runs_off_end(&HIGH_MEMORY.replace("         HLT\n", "         NOP\n")), Some(3998));
+    let looping = "         ORIG 3990\nSTART    ENT1 5\nLOOP     NOP\n         DEC1 1\n         J1P  LOOP\n         END  START\n";
+    assert_eq!(runs_off_end(looping), Some(3990));
+    // A gap of +0 words runs on, as far as the next word that stops it.
+    let gap = "         ORIG 3000\nSTART    ENTA 1\n         ORIG 3500\n         HLT\n         END  START\n";
+    assert_eq!(runs_off_end(gap), None);
+    assert_eq!(runs_off_end(&gap.replace("HLT", "CON  0")), Some(3000));
+    // Unreached code isn't flagged, only where execution can get to.
+    assert_eq!(runs_off_end("START    HLT\n         ORIG 3999\n         NOP\n         END  START\n"), None);
+
+    let text = analysis::program_map(&AssemblyState::new(&HIGH_MEMORY.replace("         HLT\n", "         NOP\n"))).unwrap().to_text();
+    assert!(text.ends_with("\nRUNS OFF THE END OF MEMORY\n  3998 EDGE\n"), "{}", text);
+}
+
+#[cfg(feature = "assembler")]
+const EASTER: &str = include_str!("../fixtures/easter.mixal");
+
+/// The month and day of Easter in `year`, by Algorithm E of exercise 1.3.2-14 as
+/// `fixtures/easter.mixal` computes it.
+#[cfg(feature = "assembler")]
+fn easter_date(year: i64) -> (i64, i64) {
+    let golden = year % 19 + 1;
+    let century = year / 100 + 1;
+    let (x, z) = (3 * century / 4 - 12, (8 * century + 5) / 25 - 5);
+    let sunday = 5 * year / 4 - x - 10;
+    let mut epact = (11 * golden + 20 + z - x).rem_euclid(30);
+    if (epact == 25 && golden > 11) || epact == 24 {
+        epact += 1;
+    }
+    let mut n = 44 - epact;
+    if n < 21 {
+        n += 30;
+    }
+    n += 7 - (sunday + n).rem_euclid(7);
+    if n > 31 { (4, n - 31) } else { (3, n) }
+}
+
+#[cfg(feature = "assembler")]
+#[test]
+fn easter_program_matches_reference() {
+    assert_eq!([1961, 2000, 2008, 2019, 2024].iter().map(|year| easter_date(*year)).collect::<Vec<_>>(),
+               vec![(4, 2), (4, 23), (3, 23), (4, 21), (3, 31)]);
+
+    let state = AssemblyState::new(EASTER);
+    let symbol = |name: &str| state.symbols.get(name).unwrap().value as usize;
+    let (year, month, day, date) = (symbol("YEAR"), symbol("MONTH"), symbol("DAY"), symbol("DATE"));
+    let program = state.program.as_ref().unwrap();
+    let mut computer = Computer::default();
+    let start = std::time::Instant::now();
+    for y in 1950..=2050 {
+        computer.reset();
+        program.load_into(&mut computer);
+        computer.write_mem(year, Word::from_value(y));
+        let loaded = Snapshot::of(&computer);
+        let result = computer.run();
+        let value = |address: usize, field: FieldSpec| computer.memory[address].field_value(field);
+        let found = (value(month, FieldSpec::WHOLE), value(day, FieldSpec::WHOLE));
+        let packed = (value(date, FieldSpec::of(4, 4)), value(date, FieldSpec::of(5, 5)));
+        assert!(result.is_ok() && found == easter_date(y) && packed == found,
+                "Easter {} is {:?}, but the program ended with {:?}, giving {:?} and packing {:?}\n{}",
+                y, easter_date(y), result, found, packed, render_delta(&loaded.delta(&computer)));
+    }
+    // A hundred runs of about 70 instructions each, with plenty of room for slow builds.
+    assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+const EVENT_LIST: &str = include_str!("../fixtures/event_list.mixal");
+
+/// `fixtures/event_list.mixal` loaded, its simulation with the list at `WAIT`, and its
+/// symbols.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn event_list_simulation() -> (Computer, Simulation, AssemblyState) {
+    let state = AssemblyState::new(EVENT_LIST);
+    let mut computer = Computer::default();
+    state.program.as_ref().unwrap().load_into(&mut computer);
+    let symbol = |name: &str| state.symbols.get(name).unwrap().value as usize;
+    let layout = NodeLayout {
+        next: NodeField::new(0, FieldSpec::of(4, 5)),
+        fields: vec![("time".to_string(), NodeField::new(1, FieldSpec::WHOLE)), ("delay".to_string(), NodeField::new(2, FieldSpec::WHOLE))],
+    };
+    let simulation = Simulation::new(symbol("CLOCK")).with_event_list(symbol("WAIT"), layout);
+    (computer, simulation, state)
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn simulation_breaks_at_simulated_time() {
+    let (mut computer, simulation, state) = event_list_simulation();
+    let symbol = |name: &str| state.symbols.get(name).map(|info| info.value as usize);
+    let condition = BreakCondition::parse("break when mem[CLOCK] >= 10", symbol).unwrap();
+    assert_eq!(condition, simulation.at_time(10));
+    assert_eq!(condition.to_string(), format!("mem[{}] >= 10", simulation.clock));
+
+    // The clock reads 3, 5, 6, and 9 before reaching 10, set by the STA after JG DONE.
+    let stop = simulation.run_until(&mut computer, &condition, 10_000).unwrap();
+    let set_clock = symbol("LOOP").unwrap() + 4;
+    assert!(matches!(stop, SimulationStop::Reached { pc, time: 10, .. } if pc == set_clock + 1), "{:?}", stop);
+    let (a, b) = (symbol("A").unwrap(), symbol("B").unwrap());
+    assert_eq!(simulation.render_event_list(&computer).unwrap(),
+               format!("clock 10\n{}: time=10 delay=5\n{}: time=12 delay=3\n", b, a));
+
+    // Continuing stops at the next step, as the condition still holds, and a condition
+    // never holding runs to the end.
+    assert!(matches!(simulation.run_until(&mut computer, &condition, 10_000).unwrap(), SimulationStop::Reached { time: 10, .. }));
+    assert_eq!(simulation.run_until(&mut computer, &simulation.at_time(100), 3).unwrap(), SimulationStop::StepLimit);
+    assert!(matches!(simulation.run_until(&mut computer, &simulation.at_time(100), 10_000).unwrap(), SimulationStop::Halted { .. }));
+    assert_eq!(simulation.time(&computer), 20);
+
+    assert_eq!(BreakCondition::parse("mem[3019]<3", |_| None).unwrap(),
+               BreakCondition { address: 3019, comparison: Comparison::Less, value: 3 });
+    assert_eq!(BreakCondition::parse("mem[TICK] == 1", symbol), Err(ConditionError::UnknownSymbol("TICK".to_string())));
+    for text in ["mem[] >= 1", "clock >= 1", "mem[1] => 1", "mem[1] >= soon"] {
+        assert_eq!(BreakCondition::parse(text, symbol), Err(ConditionError::Syntax(text.to_string())));
+    }
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn simulation_trace_records_only_clock_changes() {
+    let (mut computer, simulation, state) = event_list_simulation();
+    computer.trace = Some(simulation.trace(TraceOptions::default()));
+    computer.run().unwrap();
+    let events = simulation.events(computer.trace.as_ref().unwrap());
+    // Events at 15 come from both coroutines, but only the first changes the clock.
+    assert_eq!(events.iter().map(|event| event.time).collect::<Vec<_>>(), vec![3, 5, 6, 9, 10, 12, 15, 18, 20]);
+    let set_clock = state.symbols.get("LOOP").unwrap().value as usize + 4;
+    assert!(events.iter().all(|event| event.pc == set_clock && event.instruction == computer.memory()[set_clock]));
+    assert!(events.windows(2).all(|pair| pair[0].step < pair[1].step));
+    assert_eq!(events[1].to_string(), format!("step {} at {}: clock 5", events[1].step, set_clock));
+
+    // A plain trace has no clock to read events from.
+    let plain = Trace::with_steps(TraceOptions::default(), TraceConfig::default());
+    assert!(simulation.events(&plain).is_empty());
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn simulation_event_list_reports_bad_links() {
+    let (mut computer, simulation, state) = event_list_simulation();
+    let symbol = |name: &str| state.symbols.get(name).unwrap().value as usize;
+    let (a, b) = (symbol("A"), symbol("B"));
+    let list = simulation.event_list(&computer).unwrap();
+    assert_eq!(list.iter().map(|node| node.address).collect::<Vec<_>>(), vec![a, b]);
+    assert_eq!(list[0].values, vec![("time".to_string(), 3), ("delay".to_string(), 3)]);
+
+    computer.write_mem(b, Word::from_value(a as i64));
+    assert_eq!(simulation.event_list(&computer), Err(SimulationError::Cycle { from: b, to: a }));
+    computer.write_mem(b, Word::from_value(0));
+    assert_eq!(simulation.event_list(&computer).unwrap().len(), 2);
+    computer.write_mem(b, Word::from_value(60_000));
+    assert_eq!(simulation.event_list(&computer), Err(SimulationError::LinkOutOfRange { from: b, link: 60_000 }));
+    assert_eq!(Simulation::new(0).event_list(&computer), Err(SimulationError::NoEventList));
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn lockstep_identical_machines_never_diverge() {
+    let state = AssemblyState::new(EASTER);
+    let year = state.symbols.get("YEAR").unwrap().value as usize;
+    let program = state.program.as_ref().unwrap();
+    for y in [1961, 2000, 2024] {
+        let (mut a, mut b) = (Computer::default(), Computer::default());
+        program.load_into(&mut a);
+        program.load_into(&mut b);
+        let result = lockstep::run(a, b, &[(year, Word::from_value(y))], &LockstepOptions::default());
+        assert!(matches!(result.end, LockstepEnd::Halted { steps } if steps > 50), "{:?}", result.end);
+        assert!(result.excused.is_empty());
+        assert!(result.a.state_eq(&result.b));
+    }
+
+    let (mut a, mut b) = (Computer::default(), Computer::default());
+    AssemblyState::new(EVENT_LIST).program.unwrap().load_into(&mut a);
+    AssemblyState::new(EVENT_LIST).program.unwrap().load_into(&mut b);
+    let options = LockstepOptions { step_limit: 20, ..LockstepOptions::default() };
+    assert_eq!(lockstep::run(a, b, &[], &options).end, LockstepEnd::StepLimit);
+}
+
+#[cfg(feature = "debugger")]
+#[test]
+fn lockstep_confirms_equal_hashes() {
+    // A word written straight to the memory of b, which state_hash doesn't see.
+    let (mut a, mut b) = (Computer::default(), Computer::default());
+    a.write_mem(0, Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()));    // HLT
+    b.write_mem(0, Word::from_instruction(0, 0, 2, Opcode::SPECIAL.code()));    // HLT
+    b.memory[3000] = Word::from_value(7);
+    assert_eq!(a.state_hash(), b.state_hash());
+    match lockstep::run(a, b, &[], &LockstepOptions::default()).end {
+        LockstepEnd::Diverged(divergence) => {
+            assert_eq!(divergence.step, 1);
+            let (zero, seven) = (format!("[{}]", Word::default()), format!("[{}]", Word::from_value(7)));
+            assert_eq!(divergence.differences, vec![Difference { component: Component::Memory(3000..3001), a: zero, b: seven }]);
+        },
+        end => panic!("{:?}", end),
+    }
+}
+
+/// Two machines running `source`: `a` strict about undefined cases, and `b` lenient about
+/// them and with `MUL` twice as slow.
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+fn strict_and_lenient(source: &str) -> (Computer, Computer) {
+    let program = assemble(source).unwrap();
+    let mut strict = Computer::default();
+    strict.undefined_policy = UndefinedPolicy::Fault;
+    strict.poison_policy = PoisonPolicy::Fault;
+    let mut lenient = Computer::default();
+    lenient.undefined_policy = UndefinedPolicy::Truncate;
+    lenient.poison_policy = PoisonPolicy::Off;
+    lenient.set_cost_model(CostModel::from_text(include_str!("../fixtures/double_mul.cost")).unwrap());
+    program.load_into(&mut strict);
+    program.load_into(&mut lenient);
+    (strict, lenient)
+}
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+const STRICT_AND_LENIENT: &str = "\
+START    MUL  ONE
+         ENTA 5
+         ENTX 0
+         DIV  ZERO
+         JOV  CLEAR
+CLEAR    ENTA 1
+         ENTX 2
+         LD1  BIG
+         HLT
+ZERO     CON  0
+ONE      CON  1
+BIG      CON  100000
+         END  START
+";
+
+#[cfg(all(feature = "assembler", feature = "debugger"))]
+#[test]
+fn lockstep_stops_at_first_unexcused_divergence() {
+    let options = LockstepOptions {
+        masks: vec![
+            Mask::always(Component::Time),
+            Mask::after_overflow(Component::Register("rA"), "DIV"),
+            Mask::after_overflow(Component::Register("rX"), "DIV"),
+        ],
+        ..LockstepOptions::default()
+    };
+    let (a, b) = strict_and_lenient(STRICT_AND_LENIENT);
+    let result = lockstep::run(a, b, &[], &options);
+    // MUL takes longer on b from the first step, and DIV leaves POISON on a and +0 on b
+    // at the fourth, both excused; the LD1 of a value too large for rI1 at the eighth
+    // faults on a alone.
+    assert_eq!(result.excused, vec![(Component::Time, 1), (Component::Register("rA"), 4), (Component::Register("rX"), 4)]);
+    let divergence = match &result.end {
+        LockstepEnd::Diverged(divergence) => divergence,
+        end => panic!("{:?}", end),
+    };
+    assert_eq!((divergence.step, divergence.pc), (8, (7, 7)));
+    assert_eq!(divergence.instructions, ("LD1 11".to_string(), "LD1 11".to_string()));
+    assert_eq!(divergence.differences.len(), 1);
+    assert_eq!(divergence.differences[0].component, Component::Outcome);
+    assert!(divergence.differences[0].a.starts_with("failed"), "{}", divergence);
+    assert_eq!(divergence.differences[0].b, "ran");
+    assert!(divergence.to_string().starts_with("Diverged at step 8: a executed LD1 11 at 7, b executed LD1 11 at 7\n  outcome: failed"));
+    assert_eq!(result.b.ri1, Word::from_value(100_000 % 65_536));
+
+    // Without the masks, the time differs after the first step.
+    let (a, b) = strict_and_lenient(STRICT_AND_LENIENT);
+    match lockstep::run(a, b, &[], &LockstepOptions::default()).end {
+        LockstepEnd::Diverged(divergence) => {
+            assert_eq!(divergence.step, 1);
+            assert_eq!(divergence.differences, vec![Difference { component: Component::Time, a: "10".to_string(), b: "20".to_string() }]);
+        },
+        end => panic!("{:?}", end),
+    }
+
+    // A mask excuses only from its cause until the machines agree again. Once both enter 1
+    // into rA, b entering 3 into it where a enters 2 into rX is a divergence in rA, while
+    // rX still differs as DIV left it.
+    let (a, mut b) = strict_and_lenient(STRICT_AND_LENIENT);
+    b.write_mem(6, Word::from_instruction(3, 0, 2, 48));
+    let mut options = options;
+    options.masks.push(Mask::always(Component::Memory(0..10)));
+    match lockstep::run(a, b, &[], &options).end {
+        LockstepEnd::Diverged(divergence) => {
+            assert_eq!((divergence.step, divergence.instructions.1.as_str()), (7, "ENTA 3"));
+            assert_eq!(divergence.differences.iter().map(|difference| difference.component.clone()).collect::<Vec<_>>(),
+                       vec![Component::Register("rA")]);
+        },
+        end => panic!("{:?}", end),
+    }
+}
+
+#[cfg(feature = "assembler")]
+mod embedded {
+    include!("../fixtures/embed_library.rs");
+}
+
+#[cfg(feature = "analysis")]
+#[test]
+fn embedded_programs_load_and_run_by_name() {
+    let source = include_str!("../fixtures/embed_library.mixal");
+    let program = assemble(source).unwrap();
+    assert_eq!(codegen::embed(&program, source, "library", "crate"), include_str!("../fixtures/embed_library.rs"));
+
+    use embedded::library;
+    assert_eq!((library::TABLE, library::SIZE), (codegen::Address(1000), 4));
+    assert_eq!(library::PROGRAM_REGIONS[1], (codegen::Address(3000), codegen::Address(3012)));
+    let mut computer = Computer::default();
+    let handle = library::load_into(&mut computer);
+    assert_eq!(computer.pc(), library::ENTRY_POINT.0);
+    assert_eq!(computer.memory()[1003], Word::from_value(13));
+
+    let total = Harness::for_routine(handle.TOTAL.0)
+        .linkage(Linkage::Stj { exit_word: handle.TEXIT.0 })
+        .output(Location::Register("rA"))
+        .run_on(&computer)
+        .unwrap();
+    assert_eq!(total.outputs, vec![Word::from_value(29)]);
+    let double = Harness::for_routine(handle.DOUBLE.0)
+        .input(Location::Register("rA"), Word::from_value(21))
+        .linkage(Linkage::Stj { exit_word: handle.DEXIT.0 })
+        .output(Location::Register("rA"))
+        .run_on(&computer)
+        .unwrap();
+    assert_eq!(double.outputs, vec![Word::from_value(42)]);
+
+    // An edit to the source, even one the assembler ignores, makes the embedding stale.
+    assert!(!library::is_stale(source));
+    assert!(library::is_stale(&source.replace("CON  13", "CON  21")));
+    assert!(library::is_stale(&format!("{}* A trailing comment.\n", source)));
+}
+
+/// The entry points reading one kind of text, by name, each giving what it read or why
+/// it couldn't.
+#[cfg(feature = "debugger")]
+type EntryPoints<'a, T> = Vec<(&'static str, Box<dyn Fn(&str) -> Result<T, String> + 'a>)>;
+
+/// Feeds each of `inputs` to every one of `entry_points`, checking that all of them read
+/// it as the first does, and reject what it rejects with a message naming the text.
+#[cfg(feature = "debugger")]
+fn check_entry_points<T: std::fmt::Debug + PartialEq>(inputs: &[&str], entry_points: &EntryPoints<T>) {
+    for input in inputs.iter() {
+        let reference = (entry_points[0].1)(input);
+        for (name, read) in entry_points.iter() {
+            match (&reference, read(input)) {
+                (Ok(expected), Ok(actual)) => assert_eq!(actual, *expected, "{} read {:?}", name, input),
+                (Err(_), Err(message)) => {
+                    assert!(message.contains(&format!("'{}' is not", input.trim())), "{} rejected {:?} with {}", name, input, message);
+                },
+                (_, result) => panic!("{} gave {:?} for {:?}, where parse gave {:?}", name, result, input, reference),
+            }
+        }
+    }
+}
+
+/// What `script` reads `text` as, in a line `set TARGET = TEXT`: `expected`, when an
+/// assertion `assert TARGET = EXPECTED` holds after it, and never what the parse module
+/// rejects.
+#[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+fn script_reads<T: Clone>(target: &str, text: &str, expected: Result<T, ParseDiag>, written: impl Fn(&T) -> String) -> Result<Option<T>, String> {
+    let run = |script: String| script::run_script(&script, std::path::Path::new(""), &mut Vec::new()).map_err(|failure| failure.message);
+    run(format!("set {} = {}\n", target, text))?;
+    let expected = match expected {
+        Ok(expected) => expected,
+        Err(_) => return Ok(None),
+    };
+    run(format!("set {0} = {1}\nassert {0} = {2}\n", target, text, written(&expected)))?;
+    Ok(Some(expected))
+}
+
+#[cfg(feature = "debugger")]
+#[test]
+#[cfg_attr(not(all(feature = "tui", feature = "assembler", feature = "peripherals")), allow(unused_mut))]
+fn entry_points_read_the_same_syntax() {
+    let addresses = ["0", "+5", "-0", " 12 ", "010", "3999", "", "4000", "-1", "0x10", "1.5", "+", "5 5", "--0"];
+    let mut entry_points: EntryPoints<Option<usize>> = vec![
+        ("parse", Box::new(|text| parse::parse_address(text, 4000).map(Some).map_err(|diag| diag.to_string()))),
+        ("raw START", Box::new(|text| loader::parse_raw(&format!("START {}", text)).map(|program| Some(program.start)).map_err(|error| error.to_string()))),
+    ];
+    #[cfg(feature = "tui")]
+    {
+        let computer = Computer::default();
+        entry_points.push(("monitor", Box::new(move |text| match tui::command_for('g', text, &computer)? {
+            tui::Command::Goto(address) => Ok(Some(address)),
+            command => panic!("{:?}", command),
+        })));
+        #[cfg(all(feature = "assembler", feature = "peripherals"))]
+        entry_points.push(("script", Box::new(|text| script_reads("pc", text, parse::parse_address(text, 4000), usize::to_string))));
+    }
+    check_entry_points(&addresses, &entry_points);
+
+    let values = ["+5", "-0", "- 7", "+010", "+1099511627775", "-1099511627775", "+1099511627776", "--5", "+5-", "+0x5", "+", "-", "+1.5"];
+    let mut entry_points: EntryPoints<Option<Word>> = vec![
+        ("parse", Box::new(|text| parse::parse_value(text, 256).map(Some).map_err(|diag| diag.to_string()))),
+        ("raw word", Box::new(|text| loader::parse_raw(&format!("0: {}", text)).map(|program| program.words.get(&0).copied()).map_err(|error| error.to_string()))),
+    ];
+    #[cfg(all(feature = "tui", feature = "assembler", feature = "peripherals"))]
+    entry_points.push(("script", Box::new(|text| script_reads("rA", text, parse::parse_value(text, 256), Word::to_string))));
+    check_entry_points(&values, &entry_points);
+    assert_eq!(parse::parse_value("-0", 256), Ok(Word::new(false, [0; 5])));
+    assert_eq!(parse::parse_value("+1073741823", 64), Ok(Word::new(true, [63; 5])));
+    assert_eq!(parse::parse_value("+1073741824", 64).unwrap_err().to_string(), "'+1073741824' is not a value from -1073741823 to +1073741823");
+
+    let fields = ["0:5", "1:3", " 4:4 ", "0 : 0", "(2:3)", "3:9", "4:2", "1:", "-1:3", "+1:3", "a:b", "(1:3", "6:6", ":"];
+    let entry_points: EntryPoints<FieldSpec> = vec![
+        ("parse", Box::new(|text| parse::parse_field_spec(text).map_err(|diag| diag.to_string()))),
+        ("statement", Box::new(|text| opcodes::parse_one(&format!("LDA 0({})", text))
+            .map(|word| FieldSpec::from_byte(word.field()).unwrap())
+            .map_err(|error| error.to_string()))),
+    ];
+    check_entry_points(&fields, &entry_points);
+
+    let words = ["+ 0 100 0 5 8", "+0 100 0 5 8", "- 0 0 0 0 0", " + 1 2 3 4 5 ", "+ 0 256 0 0 0", "0 0 0 0 0", "+ 1 2 3 4", "", "+ 1 2 3 4 5 6"];
+    let mut entry_points: EntryPoints<Option<Word>> = vec![
+        ("parse", Box::new(|text| parse::parse_word(text).map(Some).map_err(|diag| diag.to_string()))),
+    ];
+    #[cfg(feature = "tui")]
+    {
+        let computer = Computer::default();
+        entry_points.push(("monitor", Box::new(move |text| match tui::command_for('e', text, &computer)? {
+            tui::Command::EditWord(word) => Ok(Some(word)),
+            command => panic!("{:?}", command),
+        })));
+        #[cfg(all(feature = "assembler", feature = "peripherals"))]
+        entry_points.push(("script", Box::new(|text| script_reads("rX", text, parse::parse_word(text), Word::to_string))));
+    }
+    check_entry_points(&words, &entry_points);
+
+    // Conditional breaks read their address and value the same way.
+    let condition = BreakCondition::parse("mem[-0] == +5", |_| None).unwrap();
+    assert_eq!((condition.address, condition.value), (0, 5));
+    assert!(BreakCondition::parse("mem[0x10] == 5", |_| None).is_err());
+}
@@ -0,0 +1,195 @@
+//! How long each operation takes, as TAOCP gives it or as a cost model overrides it, and
+//! how input-output transfers are charged against the clock.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+use crate::computer::Computer;
+use crate::consts::{Opcode, SpecialOp};
+use crate::opcodes::{self, OPCODES};
+use crate::word::Word;
+
+/// How input-output transfers are charged against the computer's clock.
+///
+/// The model also decides when `OUT` reads the block it writes. Under `Instant` the block
+/// is copied to the unit as the instruction is issued. Under the others it is copied as
+/// its last word moves, so a program that changes the block before then, even its own
+/// code, writes the changed words, as on a real machine that doesn't wait with `JBUS`.
+/// Blocks still moving when the computer halts are copied as memory stands then.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum IoTimingModel {
+    /// Transfers complete as soon as they're issued, so units are never busy.
+    Instant,
+    /// Transfers run alongside the program, one word every `word_transfer_time` units,
+    /// without costing the program anything.
+    Overlapped,
+    /// As `Overlapped`, but every word transferred steals `steal_cost` units of memory
+    /// time from the program.
+    CycleStealing,
+}
+
+/// A block transfer still in progress on some unit.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub unit: u8,
+    pub words_remaining: usize,
+    pub next_word_at: u64,
+    /// For an `OUT`, the words it writes, which are copied to the unit when the last of
+    /// them moves.
+    pub output: Option<Range<usize>>,
+    /// The address of the instruction that issued the transfer.
+    pub pc: usize,
+}
+
+/// Errors raised while reading a cost model.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CostModelError {
+    /// Line `line` isn't a comment or of the form `KEY = CYCLES`.
+    Syntax { line: usize, text: String },
+    /// The key on line `line` isn't a mnemonic, a family, or `move_word`.
+    UnknownKey { line: usize, key: String },
+    /// The cost on line `line` isn't a whole number of cycles.
+    InvalidCost { line: usize, value: String },
+    /// These implemented operations were given no cost.
+    Missing(Vec<String>),
+}
+
+impl fmt::Display for CostModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostModelError::Syntax { line, text } => write!(f, "Line {}: '{}' is not of the form KEY = CYCLES", line, text),
+            CostModelError::UnknownKey { line, key } => write!(f, "Line {}: {} is not an operation or family", line, key),
+            CostModelError::InvalidCost { line, value } => write!(f, "Line {}: {} is not a number of cycles", line, value),
+            CostModelError::Missing(mnemonics) => write!(f, "No cost given for {}", mnemonics.join(", ")),
+        }
+    }
+}
+
+/// The families a cost model may price together, with the opcodes in each.
+pub const FAMILIES: [(&str, RangeInclusive<u8>); 7] = [
+    ("io", Opcode::JBUS.code()..=Opcode::JRED.code()),
+    ("jump", Opcode::JMP.code()..=Opcode::JX.code()),
+    ("load", Opcode::LDA.code()..=Opcode::LDXN.code()),
+    ("store", Opcode::STA.code()..=Opcode::STZ.code()),
+    ("shift", Opcode::SHIFT.code()..=Opcode::SHIFT.code()),
+    ("address", Opcode::ENTA.code()..=Opcode::ENTX.code()),
+    ("compare", Opcode::CMPA.code()..=Opcode::CMPX.code()),
+];
+
+/// The number of time units each instruction takes, so that variants of MIX with their
+/// own execution times can be modelled without rebuilding. Time spent waiting on a busy
+/// unit is charged separately.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CostModel {
+    /// The cost of each operation in the MIX operation table, by mnemonic. `MOVE` costs
+    /// this plus `move_per_word` for each word moved.
+    costs: BTreeMap<&'static str, u64>,
+    pub move_per_word: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel::knuth()
+    }
+}
+
+impl CostModel {
+    /// The execution times given by Knuth in TAOCP section 1.3.1.
+    pub fn knuth() -> CostModel {
+        let costs = OPCODES.iter().map(|operation| {
+            let opcode = operation.opcode();
+            let cost = match opcode {
+                Opcode::ADD | Opcode::SUB => 2,
+                Opcode::MUL => 10,
+                Opcode::DIV => 12,
+                Opcode::SPECIAL => if operation.default_field() == Some(SpecialOp::Halt.field()) { 1 } else { 10 },
+                Opcode::SHIFT => 2,
+                _ if (Opcode::LDA..=Opcode::STZ).contains(&opcode) => 2,
+                _ if (Opcode::CMPA..=Opcode::CMPX).contains(&opcode) => 2,
+                _ => 1,
+            };
+            (operation.mnemonic, cost)
+        }).collect();
+        CostModel { costs, move_per_word: 2 }
+    }
+
+    /// Reads a cost model from lines of the form `KEY = CYCLES`, where `KEY` is a mnemonic
+    /// such as `MUL`, a family from `FAMILIES` pricing every operation under its opcodes,
+    /// or `move_word` for the cost of each word `MOVE` moves. Later lines override earlier
+    /// ones, so a family can be given first and exceptions after it. Blank lines and text
+    /// after `#` are ignored.
+    ///
+    /// ## Errors
+    /// - `Syntax`, `UnknownKey`, or `InvalidCost` for the first line that can't be read.
+    /// - `Missing`, listing every operation the computer implements that was given no
+    ///   cost, counting `move_word` as part of `MOVE`. Operations it doesn't implement
+    ///   cost 1 unless given.
+    pub fn from_text(text: &str) -> Result<CostModel, CostModelError> {
+        let mut model = CostModel { costs: BTreeMap::new(), move_per_word: 0 };
+        let mut move_word_given = false;
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let content = raw.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let (key, value) = content.split_once('=')
+                .ok_or_else(|| CostModelError::Syntax { line, text: content.to_string() })?;
+            let (key, value) = (key.trim(), value.trim());
+            let cost = value.parse::<u64>()
+                .map_err(|_| CostModelError::InvalidCost { line, value: value.to_string() })?;
+            if key == "move_word" {
+                model.move_per_word = cost;
+                move_word_given = true;
+            } else if let Some((_, codes)) = FAMILIES.iter().find(|(family, _)| *family == key) {
+                for operation in OPCODES.iter().filter(|operation| codes.contains(&operation.code)) {
+                    model.costs.insert(operation.mnemonic, cost);
+                }
+            } else if let Some(operation) = opcodes::lookup(key) {
+                model.costs.insert(operation.mnemonic, cost);
+            } else {
+                return Err(CostModelError::UnknownKey { line, key: key.to_string() });
+            }
+        }
+
+        let mut missing: Vec<String> = OPCODES.iter()
+            .filter(|operation| Computer::core_implements(operation.code, operation.default_field().unwrap_or(0)))
+            .filter(|operation| !model.costs.contains_key(operation.mnemonic))
+            .map(|operation| operation.mnemonic.to_string())
+            .collect();
+        if Computer::core_implements(7, 1) && !move_word_given {
+            missing.push("move_word".to_string());
+        }
+        if !missing.is_empty() {
+            return Err(CostModelError::Missing(missing));
+        }
+        Ok(model)
+    }
+
+    /// The cost of the operation named by `mnemonic`, if it is in the MIX operation table.
+    pub fn cost_of(&self, mnemonic: &str) -> Option<u64> {
+        opcodes::lookup(mnemonic).map(|operation| self.costs.get(operation.mnemonic).copied().unwrap_or(1))
+    }
+
+    /// The number of time units taken by `instruction`, a `MOVE` moving every word its field
+    /// counts. An instruction outside the MIX operation table, such as one added by an
+    /// extension, costs what the first operation under its opcode does.
+    pub fn instruction_cost(&self, instruction: &Word) -> u64 {
+        self.executed_cost(instruction, instruction.field() as u64)
+    }
+
+    /// The number of time units taken by `instruction` once executed, having moved `words`
+    /// words. Only `MOVE` moves words, all of them unless it faults, and none when it does,
+    /// as it checks everything before moving the first; a word with opcode `MOVE` but
+    /// moving none, such as one executed as `NOP`, costs the `MOVE` alone.
+    pub fn executed_cost(&self, instruction: &Word, words: u64) -> u64 {
+        let operation = opcodes::operation_of(instruction)
+            .or_else(|| OPCODES.iter().find(|operation| operation.code == instruction.opcode()));
+        let cost = operation.and_then(|operation| self.costs.get(operation.mnemonic)).copied().unwrap_or(1);
+        if instruction.opcode() == Opcode::MOVE.code() {
+            cost + self.move_per_word * words
+        } else {
+            cost
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Records of what a run did, kept when `Computer::trace` is set. Each `IN`, `OUT`, and
+//! `IOC` adds a record of the transfer, so that a wrong block on a device can be traced
+//! back to the instruction that wrote it and the memory it came from.
+//!
+//! A trace given a `TraceConfig` also records steps, sampled and filtered as the config
+//! says, so that a run too long to record in full can still be followed. Step records
+//! are numbered by the step they were taken at, so the gaps sampling leaves are visible.
+
+use std::ops::Range;
+use crate::computer::Computer;
+use crate::timing::FAMILIES;
+use crate::word::Word;
+
+/// The bits of a register mask, for `TraceConfig::on_change_of`, in the order of
+/// `StepRecord::registers`.
+pub const REGISTER_A: u16 = 1 << 0;
+pub const REGISTER_X: u16 = 1 << 1;
+/// The bit of rI`i`, for `i` from 1 to 6.
+pub const fn register_i(i: u8) -> u16 {
+    1 << (i + 1)
+}
+pub const REGISTER_J: u16 = 1 << 8;
+
+/// Which steps a trace records.
+///
+/// A step is recorded when it passes every filter given. Filters are checked only for the
+/// steps sampling picks, so a trace with `every_nth_step` of 1000 and a filter records
+/// those multiples of 1000 that pass the filter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceConfig {
+    /// Record one step in this many, the steps whose number is a multiple of it. 0 and 1
+    /// both record every step.
+    pub every_nth_step: u64,
+    /// Record only instructions at these addresses, or anywhere when empty.
+    pub only_pc_ranges: Vec<Range<usize>>,
+    /// Record only instructions whose opcode is in one of these families, bit `i` for
+    /// `FAMILIES[i]`, or of any opcode when `None`.
+    pub only_instruction_families: Option<u8>,
+    /// Record only steps that change one of these registers, a mask of `REGISTER_A` and
+    /// the others, or every step when `None`.
+    pub on_change_of: Option<u16>,
+    /// Record only steps that change one of the words at these addresses, or every step
+    /// when empty. Their values after each step recorded are kept in `StepRecord::words`.
+    pub on_change_of_words: Vec<usize>,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        TraceConfig { every_nth_step: 1, only_pc_ranges: Vec::new(), only_instruction_families: None, on_change_of: None, on_change_of_words: Vec::new() }
+    }
+}
+
+impl TraceConfig {
+    /// Whether the instruction `word` at `pc` passes the filters on address and opcode.
+    fn selects(&self, pc: usize, word: &Word) -> bool {
+        if !self.only_pc_ranges.is_empty() && !self.only_pc_ranges.iter().any(|range| range.contains(&pc)) {
+            return false;
+        }
+        match self.only_instruction_families {
+            Some(mask) => FAMILIES.iter().enumerate()
+                .any(|(i, (_, codes))| mask & (1 << i) != 0 && codes.contains(&word.opcode())),
+            None => true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// A block read from the unit into memory, by `IN`.
+    In,
+    /// A block written from memory to the unit, by `OUT`.
+    Out,
+    /// A control operation, by `IOC`, which moves no words.
+    Control,
+}
+
+/// One transfer between memory and a unit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IoTransfer {
+    pub unit: u8,
+    pub direction: Direction,
+    /// The words of memory read or written, empty for `Control`.
+    pub range: Range<usize>,
+    /// The words as transferred, exactly as they landed in memory or on the unit, when
+    /// `TraceOptions::include_blocks` is set.
+    pub block: Option<Vec<Word>>,
+    /// The address of the instruction issuing the transfer.
+    pub pc: usize,
+    /// The time at which the last word of the transfer moves under the computer's I/O
+    /// timing, ignoring time stolen by other transfers.
+    pub completes_at: u64,
+}
+
+/// One step of a run, as it left the registers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StepRecord {
+    /// The number of the step since the trace started, counting from 1.
+    pub step: u64,
+    pub pc: usize,
+    /// The instruction executed.
+    pub word: Word,
+    /// rA, rX, rI1 to rI6, and rJ after the step.
+    pub registers: [Word; 9],
+    /// The words at `TraceConfig::on_change_of_words` after the step, in that order.
+    pub words: Vec<Word>,
+}
+
+/// What the filters on change compare a step against: the registers and watched words
+/// before it.
+pub(crate) struct Watched {
+    registers: [Word; 9],
+    words: Vec<Word>,
+}
+
+/// A call host code answered in place of a subroutine (see `Computer::stub_subroutine`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StubInvocation {
+    /// The entry of the stubbed subroutine.
+    pub entry: usize,
+    /// The address of the jump reaching it.
+    pub caller: usize,
+    /// The address control returned to.
+    pub returned_to: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TraceRecord {
+    Io(IoTransfer),
+    Step(StepRecord),
+    StubInvoked(StubInvocation),
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TraceOptions {
+    /// Whether transfer records carry a copy of their block, which takes a hundred words
+    /// per transfer.
+    pub include_blocks: bool,
+}
+
+/// The records of a run, oldest first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Trace {
+    pub options: TraceOptions,
+    /// Which steps are recorded, or `None` to record only transfers. Kept with the
+    /// records so that a reader can tell a gap sampling left from a gap in the run.
+    pub steps: Option<TraceConfig>,
+    pub records: Vec<TraceRecord>,
+    /// Whether records were dropped once the trace held the computer's
+    /// `InstrumentationBudget::max_trace_records`.
+    pub truncated: bool,
+    /// The number of steps taken since the trace started.
+    steps_taken: u64,
+    /// The steps left until sampling picks one.
+    countdown: u64,
+}
+
+impl Trace {
+    pub fn new(options: TraceOptions) -> Trace {
+        Trace { options, ..Trace::default() }
+    }
+
+    /// A trace recording the steps `config` selects as well as transfers.
+    pub fn with_steps(options: TraceOptions, config: TraceConfig) -> Trace {
+        Trace { options, countdown: config.every_nth_step.max(1), steps: Some(config), ..Trace::default() }
+    }
+
+    /// An empty trace with the same options and config, for a computer that was reset.
+    pub fn restarted(&self) -> Trace {
+        match &self.steps {
+            Some(config) => Trace::with_steps(self.options, config.clone()),
+            None => Trace::new(self.options),
+        }
+    }
+
+    /// The registers and watched words of `computer` before a step, or `None` when no
+    /// filter needs them to tell whether the step changed them.
+    pub(crate) fn watched(&self, computer: &Computer) -> Option<Watched> {
+        match &self.steps {
+            Some(config) if config.on_change_of.is_some() || !config.on_change_of_words.is_empty() =>
+                Some(Watched { registers: computer.registers(), words: watched_words(computer, &config.on_change_of_words) }),
+            _ => None,
+        }
+    }
+
+    /// Counts a step of `computer`, which executed `word` at `pc`, and records it when the
+    /// config selects it.
+    ///
+    /// ## Arguments
+    /// - `before`: What `watched` gave before the step.
+    pub(crate) fn record_step(&mut self, computer: &Computer, pc: usize, word: Word, before: Option<Watched>) {
+        let config = match &self.steps {
+            Some(config) => config,
+            None => return,
+        };
+        self.steps_taken += 1;
+        self.countdown -= 1;
+        if self.countdown != 0 {
+            return;
+        }
+        self.countdown = config.every_nth_step.max(1);
+        if !config.selects(pc, &word) {
+            return;
+        }
+        let registers = computer.registers();
+        let words = watched_words(computer, &config.on_change_of_words);
+        if let Some(before) = before {
+            if let Some(mask) = config.on_change_of {
+                if !(0..9).any(|i| mask & (1 << i) != 0 && before.registers[i] != registers[i]) {
+                    return;
+                }
+            }
+            if !config.on_change_of_words.is_empty() && before.words == words {
+                return;
+            }
+        }
+        self.push(TraceRecord::Step(StepRecord { step: self.steps_taken, pc, word, registers, words }), computer.budget.max_trace_records);
+    }
+
+    /// Adds `record` unless the trace holds `max_records` already, in which case the trace
+    /// is marked truncated.
+    pub(crate) fn push(&mut self, record: TraceRecord, max_records: usize) {
+        crate::budget::record(&mut self.records, record, max_records, &mut self.truncated);
+    }
+
+    /// Every transfer, oldest first.
+    pub fn transfers(&self) -> impl Iterator<Item = &IoTransfer> + '_ {
+        self.records.iter().filter_map(|record| match record {
+            TraceRecord::Io(transfer) => Some(transfer),
+            TraceRecord::Step(_) | TraceRecord::StubInvoked(_) => None,
+        })
+    }
+
+    /// Every stubbed call, oldest first.
+    pub fn stub_calls(&self) -> impl Iterator<Item = &StubInvocation> + '_ {
+        self.records.iter().filter_map(|record| match record {
+            TraceRecord::StubInvoked(call) => Some(call),
+            TraceRecord::Io(_) | TraceRecord::Step(_) => None,
+        })
+    }
+
+    /// Every step recorded, oldest first.
+    pub fn steps(&self) -> impl Iterator<Item = &StepRecord> + '_ {
+        self.records.iter().filter_map(|record| match record {
+            TraceRecord::Step(step) => Some(step),
+            TraceRecord::Io(_) | TraceRecord::StubInvoked(_) => None,
+        })
+    }
+
+    /// The record of step `step`, the number the step was taken at rather than its place
+    /// among the records.
+    ///
+    /// ## Returns
+    /// - `None` when the step wasn't recorded, because it wasn't sampled, was filtered
+    ///   out, or hasn't been taken.
+    pub fn step(&self, step: u64) -> Option<&StepRecord> {
+        self.steps().find(|record| record.step == step)
+    }
+
+    /// The steps recorded whose numbers are in `steps`, oldest first.
+    pub fn steps_in(&self, steps: Range<u64>) -> impl Iterator<Item = &StepRecord> + '_ {
+        let Range { start, end } = steps;
+        self.steps().skip_while(move |record| record.step < start).take_while(move |record| record.step < end)
+    }
+
+    /// The number of steps taken since the trace started, recorded or not.
+    pub fn steps_taken(&self) -> u64 {
+        self.steps_taken
+    }
+
+    /// The transfers on `unit`, oldest first.
+    pub fn for_unit(&self, unit: u8) -> impl Iterator<Item = &IoTransfer> + '_ {
+        self.transfers().filter(move |transfer| transfer.unit == unit)
+    }
+
+    /// The transfers in `direction`, oldest first.
+    pub fn in_direction(&self, direction: Direction) -> impl Iterator<Item = &IoTransfer> + '_ {
+        self.transfers().filter(move |transfer| transfer.direction == direction)
+    }
+
+    /// The blocks written to `unit`, in the order they were written, for comparing with
+    /// what the unit holds after the run.
+    ///
+    /// ## Returns
+    /// - `None` when the trace doesn't include blocks.
+    pub fn blocks_written(&self, unit: u8) -> Option<Vec<Vec<Word>>> {
+        if !self.options.include_blocks {
+            return None;
+        }
+        self.for_unit(unit)
+            .filter(|transfer| transfer.direction == Direction::Out)
+            .map(|transfer| transfer.block.clone())
+            .collect()
+    }
+}
+
+/// The words of `computer` at `addresses`, +0 for an address past the end of memory.
+fn watched_words(computer: &Computer, addresses: &[usize]) -> Vec<Word> {
+    addresses.iter().map(|&address| computer.memory().get(address).copied().unwrap_or_else(Word::default)).collect()
+}
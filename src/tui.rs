@@ -0,0 +1,463 @@
+//! A front panel for stepping through a program in a terminal, built with the `tui`
+//! feature. It shows the registers in MIX notation, the overflow toggle and comparison
+//! indicator, and a window of memory around a cursor, each word as its bytes and as a
+//! MIXAL statement, with the line at `pc` highlighted. Each input line is a key, with an
+//! argument for the keys that take one:
+//!
+//! - `s`: execute one instruction.
+//! - `r`: run until `HLT`, an error, or a breakpoint.
+//! - `b`, `b ADDRESS`: toggle a breakpoint at the cursor, or at `ADDRESS`.
+//! - `w`, `w ADDRESS`: toggle a watch on the word at the cursor, or at `ADDRESS`. A run
+//!   stops after an instruction changes a watched word.
+//! - `e WORD`: replace the word at the cursor, given as `parse::parse_word` reads it.
+//! - `g ADDRESS`: move the cursor to `ADDRESS`.
+//! - `k`, `j`: move the cursor up or down one word.
+//! - `f FORMAT`: write the words of the memory window in `FORMAT`, one of `bytes`,
+//!   `value`, `both`, `inst`, and `float` (see `WordFormat`).
+//! - `x`: turn explanations on or off. While they are on, each step shows what the
+//!   instruction did in a sentence (see `explain`) instead of where it stepped to.
+//! - `n`: show the next page of the addresses the last search found.
+//! - `q`: quit.
+//!
+//! Three words search memory, showing the addresses found `FOUND_PER_PAGE` at a time and
+//! moving the cursor to the first of each page:
+//!
+//! - `find VALUE`: the words whose signed value is `VALUE` (see
+//!   `Computer::search_value`).
+//! - `findb PATTERN`: the words matching `PATTERN`, an optional sign and five bytes, any
+//!   of which may be `*` to match anything, such as `+ 0 * 0 5 8` (see
+//!   `Computer::search_bytes`).
+//! - `refs ADDRESS`: the instructions whose address part is `ADDRESS` (see
+//!   `Computer::search_references`).
+//!
+//! An address or value may be written with the symbols of the program loaded, as `BUF+3`
+//! (see `expression`).
+//!
+//! The panel only reads the computer's state and drives it through `Computer::step` and
+//! `Computer::write_mem`. Drawing goes to a `Grid`, which `run` writes to the terminal
+//! with ANSI escapes, so the same `update` and `render` can be checked without one.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::io::{self, BufRead, Write};
+use crate::checkpoint::CheckpointRing;
+use crate::computer::Computer;
+use crate::decode::DecodedInstruction;
+use crate::explain;
+use crate::expression;
+use crate::opcodes;
+use crate::parse;
+use crate::phases::Snapshot;
+use crate::report::{self, WordFormat};
+use crate::word::Word;
+use crate::field::FieldSpec;
+
+/// The most instructions `r` executes before handing control back.
+pub const RUN_LIMIT: usize = 100_000;
+
+/// The most addresses found by a search that the panel shows at once.
+pub const FOUND_PER_PAGE: usize = 10;
+
+/// The keys of the panel and what they do, in the order of the help line. `n` is left
+/// out, as the message of a search offers it when there is more to show.
+pub const KEYS: &[(char, &str)] = &[
+    ('s', "step"),
+    ('r', "run"),
+    ('b', "break"),
+    ('w', "watch"),
+    ('e', "edit"),
+    ('g', "go to"),
+    ('k', "up"),
+    ('j', "down"),
+    ('f', "format"),
+    ('x', "why"),
+    ('q', "quit"),
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    Step,
+    RunUntilBreak,
+    /// Toggles a breakpoint at the address, or at the cursor without one.
+    ToggleBreakpoint(Option<usize>),
+    /// Toggles a watch on the word at the address, or at the cursor without one.
+    ToggleWatch(Option<usize>),
+    EditWord(Word),
+    Goto(usize),
+    CursorUp,
+    CursorDown,
+    Format(WordFormat),
+    ToggleExplain,
+    Search(Search),
+    /// Shows the next page of what the last search found, or the first after the last.
+    NextPage,
+    Quit,
+}
+
+/// The words of the searches, which the panel reads before its keys.
+pub const SEARCHES: &[&str] = &["find", "findb", "refs"];
+
+/// A search of the whole of memory, as `find`, `findb` and `refs` make.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Search {
+    /// The words with the signed value, as `Computer::search_value` finds them.
+    Value(i64),
+    /// The words with the sign, when given, and the bytes, as `Computer::search_bytes`
+    /// finds them.
+    Bytes { sign: Option<bool>, pattern: [Option<u8>; 5] },
+    /// The instructions with the address, as `Computer::search_references` finds them.
+    References(usize),
+}
+
+impl Search {
+    /// The occupied addresses in `range` of `computer` the search finds, in order.
+    pub fn run(&self, computer: &Computer, range: Range<usize>) -> Vec<usize> {
+        match self {
+            Search::Value(value) => computer.search_value(*value, range),
+            Search::Bytes { sign, pattern } => computer.search_bytes(pattern, *sign, range),
+            Search::References(address) => computer.search_references(*address, range),
+        }
+    }
+}
+
+/// What the panel keeps between commands, apart from the computer itself.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Panel {
+    /// The address the memory window is centered on.
+    pub cursor: usize,
+    pub breakpoints: BTreeSet<usize>,
+    /// The addresses of the words a run stops after changing.
+    pub watches: BTreeSet<usize>,
+    /// The outcome of the last command, shown below the memory window.
+    pub message: String,
+    /// How the memory window writes each word.
+    pub format: WordFormat,
+    /// Whether each step is explained in the message.
+    pub explain: bool,
+    /// The checkpoints the steps and runs take, none unless its options say otherwise.
+    pub checkpoints: CheckpointRing,
+    /// The addresses the last search found, and the page of them shown.
+    pub found: Vec<usize>,
+    pub found_page: usize,
+}
+
+/// A screen of characters, with some rows highlighted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    rows: Vec<Vec<char>>,
+    highlighted: Vec<bool>,
+}
+
+impl Grid {
+    /// A blank grid of `height` rows of `width` characters.
+    pub fn new(width: usize, height: usize) -> Grid {
+        Grid { width, height, rows: vec![vec![' '; width]; height], highlighted: vec![false; height] }
+    }
+
+    /// Writes `text` into `row` from `column`, dropping whatever doesn't fit.
+    pub fn put(&mut self, row: usize, column: usize, text: &str) {
+        if let Some(cells) = self.rows.get_mut(row) {
+            for (cell, c) in cells.iter_mut().skip(column).zip(text.chars()) {
+                *cell = c;
+            }
+        }
+    }
+
+    pub fn highlight(&mut self, row: usize) {
+        if let Some(highlighted) = self.highlighted.get_mut(row) {
+            *highlighted = true;
+        }
+    }
+
+    pub fn is_highlighted(&self, row: usize) -> bool {
+        self.highlighted.get(row).copied().unwrap_or(false)
+    }
+
+    /// The text of `row`, without trailing spaces.
+    pub fn line(&self, row: usize) -> String {
+        self.rows[row].iter().collect::<String>().trim_end().to_string()
+    }
+
+    /// Every row, without trailing spaces, each ended by a newline.
+    pub fn text(&self) -> String {
+        (0..self.height).map(|row| self.line(row) + "\n").collect()
+    }
+}
+
+/// The command for `key`, given the rest of its input line as `argument`, reading
+/// addresses with the symbols of the program loaded into `computer`.
+///
+/// ## Errors
+/// - A message for the panel when `key` isn't in `KEYS` or its argument can't be read.
+pub fn command_for(key: char, argument: &str, computer: &Computer) -> Result<Command, String> {
+    let address = |argument: &str| expression::address(argument, computer.source_map.as_ref(), computer.memory_size());
+    let optional_address = |argument: &str| match argument.trim() {
+        "" => Ok(None),
+        argument => address(argument).map(Some),
+    };
+    match key {
+        's' => Ok(Command::Step),
+        'r' => Ok(Command::RunUntilBreak),
+        'b' => optional_address(argument).map(Command::ToggleBreakpoint),
+        'w' => optional_address(argument).map(Command::ToggleWatch),
+        'e' => parse::parse_word(argument).map(Command::EditWord).map_err(|diag| diag.to_string()),
+        'g' => address(argument).map(Command::Goto),
+        'k' => Ok(Command::CursorUp),
+        'j' => Ok(Command::CursorDown),
+        'f' => WordFormat::from_name(argument.trim()).map(Command::Format)
+            .ok_or(format!("'{}' is not a format, one of bytes, value, both, inst, or float", argument.trim())),
+        'x' => Ok(Command::ToggleExplain),
+        'n' => Ok(Command::NextPage),
+        'q' => Ok(Command::Quit),
+        _ => Err(format!("'{}' is not a key", key)),
+    }
+}
+
+/// The command for `word`, one of the searches or a key of `KEYS`, given the rest of its
+/// input line as `argument`, as `command_for` reads it.
+///
+/// ## Errors
+/// - A message for the panel when `word` isn't a search or a key, or its argument can't
+///   be read.
+pub fn command_for_word(word: &str, argument: &str, computer: &Computer) -> Result<Command, String> {
+    match word {
+        "find" => {
+            let symbols = computer.source_map.as_ref().map(|map| map.symbols.clone()).unwrap_or_default();
+            expression::evaluate(argument.trim(), &symbols).map(|value| Command::Search(Search::Value(value))).map_err(|error| error.to_string())
+        },
+        "findb" => byte_pattern(argument, computer.byte_size()).map(|(sign, pattern)| Command::Search(Search::Bytes { sign, pattern })),
+        "refs" => expression::address(argument, computer.source_map.as_ref(), computer.memory_size()).map(|address| Command::Search(Search::References(address))),
+        _ => {
+            let mut chars = word.chars();
+            match (chars.next(), chars.next()) {
+                (Some(key), None) => command_for(key, argument, computer),
+                _ => Err(format!("'{}' is not a command", word)),
+            }
+        },
+    }
+}
+
+/// Reads the pattern of `findb`: an optional sign and five bytes of fewer than
+/// `byte_size` values, any of them `*` to match anything.
+fn byte_pattern(text: &str, byte_size: u16) -> Result<(Option<bool>, [Option<u8>; 5]), String> {
+    let invalid = || format!("'{}' is not a sign and five bytes, each a number below {} or *", text.trim(), byte_size);
+    let mut parts: Vec<&str> = text.split_whitespace().collect();
+    let sign = match parts.first() {
+        Some(&"+") if parts.len() == 6 => Some(true),
+        Some(&"-") if parts.len() == 6 => Some(false),
+        Some(&"*") if parts.len() == 6 => None,
+        _ if parts.len() == 5 => None,
+        _ => return Err(invalid()),
+    };
+    if parts.len() == 6 {
+        parts.remove(0);
+    }
+    let mut pattern = [None; 5];
+    for (byte, part) in pattern.iter_mut().zip(parts) {
+        if part != "*" {
+            *byte = Some(part.parse::<u8>().ok().filter(|byte| u16::from(*byte) < byte_size).ok_or_else(invalid)?);
+        }
+    }
+    Ok((sign, pattern))
+}
+
+/// Carries out `command` on `computer`.
+///
+/// ## Returns
+/// - `false` once the panel should close.
+pub fn update(computer: &mut Computer, panel: &mut Panel, command: Command) -> bool {
+    let last_address = computer.memory_size() - 1;
+    match command {
+        Command::Step => {
+            let before = panel.explain.then(|| (Snapshot::of(computer), computer.memory.get(computer.pc).map(DecodedInstruction::of)));
+            let stepped = step(computer);
+            if stepped.is_ok() {
+                panel.checkpoints.after_step(computer);
+            }
+            panel.message = match (stepped, before) {
+                (Ok(()), Some((snapshot, Some(instruction)))) => explain::explain(&instruction, &snapshot.delta(computer), computer),
+                (Ok(()), _) => format!("Stepped to {}", computer.pc),
+                (Err(message), _) => message,
+            };
+            panel.cursor = computer.pc.min(last_address);
+        },
+        Command::RunUntilBreak => {
+            panel.message = run_until_break(computer, &panel.breakpoints, &panel.watches, &mut panel.checkpoints);
+            panel.cursor = computer.pc.min(last_address);
+        },
+        Command::ToggleBreakpoint(address) => toggle(&mut panel.breakpoints, address.unwrap_or(panel.cursor)),
+        Command::ToggleWatch(address) => toggle(&mut panel.watches, address.unwrap_or(panel.cursor)),
+        Command::EditWord(word) => computer.write_mem(panel.cursor, word),
+        Command::Goto(address) => panel.cursor = address.min(last_address),
+        Command::CursorUp => panel.cursor = panel.cursor.saturating_sub(1),
+        Command::CursorDown => panel.cursor = (panel.cursor + 1).min(last_address),
+        Command::Format(format) => panel.format = format,
+        Command::ToggleExplain => {
+            panel.explain = !panel.explain;
+            panel.message = format!("Explanations {}", if panel.explain { "on" } else { "off" });
+        },
+        Command::Search(search) => {
+            panel.found = search.run(computer, 0..computer.memory_size());
+            panel.found_page = 0;
+            show_found(panel);
+        },
+        Command::NextPage if panel.found.is_empty() => panel.message = "Nothing found to page through".to_string(),
+        Command::NextPage => {
+            panel.found_page = (panel.found_page + 1) % panel.found.len().div_ceil(FOUND_PER_PAGE);
+            show_found(panel);
+        },
+        Command::Quit => return false,
+    }
+    true
+}
+
+/// Shows the page of found addresses the panel is on, moving the cursor to the first.
+fn show_found(panel: &mut Panel) {
+    let first = panel.found_page * FOUND_PER_PAGE;
+    let page = match panel.found.get(first..) {
+        Some(rest) if !rest.is_empty() => &rest[..rest.len().min(FOUND_PER_PAGE)],
+        _ => {
+            panel.message = "Found nothing".to_string();
+            return;
+        },
+    };
+    panel.cursor = page[0];
+    let addresses: Vec<String> = page.iter().map(|address| address.to_string()).collect();
+    let more = if first + page.len() < panel.found.len() { " (n: more)" } else { "" };
+    panel.message = format!("Found {}, {} to {}: {}{}", panel.found.len(), first + 1, first + page.len(), addresses.join(" "), more);
+}
+
+fn toggle(addresses: &mut BTreeSet<usize>, address: usize) {
+    if !addresses.remove(&address) {
+        addresses.insert(address);
+    }
+}
+
+/// Executes one instruction, unless the computer has halted.
+///
+/// ## Errors
+/// - A message for the panel when the computer has halted or the instruction fails.
+fn step(computer: &mut Computer) -> Result<(), String> {
+    if computer.is_halted() {
+        return Err("Halted".to_string());
+    }
+    computer.step().map_err(|error| format!("Error: {}", error))?;
+    if let Some(unit) = computer.blocked_on() {
+        return Err(format!("Waiting on unit {}", unit));
+    }
+    Ok(())
+}
+
+/// Steps until the computer halts, an instruction fails, control reaches a breakpoint, or
+/// a watched word changes, always executing at least one instruction, and taking
+/// checkpoints on the way.
+fn run_until_break(computer: &mut Computer, breakpoints: &BTreeSet<usize>, watches: &BTreeSet<usize>, checkpoints: &mut CheckpointRing) -> String {
+    let watched = |computer: &Computer| -> Vec<Word> { watches.iter().map(|address| computer.memory()[*address]).collect() };
+    for _ in 0..RUN_LIMIT {
+        let before = watched(computer);
+        if let Err(message) = step(computer) {
+            return message;
+        }
+        checkpoints.after_step(computer);
+        let changed = watches.iter().zip(before).zip(watched(computer))
+            .find(|((_, before), after)| before != after);
+        if let Some(((address, before), after)) = changed {
+            return format!("Watched {} changed from [{}] to [{}]", address, before, after);
+        }
+        if breakpoints.contains(&computer.pc) {
+            return format!("Breakpoint at {}", computer.pc);
+        }
+    }
+    format!("Stopped after {} instructions at {}", RUN_LIMIT, computer.pc)
+}
+
+/// Draws the panel for `computer` onto `grid`: the registers and indicators, then the
+/// memory window, then the message and the keys.
+pub fn render(computer: &Computer, panel: &Panel, grid: &mut Grid) {
+    let registers = [
+        ("rA", &computer.ra), ("rX", &computer.rx),
+        ("rI1", &computer.ri1), ("rI2", &computer.ri2),
+        ("rI3", &computer.ri3), ("rI4", &computer.ri4),
+        ("rI5", &computer.ri5), ("rI6", &computer.ri6),
+    ];
+    for (i, pair) in registers.chunks(2).enumerate() {
+        grid.put(i, 0, &format!("{:<3} {}   {:<3} {}", pair[0].0, pair[0].1, pair[1].0, pair[1].1));
+    }
+    let state = if computer.is_halted() { "halted" } else { "running" };
+    grid.put(4, 0, &format!("rJ  {}   OV {}  CI {}", computer.rj, if computer.overflow_flag { "on" } else { "off" }, computer.comparison_flag));
+    grid.put(5, 0, &format!("pc  {:04}  {}", computer.pc, state));
+
+    let top = 7;
+    let rows = grid.height.saturating_sub(top + 2);
+    let first = panel.cursor.saturating_sub(rows / 2).min(computer.memory_size().saturating_sub(rows));
+    for (row, address) in (top..top + rows).zip(first..computer.memory_size()) {
+        let word = &computer.memory[address];
+        let is_data = computer.source_map.as_ref().is_some_and(|map| map.data.contains(&address));
+        let statement = if is_data {
+            format!("CON {}", word.field_value(FieldSpec::WHOLE))
+        } else {
+            opcodes::disassemble(word).unwrap_or_default()
+        };
+        // The instruction format is a statement already, so it isn't followed by another.
+        let text = match panel.format {
+            WordFormat::Instruction if !is_data => report::format_word_at(computer, address, panel.format),
+            format => format!("{}  {}", report::format_word_at(computer, address, format), statement),
+        };
+        grid.put(row, 0, &format!("{}{} {:04} {}",
+            if address == panel.cursor { '>' } else { ' ' },
+            if panel.breakpoints.contains(&address) { '*' } else if panel.watches.contains(&address) { 'w' } else { ' ' },
+            address, text));
+        if address == computer.pc {
+            grid.highlight(row);
+        }
+    }
+
+    grid.put(grid.height.saturating_sub(2), 0, &panel.message);
+    let keys: Vec<String> = KEYS.iter().map(|(key, action)| format!("{}:{}", key, action)).collect();
+    grid.put(grid.height.saturating_sub(1), 0, &keys.join(" "));
+}
+
+/// Runs the panel on the terminal until `q` or the end of input, drawing an 80 by 24
+/// screen before each line of input.
+///
+/// ## Errors
+/// - Any error reading from or writing to the terminal.
+pub fn run(computer: &mut Computer) -> io::Result<()> {
+    let mut panel = Panel { cursor: computer.pc, ..Panel::default() };
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        let mut grid = Grid::new(80, 24);
+        render(computer, &panel, &mut grid);
+        let mut out = io::stdout();
+        write!(out, "\x1b[2J\x1b[H")?;
+        for row in 0..grid.height {
+            if grid.is_highlighted(row) {
+                writeln!(out, "\x1b[7m{:<80}\x1b[0m", grid.line(row))?;
+            } else {
+                writeln!(out, "{}", grid.line(row))?;
+            }
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+        let line = line.trim_start();
+        let (word, argument) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+        let mut chars = line.chars();
+        let command = match chars.next() {
+            Some(_) if SEARCHES.contains(&word) => command_for_word(word, argument, computer),
+            Some(key) => command_for(key, chars.as_str(), computer),
+            None => continue,
+        };
+        match command {
+            Ok(command) => if !update(computer, &mut panel, command) { return Ok(()); },
+            Err(message) => panel.message = message,
+        }
+    }
+}
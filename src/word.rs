@@ -1,80 +1,321 @@
-use std::fmt;
-use crate::instruction_functions::adjusted_field_specification;
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct Word {
-    pub positive: bool,
-    pub bytes: [u8; 5],
-}
-
-impl Word {
-    pub fn new(positive: bool, b: [u8; 5]) -> Word {
-        Word {
-            positive: positive,
-            bytes: b,
-        }
-    }
-
-    pub fn default() -> Word {
-        Word::new(true, [0; 5])
-    }
-
-    pub fn from_value(value: i64) -> Word {
-        let positive = value >= 0;
-        let mut bytes : [u8; 5] = [0; 5];
-        let mut value_mut = value.clone();
-        for i in 0..5 {
-            bytes[4 - i] = (value_mut % 256) as u8;
-            value_mut = value_mut >> 8;
-        }
-        Word::new(positive, bytes)
-    }
-
-    pub fn address(&self) -> usize {
-        self.field_value((1, 2)) as usize
-    }
-
-    pub fn index(&self) -> u8 {
-        self.bytes[2]
-    }
-
-    // 8L + R in (L:R)
-    pub fn field(&self) -> u8 {
-        self.bytes[3]
-    }
-
-    pub fn opcode(&self) -> u8 {
-        self.bytes[4]
-    }
-
-    pub fn negate(&self) -> Word {
-        let mut new_word = self.clone();
-        new_word.positive = !new_word.positive;
-        new_word
-    }
-
-    pub fn field_value(&self, field_specification: (usize, usize)) -> i64 {
-        let (zero_included, only_zero, (l, r)) = adjusted_field_specification(field_specification);
-        if only_zero { return 0; }
-    
-        let mut result = self.bytes[l] as i64;
-        for i in (l + 1)..=(r) {
-            result = result << 8;
-            result = result + (self.bytes[i] as i64);
-        }
-        result * (if zero_included && !self.positive { -1 } else { 1 })
-    }
-}
-
-impl fmt::Display for Word {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:>2} {:>4} {:>4} {:>4} {:>4} {:>4}", 
-            if self.positive { '+' } else { '-' },
-            self.bytes[0],
-            self.bytes[1],
-            self.bytes[2],
-            self.bytes[3],
-            self.bytes[4]
-        )
-    }
-}
+//! The MIX word, a sign and five bytes, and its conversions to and from values and
+//! characters.
+
+use std::cmp::Ordering;
+use std::fmt;
+use crate::field::FieldSpec;
+use crate::instruction_functions::store_operation;
+use crate::portability::CONFIGURED_BYTE_SIZE;
+use crate::error::MixError;
+use crate::charset::{CharsetError, CharsetMapping};
+
+/// A sign and five bytes. Read them with `is_positive` and `bytes`; the fields are still
+/// public while the crate moves off them, but left out of the documentation, as the sign
+/// and bytes may not always be kept as they are.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Word {
+    #[doc(hidden)]
+    pub positive: bool,
+    #[doc(hidden)]
+    pub bytes: [u8; 5],
+}
+
+/// A value too large for the five bytes of a word, of `byte_size` values each.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct WordOverflow {
+    pub value: i64,
+    pub byte_size: u16,
+}
+
+impl fmt::Display for WordOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} doesn't fit in five bytes of {} values", self.value, self.byte_size)
+    }
+}
+
+impl Word {
+    pub fn new(positive: bool, b: [u8; 5]) -> Word {
+        Word {
+            positive: positive,
+            bytes: b,
+        }
+    }
+
+    pub fn default() -> Word {
+        Word::new(true, [0; 5])
+    }
+
+    /// The word holding `value`, its sign that of `value` and its bytes the low five bytes
+    /// of its magnitude. A magnitude past five bytes wraps; `try_from_value` refuses it.
+    pub fn from_value(value: i64) -> Word {
+        Word::from_value_in_base(value, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `from_value`, for bytes holding `byte_size` values.
+    pub fn from_value_in_base(value: i64, byte_size: u16) -> Word {
+        let positive = value >= 0;
+        let mut bytes : [u8; 5] = [0; 5];
+        let mut magnitude = value.unsigned_abs();
+        for i in 0..5 {
+            bytes[4 - i] = (magnitude % byte_size as u64) as u8;
+            magnitude /= byte_size as u64;
+        }
+        Word::new(positive, bytes)
+    }
+
+    /// The word holding `value`, as `from_value` gives it.
+    ///
+    /// ## Errors
+    /// - `WordOverflow` when the magnitude of `value` doesn't fit in five bytes.
+    pub fn try_from_value(value: i64) -> Result<Word, WordOverflow> {
+        Word::try_from_value_in_base(value, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `try_from_value`, for bytes holding `byte_size` values.
+    ///
+    /// ## Errors
+    /// - `WordOverflow` as well when `largest_magnitude` has no answer for `byte_size`.
+    pub fn try_from_value_in_base(value: i64, byte_size: u16) -> Result<Word, WordOverflow> {
+        match Word::largest_magnitude(byte_size) {
+            Some(largest) if value.unsigned_abs() <= largest => Ok(Word::from_value_in_base(value, byte_size)),
+            _ => Err(WordOverflow { value, byte_size }),
+        }
+    }
+
+    /// The largest magnitude five bytes of `byte_size` values hold, 1073741823 for 64.
+    ///
+    /// ## Returns
+    /// - `None` when `byte_size` is 0, or above 7131, where the magnitude is past a `u64`.
+    pub fn largest_magnitude(byte_size: u16) -> Option<u64> {
+        (byte_size as u64).checked_pow(5)?.checked_sub(1)
+    }
+
+    /// Packs the parts of an instruction `±AA I F C` into a word, with the sign of the
+    /// word taken from the sign of the address.
+    pub fn from_instruction(address: i64, index: u8, field: u8, opcode: u8) -> Word {
+        Word::from_instruction_in_base(address, index, field, opcode, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `from_instruction`, with the two bytes of the address holding `byte_size`
+    /// values each. The caller checks that every part fits.
+    pub fn from_instruction_in_base(address: i64, index: u8, field: u8, opcode: u8, byte_size: u16) -> Word {
+        let magnitude = address.abs();
+        let byte_size = byte_size as i64;
+        Word::new(address >= 0, [(magnitude / byte_size) as u8, (magnitude % byte_size) as u8, index, field, opcode])
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.positive
+    }
+
+    /// The five bytes, most significant first.
+    pub fn bytes(&self) -> [u8; 5] {
+        self.bytes
+    }
+
+    pub fn address(&self) -> usize {
+        self.field_value(FieldSpec::of(1, 2)) as usize
+    }
+
+    /// The magnitude of the address, with its two bytes holding `byte_size` values each.
+    pub fn address_in_base(&self, byte_size: u16) -> usize {
+        self.bytes[0] as usize * byte_size as usize + self.bytes[1] as usize
+    }
+
+    pub fn index(&self) -> u8 {
+        self.bytes[2]
+    }
+
+    // 8L + R in (L:R)
+    pub fn field(&self) -> u8 {
+        self.bytes[3]
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.bytes[4]
+    }
+
+    /// Reads a word written as `Display` writes it: a sign followed by five bytes,
+    /// separated by whitespace, such as `+ 0 100 0 5 8`. The sign may also be joined to
+    /// the first byte, as in `+0 100 0 5 8`.
+    ///
+    /// ## Returns
+    /// - `None` when the text isn't a sign and five bytes, or a byte doesn't fit.
+    pub fn from_mix_notation(text: &str) -> Option<Word> {
+        let text = text.trim();
+        let positive = match text.chars().next()? {
+            '+' => true,
+            '-' => false,
+            _ => return None,
+        };
+        let parts: Vec<&str> = text[1..].split_whitespace().collect();
+        if parts.len() != 5 {
+            return None;
+        }
+        let mut word = Word::new(positive, [0; 5]);
+        for (byte, part) in word.bytes.iter_mut().zip(parts) {
+            let value = part.parse::<u16>().ok()?;
+            if value >= CONFIGURED_BYTE_SIZE as u16 {
+                return None;
+            }
+            *byte = value as u8;
+        }
+        Some(word)
+    }
+
+    /// A copy of this word with the opposite sign, leaving this word as it is.
+    pub fn negate(&self) -> Word {
+        let mut new_word = self.clone();
+        new_word.positive = !new_word.positive;
+        new_word
+    }
+
+    /// Adds the five-byte magnitudes of two words digit by digit, ignoring their signs.
+    ///
+    /// ## Returns
+    /// - `(sum, carry_out)`, where the sum is positive and `carry_out` is set when the sum
+    ///   doesn't fit in five bytes.
+    pub fn add_with_carry(&self, other: &Word, carry_in: bool) -> (Word, bool) {
+        self.add_with_carry_in_base(other, carry_in, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `add_with_carry`, for bytes holding `byte_size` values.
+    pub fn add_with_carry_in_base(&self, other: &Word, carry_in: bool, byte_size: u16) -> (Word, bool) {
+        let mut sum = Word::default();
+        let mut carry = carry_in as u16;
+        for i in (0..5).rev() {
+            let digit = self.bytes[i] as u16 + other.bytes[i] as u16 + carry;
+            sum.bytes[i] = (digit % byte_size) as u8;
+            carry = digit / byte_size;
+        }
+        (sum, carry != 0)
+    }
+
+    /// Subtracts the five-byte magnitude of `other` from that of this word digit by digit,
+    /// ignoring their signs.
+    ///
+    /// ## Returns
+    /// - `(difference, borrow_out)`, where the difference is positive and `borrow_out` is
+    ///   set when `other` (plus the borrow in) was the larger, in which case the difference
+    ///   has wrapped around.
+    pub fn sub_with_borrow(&self, other: &Word, borrow_in: bool) -> (Word, bool) {
+        self.sub_with_borrow_in_base(other, borrow_in, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `sub_with_borrow`, for bytes holding `byte_size` values.
+    pub fn sub_with_borrow_in_base(&self, other: &Word, borrow_in: bool, byte_size: u16) -> (Word, bool) {
+        let mut difference = Word::default();
+        let mut borrow = borrow_in as u16;
+        for i in (0..5).rev() {
+            let subtrahend = other.bytes[i] as u16 + borrow;
+            borrow = (subtrahend > self.bytes[i] as u16) as u16;
+            difference.bytes[i] = (self.bytes[i] as u16 + borrow * byte_size - subtrahend) as u8;
+        }
+        (difference, borrow != 0)
+    }
+
+    /// Compares the five-byte magnitudes of two words, ignoring their signs.
+    pub fn cmp_magnitude(&self, other: &Word) -> Ordering {
+        self.bytes.cmp(&other.bytes)
+    }
+
+    /// The number of bits in a byte holding `byte_size` values.
+    ///
+    /// ## Errors
+    /// - `UnsupportedByteSize` when `byte_size` isn't a power of two of at most 256.
+    pub fn bits_per_byte(byte_size: u16) -> Result<u32, MixError> {
+        if !byte_size.is_power_of_two() || !(2..=256).contains(&byte_size) {
+            return Err(MixError::UnsupportedByteSize { byte_size });
+        }
+        Ok(byte_size.trailing_zeros())
+    }
+
+    /// Packs the magnitude of this word into `5 * byte_bits` bits, the first byte
+    /// highest. Bits of a byte above its lowest `byte_bits` are dropped.
+    ///
+    /// ## Panics
+    /// - When `byte_bits` isn't between 1 and 8.
+    pub fn to_bits(self, byte_bits: u32) -> u64 {
+        assert!((1..=8).contains(&byte_bits), "[Error to_bits] A byte holds between 1 and 8 bits");
+        let mask = (1u64 << byte_bits) - 1;
+        self.bytes.iter().fold(0, |bits, byte| (bits << byte_bits) | (*byte as u64 & mask))
+    }
+
+    /// Unpacks the lowest `5 * byte_bits` bits of `bits` into the bytes of a word, the
+    /// inverse of `to_bits`.
+    ///
+    /// ## Panics
+    /// - When `byte_bits` isn't between 1 and 8.
+    pub fn from_bits(positive: bool, bits: u64, byte_bits: u32) -> Word {
+        assert!((1..=8).contains(&byte_bits), "[Error from_bits] A byte holds between 1 and 8 bits");
+        let mask = (1u64 << byte_bits) - 1;
+        let mut word = Word::new(positive, [0; 5]);
+        for i in 0..5 {
+            word.bytes[i] = ((bits >> (byte_bits * (4 - i as u32))) & mask) as u8;
+        }
+        word
+    }
+
+    /// Renders the five bytes of this word as characters under `mapping`, as the printer
+    /// would.
+    ///
+    /// ## Errors
+    /// - Any error of `CharsetMapping::decode`, at the position of the byte in the word.
+    pub fn as_chars(&self, mapping: &CharsetMapping) -> Result<String, CharsetError> {
+        mapping.decode(&self.bytes)
+    }
+
+    /// Packs up to five characters into a positive word under `mapping`, padding with
+    /// spaces on the right, as `ALF` does.
+    ///
+    /// ## Errors
+    /// - `TooLong` when `text` has more than five characters.
+    /// - Any error of `CharsetMapping::encode`.
+    pub fn from_chars(text: &str, mapping: &CharsetMapping) -> Result<Word, CharsetError> {
+        let codes = mapping.encode(text)?;
+        if codes.len() > 5 {
+            return Err(CharsetError::TooLong(codes.len()));
+        }
+        let mut word = Word::default();
+        word.bytes[..codes.len()].copy_from_slice(&codes);
+        Ok(word)
+    }
+
+    /// Stores `value` into the field `field_specification` of this word as `STA` does: the
+    /// sign of `value` when the field includes 0, and its rightmost bytes into the bytes
+    /// of the field.
+    pub fn set_field(&mut self, field_specification: FieldSpec, value: &Word) {
+        store_operation(value, self, field_specification);
+    }
+
+    /// Takes the sign of `from` when `field_specification` includes it.
+    pub fn copy_sign(&mut self, from: &Word, field_specification: FieldSpec) {
+        if field_specification.includes_sign() {
+            self.positive = from.positive;
+        }
+    }
+
+    pub fn field_value(&self, field_specification: FieldSpec) -> i64 {
+        self.field_value_in_base(field_specification, CONFIGURED_BYTE_SIZE as u16)
+    }
+
+    /// As `field_value`, for bytes holding `byte_size` values.
+    pub fn field_value_in_base(&self, field_specification: FieldSpec, byte_size: u16) -> i64 {
+        let magnitude = self.bytes[field_specification.byte_indices()].iter()
+            .fold(0, |result, byte| result * byte_size as i64 + *byte as i64);
+        magnitude * (if field_specification.includes_sign() && !self.positive { -1 } else { 1 })
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>2} {:>4} {:>4} {:>4} {:>4} {:>4}", 
+            if self.positive { '+' } else { '-' },
+            self.bytes[0],
+            self.bytes[1],
+            self.bytes[2],
+            self.bytes[3],
+            self.bytes[4]
+        )
+    }
+}
@@ -0,0 +1,108 @@
+# Address transfers (ENT, ENN, INC, DEC), jumps, and other single-word operations.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+
+case enta_1000
+do ENTA 1000
+want rA + 0 0 0 3 232
+want time 1
+
+case enta_negative_zero
+set rA +5
+do ENTA -0
+want rA -0
+
+case enta_indexed
+set rI2 +5
+do ENTA 10,2
+want rA +15
+
+case enna_5
+do ENNA 5
+want rA -5
+
+case entx_negative
+do ENTX -7
+want rX -7
+
+case ent1_indexed
+set rI2 +5
+do ENT1 10,2
+want rI1 +15
+
+case inca_5
+set rA +10
+do INCA 5
+want rA +15
+
+case deca_below_zero
+set rA +10
+do DECA 15
+want rA -5
+
+case inca_overflow
+set rA + 255 255 255 255 255
+do INCA 1
+want rA +0
+want overflow on
+
+case inc1_1
+do INC1 1
+want rI1 +1
+
+case dec1_1
+do DEC1 1
+want rI1 -1
+
+case incx_indexed
+set rX +1
+set rI3 +2
+do INCX 3,3
+want rX +6
+
+case jmp_saves_next
+set pc 20
+do JMP 100
+want pc 100
+want rJ +21
+want time 1
+
+case jmp_backward
+set pc 20
+do JMP 10
+want pc 10
+want rJ +21
+
+case jmp_indexed
+set pc 20
+set rI2 +5
+do JMP 100,2
+want pc 105
+want rJ +21
+
+case jan_not_taken
+set pc 20
+set rA +5
+do JAN 100
+want pc 21
+
+case jsj_keeps_rj
+set pc 20
+set rJ +5
+do JSJ 100
+want pc 100
+want rJ +5
+
+case nop
+set rA +5
+do NOP 1000
+want time 1
+
+case hlt
+set pc 100
+do HLT
+want pc 101
+want state halted
+
+case out_unattached
+do OUT 1000(18)
+want fault unit-not-attached
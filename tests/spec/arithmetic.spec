@@ -0,0 +1,129 @@
+# ADD, SUB, and DIV, with overflow.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+
+case add_full
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ADD 2000
+want rA - 1 2 2 251 4
+want time 2
+
+case add_1_5
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ADD 2000(1:5)
+want rA + 1 2 3 13 6
+
+case add_0_3
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ADD 2000(0:3)
+want rA - 1 2 3 9 1
+
+case add_4_5
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ADD 2000(4:5)
+want rA + 0 0 0 13 6
+
+case add_negative
+set rA -5
+set 2000 +3
+do ADD 2000
+want rA -2
+
+case add_carry_across_bytes
+set rA + 0 0 0 0 255
+set 2000 +1
+do ADD 2000
+want rA + 0 0 0 1 0
+
+case add_overflow
+set rA + 255 255 255 255 255
+set 2000 +1
+do ADD 2000
+want rA +0
+want overflow on
+
+case add_negative_overflow
+set rA - 255 255 255 255 255
+set 2000 -2
+do ADD 2000
+want rA -1
+want overflow on
+
+case add_keeps_overflow
+set rA +1
+set 2000 +1
+set overflow on
+do ADD 2000
+want rA +2
+
+case sub_full
+set rA +10
+set 2000 +3
+do SUB 2000
+want rA +7
+want time 2
+
+case sub_below_zero
+set rA +3
+set 2000 +10
+do SUB 2000
+want rA -7
+
+case sub_negative_operand
+set rA +3
+set 2000 -10
+do SUB 2000
+want rA +13
+
+case sub_overflow
+set rA - 255 255 255 255 255
+set 2000 +2
+do SUB 2000
+want rA -1
+want overflow on
+
+case div_taocp
+set rA +0
+set rX +17
+set 1000 +3
+do DIV 1000
+want rA +5
+want rX +2
+want time 12
+
+case div_negative_dividend
+set rA -0
+set rX +17
+set 1000 +3
+do DIV 1000
+want rA -5
+want rX -2
+
+case div_by_field
+set rA +0
+set rX +17
+set 1000 - 0 0 3 0 7
+do DIV 1000(0:3)
+want rA -5
+want rX +2
+
+case div_by_zero
+set rA +0
+set rX +17
+set 1000 +0
+do DIV 1000
+want rA +0
+want rX +0
+want overflow on
+
+case div_quotient_overflow
+set rA +3
+set rX +17
+set 1000 +3
+do DIV 1000
+want rA +0
+want rX +0
+want overflow on
@@ -0,0 +1,54 @@
+# Comparisons, which only ever change the comparison indicator.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+
+case cmpa_0_0
+set rA + 1 1 1 1 1
+set 2000 - 1 1 1 1 1
+set comparison less
+do CMPA 2000(0:0)
+want comparison equal
+
+case cmpa_full
+set rA + 1 1 1 1 1
+set 2000 - 1 1 1 1 1
+do CMPA 2000
+want comparison greater
+want time 2
+
+case cmpa_full_less
+set rA - 1 1 1 1 1
+set 2000 + 1 1 1 1 1
+do CMPA 2000
+want comparison less
+
+case cmpa_1_5
+set rA - 1 1 1 1 1
+set 2000 + 1 1 1 1 1
+set comparison less
+do CMPA 2000(1:5)
+want comparison equal
+
+case cmpa_4_5
+set rA + 9 9 9 1 2
+set 2000 + 0 0 0 1 3
+do CMPA 2000(4:5)
+want comparison less
+
+case cmpa_negative_zero
+set rA +0
+set 2000 -0
+set comparison greater
+do CMPA 2000
+want comparison equal
+
+case cmpx_full
+set rX -3
+set 2000 -7
+do CMPX 2000
+want comparison greater
+
+case cmp1_full
+set rI1 +5
+set 2000 +7
+do CMP1 2000
+want comparison less
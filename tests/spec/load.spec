@@ -0,0 +1,103 @@
+# Loads: LDA, LDX, LDi and their negatives, across field specifications.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+#
+# A partial field is loaded into the same bytes of the register it came from, rather than
+# shifted to the right as in TAOCP; lda_0_3 and lda_2_2 pin that down until it changes.
+
+case lda_full
+set 2000 - 1 2 3 4 5
+do LDA 2000
+want rA - 1 2 3 4 5
+want time 2
+
+case lda_1_5
+set 2000 - 1 2 3 4 5
+do LDA 2000(1:5)
+want rA + 1 2 3 4 5
+
+case lda_3_5
+set 2000 - 1 2 3 4 5
+do LDA 2000(3:5)
+want rA + 0 0 3 4 5
+
+case lda_0_3
+set 2000 - 1 2 3 4 5
+do LDA 2000(0:3)
+want rA - 1 2 3 0 0
+
+case lda_2_2
+set 2000 - 1 2 3 4 5
+do LDA 2000(2:2)
+want rA + 0 2 0 0 0
+
+case lda_5_5
+set 2000 - 1 2 3 4 5
+do LDA 2000(5:5)
+want rA + 0 0 0 0 5
+
+case lda_indexed
+set rI1 +1000
+set 2000 - 1 2 3 4 5
+do LDA 1000,1
+want rA - 1 2 3 4 5
+
+case lda_negative_zero
+set rA +17
+set 2000 -0
+do LDA 2000
+want rA -0
+
+case ldan_full
+set 2000 - 1 2 3 4 5
+do LDAN 2000
+want rA + 1 2 3 4 5
+
+case ldan_1_5
+set 2000 - 1 2 3 4 5
+do LDAN 2000(1:5)
+want rA - 1 2 3 4 5
+
+case ldan_zero
+set 2000 +0
+do LDAN 2000
+want rA -0
+
+case ldx_full
+set 2000 - 1 2 3 4 5
+do LDX 2000
+want rX - 1 2 3 4 5
+
+case ldxn_full
+set 2000 - 1 2 3 4 5
+do LDXN 2000
+want rX + 1 2 3 4 5
+
+case ld1_full
+set 2000 - 0 0 0 4 5
+do LD1 2000
+want rI1 - 0 0 0 4 5
+
+case ld1_4_5
+set 2000 - 0 0 6 4 5
+do LD1 2000(4:5)
+want rI1 + 0 0 0 4 5
+
+case ld6_5_5
+set 2000 - 0 0 6 4 5
+do LD6 2000(5:5)
+want rI6 + 0 0 0 0 5
+
+case ld1_1_2
+set 2000 - 3 4 0 9 9
+do LD1 2000(1:2)
+want rI1 + 0 0 0 3 4
+
+case ld1_too_large
+set 2000 - 0 0 6 4 5
+do LD1 2000
+want fault index-register-overflow
+
+case ld2n_full
+set 2000 - 0 0 0 4 5
+do LD2N 2000
+want rI2 + 0 0 0 4 5
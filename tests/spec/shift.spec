@@ -0,0 +1,120 @@
+# The shifts SLA, SRA, SLAX, SRAX, SLC, and SRC, which never change signs.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+
+case sla_1
+set rA - 1 2 3 4 5
+do SLA 1
+want rA - 2 3 4 5 0
+want time 2
+
+case sla_3
+set rA - 1 2 3 4 5
+do SLA 3
+want rA - 4 5 0 0 0
+
+case sla_5
+set rA + 1 2 3 4 5
+do SLA 5
+want rA + 0 0 0 0 0
+
+case sra_1
+set rA - 1 2 3 4 5
+do SRA 1
+want rA - 0 1 2 3 4
+
+case sra_3
+set rA - 1 2 3 4 5
+do SRA 3
+want rA - 0 0 0 1 2
+
+case sra_5
+set rA + 1 2 3 4 5
+do SRA 5
+want rA + 0 0 0 0 0
+
+case slax_1
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SLAX 1
+want rA + 2 3 4 5 6
+want rX + 7 8 9 0 0
+want time 2
+
+case slax_3
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SLAX 3
+want rA + 4 5 6 7 8
+want rX + 9 0 0 0 0
+
+case slax_5
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SLAX 5
+want rA + 6 7 8 9 0
+want rX + 0 0 0 0 0
+
+case slax_keeps_signs
+set rA + 1 2 3 4 5
+set rX - 6 7 8 9 0
+do SLAX 1
+want rA + 2 3 4 5 6
+want rX - 7 8 9 0 0
+
+case srax_1
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SRAX 1
+want rA + 0 1 2 3 4
+want rX + 5 6 7 8 9
+
+case srax_3
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SRAX 3
+want rA + 0 0 0 1 2
+want rX + 3 4 5 6 7
+
+case srax_5
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SRAX 5
+want rA + 0 0 0 0 0
+want rX + 1 2 3 4 5
+
+case srax_keeps_signs
+set rA - 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SRAX 1
+want rA - 0 1 2 3 4
+want rX + 5 6 7 8 9
+
+case slc_3
+set rA + 1 2 3 4 5
+set rX - 6 7 8 9 0
+do SLC 3
+want rA + 4 5 6 7 8
+want rX - 9 0 1 2 3
+want time 2
+
+case slc_10
+set rA + 1 2 3 4 5
+set rX - 6 7 8 9 0
+do SLC 10
+want rA + 1 2 3 4 5
+want rX - 6 7 8 9 0
+
+case src_3
+set rA - 1 2 3 4 5
+set rX + 6 7 8 9 0
+do SRC 3
+want rA - 8 9 0 1 2
+want rX + 3 4 5 6 7
+
+case src_indexed
+set rA + 1 2 3 4 5
+set rX + 6 7 8 9 0
+set rI1 + 0 0 0 0 1
+do SRC 1,1
+want rA + 9 0 1 2 3
+want rX + 4 5 6 7 8
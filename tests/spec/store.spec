@@ -0,0 +1,166 @@
+# Stores: STA, STX, STi, STJ, and STZ, across field specifications.
+# The format is described above `SPEC_FILES` in src/tests.rs.
+
+case sta_full
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000
+want 2000 + 0 0 0 9 1
+want time 2
+
+case sta_1_5
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000(1:5)
+want 2000 - 0 0 0 9 1
+
+case sta_5_5
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000(5:5)
+want 2000 - 1 2 3 4 1
+
+case sta_2_2
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000(2:2)
+want 2000 - 1 1 3 4 5
+
+case sta_2_3
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000(2:3)
+want 2000 - 1 9 1 4 5
+
+case sta_0_1
+set rA + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do STA 2000(0:1)
+want 2000 + 1 2 3 4 5
+
+case sta_0_0
+set rA + 6 7 8 9 10
+set 2000 - 1 2 3 4 5
+do STA 2000(0:0)
+want 2000 + 1 2 3 4 5
+
+case sta_3_5
+set rA + 6 7 8 9 10
+set 2000 - 1 2 3 4 5
+do STA 2000(3:5)
+want 2000 - 1 2 8 9 10
+
+case sta_1_4
+set rA + 6 7 8 9 10
+set 2000 - 1 2 3 4 5
+do STA 2000(1:4)
+want 2000 - 7 8 9 10 5
+
+case sta_indexed
+set rA + 6 7 8 9 10
+set rI2 -5
+do STA 2005,2
+want 2000 + 6 7 8 9 10
+
+case st1_full
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000
+want 2000 + 0 0 0 9 1
+
+case st1_1_5
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000(1:5)
+want 2000 - 0 0 0 9 1
+
+case st1_5_5
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000(5:5)
+want 2000 - 1 2 3 4 1
+
+case st1_2_2
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000(2:2)
+want 2000 - 1 1 3 4 5
+
+case st1_2_3
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000(2:3)
+want 2000 - 1 9 1 4 5
+
+case st1_0_1
+set rI1 + 0 0 0 9 1
+set 2000 - 1 2 3 4 5
+do ST1 2000(0:1)
+want 2000 + 1 2 3 4 5
+
+case st5_full
+set rI5 - 0 0 0 1 2
+do ST5 2000
+want 2000 - 0 0 0 1 2
+
+case stx_full
+set rX - 6 7 8 9 10
+do STX 2000
+want 2000 - 6 7 8 9 10
+
+case stx_4_5
+set rX - 6 7 8 9 10
+set 2000 + 1 2 3 4 5
+do STX 2000(4:5)
+want 2000 + 1 2 3 9 10
+
+case stj_default
+set rJ + 0 0 0 1 2
+set 2000 - 1 2 3 4 5
+do STJ 2000
+want 2000 + 1 2 3 4 5
+
+case stj_4_5
+set rJ + 0 0 0 1 2
+set 2000 - 1 2 3 4 5
+do STJ 2000(4:5)
+want 2000 - 1 2 3 1 2
+
+case stz_full
+set 2000 - 1 2 3 4 5
+do STZ 2000
+want 2000 + 0 0 0 0 0
+
+case stz_1_3
+set 2000 - 1 2 3 4 5
+do STZ 2000(1:3)
+want 2000 - 0 0 0 4 5
+
+case stz_0_0
+set 2000 - 1 2 3 4 5
+do STZ 2000(0:0)
+want 2000 + 1 2 3 4 5
+
+case sta_4_4
+set rA + 6 7 8 9 10
+set 2000 - 1 2 3 4 5
+do STA 2000(4:4)
+want 2000 - 1 2 3 10 5
+
+case sta_1_1
+set rA + 6 7 8 9 10
+set 2000 - 1 2 3 4 5
+do STA 2000(1:1)
+want 2000 - 10 2 3 4 5
+
+case st2_4_5
+set rI2 - 0 0 0 1 2
+set 2000 + 1 2 3 4 5
+do ST2 2000(4:5)
+want 2000 + 1 2 3 1 2
+
+case st6_0_2
+set rI6 - 0 0 0 1 2
+set 2000 + 1 2 3 4 5
+do ST6 2000(0:2)
+want 2000 - 1 2 3 4 5